@@ -0,0 +1,298 @@
+//! Flattening of exports into one row per annotation for loading into a data warehouse.
+//!
+//! The rows are plain serde records so they can be written with any columnar or
+//! delimited writer, `write_csv` is provided for the common case. Item reports are
+//! already flat and can be written directly. With the `parquet` feature the rows and the
+//! item reports can also be written as Parquet files.
+
+#[cfg(feature = "parquet")]
+use crate::datasets::ItemReport;
+use crate::export::{Annotator, JsonExportV2};
+#[cfg(feature = "parquet")]
+use crate::parquet::{write_parquet, Values};
+#[cfg(any(feature = "reports", feature = "parquet"))]
+use anyhow::Result;
+#[cfg(feature = "reports")]
+use csv_async::AsyncSerializer;
+#[cfg(feature = "reports")]
+use futures::io::AsyncWrite;
+use serde::{Deserialize, Serialize};
+
+/// A single annotation of an export with the details of its item
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AnnotationRow {
+    pub dataset: Option<String>,
+    pub item_id: Option<String>,
+    pub item_path: Option<String>,
+    pub item_name: Option<String>,
+    pub annotation_id: Option<String>,
+    pub class_name: String,
+    /// Main annotation type e.g. `polygon`
+    pub annotation_type: Option<String>,
+    /// Slot names of the annotation joined by semicolon
+    pub slot_names: String,
+    pub x_min: Option<f32>,
+    pub y_min: Option<f32>,
+    pub x_max: Option<f32>,
+    pub y_max: Option<f32>,
+    /// Emails of the annotators joined by semicolon
+    pub annotators: String,
+    /// Emails of the reviewers joined by semicolon
+    pub reviewers: String,
+}
+
+fn emails(users: &Option<Vec<Annotator>>) -> String {
+    users
+        .iter()
+        .flatten()
+        .map(|user| user.email.as_str())
+        .collect::<Vec<&str>>()
+        .join(";")
+}
+
+/// Flattens the exports into one row per annotation, in export order
+pub fn annotation_rows(exports: &[JsonExportV2]) -> Vec<AnnotationRow> {
+    let mut rows = Vec::new();
+    for export in exports.iter() {
+        let source = export.item.source_info.as_ref();
+        for annotation in export.annotations.iter() {
+            let extent = annotation.extent();
+            rows.push(AnnotationRow {
+                dataset: source.map(|source| source.dataset.slug.clone()),
+                item_id: source.map(|source| source.item_id.clone()),
+                item_path: export.item.path.clone(),
+                item_name: export.item.name.clone(),
+                annotation_id: annotation.id.clone(),
+                class_name: annotation.name.clone(),
+                annotation_type: annotation.annotation_type().map(str::to_string),
+                slot_names: annotation.slot_names.join(";"),
+                x_min: extent.map(|extent| extent.0),
+                y_min: extent.map(|extent| extent.1),
+                x_max: extent.map(|extent| extent.2),
+                y_max: extent.map(|extent| extent.3),
+                annotators: emails(&annotation.annotators),
+                reviewers: emails(&annotation.reviewers),
+            });
+        }
+    }
+    rows
+}
+
+/// Writes `rows`, e.g. `AnnotationRow`s or `ItemReport`s, as CSV with a header row
+#[cfg(feature = "reports")]
+pub async fn write_csv<W, T>(writer: W, rows: &[T]) -> Result<()>
+where
+    W: AsyncWrite + Unpin + Send,
+    T: Serialize,
+{
+    let mut serializer = AsyncSerializer::from_writer(writer);
+    for row in rows.iter() {
+        serializer.serialize(row).await?;
+    }
+    serializer.flush().await?;
+    Ok(())
+}
+
+/// Collects a column of `rows`
+#[cfg(feature = "parquet")]
+fn column<T, V>(rows: &[T], value: impl Fn(&T) -> Option<V>) -> Vec<Option<V>> {
+    rows.iter().map(value).collect()
+}
+
+/// Writes the annotation rows as a Parquet file with a column per field of `AnnotationRow`
+#[cfg(feature = "parquet")]
+pub fn annotation_rows_to_parquet(rows: &[AnnotationRow]) -> Result<Vec<u8>> {
+    let text = |value: fn(&AnnotationRow) -> Option<&String>| {
+        Values::Utf8(column(rows, |row| value(row).cloned()))
+    };
+    write_parquet(&[
+        ("dataset", text(|row| row.dataset.as_ref())),
+        ("item_id", text(|row| row.item_id.as_ref())),
+        ("item_path", text(|row| row.item_path.as_ref())),
+        ("item_name", text(|row| row.item_name.as_ref())),
+        ("annotation_id", text(|row| row.annotation_id.as_ref())),
+        ("class_name", text(|row| Some(&row.class_name))),
+        ("annotation_type", text(|row| row.annotation_type.as_ref())),
+        ("slot_names", text(|row| Some(&row.slot_names))),
+        ("x_min", Values::Float(column(rows, |row| row.x_min))),
+        ("y_min", Values::Float(column(rows, |row| row.y_min))),
+        ("x_max", Values::Float(column(rows, |row| row.x_max))),
+        ("y_max", Values::Float(column(rows, |row| row.y_max))),
+        ("annotators", text(|row| Some(&row.annotators))),
+        ("reviewers", text(|row| Some(&row.reviewers))),
+    ])
+}
+
+/// Writes the item reports as a Parquet file with a column per field of `ItemReport`
+#[cfg(feature = "parquet")]
+pub fn item_reports_to_parquet(reports: &[ItemReport]) -> Result<Vec<u8>> {
+    let text = |value: fn(&ItemReport) -> Option<&String>| {
+        Values::Utf8(column(reports, |report| value(report).cloned()))
+    };
+    let seconds = |value: fn(&ItemReport) -> Option<u64>| {
+        Values::Int64(column(reports, |report| {
+            value(report).map(|value| value as i64)
+        }))
+    };
+    write_parquet(&[
+        ("filename", text(|report| report.filename.as_ref())),
+        (
+            "uploaded_date",
+            text(|report| report.uploaded_date.as_ref()),
+        ),
+        (
+            "status",
+            Values::Utf8(column(reports, |report| {
+                report.status.as_ref().map(|status| status.to_string())
+            })),
+        ),
+        (
+            "workflow_start_date",
+            text(|report| report.workflow_start_date.as_ref()),
+        ),
+        (
+            "workflow_complete_date",
+            text(|report| report.workflow_complete_date.as_ref()),
+        ),
+        (
+            "number_of_frames",
+            Values::Int64(column(reports, |report| {
+                report.number_of_frames.map(i64::from)
+            })),
+        ),
+        ("folder", text(|report| report.folder.as_ref())),
+        (
+            "time_spent_annotating_sec",
+            seconds(|report| report.time_spent_annotating_sec),
+        ),
+        (
+            "time_spent_reviewing_sec",
+            seconds(|report| report.time_spent_reviewing_sec),
+        ),
+        (
+            "automation_time_annotating_sec",
+            seconds(|report| report.automation_time_annotating_sec),
+        ),
+        (
+            "automation_time_reviewing_sec",
+            seconds(|report| report.automation_time_reviewing_sec),
+        ),
+        ("annotators", text(|report| report.annotators.as_ref())),
+        ("reviewers", text(|report| report.reviewers.as_ref())),
+        (
+            "was_rejected_in_review",
+            Values::Boolean(column(reports, |report| report.was_rejected_in_review)),
+        ),
+        ("url", text(|report| report.url.as_ref())),
+    ])
+}
+
+#[cfg(all(test, feature = "reports"))]
+mod tests {
+    use super::*;
+    use crate::annotation::{Keypoint, Polygon, Tag};
+    use crate::export::{ImageAnnotation, Item};
+    #[cfg(feature = "parquet")]
+    use fake::Fake;
+
+    #[tokio::test]
+    async fn test_annotation_rows() {
+        let export = JsonExportV2 {
+            item: Item {
+                name: Some("slide.svs".to_string()),
+                path: Some("/batch-3".to_string()),
+                ..Default::default()
+            },
+            annotations: vec![
+                ImageAnnotation {
+                    id: Some("a1".to_string()),
+                    name: "Tumour".to_string(),
+                    polygon: Some(Polygon {
+                        paths: vec![vec![
+                            Keypoint { x: 1.0, y: 2.0 },
+                            Keypoint { x: 5.0, y: 3.0 },
+                            Keypoint { x: 2.0, y: 8.0 },
+                        ]],
+                    }),
+                    annotators: Some(vec![Annotator {
+                        email: "fake.pathologist@franklin.ai".to_string(),
+                        full_name: "Fake Pathologist".to_string(),
+                        ..Default::default()
+                    }]),
+                    slot_names: vec!["0".to_string()],
+                    ..Default::default()
+                },
+                ImageAnnotation {
+                    id: Some("a2".to_string()),
+                    name: "Reviewed".to_string(),
+                    tag: Some(Tag {}),
+                    ..Default::default()
+                },
+            ],
+            ..Default::default()
+        };
+
+        let rows = annotation_rows(&[export]);
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[0].annotation_type.as_deref(), Some("polygon"));
+        assert_eq!(
+            (rows[0].x_min, rows[0].y_min, rows[0].x_max, rows[0].y_max),
+            (Some(1.0), Some(2.0), Some(5.0), Some(8.0))
+        );
+        assert_eq!(rows[0].annotators, "fake.pathologist@franklin.ai");
+        assert_eq!(rows[1].annotation_type.as_deref(), Some("tag"));
+        assert_eq!(rows[1].x_min, None);
+
+        #[cfg(feature = "parquet")]
+        {
+            let parquet = annotation_rows_to_parquet(&rows).unwrap();
+            assert!(parquet.starts_with(b"PAR1") && parquet.ends_with(b"PAR1"));
+            // The class names are plain encoded, each preceded by its length
+            let class_names = [
+                &6u32.to_le_bytes()[..],
+                b"Tumour",
+                &8u32.to_le_bytes(),
+                b"Reviewed",
+            ]
+            .concat();
+            assert!(parquet
+                .windows(class_names.len())
+                .any(|window| window == class_names));
+        }
+
+        let mut csv = Vec::new();
+        write_csv(&mut csv, &rows).await.unwrap();
+        let csv = String::from_utf8(csv).unwrap();
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert!(lines[0].starts_with("dataset,item_id,item_path,item_name,annotation_id"));
+        assert!(lines[2].contains(",a2,Reviewed,tag,"));
+    }
+
+    #[cfg(feature = "parquet")]
+    #[test]
+    fn test_item_reports_to_parquet() {
+        let reports = vec![
+            ItemReport {
+                filename: Some("slide.svs".to_string()),
+                time_spent_annotating_sec: Some(90),
+                was_rejected_in_review: Some(true),
+                ..fake::Faker.fake()
+            },
+            ItemReport {
+                time_spent_annotating_sec: None,
+                ..fake::Faker.fake()
+            },
+        ];
+        let parquet = item_reports_to_parquet(&reports).unwrap();
+        assert!(parquet.starts_with(b"PAR1") && parquet.ends_with(b"PAR1"));
+        // The seconds of the first report follow the levels of a value and a null
+        let seconds = [&[4, 0, 0, 0, 2, 1, 2, 0][..], &90i64.to_le_bytes()].concat();
+        assert!(parquet
+            .windows(seconds.len())
+            .any(|window| window == seconds));
+        assert!(parquet
+            .windows(b"was_rejected_in_review".len())
+            .any(|window| window == b"was_rejected_in_review"));
+    }
+}
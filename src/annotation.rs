@@ -1,25 +1,99 @@
+#[cfg(feature = "fake")]
 #[allow(unused_imports)]
 use fake::{Dummy, Fake};
 
 use anyhow::{bail, Context, Result};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
+#[cfg(feature = "client")]
+use std::path::{Path, PathBuf};
 use strum::{Display, EnumString};
 
-use crate::client::V7Methods;
+#[cfg(feature = "client")]
+use crate::client::{HttpStatusError, PresignedUrlMethods, V7Methods};
+#[cfg(feature = "client")]
 use crate::expect_http_ok;
+use crate::ids::ClassId;
+use crate::imports::AnnotationContext;
+#[cfg(feature = "client")]
+use crate::item::{DatasetItemMethods, DatasetItemV2};
+#[cfg(feature = "client")]
+use crate::response::HeaderMap;
+use crate::utils::parse_timestamp;
+#[cfg(feature = "client")]
+use md5::{Digest, Md5};
 
-#[derive(Debug, Clone, Serialize, Deserialize, Dummy, PartialEq, Eq, Default)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "fake", derive(Dummy))]
 pub struct AnnotationClassMetadata {
     #[serde(rename = "_color")]
     pub color: Option<String>,
     pub polygon: Option<HashMap<String, String>>, // TODO find out what this type actually is
-    pub auto_annotate: Option<HashMap<String, String>>, // TODO find out what this type actually is
-    pub inference: Option<HashMap<String, String>>, // TODO find out what this type actually is
-    pub measures: Option<HashMap<String, String>>, // TODO find out what this type actually is
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub auto_annotate: Option<ClassAutoAnnotate>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub inference: Option<ClassInference>,
+    /// Measurements shown for annotations of the class, keyed by measure e.g. `area`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub measures: Option<HashMap<String, ClassMeasure>>,
+    /// Other settings of the class such as skeleton definitions, kept so that they survive
+    /// an update or a copy of the class
+    #[serde(flatten)]
+    #[cfg_attr(feature = "fake", dummy(default))]
+    pub other: HashMap<String, serde_json::Value>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, Dummy, Default)]
+/// A measurement V7 computes for annotations of a class, e.g. the area of a polygon
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "fake", derive(Dummy))]
+pub struct ClassMeasure {
+    pub name: Option<String>,
+    #[serde(rename = "abbr")]
+    pub abbreviation: Option<String>,
+    /// Unit of the measurement, e.g. `mm^2`, derived from the pixel spacing of the item
+    pub unit: Option<String>,
+    /// Expression the value is computed with, for measures derived from other measures
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub formula: Option<String>,
+    /// Fields not modelled by this crate, kept so that they survive an update of the class
+    #[serde(flatten)]
+    #[cfg_attr(feature = "fake", dummy(default))]
+    pub other: HashMap<String, serde_json::Value>,
+}
+
+/// Binds a class to the model whose predictions model stages import as annotations of the class
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "fake", derive(Dummy))]
+pub struct ClassInference {
+    pub model_id: Option<String>,
+    pub model_name: Option<String>,
+    /// Name of the class in the output of the model
+    pub class_name: Option<String>,
+    /// Fields not modelled by this crate, kept so that they survive an update of the class
+    #[serde(flatten)]
+    #[cfg_attr(feature = "fake", dummy(default))]
+    pub other: HashMap<String, serde_json::Value>,
+}
+
+/// Settings of the auto-annotate tool for a class
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "fake", derive(Dummy))]
+pub struct ClassAutoAnnotate {
+    pub enabled: Option<bool>,
+    /// Model used instead of the default auto-annotate model
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model_id: Option<String>,
+    /// Annotation type the output of the tool is converted to, e.g. `polygon`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output_type: Option<String>,
+    /// Fields not modelled by this crate, kept so that they survive an update of the class
+    #[serde(flatten)]
+    #[cfg_attr(feature = "fake", dummy(default))]
+    pub other: HashMap<String, serde_json::Value>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[cfg_attr(feature = "fake", derive(Dummy))]
 pub struct BoundingBox {
     // Height of the bounding box
     pub h: Option<f32>,
@@ -31,12 +105,14 @@ pub struct BoundingBox {
     pub y: Option<f32>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, Dummy, Default)]
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[cfg_attr(feature = "fake", derive(Dummy))]
 pub struct Polygon {
     pub paths: Vec<Vec<Keypoint>>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, Dummy, Default, PartialEq, PartialOrd)]
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "fake", derive(Dummy))]
 pub struct Keypoint {
     // The horizontal coordinate of the keypoint
     pub x: f32,
@@ -44,15 +120,71 @@ pub struct Keypoint {
     pub y: f32,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, Dummy, Default)]
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[cfg_attr(feature = "fake", derive(Dummy))]
+pub struct Ellipse {
+    // Rotation of the ellipse in radians
+    pub angle: f32,
+    // Centre of the ellipse
+    pub center: Keypoint,
+    // Radii of the ellipse along its x and y axes before rotation
+    pub radius: Keypoint,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[cfg_attr(feature = "fake", derive(Dummy))]
+pub struct Cuboid {
+    // Face of the cuboid closest to the viewer
+    pub front: BoundingBox,
+    // Face of the cuboid furthest from the viewer
+    pub back: BoundingBox,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[cfg_attr(feature = "fake", derive(Dummy))]
+pub struct Line {
+    // Points of the line in drawing order
+    pub path: Vec<Keypoint>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[cfg_attr(feature = "fake", derive(Dummy))]
 pub struct Tag {}
 
-#[derive(Debug, Clone, Serialize, Deserialize, Dummy, Default)]
+/// Free form attributes attached to an annotation
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "fake", derive(Dummy))]
+pub struct Attributes {
+    pub attributes: Vec<String>,
+}
+
+/// Identifies the same object across annotations e.g. the frames of a video
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "fake", derive(Dummy))]
+pub struct InstanceId {
+    pub value: u32,
+}
+
+/// The value of a class property set on an annotation
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "fake", derive(Dummy))]
+pub struct AnnotationProperty {
+    pub name: String,
+    /// `None` when the property is set but has no value
+    pub value: Option<String>,
+    /// The video frame the value applies to, `None` for the whole annotation
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub frame_index: Option<u32>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[cfg_attr(feature = "fake", derive(Dummy))]
 pub struct Text {
     pub text: String,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, Dummy, EnumString, Display)]
+#[derive(Debug, Clone, Serialize, Deserialize, EnumString, Display)]
+#[cfg_attr(feature = "fake", derive(Dummy))]
 #[serde(rename_all = "lowercase")]
 #[serde(untagged)]
 pub enum AnnotationType {
@@ -62,17 +194,19 @@ pub enum AnnotationType {
     #[serde(rename = "bounding_box")]
     #[strum(serialize = "bounding_box")]
     BoundingBox(BoundingBox),
-    Cuboid,
+    #[strum(serialize = "cuboid")]
+    Cuboid(Cuboid),
     #[serde(rename = "directional_vector")]
     DirectionalVector,
     #[strum(serialize = "ellipse")]
-    Ellipse,
+    Ellipse(Ellipse),
     Inference,
     #[serde(rename = "instance_id")]
     InstanceId,
     #[strum(serialize = "keypoint")]
     Keypoint(Keypoint),
-    Line,
+    #[strum(serialize = "line")]
+    Line(Line),
     Measures,
     #[strum(serialize = "polygon")]
     Polygon(Polygon),
@@ -102,29 +236,33 @@ pub enum AnnotationType {
 // line: 11
 // line-text-instanceid: [6 9 11]
 // keypoint: 7
-// ellipse: 60
-// cuboid: 8
 
-impl From<AnnotationType> for u32 {
-    fn from(value: AnnotationType) -> u32 {
-        match value {
+/// The ids of the annotation types known from the V7 payloads above, the other types fail
+/// rather than being sent with a guessed id
+impl TryFrom<AnnotationType> for u32 {
+    type Error = anyhow::Error;
+
+    fn try_from(value: AnnotationType) -> Result<u32> {
+        Ok(match value {
             AnnotationType::Attributes => 5,
-            AnnotationType::AutoAnnotate => todo!(),
             AnnotationType::BoundingBox(_) => 2,
-            AnnotationType::Cuboid => todo!(),
-            AnnotationType::DirectionalVector => todo!(),
-            AnnotationType::Ellipse => todo!(),
-            AnnotationType::Inference => todo!(),
-            AnnotationType::InstanceId => todo!(),
-            AnnotationType::Keypoint(_) => todo!(),
-            AnnotationType::Line => 11,
-            AnnotationType::Measures => todo!(),
+            AnnotationType::Line(_) => 11,
             AnnotationType::Polygon(_) => 3,
             AnnotationType::Skeleton => 12,
             AnnotationType::Tag(_) => 1,
             AnnotationType::Text(_) => 6,
-            AnnotationType::RasterLayer => todo!(),
-        }
+            AnnotationType::AutoAnnotate
+            | AnnotationType::Cuboid(_)
+            | AnnotationType::DirectionalVector
+            | AnnotationType::Ellipse(_)
+            | AnnotationType::Inference
+            | AnnotationType::InstanceId
+            | AnnotationType::Keypoint(_)
+            | AnnotationType::Measures
+            | AnnotationType::RasterLayer => {
+                bail!("The id of annotation type {value} is not known")
+            }
+        })
     }
 }
 
@@ -136,13 +274,13 @@ impl AnnotationType {
             "attributes" => AnnotationType::Attributes,
             "auto_annotate" => AnnotationType::AutoAnnotate,
             "bounding_box" => AnnotationType::BoundingBox(Default::default()),
-            "cuboid" => AnnotationType::Cuboid,
+            "cuboid" => AnnotationType::Cuboid(Default::default()),
             "directional_vector" => AnnotationType::DirectionalVector,
-            "ellipse" => AnnotationType::Ellipse,
+            "ellipse" => AnnotationType::Ellipse(Default::default()),
             "inference" => AnnotationType::Inference,
             "instance_id" => AnnotationType::InstanceId,
             "keypoint" => AnnotationType::Keypoint(Default::default()),
-            "line" => AnnotationType::Line,
+            "line" => AnnotationType::Line(Default::default()),
             "measures" => AnnotationType::Measures,
             "polygon" => AnnotationType::Polygon(Default::default()),
             "skeleton" => AnnotationType::Skeleton,
@@ -154,36 +292,38 @@ impl AnnotationType {
     }
 }
 
-#[derive(Debug, Default, Clone, Serialize, Deserialize, Dummy, PartialEq, Eq)]
+#[derive(Debug, Default, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[cfg_attr(feature = "fake", derive(Dummy))]
 pub struct AnnotationDataset {
     pub id: Option<u32>,
 }
 
-#[derive(Debug, Default, Clone, Serialize, Deserialize, Dummy)]
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "fake", derive(Dummy))]
 pub struct AnnotationClass {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub annotation_class_image_url: Option<String>,
 
-    #[serde(skip_serializing_if = "Vec::is_empty")]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub annotation_types: Vec<Option<String>>,
 
     #[serde(skip_serializing_if = "Option::is_none")]
     pub dataset_id: Option<u32>,
 
-    // #[serde(skip_serializing_if = "Vec::is_empty")]
+    #[serde(default)]
     pub datasets: Vec<Option<AnnotationDataset>>,
 
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub id: Option<u32>,
+    pub id: Option<ClassId>,
 
     #[serde(skip_serializing_if = "Option::is_none")]
     pub team_id: Option<u32>,
 
-    // #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
     pub description: Option<String>,
 
-    // #[serde(skip_serializing_if = "Option::is_none")]
-    pub images: Vec<Option<String>>, // TODO: find out what this type is
+    #[serde(default)]
+    pub images: Vec<AnnotationClassImage>,
 
     #[serde(skip_serializing_if = "Option::is_none")]
     pub inserted_at: Option<String>,
@@ -196,8 +336,142 @@ pub struct AnnotationClass {
 
     #[serde(skip_serializing_if = "Option::is_none")]
     pub updated_at: Option<String>,
+
+    /// Archived classes are hidden from annotators but their annotations are kept
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub archived: Option<bool>,
+}
+
+/// An example image of an annotation class, shown to annotators next to the class
+#[derive(Debug, Default, Clone, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(feature = "fake", derive(Dummy))]
+pub struct AnnotationClassImage {
+    pub id: Option<String>,
+    /// Position of the image in the class' list of images
+    pub index: Option<u32>,
+    /// Url of the image cropped to the region selected when it was added
+    pub crop_url: Option<String>,
+    pub crop_key: Option<String>,
+    /// Url of the full image the crop was taken from
+    pub original_image_url: Option<String>,
+    pub key: Option<String>,
+    pub scale: Option<f64>,
+    pub x: Option<f64>,
+    pub y: Option<f64>,
+}
+
+/// Which version of an `AnnotationClassImage` to download
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Display, EnumString)]
+#[strum(serialize_all = "lowercase")]
+pub enum ClassImageVariant {
+    #[default]
+    Crop,
+    Original,
+}
+
+#[cfg(feature = "client")]
+impl AnnotationClassImage {
+    /// Downloads the image to `cache_dir`, returning the path of the cached file.
+    ///
+    /// Images are cached by id and variant so an image already in `cache_dir` is returned
+    /// without a request, an empty cached file is downloaded again. Urls on the V7 API are
+    /// fetched with `client` and so are sent the API key, any other url is pre-signed and
+    /// fetched with `download_client` which should not be the `V7Client`. Note that the
+    /// image is written with blocking IO.
+    pub async fn download<C>(
+        &self,
+        client: &C,
+        download_client: &dyn PresignedUrlMethods,
+        variant: ClassImageVariant,
+        cache_dir: &Path,
+    ) -> Result<PathBuf>
+    where
+        C: V7Methods + std::marker::Sync,
+    {
+        let id = self.id.as_ref().context("Class image is missing an id")?;
+        let (url, key) = match variant {
+            ClassImageVariant::Crop => (&self.crop_url, &self.crop_key),
+            ClassImageVariant::Original => (&self.original_image_url, &self.key),
+        };
+        let url = url
+            .as_ref()
+            .with_context(|| format!("Class image {id} is missing the {variant} url"))?;
+
+        // Ids that could escape `cache_dir` or clash on case-insensitive file systems are hashed
+        let is_safe = |value: &str| {
+            !value.is_empty()
+                && value
+                    .chars()
+                    .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-' || c == '_')
+        };
+        let mut file_name = if is_safe(id) {
+            format!("{id}_{variant}")
+        } else {
+            format!("{:x}_{variant}", Md5::digest(id))
+        };
+        if let Some(extension) = key
+            .as_deref()
+            .and_then(|key| Path::new(key).extension())
+            .and_then(|extension| extension.to_str())
+            .filter(|extension| extension.chars().all(|c| c.is_ascii_alphanumeric()))
+        {
+            file_name = format!("{file_name}.{}", extension.to_ascii_lowercase());
+        }
+        let path = cache_dir.join(file_name);
+        if let Ok(metadata) = std::fs::metadata(&path) {
+            if metadata.is_file() && metadata.len() > 0 {
+                return Ok(path);
+            }
+        }
+
+        let response = match url.strip_prefix(client.api_endpoint()) {
+            Some(endpoint) => client.get(endpoint).await?,
+            None => download_client.get_url(url, HeaderMap::new()).await?,
+        };
+        if !response.status().is_success() {
+            bail!(HttpStatusError::from_response(response).await);
+        }
+        let contents = response.bytes().await?;
+
+        if contents.is_empty() {
+            bail!("Class image {id} has an empty {variant} image");
+        }
+
+        // Written to a temporary file first so an interrupted download is never cached
+        std::fs::create_dir_all(cache_dir)
+            .with_context(|| format!("Unable to create {}", cache_dir.display()))?;
+        let partial = path.with_extension("part");
+        std::fs::write(&partial, &contents)
+            .with_context(|| format!("Unable to write {}", partial.display()))?;
+        std::fs::rename(&partial, &path)?;
+        Ok(path)
+    }
+}
+
+/// A user who contributed to an annotation
+#[derive(Debug, Default, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct AnnotationActor {
+    /// Either `annotator` or `reviewer`
+    pub role: Option<String>,
+    pub user_id: Option<u32>,
+}
+
+/// An annotation as returned by the v2 item annotations endpoint
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct ItemAnnotation {
+    pub id: Option<String>,
+    pub annotation_class_id: Option<u32>,
+    pub annotation_group_id: Option<String>,
+    pub context_keys: Option<AnnotationContext>,
+    /// The annotation payload e.g. `{"polygon": {"paths": [...]}}`
+    pub data: Option<serde_json::Value>,
+    #[serde(default)]
+    pub actors: Vec<AnnotationActor>,
+    pub inserted_at: Option<String>,
+    pub updated_at: Option<String>,
 }
 
+#[cfg(feature = "client")]
 impl AnnotationClass {
     pub async fn update<C>(&self, client: &C) -> Result<AnnotationClass>
     where
@@ -208,10 +482,25 @@ impl AnnotationClass {
             self.id.context("Annotation class is missing an id")?
         );
         let response = client.put(&endpoint, Some(&self)).await?;
+        if let Some(cache) = client.cache() {
+            cache.invalidate_classes();
+        }
 
         expect_http_ok!(response, AnnotationClass)
     }
 
+    /// Archives the class, hiding it from annotators while keeping its existing annotations
+    pub async fn archive<C>(&self, client: &C) -> Result<AnnotationClass>
+    where
+        C: V7Methods,
+    {
+        let mut class = self.clone();
+        class.archived = Some(true);
+        class.update(client).await
+    }
+
+    /// Permanently deletes the class, which V7 refuses if the class has annotations.
+    /// Use `archive` or `TeamDataMethods::delete_annotation_classes` in that case.
     pub async fn delete<C>(&self, client: &C) -> Result<()>
     where
         C: V7Methods,
@@ -222,15 +511,487 @@ impl AnnotationClass {
         );
 
         let response = client.delete::<AnnotationClass>(&endpoint, None).await?;
+        if let Some(cache) = client.cache() {
+            cache.invalidate_classes();
+        }
 
         if response.status() != 204 {
-            bail!(format!(
-                "Invalid status code {} {}",
-                response.status(),
-                response.text().await?
-            ));
+            bail!(HttpStatusError::from_response(response).await);
         }
 
         Ok(())
     }
+
+    /// Downloads the images of the class to `cache_dir` with `AnnotationClassImage::download`,
+    /// returning their paths in the order of the class' images
+    pub async fn download_images<C>(
+        &self,
+        client: &C,
+        download_client: &dyn PresignedUrlMethods,
+        variant: ClassImageVariant,
+        cache_dir: &Path,
+    ) -> Result<Vec<PathBuf>>
+    where
+        C: V7Methods + std::marker::Sync,
+    {
+        let mut paths = Vec::with_capacity(self.images.len());
+        for image in &self.images {
+            paths.push(
+                image
+                    .download(client, download_client, variant, cache_dir)
+                    .await?,
+            );
+        }
+        Ok(paths)
+    }
+}
+
+/// Selects the annotations to delete with `AnnotationDeletePreview`.
+/// Every criterion that is set must match, an empty filter matches every annotation.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct AnnotationDeleteFilter {
+    pub annotation_class_ids: Option<Vec<u32>>,
+    /// User ids of the annotators, matches if any of the annotators is in the list
+    pub annotator_ids: Option<Vec<u32>>,
+    /// Only annotations created at or after this time, in seconds since the unix epoch
+    pub inserted_after: Option<i64>,
+    /// Only annotations created before this time, in seconds since the unix epoch
+    pub inserted_before: Option<i64>,
+}
+
+impl AnnotationDeleteFilter {
+    pub fn matches(&self, annotation: &ItemAnnotation) -> Result<bool> {
+        if let Some(class_ids) = self.annotation_class_ids.as_ref() {
+            match annotation.annotation_class_id {
+                Some(class_id) if class_ids.contains(&class_id) => {}
+                _ => return Ok(false),
+            }
+        }
+        if let Some(annotator_ids) = self.annotator_ids.as_ref() {
+            let annotated = annotation.actors.iter().any(|actor| {
+                actor.role.as_deref() == Some("annotator")
+                    && actor.user_id.is_some_and(|id| annotator_ids.contains(&id))
+            });
+            if !annotated {
+                return Ok(false);
+            }
+        }
+        if self.inserted_after.is_some() || self.inserted_before.is_some() {
+            let inserted_at = parse_timestamp(
+                annotation
+                    .inserted_at
+                    .as_ref()
+                    .context("Annotation is missing inserted_at")?,
+            )?;
+            if self.inserted_after.is_some_and(|after| inserted_at < after)
+                || self
+                    .inserted_before
+                    .is_some_and(|before| inserted_at >= before)
+            {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+}
+
+#[cfg(feature = "client")]
+#[derive(Debug, Serialize)]
+pub(crate) struct DeleteAnnotationsPayload<'a> {
+    pub(crate) annotation_ids: Vec<&'a str>,
+}
+
+/// The annotations that will be removed by a bulk delete.
+///
+/// A bulk delete can only be executed from a preview so that the number of annotations
+/// to be deleted can be checked first, e.g. to catch a filter that matches everything.
+#[derive(Debug, Clone)]
+pub struct AnnotationDeletePreview {
+    /// Matching annotations keyed by item id
+    annotations: BTreeMap<String, Vec<ItemAnnotation>>,
+}
+
+/// A bulk delete that failed part way, the annotations of earlier items were deleted
+#[derive(Debug)]
+pub struct AnnotationDeleteError {
+    /// Number of annotations deleted before the failure
+    pub deleted: usize,
+    pub error: anyhow::Error,
+}
+
+impl std::fmt::Display for AnnotationDeleteError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Failed after deleting {} annotations: {}",
+            self.deleted, self.error
+        )
+    }
+}
+
+impl std::error::Error for AnnotationDeleteError {}
+
+impl AnnotationDeletePreview {
+    #[cfg(feature = "client")]
+    /// Lists the annotations of `items` and selects those matching `filter` for deletion.
+    /// Nothing is deleted until `execute` is called.
+    pub async fn prepare<C>(
+        client: &C,
+        items: &[DatasetItemV2],
+        filter: &AnnotationDeleteFilter,
+    ) -> Result<Self>
+    where
+        C: V7Methods + std::marker::Sync,
+    {
+        let mut annotations = BTreeMap::new();
+        for item in items.iter() {
+            let item_id = item.id.as_ref().context("Dataset item has no Id")?;
+            let mut matching = Vec::new();
+            for annotation in item.list_annotations(client).await? {
+                if filter.matches(&annotation)? {
+                    matching.push(annotation);
+                }
+            }
+            if !matching.is_empty() {
+                annotations.insert(item_id.to_string(), matching);
+            }
+        }
+        Ok(Self { annotations })
+    }
+
+    /// The annotations that will be deleted keyed by item id
+    pub fn annotations(&self) -> &BTreeMap<String, Vec<ItemAnnotation>> {
+        &self.annotations
+    }
+
+    /// Number of annotations that will be deleted
+    pub fn count(&self) -> usize {
+        self.annotations
+            .values()
+            .map(|annotations| annotations.len())
+            .sum()
+    }
+
+    #[cfg(feature = "client")]
+    /// Deletes the previewed annotations an item at a time in item id order, returning
+    /// the number deleted. If an item fails the error is an `AnnotationDeleteError`
+    /// with the number of annotations deleted before it.
+    pub async fn execute<C>(&self, client: &C) -> Result<usize>
+    where
+        C: V7Methods + std::marker::Sync,
+    {
+        let mut deleted = 0;
+        for (item_id, annotations) in self.annotations.iter() {
+            match Self::delete_item_annotations(client, item_id, annotations).await {
+                Ok(count) => deleted += count,
+                Err(error) => bail!(AnnotationDeleteError { deleted, error }),
+            }
+        }
+        Ok(deleted)
+    }
+
+    #[cfg(feature = "client")]
+    /// Deletes `annotations` of the item `item_id`, returning the number deleted
+    pub(crate) async fn delete_item_annotations<C>(
+        client: &C,
+        item_id: &str,
+        annotations: &[ItemAnnotation],
+    ) -> Result<usize>
+    where
+        C: V7Methods + std::marker::Sync,
+    {
+        let payload = DeleteAnnotationsPayload {
+            annotation_ids: annotations
+                .iter()
+                .map(|annotation| {
+                    annotation
+                        .id
+                        .as_deref()
+                        .context("Annotation is missing an id")
+                })
+                .collect::<Result<Vec<&str>>>()?,
+        };
+        let endpoint = format!("v2/teams/{}/items/{}/annotations", client.team(), item_id);
+        let response = client.delete(&endpoint, Some(&payload)).await?;
+        if !response.status().is_success() {
+            bail!(HttpStatusError::from_response(response).await);
+        }
+        Ok(payload.annotation_ids.len())
+    }
+}
+
+#[cfg(all(test, feature = "client"))]
+mod test_client_calls {
+    use super::*;
+    use crate::client::V7Client;
+    use serde_json::json;
+    use wiremock::matchers::{body_json, header, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    #[test]
+    fn test_class_metadata() {
+        let metadata = json!({
+            "_color": "rgba(255,0,0,1.0)",
+            "polygon": {},
+            "measures": {
+                "area": {"name": "Area", "abbr": "A", "unit": "mm^2"},
+                "density": {"name": "Density", "abbr": "D", "unit": "1/mm^2", "formula": "count / area", "precision": 2}
+            },
+            "inference": {"model_id": "model-1", "model_name": "Tumour detector", "class_name": "tumor"},
+            "auto_annotate": {"enabled": true, "output_type": "polygon"}
+        });
+
+        let parsed: AnnotationClassMetadata = serde_json::from_value(metadata.clone()).unwrap();
+        let measures = parsed.measures.as_ref().unwrap();
+        assert_eq!(measures["area"].unit.as_deref(), Some("mm^2"));
+        assert_eq!(measures["density"].formula.as_deref(), Some("count / area"));
+        assert_eq!(
+            parsed.inference.as_ref().unwrap().class_name.as_deref(),
+            Some("tumor")
+        );
+        assert_eq!(parsed.auto_annotate.as_ref().unwrap().enabled, Some(true));
+        assert_eq!(serde_json::to_value(&parsed).unwrap(), metadata);
+    }
+
+    #[test]
+    fn test_class_without_datasets_or_images() {
+        let class: AnnotationClass =
+            serde_json::from_value(json!({"id": 1, "name": "Tumour"})).unwrap();
+        assert_eq!(class.name.as_deref(), Some("Tumour"));
+        assert!(class.datasets.is_empty());
+        assert!(class.images.is_empty());
+        assert_eq!(class.description, None);
+    }
+
+    #[test]
+    fn test_annotation_delete_filter() {
+        let annotation = ItemAnnotation {
+            annotation_class_id: Some(1),
+            actors: vec![AnnotationActor {
+                role: Some("annotator".to_string()),
+                user_id: Some(7),
+            }],
+            inserted_at: Some("2024-03-01T10:00:00Z".to_string()),
+            ..Default::default()
+        };
+        let after = parse_timestamp("2024-03-01T00:00:00Z").unwrap();
+
+        assert!(AnnotationDeleteFilter::default()
+            .matches(&annotation)
+            .unwrap());
+        assert!(AnnotationDeleteFilter {
+            annotation_class_ids: Some(vec![1]),
+            annotator_ids: Some(vec![7]),
+            inserted_after: Some(after),
+            ..Default::default()
+        }
+        .matches(&annotation)
+        .unwrap());
+        assert!(!AnnotationDeleteFilter {
+            annotator_ids: Some(vec![8]),
+            ..Default::default()
+        }
+        .matches(&annotation)
+        .unwrap());
+        assert!(!AnnotationDeleteFilter {
+            inserted_before: Some(after),
+            ..Default::default()
+        }
+        .matches(&annotation)
+        .unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_annotation_bulk_delete() {
+        let mock_server = MockServer::start().await;
+        let item = |id: &str| DatasetItemV2 {
+            id: Some(id.to_string()),
+            ..Default::default()
+        };
+        let items = vec![item("item-2"), item("item-1")];
+
+        Mock::given(method("GET"))
+            .and(path("/v2/teams/some-team/items/item-1/annotations"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!([
+                {"id": "annotation-1", "annotation_class_id": 1},
+                {"id": "annotation-2", "annotation_class_id": 2},
+                {"id": "annotation-3", "annotation_class_id": 1}
+            ])))
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("DELETE"))
+            .and(path("/v2/teams/some-team/items/item-1/annotations"))
+            .and(body_json(
+                json!({"annotation_ids": ["annotation-1", "annotation-3"]}),
+            ))
+            .respond_with(ResponseTemplate::new(204))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/v2/teams/some-team/items/item-2/annotations"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!([
+                {"id": "annotation-4", "annotation_class_id": 1}
+            ])))
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("DELETE"))
+            .and(path("/v2/teams/some-team/items/item-2/annotations"))
+            .respond_with(ResponseTemplate::new(500))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let client: V7Client = V7Client::new(
+            format!("{}/", mock_server.uri()),
+            "api-key".to_string(),
+            "some-team".to_string(),
+        )
+        .expect("Failed to get V7Client");
+
+        let filter = AnnotationDeleteFilter {
+            annotation_class_ids: Some(vec![1]),
+            ..Default::default()
+        };
+        let preview = AnnotationDeletePreview::prepare(&client, &items, &filter)
+            .await
+            .expect("Failed to preview annotation delete");
+        assert_eq!(preview.count(), 3);
+        assert_eq!(
+            preview.annotations().keys().collect::<Vec<_>>(),
+            vec!["item-1", "item-2"]
+        );
+
+        // The annotations of item-1 are deleted before item-2 fails
+        let error = preview
+            .execute(&client)
+            .await
+            .expect_err("Failed to delete the annotations of item-2");
+        let error = error
+            .downcast_ref::<AnnotationDeleteError>()
+            .expect("Expected an AnnotationDeleteError");
+        assert_eq!(error.deleted, 2);
+    }
+
+    #[tokio::test]
+    async fn test_download_class_images() {
+        let mock_server = MockServer::start().await;
+        let client = V7Client::new(
+            format!("{}/api/", mock_server.uri()),
+            "api-key".to_string(),
+            "some-team".to_string(),
+        )
+        .unwrap();
+
+        Mock::given(method("GET"))
+            .and(path("/api/annotation_class_images/img-1/crop"))
+            .and(header("Authorization", "ApiKey api-key"))
+            .respond_with(ResponseTemplate::new(200).set_body_bytes(b"crop".to_vec()))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/bucket/original.png"))
+            .respond_with(ResponseTemplate::new(200).set_body_bytes(b"original".to_vec()))
+            .expect(3)
+            .mount(&mock_server)
+            .await;
+
+        let class: AnnotationClass = serde_json::from_value(json!({
+            "id": 1,
+            "name": "Tumour bed",
+            "datasets": [],
+            "description": null,
+            "images": [{
+                "id": "img-1",
+                "index": 0,
+                "crop_url": format!("{}/api/annotation_class_images/img-1/crop", mock_server.uri()),
+                "crop_key": "classes/img-1/crop.jpg",
+                "original_image_url": format!("{}/bucket/original.png?signature=abc", mock_server.uri()),
+                "key": "classes/img-1/original.png",
+                "scale": 1.0,
+                "x": 10.0,
+                "y": 20.0
+            }]
+        }))
+        .unwrap();
+        let cache_dir = tempfile::tempdir().unwrap();
+        let download_client = reqwest::Client::new();
+
+        for _ in 0..2 {
+            let crops = class
+                .download_images(
+                    &client,
+                    &download_client,
+                    ClassImageVariant::Crop,
+                    cache_dir.path(),
+                )
+                .await
+                .expect("Failed to download crops");
+            assert_eq!(crops, vec![cache_dir.path().join("img-1_crop.jpg")]);
+            assert_eq!(std::fs::read(&crops[0]).unwrap(), b"crop");
+        }
+
+        let original = class.images[0]
+            .download(
+                &client,
+                &download_client,
+                ClassImageVariant::Original,
+                cache_dir.path(),
+            )
+            .await
+            .expect("Failed to download original");
+        assert_eq!(original, cache_dir.path().join("img-1_original.png"));
+        assert_eq!(std::fs::read(&original).unwrap(), b"original");
+
+        // An empty cached file is not trusted
+        std::fs::write(&original, b"").unwrap();
+        let mut image = class.images[0].clone();
+        image.id = Some("../Img-1".to_string());
+        let unsafe_id = image
+            .download(
+                &client,
+                &download_client,
+                ClassImageVariant::Original,
+                cache_dir.path(),
+            )
+            .await
+            .expect("Failed to download original");
+        assert_eq!(unsafe_id.parent(), Some(cache_dir.path()));
+        assert_eq!(
+            unsafe_id.file_name().unwrap().to_str().unwrap(),
+            format!("{:x}_original.png", Md5::digest("../Img-1"))
+        );
+        class.images[0]
+            .download(
+                &client,
+                &download_client,
+                ClassImageVariant::Original,
+                cache_dir.path(),
+            )
+            .await
+            .expect("Failed to download original");
+        assert_eq!(std::fs::read(&original).unwrap(), b"original");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_annotation_type_ids() {
+        assert_eq!(
+            u32::try_from(AnnotationType::Polygon(Polygon::default())).unwrap(),
+            3
+        );
+        assert_eq!(u32::try_from(AnnotationType::Skeleton).unwrap(), 12);
+        let error = u32::try_from(AnnotationType::Cuboid(Cuboid::default())).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "The id of annotation type cuboid is not known"
+        );
+        assert!(u32::try_from(AnnotationType::Ellipse(Ellipse::default())).is_err());
+        assert!(u32::try_from(AnnotationType::RasterLayer).is_err());
+    }
 }
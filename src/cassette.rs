@@ -0,0 +1,456 @@
+//! Record and replay of the requests made through `V7Methods`, for running integration
+//! tests deterministically without access to V7.
+//!
+//! Wrap a `V7Client` in a `RecordingClient` to capture each request and response into a
+//! `Cassette`, saved as JSON, then use a `ReplayClient` loaded from the cassette in place
+//! of the client. Only the method and endpoint of a request are compared on replay, as
+//! request bodies often contain generated ids, the body is kept for reference.
+use crate::client::{ApiVersion, ResourceCache, V7Methods};
+use crate::response::{HttpError, HttpResponse};
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use base64::{engine::general_purpose::STANDARD, Engine as _};
+use log::debug;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::sync::Mutex;
+
+/// A request made through `V7Methods` and the response it received
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Interaction {
+    pub method: String,
+    /// Endpoint relative to the API endpoint of the client, including any query
+    pub endpoint: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub request_body: Option<serde_json::Value>,
+    pub status: u16,
+    #[serde(default)]
+    pub headers: BTreeMap<String, String>,
+    pub body: RecordedBody,
+}
+
+/// The body of a recorded response, kept as text when it is valid UTF-8 so that cassettes
+/// can be reviewed, and base64 encoded otherwise
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RecordedBody {
+    Text(String),
+    Base64(String),
+}
+
+impl Default for RecordedBody {
+    fn default() -> Self {
+        Self::Text(String::new())
+    }
+}
+
+impl RecordedBody {
+    pub fn from_bytes(bytes: Vec<u8>) -> Self {
+        match String::from_utf8(bytes) {
+            Ok(text) => Self::Text(text),
+            Err(error) => Self::Base64(STANDARD.encode(error.into_bytes())),
+        }
+    }
+
+    pub fn to_bytes(&self) -> Result<Vec<u8>, HttpError> {
+        match self {
+            Self::Text(text) => Ok(text.clone().into_bytes()),
+            Self::Base64(encoded) => STANDARD.decode(encoded).map_err(HttpError::new),
+        }
+    }
+}
+
+impl Interaction {
+    fn response(&self) -> Result<HttpResponse, HttpError> {
+        let mut builder = http::Response::builder().status(self.status);
+        for (name, value) in self.headers.iter() {
+            builder = builder.header(name, value);
+        }
+        builder
+            .body(self.body.to_bytes()?)
+            .map(HttpResponse::from)
+            .map_err(|err| {
+                HttpError::new(format!(
+                    "Invalid recorded response to {} {}: {err}",
+                    self.method, self.endpoint
+                ))
+            })
+    }
+}
+
+/// The interactions recorded by a `RecordingClient`, in the order they were made
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Cassette {
+    pub team: String,
+    /// API endpoint of the recording client, urls on it are answered by the `ReplayClient`
+    #[serde(default)]
+    pub api_endpoint: String,
+    /// API version of the recording client, returned by the `ReplayClient`
+    #[serde(default)]
+    pub api_version: ApiVersion,
+    pub interactions: Vec<Interaction>,
+}
+
+impl Cassette {
+    pub fn load(path: &Path) -> Result<Self> {
+        let contents = std::fs::read_to_string(path)
+            .with_context(|| format!("Unable to read cassette {}", path.display()))?;
+        Ok(serde_path_to_error::deserialize(
+            &mut serde_json::Deserializer::from_str(&contents),
+        )?)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        std::fs::write(path, serde_json::to_string_pretty(self)?)
+            .with_context(|| format!("Unable to write cassette {}", path.display()))
+    }
+}
+
+/// Forwards requests to a client, recording each request and response.
+///
+/// The API key is never recorded, though the response headers and bodies are so review
+/// a cassette before committing it.
+#[derive(Debug)]
+pub struct RecordingClient<C> {
+    client: C,
+    cassette: Mutex<Cassette>,
+}
+
+impl<C> RecordingClient<C>
+where
+    C: V7Methods,
+{
+    pub fn new(client: C) -> Self {
+        let team = client.team().to_string();
+        let api_endpoint = client.api_endpoint().to_string();
+        let api_version = client.version();
+        Self {
+            client,
+            cassette: Mutex::new(Cassette {
+                team,
+                api_endpoint,
+                api_version,
+                interactions: vec![],
+            }),
+        }
+    }
+
+    /// The interactions recorded so far
+    pub fn cassette(&self) -> Cassette {
+        self.cassette
+            .lock()
+            .map(|cassette| cassette.clone())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        self.cassette().save(path)
+    }
+
+    // Reads the response to record it, returning a copy in its place
+    async fn record(
+        &self,
+        method: &str,
+        endpoint: &str,
+        request_body: Option<serde_json::Value>,
+        response: HttpResponse,
+    ) -> Result<HttpResponse, HttpError> {
+        let status = response.status().as_u16();
+        let headers = response
+            .headers()
+            .iter()
+            .filter_map(|(name, value)| Some((name.to_string(), value.to_str().ok()?.to_string())))
+            .collect();
+        let body = RecordedBody::from_bytes(response.bytes().await?.to_vec());
+        let interaction = Interaction {
+            method: method.to_string(),
+            endpoint: endpoint.to_string(),
+            request_body,
+            status,
+            headers,
+            body,
+        };
+        let response = interaction.response()?;
+        if let Ok(mut cassette) = self.cassette.lock() {
+            cassette.interactions.push(interaction);
+        }
+        Ok(response)
+    }
+}
+
+#[async_trait]
+impl<C> V7Methods for RecordingClient<C>
+where
+    C: V7Methods + std::marker::Sync + std::marker::Send,
+{
+    async fn get(&self, endpoint: &str) -> Result<HttpResponse, HttpError> {
+        let response = self.client.get(endpoint).await?;
+        self.record("GET", endpoint, None, response).await
+    }
+
+    async fn put<S: serde::Serialize + ?Sized + std::marker::Sync>(
+        &self,
+        endpoint: &str,
+        data: Option<&S>,
+    ) -> Result<HttpResponse, HttpError> {
+        let response = self.client.put(endpoint, data).await?;
+        let body = data.and_then(|data| serde_json::to_value(data).ok());
+        self.record("PUT", endpoint, body, response).await
+    }
+
+    async fn post<S: serde::Serialize + ?Sized + std::marker::Sync>(
+        &self,
+        endpoint: &str,
+        data: &S,
+    ) -> Result<HttpResponse, HttpError> {
+        let response = self.client.post(endpoint, data).await?;
+        let body = serde_json::to_value(data).ok();
+        self.record("POST", endpoint, body, response).await
+    }
+
+    async fn delete<S: serde::Serialize + ?Sized + std::marker::Sync>(
+        &self,
+        endpoint: &str,
+        data: Option<&S>,
+    ) -> Result<HttpResponse, HttpError> {
+        let response = self.client.delete(endpoint, data).await?;
+        let body = data.and_then(|data| serde_json::to_value(data).ok());
+        self.record("DELETE", endpoint, body, response).await
+    }
+
+    fn team(&self) -> &String {
+        self.client.team()
+    }
+
+    fn api_endpoint(&self) -> &str {
+        self.client.api_endpoint()
+    }
+
+    fn version(&self) -> ApiVersion {
+        self.client.version()
+    }
+
+    fn cache(&self) -> Option<&ResourceCache> {
+        self.client.cache()
+    }
+}
+
+/// Answers requests with the responses of a `Cassette` instead of calling V7.
+///
+/// Each request is answered by the first interaction not yet replayed with the same
+/// method and endpoint, so a request made more than once receives the recorded responses
+/// in order.
+///
+/// A request for which there is no interaction left fails with an `HttpError`.
+#[derive(Debug)]
+pub struct ReplayClient {
+    team: String,
+    api_endpoint: String,
+    api_version: ApiVersion,
+    interactions: Mutex<Vec<Option<Interaction>>>,
+}
+
+impl ReplayClient {
+    pub fn new(cassette: Cassette) -> Self {
+        Self {
+            team: cassette.team,
+            api_endpoint: cassette.api_endpoint,
+            api_version: cassette.api_version,
+            interactions: Mutex::new(cassette.interactions.into_iter().map(Some).collect()),
+        }
+    }
+
+    pub fn load(path: &Path) -> Result<Self> {
+        Ok(Self::new(Cassette::load(path)?))
+    }
+
+    /// The number of interactions that have not been replayed
+    pub fn remaining(&self) -> usize {
+        self.interactions
+            .lock()
+            .map(|interactions| interactions.iter().flatten().count())
+            .unwrap_or_default()
+    }
+
+    fn replay(&self, method: &str, endpoint: &str) -> Result<HttpResponse, HttpError> {
+        debug!("ReplayClient::{method}({endpoint})");
+        let mut interactions = self
+            .interactions
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        interactions
+            .iter_mut()
+            .find(|interaction| {
+                interaction.as_ref().is_some_and(|interaction| {
+                    interaction.method == method && interaction.endpoint == endpoint
+                })
+            })
+            .and_then(Option::take)
+            .ok_or_else(|| {
+                HttpError::new(format!(
+                    "No recorded interaction left for {method} {endpoint}"
+                ))
+            })?
+            .response()
+    }
+}
+
+#[async_trait]
+impl V7Methods for ReplayClient {
+    async fn get(&self, endpoint: &str) -> Result<HttpResponse, HttpError> {
+        self.replay("GET", endpoint)
+    }
+
+    async fn put<S: serde::Serialize + ?Sized + std::marker::Sync>(
+        &self,
+        endpoint: &str,
+        _data: Option<&S>,
+    ) -> Result<HttpResponse, HttpError> {
+        self.replay("PUT", endpoint)
+    }
+
+    async fn post<S: serde::Serialize + ?Sized + std::marker::Sync>(
+        &self,
+        endpoint: &str,
+        _data: &S,
+    ) -> Result<HttpResponse, HttpError> {
+        self.replay("POST", endpoint)
+    }
+
+    async fn delete<S: serde::Serialize + ?Sized + std::marker::Sync>(
+        &self,
+        endpoint: &str,
+        _data: Option<&S>,
+    ) -> Result<HttpResponse, HttpError> {
+        self.replay("DELETE", endpoint)
+    }
+
+    fn team(&self) -> &String {
+        &self.team
+    }
+
+    fn api_endpoint(&self) -> &str {
+        &self.api_endpoint
+    }
+
+    fn version(&self) -> ApiVersion {
+        self.api_version
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::V7Client;
+    use crate::datasets::{Dataset, DatasetDescribeMethods};
+    use crate::team::{Team, TeamDescribeMethods};
+    use serde_json::json;
+    use wiremock::matchers::{method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    #[tokio::test]
+    async fn test_record_and_replay() {
+        let mock_server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/datasets"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_json(json!([{"id": 1, "name": "Biopsies", "slug": "biopsies"}])),
+            )
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/memberships"))
+            .respond_with(ResponseTemplate::new(403))
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/thumbnail"))
+            .respond_with(ResponseTemplate::new(200).set_body_bytes(vec![0x89, 0xff, 0x00]))
+            .mount(&mock_server)
+            .await;
+
+        let client = RecordingClient::new(
+            V7Client::new(
+                format!("{}/", mock_server.uri()),
+                "api-key".to_string(),
+                "some-team".to_string(),
+            )
+            .unwrap(),
+        );
+        let summaries = Dataset::list_dataset_summaries(&client)
+            .await
+            .expect("Failed to list datasets");
+        assert_eq!(summaries[0].slug.as_deref(), Some("biopsies"));
+        assert!(Team::list_memberships(&client).await.is_err());
+        let thumbnail = client
+            .get("thumbnail")
+            .await
+            .unwrap()
+            .bytes()
+            .await
+            .unwrap();
+        assert_eq!(thumbnail.as_ref(), [0x89, 0xff, 0x00]);
+        assert_eq!(
+            client.cassette().interactions[2].body,
+            RecordedBody::Base64("if8A".to_string())
+        );
+
+        let dir = tempfile::tempdir().unwrap();
+        let cassette_path = dir.path().join("cassette.json");
+        client.save(&cassette_path).unwrap();
+        let mock_server_uri = mock_server.uri();
+        drop(mock_server);
+
+        let replay = ReplayClient::load(&cassette_path).unwrap();
+        assert_eq!(replay.team(), "some-team");
+        assert_eq!(replay.api_endpoint(), format!("{}/", mock_server_uri));
+        assert_eq!(replay.remaining(), 3);
+        let replayed = Dataset::list_dataset_summaries(&replay)
+            .await
+            .expect("Failed to replay datasets");
+        assert_eq!(replayed, summaries);
+        let error = Team::list_memberships(&replay).await.unwrap_err();
+        assert!(error.to_string().contains("403"), "{error}");
+        let thumbnail = replay
+            .get("thumbnail")
+            .await
+            .unwrap()
+            .bytes()
+            .await
+            .unwrap();
+        assert_eq!(thumbnail.as_ref(), [0x89, 0xff, 0x00]);
+        assert_eq!(replay.remaining(), 0);
+        let error = replay.get("thumbnail").await.unwrap_err();
+        assert!(error
+            .to_string()
+            .contains("No recorded interaction left for GET thumbnail"));
+    }
+
+    #[test]
+    fn test_record_and_replay_version_and_cache() {
+        let client = RecordingClient::new(
+            V7Client::builder(
+                "http://localhost/".to_string(),
+                "api-key".to_string(),
+                "some-team".to_string(),
+            )
+            .api_version(ApiVersion::V2)
+            .cache_ttl(std::time::Duration::from_secs(60))
+            .build()
+            .unwrap(),
+        );
+        assert_eq!(client.version(), ApiVersion::V2);
+        assert!(client.cache().is_some());
+        assert_eq!(client.cassette().api_version, ApiVersion::V2);
+
+        let replay = ReplayClient::new(client.cassette());
+        assert_eq!(replay.version(), ApiVersion::V2);
+        assert!(replay.cache().is_none());
+
+        let cassette: Cassette =
+            serde_json::from_value(json!({"team": "some-team", "interactions": []})).unwrap();
+        assert_eq!(cassette.api_version, ApiVersion::V1);
+    }
+}
@@ -1,8 +1,10 @@
+#[cfg(feature = "fake")]
 #[allow(unused_imports)]
 use fake::{Dummy, Fake};
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Default, Clone, Serialize, Deserialize, Dummy, PartialEq)]
+#[derive(Debug, Default, Clone, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(feature = "fake", derive(Dummy))]
 pub struct BoundingBox {
     pub x: f32,
     pub y: f32,
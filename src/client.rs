@@ -1,16 +1,167 @@
-use crate::{config::Config, team::Team};
-use anyhow::{Context, Result};
+use crate::compression::{decode_response, ACCEPTED_ENCODINGS};
+use crate::debug::{DebugDump, DebugDumpOptions, DebugSink};
+use crate::response::{HttpError, HttpResponse};
+use crate::{
+    config::Config,
+    team::{Team, TeamAnnotationClasses, TeamMember},
+};
+use anyhow::{bail, Context, Result};
 use async_trait::async_trait;
 use log::debug;
-use reqwest::header::{HeaderMap, HeaderValue, ACCEPT, AUTHORIZATION, CONTENT_TYPE};
+use reqwest::header::{
+    HeaderMap, HeaderValue, ACCEPT, ACCEPT_ENCODING, AUTHORIZATION, CONTENT_TYPE, RETRY_AFTER,
+    USER_AGENT,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Metadata of a V7 response useful for support tickets and throttling decisions
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct ResponseMeta {
+    pub status: u16,
+    /// Id assigned to the request by V7, quote this when raising support tickets
+    pub request_id: Option<String>,
+    pub rate_limit_limit: Option<u64>,
+    pub rate_limit_remaining: Option<u64>,
+    /// Seconds to wait before retrying a throttled request
+    pub retry_after: Option<u64>,
+}
+
+impl ResponseMeta {
+    pub fn from_response(response: &HttpResponse) -> Self {
+        let headers = response.headers();
+        let header_str = |name: &str| -> Option<String> {
+            headers
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .map(|value| value.to_string())
+        };
+        let header_u64 = |name: &str| -> Option<u64> { header_str(name)?.trim().parse().ok() };
+
+        Self {
+            status: response.status().as_u16(),
+            request_id: header_str("x-request-id"),
+            rate_limit_limit: header_u64("x-ratelimit-limit"),
+            rate_limit_remaining: header_u64("x-ratelimit-remaining"),
+            retry_after: header_u64(RETRY_AFTER.as_str()),
+        }
+    }
+}
+
+/// Error returned when V7 responds with an unexpected status code.
+///
+/// Recover it from an `anyhow::Error` with `error.downcast_ref::<HttpStatusError>()`
+/// to access the response metadata.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HttpStatusError {
+    pub meta: ResponseMeta,
+    pub body: String,
+}
+
+impl HttpStatusError {
+    /// Consumes the response, capturing its metadata and body
+    pub async fn from_response(response: HttpResponse) -> Self {
+        let meta = ResponseMeta::from_response(&response);
+        let body = response.text().await.unwrap_or_default();
+        Self { meta, body }
+    }
+}
+
+impl Display for HttpStatusError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Invalid status code {} {}", self.meta.status, self.body)?;
+        if let Some(request_id) = self.meta.request_id.as_ref() {
+            write!(f, " (request id {request_id})")?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for HttpStatusError {}
+
+/// Version of the V7 API available to a team. Teams on V2 workflows reject the endpoints
+/// of V1 workflows, e.g. assigning items or moving them between workflow template stages.
+#[derive(
+    Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize,
+)]
+pub enum ApiVersion {
+    /// Teams still on V1 workflows, which also have access to the V2 endpoints
+    #[default]
+    V1,
+    /// Teams only on V2 workflows
+    V2,
+}
+
+impl Display for ApiVersion {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ApiVersion::V1 => write!(f, "V1"),
+            ApiVersion::V2 => write!(f, "V2"),
+        }
+    }
+}
+
+/// Error returned, before any request is made, when a method relies upon an endpoint that
+/// is not available to the `ApiVersion` of the client.
+///
+/// Recover it from an `anyhow::Error` with `error.downcast_ref::<UnsupportedApiVersionError>()`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnsupportedApiVersionError {
+    pub operation: &'static str,
+    pub required: ApiVersion,
+    pub version: ApiVersion,
+}
+
+impl Display for UnsupportedApiVersionError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} requires the {} API which is not available to {} teams",
+            self.operation, self.required, self.version
+        )
+    }
+}
+
+impl std::error::Error for UnsupportedApiVersionError {}
+
+/// Fails with an `UnsupportedApiVersionError` if `operation` uses V1 workflow endpoints
+/// and the client is for a V2 team
+pub(crate) fn require_v1<C: V7Methods + ?Sized>(client: &C, operation: &'static str) -> Result<()> {
+    if client.version() != ApiVersion::V1 {
+        bail!(UnsupportedApiVersionError {
+            operation,
+            required: ApiVersion::V1,
+            version: client.version(),
+        });
+    }
+    Ok(())
+}
+
+// Connection settings of the reqwest client, `None` keeps the reqwest default
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+struct ConnectionSettings {
+    timeout: Option<Duration>,
+    connect_timeout: Option<Duration>,
+    pool_max_idle_per_host: Option<usize>,
+    pool_idle_timeout: Option<Duration>,
+    tcp_keepalive: Option<Duration>,
+    http2_prior_knowledge: bool,
+    no_compression: bool,
+}
 
 #[derive(Debug, Default, Clone)]
 struct RawClient {
     client: reqwest::Client,
+    // Overrides the timeout of the underlying client for each request
+    request_timeout: Option<Duration>,
+    debug: Option<DebugDump>,
 }
 
 impl RawClient {
-    pub fn new() -> Result<Self> {
+    fn new(settings: &ConnectionSettings, user_agent: &str) -> Result<Self> {
         // The client currently only accepts application/json
         // The darwin-v7 documentation states that json is one
         // of the accepted content though json is the only
@@ -23,13 +174,66 @@ impl RawClient {
         let mut headers = HeaderMap::new();
         headers.insert(ACCEPT, HeaderValue::from_static(content_type));
         headers.insert(CONTENT_TYPE, HeaderValue::from_static(content_type));
+        headers.insert(
+            USER_AGENT,
+            HeaderValue::from_str(user_agent).context("Invalid user agent")?,
+        );
+        if !settings.no_compression {
+            headers.insert(
+                ACCEPT_ENCODING,
+                HeaderValue::from_static(ACCEPTED_ENCODINGS),
+            );
+        }
 
         // Build a reqwest client for use by the V1 and V2 darwin clients
-        let client: reqwest::Client = reqwest::Client::builder()
-            .default_headers(headers)
-            .build()?;
+        let mut builder = reqwest::Client::builder().default_headers(headers);
+        if let Some(timeout) = settings.timeout {
+            builder = builder.timeout(timeout);
+        }
+        if let Some(connect_timeout) = settings.connect_timeout {
+            builder = builder.connect_timeout(connect_timeout);
+        }
+        if let Some(max_idle) = settings.pool_max_idle_per_host {
+            builder = builder.pool_max_idle_per_host(max_idle);
+        }
+        if let Some(idle_timeout) = settings.pool_idle_timeout {
+            builder = builder.pool_idle_timeout(idle_timeout);
+        }
+        if let Some(keepalive) = settings.tcp_keepalive {
+            builder = builder.tcp_keepalive(keepalive);
+        }
+        if settings.http2_prior_knowledge {
+            builder = builder.http2_prior_knowledge();
+        }
+        let client: reqwest::Client = builder.build()?;
+
+        Ok(Self {
+            client,
+            request_timeout: None,
+            debug: None,
+        })
+    }
+
+    fn with_timeout(&self, request: reqwest::RequestBuilder) -> reqwest::RequestBuilder {
+        match self.request_timeout {
+            Some(timeout) => request.timeout(timeout),
+            None => request,
+        }
+    }
 
-        Ok(Self { client })
+    async fn send(
+        &self,
+        request: reqwest::RequestBuilder,
+        api_key: &str,
+    ) -> Result<reqwest::Response, reqwest::Error> {
+        let Some(dump) = self.debug.as_ref() else {
+            return request.send().await.map(decode_response);
+        };
+        let request = request.build()?;
+        let method = request.method().to_string();
+        dump.request(&request, api_key);
+        let response = decode_response(self.client.execute(request).await?);
+        dump.response(&method, response, api_key).await
     }
 
     pub async fn get(
@@ -38,13 +242,12 @@ impl RawClient {
         api_key: &str,
     ) -> Result<reqwest::Response, reqwest::Error> {
         // Construct endpoint
-        let api_key = format!("ApiKey {}", api_key);
+        let auth = format!("ApiKey {}", api_key);
 
-        self.client
-            .get(address)
-            .header(AUTHORIZATION, api_key)
-            .send()
-            .await
+        let request = self
+            .with_timeout(self.client.get(address))
+            .header(AUTHORIZATION, auth);
+        self.send(request, api_key).await
     }
 
     pub async fn post<S: serde::Serialize + ?Sized>(
@@ -53,14 +256,13 @@ impl RawClient {
         api_key: &str,
         data: &S,
     ) -> Result<reqwest::Response, reqwest::Error> {
-        let api_key = format!("ApiKey {}", api_key);
+        let auth = format!("ApiKey {}", api_key);
 
-        self.client
-            .post(address)
-            .header(AUTHORIZATION, api_key)
-            .json(data)
-            .send()
-            .await
+        let request = self
+            .with_timeout(self.client.post(address))
+            .header(AUTHORIZATION, auth)
+            .json(data);
+        self.send(request, api_key).await
     }
 
     pub async fn delete<S: serde::Serialize + ?Sized>(
@@ -69,14 +271,16 @@ impl RawClient {
         api_key: &str,
         data: Option<&S>,
     ) -> Result<reqwest::Response, reqwest::Error> {
-        let api_key = format!("ApiKey {}", api_key);
+        let auth = format!("ApiKey {}", api_key);
 
-        let req = self.client.delete(address).header(AUTHORIZATION, api_key);
+        let req = self
+            .with_timeout(self.client.delete(address))
+            .header(AUTHORIZATION, auth);
 
         if let Some(payload) = data {
-            req.json(payload).send().await
+            self.send(req.json(payload), api_key).await
         } else {
-            req.send().await
+            self.send(req, api_key).await
         }
     }
 
@@ -86,13 +290,83 @@ impl RawClient {
         api_key: &str,
         data: Option<&S>,
     ) -> Result<reqwest::Response, reqwest::Error> {
-        let api_key = format!("ApiKey {}", api_key);
-        let request = self.client.put(address).header(AUTHORIZATION, api_key);
+        let auth = format!("ApiKey {}", api_key);
+        let request = self
+            .with_timeout(self.client.put(address))
+            .header(AUTHORIZATION, auth);
 
         if let Some(payload) = data {
-            request.json(payload).send().await
+            self.send(request.json(payload), api_key).await
         } else {
-            request.send().await
+            self.send(request, api_key).await
+        }
+    }
+}
+
+/// Cache of the team memberships and annotation classes, which change rarely but are
+/// listed by many helpers e.g. to resolve emails and class names, shared by a client and
+/// its clones. Entries are refetched once older than the TTL, and dropped when the
+/// memberships or classes are changed through this crate. Changes made elsewhere are only
+/// seen once the entry expires or after `invalidate`.
+#[derive(Debug)]
+pub struct ResourceCache {
+    ttl: Duration,
+    memberships: Mutex<Option<(Instant, Vec<TeamMember>)>>,
+    // Keyed by team slug
+    classes: Mutex<HashMap<String, (Instant, TeamAnnotationClasses)>>,
+}
+
+impl ResourceCache {
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            memberships: Mutex::new(None),
+            classes: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn ttl(&self) -> Duration {
+        self.ttl
+    }
+
+    pub fn invalidate(&self) {
+        self.invalidate_memberships();
+        self.invalidate_classes();
+    }
+
+    pub fn invalidate_memberships(&self) {
+        if let Ok(mut memberships) = self.memberships.lock() {
+            *memberships = None;
+        }
+    }
+
+    pub fn invalidate_classes(&self) {
+        if let Ok(mut classes) = self.classes.lock() {
+            classes.clear();
+        }
+    }
+
+    pub(crate) fn memberships(&self) -> Option<Vec<TeamMember>> {
+        let memberships = self.memberships.lock().ok()?;
+        let (cached_at, memberships) = memberships.as_ref()?;
+        (cached_at.elapsed() < self.ttl).then(|| memberships.clone())
+    }
+
+    pub(crate) fn set_memberships(&self, memberships: &[TeamMember]) {
+        if let Ok(mut cached) = self.memberships.lock() {
+            *cached = Some((Instant::now(), memberships.to_vec()));
+        }
+    }
+
+    pub(crate) fn classes(&self, team_slug: &str) -> Option<TeamAnnotationClasses> {
+        let classes = self.classes.lock().ok()?;
+        let (cached_at, classes) = classes.get(team_slug)?;
+        (cached_at.elapsed() < self.ttl).then(|| classes.clone())
+    }
+
+    pub(crate) fn set_classes(&self, team_slug: &str, classes: &TeamAnnotationClasses) {
+        if let Ok(mut cached) = self.classes.lock() {
+            cached.insert(team_slug.to_string(), (Instant::now(), classes.clone()));
         }
     }
 }
@@ -102,41 +376,223 @@ pub struct V7Client {
     api_endpoint: String,
     api_key: String,
     team: String,
+    api_version: ApiVersion,
     client: RawClient,
+    last_response_meta: Arc<Mutex<Option<ResponseMeta>>>,
+    cache: Option<Arc<ResourceCache>>,
+}
+
+/// User agent sent by default, identifying the version of this crate
+pub const DEFAULT_USER_AGENT: &str =
+    concat!(env!("CARGO_PKG_NAME"), "/", env!("CARGO_PKG_VERSION"));
+
+/// Builder for a `V7Client` with non-default connection settings.
+///
+/// Each `build` creates a new connection pool, clones of the built client (including
+/// `V7Client::with_timeout`) share it, so build once and clone the client for bulk jobs.
+#[derive(Debug, Default, Clone)]
+pub struct V7ClientBuilder {
+    api_endpoint: String,
+    api_key: String,
+    team: String,
+    connection: ConnectionSettings,
+    user_agent: Option<String>,
+    api_version: ApiVersion,
+    debug: Option<DebugDump>,
+    cache_ttl: Option<Duration>,
 }
 
+impl V7ClientBuilder {
+    pub fn new(api_endpoint: String, api_key: String, team: String) -> Self {
+        Self {
+            api_endpoint,
+            api_key,
+            team,
+            ..Default::default()
+        }
+    }
+
+    /// Total time allowed for each request, from connecting until the response body
+    /// has been read. Requests have no timeout by default.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.connection.timeout = Some(timeout);
+        self
+    }
+
+    /// Time allowed to establish the connection of each request
+    pub fn connect_timeout(mut self, timeout: Duration) -> Self {
+        self.connection.connect_timeout = Some(timeout);
+        self
+    }
+
+    /// Maximum number of idle connections kept open to V7, raise it for jobs issuing
+    /// many concurrent requests so connections are reused rather than re-established
+    pub fn pool_max_idle_per_host(mut self, max_idle: usize) -> Self {
+        self.connection.pool_max_idle_per_host = Some(max_idle);
+        self
+    }
+
+    /// Time an idle connection is kept open for reuse, 90 seconds by default
+    pub fn pool_idle_timeout(mut self, timeout: Duration) -> Self {
+        self.connection.pool_idle_timeout = Some(timeout);
+        self
+    }
+
+    /// Interval of the TCP keep-alive probes, keeping idle connections from being
+    /// dropped by proxies and load balancers between requests
+    pub fn tcp_keepalive(mut self, interval: Duration) -> Self {
+        self.connection.tcp_keepalive = Some(interval);
+        self
+    }
+
+    /// Only use HTTP/2, multiplexing all requests over a single connection rather than
+    /// negotiating the protocol. Requests fail if the server does not support HTTP/2.
+    pub fn http2_prior_knowledge(mut self) -> Self {
+        self.connection.http2_prior_knowledge = true;
+        self
+    }
+
+    /// Do not ask V7 to compress responses. By default gzip and deflate compressed
+    /// responses are accepted and transparently decoded as they are read.
+    pub fn no_compression(mut self) -> Self {
+        self.connection.no_compression = true;
+        self
+    }
+
+    /// Identifies the calling service in the User-Agent header, e.g. `slide-ingest/1.4.2`.
+    /// The version of this crate is appended so the header reads
+    /// `slide-ingest/1.4.2 darwin-v7/<version>`.
+    pub fn user_agent(mut self, user_agent: &str) -> Self {
+        self.user_agent = Some(user_agent.to_string());
+        self
+    }
+
+    /// The API version of the team, `ApiVersion::V1` by default. Set `ApiVersion::V2` for
+    /// teams on V2 workflows so V1 only methods fail without making a request.
+    pub fn api_version(mut self, version: ApiVersion) -> Self {
+        self.api_version = version;
+        self
+    }
+
+    /// Dumps each request and response to `sink`, e.g. `LogDebugSink`, to debug failing
+    /// calls. The API key is never dumped, see `DebugDumpOptions` for the other rules.
+    pub fn debug_dump(mut self, sink: impl DebugSink + 'static, options: DebugDumpOptions) -> Self {
+        self.debug = Some(DebugDump::new(Arc::new(sink), options));
+        self
+    }
+
+    /// Caches the team memberships and annotation classes for `ttl`, see `ResourceCache`.
+    /// Nothing is cached by default.
+    pub fn cache_ttl(mut self, ttl: Duration) -> Self {
+        self.cache_ttl = Some(ttl);
+        self
+    }
+
+    pub fn build(self) -> Result<V7Client> {
+        let user_agent = match self.user_agent {
+            Some(user_agent) => format!("{user_agent} {DEFAULT_USER_AGENT}"),
+            None => DEFAULT_USER_AGENT.to_string(),
+        };
+        let mut client = RawClient::new(&self.connection, &user_agent)?;
+        client.debug = self.debug;
+
+        Ok(V7Client {
+            api_endpoint: self.api_endpoint,
+            api_key: self.api_key,
+            team: self.team,
+            api_version: self.api_version,
+            client,
+            last_response_meta: Arc::new(Mutex::new(None)),
+            cache: self.cache_ttl.map(|ttl| Arc::new(ResourceCache::new(ttl))),
+        })
+    }
+}
+
+/// HTTP methods used by the API calls of this crate.
+///
+/// All of the async methods of this crate are cancellation safe in that dropping the
+/// future (e.g. when racing it against a timer) aborts the in-flight request and leaves
+/// the client usable. A request that was already received by V7 may still be applied
+/// server side, so cancelled mutations should be verified before being retried.
 #[async_trait]
 pub trait V7Methods {
-    async fn get(&self, endpoint: &str) -> Result<reqwest::Response, reqwest::Error>;
+    async fn get(&self, endpoint: &str) -> Result<HttpResponse, HttpError>;
     async fn put<S: serde::Serialize + ?Sized + std::marker::Sync>(
         &self,
         endpoint: &str,
         data: Option<&S>,
-    ) -> Result<reqwest::Response, reqwest::Error>;
+    ) -> Result<HttpResponse, HttpError>;
     async fn post<S: serde::Serialize + ?Sized + std::marker::Sync>(
         &self,
         endpoint: &str,
         data: &S,
-    ) -> Result<reqwest::Response, reqwest::Error>;
+    ) -> Result<HttpResponse, HttpError>;
     async fn delete<S: serde::Serialize + ?Sized + std::marker::Sync>(
         &self,
         endpoint: &str,
         data: Option<&S>,
-    ) -> Result<reqwest::Response, reqwest::Error>;
+    ) -> Result<HttpResponse, HttpError>;
     fn team(&self) -> &String;
     fn api_endpoint(&self) -> &str;
+    /// The API version of the team, used to reject V1 only methods for V2 teams
+    fn version(&self) -> ApiVersion {
+        ApiVersion::V1
+    }
+    /// The cache of slow-changing team resources, if the client has one
+    fn cache(&self) -> Option<&ResourceCache> {
+        None
+    }
 }
 
 impl V7Client {
     pub fn new(api_endpoint: String, api_key: String, team: String) -> Result<Self> {
-        let client = RawClient::new()?;
+        V7ClientBuilder::new(api_endpoint, api_key, team).build()
+    }
 
-        Ok(V7Client {
-            api_endpoint,
-            api_key,
-            team,
-            client,
-        })
+    pub fn builder(api_endpoint: String, api_key: String, team: String) -> V7ClientBuilder {
+        V7ClientBuilder::new(api_endpoint, api_key, team)
+    }
+
+    /// Returns a client sharing this client's connections with `timeout` applied to each
+    /// of its requests in place of the timeout configured when building the client, e.g.
+    /// `client.with_timeout(Duration::from_secs(600))` for a large export download.
+    pub fn with_timeout(&self, timeout: Duration) -> Self {
+        let mut client = self.clone();
+        client.client.request_timeout = Some(timeout);
+        client
+    }
+
+    /// Metadata of the most recent response received by this client or any of its clones.
+    /// When the client is shared between concurrent tasks this may belong to another task's request.
+    pub fn last_response_meta(&self) -> Option<ResponseMeta> {
+        self.last_response_meta
+            .lock()
+            .map(|meta| meta.clone())
+            .unwrap_or_default()
+    }
+
+    #[cfg(feature = "tower")]
+    /// The cache shared by this client and its clones, for wrappers of the client
+    pub(crate) fn shared_cache(&self) -> Option<Arc<ResourceCache>> {
+        self.cache.clone()
+    }
+
+    /// Drops everything cached by this client and its clones, see `ResourceCache`
+    pub fn invalidate_cache(&self) {
+        if let Some(cache) = self.cache.as_ref() {
+            cache.invalidate();
+        }
+    }
+
+    fn record_response(
+        &self,
+        response: Result<reqwest::Response, reqwest::Error>,
+    ) -> Result<HttpResponse, HttpError> {
+        let response = HttpResponse::from_reqwest(response.map_err(HttpError::from_reqwest)?);
+        if let Ok(mut meta) = self.last_response_meta.lock() {
+            *meta = Some(ResponseMeta::from_response(&response));
+        }
+        Ok(response)
     }
 
     pub fn from_config(config: &Config, team: Option<&String>) -> Result<Self> {
@@ -178,40 +634,95 @@ impl V7Methods for V7Client {
         &self.team
     }
 
-    async fn get(&self, endpoint: &str) -> Result<reqwest::Response, reqwest::Error> {
+    fn version(&self) -> ApiVersion {
+        self.api_version
+    }
+
+    fn cache(&self) -> Option<&ResourceCache> {
+        self.cache.as_deref()
+    }
+
+    async fn get(&self, endpoint: &str) -> Result<HttpResponse, HttpError> {
         let endpoint = format!("{}{}", self.api_endpoint, endpoint);
         debug!("V7Client::get({endpoint})");
-        self.client.get(&endpoint, &self.api_key).await
+        self.record_response(self.client.get(&endpoint, &self.api_key).await)
     }
 
     async fn put<S: serde::Serialize + ?Sized + std::marker::Sync>(
         &self,
         endpoint: &str,
         data: Option<&S>,
-    ) -> Result<reqwest::Response, reqwest::Error> {
+    ) -> Result<HttpResponse, HttpError> {
         let endpoint = format!("{}{}", self.api_endpoint, endpoint);
         debug!("V7Client::put({endpoint})");
-        self.client.put(&endpoint, &self.api_key, data).await
+        self.record_response(self.client.put(&endpoint, &self.api_key, data).await)
     }
 
     async fn delete<S: serde::Serialize + ?Sized + std::marker::Sync>(
         &self,
         endpoint: &str,
         data: Option<&S>,
-    ) -> Result<reqwest::Response, reqwest::Error> {
+    ) -> Result<HttpResponse, HttpError> {
         let endpoint = format!("{}{}", self.api_endpoint, endpoint);
         debug!("V7Client::delete({endpoint})");
-        self.client.delete(&endpoint, &self.api_key, data).await
+        self.record_response(self.client.delete(&endpoint, &self.api_key, data).await)
     }
 
     async fn post<S: serde::Serialize + ?Sized + std::marker::Sync>(
         &self,
         endpoint: &str,
         data: &S,
-    ) -> Result<reqwest::Response, reqwest::Error> {
+    ) -> Result<HttpResponse, HttpError> {
         let endpoint = format!("{}{}", self.api_endpoint, endpoint);
         debug!("V7Client::post({endpoint})");
-        self.client.post(&endpoint, &self.api_key, data).await
+        self.record_response(self.client.post(&endpoint, &self.api_key, data).await)
+    }
+}
+
+/// Requests to the pre-signed urls V7 hands out outside of its API, such as export archives,
+/// class images and instruction attachment uploads. These must not be sent the API key so
+/// are made with a separate client rather than through `V7Methods`. Implemented for
+/// `reqwest::Client`, implement it to make these requests with another HTTP client or to
+/// mock them in tests.
+#[async_trait]
+pub trait PresignedUrlMethods: Send + Sync {
+    /// Gets `url`, sending `headers` with the request
+    async fn get_url(&self, url: &str, headers: HeaderMap) -> Result<HttpResponse, HttpError>;
+    /// Puts `body` to `url`, sending `headers` with the request
+    async fn put_url(
+        &self,
+        url: &str,
+        headers: HeaderMap,
+        body: Vec<u8>,
+    ) -> Result<HttpResponse, HttpError>;
+}
+
+#[async_trait]
+impl PresignedUrlMethods for reqwest::Client {
+    async fn get_url(&self, url: &str, headers: HeaderMap) -> Result<HttpResponse, HttpError> {
+        debug!("PresignedUrlMethods::get_url({url})");
+        self.get(url)
+            .headers(headers)
+            .send()
+            .await
+            .map(HttpResponse::from_reqwest)
+            .map_err(HttpError::from_reqwest)
+    }
+
+    async fn put_url(
+        &self,
+        url: &str,
+        headers: HeaderMap,
+        body: Vec<u8>,
+    ) -> Result<HttpResponse, HttpError> {
+        debug!("PresignedUrlMethods::put_url({url})");
+        self.put(url)
+            .headers(headers)
+            .body(body)
+            .send()
+            .await
+            .map(HttpResponse::from_reqwest)
+            .map_err(HttpError::from_reqwest)
     }
 }
 
@@ -301,6 +812,149 @@ mod tests {
         assert_eq!(client.get("status").await.unwrap().status(), 200);
     }
 
+    #[tokio::test]
+    async fn test_user_agent() {
+        let mock_server = MockServer::start().await;
+
+        Mock::given(method("GET"))
+            .and(path("/status"))
+            .and(header("user-agent", DEFAULT_USER_AGENT))
+            .respond_with(ResponseTemplate::new(200))
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/status"))
+            .and(header(
+                "user-agent",
+                format!("slide-ingest/1.4.2 {DEFAULT_USER_AGENT}").as_str(),
+            ))
+            .respond_with(ResponseTemplate::new(202))
+            .mount(&mock_server)
+            .await;
+
+        let endpoint = format!("{}/", mock_server.uri());
+        let client = V7Client::new(endpoint.clone(), "api-key".to_string(), String::new()).unwrap();
+        assert_eq!(client.get("status").await.unwrap().status(), 200);
+
+        let client = V7Client::builder(endpoint, "api-key".to_string(), String::new())
+            .user_agent("slide-ingest/1.4.2")
+            .build()
+            .unwrap();
+        assert_eq!(client.get("status").await.unwrap().status(), 202);
+    }
+
+    #[tokio::test]
+    async fn test_connection_pool_settings() {
+        let mock_server = MockServer::start().await;
+
+        Mock::given(method("GET"))
+            .and(path("/status"))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(2)
+            .mount(&mock_server)
+            .await;
+
+        let builder = V7Client::builder(
+            format!("{}/", mock_server.uri()),
+            "api-key".to_string(),
+            String::new(),
+        )
+        .pool_max_idle_per_host(32)
+        .pool_idle_timeout(Duration::from_secs(30))
+        .tcp_keepalive(Duration::from_secs(15));
+        assert_eq!(
+            builder.connection,
+            ConnectionSettings {
+                pool_max_idle_per_host: Some(32),
+                pool_idle_timeout: Some(Duration::from_secs(30)),
+                tcp_keepalive: Some(Duration::from_secs(15)),
+                ..Default::default()
+            }
+        );
+        assert!(
+            builder
+                .clone()
+                .http2_prior_knowledge()
+                .connection
+                .http2_prior_knowledge
+        );
+
+        let client = builder.build().unwrap();
+
+        assert_eq!(client.get("status").await.unwrap().status(), 200);
+        let clone = client.with_timeout(Duration::from_secs(5));
+        assert_eq!(clone.get("status").await.unwrap().status(), 200);
+    }
+
+    #[tokio::test]
+    async fn test_request_timeout() {
+        let mock_server = MockServer::start().await;
+
+        Mock::given(method("GET"))
+            .and(path("/slow"))
+            .respond_with(ResponseTemplate::new(200).set_delay(Duration::from_millis(500)))
+            .mount(&mock_server)
+            .await;
+
+        let client = V7Client::builder(
+            format!("{}/", mock_server.uri()),
+            "api-key".to_string(),
+            String::new(),
+        )
+        .timeout(Duration::from_millis(50))
+        .build()
+        .unwrap();
+
+        let error = client.get("slow").await.unwrap_err();
+        assert!(error.is_timeout());
+
+        let response = client
+            .with_timeout(Duration::from_secs(5))
+            .get("slow")
+            .await
+            .unwrap();
+        assert_eq!(response.status(), 200);
+    }
+
+    #[tokio::test]
+    async fn test_response_meta() {
+        let mock_server = MockServer::start().await;
+
+        Mock::given(method("GET"))
+            .and(path("/status"))
+            .respond_with(
+                ResponseTemplate::new(429)
+                    .insert_header("x-request-id", "request-1234")
+                    .insert_header("x-ratelimit-remaining", "0")
+                    .insert_header("retry-after", "30")
+                    .set_body_string("Too many requests"),
+            )
+            .mount(&mock_server)
+            .await;
+
+        let client = V7Client::new(
+            format!("{}/", mock_server.uri()),
+            "api-key".to_string(),
+            String::new(),
+        )
+        .unwrap();
+        assert_eq!(client.last_response_meta(), None);
+
+        let response = client.get("status").await.unwrap();
+        let meta = client.last_response_meta().unwrap();
+        assert_eq!(meta.status, 429);
+        assert_eq!(meta.request_id, Some("request-1234".to_string()));
+        assert_eq!(meta.rate_limit_remaining, Some(0));
+        assert_eq!(meta.retry_after, Some(30));
+
+        let error = HttpStatusError::from_response(response).await;
+        assert_eq!(error.meta, meta);
+        assert_eq!(
+            error.to_string(),
+            "Invalid status code 429 Too many requests (request id request-1234)"
+        );
+    }
+
     #[tokio::test]
     async fn test_raw_client_post() {
         // Setup the mock endpoint
@@ -399,4 +1053,69 @@ mod tests {
         let response = client.put::<String>("testput", None).await.unwrap();
         assert_eq!(response.status(), 200);
     }
+
+    #[tokio::test]
+    async fn test_debug_dump() {
+        use crate::debug::{DebugRecord, REDACTED};
+
+        let mock_server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/imports"))
+            .respond_with(
+                ResponseTemplate::new(400)
+                    .set_body_json(serde_json::json!({"errors": {"api_key": "api-key-1234"}})),
+            )
+            .mount(&mock_server)
+            .await;
+
+        let records = Arc::new(Mutex::new(vec![]));
+        let sink_records = records.clone();
+        let client = V7Client::builder(
+            format!("{}/", mock_server.uri()),
+            "api-key-1234".to_string(),
+            String::new(),
+        )
+        .debug_dump(
+            move |record: &DebugRecord| sink_records.lock().unwrap().push(record.clone()),
+            DebugDumpOptions {
+                max_body_len: Some(40),
+                ..Default::default()
+            },
+        )
+        .build()
+        .unwrap();
+
+        let payload = serde_json::json!({"name": "a".repeat(100), "note": "key api-key-1234"});
+        let response = client.post("imports", &payload).await.unwrap();
+        assert_eq!(response.status(), 400);
+        // The dumped response can still be read
+        let body: serde_json::Value = response.json().await.unwrap();
+        assert_eq!(body["errors"]["api_key"], "api-key-1234");
+
+        let records = records.lock().unwrap();
+        let DebugRecord::Request {
+            method,
+            headers,
+            body,
+            ..
+        } = &records[0]
+        else {
+            panic!("Expected the request first, got {:?}", records[0]);
+        };
+        assert_eq!(method, "POST");
+        assert!(headers.contains(&("authorization".to_string(), REDACTED.to_string())));
+        let body = body.as_ref().unwrap();
+        assert!(body.ends_with("(95 bytes truncated)"), "{body}");
+        let DebugRecord::Response { status, body, .. } = &records[1] else {
+            panic!("Expected the response second, got {:?}", records[1]);
+        };
+        assert_eq!(*status, 400);
+        assert_eq!(
+            body.as_deref(),
+            Some(r#"{"errors":{"api_key":"[REDACTED]"}}"#)
+        );
+        assert!(records
+            .iter()
+            .all(|record| !record.to_string().contains("api-key-1234")));
+    }
 }
@@ -1,26 +1,41 @@
 use crate::classes::BoundingBox;
+#[cfg(feature = "client")]
 use crate::client::V7Methods;
+#[cfg(feature = "client")]
+use crate::datasets::{Dataset, DatasetDescribeMethods};
+#[cfg(feature = "client")]
 use crate::expect_http_ok;
+#[cfg(feature = "client")]
 use crate::item::DatasetItemV2;
+#[cfg(feature = "client")]
+use crate::team::{Team, TeamDescribeMethods};
+#[cfg(feature = "client")]
 use anyhow::{bail, Context, Result};
+#[cfg(feature = "client")]
 use async_trait::async_trait;
+#[cfg(feature = "fake")]
 #[allow(unused_imports)]
 use fake::{Dummy, Fake};
 use serde::{Deserialize, Serialize};
 use std::cmp::PartialEq;
+#[cfg(feature = "client")]
+use std::collections::HashMap;
 
-#[derive(Debug, Default, Clone, Serialize, Deserialize, Dummy, PartialEq, Eq)]
+#[derive(Debug, Default, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[cfg_attr(feature = "fake", derive(Dummy))]
 pub struct CommentBody {
     pub body: String,
 }
 
-#[derive(Debug, Default, Clone, Serialize, Deserialize, Dummy, PartialEq)]
+#[derive(Debug, Default, Clone, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(feature = "fake", derive(Dummy))]
 pub struct CommentThread {
     pub bounding_box: BoundingBox,
     pub comments: Vec<CommentBody>,
     pub slot_name: String,
 }
 
+#[cfg(feature = "client")]
 #[async_trait]
 pub trait CommentMethods<C>
 where
@@ -32,9 +47,32 @@ where
         team_slug: String,
         data: CommentThread,
     ) -> Result<CommentThreadResponse>;
+    async fn list_comment_threads(
+        &self,
+        client: &C,
+        team_slug: &str,
+    ) -> Result<Vec<CommentThreadResponse>>;
+    async fn list_comments(
+        &self,
+        client: &C,
+        team_slug: &str,
+        thread_id: &str,
+    ) -> Result<Vec<CommentLine>>;
 }
 
-#[derive(Debug, Default, Clone, Serialize, Deserialize, Dummy, PartialEq)]
+#[cfg(feature = "client")]
+#[async_trait]
+pub trait DatasetCommentMethods<C>
+where
+    C: V7Methods,
+{
+    /// Collects every comment of every comment thread in the dataset, with the comment
+    /// authors resolved to their email addresses using the team memberships.
+    async fn export_comment_threads(&self, client: &C) -> Result<CommentAuditReport>;
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(feature = "fake", derive(Dummy))]
 pub struct CommentLine {
     pub author_id: Option<u32>,
     pub body: Option<String>,
@@ -45,7 +83,8 @@ pub struct CommentLine {
     pub updated_at: Option<String>,
 }
 
-#[derive(Debug, Default, Clone, Serialize, Deserialize, Dummy, PartialEq)]
+#[derive(Debug, Default, Clone, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(feature = "fake", derive(Dummy))]
 pub struct CommentThreadResponse {
     pub author_id: Option<u32>,
     pub bounding_box: Option<BoundingBox>,
@@ -66,6 +105,33 @@ pub struct CommentThreadResponse {
     pub updated_at: Option<String>,
 }
 
+/// A single comment of a comment thread, flattened for audit reporting
+#[derive(Debug, Default, Clone, Serialize, Deserialize, PartialEq)]
+pub struct CommentAuditEntry {
+    pub item_id: Option<String>,
+    pub item_name: Option<String>,
+    pub item_path: Option<String>,
+    pub thread_id: Option<String>,
+    pub thread_resolved: Option<bool>,
+    pub slot_name: Option<String>,
+    pub comment_id: Option<String>,
+    pub author_id: Option<u32>,
+    pub author_email: Option<String>,
+    pub body: Option<String>,
+    pub created_by_system: Option<bool>,
+    pub inserted_at: Option<String>,
+    pub updated_at: Option<String>,
+}
+
+/// All comments made within a dataset
+#[derive(Debug, Default, Clone, Serialize, Deserialize, PartialEq)]
+pub struct CommentAuditReport {
+    pub dataset_id: Option<u32>,
+    pub dataset_slug: Option<String>,
+    pub comments: Vec<CommentAuditEntry>,
+}
+
+#[cfg(feature = "client")]
 #[async_trait]
 impl<C> CommentMethods<C> for DatasetItemV2
 where
@@ -89,4 +155,179 @@ where
             .await?;
         expect_http_ok!(response, CommentThreadResponse)
     }
+
+    async fn list_comment_threads(
+        &self,
+        client: &C,
+        team_slug: &str,
+    ) -> Result<Vec<CommentThreadResponse>> {
+        let response = client
+            .get(&format!(
+                "v2/teams/{}/items/{}/comment_threads",
+                team_slug,
+                self.id.as_ref().context("Dataset item has no Id")?
+            ))
+            .await?;
+        expect_http_ok!(response, Vec<CommentThreadResponse>)
+    }
+
+    async fn list_comments(
+        &self,
+        client: &C,
+        team_slug: &str,
+        thread_id: &str,
+    ) -> Result<Vec<CommentLine>> {
+        let response = client
+            .get(&format!(
+                "v2/teams/{}/items/{}/comment_threads/{}/comments",
+                team_slug,
+                self.id.as_ref().context("Dataset item has no Id")?,
+                thread_id
+            ))
+            .await?;
+        expect_http_ok!(response, Vec<CommentLine>)
+    }
+}
+
+#[cfg(feature = "client")]
+#[async_trait]
+impl<C> DatasetCommentMethods<C> for Dataset
+where
+    C: V7Methods + std::marker::Sync,
+{
+    async fn export_comment_threads(&self, client: &C) -> Result<CommentAuditReport> {
+        let team_slug = self.team_slug.as_ref().context("Missing team slug")?;
+
+        let emails: HashMap<u32, String> = Team::list_memberships(client)
+            .await?
+            .into_iter()
+            .filter_map(|member| Some((member.user_id?, member.email?)))
+            .collect();
+
+        let mut comments: Vec<CommentAuditEntry> = Vec::new();
+        for item in self.list_all_dataset_items_v2(client).await? {
+            for thread in item.list_comment_threads(client, team_slug).await? {
+                let thread_id = thread.id.as_ref().context("Comment thread has no Id")?;
+
+                for comment in item.list_comments(client, team_slug, thread_id).await? {
+                    comments.push(CommentAuditEntry {
+                        item_id: item.id.clone(),
+                        item_name: item.name.clone(),
+                        item_path: item.path.clone(),
+                        thread_id: thread.id.clone(),
+                        thread_resolved: thread.resolved,
+                        slot_name: thread.slot_name.clone(),
+                        comment_id: comment.id,
+                        author_id: comment.author_id,
+                        author_email: comment
+                            .author_id
+                            .and_then(|author_id| emails.get(&author_id).cloned()),
+                        body: comment.body,
+                        created_by_system: comment.created_by_system,
+                        inserted_at: comment.inserted_at,
+                        updated_at: comment.updated_at,
+                    });
+                }
+            }
+        }
+
+        Ok(CommentAuditReport {
+            dataset_id: self.id,
+            dataset_slug: self.slug.clone(),
+            comments,
+        })
+    }
+}
+
+#[cfg(all(test, feature = "client"))]
+mod test_client_calls {
+    use super::*;
+    use crate::client::V7Client;
+    use crate::item::Item;
+    use crate::team::TeamMember;
+    use serde_json::json;
+    use wiremock::matchers::{method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    #[tokio::test]
+    async fn test_export_comment_threads() {
+        let mock_server = MockServer::start().await;
+        let dataset = Dataset {
+            id: Some(1),
+            slug: Some("some-dataset".to_string()),
+            team_slug: Some("some-team".to_string()),
+            ..Default::default()
+        };
+        let items = Item {
+            items: vec![Some(DatasetItemV2 {
+                id: Some("item-1".to_string()),
+                name: Some("item-1.png".to_string()),
+                ..Default::default()
+            })],
+            ..Default::default()
+        };
+        let members = vec![TeamMember {
+            user_id: Some(7),
+            email: Some("reviewer@mail.com".to_string()),
+            ..Default::default()
+        }];
+        let threads = vec![CommentThreadResponse {
+            id: Some("thread-1".to_string()),
+            resolved: Some(false),
+            slot_name: Some("0".to_string()),
+            ..Default::default()
+        }];
+        let comments = json!([{
+            "author_id": 7,
+            "body": "Missed a region",
+            "comment_thread_id": "thread-1",
+            "created_by_system": false,
+            "id": "comment-1",
+            "inserted_at": "2023-08-01T00:00:00Z",
+            "updated_at": "2023-08-01T00:00:00Z"
+        }]);
+
+        Mock::given(method("GET"))
+            .and(path("/memberships"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(members))
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/v2/teams/some-team/items"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(items))
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/v2/teams/some-team/items/item-1/comment_threads"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(threads))
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path(
+                "/v2/teams/some-team/items/item-1/comment_threads/thread-1/comments",
+            ))
+            .respond_with(ResponseTemplate::new(200).set_body_json(comments))
+            .mount(&mock_server)
+            .await;
+
+        let client: V7Client = V7Client::new(
+            format!("{}/", mock_server.uri()),
+            "api-key".to_string(),
+            "some-team".to_string(),
+        )
+        .expect("Failed to get V7Client");
+
+        let report = dataset
+            .export_comment_threads(&client)
+            .await
+            .expect("Failed to export comment threads");
+
+        assert_eq!(report.dataset_slug, Some("some-dataset".to_string()));
+        assert_eq!(report.comments.len(), 1);
+        let comment = &report.comments[0];
+        assert_eq!(comment.item_name, Some("item-1.png".to_string()));
+        assert_eq!(comment.thread_id, Some("thread-1".to_string()));
+        assert_eq!(comment.author_email, Some("reviewer@mail.com".to_string()));
+        assert_eq!(comment.body, Some("Missed a region".to_string()));
+    }
 }
@@ -0,0 +1,328 @@
+//! Transparent decoding of gzip and deflate compressed responses.
+//!
+//! The body is inflated as it is read rather than buffered, so a compressed item listing
+//! can still be streamed with `stream::json_array`. The CRC32 and size in the trailer of a
+//! gzip body are checked once it is inflated, so a corrupted or truncated body is an error.
+
+use futures::stream::{self, StreamExt};
+use miniz_oxide::inflate::stream::{inflate, InflateState};
+use miniz_oxide::{DataFormat, MZError, MZFlush, MZStatus};
+use reqwest::header::{CONTENT_ENCODING, CONTENT_LENGTH};
+use std::io;
+
+/// Value of the Accept-Encoding header sent by the client
+pub(crate) const ACCEPTED_ENCODINGS: &str = "gzip, deflate";
+
+// Size of the buffer each chunk of the body is inflated into
+const OUTPUT_CHUNK_SIZE: usize = 64 * 1024;
+
+// Size of the gzip trailer, the CRC32 and the size modulo 2^32 of the inflated data
+const GZIP_TRAILER_LEN: usize = 8;
+
+#[derive(Default)]
+struct GzipTrailer {
+    crc: u32,
+    size: u32,
+    // The bytes of the trailer read so far
+    read: Vec<u8>,
+}
+
+struct Inflater {
+    state: Box<InflateState>,
+    // The bytes of a gzip header read so far, `None` once the header has been skipped
+    // or for deflate streams which have no header
+    gzip_header: Option<Vec<u8>>,
+    // `None` for deflate streams, whose zlib checksum is checked by miniz
+    gzip_trailer: Option<GzipTrailer>,
+    stream_ended: bool,
+    finished: bool,
+}
+
+impl Inflater {
+    fn gzip() -> Self {
+        Self {
+            state: InflateState::new_boxed(DataFormat::Raw),
+            gzip_header: Some(vec![]),
+            gzip_trailer: Some(GzipTrailer::default()),
+            stream_ended: false,
+            finished: false,
+        }
+    }
+
+    // The deflate content coding is a zlib stream, see RFC 9110
+    fn deflate() -> Self {
+        Self {
+            state: InflateState::new_boxed(DataFormat::Zlib),
+            gzip_header: None,
+            gzip_trailer: None,
+            stream_ended: false,
+            finished: false,
+        }
+    }
+
+    fn inflate(&mut self, chunk: &[u8]) -> io::Result<Vec<u8>> {
+        let header_rest;
+        let mut input = chunk;
+        if let Some(header) = self.gzip_header.as_mut() {
+            header.extend_from_slice(chunk);
+            let Some(header_len) = gzip_header_len(header)? else {
+                return Ok(vec![]);
+            };
+            header_rest = header.split_off(header_len);
+            input = &header_rest;
+            self.gzip_header = None;
+        }
+
+        let mut output = Vec::new();
+        let mut buffer = vec![0; OUTPUT_CHUNK_SIZE];
+        while !self.stream_ended {
+            let result = inflate(&mut self.state, input, &mut buffer, MZFlush::None);
+            input = &input[result.bytes_consumed..];
+            let written = &buffer[..result.bytes_written];
+            if let Some(trailer) = self.gzip_trailer.as_mut() {
+                trailer.crc = crc32_update(trailer.crc, written);
+                trailer.size = trailer.size.wrapping_add(written.len() as u32);
+            }
+            output.extend_from_slice(written);
+            match result.status {
+                Ok(MZStatus::StreamEnd) => self.stream_ended = true,
+                Ok(_) | Err(MZError::Buf) => {
+                    if input.is_empty() && result.bytes_written < buffer.len() {
+                        break;
+                    }
+                }
+                Err(error) => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!("Invalid compressed response: {error:?}"),
+                    ))
+                }
+            }
+        }
+        if self.stream_ended && !self.finished {
+            self.finished = match self.gzip_trailer.as_mut() {
+                Some(trailer) => trailer.check(input)?,
+                None => true,
+            };
+        }
+        Ok(output)
+    }
+}
+
+impl GzipTrailer {
+    // Reads the trailer from `input`, returning whether all of it has been read. Anything
+    // after the trailer, such as another gzip member, is ignored
+    fn check(&mut self, input: &[u8]) -> io::Result<bool> {
+        let missing = GZIP_TRAILER_LEN.saturating_sub(self.read.len());
+        self.read
+            .extend_from_slice(&input[..missing.min(input.len())]);
+        if self.read.len() < GZIP_TRAILER_LEN {
+            return Ok(false);
+        }
+        let crc = u32::from_le_bytes([self.read[0], self.read[1], self.read[2], self.read[3]]);
+        let size = u32::from_le_bytes([self.read[4], self.read[5], self.read[6], self.read[7]]);
+        if crc != self.crc || size != self.size {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "Compressed response does not match its checksum",
+            ));
+        }
+        Ok(true)
+    }
+}
+
+const CRC32_TABLE: [u32; 256] = crc32_table();
+
+// The table of the reflected CRC32 polynomial used by gzip, see RFC 1952
+const fn crc32_table() -> [u32; 256] {
+    let mut table = [0; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 != 0 {
+                0xedb8_8320 ^ (crc >> 1)
+            } else {
+                crc >> 1
+            };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+}
+
+// Continues `crc`, the CRC32 of the data before `data`, which starts at 0
+pub(crate) fn crc32_update(crc: u32, data: &[u8]) -> u32 {
+    let mut crc = !crc;
+    for byte in data {
+        crc = CRC32_TABLE[((crc ^ *byte as u32) & 0xff) as usize] ^ (crc >> 8);
+    }
+    !crc
+}
+
+// The length of the gzip header at the start of `data`, `None` until all of it is read
+fn gzip_header_len(data: &[u8]) -> io::Result<Option<usize>> {
+    const FHCRC: u8 = 0x02;
+    const FEXTRA: u8 = 0x04;
+    const FNAME: u8 = 0x08;
+    const FCOMMENT: u8 = 0x10;
+
+    if data.len() < 10 {
+        return Ok(None);
+    }
+    if data[..3] != [0x1f, 0x8b, 0x08] {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "Response is not gzip compressed",
+        ));
+    }
+    let flags = data[3];
+    let mut len = 10;
+    if flags & FEXTRA != 0 {
+        let Some(extra) = data.get(len..len + 2) else {
+            return Ok(None);
+        };
+        len += 2 + u16::from_le_bytes([extra[0], extra[1]]) as usize;
+    }
+    for flag in [FNAME, FCOMMENT] {
+        if flags & flag != 0 {
+            // Zero terminated strings
+            match data
+                .get(len..)
+                .and_then(|rest| rest.iter().position(|b| *b == 0))
+            {
+                Some(end) => len += end + 1,
+                None => return Ok(None),
+            }
+        }
+    }
+    if flags & FHCRC != 0 {
+        len += 2;
+    }
+    Ok((data.len() >= len).then_some(len))
+}
+
+/// Returns the response with its body inflated if it is gzip or deflate compressed,
+/// otherwise the response unchanged
+pub(crate) fn decode_response(response: reqwest::Response) -> reqwest::Response {
+    let inflater = match response
+        .headers()
+        .get(CONTENT_ENCODING)
+        .and_then(|value| value.to_str().ok())
+        .map(|value| value.trim().to_ascii_lowercase())
+        .as_deref()
+    {
+        Some("gzip") | Some("x-gzip") => Inflater::gzip(),
+        Some("deflate") => Inflater::deflate(),
+        _ => return response,
+    };
+
+    let status = response.status();
+    let mut headers = response.headers().clone();
+    headers.remove(CONTENT_ENCODING);
+    headers.remove(CONTENT_LENGTH);
+
+    let chunks = response.bytes_stream().boxed();
+    let body = stream::unfold(
+        (chunks, inflater),
+        |(mut chunks, mut inflater)| async move {
+            let inflated = match chunks.next().await {
+                Some(Ok(chunk)) => inflater.inflate(&chunk),
+                Some(Err(error)) => Err(io::Error::other(error)),
+                None if inflater.finished => return None,
+                None => Err(io::Error::new(
+                    io::ErrorKind::UnexpectedEof,
+                    "Compressed response ended early",
+                )),
+            };
+            // Stop after an error rather than reading the rest of the body
+            if inflated.is_err() {
+                inflater.finished = true;
+                chunks = stream::empty().boxed();
+            }
+            Some((inflated, (chunks, inflater)))
+        },
+    );
+
+    let mut decoded = http::Response::new(reqwest::Body::wrap_stream(body));
+    *decoded.status_mut() = status;
+    *decoded.headers_mut() = headers;
+    reqwest::Response::from(decoded)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use miniz_oxide::deflate::{compress_to_vec, compress_to_vec_zlib};
+
+    fn gzip(data: &[u8]) -> Vec<u8> {
+        // Header with a file name
+        let mut compressed = vec![0x1f, 0x8b, 0x08, 0x08, 0, 0, 0, 0, 0, 0xff];
+        compressed.extend_from_slice(b"items.json\0");
+        compressed.extend(compress_to_vec(data, 6));
+        compressed.extend(crc32_update(0, data).to_le_bytes());
+        compressed.extend((data.len() as u32).to_le_bytes());
+        compressed
+    }
+
+    #[test]
+    fn test_crc32() {
+        assert_eq!(crc32_update(0, b"123456789"), 0xcbf4_3926);
+        assert_eq!(
+            crc32_update(crc32_update(0, b"1234"), b"56789"),
+            0xcbf4_3926
+        );
+    }
+
+    #[test]
+    fn test_gzip_trailer() {
+        let data = b"[{\"name\": \"item\"}]";
+        let compressed = gzip(data);
+
+        // The body has ended before the trailer was read
+        let mut inflater = Inflater::gzip();
+        let inflated = inflater
+            .inflate(&compressed[..compressed.len() - 3])
+            .unwrap();
+        assert_eq!(inflated, data);
+        assert!(!inflater.finished);
+        assert!(inflater
+            .inflate(&compressed[compressed.len() - 3..])
+            .is_ok());
+        assert!(inflater.finished);
+
+        let mut corrupted = compressed.clone();
+        let crc = corrupted.len() - GZIP_TRAILER_LEN;
+        corrupted[crc] ^= 0xff;
+        let error = Inflater::gzip().inflate(&corrupted).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Compressed response does not match its checksum"
+        );
+    }
+
+    #[test]
+    fn test_inflate_in_chunks() {
+        let data = "[{\"name\": \"item\"}]".repeat(10_000);
+
+        for (mut inflater, compressed) in [
+            (Inflater::gzip(), gzip(data.as_bytes())),
+            (
+                Inflater::deflate(),
+                compress_to_vec_zlib(data.as_bytes(), 6),
+            ),
+        ] {
+            let mut inflated = Vec::new();
+            for chunk in compressed.chunks(7) {
+                inflated.extend(inflater.inflate(chunk).unwrap());
+            }
+            assert!(inflater.finished);
+            assert_eq!(inflated, data.as_bytes());
+        }
+
+        assert!(Inflater::gzip().inflate(&[0; 16]).is_err());
+    }
+}
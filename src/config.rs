@@ -1,9 +1,13 @@
 use crate::team::Team;
-use anyhow::{Context, Result};
-use serde_yaml;
-use std::io::Read;
+use anyhow::{bail, Context, Result};
+use serde_yaml::{Mapping, Value};
+use std::fmt::Display;
+use std::io::{Read, Write};
 use std::path::Path;
-use std::{collections::HashMap, fs::File};
+use std::{
+    collections::HashMap,
+    fs::{File, OpenOptions},
+};
 
 #[derive(Debug, Clone)]
 pub struct Config {
@@ -54,6 +58,84 @@ impl Config {
 
         Self::try_from(buffer.as_str())
     }
+
+    /// Adds a team or replaces the team with the same slug, returning the replaced team
+    pub fn insert_team(&mut self, team: Team) -> Option<Team> {
+        self.teams.insert(team.slug.to_string(), team)
+    }
+
+    pub fn set_default_team(&mut self, default_team: String) -> Result<()> {
+        if !self.teams.contains_key(&default_team) {
+            bail!("Team {default_team} is not in the config");
+        }
+        self.default_team = default_team;
+        Ok(())
+    }
+
+    /// Writes the config in the darwin-py YAML format. As the file holds API keys
+    /// it is only readable by the owner on unix platforms.
+    pub fn to_file<T>(&self, file_path: T) -> Result<()>
+    where
+        T: AsRef<Path>,
+    {
+        let mut options = OpenOptions::new();
+        options.write(true).create(true).truncate(true);
+        // Created without read access for others so the keys are never exposed, and an
+        // existing file is restricted before the keys are written to it
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::OpenOptionsExt;
+            options.mode(0o600);
+        }
+        let mut file = options.open(&file_path)?;
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            file.set_permissions(std::fs::Permissions::from_mode(0o600))?;
+        }
+        file.write_all(self.to_string().as_bytes())?;
+        Ok(())
+    }
+}
+
+impl Display for Config {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut global = Mapping::new();
+        global.insert("api_endpoint".into(), self.api_endpoint.as_str().into());
+        global.insert("base_url".into(), self.base_url.as_str().into());
+        global.insert("default_team".into(), self.default_team.as_str().into());
+
+        // Sort the teams so the file does not change between writes
+        let mut slugs: Vec<&String> = self.teams.keys().collect();
+        slugs.sort();
+        let mut teams = Mapping::new();
+        for slug in slugs {
+            let team = &self.teams[slug];
+            let mut entry = Mapping::new();
+            if let Some(api_key) = team.api_key.as_ref() {
+                entry.insert("api_key".into(), api_key.as_str().into());
+            }
+            if let Some(datasets_dir) = team.datasets_dir.as_ref() {
+                entry.insert(
+                    "datasets_dir".into(),
+                    datasets_dir.to_string_lossy().as_ref().into(),
+                );
+            }
+            let entry = if entry.is_empty() {
+                Value::Null
+            } else {
+                Value::Mapping(entry)
+            };
+            teams.insert(slug.as_str().into(), entry);
+        }
+
+        let mut config = Mapping::new();
+        config.insert("global".into(), Value::Mapping(global));
+        config.insert("teams".into(), Value::Mapping(teams));
+
+        let config = serde_yaml::to_string(&config).map_err(|_| std::fmt::Error)?;
+        write!(f, "{config}")
+    }
 }
 
 fn get_from_yaml<'b>(value: &'b serde_yaml::Value, key: &'b str) -> anyhow::Result<&'b str> {
@@ -147,6 +229,49 @@ teams:
         );
     }
 
+    #[test]
+    fn test_config_to_string() {
+        let config = Config::try_from(CONFIG_STR).unwrap();
+        assert_eq!(config.to_string(), CONFIG_STR);
+    }
+
+    #[test]
+    fn test_config_to_file() {
+        let mut config = Config::try_from(CONFIG_STR).unwrap();
+        config.insert_team(Team::new(
+            "team-c".to_string(),
+            None,
+            Some("6f1b4b1e-5f0e-4c44-9d7e-1f0b3c2a4d5e".to_string()),
+            None,
+        ));
+        config.set_default_team("team-c".to_string()).unwrap();
+        config
+            .set_default_team("team-kevin".to_string())
+            .expect_err("Team team-kevin is not in the config");
+
+        let file = NamedTempFile::new().unwrap();
+        config.to_file(file.path()).unwrap();
+
+        let config = Config::from_file(file.path()).unwrap();
+        assert_eq!(config.default_team(), "team-c");
+        assert_eq!(config.teams().len(), 3);
+        assert_eq!(
+            config.teams()["team-c"].api_key.as_deref(),
+            Some("6f1b4b1e-5f0e-4c44-9d7e-1f0b3c2a4d5e")
+        );
+        assert_eq!(config.teams()["team-c"].datasets_dir, None);
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let dir = tempfile::tempdir().unwrap();
+            let path = dir.path().join("config.yaml");
+            config.to_file(&path).unwrap();
+            let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+    }
+
     #[test]
     fn test_config_from_file() {
         // Write the file out
@@ -0,0 +1,249 @@
+//! Conversion of the annotations of other tools into V7 annotation imports, e.g. the
+//! predictions of a model written as a COCO instances file.
+
+use crate::{
+    annotation::{BoundingBox, Keypoint},
+    imports::{
+        AnnotationContext, AnnotationImport, AnnotationImportAnnotation, AnnotationImportData,
+        OnExisting,
+    },
+};
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// A COCO instances file as written by most detection and segmentation models
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CocoDataset {
+    pub images: Vec<CocoImage>,
+    #[serde(default)]
+    pub annotations: Vec<CocoAnnotation>,
+    pub categories: Vec<CocoCategory>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CocoImage {
+    pub id: u64,
+    pub file_name: String,
+    #[serde(default)]
+    pub width: Option<u32>,
+    #[serde(default)]
+    pub height: Option<u32>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CocoCategory {
+    pub id: u64,
+    pub name: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CocoAnnotation {
+    pub id: u64,
+    pub image_id: u64,
+    pub category_id: u64,
+    /// `[x, y, width, height]` of the instance in pixels
+    #[serde(default)]
+    pub bbox: Option<[f32; 4]>,
+    #[serde(default)]
+    pub segmentation: Option<CocoSegmentation>,
+    /// 1 for a region of many objects of the category rather than a single instance
+    #[serde(default)]
+    pub iscrowd: u8,
+}
+
+/// Segmentation of a COCO instance, polygons are lists of alternating x and y coordinates
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum CocoSegmentation {
+    Polygons(Vec<Vec<f32>>),
+    /// Run-length encoded mask, used for crowd annotations
+    Rle(serde_json::Value),
+}
+
+/// The V7 dataset item and slot the annotations of a COCO image are imported into
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CocoTarget {
+    pub item_name: String,
+    pub slot_name: String,
+}
+
+fn polygon_paths(polygons: &[Vec<f32>]) -> Result<Vec<Vec<Keypoint>>> {
+    polygons
+        .iter()
+        .map(|coordinates| {
+            if coordinates.len() % 2 != 0 || coordinates.len() < 6 {
+                bail!("Polygon has {} coordinates", coordinates.len());
+            }
+            Ok(coordinates
+                .chunks_exact(2)
+                .map(|point| Keypoint {
+                    x: point[0],
+                    y: point[1],
+                })
+                .collect())
+        })
+        .collect()
+}
+
+fn import_data(annotation: &CocoAnnotation) -> Result<AnnotationImportData> {
+    match &annotation.segmentation {
+        Some(CocoSegmentation::Polygons(polygons)) if !polygons.is_empty() => {
+            Ok(AnnotationImportData::from(polygon_paths(polygons)?))
+        }
+        Some(CocoSegmentation::Rle(_)) => {
+            bail!("Run-length encoded segmentations are not supported")
+        }
+        _ => {
+            let [x, y, w, h] = annotation
+                .bbox
+                .context("Annotation has neither a segmentation nor a bbox")?;
+            let bounding_box = BoundingBox {
+                h: Some(h),
+                w: Some(w),
+                x: Some(x),
+                y: Some(y),
+            };
+            Ok(AnnotationImportData {
+                other: HashMap::from([(
+                    "bounding_box".to_string(),
+                    serde_json::to_value(bounding_box)?,
+                )]),
+                ..Default::default()
+            })
+        }
+    }
+}
+
+/// Converts the instances of a COCO file into annotation import payloads, keyed by the
+/// name of the V7 dataset item they belong to.
+///
+/// Instances with a polygon segmentation are imported as polygons, one path per COCO
+/// polygon, otherwise the `bbox` is imported as a bounding box. Crowd regions (`iscrowd`
+/// of 1) are skipped as V7 has no equivalent of them.
+///
+/// # Arguments
+///
+/// * `coco_json` - The content of the COCO instances file.
+/// * `class_index` - COCO category names mapped to the ID of the V7 annotation class to use.
+/// * `item_name_mapping` - The `file_name` of each COCO image mapped to the item and slot to
+///   import its annotations into.
+///
+/// # Errors
+///
+/// Returns an error if the file cannot be parsed, an instance that is not a crowd region has
+/// a run-length encoded segmentation, or a category or image has no mapping.
+pub fn coco_to_imports(
+    coco_json: &str,
+    class_index: &HashMap<String, u32>,
+    item_name_mapping: &HashMap<String, CocoTarget>,
+) -> Result<HashMap<String, AnnotationImport>> {
+    let coco: CocoDataset =
+        serde_path_to_error::deserialize(&mut serde_json::Deserializer::from_str(coco_json))?;
+
+    let categories: HashMap<u64, &str> = coco
+        .categories
+        .iter()
+        .map(|category| (category.id, category.name.as_str()))
+        .collect();
+    let images: HashMap<u64, &str> = coco
+        .images
+        .iter()
+        .map(|image| (image.id, image.file_name.as_str()))
+        .collect();
+
+    let mut imports: HashMap<String, AnnotationImport> = HashMap::new();
+    for annotation in coco
+        .annotations
+        .iter()
+        .filter(|annotation| annotation.iscrowd == 0)
+    {
+        let file_name = images
+            .get(&annotation.image_id)
+            .with_context(|| format!("No image with id {}", annotation.image_id))?;
+        let target = item_name_mapping
+            .get(*file_name)
+            .with_context(|| format!("No item mapped to image {file_name}"))?;
+        let category = categories
+            .get(&annotation.category_id)
+            .with_context(|| format!("No category with id {}", annotation.category_id))?;
+        let annotation_class_id = *class_index
+            .get(*category)
+            .with_context(|| format!("No annotation class mapped to category {category}"))?;
+        let data = import_data(annotation)
+            .with_context(|| format!("Failed to convert annotation {}", annotation.id))?;
+
+        imports
+            .entry(target.item_name.clone())
+            .or_insert_with(|| AnnotationImport {
+                annotations: vec![],
+                overwrite: false,
+            })
+            .annotations
+            .push(AnnotationImportAnnotation {
+                id: uuid::Uuid::new_v4().to_string(),
+                data,
+                annotation_class_id,
+                context_keys: AnnotationContext {
+                    slot_names: vec![target.slot_name.clone()],
+                },
+                properties: vec![],
+                on_existing: OnExisting::default(),
+            });
+    }
+    Ok(imports)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_coco_to_imports() {
+        let coco = json!({
+            "images": [
+                {"id": 1, "file_name": "a.png", "width": 100, "height": 100},
+                {"id": 2, "file_name": "b.png"}
+            ],
+            "annotations": [
+                {"id": 10, "image_id": 1, "category_id": 7, "bbox": [1, 2, 3, 4],
+                 "segmentation": [[0, 0, 10, 0, 10, 10], [20, 20, 30, 20, 30, 30]], "iscrowd": 0},
+                {"id": 11, "image_id": 2, "category_id": 8, "bbox": [5, 6, 7, 8]},
+                {"id": 12, "image_id": 2, "category_id": 8, "bbox": [0, 0, 50, 50],
+                 "segmentation": {"counts": [0, 2500], "size": [100, 100]}, "iscrowd": 1}
+            ],
+            "categories": [{"id": 7, "name": "tumour"}, {"id": 8, "name": "cell"}]
+        })
+        .to_string();
+        let class_index = HashMap::from([("tumour".to_string(), 1), ("cell".to_string(), 2)]);
+        let target = |item_name: &str| CocoTarget {
+            item_name: item_name.to_string(),
+            slot_name: "0".to_string(),
+        };
+        let mut mapping = HashMap::from([
+            ("a.png".to_string(), target("a")),
+            ("b.png".to_string(), target("b")),
+        ]);
+
+        let imports = coco_to_imports(&coco, &class_index, &mapping).expect("Failed to convert");
+
+        let polygon = &imports["a"].annotations[0];
+        assert_eq!(polygon.annotation_class_id, 1);
+        let paths = polygon.data.polygon.as_ref().unwrap().all_paths();
+        assert_eq!(paths.len(), 2);
+        assert_eq!(paths[1][2], Keypoint { x: 30.0, y: 30.0 });
+
+        assert_eq!(imports["b"].annotations.len(), 1);
+        let bounding_box = &imports["b"].annotations[0];
+        assert_eq!(bounding_box.annotation_class_id, 2);
+        assert!(bounding_box.data.polygon.is_none());
+        assert_eq!(
+            bounding_box.data.other["bounding_box"],
+            json!({"h": 8.0, "w": 7.0, "x": 5.0, "y": 6.0})
+        );
+
+        mapping.remove("b.png");
+        assert!(coco_to_imports(&coco, &class_index, &mapping).is_err());
+    }
+}
@@ -1,40 +1,228 @@
+#[cfg(feature = "fake")]
 #[allow(unused_imports)]
-use fake::Dummy;
+use fake::{Dummy, Fake, Faker};
 
-use crate::annotation::AnnotationClass;
-use crate::client::V7Methods;
-use crate::expect_http_ok;
-use crate::filter::Filter;
-use crate::imports::AnnotationImport;
+use crate::annotation::{AnnotationClass, ItemAnnotation};
+use crate::ids::{ClassId, DatasetId, StageId, WorkflowId, WorkflowTemplateId};
 use crate::item::{
-    AddDataPayload, DataPayloadLevel, DatasetItemStatus, DatasetItemTypes, ExistingSimpleItem, Item,
+    DataPayloadLevel, DatasetItemStatus, DatasetItemTypes, ExistingReadWriteItem,
+    ExistingSimpleItem,
 };
+use crate::known::MaybeKnown;
+use crate::maybe::Maybe;
 use crate::team::TypeCount;
-use crate::workflow::{WorkflowBuilder, WorkflowMethods, WorkflowV2};
-use anyhow::{bail, Context, Result};
-use async_trait::async_trait;
-use csv_async::AsyncReaderBuilder;
-use futures::io::Cursor;
-use futures::StreamExt;
+use crate::utils::null_as_default;
+#[cfg(feature = "timestamps")]
+use crate::utils::Timestamp;
+use anyhow::{bail, Result};
 use serde::{Deserialize, Serialize};
 use std::cmp::PartialEq;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::fmt::Display;
+use std::path::PathBuf;
+
+#[cfg(feature = "client")]
+use crate::{
+    annotation::DeleteAnnotationsPayload,
+    client::{require_v1, HttpStatusError, PresignedUrlMethods, V7Methods},
+    expect_http_ok,
+    filter::Filter,
+    ids::{ItemId, UserId, WorkflowStageTemplateId},
+    imports::{AnnotationImport, AnnotationImportPlan},
+    item::{fetch_page, AddDataPayload, DatasetItemV2, Item},
+    progress::{NoProgress, ProgressEvent, ProgressSink},
+    stream::json_array,
+    team::{Team, TeamDataMethods, TeamDescribeMethods},
+    utils::{encode_query, folder_path},
+    webhooks::{WebhookEventType, WebhookSubscription},
+    workflow::{
+        count_items_by_stage, ItemStageTime, StageCountFilter, StageCounts, WorkflowBuilder,
+        WorkflowMethods, WorkflowTemplate, WorkflowV2,
+    },
+};
+#[cfg(any(feature = "client", feature = "reports"))]
+use anyhow::Context;
+#[cfg(feature = "client")]
+use async_trait::async_trait;
+#[cfg(feature = "client")]
+use futures_timer::Delay;
+#[cfg(feature = "client")]
+use log::{debug, warn};
+#[cfg(feature = "client")]
+use md5::{Digest, Md5};
+#[cfg(feature = "client")]
+use reqwest::header::{HeaderMap, HeaderValue, CONTENT_RANGE, CONTENT_TYPE, ETAG, IF_RANGE, RANGE};
+#[cfg(feature = "client")]
+use std::collections::HashSet;
+#[cfg(feature = "client")]
+use std::io::{Read, Write};
+#[cfg(any(feature = "client", feature = "reports"))]
+use std::path::Path;
+#[cfg(feature = "client")]
+use std::time::{Duration, Instant};
+
+#[cfg(all(feature = "client", feature = "reports"))]
+use crate::item::ItemRow;
+#[cfg(feature = "reports")]
+use csv_async::AsyncReaderBuilder;
+#[cfg(all(feature = "client", feature = "reports"))]
+use futures::io::AsyncWriteExt;
+#[cfg(feature = "reports")]
+use futures::io::{AsyncRead, Cursor};
+#[cfg(any(feature = "client", feature = "reports"))]
+use futures::TryStreamExt;
+#[cfg(any(feature = "client", feature = "reports"))]
+use futures::{stream::BoxStream, StreamExt};
+
+/// Keys that V7 allows to be bound to an annotation hotkey
+pub const HOTKEY_KEYS: [&str; 10] = ["1", "2", "3", "4", "5", "6", "7", "8", "9", "0"];
+
+/// An action performed when an annotation hotkey is pressed
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum HotKeyAction {
+    /// Selects the annotation class with the given id, serialized as `select_class:<id>`
+    SelectClass(u32),
+    /// Any action not modelled by this crate, preserved as provided by V7
+    Other(String),
+}
+
+impl From<&str> for HotKeyAction {
+    fn from(value: &str) -> Self {
+        value
+            .strip_prefix("select_class:")
+            .and_then(|id| id.parse::<u32>().ok())
+            .map(HotKeyAction::SelectClass)
+            .unwrap_or_else(|| HotKeyAction::Other(value.to_string()))
+    }
+}
+
+impl Display for HotKeyAction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            HotKeyAction::SelectClass(id) => write!(f, "select_class:{id}"),
+            HotKeyAction::Other(action) => write!(f, "{action}"),
+        }
+    }
+}
 
-#[cfg_attr(test, derive(Dummy))]
+/// Annotation hotkeys of a dataset, mapping a key to the action it performs.
+///
+/// In JSON the hotkeys are a map of key to action string e.g.
+/// `{"1": "select_class:1234", "2": "select_class:5678"}`.
+/// Bindings returned by V7 are accepted as is, while bindings added with
+/// `bind` are validated against `HOTKEY_KEYS`.
 #[derive(Debug, Default, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(from = "HashMap<String, String>", into = "HashMap<String, String>")]
 pub struct AnnotationHotKeys {
-    pub key: String,
+    bindings: BTreeMap<String, HotKeyAction>,
+}
+
+impl AnnotationHotKeys {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Binds `action` to `key`, replacing any existing binding for the key
+    pub fn bind(&mut self, key: &str, action: HotKeyAction) -> Result<()> {
+        if !HOTKEY_KEYS.contains(&key) {
+            bail!("{key} is not a valid annotation hotkey, expected one of {HOTKEY_KEYS:?}");
+        }
+        self.bindings.insert(key.to_string(), action);
+        Ok(())
+    }
+
+    pub fn unbind(&mut self, key: &str) -> Option<HotKeyAction> {
+        self.bindings.remove(key)
+    }
+
+    pub fn get(&self, key: &str) -> Option<&HotKeyAction> {
+        self.bindings.get(key)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&String, &HotKeyAction)> {
+        self.bindings.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.bindings.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bindings.is_empty()
+    }
+
+    /// The key bound to select the annotation class `class_id` if any
+    pub fn key_for_class(&self, class_id: ClassId) -> Option<&str> {
+        self.bindings
+            .iter()
+            .find(|(_, action)| **action == HotKeyAction::SelectClass(class_id.0))
+            .map(|(key, _)| key.as_str())
+    }
+
+    /// Assigns the keys 1-9 to the most used annotation classes.
+    ///
+    /// `usage` maps annotation class ids to the number of annotations using the class.
+    /// Ties are broken by the lowest class id so that the assignment is stable.
+    pub fn from_class_usage(usage: &HashMap<u32, u64>) -> Self {
+        let mut classes: Vec<(&u32, &u64)> = usage.iter().collect();
+        classes.sort_by(|a, b| b.1.cmp(a.1).then(a.0.cmp(b.0)));
+
+        let bindings = HOTKEY_KEYS
+            .iter()
+            .take(9)
+            .zip(classes)
+            .map(|(key, (class_id, _))| (key.to_string(), HotKeyAction::SelectClass(*class_id)))
+            .collect();
+
+        Self { bindings }
+    }
+}
+
+impl From<HashMap<String, String>> for AnnotationHotKeys {
+    fn from(value: HashMap<String, String>) -> Self {
+        Self {
+            bindings: value
+                .iter()
+                .map(|(key, action)| (key.to_string(), HotKeyAction::from(action.as_str())))
+                .collect(),
+        }
+    }
+}
+
+impl From<AnnotationHotKeys> for HashMap<String, String> {
+    fn from(value: AnnotationHotKeys) -> Self {
+        value
+            .bindings
+            .iter()
+            .map(|(key, action)| (key.to_string(), action.to_string()))
+            .collect()
+    }
+}
+
+#[cfg(feature = "fake")]
+impl Dummy<Faker> for AnnotationHotKeys {
+    fn dummy_with_rng<R: rand::Rng + ?Sized>(_: &Faker, rng: &mut R) -> Self {
+        let num_keys: usize = (0..HOTKEY_KEYS.len()).fake_with_rng(rng);
+        let bindings = HOTKEY_KEYS
+            .iter()
+            .take(num_keys)
+            .map(|key| {
+                let class_id: u32 = Faker.fake_with_rng(rng);
+                (key.to_string(), HotKeyAction::SelectClass(class_id))
+            })
+            .collect();
+
+        Self { bindings }
+    }
 }
 
-#[derive(Debug, Default, Clone, Dummy, Serialize, Deserialize)]
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "fake", derive(Dummy))]
 pub struct Dataset {
     pub active: Option<bool>,
     pub archived: Option<bool>,
     pub archived_at: Option<String>,
-    // TODO: Find out what the annotation_hotkeys HashMap actually is
-    // the HashMap below is a placeholder for now, it is some kind of hashmap
-    pub annotation_hotkeys: Option<HashMap<String, String>>,
+    pub annotation_hotkeys: Option<AnnotationHotKeys>,
     pub annotators_can_create_tags: Option<bool>,
     pub annotators_can_instantiate_workflows: Option<bool>,
     pub anyone_can_double_assign: Option<bool>,
@@ -43,15 +231,17 @@ pub struct Dataset {
     #[serde(skip)]
     pub annotation_classes: Vec<Option<String>>,
 
-    pub default_workflow_template_id: Option<u32>,
+    pub default_workflow_template_id: Option<WorkflowTemplateId>,
 
     pub id: Option<u32>,
     pub inserted_at: Option<String>,
     pub instructions: Option<String>,
 
     pub name: Option<String>,
-    pub num_annotations: Option<Option<u32>>,
-    pub num_annotators: Option<Option<u32>>,
+    #[serde(default, skip_serializing_if = "Maybe::is_missing")]
+    pub num_annotations: Maybe<u32>,
+    #[serde(default, skip_serializing_if = "Maybe::is_missing")]
+    pub num_annotators: Maybe<u32>,
     pub num_classes: Option<u32>,
     pub num_complete_files: Option<u32>,
     pub num_images: Option<u32>,
@@ -73,13 +263,173 @@ pub struct Dataset {
     pub updated_at: Option<String>,
     pub version: Option<u32>,
     pub work_size: Option<u32>,
-    pub work_prioritization: Option<String>,
+    pub work_prioritization: Option<MaybeKnown<WorkPrioritization>>,
+}
+
+#[cfg(feature = "timestamps")]
+impl Dataset {
+    /// `inserted_at` parsed, see `Timestamp`
+    pub fn inserted_timestamp(&self) -> Option<Timestamp> {
+        Timestamp::parse_optional(&self.inserted_at)
+    }
+
+    /// `updated_at` parsed, see `Timestamp`
+    pub fn updated_timestamp(&self) -> Option<Timestamp> {
+        Timestamp::parse_optional(&self.updated_at)
+    }
+
+    /// `archived_at` parsed, see `Timestamp`
+    pub fn archived_timestamp(&self) -> Option<Timestamp> {
+        Timestamp::parse_optional(&self.archived_at)
+    }
+}
+
+impl Dataset {
+    /// Fraction of the items that are complete, from `num_complete_files` and `num_items`
+    /// when both are known, otherwise the `progress` reported by V7. `None` for an empty
+    /// dataset or when neither is known.
+    pub fn completion_ratio(&self) -> Option<f64> {
+        match (self.num_complete_files, self.num_items) {
+            (_, Some(0)) => None,
+            (Some(complete), Some(items)) => Some((complete as f64 / items as f64).min(1.0)),
+            _ => self.progress.map(|progress| progress.clamp(0.0, 1.0)),
+        }
+    }
+
+    /// Whether every item of a non-empty dataset is complete
+    pub fn is_complete(&self) -> bool {
+        self.num_items.is_some_and(|items| items > 0) && self.remaining_items() == Some(0)
+    }
+
+    /// Number of items that are not yet complete, `None` unless both counts are known
+    pub fn remaining_items(&self) -> Option<u32> {
+        Some(self.num_items?.saturating_sub(self.num_complete_files?))
+    }
+}
+
+/// Order in which the items of a dataset are served to annotators
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "fake", derive(Dummy))]
+pub enum WorkPrioritization {
+    #[serde(rename = "inserted_at:asc")]
+    InsertedAtAsc,
+    #[serde(rename = "inserted_at:desc")]
+    InsertedAtDesc,
+    #[serde(rename = "updated_at:asc")]
+    UpdatedAtAsc,
+    #[serde(rename = "updated_at:desc")]
+    UpdatedAtDesc,
+    #[serde(rename = "priority:desc")]
+    PriorityDesc,
+}
+
+/// The fields of a `Dataset` needed to identify it and call the dataset methods, listed
+/// with `list_dataset_summaries` to avoid parsing the many other fields of each dataset
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "fake", derive(Dummy))]
+pub struct DatasetSummary {
+    pub id: Option<u32>,
+    pub name: Option<String>,
+    pub slug: Option<String>,
+    pub team_id: Option<u32>,
+    pub team_slug: Option<String>,
+    pub archived: Option<bool>,
+    pub num_items: Option<u32>,
+}
+
+impl From<DatasetSummary> for Dataset {
+    fn from(summary: DatasetSummary) -> Self {
+        Dataset {
+            id: summary.id,
+            name: summary.name,
+            slug: summary.slug,
+            team_id: summary.team_id,
+            team_slug: summary.team_slug,
+            archived: summary.archived,
+            num_items: summary.num_items,
+            ..Default::default()
+        }
+    }
+}
+
+/// The identifiers of a dataset, checked when it is created, to share a dataset between
+/// concurrent tasks. The dataset methods are implemented for it, the `Dataset`
+/// implementations convert to a `DatasetRef` first so that a missing identifier fails the
+/// call before any request is made. The few methods that need the other fields of the
+/// dataset fetch it first.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct DatasetRef {
+    pub id: DatasetId,
+    pub slug: String,
+    pub team_slug: String,
+}
+
+impl DatasetRef {
+    pub fn new(id: DatasetId, slug: &str, team_slug: &str) -> Self {
+        DatasetRef {
+            id,
+            slug: slug.to_string(),
+            team_slug: team_slug.to_string(),
+        }
+    }
+
+    fn from_parts(
+        id: Option<u32>,
+        slug: Option<&String>,
+        team_slug: Option<&String>,
+    ) -> Result<Self> {
+        let (Some(id), Some(slug), Some(team_slug)) = (id, slug, team_slug) else {
+            bail!(
+                "Dataset is missing {}",
+                [
+                    id.is_none().then_some("Id"),
+                    slug.is_none().then_some("slug"),
+                    team_slug.is_none().then_some("team slug"),
+                ]
+                .into_iter()
+                .flatten()
+                .collect::<Vec<_>>()
+                .join(", ")
+            );
+        };
+        Ok(DatasetRef::new(DatasetId(id), slug, team_slug))
+    }
+}
+
+impl TryFrom<&Dataset> for DatasetRef {
+    type Error = anyhow::Error;
+
+    fn try_from(dataset: &Dataset) -> Result<Self> {
+        DatasetRef::from_parts(
+            dataset.id,
+            dataset.slug.as_ref(),
+            dataset.team_slug.as_ref(),
+        )
+    }
+}
+
+impl TryFrom<&DatasetSummary> for DatasetRef {
+    type Error = anyhow::Error;
+
+    fn try_from(summary: &DatasetSummary) -> Result<Self> {
+        DatasetRef::from_parts(
+            summary.id,
+            summary.slug.as_ref(),
+            summary.team_slug.as_ref(),
+        )
+    }
 }
 
-#[cfg_attr(test, derive(Dummy))]
+impl Display for DatasetRef {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}/{}", self.id, self.team_slug, self.slug)
+    }
+}
+
+#[cfg_attr(feature = "fake", derive(Dummy))]
 #[derive(Debug, Default, Clone, Serialize, Deserialize)]
 pub struct DatasetUpdate {
-    pub annotation_hotkeys: Option<HashMap<String, String>>,
+    pub annotation_hotkeys: Option<AnnotationHotKeys>,
     pub annotators_can_create_tags: Option<bool>,
     pub annotators_can_instantiate_workflows: Option<bool>,
     pub anyone_can_double_assign: Option<bool>,
@@ -90,7 +440,80 @@ pub struct DatasetUpdate {
     pub public: Option<bool>,
     pub reviewers_can_annotate: Option<bool>,
     pub work_size: Option<u32>,
-    pub work_prioritization: Option<String>,
+    pub work_prioritization: Option<MaybeKnown<WorkPrioritization>>,
+    /// Only sent when changing the v1 workflow template of the dataset
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub default_workflow_template_id: Option<WorkflowTemplateId>,
+}
+
+#[cfg(feature = "client")]
+/// Applies `modify` to the current settings of the dataset, fetched just before the update
+/// as the endpoint requires every setting and would otherwise revert concurrent changes
+async fn update_dataset_settings<C, F>(id: &DatasetId, client: &C, modify: F) -> Result<()>
+where
+    C: V7Methods + std::marker::Sync,
+    F: FnOnce(&mut DatasetUpdate) + Send,
+{
+    let current = Dataset::show_dataset(client, id).await?;
+    let mut payload = DatasetUpdate::from(&current);
+    modify(&mut payload);
+
+    let response = client
+        .put(&format!("datasets/{id}"), Some(&payload))
+        .await?;
+    if response.status() != 200 {
+        bail!(HttpStatusError::from_response(response).await);
+    }
+    Ok(())
+}
+
+#[cfg(feature = "client")]
+#[derive(Debug, Serialize)]
+struct InstructionAttachmentPayload {
+    file_name: String,
+    content_type: &'static str,
+}
+
+#[cfg(feature = "client")]
+/// Pre-signed upload of an attachment to the instructions of a dataset
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct InstructionAttachment {
+    upload_url: String,
+    /// Url the attachment is hosted at once uploaded
+    url: String,
+}
+
+#[cfg(feature = "client")]
+fn attachment_content_type(path: &Path) -> Result<&'static str> {
+    let extension = path
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .map(str::to_lowercase);
+    Ok(match extension.as_deref() {
+        Some("png") => "image/png",
+        Some("jpg") | Some("jpeg") => "image/jpeg",
+        Some("gif") => "image/gif",
+        Some("webp") => "image/webp",
+        Some("svg") => "image/svg+xml",
+        _ => bail!("Unsupported instruction attachment {}", path.display()),
+    })
+}
+
+#[cfg(feature = "client")]
+// Escapes a value to be quoted in an attribute of the instructions HTML
+fn escape_html_attribute(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
 }
 
 impl From<&Dataset> for DatasetUpdate {
@@ -106,18 +529,48 @@ impl From<&Dataset> for DatasetUpdate {
             reviewers_can_annotate: value.reviewers_can_annotate,
             work_size: value.work_size,
             work_prioritization: value.work_prioritization.clone(),
+            default_workflow_template_id: None,
         }
     }
 }
 
-#[cfg_attr(test, derive(Dummy))]
+#[cfg_attr(feature = "fake", derive(Dummy))]
 #[derive(Debug, Default, Clone, Serialize, Deserialize)]
 pub struct ExportMetadata {
+    #[serde(default, deserialize_with = "null_as_default")]
     pub annotation_classes: Vec<Option<AnnotationClass>>,
+    #[serde(default, deserialize_with = "null_as_default")]
     pub annotation_types: Vec<Option<TypeCount>>,
 }
 
-#[cfg_attr(test, derive(Dummy))]
+impl ExportMetadata {
+    /// Number of annotation classes in the export
+    pub fn class_count(&self) -> usize {
+        self.annotation_classes.iter().flatten().count()
+    }
+
+    /// Total number of annotations in the export, summed over the annotation types
+    pub fn annotation_count(&self) -> u64 {
+        self.annotation_types
+            .iter()
+            .flatten()
+            .filter_map(|type_count| type_count.count)
+            .map(u64::from)
+            .sum()
+    }
+
+    /// Number of annotations of the type, e.g. `polygon`, `None` if the export has
+    /// no count for the type
+    pub fn type_count(&self, annotation_type: &str) -> Option<u32> {
+        self.annotation_types
+            .iter()
+            .flatten()
+            .find(|type_count| type_count.name.as_deref() == Some(annotation_type))
+            .and_then(|type_count| type_count.count)
+    }
+}
+
+#[cfg_attr(feature = "fake", derive(Dummy))]
 #[derive(Debug, Default, Clone, Serialize, Deserialize)]
 pub struct Export {
     pub name: Option<String>,
@@ -125,13 +578,212 @@ pub struct Export {
     pub format: Option<ExportFormat>,
     pub inserted_at: Option<String>,
     pub latest: Option<bool>,
-    #[serde(skip_deserializing)]
+    /// Class and annotation type counts, check them before downloading a large archive
+    #[serde(default, deserialize_with = "null_as_default")]
     pub metadata: ExportMetadata,
     pub status: Option<String>,
     pub version: Option<u16>,
 }
 
-#[cfg_attr(test, derive(Dummy))]
+#[cfg(feature = "timestamps")]
+impl Export {
+    /// `inserted_at` parsed, see `Timestamp`
+    pub fn inserted_timestamp(&self) -> Option<Timestamp> {
+        Timestamp::parse_optional(&self.inserted_at)
+    }
+}
+
+/// Outcome of `Export::download`
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct ExportDownload {
+    pub path: PathBuf,
+    pub size_bytes: u64,
+    /// Number of bytes already on disk from an interrupted download
+    pub resumed_from: u64,
+    /// Whether the MD5 of the file was checked against the ETag of the archive.
+    /// Archives uploaded in multiple parts have no usable ETag and are only checked for size.
+    pub checksum_verified: bool,
+}
+
+#[cfg(feature = "client")]
+fn partial_download_path(destination: &Path) -> PathBuf {
+    let mut file_name = destination.file_name().unwrap_or_default().to_os_string();
+    file_name.push(".part");
+    destination.with_file_name(file_name)
+}
+
+#[cfg(feature = "client")]
+/// The ETag of the archive that the `.part` file is a prefix of, sent as `If-Range` when
+/// resuming so that a changed archive is downloaded again rather than appended to
+fn partial_etag_path(destination: &Path) -> PathBuf {
+    let mut file_name = partial_download_path(destination).into_os_string();
+    file_name.push(".etag");
+    PathBuf::from(file_name)
+}
+
+#[cfg(feature = "client")]
+/// The complete size from a `Content-Range` header, e.g. 19 for `bytes 5-18/19` or `bytes */19`
+fn content_range_total(headers: &HeaderMap) -> Option<u64> {
+    headers
+        .get(CONTENT_RANGE)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.rsplit_once('/'))
+        .and_then(|(_, total)| total.parse::<u64>().ok())
+}
+
+#[cfg(feature = "client")]
+fn file_md5(path: &Path) -> Result<String> {
+    let mut file = std::fs::File::open(path)?;
+    let mut hasher = Md5::new();
+    let mut buffer = vec![0; 1 << 20];
+    loop {
+        let read = file.read(&mut buffer)?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+    }
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+#[cfg(feature = "client")]
+impl Export {
+    /// Downloads the export archive to `destination`, reporting the bytes downloaded so
+    /// far and the total size (when known) to `progress` after each chunk.
+    ///
+    /// The archive is written to `<destination>.part` and only moved to `destination` once
+    /// its size and, when available, checksum have been verified. If a previous download
+    /// was interrupted the existing `.part` file is resumed using an HTTP range request,
+    /// conditional on the archive still having the ETag it had when the download started.
+    /// A `.part` file without a recorded ETag is downloaded again from the start.
+    ///
+    /// `client` should not be the `V7Client` as the download url is pre-signed and
+    /// is rejected if an authorization header is sent. Note that the file is written
+    /// with blocking IO.
+    pub async fn download(
+        &self,
+        client: &dyn PresignedUrlMethods,
+        destination: &Path,
+        progress: &dyn ProgressSink,
+    ) -> Result<ExportDownload> {
+        let url = self
+            .download_url
+            .as_ref()
+            .context("Export is missing download url")?;
+        let partial = partial_download_path(destination);
+        let etag_path = partial_etag_path(destination);
+        let mut offset = std::fs::metadata(&partial)
+            .map(|metadata| metadata.len())
+            .unwrap_or(0);
+        let partial_etag = std::fs::read_to_string(&etag_path).ok();
+
+        let mut headers = HeaderMap::new();
+        match partial_etag.as_deref() {
+            Some(partial_etag) if offset > 0 => {
+                headers.insert(RANGE, HeaderValue::from_str(&format!("bytes={offset}-"))?);
+                headers.insert(IF_RANGE, HeaderValue::from_str(partial_etag)?);
+            }
+            _ => offset = 0,
+        }
+        let mut response = client.get_url(url, headers).await?;
+
+        let mut started = false;
+        if response.status() == 416 {
+            // The partial file does not match the archive, start again. The size of the
+            // archive is in the `bytes */<size>` content range of the response
+            progress.event(ProgressEvent::Started {
+                operation: "export download",
+                total: content_range_total(response.headers()),
+            });
+            started = true;
+            progress.event(ProgressEvent::Retry {
+                attempt: 1,
+                reason: "Partial download does not match the export, restarting".to_string(),
+            });
+            offset = 0;
+            response = client.get_url(url, HeaderMap::new()).await?;
+        }
+        let total = match response.status().as_u16() {
+            206 => content_range_total(response.headers()),
+            200 => {
+                offset = 0;
+                response.content_length()
+            }
+            _ => bail!(HttpStatusError::from_response(response).await),
+        };
+        let raw_etag = response
+            .headers()
+            .get(ETAG)
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_string);
+        if offset == 0 {
+            match raw_etag.as_ref() {
+                Some(raw_etag) => std::fs::write(&etag_path, raw_etag)?,
+                None => {
+                    let _ = std::fs::remove_file(&etag_path);
+                }
+            }
+        }
+        let etag = raw_etag.map(|value| value.trim_matches('"').to_lowercase());
+        if !started {
+            progress.event(ProgressEvent::Started {
+                operation: "export download",
+                total,
+            });
+        }
+
+        let mut file = std::fs::OpenOptions::new()
+            .create(true)
+            .write(true)
+            .append(offset > 0)
+            .truncate(offset == 0)
+            .open(&partial)
+            .with_context(|| format!("Unable to open {}", partial.display()))?;
+        let mut downloaded = offset;
+        while let Some(chunk) = response.chunk().await? {
+            file.write_all(&chunk)?;
+            downloaded += chunk.len() as u64;
+            progress.event(ProgressEvent::BytesTransferred {
+                done: downloaded,
+                total,
+            });
+        }
+        file.flush()?;
+        drop(file);
+
+        if let Some(total) = total {
+            if downloaded != total {
+                bail!("Downloaded {downloaded} of {total} bytes, retry to resume the download");
+            }
+        }
+
+        // Multipart ETags are suffixed with the number of parts and are not an MD5 of the file
+        let expected_md5 =
+            etag.filter(|etag| etag.len() == 32 && etag.chars().all(|c| c.is_ascii_hexdigit()));
+        if let Some(expected_md5) = expected_md5.as_ref() {
+            let md5 = file_md5(&partial)?;
+            if &md5 != expected_md5 {
+                std::fs::remove_file(&partial)?;
+                let _ = std::fs::remove_file(&etag_path);
+                bail!("Checksum of downloaded export {md5} does not match {expected_md5}");
+            }
+        }
+
+        std::fs::rename(&partial, destination)?;
+        let _ = std::fs::remove_file(&etag_path);
+        progress.event(ProgressEvent::Finished {
+            operation: "export download",
+        });
+        Ok(ExportDownload {
+            path: destination.to_path_buf(),
+            size_bytes: downloaded,
+            resumed_from: offset,
+            checksum_verified: expected_md5.is_some(),
+        })
+    }
+}
+
+#[cfg_attr(feature = "fake", derive(Dummy))]
 #[derive(Debug, Default, Clone, Serialize, Deserialize, PartialEq, Eq)]
 #[serde(rename_all = "lowercase")]
 pub enum ExportFormat {
@@ -165,11 +817,87 @@ impl From<ExportFormat> for &str {
     }
 }
 
+impl ExportFormat {
+    /// The item types the format can represent. Darwin JSON supports every type, the
+    /// other formats describe single images and masks have no pages.
+    pub fn supported_item_types(&self) -> &'static [DatasetItemTypes] {
+        match self {
+            ExportFormat::DarwinJson2 | ExportFormat::Json => &DatasetItemTypes::ALL,
+            ExportFormat::Xml
+            | ExportFormat::Coco
+            | ExportFormat::Cvat
+            | ExportFormat::PascalVoc => &[DatasetItemTypes::Image, DatasetItemTypes::TiledImage],
+            ExportFormat::SemanticMask | ExportFormat::InstanceMask => &[
+                DatasetItemTypes::Image,
+                DatasetItemTypes::Video,
+                DatasetItemTypes::Dicom,
+                DatasetItemTypes::TiledImage,
+            ],
+        }
+    }
+
+    /// Whether items of `item_type` can be exported in the format, see
+    /// `supported_item_types`. An item is supported if the types of all its slots are
+    pub fn supports(&self, item_type: &DatasetItemTypes) -> bool {
+        self.supported_item_types().contains(item_type)
+    }
+
+    /// Checks that the format supports all of `item_types`, e.g. the slot types of the
+    /// items of a dataset, listing the unsupported types in the error
+    pub fn check_item_types<'a, I>(&self, item_types: I) -> Result<(), UnsupportedExportFormat>
+    where
+        I: IntoIterator<Item = &'a DatasetItemTypes>,
+    {
+        let mut unsupported: Vec<DatasetItemTypes> = vec![];
+        for item_type in item_types {
+            if !self.supports(item_type) && !unsupported.contains(item_type) {
+                unsupported.push(item_type.clone());
+            }
+        }
+        if unsupported.is_empty() {
+            Ok(())
+        } else {
+            Err(UnsupportedExportFormat {
+                format: self.clone(),
+                item_types: unsupported,
+            })
+        }
+    }
+}
+
+/// An export format that cannot represent some of the item types of a dataset, raised
+/// before the export is requested rather than relying upon the error of V7.
+///
+/// Recover it from an `anyhow::Error` with `error.downcast_ref::<UnsupportedExportFormat>()`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnsupportedExportFormat {
+    pub format: ExportFormat,
+    /// The item types of the dataset the format does not support
+    pub item_types: Vec<DatasetItemTypes>,
+}
+
+impl Display for UnsupportedExportFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let item_types: Vec<String> = self.item_types.iter().map(ToString::to_string).collect();
+        write!(
+            f,
+            "Export format {} does not support {} items, use {} instead",
+            Into::<&str>::into(self.format.clone()),
+            item_types.join(", "),
+            Into::<&str>::into(ExportFormat::DarwinJson2)
+        )
+    }
+}
+
+impl std::error::Error for UnsupportedExportFormat {}
+
+#[cfg(feature = "client")]
 #[derive(Debug, Default, Clone, Serialize, Deserialize, PartialEq, Eq)]
 struct DatasetName {
     pub name: String,
 }
 
+#[cfg(feature = "client")]
 #[derive(Debug, Default, Clone, Serialize, Deserialize, PartialEq, Eq)]
 struct AddDataItemsPayload {
     pub items: Vec<AddDataPayload>,
@@ -178,7 +906,7 @@ struct AddDataItemsPayload {
 
 /// Version 2.0 equivalent of `AddDataItemsPayload`
 ///
-#[cfg_attr(test, derive(Dummy))]
+#[cfg_attr(feature = "fake", derive(Dummy))]
 #[derive(Debug, Default, Clone, Serialize, Deserialize, PartialEq, Eq)]
 pub struct RegisterExistingItemPayload {
     /// Slug name of the Dataset to upload images to
@@ -189,104 +917,436 @@ pub struct RegisterExistingItemPayload {
     pub items: Vec<ExistingSimpleItem>,
 }
 
-#[cfg_attr(test, derive(Dummy))]
-#[derive(Debug, Default, Clone, Serialize, Deserialize, PartialEq, Eq)]
-pub struct ResponseItem {
-    pub dataset_item_id: Option<u64>,
-    pub filename: Option<String>,
+/// Items to register from external storage. The variant selects the registration
+/// endpoint which must match how the storage was configured in V7.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(untagged)]
+pub enum RegistrationItems {
+    /// Items from read-only storage, uses `register_existing_readonly`
+    ReadOnly(Vec<ExistingSimpleItem>),
+    /// Items from read-write storage, uses `register_existing`
+    ReadWrite(Vec<ExistingReadWriteItem>),
 }
 
-#[cfg_attr(test, derive(Dummy))]
-#[derive(Debug, Default, Clone, Serialize, Deserialize, PartialEq, Eq)]
-pub struct ArchiveResponseItems {
-    pub affected_item_count: Option<i32>,
+/// How items are considered duplicates of each other before registration
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DuplicateKey {
+    /// Items registered to the same dataset path and name
+    #[default]
+    NameAndPath,
+    /// Items whose slots reference the same storage keys
+    StorageKey,
 }
 
-#[cfg_attr(test, derive(Dummy))]
-#[derive(Debug, Default, Clone, Serialize, Deserialize, PartialEq, Eq)]
-pub struct AddDataItemsResponse {
-    pub blocked_items: Vec<Option<ResponseItem>>,
-    pub items: Vec<Option<ResponseItem>>,
+/// What to do with duplicate items before registration
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DuplicatePolicy {
+    /// Register every item, V7 blocks the duplicates of items it has already registered
+    #[default]
+    Allow,
+    /// Fail with a `DuplicateItemsError` without registering anything
+    Reject,
+    /// Register the first of the duplicates
+    KeepFirst,
+    /// Register the last of the duplicates
+    KeepLast,
 }
 
-#[cfg_attr(test, derive(Dummy))]
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
-pub struct SlotResponse {
-    pub as_frames: bool,
-    pub extract_views: bool,
-    pub file_name: String,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub reason: Option<String>,
-    pub metadata: DataPayloadLevel,
-    pub slot_name: String,
-    pub size_bytes: u64,
-    #[serde(rename = "type")]
-    pub item_type: DatasetItemTypes,
+/// Items sharing the same `DuplicateKey`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DuplicateItems {
+    pub key: String,
+    /// Positions of the duplicates in the registration items, in ascending order
+    pub indices: Vec<usize>,
 }
 
-#[cfg_attr(test, derive(Dummy))]
-#[derive(Debug, Default, Clone, Serialize, Deserialize, PartialEq, Eq)]
-pub struct RegistrationResponseItem {
-    pub id: Option<String>,
-    pub name: Option<String>,
-    pub path: Option<String>,
-    pub slots: Vec<Option<SlotResponse>>,
+/// Duplicate items found in a registration, ordered by the first position of each key
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct DuplicatesReport {
+    pub duplicates: Vec<DuplicateItems>,
 }
 
-#[cfg_attr(test, derive(Dummy))]
-#[derive(Debug, Default, Clone, Serialize, Deserialize, PartialEq, Eq)]
-pub struct RegisterExistingItemResponse {
-    pub blocked_items: Vec<Option<RegistrationResponseItem>>,
-    pub items: Vec<Option<RegistrationResponseItem>>,
+impl DuplicatesReport {
+    pub fn is_empty(&self) -> bool {
+        self.duplicates.is_empty()
+    }
 }
 
-#[cfg_attr(test, derive(Dummy))]
-#[derive(Debug, Default, Clone, Serialize, Deserialize, PartialEq, Eq)]
-struct ArchiveItemPayload {
-    pub filters: Filter,
-}
+/// Returned when registration items contain duplicates under `DuplicatePolicy::Reject`,
+/// recover it from an `anyhow::Error` with `error.downcast_ref::<DuplicateItemsError>()`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DuplicateItemsError(pub DuplicatesReport);
 
-#[cfg_attr(test, derive(Dummy))]
-#[derive(Debug, Default, Clone, Serialize, Deserialize, PartialEq, Eq)]
-struct AssignItemPayload {
-    pub assignee_id: u32,
-    pub filter: Filter,
+impl Display for DuplicateItemsError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let keys: Vec<&str> = self
+            .0
+            .duplicates
+            .iter()
+            .map(|duplicate| duplicate.key.as_str())
+            .collect();
+        write!(f, "Duplicate registration items: {}", keys.join(", "))
+    }
 }
 
-#[derive(Serialize, Deserialize)]
-struct GenerateExportPayload {
-    pub name: String,
-    pub format: String,
-    pub include_authorship: bool,
-    pub include_export_token: bool,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub filters: Option<Filter>,
-}
+impl std::error::Error for DuplicateItemsError {}
 
-#[derive(Debug, Serialize, Deserialize)]
-struct ResetToNewPayload {
+impl RegistrationItems {
+    #[cfg(feature = "client")]
+    fn endpoint(&self) -> &'static str {
+        match self {
+            RegistrationItems::ReadOnly(_) => "register_existing_readonly",
+            RegistrationItems::ReadWrite(_) => "register_existing",
+        }
+    }
+
+    /// Finds the items sharing the same `key`
+    pub fn find_duplicates(&self, key: DuplicateKey) -> DuplicatesReport {
+        match self {
+            RegistrationItems::ReadOnly(items) => find_duplicate_items(items, key),
+            RegistrationItems::ReadWrite(items) => find_duplicate_items(items, key),
+        }
+    }
+
+    /// Removes the duplicate items according to `policy`, returning the duplicates found.
+    /// Fails with a `DuplicateItemsError` for `DuplicatePolicy::Reject` if there are any,
+    /// and keeps every item for `DuplicatePolicy::Allow`.
+    pub fn deduplicate(
+        &mut self,
+        key: DuplicateKey,
+        policy: DuplicatePolicy,
+    ) -> Result<DuplicatesReport> {
+        match self {
+            RegistrationItems::ReadOnly(items) => deduplicate_items(items, key, policy),
+            RegistrationItems::ReadWrite(items) => deduplicate_items(items, key, policy),
+        }
+    }
+}
+
+// The parts of a registration item that duplicates are detected by
+trait RegistrationItem {
+    fn path_and_name(&self) -> (&str, &str);
+    fn storage_keys(&self) -> Vec<&str>;
+
+    fn duplicate_key(&self, key: DuplicateKey) -> String {
+        match key {
+            DuplicateKey::NameAndPath => {
+                let (path, name) = self.path_and_name();
+                format!("{}/{}", path.trim_end_matches('/'), name)
+            }
+            DuplicateKey::StorageKey => self.storage_keys().join(","),
+        }
+    }
+}
+
+impl RegistrationItem for ExistingSimpleItem {
+    fn path_and_name(&self) -> (&str, &str) {
+        (&self.path, &self.name)
+    }
+
+    fn storage_keys(&self) -> Vec<&str> {
+        self.slots.iter().map(|s| s.storage_key.as_str()).collect()
+    }
+}
+
+impl RegistrationItem for ExistingReadWriteItem {
+    fn path_and_name(&self) -> (&str, &str) {
+        (&self.path, &self.name)
+    }
+
+    fn storage_keys(&self) -> Vec<&str> {
+        self.slots.iter().map(|s| s.storage_key.as_str()).collect()
+    }
+}
+
+fn find_duplicate_items<T: RegistrationItem>(items: &[T], key: DuplicateKey) -> DuplicatesReport {
+    let mut positions: BTreeMap<String, Vec<usize>> = BTreeMap::new();
+    for (index, item) in items.iter().enumerate() {
+        positions
+            .entry(item.duplicate_key(key))
+            .or_default()
+            .push(index);
+    }
+    let mut duplicates: Vec<DuplicateItems> = positions
+        .into_iter()
+        .filter(|(_, indices)| indices.len() > 1)
+        .map(|(key, indices)| DuplicateItems { key, indices })
+        .collect();
+    duplicates.sort_by_key(|duplicate| duplicate.indices[0]);
+    DuplicatesReport { duplicates }
+}
+
+fn deduplicate_items<T: RegistrationItem>(
+    items: &mut Vec<T>,
+    key: DuplicateKey,
+    policy: DuplicatePolicy,
+) -> Result<DuplicatesReport> {
+    let report = find_duplicate_items(items, key);
+    if report.is_empty() {
+        return Ok(report);
+    }
+    let removed: std::collections::HashSet<usize> = match policy {
+        DuplicatePolicy::Allow => return Ok(report),
+        DuplicatePolicy::Reject => bail!(DuplicateItemsError(report)),
+        DuplicatePolicy::KeepFirst => report
+            .duplicates
+            .iter()
+            .flat_map(|duplicate| duplicate.indices[1..].iter().copied())
+            .collect(),
+        DuplicatePolicy::KeepLast => report
+            .duplicates
+            .iter()
+            .flat_map(|duplicate| {
+                duplicate.indices[..duplicate.indices.len() - 1]
+                    .iter()
+                    .copied()
+            })
+            .collect(),
+    };
+    let mut positions = 0..;
+    items.retain(|_| !removed.contains(&positions.next().unwrap_or_default()));
+    Ok(report)
+}
+
+#[cfg(feature = "client")]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+struct RegistrationPayload {
+    pub dataset_slug: String,
+    pub storage_slug: String,
+    pub items: RegistrationItems,
+}
+
+#[cfg_attr(feature = "fake", derive(Dummy))]
+#[derive(Debug, Default, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ReadWriteSlotResponse {
+    pub as_frames: Option<bool>,
+    pub extract_views: Option<bool>,
+    pub file_name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+    pub slot_name: Option<String>,
+    pub storage_key: Option<String>,
+    #[serde(rename = "type")]
+    pub item_type: Option<DatasetItemTypes>,
+    pub upload_id: Option<String>,
+}
+
+#[cfg_attr(feature = "fake", derive(Dummy))]
+#[derive(Debug, Default, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ReadWriteResponseItem {
+    pub id: Option<String>,
+    pub name: Option<String>,
+    pub path: Option<String>,
+    pub slots: Vec<Option<ReadWriteSlotResponse>>,
+}
+
+#[cfg_attr(feature = "fake", derive(Dummy))]
+#[derive(Debug, Default, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct RegisterReadWriteItemResponse {
+    pub blocked_items: Vec<Option<ReadWriteResponseItem>>,
+    pub items: Vec<Option<ReadWriteResponseItem>>,
+}
+
+/// Response of `register_existing_items`, matching the variant of `RegistrationItems`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RegistrationResponse {
+    ReadOnly(RegisterExistingItemResponse),
+    ReadWrite(RegisterReadWriteItemResponse),
+}
+
+#[cfg_attr(feature = "fake", derive(Dummy))]
+#[derive(Debug, Default, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ResponseItem {
+    pub dataset_item_id: Option<u64>,
+    pub filename: Option<String>,
+}
+
+#[cfg_attr(feature = "fake", derive(Dummy))]
+#[derive(Debug, Default, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ArchiveResponseItems {
+    pub affected_item_count: Option<i32>,
+}
+
+#[cfg_attr(feature = "fake", derive(Dummy))]
+#[derive(Debug, Default, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct AddDataItemsResponse {
+    pub blocked_items: Vec<Option<ResponseItem>>,
+    pub items: Vec<Option<ResponseItem>>,
+}
+
+#[cfg_attr(feature = "fake", derive(Dummy))]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct SlotResponse {
+    pub as_frames: bool,
+    pub extract_views: bool,
+    pub file_name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+    pub metadata: DataPayloadLevel,
+    pub slot_name: String,
+    pub size_bytes: u64,
+    #[serde(rename = "type")]
+    pub item_type: DatasetItemTypes,
+}
+
+#[cfg_attr(feature = "fake", derive(Dummy))]
+#[derive(Debug, Default, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct RegistrationResponseItem {
+    pub id: Option<String>,
+    pub name: Option<String>,
+    pub path: Option<String>,
+    pub slots: Vec<Option<SlotResponse>>,
+}
+
+#[cfg_attr(feature = "fake", derive(Dummy))]
+#[derive(Debug, Default, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct RegisterExistingItemResponse {
+    pub blocked_items: Vec<Option<RegistrationResponseItem>>,
+    pub items: Vec<Option<RegistrationResponseItem>>,
+}
+
+/// Reasons V7 blocks an item for which registering it again cannot succeed
+pub const PERMANENT_BLOCK_REASONS: [&str; 2] = ["ALREADY_EXISTS", "UNSUPPORTED_FILE_TYPE"];
+
+impl RegistrationResponseItem {
+    /// Reasons given for the blocked slots of the item, `UNKNOWN` if V7 gave none
+    fn block_reasons(&self) -> Vec<&str> {
+        let reasons: Vec<&str> = self
+            .slots
+            .iter()
+            .flatten()
+            .filter_map(|slot| slot.reason.as_deref())
+            .collect();
+        if reasons.is_empty() {
+            vec!["UNKNOWN"]
+        } else {
+            reasons
+        }
+    }
+}
+
+impl RegisterExistingItemResponse {
+    /// Whether every item was registered, i.e. none were blocked
+    pub fn is_fully_successful(&self) -> bool {
+        self.blocked_items.iter().flatten().next().is_none()
+    }
+
+    /// Name of each blocked item with the reason it was blocked, one entry per blocked slot
+    pub fn blocked_reasons(&self) -> Vec<(String, String)> {
+        self.blocked_items
+            .iter()
+            .flatten()
+            .flat_map(|item| {
+                let name = item.name.clone().unwrap_or_default();
+                item.block_reasons()
+                    .into_iter()
+                    .map(move |reason| (name.clone(), reason.to_string()))
+            })
+            .collect()
+    }
+
+    /// Blocked items worth registering again, i.e. none of their slots were blocked for one
+    /// of the `PERMANENT_BLOCK_REASONS`. Match them to the requested items on path and name.
+    pub fn retryable_items(&self) -> Vec<&RegistrationResponseItem> {
+        self.blocked_items
+            .iter()
+            .flatten()
+            .filter(|item| {
+                item.block_reasons().iter().all(|reason| {
+                    !PERMANENT_BLOCK_REASONS
+                        .iter()
+                        .any(|permanent| permanent.eq_ignore_ascii_case(reason))
+                })
+            })
+            .collect()
+    }
+
+    /// One line summary for logging e.g.
+    /// `registered 8 items, blocked 2 (ALREADY_EXISTS: 1, UNKNOWN: 1)`
+    pub fn summary(&self) -> String {
+        let registered = self.items.iter().flatten().count();
+        let blocked = self.blocked_items.iter().flatten().count();
+        if blocked == 0 {
+            return format!("registered {registered} items");
+        }
+
+        let mut reasons: BTreeMap<String, usize> = BTreeMap::new();
+        for (_, reason) in self.blocked_reasons() {
+            *reasons.entry(reason).or_default() += 1;
+        }
+        let reasons: Vec<String> = reasons
+            .iter()
+            .map(|(reason, count)| format!("{reason}: {count}"))
+            .collect();
+        format!(
+            "registered {registered} items, blocked {blocked} ({})",
+            reasons.join(", ")
+        )
+    }
+}
+
+#[cfg(feature = "client")]
+#[cfg_attr(feature = "fake", derive(Dummy))]
+#[derive(Debug, Default, Clone, Serialize, Deserialize, PartialEq, Eq)]
+struct ArchiveItemPayload {
+    pub filters: Filter,
+}
+
+#[cfg(feature = "client")]
+#[derive(Debug, Default, Clone, Serialize, Deserialize, PartialEq, Eq)]
+struct ResyncItemPayload {
+    pub filters: Filter,
+}
+
+#[cfg(feature = "client")]
+#[cfg_attr(feature = "fake", derive(Dummy))]
+#[derive(Debug, Default, Clone, Serialize, Deserialize, PartialEq, Eq)]
+struct AssignItemPayload {
+    pub assignee_id: u32,
     pub filter: Filter,
 }
 
+#[cfg(feature = "client")]
+#[derive(Serialize, Deserialize)]
+struct GenerateExportPayload {
+    pub name: String,
+    pub format: String,
+    pub include_authorship: bool,
+    pub include_export_token: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub filters: Option<Filter>,
+}
+
+#[cfg(feature = "client")]
 #[derive(Debug, Serialize, Deserialize)]
-struct SetStagePayload {
-    pub workflow_stage_template_id: u32,
+struct ResetToNewPayload {
     pub filter: Filter,
 }
 
+#[cfg(feature = "client")]
 #[derive(Debug, Serialize, Deserialize)]
+struct SetStagePayload {
+    pub workflow_stage_template_id: WorkflowStageTemplateId,
+    pub filter: Filter,
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
 pub struct SetStageFilter {
-    pub dataset_ids: Vec<u32>,
+    pub dataset_ids: Vec<DatasetId>,
     pub select_all: bool,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub workflow_stage_ids: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub item_ids: Option<Vec<String>>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct SetStagePayloadV2 {
     pub filters: SetStageFilter,
-    pub stage_id: String,
-    pub workflow_id: String,
+    pub stage_id: StageId,
+    pub workflow_id: WorkflowId,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -294,15 +1354,41 @@ pub struct SetStageResponse {
     pub created_commands: Option<u32>,
 }
 
-#[cfg_attr(test, derive(Dummy))]
+/// Number of item ids sent in each stage command by `set_stage_v2_chunked` unless
+/// another `chunk_size` is given
+pub const SET_STAGE_CHUNK_SIZE: usize = 1000;
+
+/// A chunk of `set_stage_v2_chunked` that V7 did not accept
+#[derive(Debug)]
+pub struct SetStageChunkFailure {
+    pub item_ids: Vec<String>,
+    pub error: anyhow::Error,
+}
+
+/// Aggregated outcome of the stage commands sent by `set_stage_v2_chunked`
+#[derive(Debug, Default)]
+pub struct ChunkedSetStageResponse {
+    /// Sum of the commands created for the accepted chunks
+    pub created_commands: u32,
+    pub chunks_sent: usize,
+    pub failures: Vec<SetStageChunkFailure>,
+}
+
+impl ChunkedSetStageResponse {
+    pub fn is_success(&self) -> bool {
+        self.failures.is_empty()
+    }
+}
+
+#[cfg_attr(feature = "fake", derive(Dummy))]
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 pub struct ItemReport {
     /// Original filename of the item
     pub filename: Option<String>,
     /// Timestamp of when item was added to the dataset
     pub uploaded_date: Option<String>,
-    /// Current status of the dataset
-    pub status: Option<DatasetItemStatus>,
+    /// Current status of the item
+    pub status: Option<MaybeKnown<DatasetItemStatus>>,
     /// Timestamp of when item was first entered into a workflow
     pub workflow_start_date: Option<String>,
     /// Timestamp of when work on the item was completed. null if in progress
@@ -329,6 +1415,156 @@ pub struct ItemReport {
     pub url: Option<String>,
 }
 
+#[cfg(feature = "timestamps")]
+impl ItemReport {
+    /// `uploaded_date` parsed, the space separated format of the reports is
+    /// handled by `Timestamp`
+    pub fn uploaded_timestamp(&self) -> Option<Timestamp> {
+        Timestamp::parse_optional(&self.uploaded_date)
+    }
+
+    /// `workflow_start_date` parsed, see `uploaded_timestamp`
+    pub fn workflow_start_timestamp(&self) -> Option<Timestamp> {
+        Timestamp::parse_optional(&self.workflow_start_date)
+    }
+
+    /// `workflow_complete_date` parsed, see `uploaded_timestamp`
+    pub fn workflow_complete_timestamp(&self) -> Option<Timestamp> {
+        Timestamp::parse_optional(&self.workflow_complete_date)
+    }
+}
+
+/// Number of annotations of a single annotation class
+#[derive(Debug, Default, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ClassCount {
+    pub class_id: Option<u32>,
+    pub class_name: Option<String>,
+    pub count: u64,
+}
+
+/// Number of annotations of a single annotation class made by an annotator
+#[derive(Debug, Default, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct AnnotatorClassCount {
+    pub user_id: Option<u32>,
+    pub email: Option<String>,
+    pub class_id: Option<u32>,
+    pub class_name: Option<String>,
+    pub count: u64,
+}
+
+/// Distribution of the annotations within a dataset across annotation classes.
+/// Counts are sorted in descending order.
+#[derive(Debug, Default, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ClassDistribution {
+    pub classes: Vec<ClassCount>,
+    pub annotators: Vec<AnnotatorClassCount>,
+}
+
+impl ClassDistribution {
+    /// Aggregates the annotations of many items, naming classes from `class_names`
+    /// (class id to class name) and annotators from `emails` (user id to email).
+    pub fn from_annotations<'a, I>(
+        annotations: I,
+        class_names: &HashMap<u32, String>,
+        emails: &HashMap<u32, String>,
+    ) -> Self
+    where
+        I: IntoIterator<Item = &'a ItemAnnotation>,
+    {
+        let mut tally = ClassTally::default();
+        for annotation in annotations {
+            tally.add(annotation);
+        }
+        tally.into_distribution(class_names, emails)
+    }
+}
+
+/// Running counts of annotations per class and per annotator and class, so that the
+/// annotations of a dataset can be counted an item at a time without keeping them all
+#[derive(Debug, Default)]
+struct ClassTally {
+    classes: HashMap<Option<u32>, u64>,
+    annotators: HashMap<(Option<u32>, Option<u32>), u64>,
+}
+
+impl ClassTally {
+    fn add(&mut self, annotation: &ItemAnnotation) {
+        *self
+            .classes
+            .entry(annotation.annotation_class_id)
+            .or_default() += 1;
+        for actor in annotation
+            .actors
+            .iter()
+            .filter(|actor| actor.role.as_deref() != Some("reviewer"))
+        {
+            *self
+                .annotators
+                .entry((actor.user_id, annotation.annotation_class_id))
+                .or_default() += 1;
+        }
+    }
+
+    fn into_distribution(
+        self,
+        class_names: &HashMap<u32, String>,
+        emails: &HashMap<u32, String>,
+    ) -> ClassDistribution {
+        let Self {
+            classes,
+            annotators,
+        } = self;
+        let class_name = |id: &Option<u32>| id.and_then(|id| class_names.get(&id).cloned());
+        let mut classes: Vec<ClassCount> = classes
+            .into_iter()
+            .map(|(class_id, count)| ClassCount {
+                class_id,
+                class_name: class_name(&class_id),
+                count,
+            })
+            .collect();
+        classes.sort_by(|a, b| b.count.cmp(&a.count).then(a.class_id.cmp(&b.class_id)));
+
+        let mut annotators: Vec<AnnotatorClassCount> = annotators
+            .into_iter()
+            .map(|((user_id, class_id), count)| AnnotatorClassCount {
+                user_id,
+                email: user_id.and_then(|id| emails.get(&id).cloned()),
+                class_id,
+                class_name: class_name(&class_id),
+                count,
+            })
+            .collect();
+        annotators.sort_by(|a, b| {
+            b.count
+                .cmp(&a.count)
+                .then(a.user_id.cmp(&b.user_id))
+                .then(a.class_id.cmp(&b.class_id))
+        });
+
+        ClassDistribution {
+            classes,
+            annotators,
+        }
+    }
+}
+
+#[cfg(feature = "reports")]
+/// Parses item reports from a CSV reader one record at a time
+pub fn item_reports_from_reader<R>(reader: R) -> BoxStream<'static, Result<ItemReport>>
+where
+    R: AsyncRead + Unpin + Send + 'static,
+{
+    AsyncReaderBuilder::new()
+        .delimiter(b',')
+        .has_headers(true)
+        .create_deserializer(reader)
+        .into_deserialize::<ItemReport>()
+        .map(|record| Ok(record?))
+        .boxed()
+}
+
+#[cfg(feature = "reports")]
 pub async fn item_reports_from_bytes(contents: &[u8]) -> Result<Vec<ItemReport>> {
     let cursor = Cursor::new(contents);
     let mut rdr = AsyncReaderBuilder::new()
@@ -347,66 +1583,352 @@ pub async fn item_reports_from_bytes(contents: &[u8]) -> Result<Vec<ItemReport>>
     Ok(results)
 }
 
-impl Dataset {
-    #[allow(dead_code)]
-    pub async fn create_dataset<C>(client: &C, name: &str) -> Result<Dataset>
-    where
-        C: V7Methods,
-    {
-        let response = client
-            .post(
-                "datasets",
-                &DatasetName {
-                    name: name.to_string(),
-                },
-            )
-            .await?;
-
-        expect_http_ok!(response, Dataset)
-    }
+#[cfg(feature = "reports")]
+/// Parses the item reports of a CSV file, e.g. one downloaded from V7 or written by
+/// `item_reports_to_csv`, a record at a time with `item_reports_from_reader`. Note that
+/// the file is read with blocking IO so that the reports feature does not tie the crate
+/// to a runtime.
+pub async fn item_reports_from_path(path: &Path) -> Result<Vec<ItemReport>> {
+    let file =
+        std::fs::File::open(path).with_context(|| format!("Unable to read {}", path.display()))?;
+    item_reports_from_reader(futures::io::AllowStdIo::new(file))
+        .try_collect()
+        .await
 }
 
-#[async_trait]
-pub trait DatasetArchiveMethods<C>
+#[cfg(feature = "reports")]
+/// Writes item reports as CSV with the same header as the reports generated by V7
+pub async fn item_reports_to_csv<W>(writer: W, reports: &[ItemReport]) -> Result<()>
 where
-    C: V7Methods,
+    W: futures::io::AsyncWrite + Unpin + Send,
 {
-    async fn archive_items(&self, client: &C, filter: &Filter) -> Result<ArchiveResponseItems>;
-    async fn archive_dataset(&self, client: &C) -> Result<Dataset>;
+    crate::analytics::write_csv(writer, reports).await
 }
 
-#[async_trait]
-pub trait DatasetDataMethods<C>
-where
-    C: V7Methods,
-{
-    async fn assign_items(&self, client: &C, assignee_id: &u32, filter: &Filter) -> Result<()>;
-    async fn update_batch_size(&self, client: &C, size: &u32) -> Result<()>;
-    #[deprecated = "V2 of the V7 API requires use of `register_items_to_dataset`"]
-    async fn add_data_to_dataset(
-        &self,
-        client: &C,
-        data: Vec<AddDataPayload>,
-        external_storage: String,
-    ) -> Result<AddDataItemsResponse>;
-    async fn register_items_to_dataset(
-        &self,
-        client: &C,
-        data: Vec<ExistingSimpleItem>,
-        external_storage: String,
-    ) -> Result<RegisterExistingItemResponse>;
+/// Format of the rows written by `DatasetItemReportMethods::dump_items`
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ItemDumpFormat {
+    /// CSV with a header row
+    #[default]
+    Csv,
+    /// One JSON object per line
+    Jsonl,
+}
 
-    async fn update_annotation_hotkeys(
-        &self,
-        client: &C,
-        hotkeys: HashMap<String, String>,
-    ) -> Result<()>;
+#[cfg(all(feature = "client", feature = "reports"))]
+/// Writes rows in either of the `ItemDumpFormat`s
+enum RowWriter<W: futures::io::AsyncWrite + Unpin + Send> {
+    Csv(Box<csv_async::AsyncSerializer<W>>),
+    Jsonl(W),
+}
 
-    /// Asynchronously imports an annotation into this dataset.
-    ///
-    /// This function takes a reference to a client, an item ID, and an annotation import object,
-    /// and attempts to add the annotation to the specified item in the dataset. The operation
-    /// is performed asynchronously.
+#[cfg(all(feature = "client", feature = "reports"))]
+impl<W: futures::io::AsyncWrite + Unpin + Send> RowWriter<W> {
+    fn new(writer: W, format: ItemDumpFormat) -> Self {
+        match format {
+            ItemDumpFormat::Csv => {
+                Self::Csv(Box::new(csv_async::AsyncSerializer::from_writer(writer)))
+            }
+            ItemDumpFormat::Jsonl => Self::Jsonl(writer),
+        }
+    }
+
+    async fn write<T: Serialize>(&mut self, row: &T) -> Result<()> {
+        match self {
+            Self::Csv(serializer) => serializer.serialize(row).await?,
+            Self::Jsonl(writer) => {
+                let mut line = serde_json::to_vec(row)?;
+                line.push(b'\n');
+                writer.write_all(&line).await?;
+            }
+        }
+        Ok(())
+    }
+
+    async fn flush(&mut self) -> Result<()> {
+        match self {
+            Self::Csv(serializer) => serializer.flush().await?,
+            Self::Jsonl(writer) => writer.flush().await?,
+        }
+        Ok(())
+    }
+}
+
+#[cfg(feature = "client")]
+/// Number of items requested per page when following the v2 item cursors
+const ITEM_PAGE_SIZE: u32 = 500;
+#[cfg(all(feature = "client", feature = "reports"))]
+/// Number of items whose annotations are listed at once by
+/// `DatasetItemReportMethods::class_distribution`
+const ANNOTATION_LIST_CONCURRENCY: usize = 8;
+#[cfg(feature = "client")]
+/// Number of storage keys sent in each items query, keeping the urls short
+const STORAGE_KEY_CHUNK_SIZE: usize = 100;
+
+/// Options of `DatasetDescribeMethods::list_dataset_items_with_options`
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct ItemListOptions {
+    /// Embeds the workflow data, current stage and assignees of each item
+    pub include_workflow_data: bool,
+    /// Embeds the first sections of each slot, e.g. for thumbnails of videos
+    pub include_first_sections: bool,
+    /// Only items with one of these statuses, any status when empty
+    pub statuses: Vec<DatasetItemStatus>,
+    /// Only items whose path starts with this prefix, e.g. `/scans` also matches the
+    /// items of `/scans-old`
+    pub path_prefix: Option<String>,
+    /// Only items whose name starts with this prefix
+    pub name_prefix: Option<String>,
+    /// Stops fetching pages once this many items have been retrieved
+    pub limit: Option<usize>,
+}
+
+#[cfg(feature = "client")]
+impl ItemListOptions {
+    fn query(&self, dataset_id: u32) -> String {
+        let mut query = vec![("dataset_ids", dataset_id.to_string())];
+        if self.include_workflow_data {
+            query.push(("include_workflow_data", "true".to_string()));
+        }
+        if self.include_first_sections {
+            query.push(("include_first_sections", "true".to_string()));
+        }
+        query.extend(
+            self.statuses
+                .iter()
+                .map(|status| ("statuses[]", status.to_string().to_lowercase())),
+        );
+        if let Some(prefix) = &self.path_prefix {
+            query.push(("item_path_prefix", prefix.clone()));
+        }
+        if let Some(prefix) = &self.name_prefix {
+            query.push(("item_name_prefix", prefix.clone()));
+        }
+        encode_query(&query)
+    }
+}
+
+#[cfg(feature = "client")]
+/// Retrieves the page of at most `page_size` items matching `query` starting at `cursor`
+async fn fetch_item_page<C>(
+    client: &C,
+    team_slug: &str,
+    query: &str,
+    page_size: u32,
+    cursor: Option<&str>,
+) -> Result<Item>
+where
+    C: V7Methods + std::marker::Sync,
+{
+    let endpoint = format!("v2/teams/{team_slug}/items?{query}");
+    fetch_page(client, &endpoint, page_size, cursor).await
+}
+
+#[cfg(feature = "client")]
+/// Lists every item matching `query` by following the `page.next` cursor
+/// until all pages of the v2 items endpoint have been retrieved, or `limit` items
+async fn list_item_pages<C>(
+    client: &C,
+    team_slug: &str,
+    query: &str,
+    limit: Option<usize>,
+    progress: &dyn ProgressSink,
+) -> Result<Vec<DatasetItemV2>>
+where
+    C: V7Methods + std::marker::Sync,
+{
+    let mut items: Vec<DatasetItemV2> = Vec::new();
+    let mut cursor: Option<String> = None;
+    progress.event(ProgressEvent::Started {
+        operation: "item listing",
+        total: None,
+    });
+
+    loop {
+        // Only ask for as many items as are still needed to satisfy the limit
+        let page_size = limit.map_or(ITEM_PAGE_SIZE, |limit| {
+            ITEM_PAGE_SIZE.min(limit.saturating_sub(items.len()) as u32)
+        });
+        if page_size == 0 {
+            break;
+        }
+        let page = fetch_item_page(client, team_slug, query, page_size, cursor.as_deref()).await?;
+
+        let page_len = page.items.len();
+        items.extend(page.items.into_iter().flatten());
+        if let Some(limit) = limit {
+            items.truncate(limit);
+        }
+        progress.event(ProgressEvent::ItemsProcessed {
+            done: items.len() as u64,
+            total: None,
+        });
+
+        cursor = page.page.next;
+        if cursor.is_none() || page_len == 0 {
+            break;
+        }
+    }
+
+    progress.event(ProgressEvent::Finished {
+        operation: "item listing",
+    });
+    Ok(items)
+}
+
+#[cfg(feature = "client")]
+/// Streams every item matching `query`, fetching the page of the next `page.next` cursor
+/// once the items of the previous page have been consumed
+fn stream_item_pages<C>(
+    client: &C,
+    team_slug: String,
+    query: String,
+) -> BoxStream<'_, Result<DatasetItemV2>>
+where
+    C: V7Methods + std::marker::Sync,
+{
+    // The cursor of the next page, `None` once the last page has been fetched
+    futures::stream::try_unfold(Some(None::<String>), move |cursor| {
+        let (team_slug, query) = (team_slug.clone(), query.clone());
+        async move {
+            let Some(cursor) = cursor else {
+                return anyhow::Ok(None);
+            };
+            let page = fetch_item_page(
+                client,
+                &team_slug,
+                &query,
+                ITEM_PAGE_SIZE,
+                cursor.as_deref(),
+            )
+            .await?;
+            let next = page.page.next.filter(|_| !page.items.is_empty());
+            let items = futures::stream::iter(page.items.into_iter().flatten().map(Ok));
+            Ok(Some((items, next.map(Some))))
+        }
+    })
+    .try_flatten()
+    .boxed()
+}
+
+#[cfg(feature = "client")]
+impl Dataset {
+    #[allow(dead_code)]
+    pub async fn create_dataset<C>(client: &C, name: &str) -> Result<Dataset>
+    where
+        C: V7Methods,
+    {
+        let response = client
+            .post(
+                "datasets",
+                &DatasetName {
+                    name: name.to_string(),
+                },
+            )
+            .await?;
+
+        expect_http_ok!(response, Dataset)
+    }
+}
+
+#[cfg(feature = "client")]
+#[async_trait]
+pub trait DatasetArchiveMethods<C>
+where
+    C: V7Methods,
+{
+    async fn archive_items(&self, client: &C, filter: &Filter) -> Result<ArchiveResponseItems>;
+    /// Restores the archived items matching `filter`, undoing `archive_items`
+    async fn restore_items(&self, client: &C, filter: &Filter) -> Result<ArchiveResponseItems>;
+    async fn archive_dataset(&self, client: &C) -> Result<Dataset>;
+}
+
+#[cfg(feature = "client")]
+#[async_trait]
+pub trait DatasetDataMethods<C>
+where
+    C: V7Methods,
+{
+    /// Assigns the items of a v1 workflow, fails with an `UnsupportedApiVersionError`
+    /// for V2 clients
+    async fn assign_items(&self, client: &C, assignee_id: &UserId, filter: &Filter) -> Result<()>;
+    async fn update_batch_size(&self, client: &C, size: &u32) -> Result<()>;
+    /// Sets the order in which items are served to annotators
+    async fn set_work_prioritization(
+        &self,
+        client: &C,
+        prioritization: WorkPrioritization,
+    ) -> Result<()>;
+    /// Sets whether anyone can assign an item to more than one user
+    async fn set_double_assignment(&self, client: &C, enabled: bool) -> Result<()>;
+    async fn set_annotators_can_create_tags(&self, client: &C, enabled: bool) -> Result<()>;
+    /// Replaces the annotation instructions (HTML) of the dataset
+    async fn set_instructions(&self, client: &C, instructions: &str) -> Result<()>;
+    /// Uploads an image to be embedded in the instructions and returns the url it is hosted at.
+    ///
+    /// `upload_client` should not be the `V7Client` as the upload url is pre-signed and
+    /// is rejected if an authorization header is sent. Note that the file is read with
+    /// blocking IO so that the client feature does not tie the crate to a runtime.
+    async fn upload_instruction_attachment(
+        &self,
+        client: &C,
+        upload_client: &dyn PresignedUrlMethods,
+        path: &Path,
+    ) -> Result<String>;
+    /// Uploads an image with `upload_instruction_attachment` and appends it to the end
+    /// of the instructions, returning the hosted url
+    async fn add_instruction_image(
+        &self,
+        client: &C,
+        upload_client: &dyn PresignedUrlMethods,
+        path: &Path,
+    ) -> Result<String>;
+    #[deprecated = "V2 of the V7 API requires use of `register_items_to_dataset`"]
+    async fn add_data_to_dataset(
+        &self,
+        client: &C,
+        data: Vec<AddDataPayload>,
+        external_storage: String,
+    ) -> Result<AddDataItemsResponse>;
+    async fn register_items_to_dataset(
+        &self,
+        client: &C,
+        data: Vec<ExistingSimpleItem>,
+        external_storage: String,
+    ) -> Result<RegisterExistingItemResponse>;
+    /// As `register_items_to_dataset`, first removing the items that are duplicates under
+    /// `key` according to `policy`, see `RegistrationItems::deduplicate`. Returns the
+    /// duplicates found along with the response, nothing is sent if `policy` rejects them.
+    async fn register_items_deduplicated(
+        &self,
+        client: &C,
+        data: Vec<ExistingSimpleItem>,
+        external_storage: String,
+        key: DuplicateKey,
+        policy: DuplicatePolicy,
+    ) -> Result<(RegisterExistingItemResponse, DuplicatesReport)>;
+    /// Registers items from either read-only or read-write external storage,
+    /// the endpoint used is selected by the `RegistrationItems` variant.
+    async fn register_existing_items(
+        &self,
+        client: &C,
+        items: RegistrationItems,
+        external_storage_slug: String,
+    ) -> Result<RegistrationResponse>;
+
+    async fn update_annotation_hotkeys(&self, client: &C, hotkeys: AnnotationHotKeys)
+        -> Result<()>;
+
+    /// Re-processes the items of this dataset matching `filter` from external storage,
+    /// refreshing thumbnails and metadata after the underlying objects were replaced
+    async fn resync_items(&self, client: &C, filter: &Filter) -> Result<()>;
+
+    /// Asynchronously imports an annotation into this dataset.
+    ///
+    /// This function takes a reference to a client, an item ID, and an annotation import object,
+    /// and attempts to add the annotation to the specified item in the dataset. The operation
+    /// is performed asynchronously.
     ///
     /// # Arguments
     /// * `client` - A reference to the client used to access V7.
@@ -416,11 +1938,27 @@ where
     async fn import_annotation(
         &self,
         client: &C,
-        item_id: &str,
+        item_id: &ItemId,
         annotation_import: &AnnotationImport,
     ) -> Result<()>;
+
+    /// Imports the annotations into the item without duplicating those already on it,
+    /// for imports that may be run more than once e.g. with ids from `stable_id`.
+    ///
+    /// Annotations whose id is already on the item are deleted and imported again or
+    /// left out according to their `on_existing`, the other annotations of the item are
+    /// kept unless `overwrite` is set. The deletes and the import are separate requests
+    /// so a failed import can leave replaced annotations deleted, running it again
+    /// completes it. Returns the plan that was imported.
+    async fn import_annotation_idempotent(
+        &self,
+        client: &C,
+        item_id: &ItemId,
+        annotation_import: &AnnotationImport,
+    ) -> Result<AnnotationImportPlan>;
 }
 
+#[cfg(feature = "client")]
 #[async_trait]
 pub trait DatasetExportMethods<C>
 where
@@ -435,46 +1973,198 @@ where
         include_export_token: bool,
         filter: Option<&Filter>,
     ) -> Result<()>;
+    /// Checks that the dataset has no items of a type `format` does not support, failing
+    /// with an `UnsupportedExportFormat` if it does. The items are filtered by type in V7,
+    /// with a request for a single item of each unsupported type, rather than listed
+    async fn check_export_format(&self, client: &C, format: &ExportFormat) -> Result<()>;
+    /// As `generate_export`, calling `check_export_format` first. The whole dataset is
+    /// checked even if `filter` would leave out the unsupported items.
+    async fn generate_checked_export(
+        &self,
+        client: &C,
+        export_name: &'life2 str,
+        format: &ExportFormat,
+        include_authorship: bool,
+        include_export_token: bool,
+        filter: Option<&Filter>,
+    ) -> Result<()>;
     async fn list_exports(&self, client: &C) -> Result<Vec<Option<Export>>>;
+    /// As `list_exports`, parsing each export as it is downloaded
+    async fn stream_exports(&self, client: &C) -> Result<BoxStream<'static, Result<Export>>>;
+    /// Polls the exports of the dataset every `poll_interval` until `export_name` is complete,
+    /// giving up once `deadline` has elapsed if provided. A last poll is made at the
+    /// deadline, which is given up to `poll_interval` to answer.
+    async fn wait_for_export(
+        &self,
+        client: &C,
+        export_name: &str,
+        poll_interval: Duration,
+        deadline: Option<Duration>,
+    ) -> Result<Export>;
+    /// Subscribes `url` to the `ExportFinished` events of the dataset so that exports can be
+    /// awaited by parsing the calls with `webhooks::parse_export_finished` rather than with
+    /// `wait_for_export`. An existing subscription of `url` covering the dataset is reused,
+    /// so this may be called before each `generate_export`.
+    ///
+    /// `authorization_header` is sent verbatim with each call, check it with
+    /// `webhooks::verify_authorization`. It is only set on a new subscription, the header
+    /// of an existing one is not compared. The subscription is kept once the export finishes.
+    async fn register_export_callback(
+        &self,
+        client: &C,
+        url: &str,
+        authorization_header: Option<&str>,
+    ) -> Result<WebhookSubscription>;
 }
 
+#[cfg(feature = "client")]
 #[async_trait]
 pub trait DatasetDescribeMethods<C>
 where
     C: V7Methods,
 {
     async fn list_datasets(client: &C) -> Result<Vec<Option<Dataset>>>;
+    /// As `list_datasets`, parsing only the identifying fields of each dataset which is
+    /// faster for large teams and unaffected by changes to the other fields
+    async fn list_dataset_summaries(client: &C) -> Result<Vec<DatasetSummary>>;
+    /// As `list_datasets`, parsing each dataset as it is downloaded so memory use stays
+    /// bounded for teams with many datasets
+    async fn stream_datasets(client: &C) -> Result<BoxStream<'static, Result<Dataset>>>;
     async fn list_dataset_items_v2(&self, client: &C) -> Result<Item>;
-    async fn show_dataset(client: &C, id: &u32) -> Result<Dataset>;
+    /// Lists all items in the dataset, following the pagination cursors
+    /// until every page has been retrieved
+    async fn list_all_dataset_items_v2(&self, client: &C) -> Result<Vec<DatasetItemV2>>;
+    /// As `list_all_dataset_items_v2`, fetching the next page only once the items of the
+    /// previous page have been consumed so memory use stays bounded for large datasets
+    fn stream_dataset_items_v2<'a>(&'a self, client: &'a C)
+        -> BoxStream<'a, Result<DatasetItemV2>>;
+    /// As `list_all_dataset_items_v2`, reporting the items retrieved after each page
+    async fn list_all_dataset_items_v2_with_progress(
+        &self,
+        client: &C,
+        progress: &dyn ProgressSink,
+    ) -> Result<Vec<DatasetItemV2>>;
+    /// Lists the items in the dataset matching `options`, with the embeds it requests
+    async fn list_dataset_items_with_options(
+        &self,
+        client: &C,
+        options: &ItemListOptions,
+    ) -> Result<Vec<DatasetItemV2>>;
+    async fn show_dataset(client: &C, id: &DatasetId) -> Result<Dataset>;
+    /// Re-fetches the dataset, replacing all of its fields e.g. to update the item counts
+    async fn refresh(&mut self, client: &C) -> Result<()>;
+    /// Finds the item of the dataset with exactly `name`, filtering by name in V7
+    /// rather than listing every item
+    async fn find_item_by_name(&self, client: &C, name: &str) -> Result<Option<DatasetItemV2>>;
+    /// Finds the items of the dataset with a slot registered from any of the storage `keys`
+    async fn find_items_by_storage_key(
+        &self,
+        client: &C,
+        keys: &[String],
+    ) -> Result<Vec<DatasetItemV2>>;
 }
 
+#[cfg(feature = "client")]
 #[async_trait]
 pub trait DatasetWorkflowMethods<C>
 where
     C: V7Methods,
 {
+    /// Moves the items of a v1 workflow back to new, fails with an
+    /// `UnsupportedApiVersionError` for V2 clients
     async fn reset_to_new(&self, client: &C, filter: &Filter) -> Result<()>;
+    /// Creates the workflow as is, it is not validated. Use `WorkflowBuilder::validate`
+    /// first to report invalid references to datasets, members or models rather than an
+    /// opaque 422
     async fn set_workflow_v2(&self, client: &C, workflow: &WorkflowBuilder) -> Result<WorkflowV2>;
     async fn get_workflow_v2(&self, client: &C) -> Result<Option<WorkflowV2>>;
     async fn set_stage_v2(
         &self,
         client: &C,
-        stage_id: String,
-        workflow_id: String,
+        stage_id: StageId,
+        workflow_id: WorkflowId,
         filters: Option<SetStageFilter>,
     ) -> Result<SetStageResponse>;
+    /// As `set_stage_v2`, splitting the `item_ids` of `filters` into stage commands of
+    /// at most `chunk_size` items, `SET_STAGE_CHUNK_SIZE` by default, to stay within the
+    /// payload limits of V7. Filters without item ids are sent as a single command.
+    ///
+    /// Every chunk is attempted, the chunks V7 rejected are reported in `failures` rather
+    /// than failing the whole transition.
+    async fn set_stage_v2_chunked(
+        &self,
+        client: &C,
+        stage_id: StageId,
+        workflow_id: WorkflowId,
+        filters: SetStageFilter,
+        chunk_size: Option<usize>,
+    ) -> Result<ChunkedSetStageResponse>;
+
+    /// Retrieves the current stage, assignee and stage entry time of every item in the
+    /// dataset. Combine with `workflow::summarize_stage_times` or
+    /// `workflow::items_stuck_in_stage` to monitor items sitting in a stage.
+    async fn get_stage_times(&self, client: &C) -> Result<Vec<ItemStageTime>>;
+
+    /// Counts the items of the dataset matching `filter` in each stage of its workflow,
+    /// see `workflow::count_items_by_stage`, to find the stages where work is piling up.
+    /// Only the items matching `filter` are listed.
+    async fn get_stage_counts(&self, client: &C, filter: &StageCountFilter) -> Result<StageCounts>;
+
+    /// Lists the v1 workflow templates of the dataset, see `default_workflow_template_id`
+    /// for the template in use.
+    ///
+    /// This and the other v1 workflow methods fail with an `UnsupportedApiVersionError`
+    /// when the client is for a V2 team, see `V7ClientBuilder::api_version`.
+    async fn list_workflow_templates(&self, client: &C) -> Result<Vec<WorkflowTemplate>>;
+    async fn get_workflow_template(
+        client: &C,
+        template_id: WorkflowTemplateId,
+    ) -> Result<WorkflowTemplate>;
+    /// Sets the v1 workflow template new items of the dataset enter, failing before the
+    /// update if the template is not one of `list_workflow_templates`
+    async fn set_default_workflow_template(
+        &self,
+        client: &C,
+        template_id: WorkflowTemplateId,
+    ) -> Result<()>;
+    /// Moves the items matching `filter` to a stage of a v1 workflow template
+    async fn set_stage(
+        &self,
+        client: &C,
+        workflow_stage_template_id: WorkflowStageTemplateId,
+        filter: &Filter,
+    ) -> Result<()>;
 }
 
+#[cfg(all(feature = "client", feature = "reports"))]
 #[async_trait]
 pub trait DatasetItemReportMethods<C>
 where
     C: V7Methods,
 {
     async fn get_item_reports(&self, client: &C) -> Result<Vec<ItemReport>>;
+    /// Streams the item reports of the dataset, parsing the CSV as it is downloaded so
+    /// memory use stays bounded for very large datasets
+    async fn stream_item_reports(
+        &self,
+        client: &C,
+    ) -> Result<BoxStream<'static, Result<ItemReport>>>;
+    /// Counts the annotations per class, and per annotator and class, across all items
+    /// of the dataset using the item annotations endpoint rather than a full export.
+    /// The annotations of a few items are listed at once, one request per item, and
+    /// counted as they arrive so memory use does not grow with the dataset.
+    async fn class_distribution(&self, client: &C) -> Result<ClassDistribution>;
+    /// Writes every item of the dataset to `writer` as an `ItemRow`, returning the number
+    /// of items written. The items are written a page at a time as they are listed, so
+    /// memory use stays bounded for very large datasets.
+    async fn dump_items<W>(&self, client: &C, writer: W, format: ItemDumpFormat) -> Result<u64>
+    where
+        W: futures::io::AsyncWrite + Unpin + Send;
 }
 
+#[cfg(feature = "client")]
 #[async_trait]
-impl<C> DatasetArchiveMethods<C> for Dataset
+impl<C> DatasetArchiveMethods<C> for DatasetRef
 where
     C: V7Methods + std::marker::Sync,
 {
@@ -485,44 +2175,88 @@ where
             filters: filter.clone(),
         };
 
-        let endpoint = &format!(
-            "v2/teams/{}/items/archive",
-            self.team_slug
-                .as_ref()
-                .context("Dataset is missing team slug")?
-        );
+        let endpoint = &format!("v2/teams/{}/items/archive", self.team_slug);
+        let response = client.post(endpoint, &payload).await?;
+        expect_http_ok!(response, ArchiveResponseItems)
+    }
+
+    async fn restore_items(&self, client: &C, filter: &Filter) -> Result<ArchiveResponseItems> {
+        let payload = ArchiveItemPayload {
+            filters: filter.clone(),
+        };
+
+        let endpoint = &format!("v2/teams/{}/items/restore", self.team_slug);
         let response = client.post(endpoint, &payload).await?;
         expect_http_ok!(response, ArchiveResponseItems)
     }
 
     async fn archive_dataset(&self, client: &C) -> Result<Dataset> {
         let response = client
-            .put::<String>(
-                &format!("datasets/{}/archive", &self.id.context("Id required")?),
-                None,
-            )
+            .put::<String>(&format!("datasets/{}/archive", self.id), None)
             .await?;
 
         expect_http_ok!(response, Dataset)
     }
 }
 
+#[cfg(feature = "client")]
 #[async_trait]
-impl<C> DatasetDataMethods<C> for Dataset
+impl<C> DatasetArchiveMethods<C> for Dataset
+where
+    C: V7Methods + std::marker::Sync,
+{
+    async fn archive_items(&self, client: &C, filter: &Filter) -> Result<ArchiveResponseItems> {
+        DatasetRef::try_from(self)?
+            .archive_items(client, filter)
+            .await
+    }
+
+    async fn restore_items(&self, client: &C, filter: &Filter) -> Result<ArchiveResponseItems> {
+        DatasetRef::try_from(self)?
+            .restore_items(client, filter)
+            .await
+    }
+
+    async fn archive_dataset(&self, client: &C) -> Result<Dataset> {
+        DatasetRef::try_from(self)?.archive_dataset(client).await
+    }
+}
+
+#[cfg(feature = "client")]
+/// Imports annotations into an item of a team, which identifies the dataset of the item
+pub(crate) async fn import_item_annotations<C>(
+    client: &C,
+    team_slug: &str,
+    item_id: &ItemId,
+    annotation_import: &AnnotationImport,
+) -> Result<()>
+where
+    C: V7Methods + std::marker::Sync,
+{
+    let endpoint = format!("v2/teams/{team_slug}/items/{item_id}/import");
+    let response = client.post(&endpoint, annotation_import).await?;
+    let status = response.status();
+    if status != 200 {
+        bail!("Import Annotation: Invalid status code {status}");
+    }
+    Ok(())
+}
+
+#[cfg(feature = "client")]
+#[async_trait]
+impl<C> DatasetDataMethods<C> for DatasetRef
 where
     C: V7Methods + std::marker::Sync,
 {
-    async fn assign_items(&self, client: &C, assignee_id: &u32, filter: &Filter) -> Result<()> {
+    async fn assign_items(&self, client: &C, assignee_id: &UserId, filter: &Filter) -> Result<()> {
+        require_v1(client, "assign_items")?;
         let payload = AssignItemPayload {
-            assignee_id: *assignee_id,
+            assignee_id: assignee_id.0,
             filter: filter.clone(),
         };
 
         let response = client
-            .post(
-                &format!("datasets/{}/assign_items", self.id.context("Id required")?),
-                &payload,
-            )
+            .post(&format!("datasets/{}/assign_items", self.id), &payload)
             .await?;
 
         let status = response.status();
@@ -535,25 +2269,100 @@ where
         Ok(())
     }
 
+    /// The update requires every setting of the dataset, which is fetched first
     async fn update_batch_size(&self, client: &C, size: &u32) -> Result<()> {
-        let mut payload = DatasetUpdate::from(self);
-        payload.work_size = Some(*size); // this PUT path requires every parameter
-                                         // even if we're not updating them
-                                         // so we have to replicate the rest of the existing settings
+        Dataset::show_dataset(client, &self.id)
+            .await?
+            .update_batch_size(client, size)
+            .await
+    }
+
+    async fn set_work_prioritization(
+        &self,
+        client: &C,
+        prioritization: WorkPrioritization,
+    ) -> Result<()> {
+        update_dataset_settings(&self.id, client, |settings| {
+            settings.work_prioritization = Some(MaybeKnown::Known(prioritization))
+        })
+        .await
+    }
+
+    async fn set_double_assignment(&self, client: &C, enabled: bool) -> Result<()> {
+        update_dataset_settings(&self.id, client, |settings| {
+            settings.anyone_can_double_assign = Some(enabled)
+        })
+        .await
+    }
+
+    async fn set_annotators_can_create_tags(&self, client: &C, enabled: bool) -> Result<()> {
+        update_dataset_settings(&self.id, client, |settings| {
+            settings.annotators_can_create_tags = Some(enabled)
+        })
+        .await
+    }
+
+    async fn set_instructions(&self, client: &C, instructions: &str) -> Result<()> {
+        update_dataset_settings(&self.id, client, |settings| {
+            settings.instructions = Some(instructions.to_string())
+        })
+        .await
+    }
+
+    async fn upload_instruction_attachment(
+        &self,
+        client: &C,
+        upload_client: &dyn PresignedUrlMethods,
+        path: &Path,
+    ) -> Result<String> {
+        let content_type = attachment_content_type(path)?;
+        let contents =
+            std::fs::read(path).with_context(|| format!("Unable to read {}", path.display()))?;
+        let payload = InstructionAttachmentPayload {
+            file_name: path
+                .file_name()
+                .and_then(|name| name.to_str())
+                .context("Attachment is missing a file name")?
+                .to_string(),
+            content_type,
+        };
 
         let response = client
-            .put(
-                &format!("datasets/{}", self.id.context("Id required")?),
-                Some(&payload),
+            .post(
+                &format!("datasets/{}/instructions/attachments", self.id),
+                &payload,
             )
             .await?;
-        let status = response.status();
-
-        if status != 200 {
-            bail!("Invalid status code {status}");
+        let attachment: Result<InstructionAttachment> =
+            expect_http_ok!(response, InstructionAttachment);
+        let attachment = attachment?;
+
+        let mut headers = HeaderMap::new();
+        headers.insert(CONTENT_TYPE, HeaderValue::from_static(content_type));
+        let response = upload_client
+            .put_url(&attachment.upload_url, headers, contents)
+            .await?;
+        if !response.status().is_success() {
+            bail!(HttpStatusError::from_response(response).await);
         }
+        Ok(attachment.url)
+    }
 
-        Ok(())
+    async fn add_instruction_image(
+        &self,
+        client: &C,
+        upload_client: &dyn PresignedUrlMethods,
+        path: &Path,
+    ) -> Result<String> {
+        let url = self
+            .upload_instruction_attachment(client, upload_client, path)
+            .await?;
+        let image = format!(r#"<p><img src="{}"></p>"#, escape_html_attribute(&url));
+        update_dataset_settings(&self.id, client, |settings| {
+            settings.instructions = Some(settings.instructions.take().unwrap_or_default() + &image)
+        })
+        .await?;
+        Ok(url)
     }
 
     async fn add_data_to_dataset(
@@ -562,18 +2371,13 @@ where
         data: Vec<AddDataPayload>,
         external_storage: String,
     ) -> Result<AddDataItemsResponse> {
+        require_v1(client, "add_data_to_dataset")?;
         let api_payload = AddDataItemsPayload {
             items: data,
             storage_name: external_storage,
         };
 
-        let endpoint = format!(
-            "teams/{}/datasets/{}/data",
-            self.team_slug
-                .as_ref()
-                .context("Dataset is missing team slug")?,
-            self.slug.as_ref().context("Dataset is missing slug")?
-        );
+        let endpoint = format!("teams/{}/datasets/{}/data", self.team_slug, self.slug);
 
         let response = client.put(&endpoint, Some(&api_payload)).await?;
 
@@ -587,52 +2391,96 @@ where
         external_storage_slug: String,
     ) -> Result<RegisterExistingItemResponse> {
         let api_payload = RegisterExistingItemPayload {
-            dataset_slug: self
-                .slug
-                .as_ref()
-                .context("Dataset is missing slug")?
-                .to_string(),
+            dataset_slug: self.slug.clone(),
             storage_slug: external_storage_slug,
             items: data,
         };
         let endpoint = format!(
             "v2/teams/{}/items/register_existing_readonly",
             self.team_slug
-                .as_ref()
-                .context("Dataset is missing team slug")?
         );
         let response = client.post(&endpoint, &api_payload).await?;
 
         expect_http_ok!(response, RegisterExistingItemResponse)
     }
 
-    async fn update_annotation_hotkeys(
+    async fn register_items_deduplicated(
         &self,
         client: &C,
-        hotkeys: HashMap<String, String>,
-    ) -> Result<()> {
-        let mut payload = DatasetUpdate::from(self);
-        payload.annotation_hotkeys = Some(hotkeys);
-        let response = client
-            .put(
-                &format!("datasets/{}", self.id.context("Dataset is missing Id")?),
-                Some(&payload),
-            )
+        mut data: Vec<ExistingSimpleItem>,
+        external_storage_slug: String,
+        key: DuplicateKey,
+        policy: DuplicatePolicy,
+    ) -> Result<(RegisterExistingItemResponse, DuplicatesReport)> {
+        let report = deduplicate_items(&mut data, key, policy)?;
+        let response = self
+            .register_items_to_dataset(client, data, external_storage_slug)
             .await?;
-        let status = response.status();
-        if status != 200 {
-            bail!("Invalid status code {status}");
-        }
-        Ok(())
+        Ok((response, report))
     }
 
-    /// Asynchronously imports an annotation into a dataset.
-    ///
-    /// Posts `annotation_import` data to a constructed endpoint using `item_id`. Checks for
-    /// a successful 200 HTTP response status. Errors if the dataset lacks a team slug or
-    /// if the response status indicates failure.
-    ///
-    /// # Arguments
+    async fn register_existing_items(
+        &self,
+        client: &C,
+        items: RegistrationItems,
+        external_storage_slug: String,
+    ) -> Result<RegistrationResponse> {
+        let endpoint = format!("v2/teams/{}/items/{}", self.team_slug, items.endpoint());
+        let api_payload = RegistrationPayload {
+            dataset_slug: self.slug.clone(),
+            storage_slug: external_storage_slug,
+            items,
+        };
+        let response = client.post(&endpoint, &api_payload).await?;
+
+        Ok(match api_payload.items {
+            RegistrationItems::ReadOnly(_) => {
+                let result: Result<RegisterExistingItemResponse> =
+                    expect_http_ok!(response, RegisterExistingItemResponse);
+                RegistrationResponse::ReadOnly(result?)
+            }
+            RegistrationItems::ReadWrite(_) => {
+                let result: Result<RegisterReadWriteItemResponse> =
+                    expect_http_ok!(response, RegisterReadWriteItemResponse);
+                RegistrationResponse::ReadWrite(result?)
+            }
+        })
+    }
+
+    /// The update requires every setting of the dataset, which is fetched first
+    async fn update_annotation_hotkeys(
+        &self,
+        client: &C,
+        hotkeys: AnnotationHotKeys,
+    ) -> Result<()> {
+        Dataset::show_dataset(client, &self.id)
+            .await?
+            .update_annotation_hotkeys(client, hotkeys)
+            .await
+    }
+
+    async fn resync_items(&self, client: &C, filter: &Filter) -> Result<()> {
+        // Restrict the filter to this dataset so a broad filter cannot touch other datasets
+        let mut filters = filter.clone();
+        filters.dataset_ids = Some(vec![self.id]);
+
+        let endpoint = format!("v2/teams/{}/items/reprocess", self.team_slug);
+        let response = client
+            .post(&endpoint, &ResyncItemPayload { filters })
+            .await?;
+        if !response.status().is_success() {
+            bail!(HttpStatusError::from_response(response).await)
+        }
+        Ok(())
+    }
+
+    /// Asynchronously imports an annotation into a dataset.
+    ///
+    /// Posts `annotation_import` data to a constructed endpoint using `item_id`. Checks for
+    /// a successful 200 HTTP response status. Errors if the response status indicates
+    /// failure.
+    ///
+    /// # Arguments
     /// * `client` - Client for HTTP operations.
     /// * `item_id` - Identifier for the target item.
     /// * `annotation_import` - Data for the annotation to be imported.
@@ -642,27 +2490,229 @@ where
     async fn import_annotation(
         &self,
         client: &C,
-        item_id: &str,
+        item_id: &ItemId,
         annotation_import: &AnnotationImport,
     ) -> Result<()> {
+        import_item_annotations(client, &self.team_slug, item_id, annotation_import).await
+    }
+
+    async fn import_annotation_idempotent(
+        &self,
+        client: &C,
+        item_id: &ItemId,
+        annotation_import: &AnnotationImport,
+    ) -> Result<AnnotationImportPlan> {
         let endpoint = format!(
-            "v2/teams/{team_slug}/items/{item_id}/import",
-            team_slug = self.team_slug.as_ref().with_context(|| format!(
-                "Dataset is missing team slug. dataset slug: {:?}",
-                self.slug
-            ))?
+            "v2/teams/{team_slug}/items/{item_id}/annotations",
+            team_slug = self.team_slug
         );
-        let response = client.post(&endpoint, annotation_import).await?;
+        let response = client.get(&endpoint).await?;
+        let existing: Result<Vec<ItemAnnotation>> = expect_http_ok!(response, Vec<ItemAnnotation>);
+        let existing_ids: HashSet<String> = existing?
+            .into_iter()
+            .filter_map(|annotation| annotation.id)
+            .collect();
+
+        let plan = annotation_import.resolve_existing(&existing_ids);
+        if !plan.replaced.is_empty() {
+            let payload = DeleteAnnotationsPayload {
+                annotation_ids: plan.replaced.iter().map(String::as_str).collect(),
+            };
+            let response = client.delete(&endpoint, Some(&payload)).await?;
+            if !response.status().is_success() {
+                bail!(HttpStatusError::from_response(response).await);
+            }
+        }
+        if !plan.import.annotations.is_empty() {
+            self.import_annotation(client, item_id, &plan.import)
+                .await?;
+        }
+        Ok(plan)
+    }
+}
+
+#[cfg(feature = "client")]
+#[async_trait]
+impl<C> DatasetDataMethods<C> for Dataset
+where
+    C: V7Methods + std::marker::Sync,
+{
+    async fn assign_items(&self, client: &C, assignee_id: &UserId, filter: &Filter) -> Result<()> {
+        DatasetRef::try_from(self)?
+            .assign_items(client, assignee_id, filter)
+            .await
+    }
+
+    async fn update_batch_size(&self, client: &C, size: &u32) -> Result<()> {
+        let mut payload = DatasetUpdate::from(self);
+        payload.work_size = Some(*size); // this PUT path requires every parameter
+                                         // even if we're not updating them
+                                         // so we have to replicate the rest of the existing settings
+
+        let response = client
+            .put(
+                &format!("datasets/{}", self.id.context("Id required")?),
+                Some(&payload),
+            )
+            .await?;
+        let status = response.status();
+
+        if status != 200 {
+            bail!("Invalid status code {status}");
+        }
+
+        Ok(())
+    }
+
+    async fn set_work_prioritization(
+        &self,
+        client: &C,
+        prioritization: WorkPrioritization,
+    ) -> Result<()> {
+        DatasetRef::try_from(self)?
+            .set_work_prioritization(client, prioritization)
+            .await
+    }
+
+    async fn set_double_assignment(&self, client: &C, enabled: bool) -> Result<()> {
+        DatasetRef::try_from(self)?
+            .set_double_assignment(client, enabled)
+            .await
+    }
+
+    async fn set_annotators_can_create_tags(&self, client: &C, enabled: bool) -> Result<()> {
+        DatasetRef::try_from(self)?
+            .set_annotators_can_create_tags(client, enabled)
+            .await
+    }
+
+    async fn set_instructions(&self, client: &C, instructions: &str) -> Result<()> {
+        DatasetRef::try_from(self)?
+            .set_instructions(client, instructions)
+            .await
+    }
+
+    async fn upload_instruction_attachment(
+        &self,
+        client: &C,
+        upload_client: &dyn PresignedUrlMethods,
+        path: &Path,
+    ) -> Result<String> {
+        DatasetRef::try_from(self)?
+            .upload_instruction_attachment(client, upload_client, path)
+            .await
+    }
+
+    async fn add_instruction_image(
+        &self,
+        client: &C,
+        upload_client: &dyn PresignedUrlMethods,
+        path: &Path,
+    ) -> Result<String> {
+        DatasetRef::try_from(self)?
+            .add_instruction_image(client, upload_client, path)
+            .await
+    }
+
+    async fn add_data_to_dataset(
+        &self,
+        client: &C,
+        data: Vec<AddDataPayload>,
+        external_storage: String,
+    ) -> Result<AddDataItemsResponse> {
+        #[allow(deprecated)]
+        DatasetRef::try_from(self)?
+            .add_data_to_dataset(client, data, external_storage)
+            .await
+    }
+
+    async fn register_items_to_dataset(
+        &self,
+        client: &C,
+        data: Vec<ExistingSimpleItem>,
+        external_storage_slug: String,
+    ) -> Result<RegisterExistingItemResponse> {
+        DatasetRef::try_from(self)?
+            .register_items_to_dataset(client, data, external_storage_slug)
+            .await
+    }
+
+    async fn register_items_deduplicated(
+        &self,
+        client: &C,
+        data: Vec<ExistingSimpleItem>,
+        external_storage_slug: String,
+        key: DuplicateKey,
+        policy: DuplicatePolicy,
+    ) -> Result<(RegisterExistingItemResponse, DuplicatesReport)> {
+        DatasetRef::try_from(self)?
+            .register_items_deduplicated(client, data, external_storage_slug, key, policy)
+            .await
+    }
+
+    async fn register_existing_items(
+        &self,
+        client: &C,
+        items: RegistrationItems,
+        external_storage_slug: String,
+    ) -> Result<RegistrationResponse> {
+        DatasetRef::try_from(self)?
+            .register_existing_items(client, items, external_storage_slug)
+            .await
+    }
+
+    async fn update_annotation_hotkeys(
+        &self,
+        client: &C,
+        hotkeys: AnnotationHotKeys,
+    ) -> Result<()> {
+        let mut payload = DatasetUpdate::from(self);
+        payload.annotation_hotkeys = Some(hotkeys);
+        let response = client
+            .put(
+                &format!("datasets/{}", self.id.context("Dataset is missing Id")?),
+                Some(&payload),
+            )
+            .await?;
         let status = response.status();
         if status != 200 {
-            bail!("Import Annotation: Invalid status code {status}");
+            bail!("Invalid status code {status}");
         }
         Ok(())
     }
+
+    async fn resync_items(&self, client: &C, filter: &Filter) -> Result<()> {
+        DatasetRef::try_from(self)?
+            .resync_items(client, filter)
+            .await
+    }
+
+    async fn import_annotation(
+        &self,
+        client: &C,
+        item_id: &ItemId,
+        annotation_import: &AnnotationImport,
+    ) -> Result<()> {
+        DatasetRef::try_from(self)?
+            .import_annotation(client, item_id, annotation_import)
+            .await
+    }
+
+    async fn import_annotation_idempotent(
+        &self,
+        client: &C,
+        item_id: &ItemId,
+        annotation_import: &AnnotationImport,
+    ) -> Result<AnnotationImportPlan> {
+        DatasetRef::try_from(self)?
+            .import_annotation_idempotent(client, item_id, annotation_import)
+            .await
+    }
 }
 
+#[cfg(feature = "client")]
 #[async_trait]
-impl<C> DatasetExportMethods<C> for Dataset
+impl<C> DatasetExportMethods<C> for DatasetRef
 where
     C: V7Methods + std::marker::Sync,
 {
@@ -675,11 +2725,7 @@ where
         include_export_token: bool,
         filter: Option<&Filter>,
     ) -> Result<()> {
-        let endpoint = format!(
-            "v2/teams/{}/datasets/{}/exports",
-            self.team_slug.as_ref().context("Missing team slug")?,
-            self.slug.as_ref().context("Dataset is missing slug")?
-        );
+        let endpoint = format!("v2/teams/{}/datasets/{}/exports", self.team_slug, self.slug);
 
         let payload = GenerateExportPayload {
             name: export_name.to_string(),
@@ -692,202 +2738,2794 @@ where
         let response = client.post(&endpoint, &payload).await?;
 
         if response.status() != 200 {
-            bail!(format!(
-                "Invalid status code {} {}",
-                response.status(),
-                response.text().await?
-            ))
+            bail!(HttpStatusError::from_response(response).await)
         }
 
         Ok(())
     }
 
+    async fn check_export_format(&self, client: &C, format: &ExportFormat) -> Result<()> {
+        let mut present = vec![];
+        for item_type in DatasetItemTypes::ALL
+            .iter()
+            .filter(|item_type| !format.supports(item_type))
+        {
+            let query = encode_query(&[
+                ("dataset_ids", self.id.to_string()),
+                ("types[]", item_type.to_string().to_lowercase()),
+            ]);
+            let page = fetch_item_page(client, &self.team_slug, &query, 1, None).await?;
+            if page.items.iter().flatten().next().is_some() {
+                present.push(item_type);
+            }
+        }
+        format.check_item_types(present)?;
+        Ok(())
+    }
+
+    async fn generate_checked_export(
+        &self,
+        client: &C,
+        export_name: &'life2 str,
+        format: &ExportFormat,
+        include_authorship: bool,
+        include_export_token: bool,
+        filter: Option<&Filter>,
+    ) -> Result<()> {
+        self.check_export_format(client, format).await?;
+        self.generate_export(
+            client,
+            export_name,
+            format,
+            include_authorship,
+            include_export_token,
+            filter,
+        )
+        .await
+    }
+
     async fn list_exports(&self, client: &C) -> Result<Vec<Option<Export>>> {
-        let endpoint = format!(
-            "v2/teams/{}/datasets/{}/exports",
-            self.team_slug.as_ref().context("Missing team slug")?,
-            self.slug.as_ref().context("Dataset is missing slug")?
-        );
+        let endpoint = format!("v2/teams/{}/datasets/{}/exports", self.team_slug, self.slug);
 
         let response = client.get(&endpoint).await?;
 
         expect_http_ok!(response, Vec<Option<Export>>)
     }
-}
 
-#[async_trait]
-impl<C> DatasetDescribeMethods<C> for Dataset
-where
-    C: V7Methods + std::marker::Sync,
-{
-    async fn list_datasets(client: &C) -> Result<Vec<Option<Dataset>>> {
-        let response = client.get("datasets").await?;
+    async fn stream_exports(&self, client: &C) -> Result<BoxStream<'static, Result<Export>>> {
+        let endpoint = format!("v2/teams/{}/datasets/{}/exports", self.team_slug, self.slug);
 
-        expect_http_ok!(response, Vec<Option<Dataset>>)
+        let response = client.get(&endpoint).await?;
+        if response.status() != 200 {
+            bail!(HttpStatusError::from_response(response).await)
+        }
+        Ok(json_array::<Option<Export>>(response)
+            .try_filter_map(|export| async move { Ok(export) })
+            .boxed())
     }
-    async fn list_dataset_items_v2(&self, client: &C) -> Result<Item> {
-        let response = client
-            .get(&format!(
-                "v2/teams/{}/items?dataset_ids={}",
-                self.team_slug.as_ref().context("Missing team slug")?,
-                self.id.context("Dataset is missing Id")?
-            ))
-            .await?;
 
-        expect_http_ok!(response, Item)
+    async fn wait_for_export(
+        &self,
+        client: &C,
+        export_name: &str,
+        poll_interval: Duration,
+        deadline: Option<Duration>,
+    ) -> Result<Export> {
+        let start = Instant::now();
+        let timed_out = || {
+            anyhow::anyhow!(
+                "Timed out after {deadline:?} waiting for export {export_name} of {self}"
+            )
+        };
+        loop {
+            // A poll that hangs is abandoned once the deadline has passed, the last poll
+            // made at the deadline is given a poll interval to answer
+            let exports = match deadline {
+                Some(deadline) => {
+                    let remaining = deadline.saturating_sub(start.elapsed()).max(poll_interval);
+                    match futures::future::select(self.list_exports(client), Delay::new(remaining))
+                        .await
+                    {
+                        futures::future::Either::Left((exports, _)) => exports?,
+                        futures::future::Either::Right(_) => return Err(timed_out()),
+                    }
+                }
+                None => self.list_exports(client).await?,
+            };
+            let export = exports
+                .into_iter()
+                .flatten()
+                .find(|export| export.name.as_deref() == Some(export_name));
+
+            if let Some(export) = export {
+                match export.status.as_deref() {
+                    Some("complete") if export.download_url.is_some() => return Ok(export),
+                    Some("failed") => bail!("Export {export_name} of {self} failed"),
+                    _ => {}
+                }
+            }
+
+            let delay = match deadline {
+                Some(deadline) => {
+                    let elapsed = start.elapsed();
+                    if elapsed >= deadline {
+                        return Err(timed_out());
+                    }
+                    poll_interval.min(deadline - elapsed)
+                }
+                None => poll_interval,
+            };
+            debug!("Waiting for export {export_name} of {self}");
+            Delay::new(delay).await;
+        }
     }
 
-    async fn show_dataset(client: &C, id: &u32) -> Result<Dataset> {
-        let response = client.get(&format!("datasets/{}", id)).await?;
+    async fn register_export_callback(
+        &self,
+        client: &C,
+        url: &str,
+        authorization_header: Option<&str>,
+    ) -> Result<WebhookSubscription> {
+        let team = Team::new(self.team_slug.clone(), None, None, None);
+
+        let existing = team
+            .list_webhook_subscriptions(client)
+            .await?
+            .into_iter()
+            .find(|subscription| {
+                subscription.url == url
+                    && subscription.enabled != Some(false)
+                    && subscription
+                        .events
+                        .contains(&WebhookEventType::ExportFinished.into())
+                    && (subscription.dataset_ids.is_empty()
+                        || subscription.dataset_ids.contains(&self.id))
+            });
+        if let Some(subscription) = existing {
+            return Ok(subscription);
+        }
 
-        expect_http_ok!(response, Dataset)
+        debug!("Subscribing {url} to the exports of {self}");
+        let subscription = WebhookSubscription {
+            url: url.to_string(),
+            events: vec![WebhookEventType::ExportFinished.into()],
+            dataset_ids: vec![self.id],
+            authorization_header: authorization_header.map(String::from),
+            enabled: Some(true),
+            ..Default::default()
+        };
+        team.create_webhook_subscription(client, &subscription)
+            .await
     }
 }
 
+#[cfg(feature = "client")]
 #[async_trait]
-impl<C> DatasetWorkflowMethods<C> for Dataset
+impl<C> DatasetExportMethods<C> for Dataset
 where
     C: V7Methods + std::marker::Sync,
 {
-    async fn reset_to_new(&self, client: &C, filter: &Filter) -> Result<()> {
-        let payload = ResetToNewPayload {
-            filter: filter.clone(),
-        };
-
-        let response = client
-            .put(
-                &format!(
-                    "datasets/{}/items/move_to_new",
-                    self.id.context("Dataset missing Id")?
-                ),
-                Some(&payload),
+    async fn generate_export(
+        &self,
+        client: &C,
+        export_name: &'life2 str,
+        format: &ExportFormat,
+        include_authorship: bool,
+        include_export_token: bool,
+        filter: Option<&Filter>,
+    ) -> Result<()> {
+        DatasetRef::try_from(self)?
+            .generate_export(
+                client,
+                export_name,
+                format,
+                include_authorship,
+                include_export_token,
+                filter,
             )
-            .await?;
-
-        let status = response.status();
-
-        // 204 is correct operation for this endpoint
-        if status != 204 {
-            bail!("Invalid status code {status}")
-        }
+            .await
+    }
 
-        Ok(())
+    async fn check_export_format(&self, client: &C, format: &ExportFormat) -> Result<()> {
+        DatasetRef::try_from(self)?
+            .check_export_format(client, format)
+            .await
     }
 
-    async fn set_workflow_v2(&self, client: &C, workflow: &WorkflowBuilder) -> Result<WorkflowV2> {
-        let response = client
-            .post(&format!("v2/teams/{}/workflows", client.team()), workflow)
-            .await?;
-        // 201 is correct operation for this endpoint
-        if response.status() != 201 {
-            bail!(
-                "Invalid status code {}. Response: {}",
-                response.status(),
-                response.text().await?
+    async fn generate_checked_export(
+        &self,
+        client: &C,
+        export_name: &'life2 str,
+        format: &ExportFormat,
+        include_authorship: bool,
+        include_export_token: bool,
+        filter: Option<&Filter>,
+    ) -> Result<()> {
+        DatasetRef::try_from(self)?
+            .generate_checked_export(
+                client,
+                export_name,
+                format,
+                include_authorship,
+                include_export_token,
+                filter,
+            )
+            .await
+    }
+
+    async fn list_exports(&self, client: &C) -> Result<Vec<Option<Export>>> {
+        DatasetRef::try_from(self)?.list_exports(client).await
+    }
+
+    async fn stream_exports(&self, client: &C) -> Result<BoxStream<'static, Result<Export>>> {
+        DatasetRef::try_from(self)?.stream_exports(client).await
+    }
+
+    async fn wait_for_export(
+        &self,
+        client: &C,
+        export_name: &str,
+        poll_interval: Duration,
+        deadline: Option<Duration>,
+    ) -> Result<Export> {
+        DatasetRef::try_from(self)?
+            .wait_for_export(client, export_name, poll_interval, deadline)
+            .await
+    }
+
+    async fn register_export_callback(
+        &self,
+        client: &C,
+        url: &str,
+        authorization_header: Option<&str>,
+    ) -> Result<WebhookSubscription> {
+        DatasetRef::try_from(self)?
+            .register_export_callback(client, url, authorization_header)
+            .await
+    }
+}
+
+#[cfg(feature = "client")]
+#[async_trait]
+impl<C> DatasetDescribeMethods<C> for DatasetRef
+where
+    C: V7Methods + std::marker::Sync,
+{
+    async fn list_datasets(client: &C) -> Result<Vec<Option<Dataset>>> {
+        let response = client.get("datasets").await?;
+
+        expect_http_ok!(response, Vec<Option<Dataset>>)
+    }
+    async fn list_dataset_summaries(client: &C) -> Result<Vec<DatasetSummary>> {
+        let response = client.get("datasets").await?;
+
+        expect_http_ok!(response, Vec<DatasetSummary>)
+    }
+    async fn stream_datasets(client: &C) -> Result<BoxStream<'static, Result<Dataset>>> {
+        let response = client.get("datasets").await?;
+        if response.status() != 200 {
+            bail!(HttpStatusError::from_response(response).await)
+        }
+        Ok(json_array::<Option<Dataset>>(response)
+            .try_filter_map(|dataset| async move { Ok(dataset) })
+            .boxed())
+    }
+    async fn list_dataset_items_v2(&self, client: &C) -> Result<Item> {
+        let response = client
+            .get(&format!(
+                "v2/teams/{}/items?dataset_ids={}",
+                self.team_slug, self.id
+            ))
+            .await?;
+
+        expect_http_ok!(response, Item)
+    }
+
+    async fn list_all_dataset_items_v2(&self, client: &C) -> Result<Vec<DatasetItemV2>> {
+        self.list_all_dataset_items_v2_with_progress(client, &NoProgress)
+            .await
+    }
+
+    fn stream_dataset_items_v2<'a>(
+        &'a self,
+        client: &'a C,
+    ) -> BoxStream<'a, Result<DatasetItemV2>> {
+        stream_item_pages(
+            client,
+            self.team_slug.clone(),
+            format!("dataset_ids={}", self.id),
+        )
+    }
+
+    async fn list_all_dataset_items_v2_with_progress(
+        &self,
+        client: &C,
+        progress: &dyn ProgressSink,
+    ) -> Result<Vec<DatasetItemV2>> {
+        list_item_pages(
+            client,
+            &self.team_slug,
+            &format!("dataset_ids={}", self.id),
+            None,
+            progress,
+        )
+        .await
+    }
+
+    async fn list_dataset_items_with_options(
+        &self,
+        client: &C,
+        options: &ItemListOptions,
+    ) -> Result<Vec<DatasetItemV2>> {
+        list_item_pages(
+            client,
+            &self.team_slug,
+            &options.query(self.id.0),
+            options.limit,
+            &NoProgress,
+        )
+        .await
+    }
+
+    async fn show_dataset(client: &C, id: &DatasetId) -> Result<Dataset> {
+        let response = client.get(&format!("datasets/{}", id)).await?;
+
+        expect_http_ok!(response, Dataset)
+    }
+
+    /// Re-fetches the identifiers of the dataset e.g. after its slug was changed
+    async fn refresh(&mut self, client: &C) -> Result<()> {
+        let dataset = DatasetRef::show_dataset(client, &self.id).await?;
+        *self = DatasetRef::try_from(&dataset)?;
+        Ok(())
+    }
+
+    async fn find_item_by_name(&self, client: &C, name: &str) -> Result<Option<DatasetItemV2>> {
+        let query = encode_query(&[
+            ("dataset_ids", self.id.to_string()),
+            ("item_names[]", name.to_string()),
+        ]);
+        let items = list_item_pages(client, &self.team_slug, &query, None, &NoProgress).await?;
+
+        Ok(items
+            .into_iter()
+            .find(|item| item.name.as_deref() == Some(name)))
+    }
+
+    async fn find_items_by_storage_key(
+        &self,
+        client: &C,
+        keys: &[String],
+    ) -> Result<Vec<DatasetItemV2>> {
+        let dataset_id = self.id.to_string();
+        let team_slug = &self.team_slug;
+
+        let mut items = Vec::new();
+        for chunk in keys.chunks(STORAGE_KEY_CHUNK_SIZE) {
+            let mut query = vec![("dataset_ids", dataset_id.clone())];
+            query.extend(chunk.iter().map(|key| ("storage_keys[]", key.clone())));
+            let found =
+                list_item_pages(client, team_slug, &encode_query(&query), None, &NoProgress)
+                    .await?;
+            // V7 only returns the storage keys of externally stored slots, trust its
+            // filtering for items without them
+            items.extend(found.into_iter().filter(|item| {
+                let mut storage_keys = item
+                    .slots
+                    .iter()
+                    .flatten()
+                    .filter_map(|slot| slot.storage_key.as_ref())
+                    .peekable();
+                storage_keys.peek().is_none() || storage_keys.any(|key| chunk.contains(key))
+            }));
+        }
+        Ok(items)
+    }
+}
+
+#[cfg(feature = "client")]
+#[async_trait]
+impl<C> DatasetDescribeMethods<C> for Dataset
+where
+    C: V7Methods + std::marker::Sync,
+{
+    async fn list_datasets(client: &C) -> Result<Vec<Option<Dataset>>> {
+        DatasetRef::list_datasets(client).await
+    }
+    async fn list_dataset_summaries(client: &C) -> Result<Vec<DatasetSummary>> {
+        DatasetRef::list_dataset_summaries(client).await
+    }
+    async fn stream_datasets(client: &C) -> Result<BoxStream<'static, Result<Dataset>>> {
+        DatasetRef::stream_datasets(client).await
+    }
+    async fn list_dataset_items_v2(&self, client: &C) -> Result<Item> {
+        DatasetRef::try_from(self)?
+            .list_dataset_items_v2(client)
+            .await
+    }
+
+    async fn list_all_dataset_items_v2(&self, client: &C) -> Result<Vec<DatasetItemV2>> {
+        DatasetRef::try_from(self)?
+            .list_all_dataset_items_v2(client)
+            .await
+    }
+
+    fn stream_dataset_items_v2<'a>(
+        &'a self,
+        client: &'a C,
+    ) -> BoxStream<'a, Result<DatasetItemV2>> {
+        match DatasetRef::try_from(self) {
+            Ok(dataset) => stream_item_pages(
+                client,
+                dataset.team_slug,
+                format!("dataset_ids={}", dataset.id),
+            ),
+            Err(error) => futures::stream::once(async { Err(error) }).boxed(),
+        }
+    }
+
+    async fn list_all_dataset_items_v2_with_progress(
+        &self,
+        client: &C,
+        progress: &dyn ProgressSink,
+    ) -> Result<Vec<DatasetItemV2>> {
+        DatasetRef::try_from(self)?
+            .list_all_dataset_items_v2_with_progress(client, progress)
+            .await
+    }
+
+    async fn list_dataset_items_with_options(
+        &self,
+        client: &C,
+        options: &ItemListOptions,
+    ) -> Result<Vec<DatasetItemV2>> {
+        DatasetRef::try_from(self)?
+            .list_dataset_items_with_options(client, options)
+            .await
+    }
+
+    async fn show_dataset(client: &C, id: &DatasetId) -> Result<Dataset> {
+        DatasetRef::show_dataset(client, id).await
+    }
+
+    async fn refresh(&mut self, client: &C) -> Result<()> {
+        let id = DatasetId(self.id.context("Dataset is missing Id")?);
+        *self = Dataset::show_dataset(client, &id).await?;
+        Ok(())
+    }
+
+    async fn find_item_by_name(&self, client: &C, name: &str) -> Result<Option<DatasetItemV2>> {
+        DatasetRef::try_from(self)?
+            .find_item_by_name(client, name)
+            .await
+    }
+
+    async fn find_items_by_storage_key(
+        &self,
+        client: &C,
+        keys: &[String],
+    ) -> Result<Vec<DatasetItemV2>> {
+        DatasetRef::try_from(self)?
+            .find_items_by_storage_key(client, keys)
+            .await
+    }
+}
+
+#[cfg(feature = "client")]
+impl DatasetRef {
+    /// The current stage of each item of the dataset in `workflow`
+    async fn stage_times<C>(
+        &self,
+        client: &C,
+        workflow: Option<WorkflowV2>,
+    ) -> Result<Vec<ItemStageTime>>
+    where
+        C: V7Methods + std::marker::Sync,
+    {
+        let workflow = workflow.context("Dataset has no associated workflow")?;
+        let options = ItemListOptions {
+            include_workflow_data: true,
+            ..Default::default()
+        };
+        let items = self
+            .list_dataset_items_with_options(client, &options)
+            .await?;
+
+        let mut stage_times: Vec<ItemStageTime> = Vec::new();
+        for item in items.iter() {
+            let instances = item
+                .workflow_data
+                .iter()
+                .flat_map(|data| data.current_stage_instances.iter().flatten());
+
+            for instance in instances {
+                let stage = workflow
+                    .stages
+                    .iter()
+                    .flatten()
+                    .find(|stage| stage.id.is_some() && stage.id == instance.stage_id);
+
+                stage_times.push(ItemStageTime {
+                    item_id: item.id.clone(),
+                    item_name: item.name.clone(),
+                    stage_id: instance.stage_id.clone(),
+                    stage_name: stage.and_then(|stage| stage.name.clone()),
+                    stage_type: stage.and_then(|stage| stage.stage_type.clone()),
+                    assignee_id: instance.user_id,
+                    entered_at: instance.inserted_at.clone(),
+                });
+            }
+        }
+
+        Ok(stage_times)
+    }
+
+    /// The number of items of the dataset matching `filter` in each stage of `workflow`
+    async fn stage_counts<C>(
+        &self,
+        client: &C,
+        workflow: Option<WorkflowV2>,
+        filter: &StageCountFilter,
+    ) -> Result<StageCounts>
+    where
+        C: V7Methods + std::marker::Sync,
+    {
+        let workflow = workflow.context("Dataset has no associated workflow")?;
+        let options = ItemListOptions {
+            include_workflow_data: true,
+            path_prefix: filter.folder.as_deref().map(folder_path),
+            name_prefix: filter.name_prefix.clone(),
+            ..Default::default()
+        };
+        let items = self
+            .list_dataset_items_with_options(client, &options)
+            .await?;
+
+        // The path prefix of V7 also matches sibling folders sharing the prefix, these are
+        // left out by the filter
+        Ok(count_items_by_stage(&workflow, &items, filter))
+    }
+}
+
+#[cfg(feature = "client")]
+#[async_trait]
+impl<C> DatasetWorkflowMethods<C> for DatasetRef
+where
+    C: V7Methods + std::marker::Sync,
+{
+    async fn reset_to_new(&self, client: &C, filter: &Filter) -> Result<()> {
+        require_v1(client, "reset_to_new")?;
+        let payload = ResetToNewPayload {
+            filter: filter.clone(),
+        };
+
+        let response = client
+            .put(
+                &format!("datasets/{}/items/move_to_new", self.id),
+                Some(&payload),
             )
+            .await?;
+
+        let status = response.status();
+
+        // 204 is correct operation for this endpoint
+        if status != 204 {
+            bail!("Invalid status code {status}")
+        }
+
+        Ok(())
+    }
+
+    async fn set_workflow_v2(&self, client: &C, workflow: &WorkflowBuilder) -> Result<WorkflowV2> {
+        let response = client
+            .post(&format!("v2/teams/{}/workflows", client.team()), workflow)
+            .await?;
+        // 201 is correct operation for this endpoint
+        if response.status() != 201 {
+            bail!(HttpStatusError::from_response(response).await)
         }
         Ok(response.json().await?)
     }
+
+    /// The workflow is found by the name of the dataset, which is fetched first
     async fn get_workflow_v2(&self, client: &C) -> Result<Option<WorkflowV2>> {
-        let workflows = WorkflowV2::get_workflows(client).await?;
-        let dataset_name = self.name.as_ref().context("Missing dataset name")?;
-        Ok(workflows
-            .into_iter()
-            .filter(|workflow| workflow.dataset.is_some())
-            .filter(|workflow| {
-                let dataset = workflow
-                    .dataset
-                    .as_ref()
-                    .expect("No associated dataset to workflow");
-                dataset.name.as_ref() == Some(dataset_name)
-            })
-            .collect::<Vec<_>>()
-            .first()
-            .cloned())
+        Dataset::show_dataset(client, &self.id)
+            .await?
+            .get_workflow_v2(client)
+            .await
+    }
+
+    async fn set_stage_v2(
+        &self,
+        client: &C,
+        stage_id: StageId,
+        workflow_id: WorkflowId,
+        filters: Option<SetStageFilter>,
+    ) -> Result<SetStageResponse> {
+        let filters = filters.unwrap_or_else(|| SetStageFilter {
+            dataset_ids: vec![self.id],
+            select_all: true,
+            workflow_stage_ids: None,
+            item_ids: None,
+        });
+
+        let payload = SetStagePayloadV2 {
+            filters,
+            stage_id,
+            workflow_id,
+        };
+        let response = client
+            .post(&format!("v2/teams/{}/items/stage", client.team()), &payload)
+            .await?;
+        expect_http_ok!(response, SetStageResponse)
+    }
+
+    async fn set_stage_v2_chunked(
+        &self,
+        client: &C,
+        stage_id: StageId,
+        workflow_id: WorkflowId,
+        filters: SetStageFilter,
+        chunk_size: Option<usize>,
+    ) -> Result<ChunkedSetStageResponse> {
+        let chunk_size = chunk_size.unwrap_or(SET_STAGE_CHUNK_SIZE);
+        if chunk_size == 0 {
+            bail!("Chunk size must be greater than zero");
+        }
+        let chunks: Vec<Option<Vec<String>>> = match &filters.item_ids {
+            Some(item_ids) if !item_ids.is_empty() => item_ids
+                .chunks(chunk_size)
+                .map(|chunk| Some(chunk.to_vec()))
+                .collect(),
+            _ => vec![filters.item_ids.clone()],
+        };
+
+        let mut aggregated = ChunkedSetStageResponse::default();
+        for item_ids in chunks {
+            let chunk_filters = SetStageFilter {
+                item_ids: item_ids.clone(),
+                ..filters.clone()
+            };
+            aggregated.chunks_sent += 1;
+            match self
+                .set_stage_v2(
+                    client,
+                    stage_id.clone(),
+                    workflow_id.clone(),
+                    Some(chunk_filters),
+                )
+                .await
+            {
+                Ok(response) => {
+                    aggregated.created_commands += response.created_commands.unwrap_or_default()
+                }
+                Err(error) => {
+                    warn!("Failed to set stage of chunk: {error}");
+                    aggregated.failures.push(SetStageChunkFailure {
+                        item_ids: item_ids.unwrap_or_default(),
+                        error,
+                    })
+                }
+            }
+        }
+        Ok(aggregated)
+    }
+
+    async fn list_workflow_templates(&self, client: &C) -> Result<Vec<WorkflowTemplate>> {
+        require_v1(client, "list_workflow_templates")?;
+        let response = client
+            .get(&format!("datasets/{}/workflow_templates", self.id))
+            .await?;
+        expect_http_ok!(response, Vec<WorkflowTemplate>)
+    }
+
+    async fn get_workflow_template(
+        client: &C,
+        template_id: WorkflowTemplateId,
+    ) -> Result<WorkflowTemplate> {
+        require_v1(client, "get_workflow_template")?;
+        let response = client
+            .get(&format!("workflow_templates/{template_id}"))
+            .await?;
+        expect_http_ok!(response, WorkflowTemplate)
+    }
+
+    async fn set_default_workflow_template(
+        &self,
+        client: &C,
+        template_id: WorkflowTemplateId,
+    ) -> Result<()> {
+        require_v1(client, "set_default_workflow_template")?;
+        let templates = self.list_workflow_templates(client).await?;
+        if !templates
+            .iter()
+            .any(|template| template.id == Some(template_id))
+        {
+            bail!(
+                "Workflow template {template_id} is not a template of dataset {}",
+                self.slug
+            );
+        }
+        update_dataset_settings(&self.id, client, |settings| {
+            settings.default_workflow_template_id = Some(template_id)
+        })
+        .await
+    }
+
+    async fn set_stage(
+        &self,
+        client: &C,
+        workflow_stage_template_id: WorkflowStageTemplateId,
+        filter: &Filter,
+    ) -> Result<()> {
+        require_v1(client, "set_stage")?;
+        let payload = SetStagePayload {
+            workflow_stage_template_id,
+            filter: filter.clone(),
+        };
+        let response = client
+            .put(&format!("datasets/{}/set_stage", self.id), Some(&payload))
+            .await?;
+
+        if !response.status().is_success() {
+            bail!(HttpStatusError::from_response(response).await)
+        }
+        Ok(())
+    }
+
+    async fn get_stage_times(&self, client: &C) -> Result<Vec<ItemStageTime>> {
+        let workflow = self.get_workflow_v2(client).await?;
+        self.stage_times(client, workflow).await
+    }
+
+    async fn get_stage_counts(&self, client: &C, filter: &StageCountFilter) -> Result<StageCounts> {
+        let workflow = self.get_workflow_v2(client).await?;
+        self.stage_counts(client, workflow, filter).await
+    }
+}
+
+#[cfg(feature = "client")]
+#[async_trait]
+impl<C> DatasetWorkflowMethods<C> for Dataset
+where
+    C: V7Methods + std::marker::Sync,
+{
+    async fn reset_to_new(&self, client: &C, filter: &Filter) -> Result<()> {
+        DatasetRef::try_from(self)?
+            .reset_to_new(client, filter)
+            .await
+    }
+
+    async fn set_workflow_v2(&self, client: &C, workflow: &WorkflowBuilder) -> Result<WorkflowV2> {
+        DatasetRef::try_from(self)?
+            .set_workflow_v2(client, workflow)
+            .await
+    }
+
+    async fn get_workflow_v2(&self, client: &C) -> Result<Option<WorkflowV2>> {
+        let workflows = WorkflowV2::get_workflows(client).await?;
+        let dataset_name = self.name.as_ref().context("Missing dataset name")?;
+        Ok(workflows
+            .into_iter()
+            .filter(|workflow| workflow.dataset.is_some())
+            .filter(|workflow| {
+                let dataset = workflow
+                    .dataset
+                    .as_ref()
+                    .expect("No associated dataset to workflow");
+                dataset.name.as_ref() == Some(dataset_name)
+            })
+            .collect::<Vec<_>>()
+            .first()
+            .cloned())
+    }
+
+    async fn set_stage_v2(
+        &self,
+        client: &C,
+        stage_id: StageId,
+        workflow_id: WorkflowId,
+        filters: Option<SetStageFilter>,
+    ) -> Result<SetStageResponse> {
+        DatasetRef::try_from(self)?
+            .set_stage_v2(client, stage_id, workflow_id, filters)
+            .await
+    }
+
+    async fn set_stage_v2_chunked(
+        &self,
+        client: &C,
+        stage_id: StageId,
+        workflow_id: WorkflowId,
+        filters: SetStageFilter,
+        chunk_size: Option<usize>,
+    ) -> Result<ChunkedSetStageResponse> {
+        DatasetRef::try_from(self)?
+            .set_stage_v2_chunked(client, stage_id, workflow_id, filters, chunk_size)
+            .await
+    }
+
+    async fn list_workflow_templates(&self, client: &C) -> Result<Vec<WorkflowTemplate>> {
+        DatasetRef::try_from(self)?
+            .list_workflow_templates(client)
+            .await
+    }
+
+    async fn get_workflow_template(
+        client: &C,
+        template_id: WorkflowTemplateId,
+    ) -> Result<WorkflowTemplate> {
+        DatasetRef::get_workflow_template(client, template_id).await
+    }
+
+    async fn set_default_workflow_template(
+        &self,
+        client: &C,
+        template_id: WorkflowTemplateId,
+    ) -> Result<()> {
+        DatasetRef::try_from(self)?
+            .set_default_workflow_template(client, template_id)
+            .await
+    }
+
+    async fn set_stage(
+        &self,
+        client: &C,
+        workflow_stage_template_id: WorkflowStageTemplateId,
+        filter: &Filter,
+    ) -> Result<()> {
+        DatasetRef::try_from(self)?
+            .set_stage(client, workflow_stage_template_id, filter)
+            .await
+    }
+
+    async fn get_stage_times(&self, client: &C) -> Result<Vec<ItemStageTime>> {
+        let dataset = DatasetRef::try_from(self)?;
+        let workflow = self.get_workflow_v2(client).await?;
+        dataset.stage_times(client, workflow).await
+    }
+
+    async fn get_stage_counts(&self, client: &C, filter: &StageCountFilter) -> Result<StageCounts> {
+        let dataset = DatasetRef::try_from(self)?;
+        let workflow = self.get_workflow_v2(client).await?;
+        dataset.stage_counts(client, workflow, filter).await
+    }
+}
+
+#[cfg(all(feature = "client", feature = "reports"))]
+#[async_trait]
+impl<C> DatasetItemReportMethods<C> for DatasetRef
+where
+    C: V7Methods + std::marker::Sync,
+{
+    async fn get_item_reports(&self, client: &C) -> Result<Vec<ItemReport>> {
+        self.stream_item_reports(client).await?.try_collect().await
+    }
+
+    async fn stream_item_reports(
+        &self,
+        client: &C,
+    ) -> Result<BoxStream<'static, Result<ItemReport>>> {
+        let endpoint = format!(
+            "teams/{}/datasets/{}/item_reports",
+            self.team_slug, self.slug
+        );
+        let response = client.get(&endpoint).await?;
+        if response.status() != 200 {
+            bail!(HttpStatusError::from_response(response).await)
+        }
+        let reader = response
+            .bytes_stream()
+            .map_err(std::io::Error::other)
+            .boxed()
+            .into_async_read();
+        Ok(item_reports_from_reader(reader))
+    }
+
+    async fn class_distribution(&self, client: &C) -> Result<ClassDistribution> {
+        let team_slug = &self.team_slug;
+        let team = Team::new(team_slug.to_string(), None, None, None);
+        let class_names: HashMap<u32, String> = team
+            .list_annotation_classes(client)
+            .await?
+            .annotation_classes
+            .into_iter()
+            .flatten()
+            .filter_map(|class| Some((class.id?.0, class.name?)))
+            .collect();
+        let emails: HashMap<u32, String> = Team::list_memberships(client)
+            .await?
+            .into_iter()
+            .filter_map(|member| Some((member.user_id?, member.email?)))
+            .collect();
+
+        // The annotations are listed per item, a page of items at a time, and counted as
+        // they arrive rather than kept
+        let mut tally = ClassTally::default();
+        let query = format!("dataset_ids={}", self.id);
+        let mut cursor: Option<String> = None;
+        loop {
+            let page =
+                fetch_item_page(client, team_slug, &query, ITEM_PAGE_SIZE, cursor.as_deref())
+                    .await?;
+            let page_len = page.items.len();
+            let mut annotations = futures::stream::iter(
+                page.items
+                    .into_iter()
+                    .flatten()
+                    .filter_map(|item| item.id)
+                    .map(|item_id| async move {
+                        let response = client
+                            .get(&format!("v2/teams/{team_slug}/items/{item_id}/annotations"))
+                            .await?;
+                        let annotations: Result<Vec<ItemAnnotation>> =
+                            expect_http_ok!(response, Vec<ItemAnnotation>);
+                        annotations
+                    }),
+            )
+            .buffer_unordered(ANNOTATION_LIST_CONCURRENCY);
+            while let Some(item_annotations) = annotations.try_next().await? {
+                item_annotations
+                    .iter()
+                    .for_each(|annotation| tally.add(annotation));
+            }
+
+            cursor = page.page.next;
+            if cursor.is_none() || page_len == 0 {
+                break;
+            }
+        }
+
+        Ok(tally.into_distribution(&class_names, &emails))
+    }
+
+    async fn dump_items<W>(&self, client: &C, writer: W, format: ItemDumpFormat) -> Result<u64>
+    where
+        W: futures::io::AsyncWrite + Unpin + Send,
+    {
+        let team_slug = &self.team_slug;
+        let query = format!("dataset_ids={}", self.id);
+        let mut rows = RowWriter::new(writer, format);
+        let mut written = 0;
+        let mut cursor: Option<String> = None;
+
+        loop {
+            let page =
+                fetch_item_page(client, team_slug, &query, ITEM_PAGE_SIZE, cursor.as_deref())
+                    .await?;
+            let page_len = page.items.len();
+            for item in page.items.iter().flatten() {
+                rows.write(&ItemRow::from(item)).await?;
+                written += 1;
+            }
+
+            cursor = page.page.next;
+            if cursor.is_none() || page_len == 0 {
+                break;
+            }
+        }
+
+        rows.flush().await?;
+        Ok(written)
+    }
+}
+
+#[cfg(all(feature = "client", feature = "reports"))]
+#[async_trait]
+impl<C> DatasetItemReportMethods<C> for Dataset
+where
+    C: V7Methods + std::marker::Sync,
+{
+    async fn get_item_reports(&self, client: &C) -> Result<Vec<ItemReport>> {
+        DatasetRef::try_from(self)?.get_item_reports(client).await
+    }
+
+    async fn stream_item_reports(
+        &self,
+        client: &C,
+    ) -> Result<BoxStream<'static, Result<ItemReport>>> {
+        DatasetRef::try_from(self)?
+            .stream_item_reports(client)
+            .await
+    }
+
+    async fn class_distribution(&self, client: &C) -> Result<ClassDistribution> {
+        DatasetRef::try_from(self)?.class_distribution(client).await
+    }
+
+    async fn dump_items<W>(&self, client: &C, writer: W, format: ItemDumpFormat) -> Result<u64>
+    where
+        W: futures::io::AsyncWrite + Unpin + Send,
+    {
+        DatasetRef::try_from(self)?
+            .dump_items(client, writer, format)
+            .await
+    }
+}
+
+impl Display for Dataset {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{:?}:{}/{:?}",
+            self.id,
+            self.team_slug.as_ref().unwrap_or(&"team-slug".to_string()),
+            self.slug
+        )
+    }
+}
+
+#[cfg(test)]
+mod test_hotkeys {
+    use super::*;
+
+    #[test]
+    fn test_hotkeys_serde() {
+        let contents = r#"{"1": "select_class:1234", "2": "some_other_action"}"#;
+        let hotkeys: AnnotationHotKeys = serde_json::from_str(contents).unwrap();
+
+        assert_eq!(hotkeys.get("1"), Some(&HotKeyAction::SelectClass(1234)));
+        assert_eq!(
+            hotkeys.get("2"),
+            Some(&HotKeyAction::Other("some_other_action".to_string()))
+        );
+        assert_eq!(hotkeys.key_for_class(ClassId(1234)), Some("1"));
+
+        let round_trip: HashMap<String, String> =
+            serde_json::from_str(&serde_json::to_string(&hotkeys).unwrap()).unwrap();
+        assert_eq!(round_trip.get("1"), Some(&"select_class:1234".to_string()));
+        assert_eq!(round_trip.get("2"), Some(&"some_other_action".to_string()));
+    }
+
+    #[test]
+    fn test_hotkeys_bind() {
+        let mut hotkeys = AnnotationHotKeys::new();
+        hotkeys.bind("3", HotKeyAction::SelectClass(1)).unwrap();
+        hotkeys
+            .bind("ctrl+z", HotKeyAction::SelectClass(2))
+            .expect_err("ctrl+z is not a valid annotation hotkey");
+
+        assert_eq!(hotkeys.len(), 1);
+        assert_eq!(hotkeys.unbind("3"), Some(HotKeyAction::SelectClass(1)));
+        assert!(hotkeys.is_empty());
+    }
+
+    #[test]
+    fn test_hotkeys_from_class_usage() {
+        let usage: HashMap<u32, u64> = (1..=12).map(|id| (id, id as u64 * 10)).collect();
+        let hotkeys = AnnotationHotKeys::from_class_usage(&usage);
+
+        assert_eq!(hotkeys.len(), 9);
+        assert_eq!(hotkeys.get("1"), Some(&HotKeyAction::SelectClass(12)));
+        assert_eq!(hotkeys.get("9"), Some(&HotKeyAction::SelectClass(4)));
+        assert_eq!(hotkeys.get("0"), None);
+        assert_eq!(hotkeys.key_for_class(ClassId(1)), None);
+    }
+}
+
+#[cfg(test)]
+mod test_class_distribution {
+    use super::*;
+    use crate::annotation::AnnotationActor;
+
+    fn annotation(class_id: u32, user_id: u32) -> ItemAnnotation {
+        ItemAnnotation {
+            annotation_class_id: Some(class_id),
+            actors: vec![
+                AnnotationActor {
+                    role: Some("annotator".to_string()),
+                    user_id: Some(user_id),
+                },
+                AnnotationActor {
+                    role: Some("reviewer".to_string()),
+                    user_id: Some(99),
+                },
+            ],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_class_distribution_from_annotations() {
+        let annotations = [annotation(1, 7), annotation(2, 7), annotation(2, 8)];
+        let class_names = HashMap::from([(1, "Cheese".to_string()), (2, "Crackers".to_string())]);
+        let emails = HashMap::from([(7, "kevin@mail.com".to_string())]);
+
+        let distribution =
+            ClassDistribution::from_annotations(annotations.iter(), &class_names, &emails);
+
+        assert_eq!(
+            distribution.classes,
+            vec![
+                ClassCount {
+                    class_id: Some(2),
+                    class_name: Some("Crackers".to_string()),
+                    count: 2
+                },
+                ClassCount {
+                    class_id: Some(1),
+                    class_name: Some("Cheese".to_string()),
+                    count: 1
+                },
+            ]
+        );
+        assert_eq!(distribution.annotators.len(), 3);
+        assert!(distribution
+            .annotators
+            .iter()
+            .all(|count| count.user_id != Some(99)));
+        assert_eq!(
+            distribution.annotators[0].email,
+            Some("kevin@mail.com".to_string())
+        );
+    }
+}
+
+#[cfg(test)]
+mod test_export_metadata {
+    use super::*;
+
+    #[test]
+    fn test_export_metadata_serde() {
+        let export: Export = serde_json::from_str(
+            r#"{
+                "name": "release-1",
+                "format": "json",
+                "latest": true,
+                "metadata": {
+                    "annotation_classes": [
+                        {"id": 1, "name": "tumour"},
+                        null
+                    ],
+                    "annotation_types": [
+                        {"id": 1, "name": "polygon", "count": 120},
+                        {"id": 2, "name": "tag", "count": null},
+                        {"id": 3, "name": "bounding_box", "count": 30}
+                    ]
+                },
+                "status": "complete",
+                "version": 2
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(export.metadata.class_count(), 1);
+        assert_eq!(export.metadata.annotation_count(), 150);
+        assert_eq!(export.metadata.type_count("polygon"), Some(120));
+        assert_eq!(export.metadata.type_count("tag"), None);
+
+        for metadata in [
+            r#""metadata": null"#,
+            r#""metadata": {"annotation_classes": null, "annotation_types": null}"#,
+        ] {
+            let export: Export =
+                serde_json::from_str(&format!(r#"{{"name": "a", {metadata}}}"#)).unwrap();
+            assert_eq!(export.metadata.class_count(), 0);
+            assert_eq!(export.metadata.annotation_count(), 0);
+        }
+    }
+}
+
+#[cfg(all(test, feature = "fake"))]
+mod test_registration_response {
+    use super::*;
+
+    fn item(name: &str, reasons: &[Option<&str>]) -> Option<RegistrationResponseItem> {
+        Some(RegistrationResponseItem {
+            name: Some(name.to_string()),
+            slots: reasons
+                .iter()
+                .map(|reason| {
+                    Some(SlotResponse {
+                        reason: reason.map(str::to_string),
+                        ..Faker.fake()
+                    })
+                })
+                .collect(),
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    fn test_blocked_items() {
+        let response = RegisterExistingItemResponse {
+            blocked_items: vec![
+                item("exists.png", &[Some("already_exists")]),
+                item("flaky.png", &[Some("STORAGE_TIMEOUT"), None]),
+                item("silent.png", &[]),
+            ],
+            items: vec![item("ok.png", &[None]), None],
+        };
+
+        assert!(!response.is_fully_successful());
+        assert_eq!(
+            response.blocked_reasons(),
+            vec![
+                ("exists.png".to_string(), "already_exists".to_string()),
+                ("flaky.png".to_string(), "STORAGE_TIMEOUT".to_string()),
+                ("silent.png".to_string(), "UNKNOWN".to_string()),
+            ]
+        );
+        let retryable: Vec<Option<&str>> = response
+            .retryable_items()
+            .iter()
+            .map(|item| item.name.as_deref())
+            .collect();
+        assert_eq!(retryable, vec![Some("flaky.png"), Some("silent.png")]);
+        assert_eq!(
+            response.summary(),
+            "registered 1 items, blocked 3 (STORAGE_TIMEOUT: 1, UNKNOWN: 1, already_exists: 1)"
+        );
+
+        let response = RegisterExistingItemResponse {
+            blocked_items: vec![None],
+            ..response
+        };
+        assert!(response.is_fully_successful());
+        assert_eq!(response.summary(), "registered 1 items");
+    }
+}
+
+#[cfg(all(test, feature = "client", feature = "fake"))]
+mod test_client_calls {
+    use super::*;
+    use crate::client::{ApiVersion, UnsupportedApiVersionError, V7Client};
+    use crate::imports::{
+        AnnotationContext, AnnotationImportAnnotation, AnnotationImportData, OnExisting,
+    };
+
+    use fake::{Fake, Faker};
+
+    // Utilizing Faker with an AlwaysTrueRng to guarantee that all Option types are populated with Some values
+    // This ensures consistent data generation where no field is left as None
+    use crate::item::{DatasetItemV2, Slot};
+    use fake::utils::AlwaysTrueRng;
+    use serde_json::json;
+    use wiremock::matchers::{
+        body_json, body_partial_json, header, headers, method, path, query_param,
+    };
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    #[tokio::test]
+    async fn test_list_datasets() {
+        let mock_server = MockServer::start().await;
+        let mock_data: Vec<Dataset> = fake::vec![Dataset; 2];
+
+        let client: V7Client = V7Client::new(
+            format!("{}/", mock_server.uri()),
+            "api-key".to_string(),
+            "some-team".to_string(),
+        )
+        .expect("Failed to get V7Client");
+
+        Mock::given(method("GET"))
+            .and(path("/datasets"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(mock_data.clone()))
+            .mount(&mock_server)
+            .await;
+
+        let datasets = Dataset::list_datasets(&client)
+            .await
+            .expect("Failed to list datasets");
+
+        // Pick a few values to avoid f64 comparison issues
+        assert_eq!(datasets.len(), mock_data.len());
+        assert_eq!(
+            datasets[0].as_ref().expect("Expected dataset").id,
+            mock_data[0].id
+        );
+        assert_eq!(
+            datasets[0].as_ref().expect("Expected dataset").slug,
+            mock_data[0].slug
+        );
+        assert_eq!(
+            datasets[1].as_ref().expect("Expected dataset").inserted_at,
+            mock_data[1].inserted_at
+        );
+    }
+
+    #[tokio::test]
+    async fn test_list_dataset_summaries() {
+        let mock_server = MockServer::start().await;
+        let client: V7Client = V7Client::new(
+            format!("{}/", mock_server.uri()),
+            "api-key".to_string(),
+            "some-team".to_string(),
+        )
+        .expect("Failed to get V7Client");
+
+        // Fields other than those of the summary are not parsed, even if unexpected
+        Mock::given(method("GET"))
+            .and(path("/datasets"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!([{
+                "id": 4,
+                "name": "Biopsies",
+                "slug": "biopsies",
+                "team_slug": "some-team",
+                "num_items": 12,
+                "progress": "unexpected",
+                "annotation_hotkeys": [1, 2]
+            }])))
+            .mount(&mock_server)
+            .await;
+
+        let summaries = Dataset::list_dataset_summaries(&client)
+            .await
+            .expect("Failed to list datasets");
+        assert_eq!(summaries.len(), 1);
+        assert_eq!(summaries[0].num_items, Some(12));
+
+        let dataset = Dataset::from(summaries[0].clone());
+        assert_eq!(dataset.id, Some(4));
+        assert_eq!(dataset.slug.as_deref(), Some("biopsies"));
+        assert_eq!(dataset.team_slug.as_deref(), Some("some-team"));
+    }
+
+    #[tokio::test]
+    async fn test_refresh_dataset_progress() {
+        let mock_server = MockServer::start().await;
+        let client: V7Client = V7Client::new(
+            format!("{}/", mock_server.uri()),
+            "api-key".to_string(),
+            "some-team".to_string(),
+        )
+        .expect("Failed to get V7Client");
+
+        let refreshed = Dataset {
+            id: Some(4),
+            num_items: Some(10),
+            num_complete_files: Some(10),
+            progress: Some(1.0),
+            ..Default::default()
+        };
+        Mock::given(method("GET"))
+            .and(path("/datasets/4"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(&refreshed))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let mut dataset = Dataset {
+            id: Some(4),
+            num_items: Some(10),
+            num_complete_files: Some(4),
+            ..Default::default()
+        };
+        assert_eq!(dataset.completion_ratio(), Some(0.4));
+        assert_eq!(dataset.remaining_items(), Some(6));
+        assert!(!dataset.is_complete());
+
+        dataset.refresh(&client).await.expect("Failed to refresh");
+        assert_eq!(dataset.completion_ratio(), Some(1.0));
+        assert_eq!(dataset.remaining_items(), Some(0));
+        assert!(dataset.is_complete());
+
+        // Counts that are missing or inconsistent
+        let dataset = Dataset {
+            progress: Some(0.25),
+            ..Default::default()
+        };
+        assert_eq!(dataset.completion_ratio(), Some(0.25));
+        assert_eq!(dataset.remaining_items(), None);
+        assert!(!dataset.is_complete());
+        let dataset = Dataset {
+            num_items: Some(0),
+            num_complete_files: Some(0),
+            ..Default::default()
+        };
+        assert_eq!(dataset.completion_ratio(), None);
+        assert!(!dataset.is_complete());
+        let dataset = Dataset {
+            num_items: Some(3),
+            num_complete_files: Some(5),
+            ..Default::default()
+        };
+        assert_eq!(dataset.completion_ratio(), Some(1.0));
+        assert_eq!(dataset.remaining_items(), Some(0));
+    }
+
+    #[tokio::test]
+    async fn test_stream_compressed_listings() {
+        let mock_server = MockServer::start().await;
+        let client: V7Client = V7Client::new(
+            format!("{}/", mock_server.uri()),
+            "api-key".to_string(),
+            "some-team".to_string(),
+        )
+        .expect("Failed to get V7Client");
+
+        let datasets = json!([{"id": 1, "name": "Biopsies"}, null, {"id": 2, "name": "Smears"}]);
+        // A gzip stream without optional header fields
+        let body = datasets.to_string();
+        let mut gzipped = vec![0x1f, 0x8b, 0x08, 0, 0, 0, 0, 0, 0, 0xff];
+        gzipped.extend(miniz_oxide::deflate::compress_to_vec(body.as_bytes(), 6));
+        gzipped.extend(crate::compression::crc32_update(0, body.as_bytes()).to_le_bytes());
+        gzipped.extend((body.len() as u32).to_le_bytes());
+        Mock::given(method("GET"))
+            .and(path("/datasets"))
+            .and(headers("accept-encoding", vec!["gzip", "deflate"]))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .insert_header("content-encoding", "gzip")
+                    .set_body_raw(gzipped, "application/json"),
+            )
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+        let exports = json!([{"name": "release-1", "status": "complete"}]);
+        Mock::given(method("GET"))
+            .and(path("/v2/teams/some-team/datasets/biopsies/exports"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .insert_header("content-encoding", "deflate")
+                    .set_body_raw(
+                        miniz_oxide::deflate::compress_to_vec_zlib(
+                            exports.to_string().as_bytes(),
+                            6,
+                        ),
+                        "application/json",
+                    ),
+            )
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let datasets: Vec<Dataset> = Dataset::stream_datasets(&client)
+            .await
+            .expect("Failed to list datasets")
+            .try_collect()
+            .await
+            .expect("Failed to parse datasets");
+        assert_eq!(datasets.len(), 2);
+        assert_eq!(datasets[1].name.as_deref(), Some("Smears"));
+
+        let dataset = Dataset {
+            id: Some(1),
+            slug: Some("biopsies".to_string()),
+            team_slug: Some("some-team".to_string()),
+            ..Default::default()
+        };
+        let exports: Vec<Export> = dataset
+            .stream_exports(&client)
+            .await
+            .expect("Failed to list exports")
+            .try_collect()
+            .await
+            .expect("Failed to parse exports");
+        assert_eq!(exports[0].name.as_deref(), Some("release-1"));
+    }
+
+    #[tokio::test]
+    async fn test_list_datasets_status_error() {
+        let mock_server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/datasets"))
+            .respond_with(ResponseTemplate::new(412))
+            .mount(&mock_server)
+            .await;
+
+        let client: V7Client = V7Client::new(
+            format!("{}/", mock_server.uri()),
+            "api-key".to_string(),
+            "some-team".to_string(),
+        )
+        .expect("Failed to get V7Client");
+
+        Dataset::list_datasets(&client)
+            .await
+            .expect_err("Invalid status code 412");
+    }
+
+    #[tokio::test]
+    async fn test_list_datasets_data_error() {
+        let mock_server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/datasets"))
+            .respond_with(ResponseTemplate::new(200).set_body_string("dont_render"))
+            .mount(&mock_server)
+            .await;
+
+        let client: V7Client = V7Client::new(
+            format!("{}/", mock_server.uri()),
+            "api-key".to_string(),
+            "some-team".to_string(),
+        )
+        .expect("Failed to get V7Client");
+
+        Dataset::list_datasets(&client)
+            .await
+            .expect_err("error decoding response body: expected value at line 1 column 1");
+    }
+
+    #[tokio::test]
+    async fn test_list_dataset_items() {
+        let mock_server = MockServer::start().await;
+        let mut rng = AlwaysTrueRng::default();
+        let mut mock_data: Dataset = Faker.fake_with_rng(&mut rng);
+        mock_data.team_slug = Some("some-team".to_string());
+
+        let dset_id = mock_data.id.expect("Id must be set");
+
+        // Just generate two random values for comparison
+        // let mock_result_vec: Vec<DatasetItemV2> = vec![mock_dataset_item; 2];
+        let mock_result: Item = Item {
+            items: vec![Some(DatasetItemV2 {
+                id: Some("123".to_string()),
+                ..Default::default()
+            })],
+            ..Default::default()
+        };
+
+        let mock_items: Vec<_> = mock_result.items.iter().flatten().collect();
+
+        let client: V7Client = V7Client::new(
+            format!("{}/", mock_server.uri()),
+            "api-key".to_string(),
+            "some-team".to_string(),
+        )
+        .expect("Failed to get V7Client");
+
+        Mock::given(method("GET"))
+            .and(path("/v2/teams/some-team/items"))
+            .and(query_param("dataset_ids".to_string(), dset_id.to_string()))
+            .respond_with(ResponseTemplate::new(200).set_body_json(mock_result.clone()))
+            .mount(&mock_server)
+            .await;
+
+        let result = mock_data
+            .list_dataset_items_v2(&client)
+            .await
+            .expect("Failed to list dataset items");
+        let expected_items: Vec<_> = result.items.iter().flatten().collect();
+
+        // Only compare a few values, this is mostly testing the endpoint
+        // invocation and not serde.
+        assert_eq!(expected_items.len(), mock_items.len());
+        assert_eq!(expected_items[0].status, mock_items[0].status);
+        assert_eq!(
+            expected_items[expected_items.len() - 1].id,
+            mock_items[mock_items.len() - 1].id
+        );
+    }
+
+    #[tokio::test]
+    async fn test_archive_dataset_items() {
+        let mock_server = MockServer::start().await;
+        let mut rng = AlwaysTrueRng::default();
+        let mut mock_data: Dataset = Faker.fake_with_rng(&mut rng);
+        let team_slug = mock_data.id;
+        mock_data.team_slug = team_slug.map(|s| s.to_string());
+
+        let dset_id: Option<Vec<DatasetId>> =
+            Some(vec![DatasetId(mock_data.id.expect("Id must be set"))]);
+        let complete_status: Option<Vec<String>> = Some(vec!["Complete".to_string()]);
+
+        let filter = Filter {
+            dataset_ids: dset_id,
+            statuses: complete_status,
+            ..Default::default()
+        };
+
+        let client: V7Client = V7Client::new(
+            format!("{}/", mock_server.uri()),
+            "api-key".to_string(),
+            "some-team".to_string(),
+        )
+        .expect("Failed to get V7Client");
+
+        Mock::given(method("POST"))
+            .and(path(format!(
+                "v2/teams/{}/items/archive",
+                team_slug.expect("Team slug should be set")
+            )))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "affected_item_count": 1,
+            })))
+            .mount(&mock_server)
+            .await;
+
+        let result = mock_data
+            .archive_items(&client, &filter)
+            .await
+            .expect("Failed to archive items");
+
+        assert_eq!(result.affected_item_count, Some(1));
+    }
+
+    #[tokio::test]
+    async fn test_restore_dataset_items() {
+        let mock_server = MockServer::start().await;
+        let dataset = Dataset {
+            id: Some(5),
+            slug: Some("some-dataset".to_string()),
+            team_slug: Some("some-team".to_string()),
+            ..Default::default()
+        };
+        let filter = Filter {
+            dataset_ids: Some(vec![DatasetId(5)]),
+            item_ids: Some(vec!["item-1".to_string(), "item-2".to_string()]),
+            ..Default::default()
+        };
+
+        let client: V7Client = V7Client::new(
+            format!("{}/", mock_server.uri()),
+            "api-key".to_string(),
+            "some-team".to_string(),
+        )
+        .expect("Failed to get V7Client");
+
+        Mock::given(method("POST"))
+            .and(path("v2/teams/some-team/items/restore"))
+            .and(body_json(json!({
+                "filters": {"dataset_ids": [5], "item_ids": ["item-1", "item-2"]}
+            })))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "affected_item_count": 2,
+            })))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let result = dataset
+            .restore_items(&client, &filter)
+            .await
+            .expect("Failed to restore items");
+
+        assert_eq!(result.affected_item_count, Some(2));
+    }
+
+    #[tokio::test]
+    async fn test_list_dataset_items_status_error() {
+        let mock_server = MockServer::start().await;
+
+        let mut rng = AlwaysTrueRng::default();
+        let mock_data: Dataset = Faker.fake_with_rng(&mut rng);
+        let dset_id = mock_data.id.expect("Id must be set");
+
+        Mock::given(method("GET"))
+            .and(path("/v2/teams/some-team/items"))
+            .and(query_param("dataset_ids".to_string(), dset_id.to_string()))
+            .respond_with(ResponseTemplate::new(412))
+            .mount(&mock_server)
+            .await;
+
+        let client: V7Client = V7Client::new(
+            format!("{}/", mock_server.uri()),
+            "api-key".to_string(),
+            "some-team".to_string(),
+        )
+        .expect("Failed to get V7 Client");
+
+        mock_data
+            .list_dataset_items_v2(&client)
+            .await
+            .expect_err("Invalid status code 412");
+    }
+
+    #[tokio::test]
+    async fn test_list_all_dataset_items() {
+        let mock_server = MockServer::start().await;
+        let mut rng = AlwaysTrueRng::default();
+        let mut dataset: Dataset = Faker.fake_with_rng(&mut rng);
+        dataset.team_slug = Some("some-team".to_string());
+
+        let page = |id: &str, next: Option<&str>| Item {
+            items: vec![Some(DatasetItemV2 {
+                id: Some(id.to_string()),
+                ..Default::default()
+            })],
+            page: crate::item::ItemPage {
+                next: next.map(|n| n.to_string()),
+                ..Default::default()
+            },
+        };
+
+        Mock::given(method("GET"))
+            .and(path("/v2/teams/some-team/items"))
+            .and(query_param("page[from]", "cursor+1/="))
+            .respond_with(ResponseTemplate::new(200).set_body_json(page("item-2", None)))
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/v2/teams/some-team/items"))
+            .respond_with(
+                ResponseTemplate::new(200).set_body_json(page("item-1", Some("cursor+1/="))),
+            )
+            .mount(&mock_server)
+            .await;
+
+        let client: V7Client = V7Client::new(
+            format!("{}/", mock_server.uri()),
+            "api-key".to_string(),
+            "some-team".to_string(),
+        )
+        .expect("Failed to get V7Client");
+
+        let items = dataset
+            .list_all_dataset_items_v2(&client)
+            .await
+            .expect("Failed to list all dataset items");
+
+        assert_eq!(items.len(), 2);
+        assert_eq!(items[0].id, Some("item-1".to_string()));
+        assert_eq!(items[1].id, Some("item-2".to_string()));
+
+        let streamed: Vec<DatasetItemV2> = dataset
+            .stream_dataset_items_v2(&client)
+            .try_collect()
+            .await
+            .expect("Failed to stream dataset items");
+        let ids: Vec<Option<String>> = streamed.into_iter().map(|item| item.id).collect();
+        assert_eq!(
+            ids,
+            vec![Some("item-1".to_string()), Some("item-2".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_export_format_item_types() {
+        let types = [
+            DatasetItemTypes::Image,
+            DatasetItemTypes::Video,
+            DatasetItemTypes::Dicom,
+            DatasetItemTypes::Video,
+        ];
+        assert_eq!(ExportFormat::DarwinJson2.check_item_types(&types), Ok(()));
+        let error = ExportFormat::PascalVoc
+            .check_item_types(&types)
+            .unwrap_err();
+        assert_eq!(
+            error.item_types,
+            vec![DatasetItemTypes::Video, DatasetItemTypes::Dicom]
+        );
+        assert_eq!(
+            error.to_string(),
+            "Export format pascal_voc does not support Video, DICOM items, use darwin_json_2 instead"
+        );
+        assert!(!ExportFormat::SemanticMask.supports(&DatasetItemTypes::Pdf));
+    }
+
+    #[tokio::test]
+    async fn test_dataset_ref() {
+        let mock_server = MockServer::start().await;
+        let client: V7Client = V7Client::new(
+            format!("{}/", mock_server.uri()),
+            "api-key".to_string(),
+            "some-team".to_string(),
+        )
+        .expect("Failed to get V7Client");
+
+        let dataset = Dataset {
+            id: Some(1),
+            team_slug: Some("some-team".to_string()),
+            ..Default::default()
+        };
+        let error = DatasetRef::try_from(&dataset).unwrap_err().to_string();
+        assert_eq!(error, "Dataset is missing slug");
+        let error = DatasetRef::try_from(&DatasetSummary::default())
+            .unwrap_err()
+            .to_string();
+        assert_eq!(error, "Dataset is missing Id, slug, team slug");
+
+        let dataset = Dataset {
+            slug: Some("some-dataset".to_string()),
+            name: Some("Some Dataset".to_string()),
+            num_items: Some(10),
+            ..dataset
+        };
+        let dataset_ref = DatasetRef::try_from(&dataset).unwrap();
+        assert_eq!(
+            dataset_ref,
+            DatasetRef::new(DatasetId(1), "some-dataset", "some-team")
+        );
+        assert_eq!(dataset_ref.to_string(), "1:some-team/some-dataset");
+
+        Mock::given(method("GET"))
+            .and(path("/v2/teams/some-team/datasets/some-dataset/exports"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_json(json!([{"name": "release-1", "status": "complete"}])),
+            )
+            .expect(2)
+            .mount(&mock_server)
+            .await;
+
+        let tasks: Vec<_> = (0..2)
+            .map(|_| {
+                let (client, dataset_ref) = (client.clone(), dataset_ref.clone());
+                tokio::spawn(async move { dataset_ref.list_exports(&client).await })
+            })
+            .collect();
+        for task in tasks {
+            let exports = task.await.unwrap().unwrap();
+            assert_eq!(exports.len(), 1);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_dataset_ref_data_methods() {
+        let mock_server = MockServer::start().await;
+        let dataset_ref = DatasetRef::new(DatasetId(7), "some-dataset", "some-team");
+
+        // The batch size update requires every setting, so the dataset is fetched first
+        Mock::given(method("GET"))
+            .and(path("/datasets/7"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(Dataset {
+                id: Some(7),
+                work_size: Some(10),
+                instructions: Some("<p>Outline the tumour</p>".to_string()),
+                ..Default::default()
+            }))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("PUT"))
+            .and(path("/datasets/7"))
+            .and(body_partial_json(json!({
+                "work_size": 25,
+                "instructions": "<p>Outline the tumour</p>"
+            })))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({})))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/v2/teams/some-team/items/reprocess"))
+            .and(body_json(json!({"filters": {"dataset_ids": [7]}})))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let client: V7Client = V7Client::new(
+            format!("{}/", mock_server.uri()),
+            "api-key".to_string(),
+            "some-team".to_string(),
+        )
+        .expect("Failed to get V7Client");
+
+        dataset_ref
+            .update_batch_size(&client, &25)
+            .await
+            .expect("Failed to update batch size");
+        dataset_ref
+            .resync_items(&client, &Filter::default())
+            .await
+            .expect("Failed to resync items");
+    }
+
+    #[tokio::test]
+    async fn test_dataset_ref_describe_methods() {
+        let mock_server = MockServer::start().await;
+        let mut dataset_ref = DatasetRef::new(DatasetId(3), "some-dataset", "some-team");
+
+        Mock::given(method("GET"))
+            .and(path("/v2/teams/some-team/items"))
+            .and(query_param("dataset_ids", "3"))
+            .and(query_param("item_names[]", "scan.png"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "items": [{"id": "item-1", "name": "scan.png"}],
+                "page": {}
+            })))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/datasets/3"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(Dataset {
+                id: Some(3),
+                slug: Some("renamed-dataset".to_string()),
+                team_slug: Some("some-team".to_string()),
+                ..Default::default()
+            }))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let client: V7Client = V7Client::new(
+            format!("{}/", mock_server.uri()),
+            "api-key".to_string(),
+            "some-team".to_string(),
+        )
+        .expect("Failed to get V7Client");
+
+        let item = dataset_ref
+            .find_item_by_name(&client, "scan.png")
+            .await
+            .expect("Failed to find item")
+            .expect("Item not found");
+        assert_eq!(item.id.as_deref(), Some("item-1"));
+
+        dataset_ref
+            .refresh(&client)
+            .await
+            .expect("Failed to refresh dataset");
+        assert_eq!(
+            dataset_ref,
+            DatasetRef::new(DatasetId(3), "renamed-dataset", "some-team")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_dataset_ref_workflow_methods() {
+        let mock_server = MockServer::start().await;
+        let dataset_ref = DatasetRef::new(DatasetId(3), "some-dataset", "some-team");
+        let workflow_json = |dataset_id: u32, dataset_name: &str, id: &str| {
+            json!({
+                "dataset": {"id": dataset_id, "name": dataset_name},
+                "id": id,
+                "inserted_at": null,
+                "name": "workflow",
+                "progress": null,
+                "stages": [],
+                "team_id": 1,
+                "thumbnails": [],
+                "updated_at": null
+            })
+        };
+
+        // The workflow is matched by the name of the dataset, which is fetched first
+        Mock::given(method("GET"))
+            .and(path("/datasets/3"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(Dataset {
+                id: Some(3),
+                name: Some("Some Dataset".to_string()),
+                ..Default::default()
+            }))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/v2/teams/some-team/workflows"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!([
+                workflow_json(4, "Other Dataset", "workflow-1"),
+                workflow_json(3, "Some Dataset", "workflow-2")
+            ])))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/v2/teams/some-team/items/stage"))
+            .and(body_partial_json(json!({
+                "filters": {"dataset_ids": [3], "select_all": true},
+                "stage_id": "stage-1",
+                "workflow_id": "workflow-2"
+            })))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({"created_commands": 2})))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let client: V7Client = V7Client::new(
+            format!("{}/", mock_server.uri()),
+            "api-key".to_string(),
+            "some-team".to_string(),
+        )
+        .expect("Failed to get V7Client");
+
+        let workflow = dataset_ref
+            .get_workflow_v2(&client)
+            .await
+            .expect("Failed to get workflow")
+            .expect("Dataset has no workflow");
+        assert_eq!(workflow.id, Some("workflow-2".to_string()));
+
+        let response = dataset_ref
+            .set_stage_v2(
+                &client,
+                StageId::from("stage-1"),
+                WorkflowId::from("workflow-2"),
+                None,
+            )
+            .await
+            .expect("Failed to set stage");
+        assert_eq!(response.created_commands, Some(2));
+    }
+
+    #[tokio::test]
+    async fn test_generate_checked_export() {
+        let mock_server = MockServer::start().await;
+        let mut rng = AlwaysTrueRng::default();
+        let mut dataset: Dataset = Faker.fake_with_rng(&mut rng);
+        dataset.team_slug = Some("some-team".to_string());
+        dataset.slug = Some("some-dataset".to_string());
+
+        // Cvat does not support videos, PDFs or DICOMs, each is checked with a single item
+        Mock::given(method("GET"))
+            .and(path("/v2/teams/some-team/items"))
+            .and(query_param("types[]", "video"))
+            .and(query_param("page[size]", "1"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "items": [{"id": "item-2", "slots": [{"slot_name": "0", "type": "video"}]}],
+                "page": {}
+            })))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/v2/teams/some-team/items"))
+            .respond_with(
+                ResponseTemplate::new(200).set_body_json(json!({"items": [], "page": {}})),
+            )
+            .expect(2)
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/v2/teams/some-team/datasets/some-dataset/exports"))
+            .and(body_partial_json(json!({"format": "darwin_json_2"})))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let client: V7Client = V7Client::new(
+            format!("{}/", mock_server.uri()),
+            "api-key".to_string(),
+            "some-team".to_string(),
+        )
+        .expect("Failed to get V7Client");
+
+        let error = dataset
+            .generate_checked_export(&client, "export", &ExportFormat::Cvat, false, false, None)
+            .await
+            .unwrap_err();
+        let error = error.downcast_ref::<UnsupportedExportFormat>().unwrap();
+        assert_eq!(error.item_types, vec![DatasetItemTypes::Video]);
+
+        dataset
+            .generate_checked_export(
+                &client,
+                "export",
+                &ExportFormat::DarwinJson2,
+                false,
+                false,
+                None,
+            )
+            .await
+            .expect("Failed to generate export");
+    }
+
+    #[tokio::test]
+    async fn test_get_stage_times() {
+        let mock_server = MockServer::start().await;
+        let mut rng = AlwaysTrueRng::default();
+        let mut dataset: Dataset = Faker.fake_with_rng(&mut rng);
+        dataset.team_slug = Some("some-team".to_string());
+        dataset.name = Some("some-dataset".to_string());
+
+        let workflows = json!([{
+            "dataset": {"id": dataset.id, "name": "some-dataset"},
+            "id": "workflow-1",
+            "inserted_at": null,
+            "name": "workflow",
+            "progress": null,
+            "stages": [{
+                "assignable_users": [],
+                "config": null,
+                "edges": [],
+                "id": "review-stage",
+                "name": "Review",
+                "type": "review"
+            }],
+            "team_id": 1,
+            "thumbnails": [],
+            "updated_at": null
+        }]);
+        let items = json!({
+            "items": [{
+                "archived": false,
+                "cursor": null,
+                "dataset_id": dataset.id,
+                "id": "item-1",
+                "inserted_at": null,
+                "layout": null,
+                "name": "item-1.png",
+                "path": "/",
+                "priority": 0,
+                "processing_status": "complete",
+                "slot_types": [],
+                "slots": [],
+                "status": "review",
+                "tags": [],
+                "updated_at": null,
+                "uploads": [],
+                "workflow_status": "review",
+                "workflow_data": {
+                    "workflow_id": "workflow-1",
+                    "current_stage_instances": [{
+                        "id": "instance-1",
+                        "stage_id": "review-stage",
+                        "user_id": 42,
+                        "inserted_at": "2023-08-01T00:00:00Z",
+                        "updated_at": null
+                    }]
+                }
+            }],
+            "page": {"count": 1, "previous": null}
+        });
+
+        Mock::given(method("GET"))
+            .and(path("/v2/teams/some-team/workflows"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(workflows))
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/v2/teams/some-team/items"))
+            .and(query_param("include_workflow_data", "true"))
+            .and(query_param("item_path_prefix", "/scans"))
+            .and(query_param("item_name_prefix", "other"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "items": [],
+                "page": {"count": 0, "previous": null}
+            })))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/v2/teams/some-team/items"))
+            .and(query_param("include_workflow_data", "true"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(items))
+            .mount(&mock_server)
+            .await;
+
+        let client: V7Client = V7Client::new(
+            format!("{}/", mock_server.uri()),
+            "api-key".to_string(),
+            "some-team".to_string(),
+        )
+        .expect("Failed to get V7Client");
+
+        let times = dataset
+            .get_stage_times(&client)
+            .await
+            .expect("Failed to get stage times");
+
+        assert_eq!(times.len(), 1);
+        assert_eq!(times[0].item_id, Some("item-1".to_string()));
+        assert_eq!(times[0].stage_name, Some("Review".to_string()));
+        assert_eq!(
+            times[0].stage_type,
+            Some(crate::workflow::StageType::Review)
+        );
+        assert_eq!(times[0].assignee_id, Some(42));
+        assert_eq!(
+            times[0].entered_at,
+            Some("2023-08-01T00:00:00Z".to_string())
+        );
+
+        let counts = dataset
+            .get_stage_counts(&client, &StageCountFilter::default())
+            .await
+            .expect("Failed to get stage counts");
+        assert_eq!(counts.total(), 1);
+        assert_eq!(
+            counts
+                .stage(&StageId::from("review-stage"))
+                .unwrap()
+                .item_count,
+            1
+        );
+        let filter = StageCountFilter {
+            folder: Some("scans/".to_string()),
+            name_prefix: Some("other".to_string()),
+        };
+        let counts = dataset.get_stage_counts(&client, &filter).await.unwrap();
+        assert_eq!(counts.total(), 0);
+        assert_eq!(
+            counts
+                .stage(&StageId::from("review-stage"))
+                .unwrap()
+                .item_count,
+            0
+        );
+    }
+
+    #[tokio::test]
+    async fn test_list_dataset_items_with_options() {
+        let mock_server = MockServer::start().await;
+        let dataset = Dataset {
+            id: Some(1),
+            slug: Some("some-dataset".to_string()),
+            team_slug: Some("some-team".to_string()),
+            ..Default::default()
+        };
+
+        Mock::given(method("GET"))
+            .and(path("/v2/teams/some-team/items"))
+            .and(query_param("dataset_ids", "1"))
+            .and(query_param("include_workflow_data", "true"))
+            .and(query_param("include_first_sections", "true"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "items": [{
+                    "id": "item-1",
+                    "slots": [{
+                        "slot_name": "0",
+                        "first_sections": [{"section_index": 0, "thumbnail_url": "https://thumb"}]
+                    }],
+                    "current_stage": {"id": "review-stage", "name": "Review", "type": "review"},
+                    "assignees": [{"user_id": 42, "email": "kevin@mail.com", "stage_id": "review-stage"}]
+                }],
+                "page": {"count": 1, "next": null, "previous": null}
+            })))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let client: V7Client = V7Client::new(
+            format!("{}/", mock_server.uri()),
+            "api-key".to_string(),
+            "some-team".to_string(),
+        )
+        .expect("Failed to get V7Client");
+
+        let options = ItemListOptions {
+            include_workflow_data: true,
+            include_first_sections: true,
+            ..Default::default()
+        };
+        let items = dataset
+            .list_dataset_items_with_options(&client, &options)
+            .await
+            .expect("Failed to list items");
+
+        let current_stage = items[0].current_stage.as_ref().unwrap();
+        assert_eq!(current_stage.name.as_deref(), Some("Review"));
+        assert_eq!(
+            current_stage.stage_type,
+            Some(crate::workflow::StageType::Review)
+        );
+        assert_eq!(items[0].assignees[0].user_id, Some(UserId(42)));
+        let slot = items[0].slots[0].as_ref().unwrap();
+        assert_eq!(
+            slot.first_sections[0].thumbnail_url.as_deref(),
+            Some("https://thumb")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_list_dataset_items_limit() {
+        let mock_server = MockServer::start().await;
+        let dataset = Dataset {
+            id: Some(1),
+            slug: Some("some-dataset".to_string()),
+            team_slug: Some("some-team".to_string()),
+            ..Default::default()
+        };
+        let page = |ids: &[&str], next: Option<&str>| {
+            json!({
+                "items": ids.iter().map(|id| json!({"id": id})).collect::<Vec<_>>(),
+                "page": {"count": 5, "next": next, "previous": null}
+            })
+        };
+
+        Mock::given(method("GET"))
+            .and(path("/v2/teams/some-team/items"))
+            .and(query_param("statuses[]", "complete"))
+            .and(query_param("page[size]", "3"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_json(page(&["item-1", "item-2"], Some("cursor-1"))),
+            )
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/v2/teams/some-team/items"))
+            .and(query_param("page[size]", "1"))
+            .and(query_param("page[from]", "cursor-1"))
+            .respond_with(
+                ResponseTemplate::new(200).set_body_json(page(&["item-3"], Some("cursor-2"))),
+            )
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let client: V7Client = V7Client::new(
+            format!("{}/", mock_server.uri()),
+            "api-key".to_string(),
+            "some-team".to_string(),
+        )
+        .expect("Failed to get V7Client");
+
+        let options = ItemListOptions {
+            statuses: vec![DatasetItemStatus::Complete],
+            limit: Some(3),
+            ..Default::default()
+        };
+        let items = dataset
+            .list_dataset_items_with_options(&client, &options)
+            .await
+            .expect("Failed to list items");
+        let ids: Vec<Option<&str>> = items.iter().map(|item| item.id.as_deref()).collect();
+        assert_eq!(ids, vec![Some("item-1"), Some("item-2"), Some("item-3")]);
+    }
+
+    #[tokio::test]
+    async fn test_set_double_assignment() {
+        let mock_server = MockServer::start().await;
+        let dataset = Dataset {
+            id: Some(7),
+            slug: Some("some-dataset".to_string()),
+            team_slug: Some("some-team".to_string()),
+            work_size: Some(10),
+            ..Default::default()
+        };
+
+        // The settings changed elsewhere since `dataset` was fetched must be kept
+        Mock::given(method("GET"))
+            .and(path("/datasets/7"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(Dataset {
+                work_size: Some(25),
+                anyone_can_double_assign: Some(false),
+                ..dataset.clone()
+            }))
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("PUT"))
+            .and(path("/datasets/7"))
+            .and(body_partial_json(json!({
+                "work_size": 25,
+                "anyone_can_double_assign": true
+            })))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({})))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let client: V7Client = V7Client::new(
+            format!("{}/", mock_server.uri()),
+            "api-key".to_string(),
+            "some-team".to_string(),
+        )
+        .expect("Failed to get V7Client");
+
+        dataset
+            .set_double_assignment(&client, true)
+            .await
+            .expect("Failed to set double assignment");
+    }
+
+    #[tokio::test]
+    async fn test_set_work_prioritization() {
+        let mock_server = MockServer::start().await;
+        let dataset_ref = DatasetRef::new(DatasetId(7), "some-dataset", "some-team");
+
+        Mock::given(method("GET"))
+            .and(path("/datasets/7"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "id": 7,
+                "work_prioritization": "inserted_at:asc"
+            })))
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("PUT"))
+            .and(path("/datasets/7"))
+            .and(body_partial_json(
+                json!({"work_prioritization": "priority:desc"}),
+            ))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({})))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let client: V7Client = V7Client::new(
+            format!("{}/", mock_server.uri()),
+            "api-key".to_string(),
+            "some-team".to_string(),
+        )
+        .expect("Failed to get V7Client");
+
+        dataset_ref
+            .set_work_prioritization(&client, WorkPrioritization::PriorityDesc)
+            .await
+            .expect("Failed to set work prioritization");
+    }
+
+    #[tokio::test]
+    async fn test_add_instruction_image() {
+        let mock_server = MockServer::start().await;
+        let dataset = Dataset {
+            id: Some(7),
+            slug: Some("some-dataset".to_string()),
+            team_slug: Some("some-team".to_string()),
+            instructions: Some("<p>Outline the tumour</p>".to_string()),
+            ..Default::default()
+        };
+        let hosted = "https://cdn.v7labs.com/instructions/example.png?v=2&name=\"x\"";
+
+        Mock::given(method("POST"))
+            .and(path("/datasets/7/instructions/attachments"))
+            .and(body_json(json!({
+                "file_name": "example.png",
+                "content_type": "image/png"
+            })))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "upload_url": format!("{}/signed/example.png", mock_server.uri()),
+                "url": hosted
+            })))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("PUT"))
+            .and(path("/signed/example.png"))
+            .and(header("content-type", "image/png"))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/datasets/7"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(dataset.clone()))
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("PUT"))
+            .and(path("/datasets/7"))
+            .and(body_partial_json(json!({
+                "instructions": r#"<p>Outline the tumour</p><p><img src="https://cdn.v7labs.com/instructions/example.png?v=2&amp;name=&quot;x&quot;"></p>"#
+            })))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({})))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let client: V7Client = V7Client::new(
+            format!("{}/", mock_server.uri()),
+            "api-key".to_string(),
+            "some-team".to_string(),
+        )
+        .expect("Failed to get V7Client");
+        let dir = tempfile::tempdir().unwrap();
+        let image = dir.path().join("example.png");
+        std::fs::write(&image, b"png").unwrap();
+
+        let url = dataset
+            .add_instruction_image(&client, &reqwest::Client::new(), &image)
+            .await
+            .expect("Failed to add instruction image");
+        assert_eq!(url, hosted);
+    }
+
+    #[tokio::test]
+    async fn test_resync_items() {
+        let mock_server = MockServer::start().await;
+        let dataset = Dataset {
+            id: Some(7),
+            slug: Some("some-dataset".to_string()),
+            team_slug: Some("some-team".to_string()),
+            ..Default::default()
+        };
+
+        Mock::given(method("POST"))
+            .and(path("/v2/teams/some-team/items/reprocess"))
+            .and(body_json(json!({
+                "filters": {"dataset_ids": [7], "item_paths": ["/backfill"]}
+            })))
+            .respond_with(ResponseTemplate::new(204))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let client: V7Client = V7Client::new(
+            format!("{}/", mock_server.uri()),
+            "api-key".to_string(),
+            "some-team".to_string(),
+        )
+        .expect("Failed to get V7Client");
+
+        let filter = Filter::builder().in_folder("/backfill").build().unwrap();
+        dataset
+            .resync_items(&client, &filter)
+            .await
+            .expect("Failed to resync items");
     }
 
-    async fn set_stage_v2(
-        &self,
-        client: &C,
-        stage_id: String,
-        workflow_id: String,
-        filters: Option<SetStageFilter>,
-    ) -> Result<SetStageResponse> {
-        let filters = if filters.is_none() {
-            SetStageFilter {
-                dataset_ids: vec![self.id.context("Dataset missing Id")?],
-                select_all: true,
-                workflow_stage_ids: None,
-            }
-        } else {
-            filters.context("Invalid filter to set stage")?
+    #[tokio::test]
+    async fn test_import_annotation_idempotent() {
+        let mock_server = MockServer::start().await;
+        let dataset = Dataset {
+            id: Some(3),
+            slug: Some("some-dataset".to_string()),
+            team_slug: Some("some-team".to_string()),
+            ..Default::default()
         };
-
-        let payload = SetStagePayloadV2 {
-            filters,
-            stage_id,
-            workflow_id,
+        let annotation = |id: &str, on_existing: OnExisting| AnnotationImportAnnotation {
+            id: id.to_string(),
+            data: AnnotationImportData {
+                tag: Some(Default::default()),
+                ..Default::default()
+            },
+            annotation_class_id: 1,
+            context_keys: AnnotationContext {
+                slot_names: vec!["0".to_string()],
+            },
+            properties: vec![],
+            on_existing,
         };
-        let response = client
-            .post(&format!("v2/teams/{}/items/stage", client.team()), &payload)
-            .await?;
-        expect_http_ok!(response, SetStageResponse)
+        let import = AnnotationImport {
+            annotations: vec![
+                annotation("a", OnExisting::Update),
+                annotation("b", OnExisting::Skip),
+                annotation("c", OnExisting::Skip),
+            ],
+            overwrite: false,
+        };
+
+        Mock::given(method("GET"))
+            .and(path("/v2/teams/some-team/items/item-1/annotations"))
+            .respond_with(
+                ResponseTemplate::new(200).set_body_json(json!([{"id": "a"}, {"id": "b"}])),
+            )
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("DELETE"))
+            .and(path("/v2/teams/some-team/items/item-1/annotations"))
+            .and(body_json(json!({"annotation_ids": ["a"]})))
+            .respond_with(ResponseTemplate::new(204))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/v2/teams/some-team/items/item-1/import"))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let client: V7Client = V7Client::new(
+            format!("{}/", mock_server.uri()),
+            "api-key".to_string(),
+            "some-team".to_string(),
+        )
+        .expect("Failed to get V7Client");
+
+        let plan = dataset
+            .import_annotation_idempotent(&client, &ItemId::from("item-1".to_string()), &import)
+            .await
+            .expect("Failed to import annotations");
+        assert_eq!(plan.replaced, vec!["a".to_string()]);
+        assert_eq!(plan.skipped, vec!["b".to_string()]);
+        assert_eq!(plan.import.annotations.len(), 2);
+
+        let requests = mock_server.received_requests().await.unwrap();
+        let body: serde_json::Value = requests.last().unwrap().body_json().unwrap();
+        assert_eq!(body["annotations"][0]["id"], "a");
+        assert_eq!(body["annotations"][1]["id"], "c");
     }
-}
 
-#[async_trait]
-impl<C> DatasetItemReportMethods<C> for Dataset
-where
-    C: V7Methods + std::marker::Sync,
-{
-    async fn get_item_reports(&self, client: &C) -> Result<Vec<ItemReport>> {
-        let endpoint = format!(
-            "teams/{}/datasets/{}/item_reports",
-            self.team_slug.as_ref().context("Missing team slug")?,
-            self.slug.as_ref().context("Dataset missing slug")?
-        );
-        let response = client.get(&endpoint).await?;
-        let status = response.status();
-        let result = response.text().await?;
-        if status != 200 {
-            bail!(format!("Invalid status code {} {}", status, result))
-        } else {
-            item_reports_from_bytes(result.as_bytes()).await
+    #[tokio::test]
+    async fn test_register_existing_read_write_items() {
+        let mock_server = MockServer::start().await;
+        let dataset = Dataset {
+            id: Some(3),
+            slug: Some("some-dataset".to_string()),
+            team_slug: Some("some-team".to_string()),
+            ..Default::default()
+        };
+        let items = RegistrationItems::ReadWrite(vec![ExistingReadWriteItem {
+            name: "image.png".to_string(),
+            path: "/".to_string(),
+            slots: vec![crate::item::ReadWriteSlot {
+                slot_name: "0".to_string(),
+                storage_key: "bucket/image.png".to_string(),
+                file_name: "image.png".to_string(),
+                ..Default::default()
+            }],
+        }]);
+
+        Mock::given(method("POST"))
+            .and(path("/v2/teams/some-team/items/register_existing"))
+            .and(body_json(json!({
+                "dataset_slug": "some-dataset",
+                "storage_slug": "some-storage",
+                "items": [{
+                    "name": "image.png",
+                    "path": "/",
+                    "slots": [{
+                        "slot_name": "0",
+                        "storage_key": "bucket/image.png",
+                        "file_name": "image.png"
+                    }]
+                }]
+            })))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "blocked_items": [],
+                "items": [{
+                    "id": "item-1",
+                    "name": "image.png",
+                    "path": "/",
+                    "slots": [{
+                        "as_frames": false,
+                        "extract_views": false,
+                        "file_name": "image.png",
+                        "slot_name": "0",
+                        "storage_key": "bucket/image.png",
+                        "type": "image",
+                        "upload_id": "upload-1"
+                    }]
+                }]
+            })))
+            .mount(&mock_server)
+            .await;
+
+        let client: V7Client = V7Client::new(
+            format!("{}/", mock_server.uri()),
+            "api-key".to_string(),
+            "some-team".to_string(),
+        )
+        .expect("Failed to get V7Client");
+
+        let response = dataset
+            .register_existing_items(&client, items, "some-storage".to_string())
+            .await
+            .expect("Failed to register items");
+
+        match response {
+            RegistrationResponse::ReadWrite(response) => {
+                assert_eq!(response.items.len(), 1);
+                assert_eq!(
+                    response.items[0].as_ref().unwrap().id,
+                    Some("item-1".to_string())
+                );
+            }
+            RegistrationResponse::ReadOnly(_) => panic!("Expected a read-write response"),
         }
     }
-}
 
-impl Display for Dataset {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(
-            f,
-            "{:?}:{}/{:?}",
-            self.id,
-            self.team_slug.as_ref().unwrap_or(&"team-slug".to_string()),
-            self.slug
+    #[tokio::test]
+    async fn test_find_items() {
+        let mock_server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/v2/teams/some-team/items"))
+            .and(query_param("item_names[]", "slide 1.svs"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "items": [
+                    {"id": "item-1", "name": "slide 1.svs", "slots": []},
+                    {"id": "item-2", "name": "slide 1.svs.bak", "slots": []}
+                ],
+                "page": {"count": 2, "next": null, "previous": null}
+            })))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/v2/teams/some-team/items"))
+            .and(query_param("storage_keys[]", "bucket/slide-2.svs"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "items": [
+                    {"id": "item-2", "name": "slide-2.svs", "slots": [{"storage_key": "bucket/slide-2.svs"}]},
+                    {"id": "item-3", "name": "slide-3.svs", "slots": [{"storage_key": "bucket/slide-3.svs"}]}
+                ],
+                "page": {"count": 2, "next": null, "previous": null}
+            })))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let client: V7Client = V7Client::new(
+            format!("{}/", mock_server.uri()),
+            "api-key".to_string(),
+            "some-team".to_string(),
         )
+        .expect("Failed to get V7Client");
+        let dataset = Dataset {
+            id: Some(3),
+            slug: Some("some-dataset".to_string()),
+            team_slug: Some("some-team".to_string()),
+            ..Default::default()
+        };
+
+        let item = dataset
+            .find_item_by_name(&client, "slide 1.svs")
+            .await
+            .expect("Failed to find item");
+        assert_eq!(item.and_then(|item| item.id), Some("item-1".to_string()));
+
+        let items = dataset
+            .find_items_by_storage_key(&client, &["bucket/slide-2.svs".to_string()])
+            .await
+            .expect("Failed to find items");
+        let ids: Vec<Option<String>> = items.into_iter().map(|item| item.id).collect();
+        assert_eq!(ids, vec![Some("item-2".to_string())]);
     }
-}
 
-#[cfg(test)]
-mod test_client_calls {
-    use super::*;
-    use crate::client::V7Client;
+    #[tokio::test]
+    async fn test_set_stage_v2_chunked() {
+        let mock_server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/v2/teams/some-team/items/stage"))
+            .and(body_partial_json(
+                json!({"filters": {"item_ids": ["a", "b"]}}),
+            ))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({"created_commands": 2})))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/v2/teams/some-team/items/stage"))
+            .and(body_partial_json(
+                json!({"filters": {"item_ids": ["c", "d"]}}),
+            ))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({"created_commands": 2})))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/v2/teams/some-team/items/stage"))
+            .and(body_partial_json(json!({"filters": {"item_ids": ["e"]}})))
+            .respond_with(ResponseTemplate::new(422))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
 
-    use fake::{Fake, Faker};
+        let client: V7Client = V7Client::new(
+            format!("{}/", mock_server.uri()),
+            "api-key".to_string(),
+            "some-team".to_string(),
+        )
+        .expect("Failed to get V7Client");
+        let dataset = Dataset {
+            id: Some(3),
+            slug: Some("some-dataset".to_string()),
+            team_slug: Some("some-team".to_string()),
+            ..Default::default()
+        };
 
-    // Utilizing Faker with an AlwaysTrueRng to guarantee that all Option types are populated with Some values
-    // This ensures consistent data generation where no field is left as None
-    use crate::item::DatasetItemV2;
-    use fake::utils::AlwaysTrueRng;
-    use serde_json::json;
-    use wiremock::matchers::{method, path, query_param};
-    use wiremock::{Mock, MockServer, ResponseTemplate};
+        let response = dataset
+            .set_stage_v2_chunked(
+                &client,
+                StageId::from("review"),
+                WorkflowId::from("workflow-1"),
+                SetStageFilter {
+                    dataset_ids: vec![DatasetId(3)],
+                    item_ids: Some(["a", "b", "c", "d", "e"].map(String::from).to_vec()),
+                    ..Default::default()
+                },
+                Some(2),
+            )
+            .await
+            .expect("Failed to set stage");
+
+        assert_eq!(response.chunks_sent, 3);
+        assert_eq!(response.created_commands, 4);
+        assert!(!response.is_success());
+        assert_eq!(response.failures.len(), 1);
+        assert_eq!(response.failures[0].item_ids, vec!["e".to_string()]);
+    }
 
     #[tokio::test]
-    async fn test_list_datasets() {
+    async fn test_set_stage_from_template() {
         let mock_server = MockServer::start().await;
-        let mock_data: Vec<Dataset> = fake::vec![Dataset; 2];
+        let dataset = Dataset {
+            id: Some(3),
+            slug: Some("some-dataset".to_string()),
+            team_slug: Some("some-team".to_string()),
+            default_workflow_template_id: Some(WorkflowTemplateId(12)),
+            ..Default::default()
+        };
+
+        Mock::given(method("GET"))
+            .and(path("/datasets/3/workflow_templates"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!([{
+                "id": 12,
+                "name": "Default",
+                "dataset_id": 3,
+                "workflow_stage_templates": [
+                    {"id": 100, "name": "Annotate", "stage_number": 1, "type": "annotate"},
+                    {"id": 101, "name": "Review", "stage_number": 2, "type": "review"}
+                ]
+            }])))
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("PUT"))
+            .and(path("/datasets/3/set_stage"))
+            .and(body_json(json!({
+                "workflow_stage_template_id": 101,
+                "filter": {"select_all": true}
+            })))
+            .respond_with(ResponseTemplate::new(204))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
 
         let client: V7Client = V7Client::new(
             format!("{}/", mock_server.uri()),
@@ -896,38 +5534,205 @@ mod test_client_calls {
         )
         .expect("Failed to get V7Client");
 
+        let templates = dataset
+            .list_workflow_templates(&client)
+            .await
+            .expect("Failed to list workflow templates");
+        let template = templates
+            .iter()
+            .find(|template| template.id == dataset.default_workflow_template_id)
+            .unwrap();
+        let stage_id = template.stage_by_name("Review").unwrap().id.unwrap();
+
+        dataset
+            .set_stage(
+                &client,
+                stage_id,
+                &Filter::builder().select_all().build().unwrap(),
+            )
+            .await
+            .expect("Failed to set stage");
+    }
+
+    #[tokio::test]
+    async fn test_set_default_workflow_template() {
+        let mock_server = MockServer::start().await;
+        let dataset = Dataset {
+            id: Some(3),
+            team_slug: Some("some-team".to_string()),
+            slug: Some("biopsies".to_string()),
+            ..Default::default()
+        };
+
         Mock::given(method("GET"))
-            .and(path("/datasets"))
-            .respond_with(ResponseTemplate::new(200).set_body_json(mock_data.clone()))
+            .and(path("/datasets/3/workflow_templates"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!([{"id": 12}, {"id": 13}])))
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/datasets/3"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "id": 3,
+                "name": "Biopsies",
+                "work_size": 30,
+                "default_workflow_template_id": 12
+            })))
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("PUT"))
+            .and(path("/datasets/3"))
+            .and(body_partial_json(json!({
+                "name": "Biopsies",
+                "work_size": 30,
+                "default_workflow_template_id": 13
+            })))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({"id": 3})))
+            .expect(1)
             .mount(&mock_server)
             .await;
 
-        let datasets = Dataset::list_datasets(&client)
+        let client: V7Client = V7Client::new(
+            format!("{}/", mock_server.uri()),
+            "api-key".to_string(),
+            "some-team".to_string(),
+        )
+        .expect("Failed to get V7Client");
+
+        dataset
+            .set_default_workflow_template(&client, WorkflowTemplateId(13))
             .await
-            .expect("Failed to list datasets");
+            .expect("Failed to set workflow template");
+        assert!(dataset
+            .set_default_workflow_template(&client, WorkflowTemplateId(14))
+            .await
+            .is_err());
+    }
 
-        // Pick a few values to avoid f64 comparison issues
-        assert_eq!(datasets.len(), mock_data.len());
-        assert_eq!(
-            datasets[0].as_ref().expect("Expected dataset").id,
-            mock_data[0].id
-        );
-        assert_eq!(
-            datasets[0].as_ref().expect("Expected dataset").slug,
-            mock_data[0].slug
-        );
+    #[tokio::test]
+    async fn test_v1_methods_rejected_for_v2_clients() {
+        let mock_server = MockServer::start().await;
+        Mock::given(method("PUT"))
+            .respond_with(ResponseTemplate::new(404))
+            .expect(0)
+            .mount(&mock_server)
+            .await;
+
+        let client = V7Client::builder(
+            format!("{}/", mock_server.uri()),
+            "api-key".to_string(),
+            "some-team".to_string(),
+        )
+        .api_version(ApiVersion::V2)
+        .build()
+        .expect("Failed to get V7Client");
+        let dataset = Dataset {
+            id: Some(3),
+            slug: Some("some-dataset".to_string()),
+            team_slug: Some("some-team".to_string()),
+            ..Default::default()
+        };
+
+        let error = dataset
+            .set_stage(
+                &client,
+                WorkflowStageTemplateId(12),
+                &Filter::builder().select_all().build().unwrap(),
+            )
+            .await
+            .unwrap_err();
         assert_eq!(
-            datasets[1].as_ref().expect("Expected dataset").inserted_at,
-            mock_data[1].inserted_at
+            error.downcast_ref::<UnsupportedApiVersionError>(),
+            Some(&UnsupportedApiVersionError {
+                operation: "set_stage",
+                required: ApiVersion::V1,
+                version: ApiVersion::V2,
+            })
         );
     }
 
     #[tokio::test]
-    async fn test_list_datasets_status_error() {
+    async fn test_register_export_callback() {
+        let mock_server = MockServer::start().await;
+        let dataset = |id: u32| Dataset {
+            id: Some(id),
+            slug: Some("some-dataset".to_string()),
+            team_slug: Some("some-team".to_string()),
+            ..Default::default()
+        };
+
+        Mock::given(method("GET"))
+            .and(path("/v2/teams/some-team/webhooks"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!([{
+                "id": "hook-1",
+                "url": "https://hooks.franklin.ai/exports",
+                "events": ["export_finished"],
+                "dataset_ids": [1]
+            }])))
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/v2/teams/some-team/webhooks"))
+            .and(body_json(json!({
+                "url": "https://hooks.franklin.ai/exports",
+                "events": ["export_finished"],
+                "dataset_ids": [2],
+                "authorization_header": "Bearer secret",
+                "enabled": true
+            })))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "id": "hook-2",
+                "url": "https://hooks.franklin.ai/exports",
+                "events": ["export_finished"],
+                "dataset_ids": [2]
+            })))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let client: V7Client = V7Client::new(
+            format!("{}/", mock_server.uri()),
+            "api-key".to_string(),
+            "some-team".to_string(),
+        )
+        .expect("Failed to get V7Client");
+
+        let reused = dataset(1)
+            .register_export_callback(
+                &client,
+                "https://hooks.franklin.ai/exports",
+                Some("Bearer secret"),
+            )
+            .await
+            .expect("Failed to register export callback");
+        assert_eq!(reused.id.as_deref(), Some("hook-1"));
+        let created = dataset(2)
+            .register_export_callback(
+                &client,
+                "https://hooks.franklin.ai/exports",
+                Some("Bearer secret"),
+            )
+            .await
+            .expect("Failed to register export callback");
+        assert_eq!(created.id.as_deref(), Some("hook-2"));
+    }
+
+    #[tokio::test]
+    async fn test_wait_for_export() {
         let mock_server = MockServer::start().await;
+        let dataset = Dataset {
+            id: Some(1),
+            slug: Some("some-dataset".to_string()),
+            team_slug: Some("some-team".to_string()),
+            ..Default::default()
+        };
+        let exports = json!([
+            {"name": "pending", "status": "pending", "download_url": null},
+            {"name": "ready", "status": "complete", "download_url": "https://some-url.com/export.zip"}
+        ]);
+
         Mock::given(method("GET"))
-            .and(path("/datasets"))
-            .respond_with(ResponseTemplate::new(412))
+            .and(path("/v2/teams/some-team/datasets/some-dataset/exports"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(exports.clone()))
             .mount(&mock_server)
             .await;
 
@@ -938,138 +5743,345 @@ mod test_client_calls {
         )
         .expect("Failed to get V7Client");
 
-        Dataset::list_datasets(&client)
+        let export = dataset
+            .wait_for_export(&client, "ready", Duration::from_millis(10), None)
             .await
-            .expect_err("Invalid status code 412");
-    }
+            .expect("Failed to wait for export");
+        assert_eq!(
+            export.download_url,
+            Some("https://some-url.com/export.zip".to_string())
+        );
 
-    #[tokio::test]
-    async fn test_list_datasets_data_error() {
+        dataset
+            .wait_for_export(
+                &client,
+                "pending",
+                Duration::from_millis(10),
+                Some(Duration::from_millis(50)),
+            )
+            .await
+            .expect_err("Timed out");
+
+        // A poll that hangs past the deadline is abandoned
         let mock_server = MockServer::start().await;
         Mock::given(method("GET"))
-            .and(path("/datasets"))
-            .respond_with(ResponseTemplate::new(200).set_body_string("dont_render"))
+            .and(path("/v2/teams/some-team/datasets/some-dataset/exports"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_json(json!([]))
+                    .set_delay(Duration::from_secs(30)),
+            )
             .mount(&mock_server)
             .await;
-
         let client: V7Client = V7Client::new(
             format!("{}/", mock_server.uri()),
             "api-key".to_string(),
             "some-team".to_string(),
         )
         .expect("Failed to get V7Client");
+        let start = Instant::now();
+        let error = dataset
+            .wait_for_export(
+                &client,
+                "ready",
+                Duration::from_millis(10),
+                Some(Duration::from_millis(100)),
+            )
+            .await
+            .expect_err("Timed out");
+        assert!(error.to_string().starts_with("Timed out"));
+        assert!(start.elapsed() < Duration::from_secs(5));
 
-        Dataset::list_datasets(&client)
+        // An export completed by the deadline is found by a last poll at the deadline
+        let mock_server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/v2/teams/some-team/datasets/some-dataset/exports"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(
+                json!([{"name": "ready", "status": "pending", "download_url": null}]),
+            ))
+            .up_to_n_times(1)
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/v2/teams/some-team/datasets/some-dataset/exports"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(exports))
+            .mount(&mock_server)
+            .await;
+        let client: V7Client = V7Client::new(
+            format!("{}/", mock_server.uri()),
+            "api-key".to_string(),
+            "some-team".to_string(),
+        )
+        .expect("Failed to get V7Client");
+        let export = dataset
+            .wait_for_export(
+                &client,
+                "ready",
+                Duration::from_millis(100),
+                Some(Duration::from_millis(100)),
+            )
             .await
-            .expect_err("error decoding response body: expected value at line 1 column 1");
+            .expect("Failed to wait for export");
+        assert_eq!(export.status.as_deref(), Some("complete"));
     }
 
     #[tokio::test]
-    async fn test_list_dataset_items() {
+    async fn test_export_download() {
         let mock_server = MockServer::start().await;
-        let mut rng = AlwaysTrueRng::default();
-        let mut mock_data: Dataset = Faker.fake_with_rng(&mut rng);
-        mock_data.team_slug = Some("some-team".to_string());
+        let content = b"some export archive";
+        let md5 = format!("{:x}", Md5::digest(content));
 
-        let dset_id = mock_data.id.expect("Id must be set");
+        Mock::given(method("GET"))
+            .and(path("/export.zip"))
+            .and(header("range", "bytes=5-"))
+            .and(header("if-range", format!("\"{md5}\"")))
+            .respond_with(
+                ResponseTemplate::new(206)
+                    .insert_header("content-range", format!("bytes 5-18/{}", content.len()))
+                    .insert_header("etag", format!("\"{md5}\""))
+                    .set_body_bytes(&content[5..]),
+            )
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/export.zip"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .insert_header("etag", format!("\"{md5}\""))
+                    .set_body_bytes(&content[..]),
+            )
+            .mount(&mock_server)
+            .await;
 
-        // Just generate two random values for comparison
-        // let mock_result_vec: Vec<DatasetItemV2> = vec![mock_dataset_item; 2];
-        let mock_result: Item = Item {
-            items: vec![Some(DatasetItemV2 {
-                id: Some("123".to_string()),
-                ..Default::default()
-            })],
+        let export = Export {
+            download_url: Some(format!("{}/export.zip", mock_server.uri())),
             ..Default::default()
         };
+        let dir = tempfile::tempdir().unwrap();
+        let destination = dir.path().join("export.zip");
+        let client = reqwest::Client::new();
+
+        let events = std::sync::Mutex::new(Vec::new());
+        let download = export
+            .download(&client, &destination, &|event| {
+                events.lock().unwrap().push(event)
+            })
+            .await
+            .expect("Failed to download export");
+        assert_eq!(download.size_bytes, content.len() as u64);
+        assert_eq!(download.resumed_from, 0);
+        assert!(download.checksum_verified);
+        let events = events.into_inner().unwrap();
+        assert_eq!(
+            events.first(),
+            Some(&ProgressEvent::Started {
+                operation: "export download",
+                total: Some(19)
+            })
+        );
+        assert_eq!(
+            events[events.len() - 2],
+            ProgressEvent::BytesTransferred {
+                done: 19,
+                total: Some(19)
+            }
+        );
+        assert_eq!(
+            events.last(),
+            Some(&ProgressEvent::Finished {
+                operation: "export download"
+            })
+        );
+        assert_eq!(std::fs::read(&destination).unwrap(), content);
 
-        let mock_items: Vec<_> = mock_result.items.iter().flatten().collect();
+        assert!(!partial_etag_path(&destination).exists());
 
-        let client: V7Client = V7Client::new(
-            format!("{}/", mock_server.uri()),
-            "api-key".to_string(),
-            "some-team".to_string(),
-        )
-        .expect("Failed to get V7Client");
+        // Resume an interrupted download of the same archive
+        std::fs::remove_file(&destination).unwrap();
+        std::fs::write(partial_download_path(&destination), &content[..5]).unwrap();
+        std::fs::write(partial_etag_path(&destination), format!("\"{md5}\"")).unwrap();
+        let download = export
+            .download(&client, &destination, &NoProgress)
+            .await
+            .expect("Failed to resume export download");
+        assert_eq!(download.resumed_from, 5);
+        assert!(download.checksum_verified);
+        assert_eq!(std::fs::read(&destination).unwrap(), content);
+        assert!(!partial_download_path(&destination).exists());
+        assert!(!partial_etag_path(&destination).exists());
+
+        // Without the ETag of the archive the partial file cannot be validated
+        std::fs::remove_file(&destination).unwrap();
+        std::fs::write(partial_download_path(&destination), b"stale").unwrap();
+        let download = export
+            .download(&client, &destination, &NoProgress)
+            .await
+            .expect("Failed to restart export download");
+        assert_eq!(download.resumed_from, 0);
+        assert_eq!(std::fs::read(&destination).unwrap(), content);
 
+        // A partial file longer than the archive is rejected and downloaded again
         Mock::given(method("GET"))
-            .and(path("/v2/teams/some-team/items"))
-            .and(query_param("dataset_ids".to_string(), dset_id.to_string()))
-            .respond_with(ResponseTemplate::new(200).set_body_json(mock_result.clone()))
+            .and(path("/export.zip"))
+            .and(header("range", "bytes=25-"))
+            .respond_with(
+                ResponseTemplate::new(416)
+                    .insert_header("content-range", format!("bytes */{}", content.len())),
+            )
+            .with_priority(1)
             .mount(&mock_server)
             .await;
-
-        let result = mock_data
-            .list_dataset_items_v2(&client)
+        std::fs::remove_file(&destination).unwrap();
+        std::fs::write(partial_download_path(&destination), [0; 25]).unwrap();
+        std::fs::write(partial_etag_path(&destination), format!("\"{md5}\"")).unwrap();
+        let events = std::sync::Mutex::new(Vec::new());
+        let download = export
+            .download(&client, &destination, &|event| {
+                events.lock().unwrap().push(event)
+            })
             .await
-            .expect("Failed to list dataset items");
-        let expected_items: Vec<_> = result.items.iter().flatten().collect();
-
-        // Only compare a few values, this is mostly testing the endpoint
-        // invocation and not serde.
-        assert_eq!(expected_items.len(), mock_items.len());
-        assert_eq!(expected_items[0].status, mock_items[0].status);
+            .expect("Failed to restart export download");
+        assert_eq!(download.resumed_from, 0);
+        assert_eq!(std::fs::read(&destination).unwrap(), content);
+        let events = events.into_inner().unwrap();
         assert_eq!(
-            expected_items[expected_items.len() - 1].id,
-            mock_items[mock_items.len() - 1].id
+            events[0],
+            ProgressEvent::Started {
+                operation: "export download",
+                total: Some(19)
+            }
+        );
+        assert!(matches!(events[1], ProgressEvent::Retry { attempt: 1, .. }));
+        assert_eq!(
+            events
+                .iter()
+                .filter(|event| matches!(event, ProgressEvent::Started { .. }))
+                .count(),
+            1
         );
     }
 
     #[tokio::test]
-    async fn test_archive_dataset_items() {
+    async fn test_get_item_reports() {
         let mock_server = MockServer::start().await;
-        let mut rng = AlwaysTrueRng::default();
-        let mut mock_data: Dataset = Faker.fake_with_rng(&mut rng);
-        let team_slug = mock_data.id;
-        mock_data.team_slug = team_slug.map(|s| s.to_string());
+        let mock_data = "filename,uploaded_date,status,workflow_start_date,workflow_complete_date,number_of_frames,folder,time_spent_annotating_sec,time_spent_reviewing_sec,automation_time_annotating_sec,automation_time_reviewing_sec,annotators,reviewers,was_rejected_in_review,url
+somefilename,2023-05-10 14:15:27,complete,2023-05-10 14:16:17,2023-05-17 01:28:13,,/,320,1,2,3,kevin@mail.com,,false,https://darwin.v7labs.com/workview?dataset=123456&image=789";
 
-        let dset_id: Option<Vec<u32>> = Some(vec![mock_data.id.expect("Id must be set")]);
-        let complete_status: Option<Vec<String>> = Some(vec!["Complete".to_string()]);
+        let mut rng = AlwaysTrueRng::default();
+        let mut dataset: Dataset = Faker.fake_with_rng(&mut rng);
+        let dataset_slug = dataset
+            .slug
+            .clone()
+            .expect("Dataset slug should not be null");
+        let team_slug = "some-team";
+        dataset.team_slug = Some(team_slug.to_string());
 
-        let filter = Filter {
-            dataset_ids: dset_id,
-            statuses: complete_status,
-            ..Default::default()
-        };
+        Mock::given(method("GET"))
+            .and(path(format!(
+                "/teams/{team_slug}/datasets/{dataset_slug}/item_reports",
+            )))
+            .respond_with(ResponseTemplate::new(200).set_body_string(mock_data.to_string()))
+            .mount(&mock_server)
+            .await;
 
         let client: V7Client = V7Client::new(
             format!("{}/", mock_server.uri()),
             "api-key".to_string(),
             "some-team".to_string(),
         )
-        .expect("Failed to get V7Client");
+        .expect("Failed to create V7 client");
 
-        Mock::given(method("POST"))
-            .and(path(format!(
-                "v2/teams/{}/items/archive",
-                team_slug.expect("Team slug should be set")
-            )))
-            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
-                "affected_item_count": 1,
-            })))
+        let results = dataset
+            .get_item_reports(&client)
+            .await
+            .expect("Failed to get item reports");
+
+        assert_eq!(results.len(), 1);
+
+        let result = results.first().expect("Get item reports had no result");
+
+        assert_eq!(result.filename, Some("somefilename".to_string()));
+    }
+
+    #[tokio::test]
+    async fn test_stream_item_reports() {
+        let mock_server = MockServer::start().await;
+        let mut mock_data = "filename,uploaded_date,status,workflow_start_date,workflow_complete_date,number_of_frames,folder,time_spent_annotating_sec,time_spent_reviewing_sec,automation_time_annotating_sec,automation_time_reviewing_sec,annotators,reviewers,was_rejected_in_review,url".to_string();
+        for idx in 0..1000 {
+            mock_data.push_str(&format!(
+                "\nfile-{idx},2023-05-10 14:15:27,complete,,,,/,,,,,,,false,"
+            ));
+        }
+        let dataset = Dataset {
+            id: Some(1),
+            slug: Some("some-dataset".to_string()),
+            team_slug: Some("some-team".to_string()),
+            ..Default::default()
+        };
+
+        Mock::given(method("GET"))
+            .and(path("/teams/some-team/datasets/some-dataset/item_reports"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(mock_data))
             .mount(&mock_server)
             .await;
 
-        let result = mock_data
-            .archive_items(&client, &filter)
-            .await
-            .expect("Failed to archive items");
+        let client: V7Client = V7Client::new(
+            format!("{}/", mock_server.uri()),
+            "api-key".to_string(),
+            "some-team".to_string(),
+        )
+        .expect("Failed to create V7 client");
 
-        assert_eq!(result.affected_item_count, Some(1));
+        let mut reports = dataset
+            .stream_item_reports(&client)
+            .await
+            .expect("Failed to stream item reports");
+        let mut count = 0;
+        while let Some(report) = reports.next().await {
+            let report = report.expect("Failed to parse item report");
+            assert_eq!(report.filename, Some(format!("file-{count}")));
+            count += 1;
+        }
+        assert_eq!(count, 1000);
     }
 
     #[tokio::test]
-    async fn test_list_dataset_items_status_error() {
+    async fn test_dump_items() {
         let mock_server = MockServer::start().await;
-
-        let mut rng = AlwaysTrueRng::default();
-        let mock_data: Dataset = Faker.fake_with_rng(&mut rng);
-        let dset_id = mock_data.id.expect("Id must be set");
+        let dataset = Dataset {
+            id: Some(7),
+            slug: Some("some-dataset".to_string()),
+            team_slug: Some("some-team".to_string()),
+            ..Default::default()
+        };
 
         Mock::given(method("GET"))
             .and(path("/v2/teams/some-team/items"))
-            .and(query_param("dataset_ids".to_string(), dset_id.to_string()))
-            .respond_with(ResponseTemplate::new(412))
+            .and(query_param("page[from]", "cursor-1"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "items": [{"id": "item-2", "name": "b.png", "path": "/", "status": "new"}],
+                "page": {"next": null}
+            })))
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/v2/teams/some-team/items"))
+            .and(query_param("dataset_ids", "7"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "items": [{
+                    "id": "item-1",
+                    "name": "a.pdf",
+                    "path": "/scans",
+                    "status": "complete",
+                    "workflow_status": "complete",
+                    "inserted_at": "2023-07-13T23:48:49Z",
+                    "slots": [
+                        {"slot_name": "0", "type": "pdf", "size_bytes": 100},
+                        {"slot_name": "1", "type": "image", "size_bytes": 20}
+                    ]
+                }],
+                "page": {"next": "cursor-1"}
+            })))
             .mount(&mock_server)
             .await;
 
@@ -1078,56 +6090,147 @@ mod test_client_calls {
             "api-key".to_string(),
             "some-team".to_string(),
         )
-        .expect("Failed to get V7 Client");
+        .expect("Failed to get V7Client");
 
-        mock_data
-            .list_dataset_items_v2(&client)
+        let mut csv = Vec::new();
+        let written = dataset
+            .dump_items(&client, &mut csv, ItemDumpFormat::Csv)
             .await
-            .expect_err("Invalid status code 412");
+            .expect("Failed to dump items");
+        assert_eq!(written, 2);
+        assert_eq!(
+            String::from_utf8(csv).unwrap(),
+            "id,name,path,status,stage,slot_names,slot_types,size_bytes,inserted_at,updated_at\n\
+             item-1,a.pdf,/scans,complete,Complete,0;1,PDF;Image,120,2023-07-13T23:48:49Z,\n\
+             item-2,b.png,/,new,,,,0,,\n"
+        );
+
+        let mut jsonl = Vec::new();
+        dataset
+            .dump_items(&client, &mut jsonl, ItemDumpFormat::Jsonl)
+            .await
+            .expect("Failed to dump items");
+        let rows: Vec<ItemRow> = String::from_utf8(jsonl)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[0].size_bytes, Some(120));
+        assert_eq!(rows[1].name.as_deref(), Some("b.png"));
     }
 
     #[tokio::test]
-    async fn test_get_item_reports() {
+    async fn test_class_distribution() {
         let mock_server = MockServer::start().await;
-        let mock_data = "filename,uploaded_date,status,workflow_start_date,workflow_complete_date,number_of_frames,folder,time_spent_annotating_sec,time_spent_reviewing_sec,automation_time_annotating_sec,automation_time_reviewing_sec,annotators,reviewers,was_rejected_in_review,url
-somefilename,2023-05-10 14:15:27,complete,2023-05-10 14:16:17,2023-05-17 01:28:13,,/,320,1,2,3,kevin@mail.com,,false,https://darwin.v7labs.com/workview?dataset=123456&image=789";
-
-        let mut rng = AlwaysTrueRng::default();
-        let mut dataset: Dataset = Faker.fake_with_rng(&mut rng);
-        let dataset_slug = dataset
-            .slug
-            .clone()
-            .expect("Dataset slug should not be null");
-        let team_slug = "some-team";
-        dataset.team_slug = Some(team_slug.to_string());
+        let dataset = Dataset {
+            id: Some(7),
+            slug: Some("some-dataset".to_string()),
+            team_slug: Some("other-team".to_string()),
+            ..Default::default()
+        };
 
         Mock::given(method("GET"))
-            .and(path(format!(
-                "/teams/{team_slug}/datasets/{dataset_slug}/item_reports",
-            )))
-            .respond_with(ResponseTemplate::new(200).set_body_string(mock_data.to_string()))
+            .and(path("/teams/other-team/annotation_classes"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "annotation_classes": [
+                    {"id": 1, "name": "Cheese"}
+                ],
+                "type_counts": []
+            })))
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/memberships"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_json(json!([{"user_id": 7, "email": "kevin@mail.com"}])),
+            )
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/v2/teams/other-team/items"))
+            .and(query_param("page[from]", "cursor-1"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "items": [{"id": "item-2"}],
+                "page": {"next": null}
+            })))
             .mount(&mock_server)
             .await;
+        Mock::given(method("GET"))
+            .and(path("/v2/teams/other-team/items"))
+            .and(query_param("dataset_ids", "7"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "items": [{"id": "item-1"}],
+                "page": {"next": "cursor-1"}
+            })))
+            .mount(&mock_server)
+            .await;
+        let annotation = json!({
+            "annotation_class_id": 1,
+            "actors": [{"role": "annotator", "user_id": 7}]
+        });
+        for item_id in ["item-1", "item-2"] {
+            Mock::given(method("GET"))
+                .and(path(format!(
+                    "/v2/teams/other-team/items/{item_id}/annotations"
+                )))
+                .respond_with(ResponseTemplate::new(200).set_body_json(json!([annotation.clone()])))
+                .expect(1)
+                .mount(&mock_server)
+                .await;
+        }
 
         let client: V7Client = V7Client::new(
             format!("{}/", mock_server.uri()),
             "api-key".to_string(),
             "some-team".to_string(),
         )
-        .expect("Failed to create V7 client");
+        .expect("Failed to get V7Client");
 
-        let results = dataset
-            .get_item_reports(&client)
+        let distribution = dataset
+            .class_distribution(&client)
             .await
-            .expect("Failed to get item reports");
-
-        assert_eq!(results.len(), 1);
+            .expect("Failed to count the annotation classes");
+        assert_eq!(
+            distribution.classes,
+            vec![ClassCount {
+                class_id: Some(1),
+                class_name: Some("Cheese".to_string()),
+                count: 2
+            }]
+        );
+        assert_eq!(
+            distribution.annotators,
+            vec![AnnotatorClassCount {
+                user_id: Some(7),
+                email: Some("kevin@mail.com".to_string()),
+                class_id: Some(1),
+                class_name: Some("Cheese".to_string()),
+                count: 2
+            }]
+        );
+    }
 
-        let result = results.first().expect("Get item reports had no result");
+    #[cfg(feature = "reports")]
+    #[tokio::test]
+    async fn test_item_reports_csv_round_trip() {
+        let reports: Vec<ItemReport> = fake::vec![ItemReport; 3];
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("item_reports.csv");
+
+        let mut csv = Vec::new();
+        item_reports_to_csv(&mut csv, &reports).await.unwrap();
+        assert!(String::from_utf8_lossy(&csv).starts_with(
+            "filename,uploaded_date,status,workflow_start_date,workflow_complete_date,"
+        ));
+        std::fs::write(&path, csv).unwrap();
 
-        assert_eq!(result.filename, Some("somefilename".to_string()));
+        let parsed = item_reports_from_path(&path).await.unwrap();
+        assert_eq!(parsed, reports);
     }
 
+    #[cfg(feature = "reports")]
     #[tokio::test]
     async fn test_item_reports_from_bytes() {
         let filename = "somefilename";
@@ -1180,7 +6283,7 @@ somefilename,2023-05-10 14:15:27,complete,2023-05-10 14:16:17,2023-05-17 01:28:1
 
         assert_eq!(result.filename, Some(filename.to_string()));
         assert_eq!(result.uploaded_date, Some(uploaded_date.to_string()));
-        assert_eq!(result.status, Some(DatasetItemStatus::Complete));
+        assert_eq!(result.status, Some(DatasetItemStatus::Complete.into()));
         assert_eq!(
             result.workflow_start_date,
             Some(workflow_start_date.to_string())
@@ -1189,6 +6292,13 @@ somefilename,2023-05-10 14:15:27,complete,2023-05-10 14:16:17,2023-05-17 01:28:1
             result.workflow_complete_date,
             Some(workflow_complete_date.to_string())
         );
+        #[cfg(feature = "timestamps")]
+        assert_eq!(
+            result
+                .workflow_complete_timestamp()
+                .map(|timestamp| timestamp.to_string()),
+            Some("2023-05-17T01:28:13Z".to_string())
+        );
         assert_eq!(result.number_of_frames, None);
         assert_eq!(result.folder, Some(folder.to_string()));
         assert_eq!(
@@ -1212,4 +6322,59 @@ somefilename,2023-05-10 14:15:27,complete,2023-05-10 14:16:17,2023-05-17 01:28:1
         assert_eq!(result.was_rejected_in_review, Some(was_rejected_in_review));
         assert_eq!(result.url, Some(url.to_string()));
     }
+
+    #[test]
+    fn test_deduplicate_registration_items() {
+        let item = |name: &str, storage_key: &str| ExistingSimpleItem {
+            name: name.to_string(),
+            path: "/".to_string(),
+            slots: vec![Slot {
+                storage_key: storage_key.to_string(),
+                ..Faker.fake()
+            }],
+        };
+        let items = RegistrationItems::ReadOnly(vec![
+            item("a.png", "bucket/a.png"),
+            item("b.png", "bucket/b.png"),
+            item("a.png", "bucket/c.png"),
+            item("c.png", "bucket/b.png"),
+        ]);
+
+        let report = items.find_duplicates(DuplicateKey::StorageKey);
+        assert_eq!(
+            report.duplicates,
+            vec![DuplicateItems {
+                key: "bucket/b.png".to_string(),
+                indices: vec![1, 3],
+            }]
+        );
+
+        let error = items
+            .clone()
+            .deduplicate(DuplicateKey::NameAndPath, DuplicatePolicy::Reject)
+            .unwrap_err();
+        let error = error.downcast_ref::<DuplicateItemsError>().unwrap();
+        assert_eq!(error.0.duplicates[0].key, "/a.png");
+        assert_eq!(error.0.duplicates[0].indices, vec![0, 2]);
+
+        let mut allowed = items.clone();
+        let report = allowed
+            .deduplicate(DuplicateKey::NameAndPath, DuplicatePolicy::Allow)
+            .unwrap();
+        assert_eq!(report.duplicates[0].indices, vec![0, 2]);
+        assert_eq!(allowed, items);
+
+        let mut deduplicated = items.clone();
+        deduplicated
+            .deduplicate(DuplicateKey::NameAndPath, DuplicatePolicy::KeepLast)
+            .unwrap();
+        let RegistrationItems::ReadOnly(deduplicated) = deduplicated else {
+            panic!("Expected read-only items");
+        };
+        let keys: Vec<&str> = deduplicated
+            .iter()
+            .map(|item| item.slots[0].storage_key.as_str())
+            .collect();
+        assert_eq!(keys, vec!["bucket/b.png", "bucket/c.png", "bucket/b.png"]);
+    }
 }
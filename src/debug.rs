@@ -0,0 +1,251 @@
+//! Debug mode of the client, dumping the requests it sends and the responses it receives
+//! to a sink, e.g. to see the exact payload of a failed import.
+//!
+//! The Authorization header and the API key are always redacted from the dumps, as are the
+//! values of sensitive JSON fields, and bodies are truncated so bulk payloads stay readable.
+
+use log::debug;
+use reqwest::header::{HeaderMap, AUTHORIZATION, CONTENT_TYPE, COOKIE, SET_COOKIE};
+use std::fmt::{self, Display};
+use std::sync::Arc;
+
+/// Replaces redacted values in the dumps
+pub const REDACTED: &str = "[REDACTED]";
+
+/// A request sent or a response received by a client in debug mode
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DebugRecord {
+    Request {
+        method: String,
+        url: String,
+        headers: Vec<(String, String)>,
+        body: Option<String>,
+    },
+    /// The body is only captured for JSON and text responses, other responses such as
+    /// export archives are passed through unread
+    Response {
+        method: String,
+        url: String,
+        status: u16,
+        headers: Vec<(String, String)>,
+        body: Option<String>,
+    },
+}
+
+impl Display for DebugRecord {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (headers, body) = match self {
+            Self::Request {
+                method,
+                url,
+                headers,
+                body,
+            } => {
+                write!(f, "--> {method} {url}")?;
+                (headers, body)
+            }
+            Self::Response {
+                method,
+                url,
+                status,
+                headers,
+                body,
+            } => {
+                write!(f, "<-- {status} {method} {url}")?;
+                (headers, body)
+            }
+        };
+        for (name, value) in headers {
+            write!(f, "\n{name}: {value}")?;
+        }
+        if let Some(body) = body {
+            write!(f, "\n\n{body}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Receives the records of a client in debug mode. Closures taking a `&DebugRecord`
+/// implement this trait, as does `LogDebugSink`.
+pub trait DebugSink: Send + Sync {
+    fn record(&self, record: &DebugRecord);
+}
+
+impl<F> DebugSink for F
+where
+    F: Fn(&DebugRecord) + Send + Sync,
+{
+    fn record(&self, record: &DebugRecord) {
+        self(record)
+    }
+}
+
+/// Logs each record at debug level
+#[derive(Debug, Default, Clone, Copy)]
+pub struct LogDebugSink;
+
+impl DebugSink for LogDebugSink {
+    fn record(&self, record: &DebugRecord) {
+        debug!("{record}");
+    }
+}
+
+/// Redaction and truncation rules of the debug mode
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DebugDumpOptions {
+    /// Bodies longer than this many bytes are truncated, `None` dumps them in full
+    pub max_body_len: Option<usize>,
+    /// JSON fields, at any depth, whose values are redacted. Compared case insensitively.
+    pub redacted_fields: Vec<String>,
+}
+
+impl Default for DebugDumpOptions {
+    fn default() -> Self {
+        Self {
+            max_body_len: Some(4096),
+            redacted_fields: [
+                "api_key",
+                "authorization",
+                "authorization_header",
+                "password",
+            ]
+            .into_iter()
+            .map(String::from)
+            .collect(),
+        }
+    }
+}
+
+// The sink and options of a client in debug mode, shared by its clones
+#[derive(Clone)]
+pub(crate) struct DebugDump {
+    sink: Arc<dyn DebugSink>,
+    options: DebugDumpOptions,
+}
+
+impl fmt::Debug for DebugDump {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DebugDump")
+            .field("options", &self.options)
+            .finish_non_exhaustive()
+    }
+}
+
+impl DebugDump {
+    pub(crate) fn new(sink: Arc<dyn DebugSink>, options: DebugDumpOptions) -> Self {
+        Self { sink, options }
+    }
+
+    pub(crate) fn request(&self, request: &reqwest::Request, api_key: &str) {
+        let body = request
+            .body()
+            .and_then(|body| body.as_bytes())
+            .map(|body| self.body(body, api_key));
+        self.sink.record(&DebugRecord::Request {
+            method: request.method().to_string(),
+            url: request.url().to_string(),
+            headers: redact_headers(request.headers()),
+            body,
+        });
+    }
+
+    // Reads the body of a textual response to dump it, returning a copy in its place
+    pub(crate) async fn response(
+        &self,
+        method: &str,
+        response: reqwest::Response,
+        api_key: &str,
+    ) -> Result<reqwest::Response, reqwest::Error> {
+        let url = response.url().to_string();
+        let status = response.status();
+        let headers = response.headers().clone();
+        let textual = headers
+            .get(CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .is_some_and(|value| value.contains("json") || value.starts_with("text/"));
+
+        let (response, body) = if textual {
+            let bytes = response.bytes().await?;
+            let body = self.body(&bytes, api_key);
+            let mut copy = http::Response::new(bytes);
+            *copy.status_mut() = status;
+            *copy.headers_mut() = headers.clone();
+            (reqwest::Response::from(copy), Some(body))
+        } else {
+            (response, None)
+        };
+
+        self.sink.record(&DebugRecord::Response {
+            method: method.to_string(),
+            url,
+            status: status.as_u16(),
+            headers: redact_headers(&headers),
+            body,
+        });
+        Ok(response)
+    }
+
+    fn body(&self, body: &[u8], api_key: &str) -> String {
+        let body = match serde_json::from_slice::<serde_json::Value>(body) {
+            Ok(mut json) => {
+                redact_fields(&mut json, &self.options.redacted_fields);
+                json.to_string()
+            }
+            Err(_) => String::from_utf8_lossy(body).into_owned(),
+        };
+        let body = if api_key.is_empty() {
+            body
+        } else {
+            body.replace(api_key, REDACTED)
+        };
+        truncate(body, self.options.max_body_len)
+    }
+}
+
+fn redact_headers(headers: &HeaderMap) -> Vec<(String, String)> {
+    headers
+        .iter()
+        .map(|(name, value)| {
+            let value = if [AUTHORIZATION, COOKIE, SET_COOKIE].contains(name) {
+                REDACTED
+            } else {
+                value.to_str().unwrap_or("<binary>")
+            };
+            (name.to_string(), value.to_string())
+        })
+        .collect()
+}
+
+fn redact_fields(json: &mut serde_json::Value, fields: &[String]) {
+    match json {
+        serde_json::Value::Object(map) => {
+            for (key, value) in map.iter_mut() {
+                if fields.iter().any(|field| field.eq_ignore_ascii_case(key)) {
+                    *value = serde_json::Value::String(REDACTED.to_string());
+                } else {
+                    redact_fields(value, fields);
+                }
+            }
+        }
+        serde_json::Value::Array(values) => {
+            for value in values {
+                redact_fields(value, fields);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn truncate(mut body: String, max_len: Option<usize>) -> String {
+    let Some(max_len) = max_len.filter(|max_len| body.len() > *max_len) else {
+        return body;
+    };
+    let mut end = max_len;
+    while !body.is_char_boundary(end) {
+        end -= 1;
+    }
+    let truncated = body.len() - end;
+    body.truncate(end);
+    body.push_str(&format!("... ({truncated} bytes truncated)"));
+    body
+}
@@ -0,0 +1,391 @@
+//! Comparison of two sets of Darwin JSON exports, e.g. before and after a labelling round,
+//! reporting the annotations added, removed or modified on each item.
+//!
+//! Annotations are matched between exports by id and items by path and name.
+
+use crate::annotation::Polygon;
+use crate::export::{ImageAnnotation, JsonExportV2};
+use std::collections::{BTreeMap, HashMap};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DiffOptions {
+    /// Annotations whose geometries overlap with an IoU below this threshold are modified
+    pub iou_threshold: f32,
+}
+
+impl Default for DiffOptions {
+    fn default() -> Self {
+        Self {
+            iou_threshold: 0.95,
+        }
+    }
+}
+
+/// A change to an annotation present in both exports
+#[derive(Debug, Clone, PartialEq)]
+pub enum AnnotationChange {
+    /// The annotation class changed, holds the class names before and after
+    Class(String, String),
+    /// The geometry changed, holds the IoU of the geometries before and after
+    Geometry(f32),
+    /// The annotators changed, holds the sorted annotator emails before and after
+    Annotators(Vec<String>, Vec<String>),
+}
+
+#[derive(Debug, Clone)]
+pub struct ModifiedAnnotation {
+    pub before: ImageAnnotation,
+    pub after: ImageAnnotation,
+    pub changes: Vec<AnnotationChange>,
+}
+
+/// Differences between the annotations of a single item
+#[derive(Debug, Default, Clone)]
+pub struct ItemDiff {
+    /// Path and name of the item e.g. `/batch-3/slide.svs`
+    pub item: String,
+    pub added: Vec<ImageAnnotation>,
+    pub removed: Vec<ImageAnnotation>,
+    pub modified: Vec<ModifiedAnnotation>,
+}
+
+impl ItemDiff {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.modified.is_empty()
+    }
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct ExportDiff {
+    /// Items only present in the later exports
+    pub added_items: Vec<String>,
+    /// Items only present in the earlier exports
+    pub removed_items: Vec<String>,
+    /// Items present in both exports with changed annotations, ordered by item
+    pub items: Vec<ItemDiff>,
+}
+
+impl ExportDiff {
+    pub fn is_empty(&self) -> bool {
+        self.added_items.is_empty() && self.removed_items.is_empty() && self.items.is_empty()
+    }
+}
+
+fn item_key(export: &JsonExportV2) -> String {
+    format!(
+        "{}/{}",
+        export
+            .item
+            .path
+            .as_deref()
+            .unwrap_or_default()
+            .trim_end_matches('/'),
+        export.item.name.as_deref().unwrap_or_default()
+    )
+}
+
+fn annotator_emails(annotation: &ImageAnnotation) -> Vec<String> {
+    let mut emails: Vec<String> = annotation
+        .annotators
+        .iter()
+        .flatten()
+        .map(|annotator| annotator.email.clone())
+        .collect();
+    emails.sort();
+    emails
+}
+
+// The outline of a polygon or bounding box annotation
+fn outline(annotation: &ImageAnnotation) -> Option<Polygon> {
+    match annotation.polygon.as_ref() {
+        Some(polygon) => Some(polygon.clone()),
+        None => annotation.bounding_box.as_ref()?.to_polygon(),
+    }
+}
+
+/// Intersection over union of the geometries of two annotations. Polygons and bounding
+/// boxes are compared by their outlines, other geometries by their extents. Annotations
+/// without a geometry, such as tags, are identical to each other and disjoint from any geometry.
+pub fn geometry_iou(before: &ImageAnnotation, after: &ImageAnnotation) -> f32 {
+    if let (Some(before), Some(after)) = (outline(before), outline(after)) {
+        return before.iou(&after);
+    }
+    let area = |(min_x, min_y, max_x, max_y): (f32, f32, f32, f32)| {
+        (max_x - min_x).max(0.0) * (max_y - min_y).max(0.0)
+    };
+    match (before.extent(), after.extent()) {
+        (None, None) => 1.0,
+        (Some(a), Some(b)) => {
+            let intersection = area((a.0.max(b.0), a.1.max(b.1), a.2.min(b.2), a.3.min(b.3)));
+            let union = area(a) + area(b) - intersection;
+            if union > 0.0 {
+                intersection / union
+            } else if a == b {
+                1.0
+            } else {
+                0.0
+            }
+        }
+        _ => 0.0,
+    }
+}
+
+fn annotation_changes(
+    before: &ImageAnnotation,
+    after: &ImageAnnotation,
+    options: &DiffOptions,
+) -> Vec<AnnotationChange> {
+    let mut changes = Vec::new();
+    if before.name != after.name {
+        changes.push(AnnotationChange::Class(
+            before.name.clone(),
+            after.name.clone(),
+        ));
+    }
+    let iou = geometry_iou(before, after);
+    if iou < options.iou_threshold {
+        changes.push(AnnotationChange::Geometry(iou));
+    }
+    let (annotators_before, annotators_after) = (annotator_emails(before), annotator_emails(after));
+    if annotators_before != annotators_after {
+        changes.push(AnnotationChange::Annotators(
+            annotators_before,
+            annotators_after,
+        ));
+    }
+    changes
+}
+
+/// Compares the annotations of a single item. Annotations without an id cannot be
+/// matched and are reported as removed from `before` and added to `after`.
+pub fn diff_item(before: &JsonExportV2, after: &JsonExportV2, options: &DiffOptions) -> ItemDiff {
+    let mut diff = ItemDiff {
+        item: item_key(after),
+        ..Default::default()
+    };
+    let after_by_id: HashMap<&String, &ImageAnnotation> = after
+        .annotations
+        .iter()
+        .filter_map(|annotation| Some((annotation.id.as_ref()?, annotation)))
+        .collect();
+    let before_by_id: HashMap<&String, &ImageAnnotation> = before
+        .annotations
+        .iter()
+        .filter_map(|annotation| Some((annotation.id.as_ref()?, annotation)))
+        .collect();
+
+    for annotation in before.annotations.iter() {
+        match annotation.id.as_ref().and_then(|id| after_by_id.get(id)) {
+            Some(later) => {
+                let changes = annotation_changes(annotation, later, options);
+                if !changes.is_empty() {
+                    diff.modified.push(ModifiedAnnotation {
+                        before: annotation.clone(),
+                        after: (*later).clone(),
+                        changes,
+                    });
+                }
+            }
+            None => diff.removed.push(annotation.clone()),
+        }
+    }
+    diff.added = after
+        .annotations
+        .iter()
+        .filter(|annotation| {
+            annotation
+                .id
+                .as_ref()
+                .is_none_or(|id| !before_by_id.contains_key(id))
+        })
+        .cloned()
+        .collect();
+    diff
+}
+
+/// Compares two sets of exports, reporting only the items with differences
+pub fn diff_exports(
+    before: &[JsonExportV2],
+    after: &[JsonExportV2],
+    options: &DiffOptions,
+) -> ExportDiff {
+    let before: BTreeMap<String, &JsonExportV2> = before
+        .iter()
+        .map(|export| (item_key(export), export))
+        .collect();
+    let after: BTreeMap<String, &JsonExportV2> = after
+        .iter()
+        .map(|export| (item_key(export), export))
+        .collect();
+
+    let mut diff = ExportDiff::default();
+    for (key, earlier) in before.iter() {
+        match after.get(key) {
+            Some(later) => {
+                let item = diff_item(earlier, later, options);
+                if !item.is_empty() {
+                    diff.items.push(item);
+                }
+            }
+            None => diff.removed_items.push(key.clone()),
+        }
+    }
+    diff.added_items = after
+        .keys()
+        .filter(|key| !before.contains_key(*key))
+        .cloned()
+        .collect();
+    diff
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::annotation::{BoundingBox, Ellipse, Keypoint, Tag};
+    use crate::export::{Annotator, Item};
+
+    fn export(name: &str, annotations: Vec<ImageAnnotation>) -> JsonExportV2 {
+        JsonExportV2 {
+            item: Item {
+                name: Some(name.to_string()),
+                path: Some("/".to_string()),
+                ..Default::default()
+            },
+            annotations,
+            ..Default::default()
+        }
+    }
+
+    fn bbox(id: &str, class: &str, x: f32) -> ImageAnnotation {
+        ImageAnnotation {
+            id: Some(id.to_string()),
+            name: class.to_string(),
+            bounding_box: Some(BoundingBox {
+                x: Some(x),
+                y: Some(0.0),
+                w: Some(10.0),
+                h: Some(10.0),
+            }),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_geometry_iou() {
+        assert_eq!(
+            geometry_iou(&bbox("a", "c", 0.0), &bbox("a", "c", 0.0)),
+            1.0
+        );
+        assert_eq!(
+            geometry_iou(&bbox("a", "c", 0.0), &bbox("a", "c", 5.0)),
+            1.0 / 3.0
+        );
+        assert_eq!(
+            geometry_iou(&bbox("a", "c", 0.0), &bbox("a", "c", 20.0)),
+            0.0
+        );
+
+        let tag = ImageAnnotation {
+            tag: Some(Tag {}),
+            ..Default::default()
+        };
+        assert_eq!(geometry_iou(&tag, &tag), 1.0);
+        assert_eq!(geometry_iou(&tag, &bbox("a", "c", 0.0)), 0.0);
+
+        // Polygons are compared by their outline rather than their extent
+        let diamond = ImageAnnotation {
+            polygon: Some(Polygon {
+                paths: vec![vec![
+                    Keypoint { x: 5.0, y: 0.0 },
+                    Keypoint { x: 10.0, y: 5.0 },
+                    Keypoint { x: 5.0, y: 10.0 },
+                    Keypoint { x: 0.0, y: 5.0 },
+                ]],
+            }),
+            ..Default::default()
+        };
+        assert_eq!(geometry_iou(&diamond, &bbox("a", "c", 0.0)), 0.5);
+
+        let ellipse = ImageAnnotation {
+            ellipse: Some(Ellipse {
+                angle: 0.0,
+                center: Keypoint { x: 5.0, y: 5.0 },
+                radius: Keypoint { x: 5.0, y: 5.0 },
+            }),
+            ..Default::default()
+        };
+        assert_eq!(geometry_iou(&ellipse, &bbox("a", "c", 0.0)), 1.0);
+    }
+
+    #[test]
+    fn test_diff_exports() {
+        let mut reviewed = bbox("kept", "Cheese", 0.0);
+        reviewed.annotators = Some(vec![Annotator {
+            email: "fake.pathologist@franklin.ai".to_string(),
+            full_name: "Fake Pathologist".to_string(),
+            ..Default::default()
+        }]);
+
+        let before = vec![
+            export(
+                "slide-1",
+                vec![
+                    bbox("kept", "Cheese", 0.0),
+                    bbox("moved", "Cheese", 0.0),
+                    bbox("relabelled", "Cheese", 0.0),
+                    bbox("removed", "Cheese", 0.0),
+                ],
+            ),
+            export("slide-2", vec![bbox("same", "Cheese", 0.0)]),
+            export("slide-3", vec![]),
+        ];
+        let after = vec![
+            export(
+                "slide-1",
+                vec![
+                    reviewed,
+                    bbox("moved", "Cheese", 5.0),
+                    bbox("relabelled", "Ham", 0.0),
+                    bbox("added", "Cheese", 0.0),
+                ],
+            ),
+            export("slide-2", vec![bbox("same", "Cheese", 0.1)]),
+            export("slide-4", vec![]),
+        ];
+
+        let diff = diff_exports(&before, &after, &DiffOptions::default());
+        assert_eq!(diff.added_items, vec!["/slide-4".to_string()]);
+        assert_eq!(diff.removed_items, vec!["/slide-3".to_string()]);
+        assert_eq!(diff.items.len(), 1);
+
+        let item = &diff.items[0];
+        assert_eq!(item.item, "/slide-1");
+        assert_eq!(item.added[0].id, Some("added".to_string()));
+        assert_eq!(item.removed[0].id, Some("removed".to_string()));
+
+        let changes: HashMap<String, Vec<AnnotationChange>> = item
+            .modified
+            .iter()
+            .map(|modified| (modified.after.id.clone().unwrap(), modified.changes.clone()))
+            .collect();
+        assert_eq!(changes.len(), 3);
+        assert_eq!(
+            changes["kept"],
+            vec![AnnotationChange::Annotators(
+                vec![],
+                vec!["fake.pathologist@franklin.ai".to_string()]
+            )]
+        );
+        assert_eq!(
+            changes["moved"],
+            vec![AnnotationChange::Geometry(1.0 / 3.0)]
+        );
+        assert_eq!(
+            changes["relabelled"],
+            vec![AnnotationChange::Class(
+                "Cheese".to_string(),
+                "Ham".to_string()
+            )]
+        );
+    }
+}
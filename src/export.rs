@@ -1,17 +1,32 @@
 //! This file contains structures and methods that define the Darwin Export Format
 //! https://docs.v7labs.com/v1.0/reference/darwin-json
 
-use crate::annotation::{BoundingBox, Polygon, Tag, Text};
-use crate::item::DatasetItemTypes;
+use crate::annotation::{
+    AnnotationProperty, Attributes, BoundingBox, Cuboid, Ellipse, InstanceId, ItemAnnotation,
+    Keypoint, Line, Polygon, Tag, Text,
+};
+use crate::ids::UserId;
+use crate::item::{DatasetItemTypes, DatasetItemV2};
+use crate::utils::{folder_path, in_folder};
+use anyhow::{bail, Context, Result};
 use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::{self, Display};
+use std::path::{Path, PathBuf};
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
 pub struct Annotator {
     // Email of the Annotator or reviewer on Darwin
     pub email: String,
     // Full name (first name + last name) of the annotator
     // or reviewer
     pub full_name: String,
+    /// Not part of the export, filled in by `ops::resolve_annotators`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub user_id: Option<UserId>,
+    /// Team role of the user, not part of the export, filled in by `ops::resolve_annotators`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub role: Option<String>,
 }
 #[derive(Debug, Serialize, Deserialize, Clone, Default)]
 pub struct ImageAnnotation {
@@ -35,6 +50,254 @@ pub struct ImageAnnotation {
     // Annotation Type
     #[serde(skip_serializing_if = "Option::is_none")]
     pub text: Option<Text>,
+    // Annotation Type
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ellipse: Option<Ellipse>,
+    // Annotation Type
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cuboid: Option<Cuboid>,
+    // Annotation Type
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub line: Option<Line>,
+    // Sub annotation
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub attributes: Option<Attributes>,
+    // Sub annotation
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub instance_id: Option<InstanceId>,
+    // Values of the class properties set on the annotation
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub properties: Vec<AnnotationProperty>,
+    // Names of the item slots the annotation belongs to
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub slot_names: Vec<String>,
+    // Per frame annotation data of video annotations keyed by frame index
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub frames: BTreeMap<u32, VideoFrameAnnotation>,
+    // Frame ranges of video annotations, each the first frame and the frame after the last
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub ranges: Vec<[u32; 2]>,
+    // Whether the frames of a video annotation between keyframes are interpolated
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub interpolated: Option<bool>,
+    // Interpolation algorithm of video annotations e.g. `linear-1.1`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub interpolate_algorithm: Option<String>,
+}
+
+fn bbox_extent(bbox: &BoundingBox) -> Option<(f32, f32, f32, f32)> {
+    let (x, y, w, h) = (bbox.x?, bbox.y?, bbox.w?, bbox.h?);
+    Some((x, y, x + w, y + h))
+}
+
+impl ImageAnnotation {
+    /// Indices of the video frames with annotation data, in ascending order
+    pub fn frame_indices(&self) -> Vec<u32> {
+        self.frames.keys().copied().collect()
+    }
+
+    /// Name of the main annotation type e.g. `polygon`, `None` if there is no payload
+    pub fn annotation_type(&self) -> Option<&'static str> {
+        if self.bounding_box.is_some() && self.polygon.is_none() {
+            Some("bounding_box")
+        } else if self.polygon.is_some() {
+            Some("polygon")
+        } else if self.ellipse.is_some() {
+            Some("ellipse")
+        } else if self.cuboid.is_some() {
+            Some("cuboid")
+        } else if self.line.is_some() {
+            Some("line")
+        } else if self.tag.is_some() {
+            Some("tag")
+        } else if self.text.is_some() {
+            Some("text")
+        } else {
+            None
+        }
+    }
+
+    /// Axis aligned extent (min x, min y, max x, max y) of the annotation geometry,
+    /// `None` for annotations without a geometry such as tags
+    pub fn extent(&self) -> Option<(f32, f32, f32, f32)> {
+        if let Some(extent) = self.bounding_box.as_ref().and_then(bbox_extent) {
+            return Some(extent);
+        }
+        if let Some(ellipse) = self.ellipse.as_ref() {
+            let (sin, cos) = ellipse.angle.sin_cos();
+            let (rx, ry) = (ellipse.radius.x, ellipse.radius.y);
+            let half_w = ((rx * cos).powi(2) + (ry * sin).powi(2)).sqrt();
+            let half_h = ((rx * sin).powi(2) + (ry * cos).powi(2)).sqrt();
+            let center = &ellipse.center;
+            return Some((
+                center.x - half_w,
+                center.y - half_h,
+                center.x + half_w,
+                center.y + half_h,
+            ));
+        }
+        if let Some(cuboid) = self.cuboid.as_ref() {
+            if let (Some(front), Some(back)) =
+                (bbox_extent(&cuboid.front), bbox_extent(&cuboid.back))
+            {
+                return Some((
+                    front.0.min(back.0),
+                    front.1.min(back.1),
+                    front.2.max(back.2),
+                    front.3.max(back.3),
+                ));
+            }
+        }
+        let points: Vec<&Keypoint> = match self.line.as_ref() {
+            Some(line) => line.path.iter().collect(),
+            None => self.polygon.as_ref()?.paths.iter().flatten().collect(),
+        };
+        let first = points.first()?;
+        Some(points.iter().fold(
+            (first.x, first.y, first.x, first.y),
+            |(min_x, min_y, max_x, max_y), point| {
+                (
+                    min_x.min(point.x),
+                    min_y.min(point.y),
+                    max_x.max(point.x),
+                    max_y.max(point.y),
+                )
+            },
+        ))
+    }
+
+    /// Key of the class and geometry of the annotation, equal for the copies of an
+    /// annotation made by importing it again. The id, annotators and sub annotations are
+    /// ignored, and coordinates are compared to a hundredth of a pixel.
+    /// `None` if the annotation, or a frame of it, has a type whose geometry is not
+    /// modelled such as a keypoint, skeleton or mask, as their copies cannot be told apart
+    /// from other annotations of the class.
+    pub fn identity(&self) -> Option<AnnotationIdentity> {
+        let mut frames = BTreeMap::new();
+        for (index, frame) in self.frames.iter() {
+            let geometry = AnnotationGeometry {
+                bounding_box: frame.bounding_box.as_ref(),
+                polygon: frame.polygon.as_ref(),
+                ellipse: frame.ellipse.as_ref(),
+                cuboid: frame.cuboid.as_ref(),
+                line: frame.line.as_ref(),
+                text: frame.text.as_ref(),
+                frames: None,
+                ranges: &[],
+            };
+            if geometry.is_empty() && frame.tag.is_none() {
+                return None;
+            }
+            frames.insert(*index, geometry);
+        }
+        if self.annotation_type().is_none() && frames.is_empty() {
+            return None;
+        }
+        let geometry = AnnotationGeometry {
+            bounding_box: self.bounding_box.as_ref(),
+            polygon: self.polygon.as_ref(),
+            ellipse: self.ellipse.as_ref(),
+            cuboid: self.cuboid.as_ref(),
+            line: self.line.as_ref(),
+            text: self.text.as_ref(),
+            frames: Some(frames).filter(|frames| !frames.is_empty()),
+            ranges: &self.ranges,
+        };
+        let mut value = serde_json::to_value(geometry).unwrap_or_default();
+        round_numbers(&mut value);
+        let mut slot_names = self.slot_names.clone();
+        slot_names.sort();
+        Some(AnnotationIdentity {
+            name: self.name.clone(),
+            annotation_type: self.annotation_type(),
+            slot_names,
+            geometry: value.to_string(),
+        })
+    }
+}
+
+/// Class and geometry of an annotation, see `ImageAnnotation::identity`
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct AnnotationIdentity {
+    pub name: String,
+    pub annotation_type: Option<&'static str>,
+    pub slot_names: Vec<String>,
+    /// The geometry as JSON, with its coordinates rounded
+    pub geometry: String,
+}
+
+#[derive(Serialize)]
+struct AnnotationGeometry<'a> {
+    bounding_box: Option<&'a BoundingBox>,
+    polygon: Option<&'a Polygon>,
+    ellipse: Option<&'a Ellipse>,
+    cuboid: Option<&'a Cuboid>,
+    line: Option<&'a Line>,
+    text: Option<&'a Text>,
+    frames: Option<BTreeMap<u32, AnnotationGeometry<'a>>>,
+    ranges: &'a [[u32; 2]],
+}
+
+impl AnnotationGeometry<'_> {
+    fn is_empty(&self) -> bool {
+        self.bounding_box.is_none()
+            && self.polygon.is_none()
+            && self.ellipse.is_none()
+            && self.cuboid.is_none()
+            && self.line.is_none()
+            && self.text.is_none()
+    }
+}
+
+// Replaces the numbers of `value` with strings to two decimal places, so that the float
+// noise of a round trip through V7 does not make two copies of an annotation differ
+fn round_numbers(value: &mut serde_json::Value) {
+    match value {
+        serde_json::Value::Number(number) => {
+            if let Some(float) = number.as_f64() {
+                // Adding 0.0 turns -0.0 into 0.0
+                let rounded = (float * 100.0).round() / 100.0 + 0.0;
+                *value = serde_json::Value::String(format!("{rounded:.2}"));
+            }
+        }
+        serde_json::Value::Array(values) => values.iter_mut().for_each(round_numbers),
+        serde_json::Value::Object(values) => values.values_mut().for_each(round_numbers),
+        _ => {}
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct VideoFrameAnnotation {
+    // Whether the frame is a keyframe rather than interpolated
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub keyframe: Option<bool>,
+    // Annotation Type
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bounding_box: Option<BoundingBox>,
+    // Annotation Type
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tag: Option<Tag>,
+    // Annotation Type
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub polygon: Option<Polygon>,
+    // Annotation Type
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub text: Option<Text>,
+    // Annotation Type
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ellipse: Option<Ellipse>,
+    // Annotation Type
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cuboid: Option<Cuboid>,
+    // Annotation Type
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub line: Option<Line>,
+    // Sub annotation
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub attributes: Option<Attributes>,
+    // Sub annotation
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub instance_id: Option<InstanceId>,
 }
 
 #[derive(Clone, Debug, Deserialize, Serialize, Default)]
@@ -86,12 +349,680 @@ pub struct SourceFile {
 
 #[derive(Clone, Debug, Deserialize, Serialize, Default)]
 pub struct JsonExportV2 {
+    /// Empty in exports without a version, see `parse_any`
+    #[serde(default)]
     pub version: String,
+    #[serde(default)]
     pub schema_ref: String,
     pub item: Item,
     pub annotations: Vec<ImageAnnotation>,
 }
 
+/// Version of the Darwin JSON built by `JsonExportV2::from_item_annotations`
+pub const DARWIN_JSON_VERSION: &str = "2.0";
+pub const DARWIN_JSON_SCHEMA_REF: &str =
+    "https://darwin-public.s3.eu-west-1.amazonaws.com/darwin_json/2.0/schema.json";
+
+impl ImageAnnotation {
+    /// Converts an annotation of the item annotations endpoint, whose data has the same
+    /// layout as an exported annotation, naming it with its class in `class_names`
+    pub fn from_item_annotation(
+        annotation: &ItemAnnotation,
+        class_names: &HashMap<u32, String>,
+    ) -> Result<Self> {
+        let class_id = annotation
+            .annotation_class_id
+            .context("Annotation is missing a class id")?;
+        let name = class_names
+            .get(&class_id)
+            .with_context(|| format!("No annotation class with id {class_id}"))?;
+
+        let mut value = annotation
+            .data
+            .clone()
+            .unwrap_or_else(|| serde_json::json!({}));
+        let data = value
+            .as_object_mut()
+            .context("Annotation data is not an object")?;
+        data.insert("id".to_string(), serde_json::json!(annotation.id));
+        data.insert("name".to_string(), serde_json::json!(name));
+        if let Some(context_keys) = annotation.context_keys.as_ref() {
+            data.insert(
+                "slot_names".to_string(),
+                serde_json::json!(context_keys.slot_names),
+            );
+        }
+        Ok(serde_json::from_value(value)?)
+    }
+}
+
+impl JsonExportV2 {
+    /// Builds the Darwin JSON of an item from its current annotations, as listed by
+    /// `DatasetItemMethods::list_annotations`, without generating an export.
+    ///
+    /// The item listing has no urls, so the `source_info` of the item and the thumbnail
+    /// and file urls of its slots are left empty, and the annotators and reviewers of the
+    /// annotations are not included.
+    pub fn from_item_annotations(
+        item: &DatasetItemV2,
+        annotations: &[ItemAnnotation],
+        class_names: &HashMap<u32, String>,
+    ) -> Result<Self> {
+        let slots = item
+            .slots
+            .iter()
+            .flatten()
+            .map(|slot| {
+                let metadata = slot.metadata.as_ref();
+                Some(Slot {
+                    slot_type: slot.item_slot_type.clone().unwrap_or_default(),
+                    slot_name: slot.slot_name.clone().unwrap_or_default(),
+                    width: metadata.and_then(|m| m.width).unwrap_or_default(),
+                    height: metadata.and_then(|m| m.height).unwrap_or_default(),
+                    thumbnail_url: String::new(),
+                    source_files: slot
+                        .file_name
+                        .iter()
+                        .map(|file_name| SourceFile {
+                            file_name: file_name.clone(),
+                            storage_key: slot.storage_key.clone(),
+                            url: String::new(),
+                        })
+                        .collect(),
+                })
+            })
+            .collect();
+
+        Ok(JsonExportV2 {
+            version: DARWIN_JSON_VERSION.to_string(),
+            schema_ref: DARWIN_JSON_SCHEMA_REF.to_string(),
+            item: Item {
+                name: item.name.clone(),
+                path: item.path.clone(),
+                source_info: None,
+                slots,
+            },
+            annotations: annotations
+                .iter()
+                .map(|annotation| ImageAnnotation::from_item_annotation(annotation, class_names))
+                .collect::<Result<Vec<ImageAnnotation>>>()?,
+        })
+    }
+}
+
+/// The image of a Darwin JSON 1.0 export, the format before items had slots
+#[derive(Clone, Debug, Deserialize, Serialize, Default)]
+pub struct ImageV1 {
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    pub original_filename: Option<String>,
+    pub filename: String,
+    pub url: Option<String>,
+    pub thumbnail_url: Option<String>,
+    pub path: Option<String>,
+    pub workview_url: Option<String>,
+    pub seq: Option<u32>,
+    pub frame_count: Option<u32>,
+    pub video_url: Option<String>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, Default)]
+pub struct JsonExportV1 {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+    pub dataset: String,
+    pub image: ImageV1,
+    /// The annotations, with the single `path` of 1.0 polygons and complex polygons
+    /// converted to the `paths` of `Polygon`
+    #[serde(deserialize_with = "deserialize_v1_annotations")]
+    pub annotations: Vec<ImageAnnotation>,
+}
+
+fn deserialize_v1_annotations<'de, D: serde::Deserializer<'de>>(
+    deserializer: D,
+) -> std::result::Result<Vec<ImageAnnotation>, D::Error> {
+    let mut annotations = Vec::<serde_json::Value>::deserialize(deserializer)?;
+    annotations
+        .iter_mut()
+        .map(|annotation| {
+            upgrade_v1_polygons(annotation);
+            if let Some(frames) = annotation
+                .get_mut("frames")
+                .and_then(serde_json::Value::as_object_mut)
+            {
+                frames.values_mut().for_each(upgrade_v1_polygons);
+            }
+            serde_json::from_value(annotation.take()).map_err(serde::de::Error::custom)
+        })
+        .collect()
+}
+
+// 1.0 polygons have a single `path`, complex polygons a `path` of paths
+fn upgrade_v1_polygons(annotation: &mut serde_json::Value) {
+    let Some(annotation) = annotation.as_object_mut() else {
+        return;
+    };
+    if let Some(path) = annotation
+        .get_mut("polygon")
+        .and_then(|polygon| polygon.get_mut("path"))
+    {
+        let paths = serde_json::json!([path.take()]);
+        annotation.insert("polygon".to_string(), serde_json::json!({ "paths": paths }));
+    }
+    if let Some(mut complex) = annotation.remove("complex_polygon") {
+        if let Some(paths) = complex.get_mut("path") {
+            let paths = paths.take();
+            annotation.insert("polygon".to_string(), serde_json::json!({ "paths": paths }));
+        }
+    }
+}
+
+impl JsonExportV1 {
+    /// The 2.0 layout of the export, the image becomes the single slot `0` of the item as
+    /// when V7 migrated items to slots. The 1.0 export has no item id or team.
+    pub fn into_v2(self) -> JsonExportV2 {
+        let image = self.image;
+        let is_video = image.frame_count.is_some() || image.video_url.is_some();
+        let slot = Slot {
+            slot_type: if is_video {
+                DatasetItemTypes::Video
+            } else {
+                DatasetItemTypes::Image
+            },
+            slot_name: "0".to_string(),
+            width: image.width.unwrap_or_default(),
+            height: image.height.unwrap_or_default(),
+            thumbnail_url: image.thumbnail_url.unwrap_or_default(),
+            source_files: vec![SourceFile {
+                file_name: image
+                    .original_filename
+                    .unwrap_or_else(|| image.filename.clone()),
+                storage_key: None,
+                url: image.video_url.or(image.url).unwrap_or_default(),
+            }],
+        };
+        JsonExportV2 {
+            version: DARWIN_JSON_VERSION.to_string(),
+            schema_ref: DARWIN_JSON_SCHEMA_REF.to_string(),
+            item: Item {
+                name: Some(image.filename),
+                path: image.path,
+                source_info: None,
+                slots: vec![Some(slot)],
+            },
+            annotations: self.annotations,
+        }
+    }
+}
+
+/// The major versions of Darwin JSON
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DarwinJsonVersion {
+    V1,
+    V2,
+}
+
+impl Display for DarwinJsonVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            DarwinJsonVersion::V1 => "1.0",
+            DarwinJsonVersion::V2 => "2.0",
+        })
+    }
+}
+
+// The fields that tell the versions apart, the others are skipped without parsing
+#[derive(Deserialize)]
+struct ExportVersion {
+    version: Option<String>,
+    schema_ref: Option<String>,
+    #[serde(default)]
+    image: Option<serde::de::IgnoredAny>,
+    #[serde(default)]
+    item: Option<serde::de::IgnoredAny>,
+}
+
+impl ExportVersion {
+    fn detect(&self) -> Result<DarwinJsonVersion> {
+        if let Some(version) = self.version.as_deref() {
+            return match version.split('.').next() {
+                Some("1") => Ok(DarwinJsonVersion::V1),
+                Some("2") => Ok(DarwinJsonVersion::V2),
+                _ => bail!("Unsupported Darwin JSON version {version}"),
+            };
+        }
+        if self
+            .schema_ref
+            .as_deref()
+            .is_some_and(|schema| schema.contains("darwin_json/2"))
+        {
+            return Ok(DarwinJsonVersion::V2);
+        }
+        match (self.image.is_some(), self.item.is_some()) {
+            (true, false) => Ok(DarwinJsonVersion::V1),
+            (false, true) => Ok(DarwinJsonVersion::V2),
+            _ => bail!("Unable to detect the Darwin JSON version of an export without a version"),
+        }
+    }
+}
+
+/// An export of either version of Darwin JSON, see `parse_any`
+#[derive(Clone, Debug)]
+pub enum ParsedExport {
+    V1(JsonExportV1),
+    V2(JsonExportV2),
+}
+
+impl ParsedExport {
+    pub fn version(&self) -> DarwinJsonVersion {
+        match self {
+            ParsedExport::V1(_) => DarwinJsonVersion::V1,
+            ParsedExport::V2(_) => DarwinJsonVersion::V2,
+        }
+    }
+
+    /// The export in the 2.0 layout, see `JsonExportV1::into_v2`
+    pub fn into_v2(self) -> JsonExportV2 {
+        match self {
+            ParsedExport::V1(export) => export.into_v2(),
+            ParsedExport::V2(export) => export,
+        }
+    }
+}
+
+/// Parses an export of any version of Darwin JSON, detected from its `version`, or
+/// without one its `schema_ref` or whether it has an `image` (1.0) or an `item` (2.0).
+/// Errors name the field that failed to parse.
+pub fn parse_any(json: &str) -> Result<ParsedExport> {
+    let version = serde_json::from_str::<ExportVersion>(json)?.detect()?;
+    let parsed = match version {
+        DarwinJsonVersion::V1 => deserialize_export(json.as_bytes()).map(ParsedExport::V1),
+        DarwinJsonVersion::V2 => deserialize_export(json.as_bytes()).map(ParsedExport::V2),
+    };
+    parsed.map_err(|error| {
+        let context = format!("Failed to parse Darwin JSON {version} at {}", error.path());
+        anyhow::Error::from(error.into_inner()).context(context)
+    })
+}
+
+fn deserialize_export<T: serde::de::DeserializeOwned>(
+    contents: &[u8],
+) -> std::result::Result<T, serde_path_to_error::Error<serde_json::Error>> {
+    serde_path_to_error::deserialize(&mut serde_json::Deserializer::from_slice(contents))
+}
+
+/// An export file `parse_export_dir` could not parse
+#[derive(Debug)]
+pub struct ExportFileError {
+    pub path: PathBuf,
+    /// Path of the field that failed to parse e.g. `annotations[3].polygon.paths`,
+    /// `None` if the file could not be read
+    pub field: Option<String>,
+    /// Line and column of the error within the file
+    pub position: Option<(usize, usize)>,
+    pub error: anyhow::Error,
+}
+
+impl Display for ExportFileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.path.display())?;
+        if let Some((line, column)) = self.position {
+            write!(f, ":{line}:{column}")?;
+        }
+        if let Some(field) = self.field.as_ref() {
+            write!(f, " at {field}")?;
+        }
+        write!(f, ": {}", self.error)
+    }
+}
+
+impl std::error::Error for ExportFileError {}
+
+/// The exports parsed by `parse_export_dir` and the files that failed to parse
+#[derive(Debug, Default)]
+pub struct ExportDir {
+    /// Parsed exports with the path of their file, in path order
+    pub exports: Vec<(PathBuf, JsonExportV2)>,
+    pub errors: Vec<ExportFileError>,
+}
+
+impl ExportDir {
+    pub fn is_complete(&self) -> bool {
+        self.errors.is_empty()
+    }
+}
+
+/// Parses every `.json` file in `path` and its sub-directories as a Darwin JSON export,
+/// converting 1.0 exports to 2.0 (see `parse_any`).
+/// A file that fails to parse is reported in `errors` with the field and position of the
+/// error rather than failing the others, as is a sub-directory or entry that cannot be
+/// read. Only failing to list `path` itself is an error. Symbolic links to directories
+/// are not followed, so a link back up the tree cannot loop.
+pub fn parse_export_dir(path: impl AsRef<Path>) -> Result<ExportDir> {
+    let root = path.as_ref();
+    let mut export_dir = ExportDir::default();
+    let entry_error = |path: &Path, error: std::io::Error| ExportFileError {
+        path: path.to_path_buf(),
+        field: None,
+        position: None,
+        error: error.into(),
+    };
+    let mut files = Vec::new();
+    let mut dirs = vec![root.to_path_buf()];
+    while let Some(dir) = dirs.pop() {
+        let entries = match std::fs::read_dir(&dir) {
+            Ok(entries) => entries,
+            Err(error) if dir == root => {
+                return Err(error)
+                    .with_context(|| format!("Failed to read directory {}", dir.display()))
+            }
+            Err(error) => {
+                export_dir.errors.push(entry_error(&dir, error));
+                continue;
+            }
+        };
+        for entry in entries {
+            let path = match entry {
+                Ok(entry) => entry.path(),
+                Err(error) => {
+                    export_dir.errors.push(entry_error(&dir, error));
+                    continue;
+                }
+            };
+            match std::fs::symlink_metadata(&path) {
+                Ok(metadata) if metadata.is_dir() => dirs.push(path),
+                Ok(_) if path.extension().is_some_and(|ext| ext == "json") => files.push(path),
+                Ok(_) => {}
+                Err(error) => export_dir.errors.push(entry_error(&path, error)),
+            }
+        }
+    }
+    files.sort();
+
+    for path in files {
+        match parse_export_file(&path) {
+            Ok(export) => export_dir.exports.push((path, export)),
+            Err(error) => export_dir.errors.push(error),
+        }
+    }
+    Ok(export_dir)
+}
+
+fn parse_export_file(path: &Path) -> std::result::Result<JsonExportV2, ExportFileError> {
+    let file_error = |error: anyhow::Error| ExportFileError {
+        path: path.to_path_buf(),
+        field: None,
+        position: None,
+        error,
+    };
+    let contents = std::fs::read(path).map_err(|error| file_error(error.into()))?;
+    let version = serde_json::from_slice::<ExportVersion>(&contents)
+        .map_err(|error| ExportFileError {
+            position: Some((error.line(), error.column())),
+            ..file_error(error.into())
+        })?
+        .detect()
+        .map_err(file_error)?;
+    match version {
+        DarwinJsonVersion::V1 => deserialize_export(&contents).map(JsonExportV1::into_v2),
+        DarwinJsonVersion::V2 => deserialize_export(&contents),
+    }
+    .map_err(|error| {
+        let field = error.path().to_string();
+        let inner = error.into_inner();
+        // Only data errors are within a field, not invalid or truncated JSON
+        let in_field = inner.classify() == serde_json::error::Category::Data;
+        ExportFileError {
+            path: path.to_path_buf(),
+            field: (in_field && field != ".").then_some(field),
+            position: Some((inner.line(), inner.column())),
+            error: inner.into(),
+        }
+    })
+}
+
+impl JsonExportV2 {
+    /// The distinct class names of the annotations, in name order
+    pub fn class_names(&self) -> Vec<&str> {
+        let mut names: Vec<&str> = self.annotations.iter().map(|a| a.name.as_str()).collect();
+        names.sort_unstable();
+        names.dedup();
+        names
+    }
+
+    /// Keeps only the annotations of the given classes, returning how many were removed
+    pub fn retain_classes(&mut self, classes: &[&str]) -> usize {
+        let before = self.annotations.len();
+        self.annotations
+            .retain(|annotation| classes.contains(&annotation.name.as_str()));
+        before - self.annotations.len()
+    }
+}
+
+/// A split of the items of an export for training a model
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Split {
+    Train,
+    Val,
+    Test,
+}
+
+impl Split {
+    pub const ALL: [Split; 3] = [Split::Train, Split::Val, Split::Test];
+}
+
+impl Display for Split {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Split::Train => "train",
+            Split::Val => "val",
+            Split::Test => "test",
+        })
+    }
+}
+
+/// The fractions of the items in each split, checked to be non-negative and sum to 1
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SplitRatios {
+    train: f64,
+    val: f64,
+}
+
+impl SplitRatios {
+    pub fn new(train: f64, val: f64, test: f64) -> Result<Self> {
+        if [train, val, test]
+            .iter()
+            .any(|ratio| !ratio.is_finite() || *ratio < 0.0)
+        {
+            bail!("Split ratios {train}/{val}/{test} must be non-negative");
+        }
+        if (train + val + test - 1.0).abs() > 1e-6 {
+            bail!("Split ratios {train}/{val}/{test} do not sum to 1");
+        }
+        Ok(SplitRatios { train, val })
+    }
+
+    fn split(&self, fraction: f64) -> Split {
+        if fraction < self.train {
+            Split::Train
+        } else if fraction < self.train + self.val {
+            Split::Val
+        } else {
+            Split::Test
+        }
+    }
+}
+
+/// How `split_exports` assigns the items of the exports to splits
+#[derive(Debug, Clone, PartialEq)]
+pub enum SplitStrategy {
+    /// In the order of the exports, the first items to train, the next to val and the
+    /// rest to test. Adding items moves items between splits.
+    Ratio(SplitRatios),
+    /// By a hash of the path and name of each item, so an item stays in its split as items
+    /// are added or removed and between runs. The splits only approximate the ratios.
+    Hash(SplitRatios),
+    /// The items in, or below, each folder to its split, the deepest folder wins. Items
+    /// outside the folders are left unassigned.
+    Folder(Vec<(String, Split)>),
+}
+
+/// A row of a split manifest, one per item
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SplitRow {
+    pub name: String,
+    pub path: String,
+    pub item_id: Option<String>,
+    /// The export file of the item
+    pub file: PathBuf,
+    /// Storage keys of the source files of the slots
+    pub storage_keys: Vec<String>,
+    pub annotations: usize,
+}
+
+impl SplitRow {
+    fn new(file: &Path, export: &JsonExportV2) -> Result<Self> {
+        let name = export
+            .item
+            .name
+            .clone()
+            .filter(|name| !name.is_empty())
+            .with_context(|| format!("Export {} has no item name", file.display()))?;
+        Ok(SplitRow {
+            name,
+            path: folder_path(export.item.path.as_deref().unwrap_or("/")),
+            item_id: export
+                .item
+                .source_info
+                .as_ref()
+                .map(|info| info.item_id.clone()),
+            file: file.to_path_buf(),
+            storage_keys: export
+                .item
+                .slots
+                .iter()
+                .flatten()
+                .flat_map(|slot| slot.source_files.iter())
+                .filter_map(|file| file.storage_key.clone())
+                .collect(),
+            annotations: export.annotations.len(),
+        })
+    }
+}
+
+/// The rows of each split made by `split_exports`, in the order of the exports
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct ExportSplits {
+    pub train: Vec<SplitRow>,
+    pub val: Vec<SplitRow>,
+    pub test: Vec<SplitRow>,
+    /// Items outside the folders of `SplitStrategy::Folder`
+    pub unassigned: Vec<SplitRow>,
+}
+
+impl ExportSplits {
+    pub fn rows(&self, split: Split) -> &[SplitRow] {
+        match split {
+            Split::Train => &self.train,
+            Split::Val => &self.val,
+            Split::Test => &self.test,
+        }
+    }
+
+    fn rows_mut(&mut self, split: Split) -> &mut Vec<SplitRow> {
+        match split {
+            Split::Train => &mut self.train,
+            Split::Val => &mut self.val,
+            Split::Test => &mut self.test,
+        }
+    }
+
+    /// The JSONL manifest of a split, one row per line
+    pub fn manifest(&self, split: Split) -> Result<String> {
+        let mut manifest = String::new();
+        for row in self.rows(split) {
+            manifest.push_str(&serde_json::to_string(row)?);
+            manifest.push('\n');
+        }
+        Ok(manifest)
+    }
+
+    /// Writes the manifest of each split to `{split}.jsonl` in `dir`, returning the paths
+    pub fn write_manifests(&self, dir: impl AsRef<Path>) -> Result<Vec<PathBuf>> {
+        Split::ALL
+            .into_iter()
+            .map(|split| {
+                let path = dir.as_ref().join(format!("{split}.jsonl"));
+                std::fs::write(&path, self.manifest(split)?)
+                    .with_context(|| format!("Failed to write {}", path.display()))?;
+                Ok(path)
+            })
+            .collect()
+    }
+}
+
+/// Splits the items of parsed exports, e.g. the `exports` of `parse_export_dir`, into
+/// train, val and test sets. Filter the exports beforehand, e.g. with `retain` and
+/// `JsonExportV2::retain_classes`, to split only some of the items.
+pub fn split_exports(
+    exports: &[(PathBuf, JsonExportV2)],
+    strategy: &SplitStrategy,
+) -> Result<ExportSplits> {
+    let rows = exports
+        .iter()
+        .map(|(file, export)| SplitRow::new(file, export))
+        .collect::<Result<Vec<SplitRow>>>()?;
+
+    let mut splits = ExportSplits::default();
+    match strategy {
+        SplitStrategy::Ratio(ratios) => {
+            let count = rows.len();
+            for (index, row) in rows.into_iter().enumerate() {
+                let split = ratios.split(index as f64 / count as f64);
+                splits.rows_mut(split).push(row);
+            }
+        }
+        SplitStrategy::Hash(ratios) => {
+            for row in rows {
+                let hash = stable_hash(format!("{}/{}", row.path.trim_end_matches('/'), row.name));
+                let split = ratios.split(hash as f64 / u64::MAX as f64);
+                splits.rows_mut(split).push(row);
+            }
+        }
+        SplitStrategy::Folder(folders) => {
+            let folders: Vec<(String, Split)> = folders
+                .iter()
+                .map(|(folder, split)| (folder_path(folder), *split))
+                .collect();
+            for row in rows {
+                let split = folders
+                    .iter()
+                    .filter(|(folder, _)| in_folder(&row.path, folder))
+                    .max_by_key(|(folder, _)| folder.len())
+                    .map(|(_, split)| *split);
+                match split {
+                    Some(split) => splits.rows_mut(split).push(row),
+                    None => splits.unassigned.push(row),
+                }
+            }
+        }
+    }
+    Ok(splits)
+}
+
+/// FNV-1a, unlike the std hashers it is the same across Rust versions and platforms,
+/// with the splitmix64 finalizer so names differing in their last characters still
+/// differ in the high bits that pick the split
+fn stable_hash(value: String) -> u64 {
+    let hash = value.bytes().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+    });
+    let hash = (hash ^ (hash >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    let hash = (hash ^ (hash >> 27)).wrapping_mul(0x94d049bb133111eb);
+    hash ^ (hash >> 31)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -175,6 +1106,347 @@ mod tests {
             1
         );
         assert!(export.annotations[0].tag.is_none());
+        assert_eq!(
+            export.annotations[0].slot_names,
+            vec!["bf007a29-6559-d0cc-c549-45c7c66d4c70.e47f119".to_string()]
+        );
+        assert!(export.annotations[0].frames.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_video_annotation_frames() {
+        let annotation: ImageAnnotation = serde_json::from_str(
+            r#"{
+              "id": "c3b0a2a8-5f0b-4a54-9b43-9e1d0e6f4b7a",
+              "name": "Cell",
+              "frames": {
+                "12": {"keyframe": true, "tag": {}},
+                "3": {"keyframe": true, "bounding_box": {"h": 1.0, "w": 2.0, "x": 3.0, "y": 4.0}}
+              },
+              "ranges": [[3, 13]],
+              "slot_names": ["0"]
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!(annotation.slot_names, vec!["0".to_string()]);
+        assert_eq!(annotation.frame_indices(), vec![3, 12]);
+        assert!(annotation.frames[&3].bounding_box.is_some());
+        assert!(annotation.frames[&12].tag.is_some());
+        assert_eq!(annotation.ranges, vec![[3, 13]]);
+
+        let value = serde_json::to_value(&annotation).unwrap();
+        assert_eq!(value["slot_names"][0], "0");
+        assert_eq!(value["frames"]["12"]["keyframe"], true);
+    }
+
+    #[test]
+    fn test_sub_annotations() {
+        let annotation: ImageAnnotation = serde_json::from_str(
+            r#"{
+              "id": "a1",
+              "name": "Nucleus",
+              "polygon": {"paths": [[{"x": 1.0, "y": 1.0}]]},
+              "attributes": {"attributes": ["mitotic", "atypical"]},
+              "instance_id": {"value": 4},
+              "properties": [
+                {"frame_index": null, "name": "Grade", "value": "G2"},
+                {"name": "Confidence", "value": null}
+              ],
+              "slot_names": ["0"]
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            annotation.attributes,
+            Some(Attributes {
+                attributes: vec!["mitotic".to_string(), "atypical".to_string()]
+            })
+        );
+        assert_eq!(annotation.instance_id, Some(InstanceId { value: 4 }));
+        assert_eq!(annotation.properties.len(), 2);
+        assert_eq!(annotation.properties[0].value.as_deref(), Some("G2"));
+        assert_eq!(annotation.properties[1].value, None);
+
+        let value = serde_json::to_value(&annotation).unwrap();
+        assert_eq!(value["instance_id"]["value"], 4);
+        assert_eq!(value["properties"][0]["name"], "Grade");
+    }
+
+    #[test]
+    fn test_ellipse_cuboid_and_line_annotations() {
+        let annotations: Vec<ImageAnnotation> = serde_json::from_str(
+            r#"[
+              {
+                "name": "Nucleus",
+                "ellipse": {"angle": 0.3, "center": {"x": 5.0, "y": 6.0}, "radius": {"x": 2.0, "y": 1.0}}
+              },
+              {
+                "name": "Box",
+                "cuboid": {
+                  "back": {"h": 1.0, "w": 1.0, "x": 2.0, "y": 2.0},
+                  "front": {"h": 2.0, "w": 2.0, "x": 0.0, "y": 0.0}
+                }
+              },
+              {
+                "name": "Margin",
+                "line": {"path": [{"x": 0.0, "y": 0.0}, {"x": 3.0, "y": 4.0}]}
+              }
+            ]"#,
+        )
+        .unwrap();
+
+        let ellipse = annotations[0].ellipse.as_ref().unwrap();
+        assert_eq!(ellipse.center, Keypoint { x: 5.0, y: 6.0 });
+        assert_eq!(ellipse.radius, Keypoint { x: 2.0, y: 1.0 });
+        assert_eq!(ellipse.angle, 0.3);
+        let cuboid = annotations[1].cuboid.as_ref().unwrap();
+        assert_eq!(cuboid.back.x, Some(2.0));
+        assert_eq!(cuboid.front.w, Some(2.0));
+        assert_eq!(annotations[2].line.as_ref().unwrap().path.len(), 2);
+
+        let value = serde_json::to_value(&annotations[2]).unwrap();
+        assert_eq!(value["line"]["path"][1]["y"], 4.0);
+        assert!(value.get("ellipse").is_none());
+    }
+
+    #[test]
+    fn test_annotation_identity() {
+        let annotations: Vec<ImageAnnotation> = serde_json::from_str(
+            r#"[
+              {"id": "a", "name": "Cell", "polygon": {"paths": [[{"x": 0.0, "y": 0.0}, {"x": 1.0, "y": 1.0}]]}},
+              {"id": "b", "name": "Cell", "polygon": {"paths": [[{"x": 0.0001, "y": -0.0001}, {"x": 1.0, "y": 1.0}]]}},
+              {"id": "c", "name": "Nucleus", "polygon": {"paths": [[{"x": 0.0, "y": 0.0}, {"x": 1.0, "y": 1.0}]]}},
+              {"id": "d", "name": "Cell", "polygon": {"paths": [[{"x": 0.0, "y": 0.0}, {"x": 1.0, "y": 2.0}]]}},
+              {"id": "e", "name": "Cell", "tag": {}},
+              {"id": "f", "name": "Cell", "tag": {}, "slot_names": ["1"]},
+              {"id": "g", "name": "Cell", "text": {"text": "Mitosis"}},
+              {"id": "h", "name": "Cell", "text": {"text": "Apoptosis"}}
+            ]"#,
+        )
+        .unwrap();
+        let identities: Vec<AnnotationIdentity> = annotations
+            .iter()
+            .map(|annotation| annotation.identity().unwrap())
+            .collect();
+        assert_eq!(identities[0], identities[1]);
+        assert_ne!(identities[0], identities[2]);
+        assert_ne!(identities[0], identities[3]);
+        assert_ne!(identities[0], identities[4]);
+        assert_ne!(identities[4], identities[5]);
+        assert_eq!(identities[4].annotation_type, Some("tag"));
+        assert_ne!(identities[6], identities[7]);
+    }
+
+    #[test]
+    fn test_unmodelled_annotation_identity() {
+        let annotations: Vec<ImageAnnotation> = serde_json::from_str(
+            r#"[
+              {"id": "a", "name": "Cell", "keypoint": {"x": 1.0, "y": 2.0}},
+              {"id": "b", "name": "Cell", "keypoint": {"x": 5.0, "y": 6.0}},
+              {"id": "c", "name": "Cell", "frames": {"0": {"keypoint": {"x": 1.0, "y": 2.0}}}},
+              {"id": "d", "name": "Cell", "frames": {"0": {"polygon": {"paths": []}}}}
+            ]"#,
+        )
+        .unwrap();
+        assert!(annotations[0].identity().is_none());
+        assert!(annotations[1].identity().is_none());
+        assert!(annotations[2].identity().is_none());
+        assert!(annotations[3].identity().is_some());
+    }
+
+    #[test]
+    fn test_parse_export_dir() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let export = |annotations: &str| {
+            format!(
+                r#"{{"version": "2.0", "schema_ref": "", "item": {{"slots": []}},
+                "annotations": {annotations}}}"#
+            )
+        };
+        std::fs::create_dir(dir.path().join("folder"))?;
+        std::fs::write(dir.path().join("a.json"), export("[]"))?;
+        std::fs::write(
+            dir.path().join("folder/b.json"),
+            export(r#"[{"name": "Cell", "polygon": {"paths": [[{"x": 1.0}]]}}]"#),
+        )?;
+        std::fs::write(dir.path().join("c.json"), "{")?;
+        std::fs::write(dir.path().join("notes.txt"), "not an export")?;
+        #[cfg(unix)]
+        std::os::unix::fs::symlink(dir.path(), dir.path().join("folder/loop"))?;
+
+        let parsed = parse_export_dir(dir.path())?;
+        assert!(!parsed.is_complete());
+        assert_eq!(parsed.exports.len(), 1);
+        assert_eq!(parsed.exports[0].0, dir.path().join("a.json"));
+
+        assert_eq!(parsed.errors.len(), 2);
+        assert_eq!(parsed.errors[0].path, dir.path().join("c.json"));
+        assert_eq!(parsed.errors[0].field, None);
+        let error = &parsed.errors[1];
+        assert_eq!(error.path, dir.path().join("folder/b.json"));
+        assert_eq!(
+            error.field.as_deref(),
+            Some("annotations[0].polygon.paths[0][0]")
+        );
+        assert!(error.to_string().contains("missing field `y`"), "{error}");
+
+        assert!(parse_export_dir(dir.path().join("missing")).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_split_exports() -> Result<()> {
+        let exports: Vec<(PathBuf, JsonExportV2)> = (0..100)
+            .map(|index| {
+                let folder = if index < 10 {
+                    "/held-out"
+                } else {
+                    "/scans/2024"
+                };
+                let export: JsonExportV2 = serde_json::from_value(serde_json::json!({
+                    "version": "2.0",
+                    "schema_ref": "",
+                    "item": {"name": format!("item-{index}"), "path": folder, "slots": []},
+                    "annotations": [{"name": "Cell", "tag": {}}, {"name": "Nucleus", "tag": {}}]
+                }))
+                .unwrap();
+                (PathBuf::from(format!("item-{index}.json")), export)
+            })
+            .collect();
+
+        let mut filtered = exports[0].1.clone();
+        assert_eq!(filtered.class_names(), vec!["Cell", "Nucleus"]);
+        assert_eq!(filtered.retain_classes(&["Nucleus"]), 1);
+        assert_eq!(filtered.class_names(), vec!["Nucleus"]);
+
+        assert!(SplitRatios::new(0.8, 0.1, 0.2).is_err());
+        assert!(SplitRatios::new(1.1, -0.1, 0.0).is_err());
+        let ratios = SplitRatios::new(0.8, 0.1, 0.1)?;
+
+        let splits = split_exports(&exports, &SplitStrategy::Ratio(ratios))?;
+        assert_eq!(
+            (splits.train.len(), splits.val.len(), splits.test.len()),
+            (80, 10, 10)
+        );
+        assert_eq!(splits.train[0].name, "item-0");
+        assert_eq!(splits.test[9].name, "item-99");
+        assert_eq!(splits.train[0].annotations, 2);
+
+        let splits = split_exports(&exports, &SplitStrategy::Hash(ratios))?;
+        assert_eq!(
+            splits.train.len() + splits.val.len() + splits.test.len(),
+            100
+        );
+        assert!(
+            (60..=95).contains(&splits.train.len()),
+            "{}",
+            splits.train.len()
+        );
+        // An item stays in its split when the other items change
+        let some = split_exports(&exports[..50], &SplitStrategy::Hash(ratios))?;
+        for split in Split::ALL {
+            for row in some.rows(split) {
+                assert!(splits.rows(split).contains(row), "{}", row.name);
+            }
+        }
+
+        let folders = vec![
+            ("scans".to_string(), Split::Train),
+            ("/scans/2024/".to_string(), Split::Val),
+            ("/held".to_string(), Split::Test),
+        ];
+        let splits = split_exports(&exports, &SplitStrategy::Folder(folders))?;
+        assert_eq!(splits.val.len(), 90);
+        assert!(splits.train.is_empty() && splits.test.is_empty());
+        assert_eq!(splits.unassigned.len(), 10);
+
+        let dir = tempfile::tempdir()?;
+        let paths = splits.write_manifests(dir.path())?;
+        assert_eq!(paths[1], dir.path().join("val.jsonl"));
+        let manifest = std::fs::read_to_string(&paths[1])?;
+        assert_eq!(manifest.lines().count(), 90);
+        let row: SplitRow = serde_json::from_str(manifest.lines().next().unwrap())?;
+        assert_eq!(row, splits.val[0]);
+        assert_eq!(std::fs::read_to_string(&paths[0])?, "");
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_any() -> Result<()> {
+        let v1 = r#"{
+          "dataset": "Biopsies",
+          "image": {
+            "width": 100, "height": 50, "original_filename": "scan.png", "filename": "scan.png",
+            "url": "https://example.com/scan.png", "path": "/scans", "seq": 1
+          },
+          "annotations": [
+            {"name": "Cell", "polygon": {"path": [{"x": 0.0, "y": 0.0}, {"x": 1.0, "y": 1.0}]}},
+            {"name": "Gland", "complex_polygon": {"path": [[{"x": 0.0, "y": 0.0}], [{"x": 2.0, "y": 2.0}]]}},
+            {"name": "Mitosis", "tag": {}}
+          ]
+        }"#;
+        let parsed = parse_any(v1)?;
+        assert_eq!(parsed.version(), DarwinJsonVersion::V1);
+        let export = parsed.into_v2();
+        assert_eq!(export.version, DARWIN_JSON_VERSION);
+        assert_eq!(export.item.name.as_deref(), Some("scan.png"));
+        assert_eq!(export.item.path.as_deref(), Some("/scans"));
+        let slot = export.item.slots[0].as_ref().unwrap();
+        assert_eq!((slot.slot_name.as_str(), slot.width), ("0", 100));
+        assert_eq!(slot.source_files[0].url, "https://example.com/scan.png");
+        assert_eq!(
+            export.annotations[0].polygon.as_ref().unwrap().paths.len(),
+            1
+        );
+        assert_eq!(
+            export.annotations[1].polygon.as_ref().unwrap().paths.len(),
+            2
+        );
+        assert!(export.annotations[2].tag.is_some());
+
+        let video = r#"{
+          "version": "1.0",
+          "dataset": "Videos",
+          "image": {"filename": "clip.mp4", "frame_count": 2, "video_url": "https://example.com/clip.mp4"},
+          "annotations": [{
+            "name": "Cell",
+            "frames": {"0": {"keyframe": true, "polygon": {"path": [{"x": 1.0, "y": 1.0}]}}},
+            "ranges": [[0, 2]]
+          }]
+        }"#;
+        let export = parse_any(video)?.into_v2();
+        let slot = export.item.slots[0].as_ref().unwrap();
+        assert!(matches!(slot.slot_type, DatasetItemTypes::Video));
+        let frame = &export.annotations[0].frames[&0];
+        assert_eq!(frame.polygon.as_ref().unwrap().paths[0][0].x, 1.0);
+
+        let v2 = r#"{"schema_ref": "https://darwin-public.s3.eu-west-1.amazonaws.com/darwin_json/2.0/schema.json",
+          "version": "2.0", "item": {"name": "scan.png", "slots": []}, "annotations": []}"#;
+        assert_eq!(parse_any(v2)?.version(), DarwinJsonVersion::V2);
+        let unversioned = r#"{"item": {"name": "scan.png", "slots": []}, "annotations": []}"#;
+        assert_eq!(parse_any(unversioned)?.version(), DarwinJsonVersion::V2);
+
+        let error = parse_any(r#"{"version": "3.0"}"#).unwrap_err();
+        assert_eq!(error.to_string(), "Unsupported Darwin JSON version 3.0");
+        assert!(parse_any(r#"{"annotations": []}"#).is_err());
+        let error =
+            parse_any(r#"{"dataset": "Biopsies", "image": {"filename": 1}, "annotations": []}"#)
+                .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Failed to parse Darwin JSON 1.0 at image.filename"
+        );
+
+        let dir = tempfile::tempdir()?;
+        std::fs::write(dir.path().join("a.json"), v1)?;
+        std::fs::write(dir.path().join("b.json"), v2)?;
+        let parsed = parse_export_dir(dir.path())?;
+        assert!(parsed.is_complete());
+        assert_eq!(parsed.exports[0].1.item.slots.len(), 1);
+        assert_eq!(parsed.exports[1].1.item.name.as_deref(), Some("scan.png"));
         Ok(())
     }
 }
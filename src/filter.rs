@@ -1,8 +1,15 @@
+use crate::ids::{ClassId, DatasetId, UserId};
+use crate::item::DatasetItemStatus;
+use anyhow::{bail, Result};
+#[cfg(feature = "fake")]
 #[allow(unused_imports)]
 use fake::{Dummy, Fake};
 use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::hash::Hash;
 
-#[derive(Debug, Default, Clone, Serialize, Deserialize, Dummy, PartialEq, Eq)]
+#[derive(Debug, Default, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[cfg_attr(feature = "fake", derive(Dummy))]
 pub struct Filter {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub statuses: Option<Vec<String>>,
@@ -80,7 +87,7 @@ pub struct Filter {
     pub item_ids: Option<Vec<String>>,
 
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub dataset_ids: Option<Vec<u32>>,
+    pub dataset_ids: Option<Vec<DatasetId>>,
 
     #[serde(skip_serializing_if = "Option::is_none")]
     pub not_item_name_prefix: Option<String>,
@@ -107,6 +114,270 @@ pub struct Filter {
     pub select_all: Option<bool>,
 }
 
+impl Filter {
+    pub fn builder() -> FilterBuilder {
+        FilterBuilder::default()
+    }
+}
+
+/// Builds a `Filter` one criterion at a time, checking for incompatible criteria on `build`.
+///
+/// ```
+/// use darwin_v7::filter::Filter;
+///
+/// let filter = Filter::builder()
+///     .statuses(["new", "annotate"])
+///     .in_folder("/batch-3")
+///     .build()
+///     .unwrap();
+/// ```
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct FilterBuilder {
+    filter: Filter,
+}
+
+fn extend<T, I, V>(field: &mut Option<Vec<T>>, values: I)
+where
+    I: IntoIterator<Item = V>,
+    V: Into<T>,
+{
+    field
+        .get_or_insert_with(Vec::new)
+        .extend(values.into_iter().map(Into::into));
+}
+
+fn check_disjoint<T>(name: &str, included: &Option<Vec<T>>, excluded: &Option<Vec<T>>) -> Result<()>
+where
+    T: Eq + Hash + std::fmt::Debug,
+{
+    if let (Some(included), Some(excluded)) = (included, excluded) {
+        let included: HashSet<&T> = included.iter().collect();
+        let overlap: Vec<&T> = excluded.iter().filter(|x| included.contains(x)).collect();
+        if !overlap.is_empty() {
+            bail!("Filter both includes and excludes {name} {overlap:?}");
+        }
+    }
+    Ok(())
+}
+
+impl FilterBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Matches every item of the dataset subject to the other criteria
+    pub fn select_all(mut self) -> Self {
+        self.filter.select_all = Some(true);
+        self
+    }
+
+    pub fn statuses<I, S>(mut self, statuses: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        extend(&mut self.filter.statuses, statuses);
+        self
+    }
+
+    pub fn not_statuses<I, S>(mut self, statuses: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        extend(&mut self.filter.not_statuses, statuses);
+        self
+    }
+
+    /// Matches the items in a discard stage of their workflow
+    pub fn discarded(self) -> Self {
+        self.statuses([DatasetItemStatus::Discard.to_string().to_lowercase()])
+    }
+
+    pub fn not_discarded(self) -> Self {
+        self.not_statuses([DatasetItemStatus::Discard.to_string().to_lowercase()])
+    }
+
+    /// Matches the items directly within the folder `path`
+    pub fn in_folder(mut self, path: &str) -> Self {
+        extend(&mut self.filter.item_paths, [path]);
+        self
+    }
+
+    pub fn not_in_folder(mut self, path: &str) -> Self {
+        extend(&mut self.filter.not_item_paths, [path]);
+        self
+    }
+
+    /// Matches the items within the folder `prefix` or any of its sub-folders
+    pub fn path_prefix(mut self, prefix: &str) -> Self {
+        self.filter.item_path_prefix = Some(prefix.to_string());
+        self
+    }
+
+    pub fn item_ids<I, S>(mut self, ids: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        extend(&mut self.filter.item_ids, ids);
+        self
+    }
+
+    pub fn not_item_ids<I, S>(mut self, ids: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        extend(&mut self.filter.not_item_ids, ids);
+        self
+    }
+
+    pub fn item_names<I, S>(mut self, names: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        extend(&mut self.filter.item_names, names);
+        self
+    }
+
+    pub fn not_item_names<I, S>(mut self, names: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        extend(&mut self.filter.not_item_names, names);
+        self
+    }
+
+    pub fn name_contains(mut self, value: &str) -> Self {
+        self.filter.item_name_contains = Some(value.to_string());
+        self
+    }
+
+    pub fn name_prefix(mut self, prefix: &str) -> Self {
+        self.filter.item_name_prefix = Some(prefix.to_string());
+        self
+    }
+
+    pub fn types<I, S>(mut self, types: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        extend(&mut self.filter.types, types);
+        self
+    }
+
+    pub fn dataset_ids<I, V>(mut self, ids: I) -> Self
+    where
+        I: IntoIterator<Item = V>,
+        V: Into<DatasetId>,
+    {
+        extend(
+            &mut self.filter.dataset_ids,
+            ids.into_iter().map(Into::into),
+        );
+        self
+    }
+
+    pub fn workflow_stage_ids<I: IntoIterator<Item = u32>>(mut self, ids: I) -> Self {
+        extend(&mut self.filter.workflow_stage_ids, ids);
+        self
+    }
+
+    pub fn not_workflow_stage_ids<I: IntoIterator<Item = u32>>(mut self, ids: I) -> Self {
+        extend(&mut self.filter.not_workflow_stage_ids, ids);
+        self
+    }
+
+    pub fn annotation_class_ids<I, V>(mut self, ids: I) -> Self
+    where
+        I: IntoIterator<Item = V>,
+        V: Into<ClassId>,
+    {
+        extend(
+            &mut self.filter.annotation_class_ids,
+            ids.into_iter().map(|id| id.into().0),
+        );
+        self
+    }
+
+    pub fn not_annotation_class_ids<I, V>(mut self, ids: I) -> Self
+    where
+        I: IntoIterator<Item = V>,
+        V: Into<ClassId>,
+    {
+        extend(
+            &mut self.filter.not_annotation_class_ids,
+            ids.into_iter().map(|id| id.into().0),
+        );
+        self
+    }
+
+    pub fn assignees<I, V>(mut self, user_ids: I) -> Self
+    where
+        I: IntoIterator<Item = V>,
+        V: Into<UserId>,
+    {
+        extend(
+            &mut self.filter.assignees,
+            user_ids.into_iter().map(|id| id.into().0),
+        );
+        self
+    }
+
+    pub fn current_assignees<I, V>(mut self, user_ids: I) -> Self
+    where
+        I: IntoIterator<Item = V>,
+        V: Into<UserId>,
+    {
+        extend(
+            &mut self.filter.current_assignees,
+            user_ids.into_iter().map(|id| id.into().0),
+        );
+        self
+    }
+
+    pub fn has_comments(mut self, has_comments: bool) -> Self {
+        self.filter.has_comments = Some(has_comments);
+        self
+    }
+
+    /// Returns the filter, failing if it contains incompatible criteria
+    pub fn build(self) -> Result<Filter> {
+        let filter = self.filter;
+
+        if filter.select_all == Some(true) && filter.item_ids.is_some() {
+            bail!("Filter cannot both select all items and select items by id");
+        }
+        check_disjoint("statuses", &filter.statuses, &filter.not_statuses)?;
+        check_disjoint("item ids", &filter.item_ids, &filter.not_item_ids)?;
+        check_disjoint("item names", &filter.item_names, &filter.not_item_names)?;
+        check_disjoint("item paths", &filter.item_paths, &filter.not_item_paths)?;
+        check_disjoint("types", &filter.types, &filter.not_types)?;
+        check_disjoint(
+            "workflow stages",
+            &filter.workflow_stage_ids,
+            &filter.not_workflow_stage_ids,
+        )?;
+        check_disjoint(
+            "annotation classes",
+            &filter.annotation_class_ids,
+            &filter.not_annotation_class_ids,
+        )?;
+        check_disjoint("assignees", &filter.assignees, &filter.not_assignees)?;
+        if let (Some(from), Some(to)) = (filter.accuracy_from, filter.accuracy_to) {
+            if from > to {
+                bail!("Filter accuracy range {from} to {to} is empty");
+            }
+        }
+
+        Ok(filter)
+    }
+}
+
 #[cfg(test)]
 mod test_serde {
     use super::*;
@@ -135,4 +406,55 @@ mod test_serde {
 
         assert_eq!(new_filter, filter);
     }
+
+    #[test]
+    fn test_builder() {
+        let filter = Filter::builder()
+            .statuses(["new", "annotate"])
+            .in_folder("/batch-3")
+            .workflow_stage_ids([1, 2])
+            .build()
+            .unwrap();
+
+        assert_eq!(
+            filter,
+            Filter {
+                statuses: Some(vec!["new".to_string(), "annotate".to_string()]),
+                item_paths: Some(vec!["/batch-3".to_string()]),
+                workflow_stage_ids: Some(vec![1, 2]),
+                ..Default::default()
+            }
+        );
+    }
+
+    #[test]
+    fn test_builder_discarded() {
+        let filter = Filter::builder().discarded().build().unwrap();
+        assert_eq!(filter.statuses, Some(vec!["discard".to_string()]));
+        assert_eq!(
+            serde_json::to_value(crate::workflow::StageType::Discard).unwrap(),
+            "discard"
+        );
+
+        Filter::builder()
+            .discarded()
+            .not_discarded()
+            .build()
+            .expect_err("Filter cannot both include and exclude discarded items");
+    }
+
+    #[test]
+    fn test_builder_incompatible() {
+        Filter::builder()
+            .select_all()
+            .item_ids(["some-id"])
+            .build()
+            .expect_err("Filter cannot both select all items and select items by id");
+
+        Filter::builder()
+            .statuses(["new", "complete"])
+            .not_statuses(["complete"])
+            .build()
+            .expect_err("Filter both includes and excludes statuses");
+    }
 }
@@ -0,0 +1,294 @@
+//! Bounding boxes, areas and intersection over union of annotation geometries, e.g. to
+//! check the polygons of an export.
+//!
+//! Polygons with several paths follow the even-odd rule of V7, so a path inside another
+//! is a hole. Paths are expected not to intersect themselves.
+
+use crate::annotation::{BoundingBox, Keypoint, Polygon};
+
+impl BoundingBox {
+    /// The smallest box containing all of the points, `None` if there are none
+    pub fn from_points<'a, I>(points: I) -> Option<Self>
+    where
+        I: IntoIterator<Item = &'a Keypoint>,
+    {
+        let mut points = points.into_iter();
+        let first = points.next()?;
+        let (min_x, min_y, max_x, max_y) = points.fold(
+            (first.x, first.y, first.x, first.y),
+            |(min_x, min_y, max_x, max_y), point| {
+                (
+                    min_x.min(point.x),
+                    min_y.min(point.y),
+                    max_x.max(point.x),
+                    max_y.max(point.y),
+                )
+            },
+        );
+        Some(Self {
+            h: Some(max_y - min_y),
+            w: Some(max_x - min_x),
+            x: Some(min_x),
+            y: Some(min_y),
+        })
+    }
+
+    /// `None` if any of the dimensions of the box are missing
+    pub fn area(&self) -> Option<f32> {
+        Some(self.w?.max(0.0) * self.h?.max(0.0))
+    }
+
+    /// Intersection over union with `other`, `None` if either box is missing a dimension
+    pub fn iou(&self, other: &BoundingBox) -> Option<f32> {
+        let (a_x, a_y, a_w, a_h) = (self.x?, self.y?, self.w?, self.h?);
+        let (b_x, b_y, b_w, b_h) = (other.x?, other.y?, other.w?, other.h?);
+        let overlap_w = ((a_x + a_w).min(b_x + b_w) - a_x.max(b_x)).max(0.0);
+        let overlap_h = ((a_y + a_h).min(b_y + b_h) - a_y.max(b_y)).max(0.0);
+        let intersection = overlap_w * overlap_h;
+        let union = self.area()? + other.area()? - intersection;
+        Some(if union > 0.0 {
+            intersection / union
+        } else if (a_x, a_y, a_w, a_h) == (b_x, b_y, b_w, b_h) {
+            1.0
+        } else {
+            0.0
+        })
+    }
+
+    /// The box as a polygon with a single path, clockwise from the top left corner
+    pub fn to_polygon(&self) -> Option<Polygon> {
+        let (x, y, w, h) = (self.x?, self.y?, self.w?, self.h?);
+        Some(Polygon {
+            paths: vec![vec![
+                Keypoint { x, y },
+                Keypoint { x: x + w, y },
+                Keypoint { x: x + w, y: y + h },
+                Keypoint { x, y: y + h },
+            ]],
+        })
+    }
+}
+
+impl Polygon {
+    /// The smallest box containing every path, `None` if the polygon has no points
+    pub fn bounding_box(&self) -> Option<BoundingBox> {
+        BoundingBox::from_points(self.paths.iter().flatten())
+    }
+
+    /// The area inside the paths by the even-odd rule, so inner paths are holes. A single
+    /// path is measured with the shoelace formula, which is linear in its points
+    pub fn area(&self) -> f32 {
+        match self.paths.as_slice() {
+            [path] => shoelace_area(path) as f32,
+            _ => overlap_areas(self, &Polygon::default()).0 as f32,
+        }
+    }
+
+    /// Intersection over union with `other`. Polygons without area are only identical
+    /// to polygons with the same paths.
+    pub fn iou(&self, other: &Polygon) -> f32 {
+        let (area, other_area, intersection) = overlap_areas(self, other);
+        let union = area + other_area - intersection;
+        if union > 0.0 {
+            (intersection / union) as f32
+        } else if self.paths == other.paths {
+            1.0
+        } else {
+            0.0
+        }
+    }
+}
+
+type Edge = ((f64, f64), (f64, f64));
+
+// The area of a path which does not cross itself, whichever way round it goes
+fn shoelace_area(path: &[Keypoint]) -> f64 {
+    if path.len() < 3 {
+        return 0.0;
+    }
+    let twice_area: f64 = path
+        .iter()
+        .zip(path.iter().cycle().skip(1))
+        .map(|(a, b)| a.x as f64 * b.y as f64 - b.x as f64 * a.y as f64)
+        .sum();
+    twice_area.abs() / 2.0
+}
+
+fn edges(polygon: &Polygon) -> Vec<Edge> {
+    polygon
+        .paths
+        .iter()
+        .filter(|path| path.len() > 2)
+        .flat_map(|path| {
+            path.iter()
+                .zip(path.iter().cycle().skip(1))
+                .map(|(a, b)| ((a.x as f64, a.y as f64), (b.x as f64, b.y as f64)))
+        })
+        .collect()
+}
+
+// The x coordinate where two edges cross, if they do
+fn crossing_x(((x1, y1), (x2, y2)): Edge, ((x3, y3), (x4, y4)): Edge) -> Option<f64> {
+    let denominator = (x1 - x2) * (y3 - y4) - (y1 - y2) * (x3 - x4);
+    if denominator == 0.0 {
+        return None;
+    }
+    let t = ((x1 - x3) * (y3 - y4) - (y1 - y3) * (x3 - x4)) / denominator;
+    let u = ((x1 - x3) * (y1 - y2) - (y1 - y3) * (x1 - x2)) / denominator;
+    ((0.0..=1.0).contains(&t) && (0.0..=1.0).contains(&u)).then_some(x1 + t * (x2 - x1))
+}
+
+// The intervals of the vertical line at `x` inside the polygon by the even-odd rule
+fn intervals_at(edges: &[Edge], x: f64) -> Vec<(f64, f64)> {
+    let mut crossings: Vec<f64> = edges
+        .iter()
+        .filter(|((x1, _), (x2, _))| x1.min(*x2) < x && x < x1.max(*x2))
+        .map(|((x1, y1), (x2, y2))| y1 + (x - x1) * (y2 - y1) / (x2 - x1))
+        .collect();
+    crossings.sort_by(f64::total_cmp);
+    crossings
+        .chunks_exact(2)
+        .map(|pair| (pair[0], pair[1]))
+        .collect()
+}
+
+fn intervals_length(intervals: &[(f64, f64)]) -> f64 {
+    intervals.iter().map(|(start, end)| end - start).sum()
+}
+
+fn intersection_length(a: &[(f64, f64)], b: &[(f64, f64)]) -> f64 {
+    let (mut i, mut j, mut length) = (0, 0, 0.0);
+    while i < a.len() && j < b.len() {
+        length += (a[i].1.min(b[j].1) - a[i].0.max(b[j].0)).max(0.0);
+        if a[i].1 < b[j].1 {
+            i += 1;
+        } else {
+            j += 1;
+        }
+    }
+    length
+}
+
+// The areas of `a`, `b` and their intersection, exact for polygons with straight edges.
+//
+// The plane is cut into vertical slabs at every vertex and edge crossing, within a slab
+// no edges cross so the length of each vertical section changes linearly and the area
+// of the slab is its width times the length of the section through its middle.
+fn overlap_areas(a: &Polygon, b: &Polygon) -> (f64, f64, f64) {
+    let (a_edges, b_edges) = (edges(a), edges(b));
+    let all_edges: Vec<Edge> = a_edges.iter().chain(b_edges.iter()).copied().collect();
+
+    let mut cuts: Vec<f64> = all_edges.iter().map(|((x, _), _)| *x).collect();
+    for (index, edge) in all_edges.iter().enumerate() {
+        cuts.extend(
+            all_edges[index + 1..]
+                .iter()
+                .filter_map(|other| crossing_x(*edge, *other)),
+        );
+    }
+    cuts.sort_by(f64::total_cmp);
+    cuts.dedup();
+
+    let (mut a_area, mut b_area, mut intersection) = (0.0, 0.0, 0.0);
+    for slab in cuts.windows(2) {
+        let (x, width) = ((slab[0] + slab[1]) / 2.0, slab[1] - slab[0]);
+        let a_intervals = intervals_at(&a_edges, x);
+        let b_intervals = intervals_at(&b_edges, x);
+        a_area += width * intervals_length(&a_intervals);
+        b_area += width * intervals_length(&b_intervals);
+        intersection += width * intersection_length(&a_intervals, &b_intervals);
+    }
+    (a_area, b_area, intersection)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn path(points: &[(f32, f32)]) -> Vec<Keypoint> {
+        points.iter().map(|&(x, y)| Keypoint { x, y }).collect()
+    }
+
+    fn square(x: f32, y: f32, size: f32) -> Vec<Keypoint> {
+        path(&[(x, y), (x + size, y), (x + size, y + size), (x, y + size)])
+    }
+
+    fn assert_close(actual: f32, expected: f32) {
+        assert!((actual - expected).abs() < 1e-4, "{actual} != {expected}");
+    }
+
+    #[test]
+    fn test_bounding_box() {
+        let polygon = Polygon {
+            paths: vec![path(&[(1.0, 5.0), (4.0, 2.0), (6.0, 7.0)])],
+        };
+        let bbox = polygon.bounding_box().unwrap();
+        assert_eq!(
+            (bbox.x, bbox.y, bbox.w, bbox.h),
+            (Some(1.0), Some(2.0), Some(5.0), Some(5.0))
+        );
+        assert_eq!(bbox.area(), Some(25.0));
+        assert!(Polygon::default().bounding_box().is_none());
+
+        let other = BoundingBox {
+            x: Some(3.5),
+            y: Some(2.0),
+            w: Some(5.0),
+            h: Some(5.0),
+        };
+        assert_close(bbox.iou(&other).unwrap(), 12.5 / 37.5);
+        assert_eq!(bbox.iou(&bbox), Some(1.0));
+        assert_eq!(bbox.iou(&BoundingBox::default()), None);
+    }
+
+    #[test]
+    fn test_polygon_area() {
+        let triangle = Polygon {
+            paths: vec![path(&[(0.0, 0.0), (4.0, 0.0), (0.0, 3.0)])],
+        };
+        assert_close(triangle.area(), 6.0);
+        let mut anticlockwise = square(1.0, 1.0, 3.0);
+        anticlockwise.reverse();
+        assert_close(
+            Polygon {
+                paths: vec![anticlockwise],
+            }
+            .area(),
+            9.0,
+        );
+
+        // The inner path is a hole, whichever way round it is
+        let mut hole = square(2.0, 2.0, 2.0);
+        hole.reverse();
+        let with_hole = Polygon {
+            paths: vec![square(0.0, 0.0, 6.0), hole],
+        };
+        assert_close(with_hole.area(), 32.0);
+        assert_eq!(Polygon::default().area(), 0.0);
+    }
+
+    #[test]
+    fn test_polygon_iou() {
+        let a = Polygon {
+            paths: vec![square(0.0, 0.0, 2.0)],
+        };
+        let b = Polygon {
+            paths: vec![square(1.0, 1.0, 2.0)],
+        };
+        assert_close(a.iou(&b), 1.0 / 7.0);
+        assert_close(a.iou(&a), 1.0);
+        assert_eq!(
+            a.iou(&Polygon {
+                paths: vec![square(5.0, 5.0, 1.0)]
+            }),
+            0.0
+        );
+
+        // A diamond inscribed in its bounding box covers half of it
+        let diamond = Polygon {
+            paths: vec![path(&[(1.0, 0.0), (2.0, 1.0), (1.0, 2.0), (0.0, 1.0)])],
+        };
+        let bbox = diamond.bounding_box().unwrap().to_polygon().unwrap();
+        assert_close(diamond.iou(&bbox), 0.5);
+    }
+}
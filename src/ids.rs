@@ -0,0 +1,142 @@
+//! Typed identifiers for the V7 resources, so that e.g. a class id cannot be passed where a
+//! dataset id is expected. All ids serialize exactly as the underlying value and convert
+//! to and from it with `From`, for use with the raw id fields of the API structs.
+
+#[cfg(feature = "fake")]
+#[allow(unused_imports)]
+use fake::{Dummy, Fake};
+use serde::{Deserialize, Serialize};
+use std::fmt::{self, Display};
+
+macro_rules! numeric_id {
+    ($(#[$meta:meta])* $name:ident) => {
+        $(#[$meta])*
+        #[derive(
+            Debug, Default, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize,
+        )]
+        #[cfg_attr(feature = "fake", derive(Dummy))]
+        #[serde(transparent)]
+        pub struct $name(pub u32);
+
+        impl From<u32> for $name {
+            fn from(value: u32) -> Self {
+                Self(value)
+            }
+        }
+
+        impl From<$name> for u32 {
+            fn from(value: $name) -> Self {
+                value.0
+            }
+        }
+
+        impl Display for $name {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                write!(f, "{}", self.0)
+            }
+        }
+    };
+}
+
+macro_rules! string_id {
+    ($(#[$meta:meta])* $name:ident) => {
+        $(#[$meta])*
+        #[derive(
+            Debug, Default, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize,
+        )]
+        #[cfg_attr(feature = "fake", derive(Dummy))]
+        #[serde(transparent)]
+        pub struct $name(pub String);
+
+        impl $name {
+            pub fn as_str(&self) -> &str {
+                &self.0
+            }
+        }
+
+        impl From<String> for $name {
+            fn from(value: String) -> Self {
+                Self(value)
+            }
+        }
+
+        impl From<&str> for $name {
+            fn from(value: &str) -> Self {
+                Self(value.to_string())
+            }
+        }
+
+        impl From<$name> for String {
+            fn from(value: $name) -> Self {
+                value.0
+            }
+        }
+
+        impl AsRef<str> for $name {
+            fn as_ref(&self) -> &str {
+                &self.0
+            }
+        }
+
+        impl Display for $name {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                write!(f, "{}", self.0)
+            }
+        }
+    };
+}
+
+numeric_id!(
+    /// Id of a dataset
+    DatasetId
+);
+numeric_id!(
+    /// Id of an annotation class
+    ClassId
+);
+numeric_id!(
+    /// Id of a user, e.g. a team member, assignee or annotator
+    UserId
+);
+numeric_id!(
+    /// Id of a v1 workflow template
+    WorkflowTemplateId
+);
+numeric_id!(
+    /// Id of a stage of a v1 workflow template
+    WorkflowStageTemplateId
+);
+string_id!(
+    /// UUID of a dataset item
+    ItemId
+);
+string_id!(
+    /// UUID of a V2 workflow stage
+    StageId
+);
+string_id!(
+    /// UUID of a V2 workflow
+    WorkflowId
+);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ids_are_transparent() {
+        assert_eq!(serde_json::to_string(&DatasetId(7)).unwrap(), "7");
+        assert_eq!(
+            serde_json::from_str::<ItemId>(r#""item-1""#).unwrap(),
+            ItemId::from("item-1")
+        );
+        let ids: Vec<ClassId> = serde_json::from_str("[1, 2]").unwrap();
+        assert_eq!(ids, vec![ClassId(1), ClassId(2)]);
+        assert_eq!(u32::from(ids[1]), 2);
+        assert_eq!(StageId::from("review").to_string(), "review");
+        assert_eq!(
+            serde_json::from_str::<Option<WorkflowTemplateId>>("12").unwrap(),
+            Some(WorkflowTemplateId(12))
+        );
+    }
+}
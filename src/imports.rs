@@ -1,17 +1,200 @@
 use crate::{
-    annotation::{AnnotationClass, Keypoint, Tag},
+    annotation::{
+        AnnotationClass, AnnotationProperty, Attributes, Cuboid, Ellipse, InstanceId, Keypoint,
+        Line, Polygon, Tag, Text,
+    },
     export::ImageAnnotation,
+    item::{DatasetItemV2, ItemSlot},
 };
-use anyhow::{Context, Result};
+use anyhow::{bail, Context, Result};
+#[cfg(feature = "unicode-normalization")]
+use icu_normalizer::ComposingNormalizer;
 use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+/// Interpolation algorithm of imported video annotations unless the exported annotation has its own
+pub const DEFAULT_INTERPOLATE_ALGORITHM: &str = "linear-1.1";
+
+/// How the class name of an exported annotation is compared to the names of the annotation classes
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ClassNameMatch {
+    /// Names must be identical
+    #[default]
+    Exact,
+    /// Names are compared ignoring case and leading or trailing whitespace
+    CaseInsensitive,
+    /// As `CaseInsensitive`, additionally applying Unicode NFKC normalization and
+    /// collapsing runs of whitespace, e.g. `"Ｔumour\u{a0} cells"` matches `"tumour cells"`.
+    /// Requires the `unicode-normalization` feature, matching fails without it.
+    Normalized,
+}
+
+/// Configuration of how the import constructors find the annotation class of an exported annotation
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ClassMatching {
+    #[serde(default)]
+    pub mode: ClassNameMatch,
+    /// Exported class names mapped to the name of the annotation class to use instead,
+    /// the exported names are compared using `mode`
+    #[serde(default)]
+    pub aliases: BTreeMap<String, String>,
+}
+
+impl ClassMatching {
+    pub fn new(mode: ClassNameMatch) -> Self {
+        ClassMatching {
+            mode,
+            ..Default::default()
+        }
+    }
+
+    /// Imports annotations of the exported class `from` as the annotation class named `to`
+    pub fn with_alias(mut self, from: &str, to: &str) -> Self {
+        self.aliases.insert(from.to_string(), to.to_string());
+        self
+    }
+
+    // Maps a class name to the key compared according to `mode`, failing for a mode whose
+    // feature is not enabled
+    fn key(&self) -> Result<fn(&str) -> String> {
+        let key: fn(&str) -> String = match self.mode {
+            ClassNameMatch::Exact => |name| name.to_string(),
+            ClassNameMatch::CaseInsensitive => |name| name.trim().to_lowercase(),
+            #[cfg(feature = "unicode-normalization")]
+            ClassNameMatch::Normalized => |name| {
+                ComposingNormalizer::new_nfkc()
+                    .normalize(name)
+                    .split_whitespace()
+                    .collect::<Vec<&str>>()
+                    .join(" ")
+                    .to_lowercase()
+            },
+            #[cfg(not(feature = "unicode-normalization"))]
+            ClassNameMatch::Normalized => {
+                bail!("Normalized class name matching requires the unicode-normalization feature")
+            }
+        };
+        Ok(key)
+    }
+
+    /// Finds the ID of the annotation class matching `class_name`.
+    ///
+    /// An identically named class is always preferred, otherwise the names are compared
+    /// according to `mode` after resolving any alias.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error in the following cases:
+    /// - No annotation class matching the name is found in `eligible_annotation_classes`.
+    /// - More than one annotation class matches the name, e.g. `Tumour` and `tumour` when
+    ///   matching case-insensitively.
+    /// - The annotation class found does not have an ID.
+    pub fn find_class_id(
+        &self,
+        eligible_annotation_classes: &[&AnnotationClass],
+        class_name: &str,
+    ) -> Result<u32> {
+        let (class_name, matches) = self.matches(eligible_annotation_classes, class_name)?;
+        match matches.as_slice() {
+            [] => bail!("Unable to find matching annotation class ID from export JSON"),
+            [class] => class
+                .id
+                .map(u32::from)
+                .context("Annotation Class has no ID"),
+            _ => bail!("Class name {class_name} matches more than one annotation class"),
+        }
+    }
+
+    /// Whether any of `eligible_annotation_classes` matches `class_name`
+    pub fn has_match(
+        &self,
+        eligible_annotation_classes: &[&AnnotationClass],
+        class_name: &str,
+    ) -> Result<bool> {
+        Ok(!self
+            .matches(eligible_annotation_classes, class_name)?
+            .1
+            .is_empty())
+    }
+
+    // The class name after resolving aliases and the classes matching it
+    fn matches<'a, 'b>(
+        &'a self,
+        eligible_annotation_classes: &[&'b AnnotationClass],
+        class_name: &'a str,
+    ) -> Result<(&'a str, Vec<&'b AnnotationClass>)> {
+        let key = self.key()?;
+        // An alias of the exact name wins over the aliases only matching it according to `mode`
+        let alias = self.aliases.get(class_name).or_else(|| {
+            let class_key = key(class_name);
+            self.aliases
+                .iter()
+                .find(|(from, _)| key(from) == class_key)
+                .map(|(_, to)| to)
+        });
+        let class_name = alias.map_or(class_name, String::as_str);
+
+        if let Some(class) = eligible_annotation_classes
+            .iter()
+            .find(|ac| ac.name.as_deref() == Some(class_name))
+        {
+            return Ok((class_name, vec![class]));
+        }
+
+        let class_key = key(class_name);
+        let matches = eligible_annotation_classes
+            .iter()
+            .copied()
+            .filter(|ac| ac.name.as_ref().is_some_and(|name| key(name) == class_key))
+            .collect();
+        Ok((class_name, matches))
+    }
+}
 
 /// Struct representing the payload data wrapper of a V7 annotation suitable for importing back into a V7 dataset item
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
 pub struct AnnotationImportData {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub polygon: Option<AnnotationImportPolygon>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub tag: Option<Tag>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ellipse: Option<Ellipse>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cuboid: Option<Cuboid>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub line: Option<Line>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub attributes: Option<Attributes>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub instance_id: Option<InstanceId>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub text: Option<Text>,
+    /// Payloads of the frames of a video annotation keyed by frame index, used in place of
+    /// the annotation types above
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub frames: BTreeMap<u32, AnnotationImportFrame>,
+    /// Frame ranges of a video annotation, each the first frame and the frame after the last
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub ranges: Vec<[u32; 2]>,
+    /// Whether V7 interpolates the frames of a video annotation between its keyframes
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub interpolated: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub interpolate_algorithm: Option<String>,
+    /// Payloads of any other annotation types or sub types, imported as-is
+    #[serde(flatten)]
+    pub other: HashMap<String, serde_json::Value>,
+}
+
+/// Struct representing the payload data of one frame of a V7 video annotation suitable for importing back into a V7 dataset item
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct AnnotationImportFrame {
+    #[serde(default)]
+    pub keyframe: bool,
+    #[serde(flatten)]
+    pub data: AnnotationImportData,
 }
 
 /// Struct representing the polygon payload data of a V7 annotation suitable for importing back into a V7 dataset item
@@ -21,7 +204,23 @@ pub struct AnnotationImportPolygon {
     /// We assume though that these Keypoints are ordered in some way that represents a closed polygon.
     /// Typically, we import annotations as-is from V7 exports and retain the ordering as they were exported.
     /// This may change when we start merging polygons to import those merged polygons instead.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub path: Vec<Keypoint>,
+    /// Paths of a polygon with holes or disjoint regions, the first path is the outer boundary
+    /// and V7 treats paths inside another path as holes. Used in place of `path`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub paths: Vec<Vec<Keypoint>>,
+}
+
+impl AnnotationImportPolygon {
+    /// All paths of the polygon whether it was built from a single path or several
+    pub fn all_paths(&self) -> Vec<&[Keypoint]> {
+        if self.paths.is_empty() {
+            vec![self.path.as_slice()]
+        } else {
+            self.paths.iter().map(Vec::as_slice).collect()
+        }
+    }
 }
 
 /// Struct representing the context payload data of a V7 annotation suitable for importing back into a V7 dataset item
@@ -33,6 +232,108 @@ pub struct AnnotationContext {
     pub slot_names: Vec<String>,
 }
 
+/// Where in a dataset item the import constructors attach an annotation. A slot name
+/// converts into a target of that single slot.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct AnnotationTarget {
+    /// Slots of the item the annotation is attached to, several for an annotation spanning
+    /// e.g. the views of a multi-slot item
+    pub slot_names: Vec<String>,
+    /// Section of the slots the annotation is limited to, e.g. the page of a PDF or the
+    /// frame of a DICOM series. The whole slot when `None`.
+    pub section_index: Option<u32>,
+}
+
+impl AnnotationTarget {
+    pub fn slot(slot_name: &str) -> Self {
+        Self {
+            slot_names: vec![slot_name.to_string()],
+            section_index: None,
+        }
+    }
+
+    pub fn slots<S: AsRef<str>>(slot_names: &[S]) -> Self {
+        Self {
+            slot_names: slot_names
+                .iter()
+                .map(|name| name.as_ref().to_string())
+                .collect(),
+            section_index: None,
+        }
+    }
+
+    /// Limits the target to the section at `section_index` of its slots
+    pub fn section(mut self, section_index: u32) -> Self {
+        self.section_index = Some(section_index);
+        self
+    }
+
+    fn context(&self) -> Result<AnnotationContext> {
+        if self.slot_names.is_empty() {
+            bail!("Annotation target has no slots");
+        }
+        let mut seen = HashSet::new();
+        if let Some(name) = self.slot_names.iter().find(|name| !seen.insert(*name)) {
+            bail!("Annotation target lists slot {name} more than once");
+        }
+        Ok(AnnotationContext {
+            slot_names: self.slot_names.clone(),
+        })
+    }
+
+    /// `data` placed on the targeted section, as V7 expects the annotations of a section
+    /// to be the single keyframe of a frame-indexed annotation
+    fn place(&self, data: AnnotationImportData) -> Result<AnnotationImportData> {
+        let Some(index) = self.section_index else {
+            return Ok(data);
+        };
+        if !data.frames.is_empty() {
+            bail!("Annotation is already frame-indexed and cannot target section {index}");
+        }
+        Ok(AnnotationImportData {
+            frames: BTreeMap::from([(
+                index,
+                AnnotationImportFrame {
+                    keyframe: true,
+                    data,
+                },
+            )]),
+            ranges: vec![[index, index + 1]],
+            interpolated: Some(false),
+            ..Default::default()
+        })
+    }
+}
+
+impl From<&str> for AnnotationTarget {
+    fn from(slot_name: &str) -> Self {
+        Self::slot(slot_name)
+    }
+}
+
+impl From<&String> for AnnotationTarget {
+    fn from(slot_name: &String) -> Self {
+        Self::slot(slot_name)
+    }
+}
+
+impl From<String> for AnnotationTarget {
+    fn from(slot_name: String) -> Self {
+        Self::slot(&slot_name)
+    }
+}
+
+/// What an idempotent import does with an annotation whose id is already on the item
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OnExisting {
+    /// Replace the existing annotation with the imported one
+    #[default]
+    Update,
+    /// Keep the existing annotation and leave the imported one out
+    Skip,
+}
+
 /// Struct representing an annotation payload data of a V7 annotation suitable for importing back into a V7 dataset item
 /// One instance of this struct corresponds to one annotation being imported into the dataset item
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -41,6 +342,11 @@ pub struct AnnotationImportAnnotation {
     pub data: AnnotationImportData,
     pub annotation_class_id: u32,
     pub context_keys: AnnotationContext,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub properties: Vec<AnnotationProperty>,
+    /// Only used by idempotent imports, it is not sent to V7
+    #[serde(skip)]
+    pub on_existing: OnExisting,
 }
 
 /// Struct representing a complete annotation import payload of V7 annotations into a single V7 dataset item
@@ -51,37 +357,265 @@ pub struct AnnotationImport {
     pub overwrite: bool,
 }
 
+/// The annotations of an `AnnotationImport` split by whether they are already on the item,
+/// see `AnnotationImport::resolve_existing`
+#[derive(Debug, Clone)]
+pub struct AnnotationImportPlan {
+    /// The annotations to import, never overwriting the other annotations of the item
+    pub import: AnnotationImport,
+    /// Ids of existing annotations to delete before importing their replacements
+    pub replaced: Vec<String>,
+    /// Ids of annotations left out as they are already on the item
+    pub skipped: Vec<String>,
+}
+
+impl AnnotationImport {
+    /// Checks every annotation with `AnnotationImportAnnotation::validate_target`
+    pub fn validate_targets(&self, item: &DatasetItemV2) -> Result<()> {
+        for annotation in &self.annotations {
+            annotation
+                .validate_target(item)
+                .with_context(|| format!("Invalid target of annotation {}", annotation.id))?;
+        }
+        Ok(())
+    }
+
+    /// Splits the annotations by whether their id is in `existing_ids`, the ids of the
+    /// annotations already on the item, following the `on_existing` of each annotation.
+    ///
+    /// An import with `overwrite` set replaces every annotation of the item anyway and
+    /// so is planned as-is.
+    pub fn resolve_existing(&self, existing_ids: &HashSet<String>) -> AnnotationImportPlan {
+        if self.overwrite {
+            return AnnotationImportPlan {
+                import: self.clone(),
+                replaced: vec![],
+                skipped: vec![],
+            };
+        }
+
+        let mut plan = AnnotationImportPlan {
+            import: AnnotationImport {
+                annotations: vec![],
+                overwrite: false,
+            },
+            replaced: vec![],
+            skipped: vec![],
+        };
+        for annotation in &self.annotations {
+            if existing_ids.contains(&annotation.id) {
+                match annotation.on_existing {
+                    OnExisting::Update => plan.replaced.push(annotation.id.clone()),
+                    OnExisting::Skip => {
+                        plan.skipped.push(annotation.id.clone());
+                        continue;
+                    }
+                }
+            }
+            plan.import.annotations.push(annotation.clone());
+        }
+        plan
+    }
+}
+
 impl From<Vec<Keypoint>> for AnnotationImportPolygon {
     fn from(value: Vec<Keypoint>) -> Self {
-        AnnotationImportPolygon { path: value }
+        AnnotationImportPolygon {
+            path: value,
+            paths: vec![],
+        }
     }
 }
 
-impl From<Vec<Keypoint>> for AnnotationImportData {
-    fn from(value: Vec<Keypoint>) -> Self {
+impl From<Vec<Vec<Keypoint>>> for AnnotationImportPolygon {
+    fn from(value: Vec<Vec<Keypoint>>) -> Self {
+        AnnotationImportPolygon {
+            path: vec![],
+            paths: value,
+        }
+    }
+}
+
+impl From<Polygon> for AnnotationImportPolygon {
+    fn from(value: Polygon) -> Self {
+        AnnotationImportPolygon::from(value.paths)
+    }
+}
+
+impl<P: Into<AnnotationImportPolygon>> From<P> for AnnotationImportData {
+    fn from(value: P) -> Self {
         AnnotationImportData {
-            polygon: Some(AnnotationImportPolygon::from(value)),
-            tag: None,
+            polygon: Some(value.into()),
+            ..Default::default()
         }
     }
 }
 
+impl AnnotationImportData {
+    /// Copies the attributes, instance id and text sub-annotations of `annotation`
+    fn with_sub_annotations(mut self, annotation: &ImageAnnotation) -> Self {
+        self.attributes = annotation.attributes.clone();
+        self.instance_id = annotation.instance_id.clone();
+        self.text = annotation.text.clone();
+        self
+    }
+
+    /// Copies the sub-annotations of the frame of `annotation` at `frame_index`, or those of
+    /// `annotation` itself if it has no such frame
+    fn with_frame_sub_annotations(
+        mut self,
+        annotation: &ImageAnnotation,
+        frame_index: u32,
+    ) -> Self {
+        let Some(frame) = annotation.frames.get(&frame_index) else {
+            return self.with_sub_annotations(annotation);
+        };
+        self.attributes = frame.attributes.clone();
+        self.instance_id = frame.instance_id.clone();
+        self.text = frame.text.clone();
+        self
+    }
+
+    /// Video payload with the `keyframes`, keeping the ranges and interpolation of `annotation`
+    /// if it is a video annotation. Otherwise it spans the first to the last keyframe and is
+    /// interpolated between them.
+    fn from_keyframes(
+        keyframes: BTreeMap<u32, AnnotationImportData>,
+        annotation: &ImageAnnotation,
+    ) -> Result<Self> {
+        let (Some(first), Some(last)) = (keyframes.keys().next(), keyframes.keys().next_back())
+        else {
+            bail!("Video annotation has no keyframes to import");
+        };
+        let ranges = if annotation.ranges.is_empty() {
+            vec![[*first, last + 1]]
+        } else {
+            annotation.ranges.clone()
+        };
+        let interpolated = annotation.interpolated.unwrap_or(true);
+        let interpolate_algorithm = interpolated.then(|| {
+            annotation
+                .interpolate_algorithm
+                .clone()
+                .unwrap_or_else(|| DEFAULT_INTERPOLATE_ALGORITHM.to_string())
+        });
+
+        Ok(AnnotationImportData {
+            frames: keyframes
+                .into_iter()
+                .map(|(index, data)| {
+                    let frame = AnnotationImportFrame {
+                        keyframe: true,
+                        data: data.with_frame_sub_annotations(annotation, index),
+                    };
+                    (index, frame)
+                })
+                .collect(),
+            ranges,
+            interpolated: Some(interpolated),
+            interpolate_algorithm,
+            ..Default::default()
+        })
+    }
+}
+
+// Namespace of the name based ids generated by `AnnotationImportAnnotation::stable_id`
+const STABLE_ID_NAMESPACE: uuid::Uuid =
+    uuid::Uuid::from_u128(0x5c1b_0a4e_7f1d_4c55_9d3e_26a8_f0b4_e917);
+
 impl AnnotationImportAnnotation {
+    /// An id derived from `namespace`, e.g. the id of the item being imported into, and the
+    /// class, slots and data of the annotation, so importing the same annotation again
+    /// produces the same id.
+    pub fn stable_id(&self, namespace: &str) -> Result<String> {
+        // Serialized through a `Value` as its maps are sorted, unlike the `HashMap` of `other`
+        let data = serde_json::to_value(&self.data)?.to_string();
+        let name = format!(
+            "{namespace}/{}/{}/{data}",
+            self.annotation_class_id,
+            self.context_keys.slot_names.join(",")
+        );
+        Ok(uuid::Uuid::new_v3(&STABLE_ID_NAMESPACE, name.as_bytes()).to_string())
+    }
+
+    /// Checks the slots of the annotation against the slots and layout of `item`, and that
+    /// its frames are within the sections of those slots when V7 listed their number.
+    pub fn validate_target(&self, item: &DatasetItemV2) -> Result<()> {
+        let item_name = item.name.as_deref().unwrap_or_default();
+        let slots: HashMap<&str, &ItemSlot> = item
+            .slots
+            .iter()
+            .flatten()
+            .filter_map(|slot| Some((slot.slot_name.as_deref()?, slot)))
+            .collect();
+        let layout: HashSet<&str> = item
+            .layout
+            .iter()
+            .flat_map(|layout| layout.slots.iter().flatten())
+            .map(String::as_str)
+            .collect();
+        if slots.is_empty() && layout.is_empty() {
+            bail!("Item {item_name} lists no slots to validate the annotation against");
+        }
+
+        let last_frame = self
+            .data
+            .frames
+            .keys()
+            .copied()
+            .chain(
+                self.data
+                    .ranges
+                    .iter()
+                    .map(|range| range[1].saturating_sub(1)),
+            )
+            .max();
+        for slot_name in &self.context_keys.slot_names {
+            let slot = slots.get(slot_name.as_str());
+            if slot.is_none() && !layout.contains(slot_name.as_str()) {
+                bail!("Item {item_name} has no slot {slot_name}");
+            }
+            let total_sections = slot.and_then(|slot| slot.total_sections);
+            if let (Some(last_frame), Some(total_sections)) = (last_frame, total_sections) {
+                if last_frame >= total_sections {
+                    bail!(
+                        "Frame {last_frame} is beyond the {total_sections} sections of slot {slot_name} of item {item_name}"
+                    );
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Replaces the generated id with `stable_id`
+    pub fn with_stable_id(mut self, namespace: &str) -> Result<Self> {
+        self.id = self.stable_id(namespace)?;
+        Ok(self)
+    }
+
+    /// Sets what an idempotent import does when the annotation is already on the item
+    pub fn on_existing(mut self, on_existing: OnExisting) -> Self {
+        self.on_existing = on_existing;
+        self
+    }
+
     /// Creates a new polygon annotation.
     ///
     /// This function generates an `AnnotationImportAnnotation` instance representing a polygon annotation.
     /// It assigns a unique ID, sets the annotation data with the provided `path` representing the polygon,
     /// and links it to an appropriate annotation class based on the `original_annotation` and the provided
-    /// `eligible_annotation_classes`. The `slot_name` is used to specify where in the dataset item this annotation
+    /// `eligible_annotation_classes`. The `target` is used to specify where in the dataset item this annotation
     /// should be attached.
     ///
     /// # Arguments
     ///
     /// * `original_annotation` - A reference to an `ImageAnnotation` from which the name of the annotation class is derived.
-    /// * `path` - A vector of `Keypoint` objects defining the vertices of the polygon.
+    /// * `path` - The vertices of the polygon, either a single path of `Keypoint`s or the paths of
+    ///   a polygon with holes or disjoint regions e.g. the exported `Polygon`.
     /// * `eligible_annotation_classes` - A slice of references to `AnnotationClass` objects.
-    ///    The function searches these to find a matching class ID for the `original_annotation`.
-    /// * `slot_name` - The name of the slot in the dataset item where this annotation will be attached.
+    ///   The function searches these to find a matching class ID for the `original_annotation`.
+    /// * `target` - The name of the slot in the dataset item where this annotation will be attached,
+    ///   or an `AnnotationTarget` spanning several slots or limited to one of their sections.
     ///
     /// # Returns
     ///
@@ -94,20 +628,39 @@ impl AnnotationImportAnnotation {
     /// Returns an error if no matching annotation class ID is found in `eligible_annotation_classes` for the `original_annotation`.
     pub fn new_polygon_annotation(
         original_annotation: &ImageAnnotation,
-        path: Vec<Keypoint>,
+        path: impl Into<AnnotationImportPolygon>,
         eligible_annotation_classes: &[&AnnotationClass],
-        slot_name: &str,
+        target: impl Into<AnnotationTarget>,
     ) -> Result<Self> {
+        Self::new_polygon_annotation_with_matching(
+            original_annotation,
+            path,
+            eligible_annotation_classes,
+            &ClassMatching::default(),
+            target,
+        )
+    }
+
+    /// As `new_polygon_annotation`, matching the class name of the `original_annotation` to
+    /// the `eligible_annotation_classes` according to `matching`.
+    pub fn new_polygon_annotation_with_matching(
+        original_annotation: &ImageAnnotation,
+        path: impl Into<AnnotationImportPolygon>,
+        eligible_annotation_classes: &[&AnnotationClass],
+        matching: &ClassMatching,
+        target: impl Into<AnnotationTarget>,
+    ) -> Result<Self> {
+        let target = target.into();
         Ok(AnnotationImportAnnotation {
             id: uuid::Uuid::new_v4().to_string(),
-            data: AnnotationImportData::from(path),
-            annotation_class_id: Self::find_annotation_class_id(
-                eligible_annotation_classes,
-                &original_annotation.name,
+            data: target.place(
+                AnnotationImportData::from(path).with_sub_annotations(original_annotation),
             )?,
-            context_keys: AnnotationContext {
-                slot_names: vec![slot_name.to_string()],
-            },
+            annotation_class_id: matching
+                .find_class_id(eligible_annotation_classes, &original_annotation.name)?,
+            context_keys: target.context()?,
+            properties: original_annotation.properties.clone(),
+            on_existing: OnExisting::default(),
         })
     }
 
@@ -116,7 +669,7 @@ impl AnnotationImportAnnotation {
     /// This function generates an `AnnotationImportAnnotation` instance for a tag annotation.
     /// It assigns a unique ID, sets the annotation data based on the `original_annotation`,
     /// and identifies the correct annotation class from `eligible_annotation_classes`.
-    /// The `slot_name` is used to specify the dataset item slot for the annotation.
+    /// The `target` is used to specify the dataset item slots for the annotation.
     ///
     /// # Arguments
     ///
@@ -124,7 +677,8 @@ impl AnnotationImportAnnotation {
     ///   and annotation class name.
     /// * `eligible_annotation_classes` - A slice of references to `AnnotationClass` objects.
     ///   The function searches these to find a matching class ID for the `original_annotation`.
-    /// * `slot_name` - The name of the slot in the dataset item where this annotation will be attached.
+    /// * `target` - The name of the slot in the dataset item where this annotation will be attached,
+    ///   or an `AnnotationTarget` spanning several slots or limited to one of their sections.
     ///
     /// # Returns
     ///
@@ -139,62 +693,206 @@ impl AnnotationImportAnnotation {
     pub fn new_tag_annotation(
         original_annotation: &ImageAnnotation,
         eligible_annotation_classes: &[&AnnotationClass],
-        slot_name: &str,
+        target: impl Into<AnnotationTarget>,
+    ) -> Result<Self> {
+        Self::new_tag_annotation_with_matching(
+            original_annotation,
+            eligible_annotation_classes,
+            &ClassMatching::default(),
+            target,
+        )
+    }
+
+    /// As `new_tag_annotation`, matching the class name of the `original_annotation` to
+    /// the `eligible_annotation_classes` according to `matching`.
+    pub fn new_tag_annotation_with_matching(
+        original_annotation: &ImageAnnotation,
+        eligible_annotation_classes: &[&AnnotationClass],
+        matching: &ClassMatching,
+        target: impl Into<AnnotationTarget>,
     ) -> Result<Self> {
+        let target = target.into();
         Ok(AnnotationImportAnnotation {
             id: uuid::Uuid::new_v4().to_string(),
-            data: AnnotationImportData {
-                polygon: None,
-                tag: original_annotation.tag.clone(),
-            },
-            annotation_class_id: Self::find_annotation_class_id(
-                eligible_annotation_classes,
-                &original_annotation.name,
+            data: target.place(
+                AnnotationImportData {
+                    tag: original_annotation.tag.clone(),
+                    ..Default::default()
+                }
+                .with_sub_annotations(original_annotation),
             )?,
-            context_keys: AnnotationContext {
-                slot_names: vec![slot_name.to_string()],
-            },
+            annotation_class_id: matching
+                .find_class_id(eligible_annotation_classes, &original_annotation.name)?,
+            context_keys: target.context()?,
+            properties: original_annotation.properties.clone(),
+            on_existing: OnExisting::default(),
         })
     }
 
-    /// Finds the ID of an annotation class.
+    /// Creates a new ellipse, cuboid or line annotation.
     ///
-    /// This function searches through a slice of `AnnotationClass` references to find a class
-    /// whose name matches the provided `class_name`. It is primarily used to retrieve the unique
-    /// ID associated with an annotation class based on its name.
+    /// This function generates an `AnnotationImportAnnotation` instance carrying the ellipse,
+    /// cuboid or line geometry of the `original_annotation` as-is. It assigns a unique ID and
+    /// identifies the correct annotation class from `eligible_annotation_classes`.
+    /// The `target` is used to specify the dataset item slots for the annotation.
     ///
     /// # Arguments
     ///
-    /// * `eligible_annotation_classes` - A slice of references to `AnnotationClass` objects in which
-    ///   the function searches for a matching class name.
-    /// * `class_name` - The name of the annotation class for which the ID is being searched.
+    /// * `original_annotation` - A reference to an `ImageAnnotation` used to derive the geometry
+    ///   and annotation class name.
+    /// * `eligible_annotation_classes` - A slice of references to `AnnotationClass` objects.
+    ///   The function searches these to find a matching class ID for the `original_annotation`.
+    /// * `target` - The name of the slot in the dataset item where this annotation will be attached,
+    ///   or an `AnnotationTarget` spanning several slots or limited to one of their sections.
     ///
-    /// # Returns
+    /// # Errors
+    ///
+    /// Returns an error if the `original_annotation` has no ellipse, cuboid or line geometry, or
+    /// if no matching annotation class ID is found in `eligible_annotation_classes`.
+    pub fn new_shape_annotation(
+        original_annotation: &ImageAnnotation,
+        eligible_annotation_classes: &[&AnnotationClass],
+        target: impl Into<AnnotationTarget>,
+    ) -> Result<Self> {
+        Self::new_shape_annotation_with_matching(
+            original_annotation,
+            eligible_annotation_classes,
+            &ClassMatching::default(),
+            target,
+        )
+    }
+
+    /// As `new_shape_annotation`, matching the class name of the `original_annotation` to
+    /// the `eligible_annotation_classes` according to `matching`.
+    pub fn new_shape_annotation_with_matching(
+        original_annotation: &ImageAnnotation,
+        eligible_annotation_classes: &[&AnnotationClass],
+        matching: &ClassMatching,
+        target: impl Into<AnnotationTarget>,
+    ) -> Result<Self> {
+        let target = target.into();
+        let data = AnnotationImportData {
+            ellipse: original_annotation.ellipse.clone(),
+            cuboid: original_annotation.cuboid.clone(),
+            line: original_annotation.line.clone(),
+            ..Default::default()
+        }
+        .with_sub_annotations(original_annotation);
+        if data.ellipse.is_none() && data.cuboid.is_none() && data.line.is_none() {
+            bail!("Annotation has no ellipse, cuboid or line to import");
+        }
+
+        Ok(AnnotationImportAnnotation {
+            id: uuid::Uuid::new_v4().to_string(),
+            data: target.place(data)?,
+            annotation_class_id: matching
+                .find_class_id(eligible_annotation_classes, &original_annotation.name)?,
+            context_keys: target.context()?,
+            properties: original_annotation.properties.clone(),
+            on_existing: OnExisting::default(),
+        })
+    }
+
+    /// Creates a new video polygon annotation.
+    ///
+    /// The frame-indexed counterpart of `new_polygon_annotation`, each of the `keyframes` is
+    /// the polygon of the annotation at that frame. The frame ranges, interpolation and
+    /// per frame sub-annotations are taken from the `original_annotation` if it is a video
+    /// annotation, otherwise the annotation spans the first to the last keyframe and V7
+    /// interpolates the frames between them.
     ///
-    /// Returns `Result<u32, Error>`. On success, it contains the ID of the matching annotation class.
-    /// If no class with the given name is found, or if the found class lacks an ID, it returns an error.
+    /// # Arguments
+    ///
+    /// * `original_annotation` - A reference to an `ImageAnnotation` from which the name of the annotation class is derived.
+    /// * `keyframes` - Pairs of frame index and the vertices of the polygon at that frame, in any order.
+    /// * `eligible_annotation_classes` - A slice of references to `AnnotationClass` objects.
+    ///   The function searches these to find a matching class ID for the `original_annotation`.
+    /// * `matching` - How the class name of the `original_annotation` is matched to the eligible classes.
+    /// * `target` - The name of the slot in the dataset item where this annotation will be attached,
+    ///   or an `AnnotationTarget` spanning several slots or limited to one of their sections.
     ///
     /// # Errors
     ///
-    /// Returns an error in the following cases:
-    /// - No annotation class with the given name is found in `eligible_annotation_classes`.
-    /// - The annotation class found does not have an ID.
-    fn find_annotation_class_id(
+    /// Returns an error if there are no `keyframes`, or if no matching annotation class ID is
+    /// found in `eligible_annotation_classes` for the `original_annotation`.
+    pub fn new_video_polygon_annotation<P: Into<AnnotationImportPolygon>>(
+        original_annotation: &ImageAnnotation,
+        keyframes: impl IntoIterator<Item = (u32, P)>,
         eligible_annotation_classes: &[&AnnotationClass],
-        class_name: &str,
-    ) -> Result<u32> {
-        eligible_annotation_classes
-            .iter()
-            .find(|ac| {
-                if ac.name.is_some() {
-                    ac.name.clone().unwrap() == class_name
-                } else {
-                    false
-                }
+        matching: &ClassMatching,
+        target: impl Into<AnnotationTarget>,
+    ) -> Result<Self> {
+        let target = target.into();
+        let keyframes = keyframes
+            .into_iter()
+            .map(|(index, path)| (index, AnnotationImportData::from(path)))
+            .collect();
+
+        Ok(AnnotationImportAnnotation {
+            id: uuid::Uuid::new_v4().to_string(),
+            data: target.place(AnnotationImportData::from_keyframes(
+                keyframes,
+                original_annotation,
+            )?)?,
+            annotation_class_id: matching
+                .find_class_id(eligible_annotation_classes, &original_annotation.name)?,
+            context_keys: target.context()?,
+            properties: original_annotation.properties.clone(),
+            on_existing: OnExisting::default(),
+        })
+    }
+
+    /// Creates a new video tag annotation.
+    ///
+    /// The frame-indexed counterpart of `new_tag_annotation`, tagging the frames from the
+    /// first to the last of the `keyframes` unless the `original_annotation` is a video
+    /// annotation with its own frame ranges.
+    ///
+    /// # Arguments
+    ///
+    /// * `original_annotation` - A reference to an `ImageAnnotation` from which the name of the annotation class is derived.
+    /// * `keyframes` - Indices of the keyframes of the tag.
+    /// * `eligible_annotation_classes` - A slice of references to `AnnotationClass` objects.
+    ///   The function searches these to find a matching class ID for the `original_annotation`.
+    /// * `matching` - How the class name of the `original_annotation` is matched to the eligible classes.
+    /// * `target` - The name of the slot in the dataset item where this annotation will be attached,
+    ///   or an `AnnotationTarget` spanning several slots or limited to one of their sections.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if there are no `keyframes`, or if no matching annotation class ID is
+    /// found in `eligible_annotation_classes` for the `original_annotation`.
+    pub fn new_video_tag_annotation(
+        original_annotation: &ImageAnnotation,
+        keyframes: impl IntoIterator<Item = u32>,
+        eligible_annotation_classes: &[&AnnotationClass],
+        matching: &ClassMatching,
+        target: impl Into<AnnotationTarget>,
+    ) -> Result<Self> {
+        let target = target.into();
+        let keyframes = keyframes
+            .into_iter()
+            .map(|index| {
+                let data = AnnotationImportData {
+                    tag: Some(Tag {}),
+                    ..Default::default()
+                };
+                (index, data)
             })
-            .context("Unable to find matching annotation class ID from export JSON")?
-            .id
-            .context("Annotation Class has no ID")
+            .collect();
+
+        Ok(AnnotationImportAnnotation {
+            id: uuid::Uuid::new_v4().to_string(),
+            data: target.place(AnnotationImportData::from_keyframes(
+                keyframes,
+                original_annotation,
+            )?)?,
+            annotation_class_id: matching
+                .find_class_id(eligible_annotation_classes, &original_annotation.name)?,
+            context_keys: target.context()?,
+            properties: original_annotation.properties.clone(),
+            on_existing: OnExisting::default(),
+        })
     }
 }
 
@@ -202,6 +900,7 @@ impl AnnotationImportAnnotation {
 mod tests {
     use super::*;
     use crate::annotation::{AnnotationClass, Keypoint};
+    use crate::ids::ClassId;
     use crate::imports::ImageAnnotation;
     use anyhow::Result;
 
@@ -218,7 +917,7 @@ mod tests {
     fn create_sample_annotation_class(name: &str, id: u32) -> AnnotationClass {
         AnnotationClass {
             name: Some(name.to_string()),
-            id: Some(id),
+            id: Some(ClassId(id)),
             ..AnnotationClass::default()
         }
     }
@@ -247,6 +946,132 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_new_polygon_annotation_multiple_paths() -> Result<()> {
+        let outer = vec![
+            Keypoint { x: 0.0, y: 0.0 },
+            Keypoint { x: 10.0, y: 0.0 },
+            Keypoint { x: 10.0, y: 10.0 },
+        ];
+        let hole = vec![
+            Keypoint { x: 2.0, y: 2.0 },
+            Keypoint { x: 4.0, y: 2.0 },
+            Keypoint { x: 4.0, y: 4.0 },
+        ];
+        let polygon = Polygon {
+            paths: vec![outer.clone(), hole],
+        };
+        let eligible_annotation_classes = &[&create_sample_annotation_class("Sample Class", 1)];
+
+        let result = AnnotationImportAnnotation::new_polygon_annotation(
+            &create_sample_image_annotation(None),
+            polygon,
+            eligible_annotation_classes,
+            "sample_slot",
+        )?;
+        let value = serde_json::to_value(&result.data)?;
+        assert_eq!(value["polygon"]["paths"].as_array().unwrap().len(), 2);
+        assert!(value["polygon"].get("path").is_none());
+
+        let single = AnnotationImportPolygon::from(outer.clone());
+        assert_eq!(single.all_paths(), vec![outer.as_slice()]);
+        let parsed: AnnotationImportPolygon = serde_json::from_value(value["polygon"].clone())?;
+        assert_eq!(parsed.all_paths().len(), 2);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_new_polygon_annotation_sub_annotations() -> Result<()> {
+        let original_annotation = ImageAnnotation {
+            name: "Sample Class".to_string(),
+            attributes: Some(Attributes {
+                attributes: vec!["mitotic".to_string()],
+            }),
+            instance_id: Some(InstanceId { value: 4 }),
+            properties: vec![AnnotationProperty {
+                name: "Grade".to_string(),
+                value: Some("G2".to_string()),
+                frame_index: None,
+            }],
+            ..ImageAnnotation::default()
+        };
+        let eligible_annotation_classes = &[&create_sample_annotation_class("Sample Class", 1)];
+
+        let result = AnnotationImportAnnotation::new_polygon_annotation(
+            &original_annotation,
+            vec![Keypoint { x: 10.0, y: 10.0 }],
+            eligible_annotation_classes,
+            "sample_slot",
+        )?;
+        let value = serde_json::to_value(&result)?;
+        assert_eq!(value["data"]["attributes"]["attributes"][0], "mitotic");
+        assert_eq!(value["data"]["instance_id"]["value"], 4);
+        assert_eq!(value["properties"][0]["value"], "G2");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_annotation_targets() -> Result<()> {
+        let original_annotation = create_sample_image_annotation(Some(Tag {}));
+        let eligible_annotation_classes = &[&create_sample_annotation_class("Sample Class", 1)];
+        let item: DatasetItemV2 = serde_json::from_value(serde_json::json!({
+            "name": "report.pdf",
+            "slots": [
+                {"slot_name": "0", "type": "pdf", "total_sections": 3},
+                {"slot_name": "1", "type": "image"}
+            ]
+        }))?;
+
+        let result = AnnotationImportAnnotation::new_tag_annotation(
+            &original_annotation,
+            eligible_annotation_classes,
+            AnnotationTarget::slots(&["0", "1"]).section(2),
+        )?;
+        assert_eq!(result.context_keys.slot_names, vec!["0", "1"]);
+        let value = serde_json::to_value(&result.data)?;
+        assert_eq!(value["frames"]["2"]["keyframe"], true);
+        assert!(value["frames"]["2"].get("tag").is_some());
+        assert_eq!(value["ranges"], serde_json::json!([[2, 3]]));
+        assert!(value.get("tag").is_none());
+        result.validate_target(&item)?;
+
+        let beyond = AnnotationImportAnnotation::new_tag_annotation(
+            &original_annotation,
+            eligible_annotation_classes,
+            AnnotationTarget::slot("0").section(3),
+        )?;
+        assert!(beyond.validate_target(&item).is_err());
+        let missing = AnnotationImportAnnotation::new_tag_annotation(
+            &original_annotation,
+            eligible_annotation_classes,
+            "2",
+        )?;
+        let import = AnnotationImport {
+            annotations: vec![missing],
+            overwrite: false,
+        };
+        assert!(import.validate_targets(&item).is_err());
+
+        assert!(AnnotationImportAnnotation::new_tag_annotation(
+            &original_annotation,
+            eligible_annotation_classes,
+            AnnotationTarget::slots(&["0", "0"]),
+        )
+        .is_err());
+        assert!(AnnotationImportAnnotation::new_video_tag_annotation(
+            &original_annotation,
+            [0, 4],
+            eligible_annotation_classes,
+            &ClassMatching::default(),
+            AnnotationTarget::slot("0").section(1),
+        )
+        .is_err());
+
+        Ok(())
+    }
+
     #[test]
     fn test_new_polygon_annotation_with_invalid_class() {
         let original_annotation = create_sample_image_annotation(None);
@@ -285,6 +1110,138 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_new_shape_annotation() -> Result<()> {
+        let original_annotation = ImageAnnotation {
+            name: "Sample Class".to_string(),
+            ellipse: Some(Ellipse {
+                angle: 0.5,
+                center: Keypoint { x: 10.0, y: 20.0 },
+                radius: Keypoint { x: 4.0, y: 2.0 },
+            }),
+            ..ImageAnnotation::default()
+        };
+        let eligible_annotation_classes = &[&create_sample_annotation_class("Sample Class", 1)];
+
+        let result = AnnotationImportAnnotation::new_shape_annotation(
+            &original_annotation,
+            eligible_annotation_classes,
+            "sample_slot",
+        )?;
+        assert_eq!(
+            serde_json::to_value(&result.data)?,
+            serde_json::json!({
+                "ellipse": {
+                    "angle": 0.5,
+                    "center": {"x": 10.0, "y": 20.0},
+                    "radius": {"x": 4.0, "y": 2.0}
+                }
+            })
+        );
+
+        let result = AnnotationImportAnnotation::new_shape_annotation(
+            &create_sample_image_annotation(Some(Tag {})),
+            eligible_annotation_classes,
+            "sample_slot",
+        );
+        assert!(result.is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_new_video_polygon_annotation() -> Result<()> {
+        let eligible_annotation_classes = &[&create_sample_annotation_class("Sample Class", 1)];
+        let square = |x: f32| {
+            vec![
+                Keypoint { x, y: 0.0 },
+                Keypoint { x: x + 1.0, y: 0.0 },
+                Keypoint { x: x + 1.0, y: 1.0 },
+            ]
+        };
+
+        let result = AnnotationImportAnnotation::new_video_polygon_annotation(
+            &create_sample_image_annotation(None),
+            [(10, square(5.0)), (2, square(0.0))],
+            eligible_annotation_classes,
+            &ClassMatching::default(),
+            "sample_slot",
+        )?;
+        let value = serde_json::to_value(&result.data)?;
+        assert_eq!(value["ranges"], serde_json::json!([[2, 11]]));
+        assert_eq!(value["interpolated"], true);
+        assert_eq!(
+            value["interpolate_algorithm"],
+            DEFAULT_INTERPOLATE_ALGORITHM
+        );
+        assert_eq!(value["frames"]["2"]["keyframe"], true);
+        assert_eq!(value["frames"]["10"]["polygon"]["path"][0]["x"], 5.0);
+        assert!(value.get("polygon").is_none());
+
+        let parsed: AnnotationImportData = serde_json::from_value(value)?;
+        assert_eq!(
+            parsed.frames.keys().copied().collect::<Vec<_>>(),
+            vec![2, 10]
+        );
+        assert!(parsed.frames[&10].data.polygon.is_some());
+        assert!(parsed.other.is_empty());
+
+        // The ranges, interpolation and frame sub-annotations of an exported video annotation are kept
+        let exported: ImageAnnotation = serde_json::from_value(serde_json::json!({
+            "name": "Sample Class",
+            "frames": {"0": {"keyframe": true, "instance_id": {"value": 7}}},
+            "ranges": [[0, 4], [8, 20]],
+            "interpolated": false
+        }))?;
+        let result = AnnotationImportAnnotation::new_video_polygon_annotation(
+            &exported,
+            [(0, square(0.0))],
+            eligible_annotation_classes,
+            &ClassMatching::default(),
+            "sample_slot",
+        )?;
+        assert_eq!(result.data.ranges, vec![[0, 4], [8, 20]]);
+        assert_eq!(result.data.interpolated, Some(false));
+        assert!(result.data.interpolate_algorithm.is_none());
+        assert_eq!(
+            result.data.frames[&0]
+                .data
+                .instance_id
+                .as_ref()
+                .map(|id| id.value),
+            Some(7)
+        );
+
+        let keyframes: [(u32, Vec<Keypoint>); 0] = [];
+        assert!(AnnotationImportAnnotation::new_video_polygon_annotation(
+            &exported,
+            keyframes,
+            eligible_annotation_classes,
+            &ClassMatching::default(),
+            "sample_slot",
+        )
+        .is_err());
+
+        let tag = AnnotationImportAnnotation::new_video_tag_annotation(
+            &create_sample_image_annotation(None),
+            [4],
+            eligible_annotation_classes,
+            &ClassMatching::default(),
+            "sample_slot",
+        )?;
+        assert_eq!(
+            serde_json::to_value(&tag.data)?,
+            serde_json::json!({
+                "frames": {"4": {"keyframe": true, "tag": {}}},
+                "ranges": [[4, 5]],
+                "interpolated": true,
+                "interpolate_algorithm": DEFAULT_INTERPOLATE_ALGORITHM
+            })
+        );
+
+        Ok(())
+    }
+
     #[test]
     fn test_new_tag_annotation_with_invalid_class() {
         let original_annotation = create_sample_image_annotation(Some(Tag {}));
@@ -298,4 +1255,104 @@ mod tests {
 
         assert!(result.is_err());
     }
+
+    #[test]
+    fn test_class_matching() -> Result<()> {
+        let tumour = create_sample_annotation_class("Tumour cells", 1);
+        let stroma = create_sample_annotation_class("Stroma", 2);
+        let classes = &[&tumour, &stroma];
+
+        assert!(ClassMatching::default()
+            .find_class_id(classes, "tumour cells ")
+            .is_err());
+        let case_insensitive = ClassMatching::new(ClassNameMatch::CaseInsensitive);
+        assert_eq!(case_insensitive.find_class_id(classes, "tumour cells ")?, 1);
+        assert!(case_insensitive
+            .find_class_id(classes, "Ｔumour\u{a0} cells")
+            .is_err());
+        #[cfg(feature = "unicode-normalization")]
+        assert_eq!(
+            ClassMatching::new(ClassNameMatch::Normalized)
+                .find_class_id(classes, "Ｔumour\u{a0} cells")?,
+            1
+        );
+        #[cfg(not(feature = "unicode-normalization"))]
+        assert_eq!(
+            ClassMatching::new(ClassNameMatch::Normalized)
+                .find_class_id(classes, "Tumour cells")
+                .unwrap_err()
+                .to_string(),
+            "Normalized class name matching requires the unicode-normalization feature"
+        );
+
+        let aliased =
+            ClassMatching::new(ClassNameMatch::CaseInsensitive).with_alias("ST", "Stroma");
+        assert_eq!(aliased.find_class_id(classes, "st")?, 2);
+        let exact_alias = aliased.clone().with_alias("st", "Tumour cells");
+        assert_eq!(exact_alias.find_class_id(classes, "st")?, 1);
+        assert_eq!(exact_alias.find_class_id(classes, "ST")?, 2);
+
+        let lowercase_tumour = create_sample_annotation_class("tumour cells", 3);
+        let ambiguous = &[&tumour, &lowercase_tumour];
+        assert_eq!(
+            case_insensitive.find_class_id(ambiguous, "tumour cells")?,
+            3
+        );
+        assert!(case_insensitive
+            .find_class_id(ambiguous, "TUMOUR CELLS")
+            .is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_stable_ids_and_resolve_existing() -> Result<()> {
+        let classes = &[&create_sample_annotation_class("Sample Class", 1)];
+        let polygon = |x: f32| {
+            AnnotationImportAnnotation::new_polygon_annotation(
+                &create_sample_image_annotation(None),
+                vec![Keypoint { x, y: 10.0 }, Keypoint { x: 20.0, y: 20.0 }],
+                classes,
+                "0",
+            )
+        };
+
+        let first = polygon(10.0)?.with_stable_id("item-1")?;
+        assert_eq!(first.id, polygon(10.0)?.with_stable_id("item-1")?.id);
+        assert_ne!(first.id, polygon(10.0)?.with_stable_id("item-2")?.id);
+        let second = polygon(15.0)?.with_stable_id("item-1")?;
+        assert_ne!(first.id, second.id);
+        let skipped = polygon(12.0)?
+            .with_stable_id("item-1")?
+            .on_existing(OnExisting::Skip);
+
+        let import = AnnotationImport {
+            annotations: vec![first.clone(), second.clone(), skipped.clone()],
+            overwrite: false,
+        };
+        let existing = HashSet::from([first.id.clone(), skipped.id.clone()]);
+        let plan = import.resolve_existing(&existing);
+        assert_eq!(plan.replaced, vec![first.id.clone()]);
+        assert_eq!(plan.skipped, vec![skipped.id.clone()]);
+        let ids: Vec<&str> = plan
+            .import
+            .annotations
+            .iter()
+            .map(|annotation| annotation.id.as_str())
+            .collect();
+        assert_eq!(ids, vec![first.id.as_str(), second.id.as_str()]);
+        assert!(serde_json::to_value(&plan.import.annotations[0])?
+            .get("on_existing")
+            .is_none());
+
+        let overwrite = AnnotationImport {
+            overwrite: true,
+            ..import
+        };
+        let plan = overwrite.resolve_existing(&existing);
+        assert_eq!(plan.import.annotations.len(), 3);
+        assert!(plan.replaced.is_empty() && plan.skipped.is_empty());
+
+        Ok(())
+    }
 }
@@ -1,8 +1,38 @@
-use crate::workflow::StageType;
-use anyhow::{bail, Result};
+#[cfg(feature = "client")]
+use crate::annotation::ItemAnnotation;
+#[cfg(feature = "client")]
+use crate::client::{HttpStatusError, PresignedUrlMethods, V7Methods};
+#[cfg(feature = "client")]
+use crate::datasets::SetStageResponse;
+#[cfg(feature = "client")]
+use crate::expect_http_ok;
+#[cfg(feature = "client")]
+use crate::export::JsonExportV2;
+#[cfg(feature = "client")]
+use crate::ids::WorkflowId;
+use crate::known::MaybeKnown;
+#[cfg(feature = "client")]
+use crate::response::HeaderMap;
+#[cfg(feature = "client")]
+use crate::team::{Team, TeamDescribeMethods};
+#[cfg(feature = "timestamps")]
+use crate::utils::Timestamp;
+#[cfg(feature = "client")]
+use crate::utils::{encode_path_segment, encode_query};
+use crate::workflow::{
+    ItemAssignee, ItemCurrentStage, ItemHistoryEvent, ItemWorkflowData, StageType,
+};
+#[cfg(feature = "client")]
+use crate::workflow::{WorkflowMethods, WorkflowV2};
+use anyhow::{bail, Context, Result};
+#[cfg(feature = "client")]
+use async_trait::async_trait;
+#[cfg(feature = "fake")]
 use fake::{Dummy, Fake, Faker};
 use serde::ser::SerializeMap;
-use serde::{de::MapAccess, de::Visitor, Deserialize, Deserializer, Serialize, Serializer};
+use serde::{
+    de::MapAccess, de::SeqAccess, de::Visitor, Deserialize, Deserializer, Serialize, Serializer,
+};
 use std::cmp::PartialEq;
 use std::collections::HashMap;
 use std::fmt::{self, Display};
@@ -12,7 +42,8 @@ use std::fmt::{self, Display};
 // value.  There is a high degree of variability as to when and why
 // a null may be provided in the JSON payload.
 
-#[derive(Debug, Clone, Serialize, Deserialize, Dummy, PartialEq, Eq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[cfg_attr(feature = "fake", derive(Dummy))]
 pub struct ImageLevel {
     pub format: String,
     pub pixel_ratio: u16,
@@ -22,6 +53,12 @@ pub struct ImageLevel {
     pub y_tiles: u32,
 }
 
+impl ImageLevel {
+    pub fn tile_count(&self) -> u32 {
+        self.x_tiles * self.y_tiles
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Levels {
     pub image_levels: HashMap<u32, ImageLevel>,
@@ -108,6 +145,7 @@ impl<'de> Visitor<'de> for LevelVisitor {
     }
 }
 
+#[cfg(feature = "fake")]
 impl Dummy<fake::Faker> for Levels {
     fn dummy_with_rng<R: rand::Rng + ?Sized>(_: &fake::Faker, rng: &mut R) -> Self {
         let max_levels: u32 = (2..5).fake_with_rng(rng);
@@ -127,7 +165,195 @@ impl Dummy<fake::Faker> for Levels {
     }
 }
 
-#[derive(Debug, Default, Clone, Serialize, Deserialize, Dummy, PartialEq, Eq)]
+/// A tag of a dataset item with its optional text attribute
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[cfg_attr(feature = "fake", derive(Dummy))]
+pub struct ItemTag {
+    pub name: String,
+    pub attribute: Option<String>,
+}
+
+/// The tags of a dataset item, in the order they were added.
+///
+/// V7 sends and accepts tags either as a list of tag names, `["biopsy"]`, or as a map of
+/// tag name to attribute, `{"biopsy": "left lobe"}`. Both are read into `ItemTags` and the
+/// tags are written back as a list unless one of them has an attribute.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ItemTags(Vec<ItemTag>);
+
+impl ItemTags {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn iter(&self) -> std::slice::Iter<'_, ItemTag> {
+        self.0.iter()
+    }
+
+    /// The tag names in order
+    pub fn names(&self) -> Vec<&str> {
+        self.0.iter().map(|tag| tag.name.as_str()).collect()
+    }
+
+    pub fn get(&self, name: &str) -> Option<&ItemTag> {
+        self.0.iter().find(|tag| tag.name == name)
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.get(name).is_some()
+    }
+
+    /// Whether any of the tags has an attribute, i.e. whether they are written as a map
+    pub fn has_attributes(&self) -> bool {
+        self.0.iter().any(|tag| tag.attribute.is_some())
+    }
+
+    /// Adds the tag `name`, replacing the attribute of an existing tag with the same name
+    pub fn insert(&mut self, name: &str, attribute: Option<&str>) {
+        let attribute = attribute.map(String::from);
+        match self.0.iter_mut().find(|tag| tag.name == name) {
+            Some(tag) => tag.attribute = attribute,
+            None => self.0.push(ItemTag {
+                name: name.to_string(),
+                attribute,
+            }),
+        }
+    }
+
+    /// Removes the tag `name`, returning it if it was present
+    pub fn remove(&mut self, name: &str) -> Option<ItemTag> {
+        let position = self.0.iter().position(|tag| tag.name == name)?;
+        Some(self.0.remove(position))
+    }
+}
+
+impl<S: Into<String>> FromIterator<S> for ItemTags {
+    fn from_iter<I: IntoIterator<Item = S>>(names: I) -> Self {
+        let mut tags = ItemTags::new();
+        for name in names {
+            tags.insert(&name.into(), None);
+        }
+        tags
+    }
+}
+
+impl From<Vec<String>> for ItemTags {
+    fn from(names: Vec<String>) -> Self {
+        names.into_iter().collect()
+    }
+}
+
+impl IntoIterator for ItemTags {
+    type Item = ItemTag;
+    type IntoIter = std::vec::IntoIter<ItemTag>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.into_iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a ItemTags {
+    type Item = &'a ItemTag;
+    type IntoIter = std::slice::Iter<'a, ItemTag>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.iter()
+    }
+}
+
+impl Serialize for ItemTags {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        if self.has_attributes() {
+            let mut map = serializer.serialize_map(Some(self.0.len()))?;
+            for tag in &self.0 {
+                map.serialize_entry(&tag.name, &tag.attribute)?;
+            }
+            map.end()
+        } else {
+            serializer.collect_seq(self.0.iter().map(|tag| &tag.name))
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for ItemTags {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_any(ItemTagsVisitor)
+    }
+}
+
+struct ItemTagsVisitor;
+
+impl<'de> Visitor<'de> for ItemTagsVisitor {
+    type Value = ItemTags;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            formatter,
+            "a list of tag names or a map of tag name to attribute"
+        )
+    }
+
+    fn visit_unit<E: serde::de::Error>(self) -> Result<Self::Value, E> {
+        Ok(ItemTags::new())
+    }
+
+    fn visit_none<E: serde::de::Error>(self) -> Result<Self::Value, E> {
+        Ok(ItemTags::new())
+    }
+
+    // V7 lists the tags of an item as nullable strings, nulls are skipped
+    fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+    where
+        A: SeqAccess<'de>,
+    {
+        let mut tags = ItemTags::new();
+        while let Some(name) = seq.next_element::<Option<String>>()? {
+            if let Some(name) = name {
+                tags.insert(&name, None);
+            }
+        }
+        Ok(tags)
+    }
+
+    fn visit_map<M>(self, mut map: M) -> Result<Self::Value, M::Error>
+    where
+        M: MapAccess<'de>,
+    {
+        let mut tags = ItemTags::new();
+        while let Some((name, attribute)) = map.next_entry::<String, Option<String>>()? {
+            tags.insert(&name, attribute.as_deref());
+        }
+        Ok(tags)
+    }
+}
+
+#[cfg(feature = "fake")]
+impl Dummy<fake::Faker> for ItemTags {
+    fn dummy_with_rng<R: rand::Rng + ?Sized>(config: &fake::Faker, rng: &mut R) -> Self {
+        let mut tags = ItemTags::new();
+        for tag in config.fake_with_rng::<Vec<ItemTag>, R>(rng) {
+            tags.insert(&tag.name, tag.attribute.as_deref());
+        }
+        tags
+    }
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[cfg_attr(feature = "fake", derive(Dummy))]
 pub struct Image {
     pub external: Option<bool>,
     pub format: Option<String>,
@@ -142,7 +368,8 @@ pub struct Image {
     pub url: Option<String>,
 }
 
-#[derive(Debug, Default, Clone, Serialize, Deserialize, Dummy, PartialEq, Eq)]
+#[derive(Debug, Default, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[cfg_attr(feature = "fake", derive(Dummy))]
 pub struct DatasetImage {
     pub dataset_id: Option<u32>,
     pub dataset_video_id: Option<u32>,
@@ -153,10 +380,12 @@ pub struct DatasetImage {
 }
 
 // TODO: Define this struct
-#[derive(Debug, Clone, Serialize, Deserialize, Dummy, PartialEq, Eq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[cfg_attr(feature = "fake", derive(Dummy))]
 pub struct DatasetVideo {}
 
-#[derive(Debug, Default, Clone, Serialize, Deserialize, Dummy, PartialEq, Eq)]
+#[derive(Debug, Default, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[cfg_attr(feature = "fake", derive(Dummy))]
 #[serde(rename_all = "lowercase")]
 pub enum DatasetItemTypes {
     #[default]
@@ -179,12 +408,41 @@ impl Display for DatasetItemTypes {
     }
 }
 
-#[derive(Debug, Default, Clone, Serialize, Deserialize, Dummy, PartialEq, Eq)]
+impl DatasetItemTypes {
+    pub const ALL: [DatasetItemTypes; 5] = [
+        DatasetItemTypes::Image,
+        DatasetItemTypes::Video,
+        DatasetItemTypes::Pdf,
+        DatasetItemTypes::Dicom,
+        DatasetItemTypes::TiledImage,
+    ];
+
+    /// Infers the item type from the extension of a file name, `None` if the
+    /// extension is not a format supported by V7
+    pub fn from_file_name(file_name: &str) -> Option<Self> {
+        let (_, extension) = file_name.rsplit_once('.')?;
+        Some(match extension.to_lowercase().as_str() {
+            "jpg" | "jpeg" | "png" | "bmp" | "gif" | "webp" | "jfif" | "tif" | "tiff" => {
+                Self::Image
+            }
+            "svs" | "ndpi" | "scn" | "mrxs" | "qptiff" => Self::TiledImage,
+            "mp4" | "mov" | "avi" | "mkv" | "webm" => Self::Video,
+            "pdf" => Self::Pdf,
+            "dcm" | "dicom" | "nii" => Self::Dicom,
+            _ => return None,
+        })
+    }
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[cfg_attr(feature = "fake", derive(Dummy))]
 #[serde(rename_all = "lowercase")]
 pub enum DatasetItemStatus {
     Annotate,
     Archived,
     Complete,
+    /// In a discard stage of the workflow
+    Discard,
     Error,
     #[default]
     New,
@@ -199,6 +457,7 @@ impl Display for DatasetItemStatus {
             DatasetItemStatus::Annotate => write!(f, "Annotate"),
             DatasetItemStatus::Archived => write!(f, "Archived"),
             DatasetItemStatus::Complete => write!(f, "Complete"),
+            DatasetItemStatus::Discard => write!(f, "Discard"),
             DatasetItemStatus::Error => write!(f, "Error"),
             DatasetItemStatus::New => write!(f, "New"),
             DatasetItemStatus::Processing => write!(f, "Processing"),
@@ -226,13 +485,15 @@ impl TryFrom<&str> for DatasetItemStatus {
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, Dummy, PartialEq, Eq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[cfg_attr(feature = "fake", derive(Dummy))]
 pub struct DataPayloadLevel {
     pub levels: HashMap<usize, ImageLevel>,
     pub base_key: String,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, Dummy, PartialEq, Eq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[cfg_attr(feature = "fake", derive(Dummy))]
 pub struct AddDataPayload {
     #[serde(rename = "type")]
     pub item_type: DatasetItemTypes,
@@ -245,7 +506,8 @@ pub struct AddDataPayload {
     pub metadata: DataPayloadLevel,
 }
 
-#[derive(Debug, Default, Clone, Serialize, Deserialize, Dummy, PartialEq, Eq)]
+#[derive(Debug, Default, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[cfg_attr(feature = "fake", derive(Dummy))]
 pub struct NewSimpleItem {
     pub as_frames: bool,
     pub extract_views: bool,
@@ -255,13 +517,14 @@ pub struct NewSimpleItem {
     pub metadata: HashMap<String, String>,
     pub name: String,
     pub path: String,
-    #[serde(skip_serializing_if = "Vec::is_empty")]
-    pub tags: Vec<String>, // TODO: https://docs.v7labs.com/reference/imports-upload tags in the json object can either be Vec<String> or HashMap<String, String>
+    #[serde(default, skip_serializing_if = "ItemTags::is_empty")]
+    pub tags: ItemTags,
     #[serde(rename = "type")]
     pub typ: DatasetItemTypes,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, Dummy, PartialEq, Eq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[cfg_attr(feature = "fake", derive(Dummy))]
 pub struct RegisterNewItemOptions {
     pub force_tiling: bool,
     pub ignore_dicom_layout: bool,
@@ -276,14 +539,16 @@ impl Default for RegisterNewItemOptions {
     }
 }
 
-#[derive(Debug, Default, Clone, Serialize, Deserialize, Dummy, PartialEq, Eq)]
+#[derive(Debug, Default, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[cfg_attr(feature = "fake", derive(Dummy))]
 pub struct RegisterNewSimpleItemRequest {
     pub dataset_slug: String,
     pub items: Vec<NewSimpleItem>,
     pub options: RegisterNewItemOptions,
 }
 
-#[derive(Debug, Default, Clone, Serialize, Deserialize, Dummy, PartialEq, Eq)]
+#[derive(Debug, Default, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[cfg_attr(feature = "fake", derive(Dummy))]
 pub struct ImageSection {
     pub height: u32,
     pub width: u32,
@@ -294,7 +559,8 @@ pub struct ImageSection {
     pub image_section_type: String,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, Dummy, PartialEq, Eq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[cfg_attr(feature = "fake", derive(Dummy))]
 pub struct Slot {
     pub sections: Vec<ImageSection>,
     pub file_name: String,
@@ -307,13 +573,192 @@ pub struct Slot {
     pub metadata: DataPayloadLevel,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, Dummy, PartialEq, Eq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[cfg_attr(feature = "fake", derive(Dummy))]
 pub struct ExistingSimpleItem {
     pub name: String,
     pub path: String,
     pub slots: Vec<Slot>,
 }
 
+/// An object listed from external storage, e.g. an entry of an S3 `ListObjectsV2` response
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct StorageObject {
+    pub key: String,
+    pub size_bytes: u32,
+    /// Key of the thumbnail of the object, derived from the builder suffix if not provided
+    pub thumbnail_key: Option<String>,
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+}
+
+/// Builds `ExistingSimpleItem`s for read-only registration from a listing of external storage.
+///
+/// Objects under `key_prefix` are registered in the dataset folder `path` with any
+/// directories between the prefix and the file name appended to the folder, e.g. with the
+/// prefix `scans/batch-3/` the key `scans/batch-3/au1/slide.svs` becomes `slide.svs` in
+/// `/au1`. Item types are inferred from the file extension.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExistingSimpleItemBuilder {
+    key_prefix: String,
+    path: String,
+    slot_name: String,
+    thumbnail_suffix: String,
+    item_type: Option<DatasetItemTypes>,
+}
+
+impl Default for ExistingSimpleItemBuilder {
+    fn default() -> Self {
+        Self {
+            key_prefix: String::new(),
+            path: "/".to_string(),
+            slot_name: "0".to_string(),
+            thumbnail_suffix: "_thumbnail.jpg".to_string(),
+            item_type: None,
+        }
+    }
+}
+
+impl ExistingSimpleItemBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Only objects with keys starting with `prefix` are built
+    pub fn key_prefix(mut self, prefix: &str) -> Self {
+        self.key_prefix = prefix.to_string();
+        self
+    }
+
+    /// Dataset folder the items are registered into, defaults to `/`
+    pub fn path(mut self, path: &str) -> Self {
+        self.path = format!("/{}", path.trim_matches('/'));
+        self
+    }
+
+    /// Name of the slot of each item, defaults to `0`
+    pub fn slot_name(mut self, slot_name: &str) -> Self {
+        self.slot_name = slot_name.to_string();
+        self
+    }
+
+    /// Suffix appended to an object key to find its thumbnail, defaults to `_thumbnail.jpg`.
+    /// Objects ending with the suffix are treated as thumbnails and not built into items.
+    pub fn thumbnail_suffix(mut self, suffix: &str) -> Self {
+        self.thumbnail_suffix = suffix.to_string();
+        self
+    }
+
+    /// Uses `item_type` for every object instead of inferring it from the file extension
+    pub fn item_type(mut self, item_type: DatasetItemTypes) -> Self {
+        self.item_type = Some(item_type);
+        self
+    }
+
+    /// Builds the item for a single storage object
+    pub fn build(&self, object: &StorageObject) -> Result<ExistingSimpleItem> {
+        let relative_key = object
+            .key
+            .strip_prefix(&self.key_prefix)
+            .with_context(|| format!("{} is not under prefix {}", object.key, self.key_prefix))?;
+        let (directory, file_name) = relative_key.rsplit_once('/').unwrap_or(("", relative_key));
+        if file_name.is_empty() {
+            bail!("{} is not a file", object.key);
+        }
+        let slot_type = match self.item_type.as_ref() {
+            Some(item_type) => item_type.clone(),
+            None => DatasetItemTypes::from_file_name(file_name)
+                .with_context(|| format!("Unable to infer the item type of {}", object.key))?,
+        };
+
+        let path = match (self.path.trim_end_matches('/'), directory) {
+            ("", "") => "/".to_string(),
+            (base, "") => base.to_string(),
+            (base, directory) => format!("{base}/{directory}"),
+        };
+        let base_key = object
+            .key
+            .rsplit_once('/')
+            .map(|(base, _)| format!("{base}/"))
+            .unwrap_or_default();
+
+        let sections = match (object.width, object.height) {
+            (Some(width), Some(height)) => vec![ImageSection {
+                height,
+                width,
+                size_bytes: object.size_bytes,
+                section_index: 0,
+                storage_hq_key: object.key.clone(),
+                image_section_type: "main".to_string(),
+            }],
+            _ => vec![],
+        };
+
+        Ok(ExistingSimpleItem {
+            name: file_name.to_string(),
+            path,
+            slots: vec![Slot {
+                sections,
+                file_name: file_name.to_string(),
+                size_bytes: object.size_bytes,
+                slot_name: self.slot_name.clone(),
+                storage_key: object.key.clone(),
+                storage_thumbnail_key: object
+                    .thumbnail_key
+                    .clone()
+                    .unwrap_or_else(|| format!("{}{}", object.key, self.thumbnail_suffix)),
+                slot_type,
+                metadata: DataPayloadLevel {
+                    levels: HashMap::new(),
+                    base_key,
+                },
+            }],
+        })
+    }
+
+    /// Builds items for every object in a storage listing. Thumbnails, directory
+    /// markers and objects outside of the prefix are skipped.
+    pub fn build_all<I>(&self, objects: I) -> Result<Vec<ExistingSimpleItem>>
+    where
+        I: IntoIterator<Item = StorageObject>,
+    {
+        objects
+            .into_iter()
+            .filter(|object| object.key.starts_with(&self.key_prefix))
+            .filter(|object| !object.key.ends_with('/'))
+            .filter(|object| !object.key.ends_with(&self.thumbnail_suffix))
+            .map(|object| self.build(&object))
+            .collect()
+    }
+}
+
+/// Slot of an item registered from read-write external storage.
+/// V7 generates the thumbnails and tiles itself, so only the location of the file is required.
+#[derive(Debug, Default, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[cfg_attr(feature = "fake", derive(Dummy))]
+pub struct ReadWriteSlot {
+    pub slot_name: String,
+    pub storage_key: String,
+    pub file_name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub as_frames: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub extract_views: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fps: Option<String>, // is either a positive integer number or the string `native`
+    #[serde(rename = "type", skip_serializing_if = "Option::is_none")]
+    pub slot_type: Option<DatasetItemTypes>,
+}
+
+/// Item registered from read-write external storage
+#[derive(Debug, Default, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[cfg_attr(feature = "fake", derive(Dummy))]
+pub struct ExistingReadWriteItem {
+    pub name: String,
+    pub path: String,
+    pub slots: Vec<ReadWriteSlot>,
+}
+
 impl Display for DatasetItemV2 {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(
@@ -324,7 +769,8 @@ impl Display for DatasetItemV2 {
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, Dummy, PartialEq, Eq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[cfg_attr(feature = "fake", derive(Dummy))]
 pub struct ItemSlotLevel {
     pub levels: HashMap<usize, ImageLevel>,
     pub base_key: String,
@@ -334,7 +780,25 @@ pub struct ItemSlotLevel {
     pub width: Option<u32>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, Dummy)]
+impl ItemSlotLevel {
+    /// The level with the largest pixel ratio, i.e. the most downsampled, and its index.
+    /// Levels with the same ratio are told apart by their number of tiles.
+    pub fn lowest_resolution_level(&self) -> Option<(usize, &ImageLevel)> {
+        self.levels
+            .iter()
+            .max_by_key(|(index, level)| {
+                (
+                    level.pixel_ratio,
+                    std::cmp::Reverse(level.tile_count()),
+                    **index,
+                )
+            })
+            .map(|(index, level)| (*index, level))
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "fake", derive(Dummy))]
 pub struct ItemSlot {
     pub file_name: Option<String>,
     pub fps: Option<f32>,
@@ -343,6 +807,12 @@ pub struct ItemSlot {
     pub metadata: Option<ItemSlotLevel>,
     pub size_bytes: Option<u64>,
     pub slot_name: Option<String>,
+    /// The first sections of the slot, only included when `include_first_sections` is requested
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub first_sections: Vec<ItemSection>,
+    /// Only included by V7 for items registered from external storage
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub storage_key: Option<String>,
     pub streamable: Option<bool>,
     pub total_sections: Option<u32>,
     #[serde(rename = "type")]
@@ -350,7 +820,8 @@ pub struct ItemSlot {
     pub upload_id: Option<String>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, Dummy)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "fake", derive(Dummy))]
 pub struct DatasetItemLayout {
     pub slots: Vec<Option<String>>,
     #[serde(rename = "type")]
@@ -358,7 +829,8 @@ pub struct DatasetItemLayout {
     pub version: Option<u32>,
 }
 
-#[derive(Debug, Clone, Default, Serialize, Deserialize, Dummy)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "fake", derive(Dummy))]
 pub struct DatasetItemV2 {
     pub archived: Option<bool>,
     pub cursor: Option<String>,
@@ -370,16 +842,121 @@ pub struct DatasetItemV2 {
     pub path: Option<String>,
     pub priority: Option<u32>,
     pub processing_status: Option<DatasetItemStatus>,
+    #[serde(default)]
     pub slot_types: Vec<Option<DatasetItemTypes>>,
+    #[serde(default)]
     pub slots: Vec<Option<ItemSlot>>,
-    pub status: Option<DatasetItemStatus>,
-    pub tags: Vec<Option<String>>,
+    pub status: Option<MaybeKnown<DatasetItemStatus>>,
+    #[serde(default)]
+    pub tags: ItemTags,
     pub updated_at: Option<String>,
+    #[serde(default)]
     pub uploads: Vec<Option<String>>,
     pub workflow_status: Option<StageType>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub workflow_data: Option<ItemWorkflowData>,
+    /// Only included when `include_workflow_data` is requested
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub current_stage: Option<ItemCurrentStage>,
+    /// Only included when `include_workflow_data` is requested
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub assignees: Vec<ItemAssignee>,
+}
+
+#[cfg(feature = "timestamps")]
+impl DatasetItemV2 {
+    /// `inserted_at` parsed, see `Timestamp`
+    pub fn inserted_timestamp(&self) -> Option<Timestamp> {
+        Timestamp::parse_optional(&self.inserted_at)
+    }
+
+    /// `updated_at` parsed, see `Timestamp`
+    pub fn updated_timestamp(&self) -> Option<Timestamp> {
+        Timestamp::parse_optional(&self.updated_at)
+    }
 }
 
-#[derive(Debug, Clone, Default, Serialize, Deserialize, Dummy)]
+impl DatasetItemV2 {
+    /// Whether the item is in a discard stage of its workflow
+    pub fn is_discarded(&self) -> bool {
+        self.workflow_status == Some(StageType::Discard)
+    }
+}
+
+/// A dataset item flattened into a single row, e.g. for reconciling the items of a
+/// dataset against another inventory, see `DatasetItemReportMethods::dump_items`
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ItemRow {
+    pub id: Option<String>,
+    pub name: Option<String>,
+    pub path: Option<String>,
+    pub status: Option<MaybeKnown<DatasetItemStatus>>,
+    /// Name of the current workflow stage when the workflow data was included,
+    /// otherwise the type of the stage
+    pub stage: Option<String>,
+    /// Slot names of the item joined by semicolon
+    pub slot_names: String,
+    /// Slot types of the item joined by semicolon, in the order of `slot_names`
+    pub slot_types: String,
+    /// Total size of the files of all slots, `None` if the size of any slot is unknown
+    pub size_bytes: Option<u64>,
+    pub inserted_at: Option<String>,
+    pub updated_at: Option<String>,
+}
+
+#[cfg(feature = "timestamps")]
+impl ItemRow {
+    /// `inserted_at` parsed, see `Timestamp`
+    pub fn inserted_timestamp(&self) -> Option<Timestamp> {
+        Timestamp::parse_optional(&self.inserted_at)
+    }
+
+    /// `updated_at` parsed, see `Timestamp`
+    pub fn updated_timestamp(&self) -> Option<Timestamp> {
+        Timestamp::parse_optional(&self.updated_at)
+    }
+}
+
+impl From<&DatasetItemV2> for ItemRow {
+    fn from(item: &DatasetItemV2) -> Self {
+        let slots: Vec<&ItemSlot> = item.slots.iter().flatten().collect();
+        let join = |values: Vec<String>| values.join(";");
+        ItemRow {
+            id: item.id.clone(),
+            name: item.name.clone(),
+            path: item.path.clone(),
+            status: item.status.clone(),
+            stage: item
+                .current_stage
+                .as_ref()
+                .and_then(|stage| stage.name.clone())
+                .or_else(|| item.workflow_status.as_ref().map(ToString::to_string)),
+            slot_names: join(
+                slots
+                    .iter()
+                    .map(|slot| slot.slot_name.clone().unwrap_or_default())
+                    .collect(),
+            ),
+            slot_types: join(
+                slots
+                    .iter()
+                    .map(|slot| {
+                        slot.item_slot_type
+                            .as_ref()
+                            .map(ToString::to_string)
+                            .unwrap_or_default()
+                    })
+                    .collect(),
+            ),
+            size_bytes: slots.iter().map(|slot| slot.size_bytes).sum(),
+            inserted_at: item.inserted_at.clone(),
+            updated_at: item.updated_at.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "fake", derive(Dummy))]
 pub struct ItemPage {
     pub count: Option<u32>,
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -387,16 +964,994 @@ pub struct ItemPage {
     pub previous: Option<String>,
 }
 
-#[derive(Debug, Clone, Default, Serialize, Deserialize, Dummy)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "fake", derive(Dummy))]
 pub struct Item {
     pub items: Vec<Option<DatasetItemV2>>,
     pub page: ItemPage,
 }
 
+/// Processing status of a section of an item slot
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[cfg_attr(feature = "fake", derive(Dummy))]
+#[serde(rename_all = "snake_case")]
+pub enum SectionStatus {
+    Uploading,
+    Processing,
+    Processed,
+    Error,
+}
+
+/// A section of an item slot, e.g. a page of a PDF or a frame of a DICOM series
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[cfg_attr(feature = "fake", derive(Dummy))]
+pub struct ItemSection {
+    pub section_index: Option<usize>,
+    pub height: Option<u32>,
+    pub width: Option<u32>,
+    pub size_bytes: Option<u64>,
+    pub storage_hq_key: Option<String>,
+    pub storage_key: Option<String>,
+    pub storage_thumbnail_key: Option<String>,
+    pub url: Option<String>,
+    pub thumbnail_url: Option<String>,
+    #[serde(rename = "type")]
+    pub section_type: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub status: Option<MaybeKnown<SectionStatus>>,
+}
+
+/// A small image of an item slot for visual QC, see `DatasetItemMethods::fetch_preview`
+#[derive(Debug, Clone, Default)]
+pub struct SlotPreview {
+    pub slot_name: String,
+    /// The lowest resolution level of a tiled slot and its index, for the scale of the
+    /// preview in a report. `None` for slots without levels.
+    pub level: Option<(usize, ImageLevel)>,
+    /// Content type of the image e.g. `image/jpeg`, when V7 sent one
+    pub content_type: Option<String>,
+    pub bytes: Vec<u8>,
+}
+
+/// A frame of a video slot registered `as_frames`
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ItemFrame {
+    /// Position of the frame in the video, starting at 0
+    pub frame_index: usize,
+    /// The section holding the frame, with its urls, thumbnail and status
+    pub section: ItemSection,
+}
+
+impl ItemFrame {
+    /// Whether the frame failed to process and needs attention during QA
+    pub fn has_error(&self) -> bool {
+        self.section.status == Some(MaybeKnown::Known(SectionStatus::Error))
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "fake", derive(Dummy))]
+pub struct ItemSectionPage {
+    pub sections: Vec<Option<ItemSection>>,
+    pub page: ItemPage,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ItemHistoryPage {
+    pub events: Vec<Option<ItemHistoryEvent>>,
+    pub page: ItemPage,
+}
+
+#[cfg(feature = "client")]
+/// A page of a V7 listing paginated with `page[from]` cursors
+pub(crate) trait CursorPage: serde::de::DeserializeOwned {
+    type Entry;
+
+    fn into_entries(self) -> (Vec<Option<Self::Entry>>, ItemPage);
+}
+
+#[cfg(feature = "client")]
+impl CursorPage for Item {
+    type Entry = DatasetItemV2;
+
+    fn into_entries(self) -> (Vec<Option<DatasetItemV2>>, ItemPage) {
+        (self.items, self.page)
+    }
+}
+
+#[cfg(feature = "client")]
+impl CursorPage for ItemSectionPage {
+    type Entry = ItemSection;
+
+    fn into_entries(self) -> (Vec<Option<ItemSection>>, ItemPage) {
+        (self.sections, self.page)
+    }
+}
+
+#[cfg(feature = "client")]
+impl CursorPage for ItemHistoryPage {
+    type Entry = ItemHistoryEvent;
+
+    fn into_entries(self) -> (Vec<Option<ItemHistoryEvent>>, ItemPage) {
+        (self.events, self.page)
+    }
+}
+
+#[cfg(feature = "client")]
+/// Retrieves the page of at most `page_size` entries of `endpoint` starting at `cursor`,
+/// the endpoint may already have a query string
+pub(crate) async fn fetch_page<C, P>(
+    client: &C,
+    endpoint: &str,
+    page_size: u32,
+    cursor: Option<&str>,
+) -> Result<P>
+where
+    C: V7Methods + std::marker::Sync,
+    P: CursorPage,
+{
+    let mut query = vec![("page[size]", page_size.to_string())];
+    if let Some(cursor) = cursor {
+        query.push(("page[from]", cursor.to_string()));
+    }
+    let separator = if endpoint.contains('?') { '&' } else { '?' };
+    let response = client
+        .get(&format!("{endpoint}{separator}{}", encode_query(&query)))
+        .await?;
+    expect_http_ok!(response, P)
+}
+
+#[cfg(feature = "client")]
+/// Lists every entry of `endpoint` by following the `page.next` cursors
+pub(crate) async fn list_all_pages<C, P>(
+    client: &C,
+    endpoint: &str,
+    page_size: u32,
+) -> Result<Vec<P::Entry>>
+where
+    C: V7Methods + std::marker::Sync,
+    P: CursorPage,
+{
+    let mut entries = Vec::new();
+    let mut cursor: Option<String> = None;
+    loop {
+        let page: P = fetch_page(client, endpoint, page_size, cursor.as_deref()).await?;
+        let (page_entries, page) = page.into_entries();
+        let page_len = page_entries.len();
+        entries.extend(page_entries.into_iter().flatten());
+
+        cursor = page.next;
+        if cursor.is_none() || page_len == 0 {
+            break;
+        }
+    }
+    Ok(entries)
+}
+
+#[cfg(feature = "client")]
+/// Number of sections requested per page when listing item slot sections
+const SECTION_PAGE_SIZE: u32 = 500;
+
+#[cfg(feature = "client")]
+/// Number of events requested per page when listing an item's history
+const HISTORY_PAGE_SIZE: u32 = 500;
+
+#[cfg(feature = "client")]
+#[async_trait]
+pub trait DatasetItemMethods<C>
+where
+    C: V7Methods,
+{
+    /// Lists all sections (e.g. the pages of a document) of the item slot `slot_name`
+    /// ordered by section index, following the pagination cursors.
+    async fn list_sections(&self, client: &C, slot_name: &str) -> Result<Vec<ItemSection>>;
+    /// Lists the frames of the video slot `slot_name` with their thumbnails and statuses,
+    /// in frame order. Fails if the slot is known not to be a video.
+    async fn list_frames(&self, client: &C, slot_name: &str) -> Result<Vec<ItemFrame>>;
+    /// Lists the current annotations of the item
+    async fn list_annotations(&self, client: &C) -> Result<Vec<ItemAnnotation>>;
+    /// Renames the item, updating `name` once V7 has accepted the change
+    async fn rename(&mut self, client: &C, new_name: &str) -> Result<()>;
+    /// Moves the item to the folder `new_path`, updating `path` once V7 has accepted the change
+    async fn set_path(&mut self, client: &C, new_path: &str) -> Result<()>;
+    /// Adds `tags` to the item, replacing the attributes of tags it already has,
+    /// and updates `tags` once V7 has accepted the change
+    async fn add_tags(&mut self, client: &C, tags: &ItemTags) -> Result<()>;
+    /// Removes the tags named `names` from the item and updates `tags` once V7 has
+    /// accepted the change. Names the item is not tagged with are ignored by V7.
+    async fn remove_tags(&mut self, client: &C, names: &[&str]) -> Result<()>;
+    /// Re-processes the item from external storage, refreshing its thumbnails and
+    /// metadata after the underlying object was replaced
+    async fn resync(&self, client: &C) -> Result<()>;
+    /// Lists the workflow history of the item (stage changes, assignments etc.)
+    /// in the order returned by V7, following the pagination cursors.
+    async fn history(&self, client: &C) -> Result<Vec<ItemHistoryEvent>>;
+    /// The current annotations of the item as Darwin JSON, built from the annotations and
+    /// classes endpoints rather than a dataset export, see `JsonExportV2::from_item_annotations`
+    async fn export_json(&self, client: &C) -> Result<JsonExportV2>;
+    /// Fetches a small image of the slot `slot_name` for QC sampling rather than the full
+    /// resolution file, i.e. the thumbnail V7 renders for the slot. For a tiled slot the
+    /// preview also records its lowest resolution level.
+    ///
+    /// The tiles of a level are not stitched together as this crate decodes no images.
+    /// As with `AnnotationClassImage::download`, urls on the V7 API are fetched with `client`
+    /// and any other, pre-signed, url with `download_client`.
+    async fn fetch_preview(
+        &self,
+        client: &C,
+        download_client: &dyn PresignedUrlMethods,
+        slot_name: &str,
+    ) -> Result<SlotPreview>;
+    /// Sends the item to the discard stage of its workflow, see `WorkflowMethods::discard_item`
+    async fn discard(&self, client: &C, reason: Option<&str>) -> Result<SetStageResponse>;
+}
+
+#[cfg(feature = "client")]
+#[derive(Debug, Serialize)]
+struct RenameItemPayload<'a> {
+    name: &'a str,
+}
+
+#[cfg(feature = "client")]
+#[derive(Debug, Serialize)]
+struct ItemIdFilter<'a> {
+    item_ids: Vec<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    dataset_ids: Option<Vec<u32>>,
+}
+
+#[cfg(feature = "client")]
+#[derive(Debug, Serialize)]
+struct SetItemPathPayload<'a> {
+    filters: ItemIdFilter<'a>,
+    path: &'a str,
+}
+
+#[cfg(feature = "client")]
+#[derive(Debug, Serialize)]
+struct ItemTagsPayload<'a, T: Serialize> {
+    filters: ItemIdFilter<'a>,
+    tags: T,
+}
+
+#[cfg(feature = "client")]
+#[derive(Debug, Serialize)]
+struct ResyncItemPayload<'a> {
+    filters: ItemIdFilter<'a>,
+}
+
+#[cfg(feature = "client")]
+#[async_trait]
+impl<C> DatasetItemMethods<C> for DatasetItemV2
+where
+    C: V7Methods + std::marker::Sync,
+{
+    async fn list_sections(&self, client: &C, slot_name: &str) -> Result<Vec<ItemSection>> {
+        let item_id = self.id.as_ref().context("Dataset item has no Id")?;
+        let endpoint = format!(
+            "v2/teams/{}/items/{}/slots/{}/sections",
+            client.team(),
+            item_id,
+            encode_path_segment(slot_name)
+        );
+        let mut sections =
+            list_all_pages::<C, ItemSectionPage>(client, &endpoint, SECTION_PAGE_SIZE).await?;
+
+        sections.sort_by_key(|section| section.section_index);
+        Ok(sections)
+    }
+
+    async fn list_frames(&self, client: &C, slot_name: &str) -> Result<Vec<ItemFrame>> {
+        let slot_type = self
+            .slots
+            .iter()
+            .flatten()
+            .find(|slot| slot.slot_name.as_deref() == Some(slot_name))
+            .and_then(|slot| slot.item_slot_type.as_ref());
+        if let Some(slot_type) = slot_type {
+            if *slot_type != DatasetItemTypes::Video {
+                bail!("Slot {slot_name} is a {slot_type} rather than a video");
+            }
+        }
+
+        let sections = self.list_sections(client, slot_name).await?;
+        Ok(sections
+            .into_iter()
+            .enumerate()
+            .map(|(position, section)| ItemFrame {
+                frame_index: section.section_index.unwrap_or(position),
+                section,
+            })
+            .collect())
+    }
+
+    async fn list_annotations(&self, client: &C) -> Result<Vec<ItemAnnotation>> {
+        let response = client
+            .get(&format!(
+                "v2/teams/{}/items/{}/annotations",
+                client.team(),
+                self.id.as_ref().context("Dataset item has no Id")?
+            ))
+            .await?;
+        expect_http_ok!(response, Vec<ItemAnnotation>)
+    }
+
+    async fn rename(&mut self, client: &C, new_name: &str) -> Result<()> {
+        let response = client
+            .put(
+                &format!(
+                    "v2/teams/{}/items/{}",
+                    client.team(),
+                    self.id.as_ref().context("Dataset item has no Id")?
+                ),
+                Some(&RenameItemPayload { name: new_name }),
+            )
+            .await?;
+        if !response.status().is_success() {
+            bail!(HttpStatusError::from_response(response).await)
+        }
+
+        self.name = Some(new_name.to_string());
+        Ok(())
+    }
+
+    async fn set_path(&mut self, client: &C, new_path: &str) -> Result<()> {
+        let payload = SetItemPathPayload {
+            filters: ItemIdFilter {
+                item_ids: vec![self.id.as_ref().context("Dataset item has no Id")?],
+                dataset_ids: self.dataset_id.map(|id| vec![id]),
+            },
+            path: new_path,
+        };
+        let response = client
+            .put(
+                &format!("v2/teams/{}/items/path", client.team()),
+                Some(&payload),
+            )
+            .await?;
+        if !response.status().is_success() {
+            bail!(HttpStatusError::from_response(response).await)
+        }
+
+        self.path = Some(new_path.to_string());
+        Ok(())
+    }
+
+    async fn add_tags(&mut self, client: &C, tags: &ItemTags) -> Result<()> {
+        let payload = ItemTagsPayload {
+            filters: ItemIdFilter {
+                item_ids: vec![self.id.as_ref().context("Dataset item has no Id")?],
+                dataset_ids: self.dataset_id.map(|id| vec![id]),
+            },
+            tags,
+        };
+        let response = client
+            .post(&format!("v2/teams/{}/items/tags", client.team()), &payload)
+            .await?;
+        if !response.status().is_success() {
+            bail!(HttpStatusError::from_response(response).await)
+        }
+
+        for tag in tags {
+            self.tags.insert(&tag.name, tag.attribute.as_deref());
+        }
+        Ok(())
+    }
+
+    async fn remove_tags(&mut self, client: &C, names: &[&str]) -> Result<()> {
+        let payload = ItemTagsPayload {
+            filters: ItemIdFilter {
+                item_ids: vec![self.id.as_ref().context("Dataset item has no Id")?],
+                dataset_ids: self.dataset_id.map(|id| vec![id]),
+            },
+            tags: names,
+        };
+        let response = client
+            .delete(
+                &format!("v2/teams/{}/items/tags", client.team()),
+                Some(&payload),
+            )
+            .await?;
+        if !response.status().is_success() {
+            bail!(HttpStatusError::from_response(response).await)
+        }
+
+        for name in names {
+            self.tags.remove(name);
+        }
+        Ok(())
+    }
+
+    async fn resync(&self, client: &C) -> Result<()> {
+        let payload = ResyncItemPayload {
+            filters: ItemIdFilter {
+                item_ids: vec![self.id.as_ref().context("Dataset item has no Id")?],
+                dataset_ids: self.dataset_id.map(|id| vec![id]),
+            },
+        };
+        let response = client
+            .post(
+                &format!("v2/teams/{}/items/reprocess", client.team()),
+                &payload,
+            )
+            .await?;
+        if !response.status().is_success() {
+            bail!(HttpStatusError::from_response(response).await)
+        }
+        Ok(())
+    }
+
+    async fn export_json(&self, client: &C) -> Result<JsonExportV2> {
+        let annotations = self.list_annotations(client).await?;
+        let team = Team {
+            slug: client.team().to_string(),
+            ..Default::default()
+        };
+        let class_names: HashMap<u32, String> = team
+            .list_annotation_classes(client)
+            .await?
+            .annotation_classes
+            .into_iter()
+            .flatten()
+            .filter_map(|class| Some((class.id?.0, class.name?)))
+            .collect();
+        JsonExportV2::from_item_annotations(self, &annotations, &class_names)
+    }
+
+    async fn fetch_preview(
+        &self,
+        client: &C,
+        download_client: &dyn PresignedUrlMethods,
+        slot_name: &str,
+    ) -> Result<SlotPreview> {
+        let level = self
+            .slots
+            .iter()
+            .flatten()
+            .find(|slot| slot.slot_name.as_deref() == Some(slot_name))
+            .and_then(|slot| slot.metadata.as_ref())
+            .and_then(ItemSlotLevel::lowest_resolution_level)
+            .map(|(index, level)| (index, level.clone()));
+
+        let sections = self.list_sections(client, slot_name).await?;
+        let url = sections
+            .iter()
+            .find_map(|section| section.thumbnail_url.as_ref())
+            .with_context(|| format!("Slot {slot_name} has no thumbnail"))?;
+        let response = match url.strip_prefix(client.api_endpoint()) {
+            Some(endpoint) => client.get(endpoint).await?,
+            None => download_client.get_url(url, HeaderMap::new()).await?,
+        };
+        if !response.status().is_success() {
+            bail!(HttpStatusError::from_response(response).await);
+        }
+        let content_type = response
+            .headers()
+            .get(reqwest::header::CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .map(String::from);
+
+        Ok(SlotPreview {
+            slot_name: slot_name.to_string(),
+            level,
+            content_type,
+            bytes: response.bytes().await?.to_vec(),
+        })
+    }
+
+    async fn discard(&self, client: &C, reason: Option<&str>) -> Result<SetStageResponse> {
+        let workflow_id = self
+            .workflow_data
+            .as_ref()
+            .and_then(|data| data.workflow_id.as_deref())
+            .context("Item is missing workflow data")?;
+        let workflow = WorkflowV2::get_workflow(client, &WorkflowId::from(workflow_id)).await?;
+        workflow.discard_item(client, self, reason).await
+    }
+
+    async fn history(&self, client: &C) -> Result<Vec<ItemHistoryEvent>> {
+        let item_id = self.id.as_ref().context("Dataset item has no Id")?;
+        let endpoint = format!("v2/teams/{}/items/{}/history", client.team(), item_id);
+        list_all_pages::<C, ItemHistoryPage>(client, &endpoint, HISTORY_PAGE_SIZE).await
+    }
+}
+
+#[cfg(all(test, feature = "client", feature = "fake"))]
+mod test_client_calls {
+    use super::*;
+    use crate::client::V7Client;
+    use serde_json::json;
+    use wiremock::matchers::{body_json, method, path, query_param};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    #[tokio::test]
+    async fn test_list_sections() {
+        let mock_server = MockServer::start().await;
+        let item = DatasetItemV2 {
+            id: Some("item-1".to_string()),
+            ..Default::default()
+        };
+
+        Mock::given(method("GET"))
+            .and(path(
+                "/v2/teams/some-team/items/item-1/slots/slot%20a/sections",
+            ))
+            .and(query_param("page[from]", "cursor+1/="))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "sections": [{"section_index": 0, "url": "https://page-0"}],
+                "page": {"count": 2, "previous": "cursor-0"}
+            })))
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path(
+                "/v2/teams/some-team/items/item-1/slots/slot%20a/sections",
+            ))
+            .and(query_param("page[size]", "500"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "sections": [{"section_index": 1, "url": "https://page-1"}],
+                "page": {"count": 2, "next": "cursor+1/=", "previous": null}
+            })))
+            .mount(&mock_server)
+            .await;
+
+        let client: V7Client = V7Client::new(
+            format!("{}/", mock_server.uri()),
+            "api-key".to_string(),
+            "some-team".to_string(),
+        )
+        .expect("Failed to get V7Client");
+
+        let sections = item
+            .list_sections(&client, "slot a")
+            .await
+            .expect("Failed to list sections");
+
+        assert_eq!(sections.len(), 2);
+        assert_eq!(sections[0].section_index, Some(0));
+        assert_eq!(sections[0].url, Some("https://page-0".to_string()));
+        assert_eq!(sections[1].url, Some("https://page-1".to_string()));
+    }
+
+    #[tokio::test]
+    async fn test_fetch_preview() {
+        let mock_server = MockServer::start().await;
+        let item: DatasetItemV2 = serde_json::from_value(json!({
+            "id": "item-1",
+            "slots": [{
+                "slot_name": "0",
+                "type": "image",
+                "metadata": {
+                    "base_key": "scans/",
+                    "levels": {
+                        "0": {"format": "jpeg", "pixel_ratio": 1, "tile_height": 256,
+                              "tile_width": 256, "x_tiles": 40, "y_tiles": 30},
+                        "1": {"format": "jpeg", "pixel_ratio": 16, "tile_height": 256,
+                              "tile_width": 256, "x_tiles": 3, "y_tiles": 2}
+                    }
+                }
+            }]
+        }))
+        .unwrap();
+
+        Mock::given(method("GET"))
+            .and(path("/v2/teams/some-team/items/item-1/slots/0/sections"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "sections": [{
+                    "section_index": 0,
+                    "thumbnail_url": format!("{}/v2/teams/some-team/files/file-1/thumbnail", mock_server.uri())
+                }],
+                "page": {"count": 1}
+            })))
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/v2/teams/some-team/files/file-1/thumbnail"))
+            .respond_with(
+                ResponseTemplate::new(200).set_body_raw(vec![0xff, 0xd8, 0xff], "image/jpeg"),
+            )
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let client: V7Client = V7Client::new(
+            format!("{}/", mock_server.uri()),
+            "api-key".to_string(),
+            "some-team".to_string(),
+        )
+        .expect("Failed to get V7Client");
+
+        let preview = item
+            .fetch_preview(&client, &reqwest::Client::new(), "0")
+            .await
+            .expect("Failed to fetch preview");
+        assert_eq!(preview.slot_name, "0");
+        assert_eq!(preview.content_type.as_deref(), Some("image/jpeg"));
+        assert_eq!(preview.bytes, vec![0xff, 0xd8, 0xff]);
+        let (index, level) = preview.level.unwrap();
+        assert_eq!((index, level.pixel_ratio), (1, 16));
+    }
+
+    #[tokio::test]
+    async fn test_list_frames() {
+        let mock_server = MockServer::start().await;
+        let mut item = DatasetItemV2 {
+            id: Some("item-1".to_string()),
+            slots: vec![Some(ItemSlot {
+                slot_name: Some("video".to_string()),
+                item_slot_type: Some(DatasetItemTypes::Video),
+                ..Faker.fake()
+            })],
+            ..Default::default()
+        };
+
+        Mock::given(method("GET"))
+            .and(path(
+                "/v2/teams/some-team/items/item-1/slots/video/sections",
+            ))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "sections": [
+                    {"section_index": 1, "thumbnail_url": "https://frame-1", "status": "error"},
+                    {"section_index": 0, "thumbnail_url": "https://frame-0", "status": "processed"}
+                ],
+                "page": {"count": 2, "next": null, "previous": null}
+            })))
+            .mount(&mock_server)
+            .await;
+
+        let client: V7Client = V7Client::new(
+            format!("{}/", mock_server.uri()),
+            "api-key".to_string(),
+            "some-team".to_string(),
+        )
+        .expect("Failed to get V7Client");
+
+        let frames = item
+            .list_frames(&client, "video")
+            .await
+            .expect("Failed to list frames");
+        let problems: Vec<usize> = frames
+            .iter()
+            .filter(|frame| frame.has_error())
+            .map(|frame| frame.frame_index)
+            .collect();
+        assert_eq!(
+            frames[0].section.status,
+            Some(SectionStatus::Processed.into())
+        );
+        assert_eq!(problems, vec![1]);
+
+        item.slots[0].as_mut().unwrap().item_slot_type = Some(DatasetItemTypes::Pdf);
+        assert!(item.list_frames(&client, "video").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_export_json() {
+        let mock_server = MockServer::start().await;
+        let item = DatasetItemV2 {
+            id: Some("item-1".to_string()),
+            name: Some("biopsy.png".to_string()),
+            path: Some("/".to_string()),
+            slots: vec![Some(ItemSlot {
+                file_name: Some("biopsy.png".to_string()),
+                slot_name: Some("0".to_string()),
+                item_slot_type: Some(DatasetItemTypes::Image),
+                metadata: None,
+                storage_key: None,
+                ..Faker.fake()
+            })],
+            ..Default::default()
+        };
+
+        Mock::given(method("GET"))
+            .and(path("/v2/teams/some-team/items/item-1/annotations"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!([
+                {
+                    "id": "a",
+                    "annotation_class_id": 1,
+                    "context_keys": {"slot_names": ["0"]},
+                    "data": {
+                        "polygon": {"paths": [[{"x": 0, "y": 0}, {"x": 1, "y": 0}, {"x": 1, "y": 1}]]},
+                        "attributes": {"attributes": ["mitotic"]}
+                    }
+                },
+                {"id": "b", "annotation_class_id": 2, "context_keys": {"slot_names": ["0"]}, "data": {"tag": {}}}
+            ])))
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/teams/some-team/annotation_classes"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "annotation_classes": [
+                    {"id": 1, "name": "Tumour"},
+                    {"id": 2, "name": "Reviewed"}
+                ],
+                "type_counts": []
+            })))
+            .mount(&mock_server)
+            .await;
+
+        let client: V7Client = V7Client::new(
+            format!("{}/", mock_server.uri()),
+            "api-key".to_string(),
+            "some-team".to_string(),
+        )
+        .expect("Failed to get V7Client");
+
+        let export = item
+            .export_json(&client)
+            .await
+            .expect("Failed to export item");
+        assert_eq!(export.version, "2.0");
+        assert_eq!(export.item.name.as_deref(), Some("biopsy.png"));
+        let slot = export.item.slots[0].as_ref().unwrap();
+        assert_eq!(slot.source_files[0].file_name, "biopsy.png");
+
+        let polygon = &export.annotations[0];
+        assert_eq!(polygon.name, "Tumour");
+        assert_eq!(polygon.annotation_type(), Some("polygon"));
+        assert_eq!(polygon.slot_names, vec!["0".to_string()]);
+        assert_eq!(
+            polygon.attributes.as_ref().unwrap().attributes,
+            vec!["mitotic".to_string()]
+        );
+        assert_eq!(export.annotations[1].name, "Reviewed");
+        assert_eq!(export.annotations[1].annotation_type(), Some("tag"));
+    }
+
+    #[tokio::test]
+    async fn test_history() {
+        let mock_server = MockServer::start().await;
+        let item = DatasetItemV2 {
+            id: Some("item-1".to_string()),
+            ..Default::default()
+        };
+
+        Mock::given(method("GET"))
+            .and(path("/v2/teams/some-team/items/item-1/history"))
+            .and(query_param("page[from]", "cursor+1/="))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "events": [{"id": "e2", "type": "assigned", "assignee_id": 7}],
+                "page": {"count": 2, "previous": "cursor-0"}
+            })))
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/v2/teams/some-team/items/item-1/history"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "events": [{"id": "e1", "type": "created"}],
+                "page": {"count": 2, "next": "cursor+1/=", "previous": null}
+            })))
+            .mount(&mock_server)
+            .await;
+
+        let client: V7Client = V7Client::new(
+            format!("{}/", mock_server.uri()),
+            "api-key".to_string(),
+            "some-team".to_string(),
+        )
+        .expect("Failed to get V7Client");
+
+        let history = item
+            .history(&client)
+            .await
+            .expect("Failed to get item history");
+        let ids: Vec<Option<String>> = history.into_iter().map(|event| event.id).collect();
+        assert_eq!(ids, vec![Some("e1".to_string()), Some("e2".to_string())]);
+    }
+
+    #[tokio::test]
+    async fn test_rename_and_set_path() {
+        let mock_server = MockServer::start().await;
+        let mut item = DatasetItemV2 {
+            id: Some("item-1".to_string()),
+            dataset_id: Some(3),
+            name: Some("a1b2c3.svs".to_string()),
+            path: Some("/".to_string()),
+            ..Default::default()
+        };
+
+        Mock::given(method("PUT"))
+            .and(path("/v2/teams/some-team/items/item-1"))
+            .and(body_json(json!({"name": "AU-1234.svs"})))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({})))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("PUT"))
+            .and(path("/v2/teams/some-team/items/path"))
+            .and(body_json(json!({
+                "filters": {"item_ids": ["item-1"], "dataset_ids": [3]},
+                "path": "/accessioned"
+            })))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({})))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let client: V7Client = V7Client::new(
+            format!("{}/", mock_server.uri()),
+            "api-key".to_string(),
+            "some-team".to_string(),
+        )
+        .expect("Failed to get V7Client");
+
+        item.rename(&client, "AU-1234.svs")
+            .await
+            .expect("Failed to rename item");
+        item.set_path(&client, "/accessioned")
+            .await
+            .expect("Failed to set item path");
+
+        assert_eq!(item.name, Some("AU-1234.svs".to_string()));
+        assert_eq!(item.path, Some("/accessioned".to_string()));
+    }
+
+    #[tokio::test]
+    async fn test_resync() {
+        let mock_server = MockServer::start().await;
+        let item = DatasetItemV2 {
+            id: Some("item-1".to_string()),
+            dataset_id: Some(3),
+            ..Default::default()
+        };
+
+        Mock::given(method("POST"))
+            .and(path("/v2/teams/some-team/items/reprocess"))
+            .and(body_json(json!({
+                "filters": {"item_ids": ["item-1"], "dataset_ids": [3]}
+            })))
+            .respond_with(ResponseTemplate::new(204))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let client: V7Client = V7Client::new(
+            format!("{}/", mock_server.uri()),
+            "api-key".to_string(),
+            "some-team".to_string(),
+        )
+        .expect("Failed to get V7Client");
+
+        item.resync(&client).await.expect("Failed to resync item");
+
+        DatasetItemV2::default()
+            .resync(&client)
+            .await
+            .expect_err("Dataset item has no Id");
+    }
+
+    #[tokio::test]
+    async fn test_add_and_remove_tags() {
+        let mock_server = MockServer::start().await;
+        let mut item: DatasetItemV2 = serde_json::from_value(json!({
+            "id": "item-1",
+            "dataset_id": 3,
+            "tags": ["biopsy", null]
+        }))
+        .expect("Failed to parse item");
+
+        Mock::given(method("POST"))
+            .and(path("/v2/teams/some-team/items/tags"))
+            .and(body_json(json!({
+                "filters": {"item_ids": ["item-1"], "dataset_ids": [3]},
+                "tags": {"stain": "H&E"}
+            })))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({})))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("DELETE"))
+            .and(path("/v2/teams/some-team/items/tags"))
+            .and(body_json(json!({
+                "filters": {"item_ids": ["item-1"], "dataset_ids": [3]},
+                "tags": ["biopsy"]
+            })))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({})))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let client: V7Client = V7Client::new(
+            format!("{}/", mock_server.uri()),
+            "api-key".to_string(),
+            "some-team".to_string(),
+        )
+        .expect("Failed to get V7Client");
+
+        let mut tags = ItemTags::new();
+        tags.insert("stain", Some("H&E"));
+        item.add_tags(&client, &tags)
+            .await
+            .expect("Failed to add tags");
+        item.remove_tags(&client, &["biopsy"])
+            .await
+            .expect("Failed to remove tags");
+
+        assert_eq!(item.tags.names(), vec!["stain"]);
+        assert_eq!(
+            item.tags
+                .get("stain")
+                .and_then(|tag| tag.attribute.as_deref()),
+            Some("H&E")
+        );
+    }
+}
+
 #[cfg(test)]
 mod test_serde {
     use super::*;
 
+    #[test]
+    fn test_item_type_from_file_name() {
+        assert_eq!(
+            DatasetItemTypes::from_file_name("image.PNG"),
+            Some(DatasetItemTypes::Image)
+        );
+        assert_eq!(
+            DatasetItemTypes::from_file_name("slide.svs"),
+            Some(DatasetItemTypes::TiledImage)
+        );
+        assert_eq!(DatasetItemTypes::from_file_name("notes.txt"), None);
+        assert_eq!(DatasetItemTypes::from_file_name("no-extension"), None);
+    }
+
+    #[test]
+    fn test_existing_simple_item_builder() {
+        let builder = ExistingSimpleItemBuilder::new()
+            .key_prefix("scans/batch-3/")
+            .path("/batch-3");
+        let objects = vec![
+            StorageObject {
+                key: "scans/batch-3/au1/slide.svs".to_string(),
+                size_bytes: 1024,
+                width: Some(100),
+                height: Some(50),
+                ..Default::default()
+            },
+            StorageObject {
+                key: "scans/batch-3/au1/slide.svs_thumbnail.jpg".to_string(),
+                ..Default::default()
+            },
+            StorageObject {
+                key: "scans/batch-3/image.png".to_string(),
+                thumbnail_key: Some("thumbs/image.jpg".to_string()),
+                ..Default::default()
+            },
+            StorageObject {
+                key: "scans/batch-4/image.png".to_string(),
+                ..Default::default()
+            },
+        ];
+
+        let items = builder.build_all(objects).unwrap();
+        assert_eq!(items.len(), 2);
+
+        assert_eq!(items[0].name, "slide.svs");
+        assert_eq!(items[0].path, "/batch-3/au1");
+        let slot = &items[0].slots[0];
+        assert_eq!(slot.slot_name, "0");
+        assert_eq!(slot.slot_type, DatasetItemTypes::TiledImage);
+        assert_eq!(
+            slot.storage_thumbnail_key,
+            "scans/batch-3/au1/slide.svs_thumbnail.jpg"
+        );
+        assert_eq!(slot.metadata.base_key, "scans/batch-3/au1/");
+        assert_eq!(slot.sections.len(), 1);
+
+        assert_eq!(items[1].path, "/batch-3");
+        assert_eq!(items[1].slots[0].storage_thumbnail_key, "thumbs/image.jpg");
+        assert!(items[1].slots[0].sections.is_empty());
+
+        builder
+            .build(&StorageObject {
+                key: "scans/batch-3/notes.txt".to_string(),
+                ..Default::default()
+            })
+            .expect_err("Unable to infer the item type of scans/batch-3/notes.txt");
+    }
+
+    #[cfg(feature = "fake")]
     #[test]
     fn test_levels_dummy() {
         let level: Levels = Faker.fake();
@@ -433,6 +1988,61 @@ mod test_serde {
         assert_eq!(&image_level_deser, &contents.replace(['\n', ' '], ""));
     }
 
+    #[test]
+    fn test_item_tags_ser_deser() {
+        let tags: ItemTags = serde_json::from_str(r#"["a", null, "b", "a"]"#).unwrap();
+        assert_eq!(tags.names(), vec!["a", "b"]);
+        assert_eq!(
+            serde_json::to_value(&tags).unwrap(),
+            serde_json::json!(["a", "b"])
+        );
+
+        let mut tags: ItemTags =
+            serde_json::from_str(r#"{"stain": "H&E", "biopsy": null}"#).unwrap();
+        assert_eq!(tags.names(), vec!["stain", "biopsy"]);
+        assert_eq!(
+            serde_json::to_value(&tags).unwrap(),
+            serde_json::json!({"stain": "H&E", "biopsy": null})
+        );
+
+        tags.remove("stain");
+        assert!(!tags.has_attributes());
+        assert_eq!(
+            serde_json::to_value(&tags).unwrap(),
+            serde_json::json!(["biopsy"])
+        );
+
+        let tags: ItemTags = serde_json::from_str("null").unwrap();
+        assert!(tags.is_empty());
+    }
+
+    #[test]
+    fn test_lowest_resolution_level() {
+        let level = |pixel_ratio, x_tiles| ImageLevel {
+            format: "jpeg".to_string(),
+            pixel_ratio,
+            tile_height: 256,
+            tile_width: 256,
+            x_tiles,
+            y_tiles: 1,
+        };
+        let slot_level = ItemSlotLevel {
+            levels: HashMap::from([(0, level(1, 40)), (1, level(8, 5)), (2, level(8, 1))]),
+            base_key: "scans/".to_string(),
+            height: None,
+            width: None,
+        };
+        let (index, lowest) = slot_level.lowest_resolution_level().unwrap();
+        assert_eq!(index, 2);
+        assert_eq!(lowest.tile_count(), 1);
+
+        let no_levels = ItemSlotLevel {
+            levels: HashMap::new(),
+            ..slot_level
+        };
+        assert!(no_levels.lowest_resolution_level().is_none());
+    }
+
     #[test]
     fn test_image_level_ser_deser_error() {
         // Test serialization
@@ -575,9 +2185,16 @@ mod test_serde {
 
         let ser_item: DatasetItemV2 = serde_json::from_str(contents).unwrap();
 
-        assert_eq!(ser_item.status, Some(DatasetItemStatus::New));
+        assert_eq!(ser_item.status, Some(DatasetItemStatus::New.into()));
         assert_eq!(ser_item.dataset_id, Some(657106));
         assert_eq!(ser_item.slots.len(), 1);
+        #[cfg(feature = "timestamps")]
+        assert_eq!(
+            ser_item
+                .updated_timestamp()
+                .map(|timestamp| timestamp.to_string()),
+            Some("2023-07-18T01:56:05Z".to_string())
+        );
         let levels = &ser_item
             .slots
             .first()
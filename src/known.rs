@@ -0,0 +1,114 @@
+//! A wrapper for enum fields whose set of values V7 extends over time, so that a value
+//! added by V7 is preserved rather than failing the deserialization of the whole response.
+
+#[cfg(feature = "fake")]
+use fake::{Dummy, Fake, Faker};
+use serde::de::{value::StrDeserializer, DeserializeOwned, IntoDeserializer};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt::{self, Display};
+
+/// Either a value of the enum `T` or, for a value `T` does not know, the raw string.
+///
+/// (De)serializes as the string of the value in both cases, so a value that is not yet
+/// known survives a read-modify-write unchanged.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum MaybeKnown<T> {
+    Known(T),
+    Unknown(String),
+}
+
+impl<T> MaybeKnown<T> {
+    pub fn known(&self) -> Option<&T> {
+        match self {
+            Self::Known(value) => Some(value),
+            Self::Unknown(_) => None,
+        }
+    }
+
+    pub fn is_known(&self) -> bool {
+        matches!(self, Self::Known(_))
+    }
+}
+
+impl<T: DeserializeOwned> MaybeKnown<T> {
+    /// Parses `raw` as it would be deserialized from a response
+    pub fn parse(raw: &str) -> Self {
+        let deserializer: StrDeserializer<serde::de::value::Error> = raw.into_deserializer();
+        match T::deserialize(deserializer) {
+            Ok(value) => Self::Known(value),
+            Err(_) => Self::Unknown(raw.to_string()),
+        }
+    }
+}
+
+impl<T: Default> Default for MaybeKnown<T> {
+    fn default() -> Self {
+        Self::Known(T::default())
+    }
+}
+
+impl<T> From<T> for MaybeKnown<T> {
+    fn from(value: T) -> Self {
+        Self::Known(value)
+    }
+}
+
+impl<T: Display> Display for MaybeKnown<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Known(value) => write!(f, "{value}"),
+            Self::Unknown(raw) => write!(f, "{raw}"),
+        }
+    }
+}
+
+impl<T: Serialize> Serialize for MaybeKnown<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            Self::Known(value) => value.serialize(serializer),
+            Self::Unknown(raw) => serializer.serialize_str(raw),
+        }
+    }
+}
+
+impl<'de, T: DeserializeOwned> Deserialize<'de> for MaybeKnown<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let raw = String::deserialize(deserializer)?;
+        Ok(Self::parse(&raw))
+    }
+}
+
+#[cfg(feature = "fake")]
+impl<T: Dummy<Faker>> Dummy<Faker> for MaybeKnown<T> {
+    fn dummy_with_rng<R: rand::Rng + ?Sized>(config: &Faker, rng: &mut R) -> Self {
+        Self::Known(config.fake_with_rng(rng))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::item::DatasetItemStatus;
+
+    #[test]
+    fn test_maybe_known() {
+        let known: MaybeKnown<DatasetItemStatus> = serde_json::from_str(r#""complete""#).unwrap();
+        assert_eq!(known, MaybeKnown::Known(DatasetItemStatus::Complete));
+        assert_eq!(known.known(), Some(&DatasetItemStatus::Complete));
+
+        let unknown: MaybeKnown<DatasetItemStatus> = serde_json::from_str(r#""paused""#).unwrap();
+        assert_eq!(unknown, MaybeKnown::Unknown("paused".to_string()));
+        assert!(!unknown.is_known());
+        assert_eq!(serde_json::to_string(&unknown).unwrap(), r#""paused""#);
+        assert_eq!(unknown.to_string(), "paused");
+
+        assert_eq!(
+            serde_json::to_string(&known).unwrap(),
+            serde_json::to_string(&DatasetItemStatus::Complete).unwrap()
+        );
+        assert_eq!(
+            MaybeKnown::<DatasetItemStatus>::parse("review"),
+            MaybeKnown::Known(DatasetItemStatus::Review)
+        );
+    }
+}
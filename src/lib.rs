@@ -1,13 +1,41 @@
+pub mod analytics;
 pub mod annotation;
+#[cfg(feature = "cassette")]
+pub mod cassette;
 pub mod classes;
+#[cfg(feature = "client")]
 pub mod client;
 pub mod comment;
+#[cfg(feature = "client")]
+mod compression;
 pub mod config;
+pub mod convert;
 pub mod datasets;
+#[cfg(feature = "client")]
+pub mod debug;
+pub mod diff;
 pub mod export;
 pub mod filter;
+pub mod geometry;
+pub mod ids;
 pub mod imports;
 pub mod item;
+pub mod known;
+pub mod manifest;
+pub mod maybe;
+#[cfg(all(feature = "client", feature = "reports"))]
+pub mod ops;
+#[cfg(feature = "parquet")]
+mod parquet;
+pub mod progress;
+pub mod reports;
+#[cfg(feature = "client")]
+pub mod response;
+#[cfg(feature = "tower")]
+pub mod service;
+#[cfg(feature = "client")]
+pub mod stream;
 pub mod team;
 pub mod utils;
+pub mod webhooks;
 pub mod workflow;
@@ -0,0 +1,378 @@
+//! Registration manifests, files listing the items to register into a dataset one row
+//! per item, read from JSONL or CSV into `ExistingSimpleItem`s or `NewSimpleItem`s.
+//!
+//! The columns, or fields of each JSON object, are `name`, `path`, `storage_key`, `type`,
+//! `width`, `height`, `size_bytes`, `thumbnail_key` and `tags`. Only `name` is required by
+//! every row. In a CSV the tags are separated by `;`, in JSONL they may also be an array.
+//!
+//! A row that fails to parse or convert is reported with its line rather than failing
+//! the whole manifest, so the rest can be registered while the bad rows are fixed.
+
+use crate::item::{
+    DatasetItemTypes, ExistingSimpleItem, ExistingSimpleItemBuilder, NewSimpleItem, StorageObject,
+};
+use anyhow::{bail, Context, Result};
+#[cfg(feature = "reports")]
+use csv_async::AsyncReaderBuilder;
+#[cfg(feature = "reports")]
+use futures::{io::AsyncRead, StreamExt};
+use serde::{Deserialize, Deserializer};
+use std::collections::HashMap;
+use std::fmt::{self, Display};
+
+/// Separates the tags of a row in a CSV manifest
+pub const TAG_SEPARATOR: char = ';';
+
+/// A row of a manifest
+#[derive(Debug, Default, Clone, PartialEq, Eq, Deserialize)]
+pub struct ManifestRow {
+    pub name: String,
+    /// Dataset folder of the item, defaults to `/`
+    #[serde(default)]
+    pub path: Option<String>,
+    /// Key of the file in external storage, required to register existing items
+    #[serde(default)]
+    pub storage_key: Option<String>,
+    /// Item type e.g. `image` or `tiled_image`, inferred from the name when not given
+    #[serde(default, rename = "type")]
+    pub item_type: Option<String>,
+    #[serde(default)]
+    pub width: Option<u32>,
+    #[serde(default)]
+    pub height: Option<u32>,
+    #[serde(default)]
+    pub size_bytes: Option<u32>,
+    #[serde(default)]
+    pub thumbnail_key: Option<String>,
+    /// Only registered with new items, existing items are tagged with `DatasetItemMethods::add_tags`
+    #[serde(default, deserialize_with = "deserialize_tags")]
+    pub tags: Vec<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Tags {
+    Separated(String),
+    List(Vec<String>),
+}
+
+fn deserialize_tags<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<String>, D::Error> {
+    Ok(match Option::<Tags>::deserialize(deserializer)? {
+        Some(Tags::Separated(tags)) => tags
+            .split(TAG_SEPARATOR)
+            .map(str::trim)
+            .filter(|tag| !tag.is_empty())
+            .map(String::from)
+            .collect(),
+        Some(Tags::List(tags)) => tags,
+        None => vec![],
+    })
+}
+
+/// A row of a manifest that could not be parsed or converted into an item
+#[derive(Debug)]
+pub struct ManifestError {
+    /// Line of the row in the manifest, starting at 1 and counting the CSV header
+    pub line: usize,
+    /// Name of the item, `None` if the row could not be parsed
+    pub name: Option<String>,
+    pub error: anyhow::Error,
+}
+
+impl Display for ManifestError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Line {}", self.line)?;
+        if let Some(name) = self.name.as_ref() {
+            write!(f, " ({name})")?;
+        }
+        write!(f, ": {:#}", self.error)
+    }
+}
+
+impl std::error::Error for ManifestError {}
+
+/// The parsed rows of a manifest, with their lines, and the rows that failed to parse
+#[derive(Debug, Default)]
+pub struct Manifest {
+    pub rows: Vec<(usize, ManifestRow)>,
+    pub errors: Vec<ManifestError>,
+}
+
+/// The items converted from the rows of a manifest, the errors include the rows of the
+/// manifest that failed to parse
+#[derive(Debug)]
+pub struct ManifestItems<T> {
+    pub items: Vec<T>,
+    pub errors: Vec<ManifestError>,
+}
+
+impl<T> ManifestItems<T> {
+    pub fn is_complete(&self) -> bool {
+        self.errors.is_empty()
+    }
+
+    /// The items, or the first error if any row failed
+    pub fn into_result(self) -> Result<Vec<T>> {
+        match self.errors.into_iter().next() {
+            Some(error) => Err(error.into()),
+            None => Ok(self.items),
+        }
+    }
+}
+
+impl Manifest {
+    /// Parses a JSONL manifest, one JSON object per line. Blank lines are skipped.
+    pub fn from_jsonl(contents: &str) -> Self {
+        let mut manifest = Self::default();
+        for (index, line) in contents.lines().enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            let row =
+                serde_path_to_error::deserialize(&mut serde_json::Deserializer::from_str(line));
+            manifest.push(index + 1, row.map_err(anyhow::Error::from));
+        }
+        manifest
+    }
+
+    /// Parses a CSV manifest with a header row naming the columns
+    #[cfg(feature = "reports")]
+    pub async fn from_csv<R>(reader: R) -> Self
+    where
+        R: AsyncRead + Unpin + Send,
+    {
+        let mut manifest = Self::default();
+        let mut deserializer = AsyncReaderBuilder::new()
+            .has_headers(true)
+            .trim(csv_async::Trim::All)
+            .create_deserializer(reader);
+        let mut records = deserializer.deserialize::<ManifestRow>();
+        let mut line = 1;
+        while let Some(row) = records.next().await {
+            line += 1;
+            manifest.push(line, row.map_err(anyhow::Error::from));
+        }
+        manifest
+    }
+
+    /// Parses the manifest at `path` as CSV if its extension is `csv`, otherwise as JSONL.
+    /// Note that the file is read with blocking IO.
+    #[cfg(feature = "reports")]
+    pub async fn from_path(path: &std::path::Path) -> Result<Self> {
+        let contents =
+            std::fs::read(path).with_context(|| format!("Unable to read {}", path.display()))?;
+        if path
+            .extension()
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("csv"))
+        {
+            Ok(Self::from_csv(futures::io::Cursor::new(contents)).await)
+        } else {
+            Ok(Self::from_jsonl(&String::from_utf8(contents)?))
+        }
+    }
+
+    fn push(&mut self, line: usize, row: Result<ManifestRow>) {
+        match row {
+            Ok(row) => self.rows.push((line, row)),
+            Err(error) => self.errors.push(ManifestError {
+                line,
+                name: None,
+                error,
+            }),
+        }
+    }
+
+    /// Converts the rows into items to register from read-only external storage,
+    /// with a single slot named `slot_name`
+    pub fn existing_items(&self, slot_name: &str) -> ManifestItems<ExistingSimpleItem> {
+        self.convert(|row| row.existing_item(slot_name))
+    }
+
+    /// Converts the rows into items to register for upload
+    pub fn new_items(&self) -> ManifestItems<NewSimpleItem> {
+        self.convert(ManifestRow::new_item)
+    }
+
+    fn convert<T>(&self, to_item: impl Fn(&ManifestRow) -> Result<T>) -> ManifestItems<T> {
+        let mut errors: Vec<ManifestError> = self
+            .errors
+            .iter()
+            .map(|error| ManifestError {
+                line: error.line,
+                name: None,
+                error: anyhow::anyhow!("{:#}", error.error),
+            })
+            .collect();
+        let mut items = Vec::with_capacity(self.rows.len());
+        for (line, row) in &self.rows {
+            match to_item(row) {
+                Ok(item) => items.push(item),
+                Err(error) => errors.push(ManifestError {
+                    line: *line,
+                    name: Some(row.name.clone()),
+                    error,
+                }),
+            }
+        }
+        errors.sort_by_key(|error| error.line);
+        ManifestItems { items, errors }
+    }
+}
+
+fn parse_item_type(item_type: &str) -> Result<DatasetItemTypes> {
+    Ok(
+        match item_type
+            .to_lowercase()
+            .replace(['_', '-', ' '], "")
+            .as_str()
+        {
+            "image" => DatasetItemTypes::Image,
+            "video" => DatasetItemTypes::Video,
+            "pdf" => DatasetItemTypes::Pdf,
+            "dicom" => DatasetItemTypes::Dicom,
+            "tiledimage" => DatasetItemTypes::TiledImage,
+            _ => bail!("Unknown item type {item_type}"),
+        },
+    )
+}
+
+impl ManifestRow {
+    fn checked_name(&self) -> Result<&str> {
+        let name = self.name.trim();
+        if name.is_empty() {
+            bail!("Item name is empty");
+        }
+        if name.contains('/') {
+            bail!("Item name {name} contains a /, use the path for folders");
+        }
+        Ok(name)
+    }
+
+    fn checked_path(&self) -> String {
+        match self
+            .path
+            .as_deref()
+            .map(|path| path.trim().trim_matches('/'))
+        {
+            None | Some("") => "/".to_string(),
+            Some(path) => format!("/{path}"),
+        }
+    }
+
+    fn checked_type(&self, file_name: &str) -> Result<DatasetItemTypes> {
+        match self
+            .item_type
+            .as_deref()
+            .filter(|typ| !typ.trim().is_empty())
+        {
+            Some(item_type) => parse_item_type(item_type.trim()),
+            None => DatasetItemTypes::from_file_name(file_name)
+                .with_context(|| format!("Unable to infer the item type of {file_name}")),
+        }
+    }
+
+    /// The item to register from read-only external storage, see `ExistingSimpleItemBuilder`
+    pub fn existing_item(&self, slot_name: &str) -> Result<ExistingSimpleItem> {
+        let name = self.checked_name()?;
+        let storage_key = self
+            .storage_key
+            .as_deref()
+            .map(str::trim)
+            .filter(|key| !key.is_empty())
+            .context("Row has no storage key")?;
+        if self.width.is_some() != self.height.is_some() {
+            bail!("Row has only one of width and height");
+        }
+        let file_name = storage_key.rsplit('/').next().unwrap_or(storage_key);
+        let object = StorageObject {
+            key: storage_key.to_string(),
+            size_bytes: self.size_bytes.unwrap_or_default(),
+            thumbnail_key: self.thumbnail_key.clone(),
+            width: self.width,
+            height: self.height,
+        };
+        let mut item = ExistingSimpleItemBuilder::new()
+            .slot_name(slot_name)
+            .item_type(self.checked_type(file_name)?)
+            .build(&object)?;
+        // The manifest names the item and its folder rather than the storage layout
+        item.name = name.to_string();
+        item.path = self.checked_path();
+        Ok(item)
+    }
+
+    /// The item to register for upload
+    pub fn new_item(&self) -> Result<NewSimpleItem> {
+        let name = self.checked_name()?;
+        Ok(NewSimpleItem {
+            as_frames: false,
+            extract_views: false,
+            fps: None,
+            metadata: HashMap::new(),
+            name: name.to_string(),
+            path: self.checked_path(),
+            tags: self.tags.iter().cloned().collect(),
+            typ: self.checked_type(name)?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_jsonl_manifest() {
+        let manifest = Manifest::from_jsonl(
+            r#"{"name": "slide-1.svs", "path": "batch-3/", "storage_key": "scans/s1.svs", "width": 100, "height": 50, "tags": ["biopsy"]}
+
+{"name": "notes", "storage_key": "scans/notes.txt"}
+{"name": "slide-2.svs", "tags": "a; b"
+{"name": "slide-3.svs", "type": "image", "width": "wide"}
+"#,
+        );
+        assert_eq!(manifest.rows.len(), 2);
+        assert_eq!(manifest.rows[0].1.tags, vec!["biopsy".to_string()]);
+        let lines: Vec<usize> = manifest.errors.iter().map(|error| error.line).collect();
+        assert_eq!(lines, vec![4, 5]);
+        assert!(manifest.errors[1].to_string().contains("width"));
+
+        let existing = manifest.existing_items("0");
+        assert_eq!(existing.items.len(), 1);
+        let item = &existing.items[0];
+        assert_eq!(
+            (item.name.as_str(), item.path.as_str()),
+            ("slide-1.svs", "/batch-3")
+        );
+        assert_eq!(item.slots[0].storage_key, "scans/s1.svs");
+        assert_eq!(item.slots[0].slot_type, DatasetItemTypes::TiledImage);
+        assert_eq!(item.slots[0].sections[0].width, 100);
+        let lines: Vec<usize> = existing.errors.iter().map(|error| error.line).collect();
+        assert_eq!(lines, vec![3, 4, 5]);
+        assert_eq!(existing.errors[0].name.as_deref(), Some("notes"));
+        assert!(existing.into_result().is_err());
+    }
+
+    #[cfg(feature = "reports")]
+    #[tokio::test]
+    async fn test_csv_manifest() {
+        let csv = "name,path,storage_key,type,width,height,tags\n\
+                   a.png,/,,,,, train ; cells \n\
+                   b,/x,,tiled_image,,,\n\
+                   c,/x,,hologram,,,\n";
+        let manifest = Manifest::from_csv(futures::io::Cursor::new(csv)).await;
+        assert!(manifest.errors.is_empty());
+
+        let new_items = manifest.new_items();
+        assert_eq!(new_items.items.len(), 2);
+        assert_eq!(new_items.items[0].typ, DatasetItemTypes::Image);
+        assert_eq!(new_items.items[0].tags.names(), vec!["train", "cells"]);
+        assert_eq!(new_items.items[1].typ, DatasetItemTypes::TiledImage);
+        assert_eq!(new_items.items[1].path, "/x");
+        assert_eq!(new_items.errors.len(), 1);
+        assert_eq!(
+            new_items.errors[0].to_string(),
+            "Line 4 (c): Unknown item type hologram"
+        );
+    }
+}
@@ -0,0 +1,128 @@
+//! A field V7 may leave out of a response, send as `null` or send with a value, for the
+//! fields where the difference matters, e.g. to send a workflow back unchanged.
+
+#[cfg(feature = "fake")]
+use fake::{Dummy, Fake, Faker};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+/// A value that is missing, null or present.
+///
+/// Use it with `#[serde(default, skip_serializing_if = "Maybe::is_missing")]` so that a
+/// missing field stays missing when serialized and a null one is sent as `null`. Code that
+/// does not care about the difference can use `value` or convert it into an `Option`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Maybe<T> {
+    #[default]
+    Missing,
+    Null,
+    Value(T),
+}
+
+impl<T> Maybe<T> {
+    pub fn value(&self) -> Option<&T> {
+        match self {
+            Self::Value(value) => Some(value),
+            Self::Missing | Self::Null => None,
+        }
+    }
+
+    pub fn into_value(self) -> Option<T> {
+        match self {
+            Self::Value(value) => Some(value),
+            Self::Missing | Self::Null => None,
+        }
+    }
+
+    pub fn is_missing(&self) -> bool {
+        matches!(self, Self::Missing)
+    }
+
+    pub fn is_null(&self) -> bool {
+        matches!(self, Self::Null)
+    }
+
+    pub fn is_value(&self) -> bool {
+        matches!(self, Self::Value(_))
+    }
+}
+
+impl<T> From<T> for Maybe<T> {
+    fn from(value: T) -> Self {
+        Self::Value(value)
+    }
+}
+
+/// `None` is null rather than missing, as it is serialized
+impl<T> From<Option<T>> for Maybe<T> {
+    fn from(value: Option<T>) -> Self {
+        match value {
+            Some(value) => Self::Value(value),
+            None => Self::Null,
+        }
+    }
+}
+
+impl<T> From<Maybe<T>> for Option<T> {
+    fn from(value: Maybe<T>) -> Self {
+        value.into_value()
+    }
+}
+
+impl<T: Serialize> Serialize for Maybe<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            Self::Value(value) => value.serialize(serializer),
+            // Only reached when the field is not skipped while missing
+            Self::Missing | Self::Null => serializer.serialize_none(),
+        }
+    }
+}
+
+// Only called for fields that are present, missing fields take the default
+impl<'de, T: Deserialize<'de>> Deserialize<'de> for Maybe<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Ok(Option::<T>::deserialize(deserializer)?.into())
+    }
+}
+
+#[cfg(feature = "fake")]
+impl<T: Dummy<Faker>> Dummy<Faker> for Maybe<T> {
+    fn dummy_with_rng<R: rand::Rng + ?Sized>(config: &Faker, rng: &mut R) -> Self {
+        let choice: u8 = (0..3).fake_with_rng(rng);
+        match choice {
+            0 => Self::Missing,
+            1 => Self::Null,
+            _ => Self::Value(config.fake_with_rng(rng)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[derive(Debug, Default, Serialize, Deserialize, PartialEq)]
+    struct Counts {
+        #[serde(default, skip_serializing_if = "Maybe::is_missing")]
+        count: Maybe<u32>,
+    }
+
+    #[test]
+    fn test_maybe() {
+        for (value, expected) in [
+            (json!({}), Maybe::Missing),
+            (json!({"count": null}), Maybe::Null),
+            (json!({"count": 4}), Maybe::Value(4)),
+        ] {
+            let counts: Counts = serde_json::from_value(value.clone()).unwrap();
+            assert_eq!(counts.count, expected);
+            assert_eq!(serde_json::to_value(&counts).unwrap(), value);
+        }
+
+        assert_eq!(Maybe::Value(4).value(), Some(&4));
+        assert_eq!(Option::<u32>::from(Maybe::Null), None);
+        assert_eq!(Maybe::<u32>::from(None), Maybe::Null);
+        assert!(Maybe::<u32>::default().is_missing());
+    }
+}
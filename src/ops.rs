@@ -0,0 +1,2240 @@
+//! High level operations composed from the lower level API calls, covering
+//! the workflows that are otherwise re-implemented by every script using this crate.
+
+use crate::annotation::{
+    AnnotationClass, AnnotationClassMetadata, AnnotationDataset, AnnotationDeleteFilter,
+    AnnotationDeletePreview, ItemAnnotation,
+};
+use crate::client::{PresignedUrlMethods, V7Methods};
+use crate::datasets::{
+    import_item_annotations, Dataset, DatasetDataMethods, DatasetDescribeMethods,
+    DatasetExportMethods, DatasetItemReportMethods, DatasetRef, DatasetWorkflowMethods,
+    DuplicateKey, DuplicatePolicy, ExportDownload, ExportFormat,
+};
+use crate::export::{AnnotationIdentity, ImageAnnotation, JsonExportV2};
+use crate::ids::{ClassId, DatasetId, ItemId, UserId};
+use crate::imports::{
+    AnnotationContext, AnnotationImport, AnnotationImportAnnotation, AnnotationImportData,
+    ClassMatching, OnExisting,
+};
+use crate::item::{DatasetItemMethods, DatasetItemV2, ExistingSimpleItem};
+use crate::progress::{NoProgress, ProgressEvent, ProgressSink};
+use crate::team::{ClassUsage, Team, TeamDataMethods, TeamDescribeMethods, TeamMember};
+use crate::utils::{utc_date, utc_time};
+use crate::workflow::{StageType, WorkflowBuilder, WorkflowMethods};
+use anyhow::{bail, Context, Result};
+use futures::StreamExt;
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Declarative description of the expected state of a dataset
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct DatasetManifest {
+    /// Name of the dataset, the dataset is created if it does not exist
+    pub dataset_name: String,
+    /// Slug of the external storage the items are registered from
+    pub storage_slug: String,
+    /// Items expected to be registered in the dataset, matched on path and name
+    #[serde(default)]
+    pub items: Vec<ExistingSimpleItem>,
+    /// How items with the same path and name are handled, all registered by default
+    #[serde(default)]
+    pub duplicates: DuplicatePolicy,
+    /// Annotation classes expected to be available to the dataset, matched on name
+    #[serde(default)]
+    pub annotation_classes: Vec<AnnotationClass>,
+    /// Workflow the dataset is expected to use
+    #[serde(default)]
+    pub workflow: Option<WorkflowBuilder>,
+}
+
+/// Summary of the changes made by `sync_dataset`
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct SyncReport {
+    pub dataset: Dataset,
+    pub dataset_created: bool,
+    /// Names of the items registered to the dataset
+    pub registered_items: Vec<String>,
+    /// Names of the annotation classes created on the team
+    pub created_classes: Vec<String>,
+    /// Names of existing team annotation classes that were added to the dataset
+    pub linked_classes: Vec<String>,
+    pub workflow_updated: bool,
+}
+
+fn item_key(path: &str, name: &str) -> String {
+    format!("{}/{}", path.trim_end_matches('/'), name)
+}
+
+/// Converges the remote dataset towards `manifest`.
+///
+/// The dataset is created if missing, items not yet in the dataset are registered,
+/// annotation classes missing from the team are created (and existing ones linked to
+/// the dataset) and the workflow is created or updated. Nothing is ever deleted.
+pub async fn sync_dataset<C>(client: &C, manifest: &DatasetManifest) -> Result<SyncReport>
+where
+    C: V7Methods + std::marker::Sync,
+{
+    sync_dataset_with_progress(client, manifest, &NoProgress).await
+}
+
+/// As `sync_dataset`, reporting the listing of existing items and the
+/// registration of missing items to `progress`
+pub async fn sync_dataset_with_progress<C>(
+    client: &C,
+    manifest: &DatasetManifest,
+    progress: &dyn ProgressSink,
+) -> Result<SyncReport>
+where
+    C: V7Methods + std::marker::Sync,
+{
+    let mut report = SyncReport::default();
+
+    // Find or create the dataset
+    let existing = Dataset::list_datasets(client)
+        .await?
+        .into_iter()
+        .flatten()
+        .find(|dataset| dataset.name.as_ref() == Some(&manifest.dataset_name));
+    let mut dataset = match existing {
+        Some(dataset) => dataset,
+        None => {
+            info!("Creating dataset {}", manifest.dataset_name);
+            report.dataset_created = true;
+            Dataset::create_dataset(client, &manifest.dataset_name).await?
+        }
+    };
+    if dataset.team_slug.is_none() {
+        dataset.team_slug = Some(client.team().to_string());
+    }
+    let dataset_id = dataset.id.context("Dataset is missing Id")?;
+
+    // Register the missing items
+    let registered: HashSet<String> = if report.dataset_created {
+        HashSet::new()
+    } else {
+        dataset
+            .list_all_dataset_items_v2_with_progress(client, progress)
+            .await?
+            .iter()
+            .filter_map(|item| Some(item_key(item.path.as_ref()?, item.name.as_ref()?)))
+            .collect()
+    };
+    let missing_items: Vec<ExistingSimpleItem> = manifest
+        .items
+        .iter()
+        .filter(|item| !registered.contains(&item_key(&item.path, &item.name)))
+        .cloned()
+        .collect();
+    if !missing_items.is_empty() {
+        // Items sharing a key are registered at most once whatever the policy
+        let mut names: Vec<(String, String)> = missing_items
+            .iter()
+            .map(|item| (item_key(&item.path, &item.name), item.name.clone()))
+            .collect();
+        let mut seen = HashSet::new();
+        names.retain(|(key, _)| seen.insert(key.clone()));
+        let total = names.len() as u64;
+        info!("Registering {total} items to {dataset}");
+        progress.event(ProgressEvent::Started {
+            operation: "item registration",
+            total: Some(total),
+        });
+        let (response, _) = dataset
+            .register_items_deduplicated(
+                client,
+                missing_items,
+                manifest.storage_slug.clone(),
+                DuplicateKey::NameAndPath,
+                manifest.duplicates,
+            )
+            .await?;
+        let blocked: HashSet<String> = response
+            .blocked_items
+            .iter()
+            .flatten()
+            .filter_map(|item| {
+                let name = item.name.as_deref()?;
+                Some(item_key(item.path.as_deref().unwrap_or("/"), name))
+            })
+            .collect();
+        report.registered_items = names
+            .into_iter()
+            .filter(|(key, _)| !blocked.contains(key))
+            .map(|(_, name)| name)
+            .collect();
+        if response.is_fully_successful() {
+            info!("Registration to {dataset}: {}", response.summary());
+        } else {
+            warn!("Registration to {dataset}: {}", response.summary());
+        }
+        progress.event(ProgressEvent::ItemsProcessed {
+            done: total,
+            total: Some(total),
+        });
+        progress.event(ProgressEvent::Finished {
+            operation: "item registration",
+        });
+    }
+
+    // Create the missing annotation classes
+    let team = Team::new(client.team().to_string(), None, None, None);
+    let team_classes = team.list_annotation_classes(client).await?;
+    for expected in manifest.annotation_classes.iter() {
+        let name = expected
+            .name
+            .as_ref()
+            .context("Annotation class in manifest is missing a name")?;
+        let existing = team_classes
+            .annotation_classes
+            .iter()
+            .flatten()
+            .find(|class| class.name.as_ref() == Some(name));
+        let dataset_link = Some(AnnotationDataset {
+            id: Some(dataset_id),
+        });
+
+        match existing {
+            Some(class) if class.datasets.contains(&dataset_link) => {}
+            Some(class) => {
+                info!("Adding annotation class {name} to {dataset}");
+                let mut class = class.clone();
+                class.datasets.push(dataset_link);
+                class.update(client).await?;
+                report.linked_classes.push(name.to_string());
+            }
+            None => {
+                info!("Creating annotation class {name}");
+                let mut class = expected.clone();
+                class.datasets = vec![dataset_link];
+                team.create_annotation_class(client, &class).await?;
+                report.created_classes.push(name.to_string());
+            }
+        }
+    }
+
+    // Create or update the workflow
+    if let Some(workflow) = manifest.workflow.as_ref() {
+        match dataset.get_workflow_v2(client).await? {
+            Some(existing) if workflow.matches(&existing) => {}
+            Some(existing) => {
+                existing.update_workflow(client, workflow).await?;
+                report.workflow_updated = true;
+            }
+            None => {
+                dataset.set_workflow_v2(client, workflow).await?;
+                report.workflow_updated = true;
+            }
+        }
+    }
+
+    report.dataset = dataset;
+    Ok(report)
+}
+
+fn slot_names(item: &DatasetItemV2) -> Vec<String> {
+    item.slots
+        .iter()
+        .flatten()
+        .filter_map(|slot| slot.slot_name.clone())
+        .collect()
+}
+
+/// Copies the current annotations of `from_item` onto `to_item`, e.g. from the original
+/// scan of a slide to its re-scan, returning the number of annotations copied.
+///
+/// Class ids found in `class_mapping` are replaced by their mapped id, other classes are
+/// kept as is. Slots are mapped by position, so the annotations of the first slot of
+/// `from_item` are imported to the first slot of `to_item` and so on. Existing annotations
+/// of `to_item` are kept.
+pub async fn copy_annotations<C>(
+    client: &C,
+    from_item: &DatasetItemV2,
+    to_item: &DatasetItemV2,
+    class_mapping: &HashMap<ClassId, ClassId>,
+) -> Result<usize>
+where
+    C: V7Methods + std::marker::Sync,
+{
+    let to_item_id = ItemId::from(to_item.id.as_deref().context("Target item has no Id")?);
+    let slot_mapping: HashMap<String, String> = slot_names(from_item)
+        .into_iter()
+        .zip(slot_names(to_item))
+        .collect();
+
+    let annotations = from_item
+        .list_annotations(client)
+        .await?
+        .iter()
+        .map(|annotation| remapped_annotation(annotation, class_mapping, &slot_mapping))
+        .collect::<Result<Vec<_>>>()?;
+
+    let copied = annotations.len();
+    if copied > 0 {
+        info!("Copying {copied} annotations to item {to_item_id}");
+        import_to_item(client, &to_item_id, annotations).await?;
+    }
+    Ok(copied)
+}
+
+// A new annotation with the same data as `annotation`, remapping its class and slots
+fn remapped_annotation(
+    annotation: &ItemAnnotation,
+    class_mapping: &HashMap<ClassId, ClassId>,
+    slot_mapping: &HashMap<String, String>,
+) -> Result<AnnotationImportAnnotation> {
+    let class_id = annotation
+        .annotation_class_id
+        .context("Annotation is missing a class id")?;
+    let data = match annotation.data.as_ref() {
+        Some(serde_json::Value::Object(data)) => data.clone().into_iter().collect(),
+        _ => bail!("Annotation {:?} has no data", annotation.id),
+    };
+    let slot_names = annotation
+        .context_keys
+        .as_ref()
+        .map(|context| context.slot_names.clone())
+        .unwrap_or_default()
+        .into_iter()
+        .map(|slot| slot_mapping.get(&slot).cloned().unwrap_or(slot))
+        .collect();
+
+    Ok(AnnotationImportAnnotation {
+        id: uuid::Uuid::new_v4().to_string(),
+        data: AnnotationImportData {
+            other: data,
+            ..Default::default()
+        },
+        annotation_class_id: class_mapping
+            .get(&ClassId(class_id))
+            .map_or(class_id, |mapped| mapped.0),
+        context_keys: AnnotationContext { slot_names },
+        properties: Vec::new(),
+        on_existing: OnExisting::default(),
+    })
+}
+
+async fn import_to_item<C>(
+    client: &C,
+    item_id: &ItemId,
+    annotations: Vec<AnnotationImportAnnotation>,
+) -> Result<()>
+where
+    C: V7Methods + std::marker::Sync,
+{
+    let import = AnnotationImport {
+        annotations,
+        overwrite: false,
+    };
+    import_item_annotations(client, client.team(), item_id, &import).await
+}
+
+/// Removes the annotations with the same class and geometry as an earlier annotation,
+/// e.g. left by importing the same annotations twice, returning the removed duplicates.
+/// The first copy of each annotation is kept, in its original order. Annotations without
+/// an identity, such as keypoints, are always kept (see `ImageAnnotation::identity`).
+pub fn dedup_annotations(annotations: &mut Vec<ImageAnnotation>) -> Vec<ImageAnnotation> {
+    let mut seen = HashSet::<AnnotationIdentity>::new();
+    let (kept, duplicates) = std::mem::take(annotations)
+        .into_iter()
+        .partition(|annotation| {
+            annotation
+                .identity()
+                .is_none_or(|identity| seen.insert(identity))
+        });
+    *annotations = kept;
+    duplicates
+}
+
+/// Exported annotations to import into one slot of a dataset item
+#[derive(Debug, Clone)]
+pub struct ItemAnnotations {
+    pub item_id: ItemId,
+    pub slot_name: String,
+    pub annotations: Vec<ImageAnnotation>,
+}
+
+/// Options of `import_annotations`
+#[derive(Debug, Default, Clone)]
+pub struct ImportOptions {
+    /// How the class names of the annotations are matched to the team annotation classes
+    pub matching: ClassMatching,
+    /// Replace the existing annotations of the items rather than adding to them
+    pub overwrite: bool,
+    /// Create the tag classes missing from the team, linked to the dataset, before importing.
+    /// Annotations of any other type always require an existing class.
+    pub create_missing_tag_classes: bool,
+    /// Only work out the tag classes that would be created, nothing is created or imported
+    pub dry_run: bool,
+}
+
+/// Summary of the changes made by `import_annotations`
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct ImportReport {
+    /// Names of the tag classes created on the team, or that would be created on a dry run
+    pub created_classes: Vec<String>,
+    /// Number of annotations imported
+    pub imported: usize,
+}
+
+fn import_annotation(
+    annotation: &ImageAnnotation,
+    classes: &[&AnnotationClass],
+    matching: &ClassMatching,
+    slot_name: &str,
+) -> Result<AnnotationImportAnnotation> {
+    match (annotation.annotation_type(), annotation.polygon.as_ref()) {
+        (Some("polygon"), Some(polygon)) => {
+            AnnotationImportAnnotation::new_polygon_annotation_with_matching(
+                annotation,
+                polygon.clone(),
+                classes,
+                matching,
+                slot_name,
+            )
+        }
+        (Some("tag"), _) => AnnotationImportAnnotation::new_tag_annotation_with_matching(
+            annotation, classes, matching, slot_name,
+        ),
+        (Some("ellipse" | "cuboid" | "line"), _) => {
+            AnnotationImportAnnotation::new_shape_annotation_with_matching(
+                annotation, classes, matching, slot_name,
+            )
+        }
+        (annotation_type, _) => bail!(
+            "Unable to import {} annotation of class {}",
+            annotation_type.unwrap_or("empty"),
+            annotation.name
+        ),
+    }
+}
+
+/// Imports exported annotations into the items of `dataset`, returning a summary of the
+/// changes made.
+///
+/// Class names are matched to the annotation classes of the team using `options.matching`.
+/// With `options.create_missing_tag_classes` set, tag classes that match no team class are
+/// created first rather than failing the import, use `options.dry_run` to list them without
+/// making any change.
+///
+/// # Errors
+///
+/// Returns an error if an annotation has no matching class, or is of a type that cannot be
+/// imported. Items are imported one at a time, so items before the failing one stay imported.
+pub async fn import_annotations<C>(
+    client: &C,
+    dataset: &Dataset,
+    items: &[ItemAnnotations],
+    options: &ImportOptions,
+) -> Result<ImportReport>
+where
+    C: V7Methods + std::marker::Sync,
+{
+    import_annotations_with_progress(client, dataset, items, options, &NoProgress).await
+}
+
+/// As `import_annotations`, reporting the items imported to `progress`
+pub async fn import_annotations_with_progress<C>(
+    client: &C,
+    dataset: &Dataset,
+    items: &[ItemAnnotations],
+    options: &ImportOptions,
+    progress: &dyn ProgressSink,
+) -> Result<ImportReport>
+where
+    C: V7Methods + std::marker::Sync,
+{
+    let mut report = ImportReport::default();
+    let team = Team::new(client.team().to_string(), None, None, None);
+    let mut team_classes: Vec<AnnotationClass> = team
+        .list_annotation_classes(client)
+        .await?
+        .annotation_classes
+        .into_iter()
+        .flatten()
+        .collect();
+
+    if options.create_missing_tag_classes {
+        let classes: Vec<&AnnotationClass> = team_classes.iter().collect();
+        let mut missing = BTreeSet::new();
+        let tags = items
+            .iter()
+            .flat_map(|item| item.annotations.iter())
+            .filter(|annotation| annotation.annotation_type() == Some("tag"));
+        for annotation in tags {
+            if !options.matching.has_match(&classes, &annotation.name)? {
+                missing.insert(annotation.name.as_str());
+            }
+        }
+        report.created_classes = missing.into_iter().map(str::to_string).collect();
+    }
+    if options.dry_run {
+        return Ok(report);
+    }
+
+    if !report.created_classes.is_empty() {
+        let dataset_id = dataset.id.context("Dataset is missing an id")?;
+        for name in &report.created_classes {
+            info!("Creating tag annotation class {name}");
+            let class = AnnotationClass {
+                name: Some(name.clone()),
+                annotation_types: vec![Some("tag".to_string())],
+                datasets: vec![Some(AnnotationDataset {
+                    id: Some(dataset_id),
+                })],
+                ..Default::default()
+            };
+            team_classes.push(team.create_annotation_class(client, &class).await?);
+        }
+    }
+
+    let classes: Vec<&AnnotationClass> = team_classes.iter().collect();
+    let total = items.len() as u64;
+    progress.event(ProgressEvent::Started {
+        operation: "annotation import",
+        total: Some(total),
+    });
+    for (done, item) in (1..).zip(items) {
+        let annotations = item
+            .annotations
+            .iter()
+            .map(|annotation| {
+                import_annotation(annotation, &classes, &options.matching, &item.slot_name)
+            })
+            .collect::<Result<Vec<_>>>()?;
+        if !annotations.is_empty() || options.overwrite {
+            report.imported += annotations.len();
+            let import = AnnotationImport {
+                annotations,
+                overwrite: options.overwrite,
+            };
+            dataset
+                .import_annotation(client, &item.item_id, &import)
+                .await
+                .with_context(|| {
+                    format!("Failed to import annotations to item {}", item.item_id)
+                })?;
+        }
+        progress.event(ProgressEvent::ItemsProcessed {
+            done,
+            total: Some(total),
+        });
+    }
+    progress.event(ProgressEvent::Finished {
+        operation: "annotation import",
+    });
+    Ok(report)
+}
+
+/// Retires the annotation class `class` by archiving it, returning the number of
+/// annotations moved to `replacement`.
+///
+/// Archiving rather than deleting keeps existing annotations intact. When a `replacement`
+/// class is given, the annotations of `class` on `items` are first re-created with the
+/// replacement class, as V7 has no endpoint to change the class of an existing annotation.
+/// Each item is done in turn, deleting its originals before importing the replacements so
+/// that a failure cannot leave both copies. Should the import fail the originals are
+/// imported again before the error is returned.
+pub async fn retire_annotation_class<C>(
+    client: &C,
+    class: &AnnotationClass,
+    items: &[DatasetItemV2],
+    replacement: Option<ClassId>,
+) -> Result<usize>
+where
+    C: V7Methods + std::marker::Sync,
+{
+    let class_id = class.id.context("Annotation class is missing an id")?;
+    let mut reassigned = 0;
+
+    if let Some(replacement) = replacement {
+        let filter = AnnotationDeleteFilter {
+            annotation_class_ids: Some(vec![class_id.0]),
+            ..Default::default()
+        };
+        let preview = AnnotationDeletePreview::prepare(client, items, &filter).await?;
+        let class_mapping = HashMap::from([(class_id, replacement)]);
+        let remap = |annotations: &[ItemAnnotation], class_mapping: &HashMap<ClassId, ClassId>| {
+            annotations
+                .iter()
+                .map(|annotation| remapped_annotation(annotation, class_mapping, &HashMap::new()))
+                .collect::<Result<Vec<_>>>()
+        };
+        for (item_id, annotations) in preview.annotations().iter() {
+            let item_id = ItemId::from(item_id.as_str());
+            let replacements = remap(annotations, &class_mapping)?;
+            let deleted = AnnotationDeletePreview::delete_item_annotations(
+                client,
+                item_id.as_str(),
+                annotations,
+            )
+            .await?;
+            if let Err(error) = import_to_item(client, &item_id, replacements).await {
+                let restored =
+                    import_to_item(client, &item_id, remap(annotations, &HashMap::new())?)
+                        .await
+                        .is_ok();
+                return Err(error.context(format!(
+                    "Failed to reassign the annotations of item {item_id}, the originals were {}",
+                    if restored {
+                        "restored"
+                    } else {
+                        "deleted and could not be restored"
+                    }
+                )));
+            }
+            reassigned += deleted;
+        }
+        info!("Reassigned {reassigned} annotations from class {class_id} to {replacement}");
+    }
+
+    class.archive(client).await?;
+    Ok(reassigned)
+}
+
+/// Counts the annotations of every team annotation class across `datasets`, see
+/// `TeamAnnotationClasses::usage`. Pass every dataset of the team, e.g. from
+/// `Dataset::list_datasets`, to safely find the classes that are unused before archiving them.
+///
+/// The annotations of every item are listed, so this makes one request per item.
+pub async fn class_usage<C>(client: &C, datasets: &[Dataset]) -> Result<Vec<ClassUsage>>
+where
+    C: V7Methods + std::marker::Sync,
+{
+    let team = Team::new(client.team().to_string(), None, None, None);
+    let classes = team.list_annotation_classes(client).await?;
+
+    let mut distributions = Vec::new();
+    for dataset in datasets.iter() {
+        let mut dataset = dataset.clone();
+        if dataset.team_slug.is_none() {
+            dataset.team_slug = Some(client.team().to_string());
+        }
+        distributions.push(dataset.class_distribution(client).await?);
+    }
+    Ok(classes.usage(&distributions))
+}
+
+/// Declarative description of a team annotation class, see `ensure_annotation_classes`
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ClassSpec {
+    pub name: String,
+    /// Main annotation type e.g. `polygon` or `tag`
+    pub annotation_type: String,
+    /// Sub annotation types e.g. `attributes` or `text`
+    #[serde(default)]
+    pub subtypes: Vec<String>,
+    /// Color as V7 stores it e.g. `rgba(255,0,0,1.0)`, chosen by V7 when not given
+    #[serde(default)]
+    pub color: Option<String>,
+    /// Left unchanged on existing classes when not given
+    #[serde(default)]
+    pub description: Option<String>,
+}
+
+fn normalized_color(color: &str) -> String {
+    color
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .collect::<String>()
+        .to_lowercase()
+}
+
+impl ClassSpec {
+    fn annotation_types(&self) -> BTreeSet<String> {
+        std::iter::once(&self.annotation_type)
+            .chain(self.subtypes.iter())
+            .cloned()
+            .collect()
+    }
+
+    /// Names of the fields of `class` that differ from the spec, empty if it is up to date.
+    /// Colors are compared ignoring case and whitespace.
+    pub fn drift(&self, class: &AnnotationClass) -> Vec<&'static str> {
+        let mut drifted = vec![];
+        let types: BTreeSet<String> = class.annotation_types.iter().flatten().cloned().collect();
+        if types != self.annotation_types() {
+            drifted.push("annotation_types");
+        }
+        if let Some(color) = self.color.as_deref() {
+            let current = class.metadata.as_ref().and_then(|m| m.color.as_deref());
+            if current.map(normalized_color) != Some(normalized_color(color)) {
+                drifted.push("color");
+            }
+        }
+        if self.description.is_some() && class.description != self.description {
+            drifted.push("description");
+        }
+        if class.archived == Some(true) {
+            drifted.push("archived");
+        }
+        drifted
+    }
+
+    /// `class` updated to match the spec, keeping its other settings
+    fn apply(&self, class: &AnnotationClass) -> AnnotationClass {
+        let mut class = class.clone();
+        class.annotation_types = self.annotation_types().into_iter().map(Some).collect();
+        if let Some(color) = self.color.as_ref() {
+            class.metadata.get_or_insert_with(Default::default).color = Some(color.clone());
+        }
+        if self.description.is_some() {
+            class.description = self.description.clone();
+        }
+        if class.archived == Some(true) {
+            class.archived = Some(false);
+        }
+        class
+    }
+
+    fn to_class(&self) -> AnnotationClass {
+        AnnotationClass {
+            name: Some(self.name.clone()),
+            annotation_types: self.annotation_types().into_iter().map(Some).collect(),
+            description: self.description.clone(),
+            metadata: self.color.as_ref().map(|color| AnnotationClassMetadata {
+                color: Some(color.clone()),
+                ..Default::default()
+            }),
+            ..Default::default()
+        }
+    }
+}
+
+/// Summary of the changes made by `ensure_annotation_classes`, by class name
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ClassSyncReport {
+    pub created: Vec<String>,
+    pub updated: Vec<String>,
+    pub unchanged: Vec<String>,
+    /// Classes of the team, not archived, that are absent from the specs. They are
+    /// never changed, archive them with `retire_annotation_class` if no longer needed.
+    pub unmanaged: Vec<String>,
+}
+
+/// Converges the annotation classes of `team` towards `specs`, matched on name.
+///
+/// Missing classes are created and classes whose annotation types, color or description
+/// drifted from their spec are updated, unarchiving them if needed. Nothing is deleted.
+pub async fn ensure_annotation_classes<C>(
+    client: &C,
+    team: &Team,
+    specs: &[ClassSpec],
+) -> Result<ClassSyncReport>
+where
+    C: V7Methods + std::marker::Sync,
+{
+    let mut names = HashSet::new();
+    if let Some(spec) = specs.iter().find(|spec| !names.insert(spec.name.as_str())) {
+        bail!("Annotation class {} is specified more than once", spec.name);
+    }
+
+    let classes = team.list_annotation_classes(client).await?;
+    let existing: HashMap<&str, &AnnotationClass> = classes
+        .annotation_classes
+        .iter()
+        .flatten()
+        .filter_map(|class| Some((class.name.as_deref()?, class)))
+        .collect();
+
+    let mut report = ClassSyncReport::default();
+    for spec in specs {
+        match existing.get(spec.name.as_str()) {
+            None => {
+                info!("Creating annotation class {}", spec.name);
+                team.create_annotation_class(client, &spec.to_class())
+                    .await?;
+                report.created.push(spec.name.clone());
+            }
+            Some(class) => {
+                let drifted = spec.drift(class);
+                if drifted.is_empty() {
+                    report.unchanged.push(spec.name.clone());
+                } else {
+                    info!(
+                        "Updating {} of annotation class {}",
+                        drifted.join(", "),
+                        spec.name
+                    );
+                    spec.apply(class).update(client).await?;
+                    report.updated.push(spec.name.clone());
+                }
+            }
+        }
+    }
+
+    report.unmanaged = existing
+        .iter()
+        .filter(|(name, class)| !names.contains(*name) && class.archived != Some(true))
+        .map(|(name, _)| name.to_string())
+        .collect();
+    report.unmanaged.sort();
+    Ok(report)
+}
+
+/// Declarative description of a dataset of a `ProvisioningSpec`
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct DatasetSpec {
+    /// Name of the dataset, the dataset is created if it does not exist
+    pub name: String,
+    /// Annotation classes of the dataset, created on the team if missing. A class shared
+    /// by several datasets must be specified identically for each of them.
+    #[serde(default)]
+    pub classes: Vec<ClassSpec>,
+    /// Workflow of the dataset. Dataset stages without a `dataset_id` are pointed at
+    /// the dataset once it exists.
+    #[serde(default)]
+    pub workflow: Option<WorkflowBuilder>,
+    /// Annotation instructions (HTML), left unchanged when not given
+    #[serde(default)]
+    pub instructions: Option<String>,
+    /// Slug of the external storage the items of the dataset are registered from
+    #[serde(default)]
+    pub storage_slug: Option<String>,
+    /// Prefix of the storage keys of the items of the dataset, see `DatasetSpec::manifest`
+    #[serde(default)]
+    pub storage_prefix: Option<String>,
+}
+
+impl DatasetSpec {
+    /// A manifest for `sync_dataset` registering `items` from the storage of the dataset,
+    /// with the storage and thumbnail keys of their slots placed below `storage_prefix`.
+    ///
+    /// V7 has no per dataset storage prefix, so the prefix is only applied here.
+    pub fn manifest(&self, items: Vec<ExistingSimpleItem>) -> Result<DatasetManifest> {
+        let storage_slug = self
+            .storage_slug
+            .clone()
+            .with_context(|| format!("Dataset {} has no storage slug", self.name))?;
+        let prefixed = |key: &str| match self.storage_prefix.as_deref() {
+            Some(prefix) if !key.is_empty() => {
+                format!("{}/{}", prefix.trim_end_matches('/'), key)
+            }
+            _ => key.to_string(),
+        };
+        let items = items
+            .into_iter()
+            .map(|mut item| {
+                for slot in item.slots.iter_mut() {
+                    slot.storage_key = prefixed(&slot.storage_key);
+                    slot.storage_thumbnail_key = prefixed(&slot.storage_thumbnail_key);
+                }
+                item
+            })
+            .collect();
+        Ok(DatasetManifest {
+            dataset_name: self.name.clone(),
+            storage_slug,
+            items,
+            ..Default::default()
+        })
+    }
+}
+
+/// Declarative description of the datasets of a team, see `provision_from_spec`
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct ProvisioningSpec {
+    pub datasets: Vec<DatasetSpec>,
+}
+
+impl ProvisioningSpec {
+    pub fn from_json(json: &str) -> Result<Self> {
+        Ok(serde_path_to_error::deserialize(
+            &mut serde_json::Deserializer::from_str(json),
+        )?)
+    }
+
+    pub fn from_yaml(yaml: &str) -> Result<Self> {
+        Ok(serde_path_to_error::deserialize(
+            serde_yaml::Deserializer::from_str(yaml),
+        )?)
+    }
+
+    /// Reads a spec file, as JSON if its extension is `json` and as YAML otherwise
+    pub fn from_path(path: &Path) -> Result<Self> {
+        let contents = std::fs::read_to_string(path)
+            .with_context(|| format!("Unable to read {}", path.display()))?;
+        let spec = match path.extension().and_then(|extension| extension.to_str()) {
+            Some("json") => Self::from_json(&contents),
+            _ => Self::from_yaml(&contents),
+        };
+        spec.with_context(|| format!("Invalid provisioning spec {}", path.display()))
+    }
+
+    /// Every class of the datasets once, failing if a class is specified differently
+    /// by two datasets
+    fn classes(&self) -> Result<Vec<ClassSpec>> {
+        let mut classes: Vec<ClassSpec> = vec![];
+        for spec in self
+            .datasets
+            .iter()
+            .flat_map(|dataset| dataset.classes.iter())
+        {
+            match classes.iter().find(|class| class.name == spec.name) {
+                Some(class) if class != spec => {
+                    bail!("Annotation class {} is specified differently", spec.name)
+                }
+                Some(_) => {}
+                None => classes.push(spec.clone()),
+            }
+        }
+        Ok(classes)
+    }
+}
+
+/// Summary of the changes made to a dataset by `provision_from_spec`
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct DatasetProvisionReport {
+    pub dataset: Dataset,
+    pub created: bool,
+    /// Names of the annotation classes that were added to the dataset
+    pub linked_classes: Vec<String>,
+    pub instructions_updated: bool,
+    pub workflow_created: bool,
+    pub workflow_updated: bool,
+}
+
+/// Summary of the changes made by `provision_from_spec`
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct ProvisionReport {
+    pub classes: ClassSyncReport,
+    /// In the order of the datasets of the spec
+    pub datasets: Vec<DatasetProvisionReport>,
+}
+
+/// Converges the datasets of the team towards `spec`, so that running it again with the
+/// same spec makes no further changes.
+///
+/// Missing datasets are created, annotation classes are converged with
+/// `ensure_annotation_classes` and linked to their datasets, and instructions and
+/// workflows that differ from the spec are updated. Nothing is ever deleted. Items are
+/// not registered, see `DatasetSpec::manifest` and `sync_dataset`.
+pub async fn provision_from_spec<C>(client: &C, spec: &ProvisioningSpec) -> Result<ProvisionReport>
+where
+    C: V7Methods + std::marker::Sync,
+{
+    let mut names = HashSet::new();
+    if let Some(dataset) = spec
+        .datasets
+        .iter()
+        .find(|dataset| !names.insert(dataset.name.as_str()))
+    {
+        bail!("Dataset {} is specified more than once", dataset.name);
+    }
+    let classes = spec.classes()?;
+
+    let team = Team::new(client.team().to_string(), None, None, None);
+    let mut report = ProvisionReport {
+        classes: ensure_annotation_classes(client, &team, &classes).await?,
+        ..Default::default()
+    };
+    // Kept up to date as classes are linked, so a class shared by several new datasets
+    // keeps the links made for the earlier datasets
+    let mut team_classes = team.list_annotation_classes(client).await?;
+    let existing_datasets: Vec<Dataset> = Dataset::list_datasets(client)
+        .await?
+        .into_iter()
+        .flatten()
+        .collect();
+
+    for dataset_spec in spec.datasets.iter() {
+        let mut dataset_report = DatasetProvisionReport::default();
+        let existing = existing_datasets
+            .iter()
+            .find(|dataset| dataset.name.as_ref() == Some(&dataset_spec.name));
+        let mut dataset = match existing {
+            Some(dataset) => {
+                let id = DatasetId(dataset.id.context("Dataset is missing Id")?);
+                Dataset::show_dataset(client, &id).await?
+            }
+            None => {
+                info!("Creating dataset {}", dataset_spec.name);
+                dataset_report.created = true;
+                Dataset::create_dataset(client, &dataset_spec.name).await?
+            }
+        };
+        if dataset.team_slug.is_none() {
+            dataset.team_slug = Some(client.team().to_string());
+        }
+        let dataset_id = dataset.id.context("Dataset is missing Id")?;
+
+        // Link the classes to the dataset
+        let dataset_link = Some(AnnotationDataset {
+            id: Some(dataset_id),
+        });
+        for class_spec in dataset_spec.classes.iter() {
+            let class = team_classes
+                .annotation_classes
+                .iter_mut()
+                .flatten()
+                .find(|class| class.name.as_ref() == Some(&class_spec.name))
+                .with_context(|| format!("Annotation class {} was not created", class_spec.name))?;
+            if !class.datasets.contains(&dataset_link) {
+                info!("Adding annotation class {} to {dataset}", class_spec.name);
+                let mut linked = class.clone();
+                linked.datasets.push(dataset_link.clone());
+                linked.update(client).await?;
+                *class = linked;
+                dataset_report.linked_classes.push(class_spec.name.clone());
+            }
+        }
+
+        if let Some(instructions) = dataset_spec.instructions.as_ref() {
+            if dataset.instructions.as_ref() != Some(instructions) {
+                info!("Updating the instructions of {dataset}");
+                dataset.set_instructions(client, instructions).await?;
+                dataset.instructions = Some(instructions.clone());
+                dataset_report.instructions_updated = true;
+            }
+        }
+
+        if let Some(workflow) = dataset_spec.workflow.as_ref() {
+            let mut workflow = workflow.clone();
+            for stage in workflow.stages.iter_mut() {
+                if stage.stage_type == Some(StageType::Dataset) {
+                    let config = stage.config.get_or_insert_with(Default::default);
+                    config.dataset_id.get_or_insert(dataset_id);
+                }
+            }
+            match dataset.get_workflow_v2(client).await? {
+                Some(existing) => {
+                    if !workflow.matches(&existing) {
+                        info!("Updating the workflow of {dataset}");
+                        existing.update_workflow(client, &workflow).await?;
+                        dataset_report.workflow_updated = true;
+                    }
+                }
+                None => {
+                    info!("Creating the workflow of {dataset}");
+                    dataset.set_workflow_v2(client, &workflow).await?;
+                    dataset_report.workflow_created = true;
+                }
+            }
+        }
+
+        dataset_report.dataset = dataset;
+        report.datasets.push(dataset_report);
+    }
+    Ok(report)
+}
+
+/// Fills in the user id and team role of the annotators and reviewers of `export` from
+/// the team `memberships`, see `TeamDescribeMethods::list_memberships`. Emails are
+/// matched ignoring case.
+///
+/// Returns the emails that are not members of the team, e.g. users that have since left,
+/// sorted and without duplicates. Their annotator entries are left unchanged.
+pub fn resolve_annotators(export: &mut JsonExportV2, memberships: &[TeamMember]) -> Vec<String> {
+    let members: HashMap<String, &TeamMember> = memberships
+        .iter()
+        .filter_map(|member| Some((member.email.as_ref()?.to_lowercase(), member)))
+        .collect();
+
+    let mut unresolved = HashSet::new();
+    for annotation in export.annotations.iter_mut() {
+        let users = annotation
+            .annotators
+            .iter_mut()
+            .chain(annotation.reviewers.iter_mut())
+            .flatten();
+        for user in users {
+            match members.get(&user.email.to_lowercase()) {
+                Some(member) => {
+                    user.user_id = member.user_id.map(UserId);
+                    user.role = member.role.as_ref().map(ToString::to_string);
+                }
+                None => {
+                    unresolved.insert(user.email.clone());
+                }
+            }
+        }
+    }
+
+    let mut unresolved: Vec<String> = unresolved.into_iter().collect();
+    unresolved.sort();
+    unresolved
+}
+
+/// Options of `export_team`
+#[derive(Debug, Clone)]
+pub struct TeamExportOptions {
+    /// Root directory the exports are downloaded into as `<team>/<dataset>/<date>/<name>.zip`
+    pub destination: PathBuf,
+    /// Date of the export directories and names, today (UTC) if not provided
+    pub date: Option<String>,
+    /// Time of day appended to the export names as `<dataset>-<date>-<time>` so that the
+    /// exports of several runs on the same day are told apart, now (UTC) if not provided
+    pub time: Option<String>,
+    /// Maximum number of datasets exported at the same time
+    pub concurrency: usize,
+    pub poll_interval: Duration,
+    /// Time to wait for each export to complete before giving up on its dataset
+    pub deadline: Option<Duration>,
+    pub include_authorship: bool,
+}
+
+impl Default for TeamExportOptions {
+    fn default() -> Self {
+        TeamExportOptions {
+            destination: PathBuf::from("."),
+            date: None,
+            time: None,
+            concurrency: 4,
+            poll_interval: Duration::from_secs(10),
+            deadline: Some(Duration::from_secs(60 * 60)),
+            include_authorship: false,
+        }
+    }
+}
+
+/// A dataset that `export_team` was unable to export or download
+#[derive(Debug)]
+pub struct DatasetExportFailure {
+    pub dataset_slug: String,
+    pub error: anyhow::Error,
+}
+
+/// Outcome of `export_team`, ordered by dataset slug
+#[derive(Debug, Default)]
+pub struct TeamExportReport {
+    pub downloads: Vec<(String, ExportDownload)>,
+    pub failures: Vec<DatasetExportFailure>,
+}
+
+async fn export_dataset<C>(
+    client: &C,
+    download_client: &dyn PresignedUrlMethods,
+    dataset: &DatasetRef,
+    format: &ExportFormat,
+    options: &TeamExportOptions,
+    date: &str,
+    time: &str,
+) -> Result<ExportDownload>
+where
+    C: V7Methods + std::marker::Sync,
+{
+    let slug = &dataset.slug;
+    let export_name = format!("{slug}-{date}-{time}");
+    let directory = options
+        .destination
+        .join(client.team())
+        .join(slug)
+        .join(date);
+    std::fs::create_dir_all(&directory)?;
+
+    dataset
+        .generate_export(
+            client,
+            &export_name,
+            format,
+            options.include_authorship,
+            false,
+            None,
+        )
+        .await?;
+    let export = dataset
+        .wait_for_export(
+            client,
+            &export_name,
+            options.poll_interval,
+            options.deadline,
+        )
+        .await?;
+    export
+        .download(
+            download_client,
+            &directory.join(format!("{export_name}.zip")),
+            &NoProgress,
+        )
+        .await
+}
+
+/// Exports every dataset of the team matching `dataset_filter` and downloads the archives
+/// below `options.destination`, e.g. for backups.
+///
+/// At most `options.concurrency` datasets are exported at the same time. A failure to
+/// export one dataset does not stop the others, failed datasets are listed in the report.
+/// `download_client` should not be the `V7Client`, see `Export::download`.
+pub async fn export_team<C, F>(
+    client: &C,
+    download_client: &dyn PresignedUrlMethods,
+    dataset_filter: F,
+    format: &ExportFormat,
+    options: &TeamExportOptions,
+) -> Result<TeamExportReport>
+where
+    C: V7Methods + std::marker::Sync,
+    F: Fn(&Dataset) -> bool,
+{
+    if options.concurrency == 0 {
+        bail!("Export concurrency must be greater than zero");
+    }
+    let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs() as i64;
+    let date = options.date.clone().unwrap_or_else(|| utc_date(now));
+    let time = options.time.clone().unwrap_or_else(|| utc_time(now));
+    let (date, time) = (date.as_str(), time.as_str());
+    let datasets: Vec<Dataset> = Dataset::list_datasets(client)
+        .await?
+        .into_iter()
+        .flatten()
+        .filter(|dataset| dataset_filter(dataset))
+        .map(|mut dataset| {
+            if dataset.team_slug.is_none() {
+                dataset.team_slug = Some(client.team().to_string());
+            }
+            dataset
+        })
+        .collect();
+    info!("Exporting {} datasets", datasets.len());
+
+    let outcomes: Vec<(String, Result<ExportDownload>)> = futures::stream::iter(datasets.iter())
+        .map(|dataset| async move {
+            let outcome = match DatasetRef::try_from(dataset) {
+                Ok(dataset_ref) => {
+                    export_dataset(
+                        client,
+                        download_client,
+                        &dataset_ref,
+                        format,
+                        options,
+                        date,
+                        time,
+                    )
+                    .await
+                }
+                Err(error) => Err(error),
+            };
+            (dataset.slug.clone().unwrap_or_default(), outcome)
+        })
+        .buffer_unordered(options.concurrency)
+        .collect()
+        .await;
+
+    let mut report = TeamExportReport::default();
+    for (dataset_slug, outcome) in outcomes {
+        match outcome {
+            Ok(download) => report.downloads.push((dataset_slug, download)),
+            Err(error) => {
+                warn!("Failed to export {dataset_slug}: {error}");
+                report.failures.push(DatasetExportFailure {
+                    dataset_slug,
+                    error,
+                })
+            }
+        }
+    }
+    report.downloads.sort_by(|a, b| a.0.cmp(&b.0));
+    report
+        .failures
+        .sort_by(|a, b| a.dataset_slug.cmp(&b.dataset_slug));
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::annotation::Tag;
+    use crate::client::V7Client;
+    use crate::export::Annotator;
+    use crate::item::{Item, ItemSlot, Slot};
+    use crate::team::{Role, TeamAnnotationClasses};
+    use crate::workflow::WorkflowV2;
+    use fake::{Fake, Faker};
+    use serde_json::json;
+    use wiremock::matchers::{body_json, body_partial_json, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    fn existing_item(name: &str) -> ExistingSimpleItem {
+        ExistingSimpleItem {
+            name: name.to_string(),
+            path: "/".to_string(),
+            slots: vec![],
+        }
+    }
+
+    #[test]
+    fn test_resolve_annotators() {
+        let user = |email: &str| Annotator {
+            email: email.to_string(),
+            ..Default::default()
+        };
+        let mut export = JsonExportV2 {
+            annotations: vec![ImageAnnotation {
+                annotators: Some(vec![user("Fake.Pathologist@franklin.ai")]),
+                reviewers: Some(vec![user("former@franklin.ai")]),
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        let memberships = vec![TeamMember {
+            email: Some("fake.pathologist@franklin.ai".to_string()),
+            role: Some(Role::Annotator.into()),
+            user_id: Some(42),
+            ..Default::default()
+        }];
+
+        let unresolved = resolve_annotators(&mut export, &memberships);
+        assert_eq!(unresolved, vec!["former@franklin.ai".to_string()]);
+        let annotator = &export.annotations[0].annotators.as_ref().unwrap()[0];
+        assert_eq!(annotator.user_id, Some(UserId(42)));
+        assert_eq!(annotator.role.as_deref(), Some("annotator"));
+        assert_eq!(
+            export.annotations[0].reviewers.as_ref().unwrap()[0].user_id,
+            None
+        );
+    }
+
+    #[test]
+    fn test_dedup_annotations() {
+        let tag = |id: &str, name: &str| ImageAnnotation {
+            id: Some(id.to_string()),
+            name: name.to_string(),
+            tag: Some(Default::default()),
+            ..Default::default()
+        };
+        let mut annotations = vec![tag("1", "Tumour"), tag("2", "Normal"), tag("3", "Tumour")];
+        let duplicates = dedup_annotations(&mut annotations);
+        let ids = |annotations: &[ImageAnnotation]| -> Vec<String> {
+            annotations.iter().filter_map(|a| a.id.clone()).collect()
+        };
+        assert_eq!(ids(&annotations), vec!["1", "2"]);
+        assert_eq!(ids(&duplicates), vec!["3"]);
+
+        // Keypoints are not modelled, so different keypoints of a class are both kept
+        let mut keypoints: Vec<ImageAnnotation> = serde_json::from_value(json!([
+            {"id": "4", "name": "Cell", "keypoint": {"x": 1.0, "y": 2.0}},
+            {"id": "5", "name": "Cell", "keypoint": {"x": 8.0, "y": 9.0}}
+        ]))
+        .unwrap();
+        assert!(dedup_annotations(&mut keypoints).is_empty());
+        assert_eq!(ids(&keypoints), vec!["4", "5"]);
+    }
+
+    #[tokio::test]
+    async fn test_export_team() {
+        let mock_server = MockServer::start().await;
+        let dataset = |id: u32, slug: &str| json!({"id": id, "slug": slug, "name": slug, "team_slug": "some-team"});
+        Mock::given(method("GET"))
+            .and(path("/datasets"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!([
+                dataset(1, "slides"),
+                dataset(2, "archive"),
+                dataset(3, "broken")
+            ])))
+            .mount(&mock_server)
+            .await;
+        for slug in ["slides", "broken"] {
+            Mock::given(method("POST"))
+                .and(path(format!("/v2/teams/some-team/datasets/{slug}/exports")))
+                .and(body_partial_json(
+                    json!({"name": format!("{slug}-2024-03-04-101500")}),
+                ))
+                .respond_with(ResponseTemplate::new(200))
+                .expect(1)
+                .mount(&mock_server)
+                .await;
+        }
+        Mock::given(method("GET"))
+            .and(path("/v2/teams/some-team/datasets/slides/exports"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!([{
+                "name": "slides-2024-03-04-101500",
+                "status": "complete",
+                "download_url": format!("{}/downloads/slides.zip", mock_server.uri())
+            }])))
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/v2/teams/some-team/datasets/broken/exports"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!([{
+                "name": "broken-2024-03-04-101500",
+                "status": "failed"
+            }])))
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/downloads/slides.zip"))
+            .respond_with(ResponseTemplate::new(200).set_body_bytes(b"archive".to_vec()))
+            .mount(&mock_server)
+            .await;
+
+        let client: V7Client = V7Client::new(
+            format!("{}/", mock_server.uri()),
+            "api-key".to_string(),
+            "some-team".to_string(),
+        )
+        .expect("Failed to get V7Client");
+        let dir = tempfile::tempdir().unwrap();
+        let options = TeamExportOptions {
+            destination: dir.path().to_path_buf(),
+            date: Some("2024-03-04".to_string()),
+            time: Some("101500".to_string()),
+            concurrency: 2,
+            poll_interval: Duration::from_millis(10),
+            ..Default::default()
+        };
+
+        let report = export_team(
+            &client,
+            &reqwest::Client::new(),
+            |dataset| dataset.slug.as_deref() != Some("archive"),
+            &ExportFormat::DarwinJson2,
+            &options,
+        )
+        .await
+        .expect("Failed to export team");
+
+        assert_eq!(report.downloads.len(), 1);
+        assert_eq!(
+            report.downloads[0].1.path,
+            dir.path()
+                .join("some-team/slides/2024-03-04/slides-2024-03-04-101500.zip")
+        );
+        assert_eq!(
+            std::fs::read(&report.downloads[0].1.path).unwrap(),
+            b"archive"
+        );
+        assert_eq!(report.failures.len(), 1);
+        assert_eq!(report.failures[0].dataset_slug, "broken");
+    }
+
+    #[tokio::test]
+    async fn test_copy_annotations() {
+        let mock_server = MockServer::start().await;
+        let item = |id: &str, slot: &str| DatasetItemV2 {
+            id: Some(id.to_string()),
+            slots: vec![Some(ItemSlot {
+                slot_name: Some(slot.to_string()),
+                ..Faker.fake()
+            })],
+            ..Default::default()
+        };
+
+        Mock::given(method("GET"))
+            .and(path("/v2/teams/some-team/items/original/annotations"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!([
+                {
+                    "id": "annotation-1",
+                    "annotation_class_id": 1,
+                    "context_keys": {"slot_names": ["scan-1"]},
+                    "data": {"polygon": {"paths": [[{"x": 1.0, "y": 2.0}]]}}
+                },
+                {
+                    "id": "annotation-2",
+                    "annotation_class_id": 2,
+                    "context_keys": {"slot_names": ["scan-1"]},
+                    "data": {"tag": {}}
+                }
+            ])))
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/v2/teams/some-team/items/rescan/import"))
+            .and(body_partial_json(json!({
+                "annotations": [
+                    {
+                        "annotation_class_id": 10,
+                        "context_keys": {"slot_names": ["scan-2"]},
+                        "data": {"polygon": {"paths": [[{"x": 1.0, "y": 2.0}]]}}
+                    },
+                    {
+                        "annotation_class_id": 2,
+                        "context_keys": {"slot_names": ["scan-2"]},
+                        "data": {"tag": {}}
+                    }
+                ],
+                "overwrite": false
+            })))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let client: V7Client = V7Client::new(
+            format!("{}/", mock_server.uri()),
+            "api-key".to_string(),
+            "some-team".to_string(),
+        )
+        .expect("Failed to get V7Client");
+
+        let copied = copy_annotations(
+            &client,
+            &item("original", "scan-1"),
+            &item("rescan", "scan-2"),
+            &HashMap::from([(ClassId(1), ClassId(10))]),
+        )
+        .await
+        .expect("Failed to copy annotations");
+        assert_eq!(copied, 2);
+    }
+
+    #[tokio::test]
+    async fn test_retire_annotation_class() {
+        let mock_server = MockServer::start().await;
+        let item = DatasetItemV2 {
+            id: Some("item-1".to_string()),
+            ..Default::default()
+        };
+        let class = AnnotationClass {
+            id: Some(ClassId(5)),
+            name: Some("Tumor".to_string()),
+            ..Default::default()
+        };
+
+        Mock::given(method("GET"))
+            .and(path("/v2/teams/some-team/items/item-1/annotations"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!([
+                {
+                    "id": "annotation-1",
+                    "annotation_class_id": 5,
+                    "context_keys": {"slot_names": ["0"]},
+                    "data": {"tag": {}}
+                },
+                {
+                    "id": "annotation-2",
+                    "annotation_class_id": 6,
+                    "context_keys": {"slot_names": ["0"]},
+                    "data": {"tag": {}}
+                }
+            ])))
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/v2/teams/some-team/items/item-1/import"))
+            .and(body_partial_json(json!({
+                "annotations": [{"annotation_class_id": 9, "data": {"tag": {}}}]
+            })))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("DELETE"))
+            .and(path("/v2/teams/some-team/items/item-1/annotations"))
+            .and(body_json(json!({"annotation_ids": ["annotation-1"]})))
+            .respond_with(ResponseTemplate::new(204))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("PUT"))
+            .and(path("/annotation_classes/5"))
+            .and(body_partial_json(json!({"id": 5, "archived": true})))
+            .respond_with(ResponseTemplate::new(200).set_body_json(AnnotationClass {
+                archived: Some(true),
+                ..class.clone()
+            }))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let client: V7Client = V7Client::new(
+            format!("{}/", mock_server.uri()),
+            "api-key".to_string(),
+            "some-team".to_string(),
+        )
+        .expect("Failed to get V7Client");
+
+        let reassigned = retire_annotation_class(&client, &class, &[item], Some(ClassId(9)))
+            .await
+            .expect("Failed to retire annotation class");
+        assert_eq!(reassigned, 1);
+    }
+
+    #[tokio::test]
+    async fn test_retire_annotation_class_restores_on_failure() {
+        let mock_server = MockServer::start().await;
+        let item = DatasetItemV2 {
+            id: Some("item-1".to_string()),
+            ..Default::default()
+        };
+        let class = AnnotationClass {
+            id: Some(ClassId(5)),
+            name: Some("Tumor".to_string()),
+            ..Default::default()
+        };
+
+        Mock::given(method("GET"))
+            .and(path("/v2/teams/some-team/items/item-1/annotations"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!([{
+                "id": "annotation-1",
+                "annotation_class_id": 5,
+                "context_keys": {"slot_names": ["0"]},
+                "data": {"tag": {}}
+            }])))
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("DELETE"))
+            .and(path("/v2/teams/some-team/items/item-1/annotations"))
+            .respond_with(ResponseTemplate::new(204))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/v2/teams/some-team/items/item-1/import"))
+            .and(body_partial_json(json!({
+                "annotations": [{"annotation_class_id": 9}]
+            })))
+            .respond_with(ResponseTemplate::new(500))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/v2/teams/some-team/items/item-1/import"))
+            .and(body_partial_json(json!({
+                "annotations": [{"annotation_class_id": 5}]
+            })))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("PUT"))
+            .and(path("/annotation_classes/5"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(class.clone()))
+            .expect(0)
+            .mount(&mock_server)
+            .await;
+
+        let client: V7Client = V7Client::new(
+            format!("{}/", mock_server.uri()),
+            "api-key".to_string(),
+            "some-team".to_string(),
+        )
+        .expect("Failed to get V7Client");
+
+        let error = retire_annotation_class(&client, &class, &[item], Some(ClassId(9)))
+            .await
+            .expect_err("Failed to import the replacement annotations");
+        assert!(error.to_string().contains("the originals were restored"));
+    }
+
+    #[tokio::test]
+    async fn test_ensure_annotation_classes() {
+        let mock_server = MockServer::start().await;
+        let class = |id: u32, name: &str, types: &[&str], color: &str| AnnotationClass {
+            id: Some(ClassId(id)),
+            name: Some(name.to_string()),
+            annotation_types: types.iter().map(|t| Some(t.to_string())).collect(),
+            metadata: Some(AnnotationClassMetadata {
+                color: Some(color.to_string()),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        let classes = TeamAnnotationClasses {
+            annotation_classes: vec![
+                Some(class(
+                    1,
+                    "Tumour",
+                    &["polygon", "attributes"],
+                    "rgba(255, 0, 0, 1.0)",
+                )),
+                Some(class(2, "Stroma", &["polygon"], "rgba(0,255,0,1.0)")),
+                Some(class(3, "Legacy", &["tag"], "rgba(0,0,255,1.0)")),
+                Some(AnnotationClass {
+                    archived: Some(true),
+                    ..class(4, "Retired", &["tag"], "rgba(0,0,0,1.0)")
+                }),
+            ],
+            type_counts: vec![],
+            page: None,
+        };
+
+        Mock::given(method("GET"))
+            .and(path("/teams/some-team/annotation_classes"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(classes))
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("PUT"))
+            .and(path("/annotation_classes/2"))
+            .and(body_partial_json(json!({
+                "annotation_types": ["polygon"],
+                "metadata": {"_color": "rgba(0,128,0,1.0)"}
+            })))
+            .respond_with(ResponseTemplate::new(200).set_body_json(AnnotationClass::default()))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/teams/some-team/annotation_classes"))
+            .and(body_partial_json(json!({
+                "name": "Nucleus",
+                "annotation_types": ["ellipse", "text"]
+            })))
+            .respond_with(ResponseTemplate::new(200).set_body_json(AnnotationClass::default()))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let client: V7Client = V7Client::new(
+            format!("{}/", mock_server.uri()),
+            "api-key".to_string(),
+            "some-team".to_string(),
+        )
+        .expect("Failed to get V7Client");
+        let team = Team::new("some-team".to_string(), None, None, None);
+        let spec = |name: &str, annotation_type: &str, subtypes: &[&str], color: &str| ClassSpec {
+            name: name.to_string(),
+            annotation_type: annotation_type.to_string(),
+            subtypes: subtypes.iter().map(|t| t.to_string()).collect(),
+            color: Some(color.to_string()),
+            description: None,
+        };
+        let specs = vec![
+            spec("Tumour", "polygon", &["attributes"], "RGBA(255,0,0,1.0)"),
+            spec("Stroma", "polygon", &[], "rgba(0,128,0,1.0)"),
+            spec("Nucleus", "ellipse", &["text"], "rgba(1,2,3,1.0)"),
+        ];
+
+        let report = ensure_annotation_classes(&client, &team, &specs)
+            .await
+            .expect("Failed to ensure annotation classes");
+        assert_eq!(
+            report,
+            ClassSyncReport {
+                created: vec!["Nucleus".to_string()],
+                updated: vec!["Stroma".to_string()],
+                unchanged: vec!["Tumour".to_string()],
+                unmanaged: vec!["Legacy".to_string()],
+            }
+        );
+
+        let duplicated = vec![specs[0].clone(), specs[0].clone()];
+        assert!(ensure_annotation_classes(&client, &team, &duplicated)
+            .await
+            .is_err());
+    }
+
+    #[test]
+    fn test_provisioning_spec() {
+        let spec = ProvisioningSpec::from_yaml(
+            r#"
+datasets:
+  - name: biopsies
+    storage_slug: s3-slides
+    storage_prefix: biopsies/
+    classes:
+      - name: Tumour
+        annotation_type: polygon
+"#,
+        )
+        .expect("Failed to parse spec");
+        let json = serde_json::to_string(&spec).unwrap();
+        let from_json = ProvisioningSpec::from_json(&json).expect("Failed to parse json spec");
+        assert_eq!(from_json.datasets[0].classes, spec.datasets[0].classes);
+
+        let mut item = existing_item("slide.svs");
+        item.slots.push(Slot {
+            storage_key: "2024/slide.svs".to_string(),
+            ..Faker.fake()
+        });
+        item.slots[0].storage_thumbnail_key = String::new();
+        let manifest = spec.datasets[0]
+            .manifest(vec![item])
+            .expect("Failed to build manifest");
+        assert_eq!(manifest.storage_slug, "s3-slides");
+        assert_eq!(
+            manifest.items[0].slots[0].storage_key,
+            "biopsies/2024/slide.svs"
+        );
+        assert_eq!(manifest.items[0].slots[0].storage_thumbnail_key, "");
+
+        let conflicting = ProvisioningSpec::from_yaml(
+            r#"
+datasets:
+  - name: a
+    classes: [{name: Tumour, annotation_type: polygon}]
+  - name: b
+    classes: [{name: Tumour, annotation_type: tag}]
+"#,
+        )
+        .unwrap();
+        assert!(conflicting.classes().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_provision_from_spec() {
+        let mock_server = MockServer::start().await;
+        let existing = Dataset {
+            id: Some(1),
+            name: Some("biopsies".to_string()),
+            slug: Some("biopsies".to_string()),
+            team_slug: Some("some-team".to_string()),
+            instructions: Some("<p>Outline tumours</p>".to_string()),
+            ..Default::default()
+        };
+        let classes = TeamAnnotationClasses {
+            annotation_classes: vec![Some(AnnotationClass {
+                id: Some(ClassId(10)),
+                name: Some("Tumour".to_string()),
+                annotation_types: vec![Some("polygon".to_string())],
+                datasets: vec![Some(AnnotationDataset { id: Some(1) })],
+                ..Default::default()
+            })],
+            type_counts: vec![],
+            page: None,
+        };
+
+        Mock::given(method("GET"))
+            .and(path("/teams/some-team/annotation_classes"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(classes))
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/datasets"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(vec![existing.clone()]))
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/datasets/1"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(existing))
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/datasets"))
+            .and(body_json(json!({"name": "resections"})))
+            .respond_with(ResponseTemplate::new(200).set_body_json(Dataset {
+                id: Some(2),
+                name: Some("resections".to_string()),
+                slug: Some("resections".to_string()),
+                team_slug: Some("some-team".to_string()),
+                ..Default::default()
+            }))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("PUT"))
+            .and(path("/annotation_classes/10"))
+            .and(body_partial_json(
+                json!({"datasets": [{"id": 1}, {"id": 2}]}),
+            ))
+            .respond_with(ResponseTemplate::new(200).set_body_json(AnnotationClass::default()))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/v2/teams/some-team/workflows"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!([])))
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/v2/teams/some-team/workflows"))
+            .and(body_partial_json(json!({
+                "stages": [{"type": "dataset", "config": {"dataset_id": 2}}]
+            })))
+            .respond_with(ResponseTemplate::new(201).set_body_json(WorkflowV2::default()))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let client: V7Client = V7Client::new(
+            format!("{}/", mock_server.uri()),
+            "api-key".to_string(),
+            "some-team".to_string(),
+        )
+        .expect("Failed to get V7Client");
+
+        let spec = ProvisioningSpec::from_yaml(
+            r#"
+datasets:
+  - name: biopsies
+    instructions: <p>Outline tumours</p>
+    classes: &classes
+      - name: Tumour
+        annotation_type: polygon
+  - name: resections
+    classes: *classes
+    workflow:
+      stages:
+        - id: 00000000-0000-0000-0000-000000000001
+          name: Dataset
+          type: dataset
+          assignable_users: []
+          edges: []
+"#,
+        )
+        .expect("Failed to parse spec");
+
+        let report = provision_from_spec(&client, &spec)
+            .await
+            .expect("Failed to provision datasets");
+        assert_eq!(report.classes.unchanged, vec!["Tumour".to_string()]);
+        assert!(!report.datasets[0].created);
+        assert!(report.datasets[0].linked_classes.is_empty());
+        assert!(!report.datasets[0].instructions_updated);
+        assert!(report.datasets[1].created);
+        assert_eq!(
+            report.datasets[1].linked_classes,
+            vec!["Tumour".to_string()]
+        );
+        assert!(report.datasets[1].workflow_created);
+    }
+
+    #[tokio::test]
+    async fn test_provision_shared_class_and_unchanged_workflow() {
+        let mock_server = MockServer::start().await;
+        let existing = Dataset {
+            id: Some(1),
+            name: Some("biopsies".to_string()),
+            ..Default::default()
+        };
+        let classes = TeamAnnotationClasses {
+            annotation_classes: vec![Some(AnnotationClass {
+                id: Some(ClassId(10)),
+                name: Some("Tumour".to_string()),
+                annotation_types: vec![Some("polygon".to_string())],
+                datasets: vec![Some(AnnotationDataset { id: Some(1) })],
+                ..Default::default()
+            })],
+            type_counts: vec![],
+            page: None,
+        };
+
+        Mock::given(method("GET"))
+            .and(path("/teams/some-team/annotation_classes"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(classes))
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/datasets"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(vec![existing.clone()]))
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/datasets/1"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(existing))
+            .mount(&mock_server)
+            .await;
+        for (id, name) in [(2, "resections"), (3, "excisions")] {
+            Mock::given(method("POST"))
+                .and(path("/datasets"))
+                .and(body_json(json!({ "name": name })))
+                .respond_with(ResponseTemplate::new(200).set_body_json(Dataset {
+                    id: Some(id),
+                    name: Some(name.to_string()),
+                    ..Default::default()
+                }))
+                .expect(1)
+                .mount(&mock_server)
+                .await;
+        }
+        // The second link keeps the first, the classes are not listed again
+        Mock::given(method("PUT"))
+            .and(path("/annotation_classes/10"))
+            .and(body_partial_json(
+                json!({"datasets": [{"id": 1}, {"id": 2}, {"id": 3}]}),
+            ))
+            .respond_with(ResponseTemplate::new(200).set_body_json(AnnotationClass::default()))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("PUT"))
+            .and(path("/annotation_classes/10"))
+            .and(body_partial_json(
+                json!({"datasets": [{"id": 1}, {"id": 2}]}),
+            ))
+            .respond_with(ResponseTemplate::new(200).set_body_json(AnnotationClass::default()))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+        // The existing workflow has the stages of the spec, with the defaults V7 fills in
+        Mock::given(method("GET"))
+            .and(path("/v2/teams/some-team/workflows"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!([{
+                "id": "workflow-1",
+                "name": "Biopsies",
+                "dataset": {"id": 1, "name": "biopsies"},
+                "thumbnails": [],
+                "stages": [{
+                    "id": "00000000-0000-0000-0000-000000000001",
+                    "name": "Dataset",
+                    "type": "dataset",
+                    "assignable_users": [],
+                    "edges": [],
+                    "config": {"dataset_id": 1, "readonly": false}
+                }]
+            }])))
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("PUT"))
+            .and(path("/v2/teams/some-team/workflows/workflow-1"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(WorkflowV2::default()))
+            .expect(0)
+            .mount(&mock_server)
+            .await;
+
+        let client: V7Client = V7Client::new(
+            format!("{}/", mock_server.uri()),
+            "api-key".to_string(),
+            "some-team".to_string(),
+        )
+        .expect("Failed to get V7Client");
+
+        let spec = ProvisioningSpec::from_yaml(
+            r#"
+datasets:
+  - name: biopsies
+    classes: &classes
+      - name: Tumour
+        annotation_type: polygon
+    workflow:
+      name: Biopsies
+      stages:
+        - id: 00000000-0000-0000-0000-000000000001
+          name: Dataset
+          type: dataset
+          assignable_users: []
+          edges: []
+  - name: resections
+    classes: *classes
+  - name: excisions
+    classes: *classes
+"#,
+        )
+        .expect("Failed to parse spec");
+
+        let report = provision_from_spec(&client, &spec)
+            .await
+            .expect("Failed to provision datasets");
+        assert!(!report.datasets[0].workflow_updated);
+        assert!(!report.datasets[0].workflow_created);
+        for dataset in &report.datasets[1..] {
+            assert!(dataset.created);
+            assert_eq!(dataset.linked_classes, vec!["Tumour".to_string()]);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_sync_dataset() {
+        let mock_server = MockServer::start().await;
+        let dataset = Dataset {
+            id: Some(1),
+            name: Some("some-dataset".to_string()),
+            slug: Some("some-dataset".to_string()),
+            team_slug: Some("some-team".to_string()),
+            ..Default::default()
+        };
+        let items = Item {
+            items: vec![Some(DatasetItemV2 {
+                name: Some("registered.png".to_string()),
+                path: Some("/".to_string()),
+                ..Default::default()
+            })],
+            ..Default::default()
+        };
+        let classes = TeamAnnotationClasses {
+            annotation_classes: vec![Some(AnnotationClass {
+                id: Some(ClassId(10)),
+                name: Some("existing".to_string()),
+                ..Default::default()
+            })],
+            type_counts: vec![],
+            page: None,
+        };
+        let created_class = AnnotationClass {
+            id: Some(ClassId(11)),
+            name: Some("new".to_string()),
+            ..Default::default()
+        };
+
+        Mock::given(method("GET"))
+            .and(path("/datasets"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(vec![dataset.clone()]))
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/v2/teams/some-team/items"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(items))
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/v2/teams/some-team/items/register_existing_readonly"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "blocked_items": [{"name": "blocked.png", "path": "/", "slots": []}],
+                "items": []
+            })))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/teams/some-team/annotation_classes"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(classes))
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("PUT"))
+            .and(path("/annotation_classes/10"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(AnnotationClass::default()))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/teams/some-team/annotation_classes"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(created_class))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let client: V7Client = V7Client::new(
+            format!("{}/", mock_server.uri()),
+            "api-key".to_string(),
+            "some-team".to_string(),
+        )
+        .expect("Failed to get V7Client");
+
+        let manifest = DatasetManifest {
+            dataset_name: "some-dataset".to_string(),
+            storage_slug: "some-storage".to_string(),
+            items: vec![
+                existing_item("registered.png"),
+                existing_item("new.png"),
+                existing_item("new.png"),
+                existing_item("blocked.png"),
+            ],
+            duplicates: DuplicatePolicy::KeepFirst,
+            annotation_classes: vec![
+                AnnotationClass {
+                    name: Some("existing".to_string()),
+                    ..Default::default()
+                },
+                AnnotationClass {
+                    name: Some("new".to_string()),
+                    ..Default::default()
+                },
+            ],
+            workflow: None,
+        };
+
+        let events = std::sync::Mutex::new(Vec::new());
+        let report = sync_dataset_with_progress(&client, &manifest, &|event| {
+            events.lock().unwrap().push(event)
+        })
+        .await
+        .expect("Failed to sync dataset");
+        assert!(events
+            .into_inner()
+            .unwrap()
+            .contains(&ProgressEvent::ItemsProcessed {
+                done: 2,
+                total: Some(2)
+            }));
+
+        assert!(!report.dataset_created);
+        assert_eq!(report.registered_items, vec!["new.png".to_string()]);
+        assert_eq!(report.created_classes, vec!["new".to_string()]);
+        assert_eq!(report.linked_classes, vec!["existing".to_string()]);
+        assert!(!report.workflow_updated);
+    }
+
+    #[tokio::test]
+    async fn test_import_annotations_creates_tag_classes() {
+        let mock_server = MockServer::start().await;
+        let dataset = Dataset {
+            id: Some(1),
+            slug: Some("some-dataset".to_string()),
+            team_slug: Some("some-team".to_string()),
+            ..Default::default()
+        };
+        let classes = TeamAnnotationClasses {
+            annotation_classes: vec![Some(AnnotationClass {
+                id: Some(ClassId(10)),
+                name: Some("Tumour".to_string()),
+                ..Default::default()
+            })],
+            type_counts: vec![],
+            page: None,
+        };
+        let tag = |name: &str| ImageAnnotation {
+            name: name.to_string(),
+            tag: Some(Tag::default()),
+            ..Default::default()
+        };
+        let items = vec![ItemAnnotations {
+            item_id: ItemId::from("item-1"),
+            slot_name: "0".to_string(),
+            annotations: vec![tag("tumour"), tag("Blurry"), tag("Blurry")],
+        }];
+
+        Mock::given(method("GET"))
+            .and(path("/teams/some-team/annotation_classes"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(classes))
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/teams/some-team/annotation_classes"))
+            .and(body_partial_json(json!({
+                "name": "Blurry",
+                "annotation_types": ["tag"],
+                "datasets": [{"id": 1}]
+            })))
+            .respond_with(ResponseTemplate::new(200).set_body_json(AnnotationClass {
+                id: Some(ClassId(11)),
+                name: Some("Blurry".to_string()),
+                ..Default::default()
+            }))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/v2/teams/some-team/items/item-1/import"))
+            .and(body_partial_json(json!({
+                "annotations": [
+                    {"annotation_class_id": 10},
+                    {"annotation_class_id": 11},
+                    {"annotation_class_id": 11}
+                ]
+            })))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let client: V7Client = V7Client::new(
+            format!("{}/", mock_server.uri()),
+            "api-key".to_string(),
+            "some-team".to_string(),
+        )
+        .expect("Failed to get V7Client");
+
+        let mut options = ImportOptions {
+            matching: ClassMatching::new(crate::imports::ClassNameMatch::CaseInsensitive),
+            create_missing_tag_classes: true,
+            dry_run: true,
+            ..Default::default()
+        };
+        let report = import_annotations(&client, &dataset, &items, &options)
+            .await
+            .expect("Failed to plan import");
+        assert_eq!(report.created_classes, vec!["Blurry".to_string()]);
+        assert_eq!(report.imported, 0);
+
+        options.dry_run = false;
+        let events = std::sync::Mutex::new(Vec::new());
+        let report =
+            import_annotations_with_progress(&client, &dataset, &items, &options, &|event| {
+                events.lock().unwrap().push(event)
+            })
+            .await
+            .expect("Failed to import annotations");
+        assert_eq!(report.created_classes, vec!["Blurry".to_string()]);
+        assert_eq!(report.imported, 3);
+        let events = events.into_inner().unwrap();
+        let total = Some(items.len() as u64);
+        assert_eq!(
+            events.first(),
+            Some(&ProgressEvent::Started {
+                operation: "annotation import",
+                total
+            })
+        );
+        assert!(events.contains(&ProgressEvent::ItemsProcessed {
+            done: items.len() as u64,
+            total
+        }));
+        assert_eq!(
+            events.last(),
+            Some(&ProgressEvent::Finished {
+                operation: "annotation import"
+            })
+        );
+    }
+}
@@ -0,0 +1,347 @@
+//! A minimal Parquet writer for the flat tables of `analytics`, so that they can be loaded
+//! into a data warehouse without depending on the arrow crates.
+//!
+//! Every column is optional and written as a single uncompressed, plain encoded data page
+//! of a single row group, see https://github.com/apache/parquet-format. The file metadata
+//! is encoded with the Thrift compact protocol.
+
+use anyhow::{bail, Result};
+
+const MAGIC: &[u8] = b"PAR1";
+
+// Physical types
+const BOOLEAN: i32 = 0;
+const INT64: i32 = 2;
+const FLOAT: i32 = 4;
+const BYTE_ARRAY: i32 = 6;
+
+const OPTIONAL: i32 = 1;
+const UTF8: i32 = 0;
+const PLAIN: i32 = 0;
+const RLE: i32 = 3;
+const UNCOMPRESSED: i32 = 0;
+const DATA_PAGE: i32 = 0;
+
+/// The values of a column, `None` for a null
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Values {
+    Utf8(Vec<Option<String>>),
+    Float(Vec<Option<f32>>),
+    Int64(Vec<Option<i64>>),
+    Boolean(Vec<Option<bool>>),
+}
+
+impl Values {
+    fn len(&self) -> usize {
+        match self {
+            Values::Utf8(values) => values.len(),
+            Values::Float(values) => values.len(),
+            Values::Int64(values) => values.len(),
+            Values::Boolean(values) => values.len(),
+        }
+    }
+
+    fn physical_type(&self) -> i32 {
+        match self {
+            Values::Utf8(_) => BYTE_ARRAY,
+            Values::Float(_) => FLOAT,
+            Values::Int64(_) => INT64,
+            Values::Boolean(_) => BOOLEAN,
+        }
+    }
+
+    fn defined(&self) -> Vec<bool> {
+        match self {
+            Values::Utf8(values) => values.iter().map(Option::is_some).collect(),
+            Values::Float(values) => values.iter().map(Option::is_some).collect(),
+            Values::Int64(values) => values.iter().map(Option::is_some).collect(),
+            Values::Boolean(values) => values.iter().map(Option::is_some).collect(),
+        }
+    }
+
+    /// The non-null values, plain encoded
+    fn plain(&self) -> Vec<u8> {
+        let mut buffer = Vec::new();
+        match self {
+            Values::Utf8(values) => {
+                for value in values.iter().flatten() {
+                    buffer.extend((value.len() as u32).to_le_bytes());
+                    buffer.extend(value.as_bytes());
+                }
+            }
+            Values::Float(values) => {
+                for value in values.iter().flatten() {
+                    buffer.extend(value.to_le_bytes());
+                }
+            }
+            Values::Int64(values) => {
+                for value in values.iter().flatten() {
+                    buffer.extend(value.to_le_bytes());
+                }
+            }
+            Values::Boolean(values) => {
+                // Bit packed, least significant bit first
+                for (index, value) in values.iter().flatten().enumerate() {
+                    if index % 8 == 0 {
+                        buffer.push(0);
+                    }
+                    if *value {
+                        *buffer.last_mut().unwrap() |= 1 << (index % 8);
+                    }
+                }
+            }
+        }
+        buffer
+    }
+}
+
+/// Writes `columns` as a Parquet file of a single row group, all columns must have the
+/// same number of values
+pub(crate) fn write_parquet(columns: &[(&str, Values)]) -> Result<Vec<u8>> {
+    let num_rows = columns.first().map_or(0, |(_, values)| values.len());
+    if let Some((name, _)) = columns.iter().find(|(_, values)| values.len() != num_rows) {
+        bail!("Column {name} does not have {num_rows} values");
+    }
+
+    let mut file = MAGIC.to_vec();
+    let mut chunks = Vec::new();
+    for (_, values) in columns {
+        let mut page = definition_levels(&values.defined());
+        page.extend(values.plain());
+
+        let mut header = CompactWriter::default();
+        header.i32_field(1, DATA_PAGE);
+        header.i32_field(2, page.len() as i32);
+        header.i32_field(3, page.len() as i32);
+        header.struct_field(5);
+        header.i32_field(1, num_rows as i32);
+        header.i32_field(2, PLAIN);
+        header.i32_field(3, RLE);
+        header.i32_field(4, RLE);
+        header.struct_end();
+        header.struct_end();
+
+        let offset = file.len() as i64;
+        let size = (header.buffer.len() + page.len()) as i64;
+        file.extend(header.buffer);
+        file.extend(page);
+        chunks.push((offset, size));
+    }
+
+    let mut metadata = CompactWriter::default();
+    metadata.i32_field(1, 1);
+    metadata.list_field(2, STRUCT, columns.len() + 1);
+    metadata.struct_element();
+    metadata.binary_field(4, b"schema");
+    metadata.i32_field(5, columns.len() as i32);
+    metadata.struct_end();
+    for (name, values) in columns {
+        metadata.struct_element();
+        metadata.i32_field(1, values.physical_type());
+        metadata.i32_field(3, OPTIONAL);
+        metadata.binary_field(4, name.as_bytes());
+        if let Values::Utf8(_) = values {
+            metadata.i32_field(6, UTF8);
+        }
+        metadata.struct_end();
+    }
+    metadata.i64_field(3, num_rows as i64);
+    metadata.list_field(4, STRUCT, 1);
+    metadata.struct_element();
+    metadata.list_field(1, STRUCT, columns.len());
+    for ((name, values), (offset, size)) in columns.iter().zip(&chunks) {
+        metadata.struct_element();
+        metadata.i64_field(2, *offset);
+        metadata.struct_field(3);
+        metadata.i32_field(1, values.physical_type());
+        metadata.list_field(2, I32, 2);
+        metadata.i32_element(PLAIN);
+        metadata.i32_element(RLE);
+        metadata.list_field(3, BINARY, 1);
+        metadata.binary_element(name.as_bytes());
+        metadata.i32_field(4, UNCOMPRESSED);
+        metadata.i64_field(5, num_rows as i64);
+        metadata.i64_field(6, *size);
+        metadata.i64_field(7, *size);
+        metadata.i64_field(9, *offset);
+        metadata.struct_end();
+        metadata.struct_end();
+    }
+    metadata.i64_field(2, chunks.iter().map(|(_, size)| size).sum());
+    metadata.i64_field(3, num_rows as i64);
+    metadata.struct_end();
+    metadata.binary_field(6, b"darwin-v7");
+    metadata.struct_end();
+
+    file.extend(&metadata.buffer);
+    file.extend((metadata.buffer.len() as u32).to_le_bytes());
+    file.extend(MAGIC);
+    Ok(file)
+}
+
+/// Definition levels of an optional column, 1 for a value and 0 for a null, as runs of
+/// the RLE / bit packing hybrid encoding with a bit width of 1 preceded by their length
+fn definition_levels(defined: &[bool]) -> Vec<u8> {
+    let mut runs = Vec::new();
+    let mut levels = defined.iter().peekable();
+    while let Some(level) = levels.next() {
+        let mut count: u64 = 1;
+        while levels.next_if_eq(&level).is_some() {
+            count += 1;
+        }
+        put_varint(&mut runs, count << 1);
+        runs.push(*level as u8);
+    }
+    let mut buffer = (runs.len() as u32).to_le_bytes().to_vec();
+    buffer.extend(runs);
+    buffer
+}
+
+fn put_varint(buffer: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        buffer.push((value as u8 & 0x7f) | 0x80);
+        value >>= 7;
+    }
+    buffer.push(value as u8);
+}
+
+fn zigzag(value: i64) -> u64 {
+    ((value << 1) ^ (value >> 63)) as u64
+}
+
+// Thrift compact protocol types
+const I32: u8 = 5;
+const I64: u8 = 6;
+const BINARY: u8 = 8;
+const LIST: u8 = 9;
+const STRUCT: u8 = 12;
+
+/// Encodes Thrift structs with the compact protocol, fields must be written in increasing
+/// id order within a struct
+#[derive(Default)]
+struct CompactWriter {
+    buffer: Vec<u8>,
+    last_field: i16,
+    parents: Vec<i16>,
+}
+
+impl CompactWriter {
+    fn field(&mut self, id: i16, field_type: u8) {
+        let delta = id - self.last_field;
+        if (1..=15).contains(&delta) {
+            self.buffer.push(((delta as u8) << 4) | field_type);
+        } else {
+            self.buffer.push(field_type);
+            put_varint(&mut self.buffer, zigzag(id as i64));
+        }
+        self.last_field = id;
+    }
+
+    fn i32_field(&mut self, id: i16, value: i32) {
+        self.field(id, I32);
+        self.i32_element(value);
+    }
+
+    fn i64_field(&mut self, id: i16, value: i64) {
+        self.field(id, I64);
+        put_varint(&mut self.buffer, zigzag(value));
+    }
+
+    fn binary_field(&mut self, id: i16, value: &[u8]) {
+        self.field(id, BINARY);
+        self.binary_element(value);
+    }
+
+    fn list_field(&mut self, id: i16, element_type: u8, size: usize) {
+        self.field(id, LIST);
+        if size < 15 {
+            self.buffer.push(((size as u8) << 4) | element_type);
+        } else {
+            self.buffer.push(0xf0 | element_type);
+            put_varint(&mut self.buffer, size as u64);
+        }
+    }
+
+    fn struct_field(&mut self, id: i16) {
+        self.field(id, STRUCT);
+        self.struct_element();
+    }
+
+    fn i32_element(&mut self, value: i32) {
+        put_varint(&mut self.buffer, zigzag(value as i64));
+    }
+
+    fn binary_element(&mut self, value: &[u8]) {
+        put_varint(&mut self.buffer, value.len() as u64);
+        self.buffer.extend(value);
+    }
+
+    fn struct_element(&mut self) {
+        self.parents.push(self.last_field);
+        self.last_field = 0;
+    }
+
+    fn struct_end(&mut self) {
+        self.buffer.push(0);
+        self.last_field = self.parents.pop().unwrap_or_default();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_definition_levels() {
+        assert_eq!(definition_levels(&[]), vec![0, 0, 0, 0]);
+        assert_eq!(
+            definition_levels(&[true, true, false, true]),
+            vec![6, 0, 0, 0, 4, 1, 2, 0, 2, 1]
+        );
+        let mut buffer = Vec::new();
+        put_varint(&mut buffer, 300);
+        assert_eq!(buffer, vec![0xac, 0x02]);
+        assert_eq!((zigzag(-1), zigzag(1), zigzag(-2)), (1, 2, 3));
+    }
+
+    #[test]
+    fn test_write_parquet() {
+        let file = write_parquet(&[("n", Values::Int64(vec![Some(1), None]))]).unwrap();
+        #[rustfmt::skip]
+        let expected: Vec<u8> = [
+            &b"PAR1"[..],
+            // Page header: data page, 16 bytes, 2 values, plain values and RLE levels
+            &[0x15, 0x00, 0x15, 0x20, 0x15, 0x20, 0x2c, 0x15, 0x04, 0x15, 0x00, 0x15, 0x06, 0x15, 0x06, 0x00, 0x00],
+            // Definition levels: a run of one 1 and a run of one 0
+            &[0x04, 0x00, 0x00, 0x00, 0x02, 0x01, 0x02, 0x00],
+            &1i64.to_le_bytes(),
+            // File metadata: version 1
+            &[0x15, 0x02],
+            // Schema of the root and the optional int64 column
+            &[0x19, 0x2c, 0x48, 0x06], b"schema", &[0x15, 0x02, 0x00],
+            &[0x15, 0x04, 0x25, 0x02, 0x18, 0x01], b"n", &[0x00],
+            // 2 rows
+            &[0x16, 0x04],
+            // A row group of one column chunk at offset 4 and 33 bytes long
+            &[0x19, 0x1c, 0x19, 0x1c, 0x26, 0x08, 0x1c, 0x15, 0x04, 0x19, 0x25, 0x00, 0x06, 0x19, 0x18, 0x01],
+            b"n",
+            &[0x15, 0x00, 0x16, 0x04, 0x16, 0x42, 0x16, 0x42, 0x26, 0x08, 0x00, 0x00],
+            &[0x16, 0x42, 0x16, 0x04, 0x00],
+            &[0x28, 0x09], b"darwin-v7", &[0x00],
+        ]
+        .concat();
+        let metadata_len = expected.len() - 4 - 33;
+        assert_eq!(&file[..expected.len()], &expected[..]);
+        assert_eq!(
+            &file[expected.len()..],
+            &[&(metadata_len as u32).to_le_bytes()[..], b"PAR1"].concat()[..]
+        );
+
+        let error = write_parquet(&[
+            ("a", Values::Boolean(vec![Some(true)])),
+            ("b", Values::Float(vec![])),
+        ])
+        .unwrap_err();
+        assert_eq!(error.to_string(), "Column b does not have 1 values");
+    }
+}
@@ -0,0 +1,65 @@
+//! Progress reporting for the long running helpers, such as export downloads, item
+//! pagination and bulk registration, so callers can render progress bars or log milestones.
+
+use log::info;
+
+/// A unit of progress reported by a long running helper
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ProgressEvent {
+    /// An operation started, with the total amount of work when known
+    Started {
+        operation: &'static str,
+        total: Option<u64>,
+    },
+    /// Items (or pages of items) processed so far out of the total when known
+    ItemsProcessed { done: u64, total: Option<u64> },
+    /// Bytes transferred so far out of the total when known
+    BytesTransferred { done: u64, total: Option<u64> },
+    /// A request is being retried, `attempt` starts at 1 for the first retry
+    Retry { attempt: u32, reason: String },
+    /// The operation finished successfully
+    Finished { operation: &'static str },
+}
+
+/// Receives the progress events of a helper. Closures taking a `ProgressEvent`
+/// implement this trait, as does `NoProgress` for callers that are not interested.
+pub trait ProgressSink: Send + Sync {
+    fn event(&self, event: ProgressEvent);
+}
+
+impl<F> ProgressSink for F
+where
+    F: Fn(ProgressEvent) + Send + Sync,
+{
+    fn event(&self, event: ProgressEvent) {
+        self(event)
+    }
+}
+
+/// Discards all progress events
+#[derive(Debug, Default, Clone, Copy)]
+pub struct NoProgress;
+
+impl ProgressSink for NoProgress {
+    fn event(&self, _event: ProgressEvent) {}
+}
+
+/// Logs the start and end of operations and any retries at info level,
+/// intermediate progress is ignored to keep the logs readable
+#[derive(Debug, Default, Clone, Copy)]
+pub struct LogProgress;
+
+impl ProgressSink for LogProgress {
+    fn event(&self, event: ProgressEvent) {
+        match event {
+            ProgressEvent::Started {
+                operation,
+                total: Some(total),
+            } => info!("Started {operation} of {total}"),
+            ProgressEvent::Started { operation, .. } => info!("Started {operation}"),
+            ProgressEvent::Retry { attempt, reason } => info!("Retry {attempt}: {reason}"),
+            ProgressEvent::Finished { operation } => info!("Finished {operation}"),
+            ProgressEvent::ItemsProcessed { .. } | ProgressEvent::BytesTransferred { .. } => {}
+        }
+    }
+}
@@ -0,0 +1,221 @@
+//! Aggregation of item reports into time spent per annotator, stage and week,
+//! e.g. for ingestion into BI tools.
+
+use crate::datasets::ItemReport;
+use crate::utils::{iso_week, parse_timestamp};
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+
+/// A dimension to aggregate item reports by
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum GroupBy {
+    /// The annotator or reviewer email
+    Annotator,
+    /// Annotate or review stages
+    Stage,
+    /// ISO week in which the item was completed, or started if still in progress
+    Week,
+}
+
+/// The kind of stage that time was spent in
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ReportStage {
+    Annotate,
+    Review,
+}
+
+/// Total time spent for one group, fields not grouped by are `None`
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ReportTotals {
+    pub user: Option<String>,
+    pub stage: Option<ReportStage>,
+    /// ISO week e.g. `2023-W19`
+    pub week: Option<String>,
+    /// Number of distinct items worked on
+    pub item_count: u64,
+    pub seconds: f64,
+    pub automation_seconds: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+struct GroupKey {
+    user: Option<String>,
+    stage: Option<ReportStage>,
+    week: Option<String>,
+}
+
+fn report_week(report: &ItemReport) -> Result<Option<String>> {
+    let date = report
+        .workflow_complete_date
+        .as_ref()
+        .or(report.workflow_start_date.as_ref());
+    Ok(match date {
+        Some(date) => {
+            let (year, week) = iso_week(parse_timestamp(date)?);
+            Some(format!("{year}-W{week:02}"))
+        }
+        None => None,
+    })
+}
+
+fn users(emails: &Option<String>) -> Vec<String> {
+    emails
+        .as_deref()
+        .unwrap_or_default()
+        .split(';')
+        .map(str::trim)
+        .filter(|email| !email.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+/// Aggregates the time spent on items by the dimensions in `group_by`, ordered by
+/// user, stage and week.
+///
+/// Item reports only hold the total time per item, so when several annotators (or
+/// reviewers) worked on an item the time is split evenly between them. Items without
+/// any time spent in a stage do not contribute to that stage.
+pub fn aggregate_item_reports(
+    reports: &[ItemReport],
+    group_by: &[GroupBy],
+) -> Result<Vec<ReportTotals>> {
+    let mut groups: BTreeMap<GroupKey, (HashSet<usize>, ReportTotals)> = BTreeMap::new();
+
+    for (index, report) in reports.iter().enumerate() {
+        let week = if group_by.contains(&GroupBy::Week) {
+            report_week(report)?
+        } else {
+            None
+        };
+        let stages = [
+            (
+                ReportStage::Annotate,
+                report.time_spent_annotating_sec,
+                report.automation_time_annotating_sec,
+                users(&report.annotators),
+            ),
+            (
+                ReportStage::Review,
+                report.time_spent_reviewing_sec,
+                report.automation_time_reviewing_sec,
+                users(&report.reviewers),
+            ),
+        ];
+
+        for (stage, seconds, automation_seconds, users) in stages {
+            let (seconds, automation_seconds) = (
+                seconds.unwrap_or_default() as f64,
+                automation_seconds.unwrap_or_default() as f64,
+            );
+            if seconds == 0.0 && automation_seconds == 0.0 {
+                continue;
+            }
+            let users: Vec<Option<String>> = if users.is_empty() {
+                vec![None]
+            } else {
+                users.into_iter().map(Some).collect()
+            };
+            let share = users.len() as f64;
+
+            for user in users {
+                let key = GroupKey {
+                    user: user.filter(|_| group_by.contains(&GroupBy::Annotator)),
+                    stage: Some(stage).filter(|_| group_by.contains(&GroupBy::Stage)),
+                    week: week.clone(),
+                };
+                let (items, totals) = groups.entry(key.clone()).or_insert_with(|| {
+                    (
+                        HashSet::new(),
+                        ReportTotals {
+                            user: key.user,
+                            stage: key.stage,
+                            week: key.week,
+                            ..Default::default()
+                        },
+                    )
+                });
+                items.insert(index);
+                totals.seconds += seconds / share;
+                totals.automation_seconds += automation_seconds / share;
+            }
+        }
+    }
+
+    Ok(groups
+        .into_values()
+        .map(|(items, mut totals)| {
+            totals.item_count = items.len() as u64;
+            totals
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn report(annotators: &str, annotating: u64, reviewing: u64, completed: &str) -> ItemReport {
+        serde_json::from_value(json!({
+            "filename": "slide.svs",
+            "uploaded_date": null,
+            "status": "complete",
+            "workflow_start_date": "2023-05-01 09:00:00",
+            "workflow_complete_date": completed,
+            "number_of_frames": null,
+            "folder": "/",
+            "time_spent_annotating_sec": annotating,
+            "time_spent_reviewing_sec": reviewing,
+            "automation_time_annotating_sec": 0,
+            "automation_time_reviewing_sec": 0,
+            "annotators": annotators,
+            "reviewers": "reviewer@franklin.ai",
+            "was_rejected_in_review": false,
+            "url": null
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_aggregate_item_reports() {
+        let reports = vec![
+            report(
+                "a@franklin.ai;b@franklin.ai",
+                100,
+                10,
+                "2023-05-10 14:15:27",
+            ),
+            report("a@franklin.ai", 50, 0, "2023-05-17 08:00:00"),
+        ];
+
+        let totals =
+            aggregate_item_reports(&reports, &[GroupBy::Annotator, GroupBy::Stage]).unwrap();
+        let summary: Vec<(Option<&str>, Option<ReportStage>, f64, u64)> = totals
+            .iter()
+            .map(|t| (t.user.as_deref(), t.stage, t.seconds, t.item_count))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                (Some("a@franklin.ai"), Some(ReportStage::Annotate), 100.0, 2),
+                (Some("b@franklin.ai"), Some(ReportStage::Annotate), 50.0, 1),
+                (
+                    Some("reviewer@franklin.ai"),
+                    Some(ReportStage::Review),
+                    10.0,
+                    1
+                ),
+            ]
+        );
+
+        let weekly = aggregate_item_reports(&reports, &[GroupBy::Week]).unwrap();
+        assert_eq!(weekly.len(), 2);
+        assert_eq!(weekly[0].week.as_deref(), Some("2023-W19"));
+        assert_eq!(weekly[0].seconds, 110.0);
+        assert_eq!(weekly[0].item_count, 1);
+        assert_eq!(weekly[1].week.as_deref(), Some("2023-W20"));
+        assert_eq!(weekly[1].user, None);
+    }
+}
@@ -0,0 +1,207 @@
+//! The response and error of a request made through `V7Methods`, owned by this crate so
+//! that the HTTP client used to make requests can change without breaking `V7Methods`.
+//!
+//! Build an `HttpResponse` from an `http::Response` to implement `V7Methods` with another
+//! HTTP client or to mock V7 in tests.
+
+use bytes::Bytes;
+use futures::stream::{BoxStream, Stream, StreamExt, TryStreamExt};
+use serde::de::DeserializeOwned;
+use std::fmt::{self, Display};
+
+pub use http::{HeaderMap, StatusCode};
+
+type BoxError = Box<dyn std::error::Error + Send + Sync>;
+
+/// A response to a request made through `V7Methods`, the body is read as it is consumed
+#[derive(Debug)]
+pub struct HttpResponse {
+    inner: reqwest::Response,
+}
+
+impl HttpResponse {
+    pub(crate) fn from_reqwest(response: reqwest::Response) -> Self {
+        Self { inner: response }
+    }
+
+    /// A response whose body is read from `body` as it is consumed, e.g. the body of a
+    /// response of another HTTP client
+    pub fn streaming<S, B>(response: http::Response<()>, body: S) -> Self
+    where
+        S: Stream<Item = Result<B, HttpError>> + Send + 'static,
+        B: Into<Bytes> + 'static,
+    {
+        let body = body.map_ok(Into::into);
+        let (parts, ()) = response.into_parts();
+        let response = http::Response::from_parts(parts, reqwest::Body::wrap_stream(body));
+        Self::from_reqwest(response.into())
+    }
+
+    pub fn status(&self) -> StatusCode {
+        self.inner.status()
+    }
+
+    pub fn headers(&self) -> &HeaderMap {
+        self.inner.headers()
+    }
+
+    /// The length of the body if known in advance
+    pub fn content_length(&self) -> Option<u64> {
+        self.inner.content_length()
+    }
+
+    pub async fn bytes(self) -> Result<Bytes, HttpError> {
+        self.inner.bytes().await.map_err(HttpError::from_reqwest)
+    }
+
+    pub async fn text(self) -> Result<String, HttpError> {
+        self.inner.text().await.map_err(HttpError::from_reqwest)
+    }
+
+    pub async fn json<T: DeserializeOwned>(self) -> Result<T, HttpError> {
+        self.inner.json().await.map_err(HttpError::from_reqwest)
+    }
+
+    /// The next chunk of the body, `None` once it has been read
+    pub async fn chunk(&mut self) -> Result<Option<Bytes>, HttpError> {
+        self.inner.chunk().await.map_err(HttpError::from_reqwest)
+    }
+
+    pub fn bytes_stream(self) -> BoxStream<'static, Result<Bytes, HttpError>> {
+        self.inner
+            .bytes_stream()
+            .map_err(HttpError::from_reqwest)
+            .boxed()
+    }
+}
+
+impl From<http::Response<Vec<u8>>> for HttpResponse {
+    fn from(response: http::Response<Vec<u8>>) -> Self {
+        Self::from_reqwest(response.into())
+    }
+}
+
+impl From<http::Response<String>> for HttpResponse {
+    fn from(response: http::Response<String>) -> Self {
+        Self::from_reqwest(response.into())
+    }
+}
+
+/// A request made through `V7Methods` that failed before a response was received, or
+/// whose body could not be read
+#[derive(Debug)]
+pub struct HttpError {
+    timeout: bool,
+    connect: bool,
+    status: Option<StatusCode>,
+    source: BoxError,
+}
+
+impl HttpError {
+    pub fn new(error: impl Into<BoxError>) -> Self {
+        Self {
+            timeout: false,
+            connect: false,
+            status: None,
+            source: error.into(),
+        }
+    }
+
+    /// A request that did not complete in time
+    pub fn timeout(error: impl Into<BoxError>) -> Self {
+        Self {
+            timeout: true,
+            ..Self::new(error)
+        }
+    }
+
+    /// A request for which no connection could be established
+    pub fn connect(error: impl Into<BoxError>) -> Self {
+        Self {
+            connect: true,
+            ..Self::new(error)
+        }
+    }
+
+    pub fn is_timeout(&self) -> bool {
+        self.timeout
+    }
+
+    pub fn is_connect(&self) -> bool {
+        self.connect
+    }
+
+    /// The status of the response, if the error was caused by one
+    pub fn status(&self) -> Option<StatusCode> {
+        self.status
+    }
+
+    pub(crate) fn from_reqwest(error: reqwest::Error) -> Self {
+        Self {
+            timeout: error.is_timeout(),
+            connect: error.is_connect(),
+            status: error.status(),
+            source: error.into(),
+        }
+    }
+}
+
+impl Display for HttpError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.source)
+    }
+}
+
+impl std::error::Error for HttpError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&*self.source)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::stream;
+
+    #[tokio::test]
+    async fn test_http_response() {
+        let response = http::Response::builder()
+            .status(201)
+            .header("x-request-id", "request-1")
+            .body(r#"{"id": 1}"#.to_string())
+            .unwrap();
+        let response = HttpResponse::from(response);
+        assert_eq!(response.status(), StatusCode::CREATED);
+        assert_eq!(response.headers()["x-request-id"], "request-1");
+        let body: serde_json::Value = response.json().await.unwrap();
+        assert_eq!(body["id"], 1);
+
+        let chunks = stream::iter(vec![Ok("[1, "), Ok("2]")]);
+        let response = HttpResponse::streaming(http::Response::new(()), chunks);
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.text().await.unwrap(), "[1, 2]");
+
+        let chunks = stream::iter(vec![
+            Ok("[1, "),
+            Err(HttpError::timeout("Request timed out")),
+        ]);
+        let error = HttpResponse::streaming(http::Response::new(()), chunks)
+            .bytes()
+            .await
+            .unwrap_err();
+        // The error of the stream is the cause of the body error
+        let error = anyhow::Error::from(error);
+        assert!(format!("{error:#}").contains("timed out"), "{error:#}");
+    }
+
+    #[test]
+    fn test_http_error_source() {
+        use std::error::Error;
+
+        let error = HttpError::connect(std::io::Error::other("Connection refused"));
+        assert!(error.is_connect());
+        let source = error.source().expect("HttpError has a source");
+        assert_eq!(source.to_string(), "Connection refused");
+        assert!(source.is::<std::io::Error>());
+    }
+}
@@ -0,0 +1,439 @@
+//! A `tower::Service` adapter of `V7Methods`, so the calls to V7 can be wrapped in the
+//! same tower middleware (timeouts, retries, rate limits, metrics) as other HTTP
+//! dependencies of a service. `V7ServiceClient` goes the other way and implements
+//! `V7Methods` over the wrapped service, so the methods of this crate send their requests
+//! through the middleware.
+//!
+//! ```no_run
+//! # use darwin_v7::{client::V7Client, service::{V7Request, V7Service}};
+//! # use std::time::Duration;
+//! # use tower::ServiceExt;
+//! # async fn example(client: V7Client) -> Result<(), tower::BoxError> {
+//! let service = tower::ServiceBuilder::new()
+//!     .timeout(Duration::from_secs(30))
+//!     .service(V7Service::new(client));
+//! let response = service.oneshot(V7Request::get("datasets")).await?;
+//! # Ok(())
+//! # }
+//! ```
+use crate::client::{ApiVersion, ResourceCache, V7Client, V7Methods};
+use crate::response::{HttpError, HttpResponse};
+use async_trait::async_trait;
+use futures::future::{poll_fn, BoxFuture};
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use tower_service::Service;
+
+type BoxError = Box<dyn std::error::Error + Send + Sync>;
+
+/// A call to V7, the endpoint is relative to the API endpoint of the client
+#[derive(Debug, Clone, PartialEq)]
+pub enum V7Request {
+    Get {
+        endpoint: String,
+    },
+    Put {
+        endpoint: String,
+        body: Option<serde_json::Value>,
+    },
+    Post {
+        endpoint: String,
+        body: serde_json::Value,
+    },
+    Delete {
+        endpoint: String,
+        body: Option<serde_json::Value>,
+    },
+}
+
+impl V7Request {
+    pub fn get(endpoint: &str) -> Self {
+        Self::Get {
+            endpoint: endpoint.to_string(),
+        }
+    }
+
+    pub fn put(endpoint: &str, body: Option<serde_json::Value>) -> Self {
+        Self::Put {
+            endpoint: endpoint.to_string(),
+            body,
+        }
+    }
+
+    pub fn post(endpoint: &str, body: serde_json::Value) -> Self {
+        Self::Post {
+            endpoint: endpoint.to_string(),
+            body,
+        }
+    }
+
+    pub fn delete(endpoint: &str, body: Option<serde_json::Value>) -> Self {
+        Self::Delete {
+            endpoint: endpoint.to_string(),
+            body,
+        }
+    }
+
+    pub fn method(&self) -> &'static str {
+        match self {
+            Self::Get { .. } => "GET",
+            Self::Put { .. } => "PUT",
+            Self::Post { .. } => "POST",
+            Self::Delete { .. } => "DELETE",
+        }
+    }
+
+    pub fn endpoint(&self) -> &str {
+        match self {
+            Self::Get { endpoint }
+            | Self::Put { endpoint, .. }
+            | Self::Post { endpoint, .. }
+            | Self::Delete { endpoint, .. } => endpoint,
+        }
+    }
+
+    /// Whether the request can be retried without changing state in V7, for use by
+    /// retry policies
+    pub fn is_idempotent(&self) -> bool {
+        !matches!(self, Self::Post { .. })
+    }
+}
+
+/// Sends `V7Request`s with a client. Clones share the client, so its connection pool
+/// is reused by services such as `tower::buffer` that clone the inner service.
+#[derive(Debug)]
+pub struct V7Service<C> {
+    client: Arc<C>,
+}
+
+impl<C> Clone for V7Service<C> {
+    fn clone(&self) -> Self {
+        Self {
+            client: self.client.clone(),
+        }
+    }
+}
+
+impl<C> V7Service<C> {
+    pub fn new(client: C) -> Self {
+        Self {
+            client: Arc::new(client),
+        }
+    }
+
+    pub fn from_arc(client: Arc<C>) -> Self {
+        Self { client }
+    }
+
+    pub fn client(&self) -> &C {
+        &self.client
+    }
+}
+
+impl<C> Service<V7Request> for V7Service<C>
+where
+    C: V7Methods + Send + Sync + 'static,
+{
+    type Response = HttpResponse;
+    type Error = HttpError;
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        // The client queues requests on its connection pool, so it is always ready
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, request: V7Request) -> Self::Future {
+        let client = self.client.clone();
+        Box::pin(async move {
+            match request {
+                V7Request::Get { endpoint } => client.get(&endpoint).await,
+                V7Request::Put { endpoint, body } => client.put(&endpoint, body.as_ref()).await,
+                V7Request::Post { endpoint, body } => client.post(&endpoint, &body).await,
+                V7Request::Delete { endpoint, body } => {
+                    client.delete(&endpoint, body.as_ref()).await
+                }
+            }
+        })
+    }
+}
+
+/// Implements `V7Methods` over a service of `V7Request`s, e.g. a `V7Service` wrapped in
+/// middleware. The service is cloned for each request, as is usual for tower services.
+/// Errors of the middleware, such as a timeout, are returned as an `HttpError` with the
+/// middleware error as its source.
+#[derive(Debug, Clone)]
+pub struct V7ServiceClient<S> {
+    service: S,
+    api_endpoint: String,
+    team: String,
+    api_version: ApiVersion,
+    cache: Option<Arc<ResourceCache>>,
+}
+
+impl<S> V7ServiceClient<S> {
+    /// A client of a V1 team without a cache, see `from_client` to take these from the
+    /// wrapped client
+    pub fn new(service: S, api_endpoint: String, team: String) -> Self {
+        Self {
+            service,
+            api_endpoint,
+            team,
+            api_version: ApiVersion::default(),
+            cache: None,
+        }
+    }
+
+    /// Takes the API endpoint, team, API version and cache of `client`, the client of the
+    /// `V7Service` wrapped by `service`
+    pub fn from_client(service: S, client: &V7Client) -> Self {
+        Self {
+            service,
+            api_endpoint: client.api_endpoint().to_string(),
+            team: client.team().clone(),
+            api_version: client.version(),
+            cache: client.shared_cache(),
+        }
+    }
+
+    /// Sets the API version of the team, so that V1 only methods are rejected for V2 teams
+    pub fn api_version(mut self, api_version: ApiVersion) -> Self {
+        self.api_version = api_version;
+        self
+    }
+
+    pub fn service(&self) -> &S {
+        &self.service
+    }
+
+    async fn send(&self, request: V7Request) -> Result<HttpResponse, HttpError>
+    where
+        S: Service<V7Request, Response = HttpResponse> + Clone + Send + Sync,
+        S::Error: Into<BoxError>,
+        S::Future: Send,
+    {
+        let mut service = self.service.clone();
+        poll_fn(|cx| service.poll_ready(cx))
+            .await
+            .map_err(into_http_error)?;
+        service.call(request).await.map_err(into_http_error)
+    }
+}
+
+// Keeps the errors of the inner `V7Service` as they are, so e.g. `is_timeout` still works
+fn into_http_error(error: impl Into<BoxError>) -> HttpError {
+    match error.into().downcast::<HttpError>() {
+        Ok(error) => *error,
+        Err(error) => HttpError::new(error),
+    }
+}
+
+fn to_body<S: serde::Serialize + ?Sized>(data: &S) -> Result<serde_json::Value, HttpError> {
+    serde_json::to_value(data).map_err(HttpError::new)
+}
+
+#[async_trait]
+impl<S> V7Methods for V7ServiceClient<S>
+where
+    S: Service<V7Request, Response = HttpResponse> + Clone + Send + Sync,
+    S::Error: Into<BoxError>,
+    S::Future: Send,
+{
+    async fn get(&self, endpoint: &str) -> Result<HttpResponse, HttpError> {
+        self.send(V7Request::get(endpoint)).await
+    }
+
+    async fn put<T: serde::Serialize + ?Sized + std::marker::Sync>(
+        &self,
+        endpoint: &str,
+        data: Option<&T>,
+    ) -> Result<HttpResponse, HttpError> {
+        let body = data.map(to_body).transpose()?;
+        self.send(V7Request::put(endpoint, body)).await
+    }
+
+    async fn post<T: serde::Serialize + ?Sized + std::marker::Sync>(
+        &self,
+        endpoint: &str,
+        data: &T,
+    ) -> Result<HttpResponse, HttpError> {
+        self.send(V7Request::post(endpoint, to_body(data)?)).await
+    }
+
+    async fn delete<T: serde::Serialize + ?Sized + std::marker::Sync>(
+        &self,
+        endpoint: &str,
+        data: Option<&T>,
+    ) -> Result<HttpResponse, HttpError> {
+        let body = data.map(to_body).transpose()?;
+        self.send(V7Request::delete(endpoint, body)).await
+    }
+
+    fn team(&self) -> &String {
+        &self.team
+    }
+
+    fn api_endpoint(&self) -> &str {
+        &self.api_endpoint
+    }
+
+    fn version(&self) -> ApiVersion {
+        self.api_version
+    }
+
+    fn cache(&self) -> Option<&ResourceCache> {
+        self.cache.as_deref()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::UnsupportedApiVersionError;
+    use crate::client::V7Client;
+    use crate::datasets::{Dataset, DatasetDescribeMethods, DatasetWorkflowMethods};
+    use crate::filter::Filter;
+    use serde_json::json;
+    use std::time::Duration;
+    use tower::{ServiceBuilder, ServiceExt};
+    use wiremock::matchers::{body_json, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    #[tokio::test]
+    async fn test_v7_service() {
+        let mock_server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/datasets"))
+            .and(body_json(json!({"name": "Biopsies"})))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({"id": 1})))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/datasets"))
+            .respond_with(ResponseTemplate::new(200).set_delay(Duration::from_millis(500)))
+            .mount(&mock_server)
+            .await;
+
+        let client = V7Client::new(
+            format!("{}/", mock_server.uri()),
+            "api-key".to_string(),
+            "some-team".to_string(),
+        )
+        .unwrap();
+        let service = ServiceBuilder::new()
+            .timeout(Duration::from_millis(50))
+            .service(V7Service::new(client));
+
+        let request = V7Request::post("datasets", json!({"name": "Biopsies"}));
+        assert!(!request.is_idempotent());
+        let response = service.clone().oneshot(request).await.unwrap();
+        assert_eq!(response.status(), 200);
+
+        let error = service
+            .oneshot(V7Request::get("datasets"))
+            .await
+            .unwrap_err();
+        assert!(error.is::<tower::timeout::error::Elapsed>(), "{error}");
+    }
+
+    #[tokio::test]
+    async fn test_v7_service_client() {
+        let mock_server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/datasets"))
+            .respond_with(
+                ResponseTemplate::new(200).set_body_json(json!([{"id": 1, "name": "Biopsies"}])),
+            )
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("PUT"))
+            .and(path("/datasets/1"))
+            .and(body_json(json!({"name": "Resections"})))
+            .respond_with(ResponseTemplate::new(200).set_delay(Duration::from_millis(500)))
+            .mount(&mock_server)
+            .await;
+
+        let client = V7Client::new(
+            format!("{}/", mock_server.uri()),
+            "api-key".to_string(),
+            "some-team".to_string(),
+        )
+        .unwrap();
+        let service = ServiceBuilder::new()
+            .timeout(Duration::from_millis(50))
+            .service(V7Service::new(client.clone()));
+        let service_client = V7ServiceClient::new(
+            service,
+            client.api_endpoint().to_string(),
+            client.team().clone(),
+        );
+
+        let datasets = Dataset::list_datasets(&service_client).await.unwrap();
+        assert_eq!(
+            datasets[0].as_ref().unwrap().name.as_deref(),
+            Some("Biopsies")
+        );
+
+        let error = service_client
+            .put("datasets/1", Some(&json!({"name": "Resections"})))
+            .await
+            .unwrap_err();
+        assert_eq!(error.to_string(), "request timed out");
+    }
+
+    #[tokio::test]
+    async fn test_v7_service_client_version_and_cache() {
+        let mock_server = MockServer::start().await;
+        Mock::given(method("PUT"))
+            .respond_with(ResponseTemplate::new(204))
+            .expect(0)
+            .mount(&mock_server)
+            .await;
+
+        let client = V7Client::builder(
+            format!("{}/", mock_server.uri()),
+            "api-key".to_string(),
+            "some-team".to_string(),
+        )
+        .api_version(ApiVersion::V2)
+        .cache_ttl(Duration::from_secs(60))
+        .build()
+        .unwrap();
+        let service_client = V7ServiceClient::from_client(V7Service::new(client.clone()), &client);
+        assert_eq!(service_client.version(), ApiVersion::V2);
+        assert!(service_client.cache().is_some());
+
+        let dataset = Dataset {
+            id: Some(3),
+            slug: Some("some-dataset".to_string()),
+            team_slug: Some("some-team".to_string()),
+            ..Default::default()
+        };
+        let error = dataset
+            .reset_to_new(&service_client, &Filter::default())
+            .await
+            .unwrap_err();
+        assert_eq!(
+            error.downcast_ref::<UnsupportedApiVersionError>(),
+            Some(&UnsupportedApiVersionError {
+                operation: "reset_to_new",
+                required: ApiVersion::V1,
+                version: ApiVersion::V2,
+            })
+        );
+
+        let service_client = V7ServiceClient::new(
+            V7Service::new(client.clone()),
+            client.api_endpoint().to_string(),
+            client.team().clone(),
+        );
+        assert_eq!(service_client.version(), ApiVersion::V1);
+        assert!(service_client.cache().is_none());
+        assert_eq!(
+            service_client.api_version(ApiVersion::V2).version(),
+            ApiVersion::V2
+        );
+    }
+}
@@ -0,0 +1,263 @@
+//! Streaming deserialization of large JSON responses, parsing each element of an array as
+//! it is downloaded rather than reading the whole body into a `String` first.
+
+use crate::response::HttpResponse;
+use anyhow::{Context, Result};
+use futures::stream::{self, BoxStream, StreamExt};
+use serde::de::DeserializeOwned;
+use std::collections::VecDeque;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ScanState {
+    // Looking for the opening bracket of the array
+    Seeking,
+    // Within the array, holds the nesting depth of its elements
+    Elements(usize),
+    Done,
+}
+
+// Splits a JSON document into the raw elements of one of its arrays, fed a chunk at a time
+#[derive(Debug)]
+struct ArrayScanner {
+    // The field of the top level object holding the array, the document itself when `None`
+    field: Option<Vec<u8>>,
+    state: ScanState,
+    depth: usize,
+    in_string: bool,
+    escaped: bool,
+    // The last string read at the top level of the object, i.e. the key of the next value
+    key: Vec<u8>,
+    element: Vec<u8>,
+}
+
+impl ArrayScanner {
+    fn new(field: Option<&str>) -> Self {
+        Self {
+            field: field.map(|field| field.as_bytes().to_vec()),
+            state: ScanState::Seeking,
+            depth: 0,
+            in_string: false,
+            escaped: false,
+            key: vec![],
+            element: vec![],
+        }
+    }
+
+    // Feeds the next chunk of the document, returning the elements completed by it
+    fn feed(&mut self, chunk: &[u8]) -> Vec<Vec<u8>> {
+        let mut elements = vec![];
+        for &byte in chunk {
+            if self.state == ScanState::Done {
+                break;
+            }
+            let in_elements = matches!(self.state, ScanState::Elements(_));
+            if self.in_string {
+                if self.escaped {
+                    self.escaped = false;
+                } else if byte == b'\\' {
+                    self.escaped = true;
+                } else if byte == b'"' {
+                    self.in_string = false;
+                }
+                if in_elements {
+                    self.element.push(byte);
+                } else if self.in_string && self.depth == 1 {
+                    self.key.push(byte);
+                }
+                continue;
+            }
+
+            match (self.state, byte) {
+                (_, b'"') => {
+                    self.in_string = true;
+                    if in_elements {
+                        self.element.push(byte);
+                    } else if self.depth == 1 {
+                        self.key.clear();
+                    }
+                }
+                (ScanState::Elements(depth), b',') if self.depth == depth => {
+                    elements.extend(self.take_element());
+                }
+                (ScanState::Elements(depth), b']') if self.depth == depth => {
+                    elements.extend(self.take_element());
+                    self.state = ScanState::Done;
+                }
+                (ScanState::Elements(_), b'[' | b'{') => {
+                    self.depth += 1;
+                    self.element.push(byte);
+                }
+                (ScanState::Elements(_), b']' | b'}') => {
+                    self.depth -= 1;
+                    self.element.push(byte);
+                }
+                (ScanState::Elements(_), _) => self.element.push(byte),
+                (ScanState::Seeking, b'[') => {
+                    self.depth += 1;
+                    let found = match self.field.as_ref() {
+                        None => self.depth == 1,
+                        Some(field) => self.depth == 2 && &self.key == field,
+                    };
+                    if found {
+                        self.state = ScanState::Elements(self.depth);
+                    }
+                }
+                (ScanState::Seeking, b'{') => self.depth += 1,
+                (ScanState::Seeking, b']' | b'}') => self.depth = self.depth.saturating_sub(1),
+                (ScanState::Seeking, b',') if self.depth == 1 => self.key.clear(),
+                _ => {}
+            }
+        }
+        elements
+    }
+
+    fn take_element(&mut self) -> Option<Vec<u8>> {
+        let element = std::mem::take(&mut self.element);
+        (!element.trim_ascii().is_empty()).then_some(element)
+    }
+}
+
+/// Streams the elements of the JSON array making up the body of `response`, check the
+/// status of the response first
+pub fn json_array<T>(response: HttpResponse) -> BoxStream<'static, Result<T>>
+where
+    T: DeserializeOwned + Send + 'static,
+{
+    json_elements(response.bytes_stream(), None)
+}
+
+// The state of the stream of elements, the elements split from the chunks read so far
+// are parsed one at a time as they are polled
+struct Elements {
+    chunks: BoxStream<'static, Result<Vec<u8>>>,
+    scanner: ArrayScanner,
+    pending: VecDeque<Vec<u8>>,
+    index: usize,
+    ended: bool,
+}
+
+fn json_elements<T, S, B, E>(chunks: S, field: Option<&str>) -> BoxStream<'static, Result<T>>
+where
+    T: DeserializeOwned + Send + 'static,
+    S: futures::Stream<Item = std::result::Result<B, E>> + Send + 'static,
+    B: AsRef<[u8]>,
+    E: std::error::Error + Send + Sync + 'static,
+{
+    let description = match field {
+        Some(field) => format!("array {field}"),
+        None => "array".to_string(),
+    };
+    let elements = Elements {
+        chunks: chunks.map(|chunk| Ok(chunk?.as_ref().to_vec())).boxed(),
+        scanner: ArrayScanner::new(field),
+        pending: VecDeque::new(),
+        index: 0,
+        ended: false,
+    };
+    stream::unfold(elements, move |mut elements| {
+        let description = description.clone();
+        async move {
+            let next = elements.next(&description).await?;
+            Some((next, elements))
+        }
+    })
+    .boxed()
+}
+
+impl Elements {
+    async fn next<T: DeserializeOwned>(&mut self, description: &str) -> Option<Result<T>> {
+        loop {
+            if let Some(element) = self.pending.pop_front() {
+                let index = self.index;
+                self.index += 1;
+                return Some(
+                    serde_path_to_error::deserialize(&mut serde_json::Deserializer::from_slice(
+                        &element,
+                    ))
+                    .with_context(|| format!("Failed to parse element {index} of {description}")),
+                );
+            }
+            if self.ended {
+                return None;
+            }
+            match self.chunks.next().await {
+                Some(Ok(chunk)) => self.pending.extend(self.scanner.feed(&chunk)),
+                Some(Err(error)) => {
+                    self.ended = true;
+                    return Some(Err(error));
+                }
+                None => {
+                    self.ended = true;
+                    let error = match self.scanner.state {
+                        ScanState::Done => continue,
+                        ScanState::Seeking => format!("No {description} in response"),
+                        ScanState::Elements(_) => format!("Response ended within {description}"),
+                    };
+                    return Some(Err(anyhow::anyhow!(error)));
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::{json, Value};
+
+    fn scan(document: &str, field: Option<&str>, chunk_size: usize) -> Vec<String> {
+        let mut scanner = ArrayScanner::new(field);
+        document
+            .as_bytes()
+            .chunks(chunk_size)
+            .flat_map(|chunk| scanner.feed(chunk))
+            .map(|element| String::from_utf8(element).unwrap().trim().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_scan_array_elements() {
+        let document = r#"{
+            "items": "not [this]",
+            "page": {"items": [0]},
+            "items": [{"name": "a, \"b\" ]}"}, [1, [2]], null, "c"],
+            "count": 4
+        }"#;
+        for chunk_size in [1, 3, document.len()] {
+            assert_eq!(
+                scan(document, Some("items"), chunk_size),
+                vec![r#"{"name": "a, \"b\" ]}"}"#, "[1, [2]]", "null", r#""c""#]
+            );
+        }
+        assert_eq!(scan(" [1, 2 ,3]", None, 2), vec!["1", "2", "3"]);
+        assert!(scan("[]", None, 1).is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_json_elements() {
+        let chunks = [
+            "{\"items\": [{\"id\":",
+            " 1}, {\"id\": 2}",
+            "], \"page\": {}}",
+        ]
+        .map(|chunk| Ok::<_, std::io::Error>(chunk.as_bytes()));
+        let values: Vec<Value> = json_elements(stream::iter(chunks), Some("items"))
+            .map(|value| value.unwrap())
+            .collect()
+            .await;
+        assert_eq!(values, vec![json!({"id": 1}), json!({"id": 2})]);
+
+        let chunks = ["[1, 2"].map(|chunk| Ok::<_, std::io::Error>(chunk.as_bytes()));
+        let values: Vec<Result<u32>> = json_elements(stream::iter(chunks), None).collect().await;
+        assert_eq!(values[0].as_ref().unwrap(), &1);
+        assert!(values[1].is_err());
+        let chunks = ["{}"].map(|chunk| Ok::<_, std::io::Error>(chunk.as_bytes()));
+        let values: Vec<Result<u32>> = json_elements(stream::iter(chunks), Some("items"))
+            .collect()
+            .await;
+        assert_eq!(
+            values[0].as_ref().unwrap_err().to_string(),
+            "No array items in response"
+        );
+    }
+}
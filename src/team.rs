@@ -1,15 +1,28 @@
+#[cfg(feature = "fake")]
 #[allow(unused_imports)]
 use fake::{Dummy, Fake};
 
 use crate::annotation::AnnotationClass;
+use crate::datasets::ClassDistribution;
+#[cfg(feature = "client")]
 use crate::expect_http_ok;
-use anyhow::{bail, Context, Result};
+use crate::item::ItemPage;
+use crate::known::MaybeKnown;
+#[cfg(feature = "client")]
+use crate::utils::encode_query;
+#[cfg(feature = "client")]
+use crate::webhooks::WebhookSubscription;
+#[cfg(feature = "client")]
+use anyhow::bail;
+use anyhow::{Context, Result};
+#[cfg(feature = "client")]
 use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
 use serde_yaml::Value;
-use std::{fmt::Display, path::PathBuf};
+use std::{collections::HashMap, fmt::Display, path::PathBuf};
 
-use crate::client::V7Methods;
+#[cfg(feature = "client")]
+use crate::client::{HttpStatusError, V7Methods};
 
 #[derive(Debug, Default, PartialEq, Eq, Clone)]
 pub struct Team {
@@ -19,17 +32,80 @@ pub struct Team {
     pub team_id: Option<u32>,
 }
 
-#[derive(Debug, Default, Serialize, Deserialize, Dummy, PartialEq, Eq, Clone)]
+#[derive(Debug, Default, Serialize, Deserialize, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "fake", derive(Dummy))]
 pub struct TeamMember {
     pub id: Option<u32>,
     pub email: Option<String>,
     pub first_name: Option<String>,
     pub last_name: Option<String>,
-    pub role: Option<String>,
+    pub role: Option<MaybeKnown<Role>>,
     pub team_id: Option<u32>,
     pub user_id: Option<u32>,
 }
 
+impl TeamMember {
+    /// Whether the member has `role` or a role with more permissions, false for a role
+    /// not known to this crate
+    pub fn has_role_at_least(&self, role: Role) -> bool {
+        self.role
+            .as_ref()
+            .and_then(MaybeKnown::known)
+            .is_some_and(|member_role| member_role.is_at_least(role))
+    }
+}
+
+/// Role of a member of a team, ordered from the fewest to the most permissions
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[cfg_attr(feature = "fake", derive(Dummy))]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    /// A member of another team given access to some datasets
+    External,
+    Annotator,
+    Member,
+    Admin,
+    Owner,
+}
+
+impl Role {
+    /// Whether the role has at least the permissions of `role`
+    pub fn is_at_least(&self, role: Role) -> bool {
+        *self >= role
+    }
+}
+
+impl Display for Role {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let val = match self {
+            Role::External => "external",
+            Role::Annotator => "annotator",
+            Role::Member => "member",
+            Role::Admin => "admin",
+            Role::Owner => "owner",
+        };
+        write!(f, "{val}")
+    }
+}
+
+/// Invitation of a user to join the team, see `TeamDataMethods::invite_member`
+#[derive(Debug, Default, Serialize, Deserialize, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "fake", derive(Dummy))]
+pub struct TeamInvitation {
+    pub id: Option<u32>,
+    pub email: Option<String>,
+    pub role: Option<MaybeKnown<Role>>,
+    pub team_id: Option<u32>,
+}
+
+#[cfg(feature = "client")]
+#[derive(Debug, Serialize)]
+struct MemberRolePayload<'a> {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    email: Option<&'a str>,
+    role: Role,
+}
+
 impl Display for TeamMember {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(
@@ -43,17 +119,166 @@ impl Display for TeamMember {
     }
 }
 
-#[derive(Debug, Default, Clone, Serialize, Deserialize, Dummy, PartialEq, Eq)]
+#[derive(Debug, Default, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[cfg_attr(feature = "fake", derive(Dummy))]
 pub struct TypeCount {
     pub count: Option<u32>,
     pub id: Option<u32>,
     pub name: Option<String>,
 }
 
-#[derive(Debug, Default, Clone, Serialize, Deserialize, Dummy)]
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "fake", derive(Dummy))]
 pub struct TeamAnnotationClasses {
     pub annotation_classes: Vec<Option<AnnotationClass>>,
     pub type_counts: Vec<Option<TypeCount>>,
+    /// Pagination metadata, present when a page size was requested
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub page: Option<ItemPage>,
+}
+
+/// Number of annotations using a team annotation class
+#[derive(Debug, Default, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ClassUsage {
+    pub class_id: Option<u32>,
+    pub class_name: Option<String>,
+    pub archived: bool,
+    pub annotation_count: u64,
+}
+
+impl TeamAnnotationClasses {
+    /// Joins the classes with the annotation counts of `distributions`, e.g. one per dataset
+    /// from `DatasetItemReportMethods::class_distribution`. Classes without any annotations
+    /// are included with a count of zero.
+    ///
+    /// Sorted by ascending count, then class id, so unused classes come first. A class is
+    /// only unused within the datasets counted, include every dataset of the team before
+    /// deleting classes.
+    pub fn usage(&self, distributions: &[ClassDistribution]) -> Vec<ClassUsage> {
+        let mut counts: HashMap<u32, u64> = HashMap::new();
+        for class in distributions.iter().flat_map(|d| d.classes.iter()) {
+            if let Some(class_id) = class.class_id {
+                *counts.entry(class_id).or_default() += class.count;
+            }
+        }
+
+        let mut usage: Vec<ClassUsage> = self
+            .annotation_classes
+            .iter()
+            .flatten()
+            .map(|class| ClassUsage {
+                class_id: class.id.map(u32::from),
+                class_name: class.name.clone(),
+                archived: class.archived.unwrap_or_default(),
+                annotation_count: class
+                    .id
+                    .and_then(|id| counts.get(&id.0).copied())
+                    .unwrap_or_default(),
+            })
+            .collect();
+        usage.sort_by(|a, b| {
+            a.annotation_count
+                .cmp(&b.annotation_count)
+                .then(a.class_id.cmp(&b.class_id))
+        });
+        usage
+    }
+}
+
+/// Usage of a team against the limits of its plan, a limit of `None` is unlimited
+#[derive(Debug, Default, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[cfg_attr(feature = "fake", derive(Dummy))]
+pub struct TeamUsage {
+    pub item_count: Option<u64>,
+    pub item_limit: Option<u64>,
+    pub storage_bytes: Option<u64>,
+    pub storage_limit_bytes: Option<u64>,
+    pub seats_used: Option<u32>,
+    pub seat_limit: Option<u32>,
+}
+
+fn fraction_used(used: Option<u64>, limit: Option<u64>) -> Option<f64> {
+    match (used, limit) {
+        (Some(used), Some(limit)) if limit > 0 => Some(used as f64 / limit as f64),
+        _ => None,
+    }
+}
+
+impl TeamUsage {
+    /// Number of items that can still be added, `None` if unlimited or unknown
+    pub fn items_remaining(&self) -> Option<u64> {
+        Some(self.item_limit?.saturating_sub(self.item_count?))
+    }
+
+    /// Bytes of storage still available, `None` if unlimited or unknown
+    pub fn storage_remaining_bytes(&self) -> Option<u64> {
+        Some(
+            self.storage_limit_bytes?
+                .saturating_sub(self.storage_bytes?),
+        )
+    }
+
+    /// Number of seats still available, `None` if unlimited or unknown
+    pub fn seats_remaining(&self) -> Option<u32> {
+        Some(self.seat_limit?.saturating_sub(self.seats_used?))
+    }
+
+    /// Whether the items, storage or seats used have reached `threshold`
+    /// (e.g. `0.9` for 90%) of their limit
+    pub fn is_near_limit(&self, threshold: f64) -> bool {
+        [
+            fraction_used(self.item_count, self.item_limit),
+            fraction_used(self.storage_bytes, self.storage_limit_bytes),
+            fraction_used(
+                self.seats_used.map(u64::from),
+                self.seat_limit.map(u64::from),
+            ),
+        ]
+        .into_iter()
+        .flatten()
+        .any(|fraction| fraction >= threshold)
+    }
+}
+
+/// Server side filtering and pagination of the team annotation classes
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct AnnotationClassQuery {
+    /// Include the tag classes
+    pub include_tags: Option<bool>,
+    /// Only classes with one of these main annotation types e.g. `polygon`
+    pub annotation_types: Vec<String>,
+    pub name_contains: Option<String>,
+    /// Only classes available to the dataset
+    pub dataset_id: Option<u32>,
+    pub page_size: Option<u32>,
+    /// Cursor of the page to retrieve, see `TeamAnnotationClasses::page`
+    pub page_from: Option<String>,
+}
+
+#[cfg(feature = "client")]
+impl AnnotationClassQuery {
+    fn to_query_string(&self) -> String {
+        let mut pairs: Vec<(&str, String)> = Vec::new();
+        if let Some(include_tags) = self.include_tags {
+            pairs.push(("include_tags", include_tags.to_string()));
+        }
+        for annotation_type in self.annotation_types.iter() {
+            pairs.push(("annotation_types[]", annotation_type.to_string()));
+        }
+        if let Some(name) = self.name_contains.as_ref() {
+            pairs.push(("name_contains", name.to_string()));
+        }
+        if let Some(dataset_id) = self.dataset_id {
+            pairs.push(("dataset_id", dataset_id.to_string()));
+        }
+        if let Some(size) = self.page_size {
+            pairs.push(("page[size]", size.to_string()));
+        }
+        if let Some(from) = self.page_from.as_ref() {
+            pairs.push(("page[from]", from.to_string()));
+        }
+        encode_query(&pairs)
+    }
 }
 
 impl TryFrom<(&Value, &Value)> for Team {
@@ -80,6 +305,7 @@ impl TryFrom<(&Value, &Value)> for Team {
         })
     }
 }
+#[cfg(feature = "client")]
 #[async_trait]
 pub trait TeamDescribeMethods<C>
 where
@@ -87,8 +313,21 @@ where
 {
     async fn list_memberships(client: &C) -> Result<Vec<TeamMember>>;
     async fn list_annotation_classes(&self, client: &C) -> Result<TeamAnnotationClasses>;
+    async fn query_annotation_classes(
+        &self,
+        client: &C,
+        query: &AnnotationClassQuery,
+    ) -> Result<TeamAnnotationClasses>;
+    /// Item, storage and seat usage of the team and the limits of its plan
+    async fn usage(&self, client: &C) -> Result<TeamUsage>;
+    async fn list_webhook_subscriptions(&self, client: &C) -> Result<Vec<WebhookSubscription>>;
+    /// The annotation classes of the team without their ids, datasets and other fields
+    /// specific to the team, ordered by name so that a taxonomy can be kept in version
+    /// control and applied to another team with `TeamDataMethods::import_classes`
+    async fn export_classes(&self, client: &C) -> Result<Vec<AnnotationClass>>;
 }
 
+#[cfg(feature = "client")]
 #[async_trait]
 pub trait TeamDataMethods<C>
 where
@@ -99,11 +338,81 @@ where
         client: &C,
         class: &AnnotationClass,
     ) -> Result<AnnotationClass>;
-    // async fn delete_annotation_classes(
-    //     &self,
-    //     client: &C,
-    //     classes: &[AnnotationClass],
-    // ) -> Result<()>;
+    /// Deletes the classes along with all of their annotations. V7 requires the number of
+    /// annotations that will be deleted as confirmation and refuses the request if
+    /// `annotations_to_delete_count` does not match.
+    async fn delete_annotation_classes(
+        &self,
+        client: &C,
+        classes: &[AnnotationClass],
+        annotations_to_delete_count: u32,
+    ) -> Result<()>;
+    async fn create_webhook_subscription(
+        &self,
+        client: &C,
+        subscription: &WebhookSubscription,
+    ) -> Result<WebhookSubscription>;
+    /// Replaces the url, events, datasets and authorization of the subscription with `subscription.id`
+    async fn update_webhook_subscription(
+        &self,
+        client: &C,
+        subscription: &WebhookSubscription,
+    ) -> Result<WebhookSubscription>;
+    async fn delete_webhook_subscription(&self, client: &C, subscription_id: &str) -> Result<()>;
+    /// Invites the user with `email` to the team of the authentication token with `role`
+    async fn invite_member(&self, client: &C, email: &str, role: Role) -> Result<TeamInvitation>;
+    /// Changes the role of the membership `membership_id`, the `id` of a `TeamMember`.
+    /// Ownership is transferred in the V7 UI, so `Role::Owner` is refused.
+    async fn set_member_role(
+        &self,
+        client: &C,
+        membership_id: u32,
+        role: Role,
+    ) -> Result<TeamMember>;
+    /// Removes the membership `membership_id` from the team, freeing its seat
+    async fn remove_member(&self, client: &C, membership_id: u32) -> Result<()>;
+    /// Creates the classes, e.g. from `export_classes` of another team, resolving classes
+    /// whose name is already used by a class of the team according to `policy`. With
+    /// `ConflictPolicy::Fail` nothing is created if any class conflicts.
+    async fn import_classes(
+        &self,
+        client: &C,
+        classes: &[AnnotationClass],
+        policy: ConflictPolicy,
+    ) -> Result<ClassImportReport>;
+}
+
+/// What `TeamDataMethods::import_classes` does with a class whose name is already used
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConflictPolicy {
+    /// Keep the existing class unchanged
+    #[default]
+    Skip,
+    /// Replace the annotation types, description and metadata of the existing class
+    Update,
+    /// Fail the import
+    Fail,
+}
+
+/// Names of the classes created, updated and skipped by `TeamDataMethods::import_classes`
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct ClassImportReport {
+    pub created: Vec<String>,
+    pub updated: Vec<String>,
+    pub skipped: Vec<String>,
+}
+
+#[cfg(feature = "client")]
+// The class without the fields that only have meaning within its team
+fn portable_class(class: &AnnotationClass) -> AnnotationClass {
+    AnnotationClass {
+        annotation_types: class.annotation_types.clone(),
+        description: class.description.clone(),
+        metadata: class.metadata.clone(),
+        name: class.name.clone(),
+        ..Default::default()
+    }
 }
 
 impl Team {
@@ -122,6 +431,7 @@ impl Team {
     }
 }
 
+#[cfg(feature = "client")]
 #[async_trait]
 impl<C> TeamDescribeMethods<C> for Team
 where
@@ -129,27 +439,84 @@ where
 {
     // This uses the authentication token
     async fn list_memberships(client: &C) -> Result<Vec<TeamMember>> {
+        if let Some(memberships) = client.cache().and_then(|cache| cache.memberships()) {
+            return Ok(memberships);
+        }
         let response = client.get("memberships").await?;
 
-        expect_http_ok!(response, Vec<TeamMember>)
+        let memberships: Result<Vec<TeamMember>> = expect_http_ok!(response, Vec<TeamMember>);
+        if let (Some(cache), Ok(memberships)) = (client.cache(), memberships.as_ref()) {
+            cache.set_memberships(memberships);
+        }
+        memberships
     }
 
     // Relies upon the team id / slug
     async fn list_annotation_classes(&self, client: &C) -> Result<TeamAnnotationClasses> {
-        // TODO: add query params
-        let endpoint = format!("teams/{}/annotation_classes", self.slug);
+        if let Some(classes) = client.cache().and_then(|cache| cache.classes(&self.slug)) {
+            return Ok(classes);
+        }
+        let classes = self
+            .query_annotation_classes(client, &AnnotationClassQuery::default())
+            .await?;
+        if let Some(cache) = client.cache() {
+            cache.set_classes(&self.slug, &classes);
+        }
+        Ok(classes)
+    }
+
+    async fn query_annotation_classes(
+        &self,
+        client: &C,
+        query: &AnnotationClassQuery,
+    ) -> Result<TeamAnnotationClasses> {
+        let mut endpoint = format!("teams/{}/annotation_classes", self.slug);
+        let query = query.to_query_string();
+        if !query.is_empty() {
+            endpoint = format!("{endpoint}?{query}");
+        }
         let response = client.get(&endpoint).await?;
 
         expect_http_ok!(response, TeamAnnotationClasses)
     }
+
+    async fn usage(&self, client: &C) -> Result<TeamUsage> {
+        let response = client.get(&format!("teams/{}/usage", self.slug)).await?;
+
+        expect_http_ok!(response, TeamUsage)
+    }
+
+    async fn list_webhook_subscriptions(&self, client: &C) -> Result<Vec<WebhookSubscription>> {
+        let response = client
+            .get(&format!("v2/teams/{}/webhooks", self.slug))
+            .await?;
+
+        expect_http_ok!(response, Vec<WebhookSubscription>)
+    }
+
+    async fn export_classes(&self, client: &C) -> Result<Vec<AnnotationClass>> {
+        let mut classes: Vec<AnnotationClass> = self
+            .list_annotation_classes(client)
+            .await?
+            .annotation_classes
+            .iter()
+            .flatten()
+            .map(portable_class)
+            .collect();
+        classes.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(classes)
+    }
 }
 
-#[derive(Debug, Default, Serialize, Deserialize, Dummy, PartialEq, Eq, Clone)]
+#[cfg(feature = "client")]
+#[derive(Debug, Default, Serialize, Deserialize, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "fake", derive(Dummy))]
 struct DeleteClassesPayload {
     pub annotation_class_ids: Vec<u32>,
     pub annotations_to_delete_count: u32,
 }
 
+#[cfg(feature = "client")]
 #[async_trait]
 impl<C> TeamDataMethods<C> for Team
 where
@@ -165,43 +532,209 @@ where
     {
         let endpoint = format!("teams/{}/annotation_classes", self.slug);
         let response = client.post(&endpoint, class).await?;
+        if let Some(cache) = client.cache() {
+            cache.invalidate_classes();
+        }
 
         expect_http_ok!(response, AnnotationClass)
     }
-    // async fn delete_annotation_classes(&self, client: &C, classes: &[AnnotationClass]) -> Result<()>
-    // where
-    //     C: V7Methods,
-    // {
-    //     let endpoint = format!(
-    //         "teams/{}/delete_classes",
-    //         self.team_id.context("Missing team id")?
-    //     );
-
-    //     let mut payload = DeleteClassesPayload::default();
-    //     for class in classes.iter() {
-    //         payload.annotation_class_ids.push(class.id.context(format!(
-    //             "Class {} missing id",
-    //             class.name.clone().unwrap_or(String::new())
-    //         ))?);
-    //     }
-
-    //     let response = client.delete(&endpoint, &payload).await?;
-
-    //     let status = response.status();
-    //     if status != 204 {
-    //         bail!("Unable to delete classes with status code {}", status);
-    //     }
-
-    //     Ok(())
-    // }
+
+    async fn delete_annotation_classes(
+        &self,
+        client: &C,
+        classes: &[AnnotationClass],
+        annotations_to_delete_count: u32,
+    ) -> Result<()> {
+        let endpoint = format!(
+            "teams/{}/delete_classes",
+            self.team_id.context("Missing team id")?
+        );
+
+        let mut payload = DeleteClassesPayload {
+            annotations_to_delete_count,
+            ..Default::default()
+        };
+        for class in classes.iter() {
+            payload.annotation_class_ids.push(
+                class
+                    .id
+                    .context(format!(
+                        "Class {} missing id",
+                        class.name.clone().unwrap_or_default()
+                    ))?
+                    .0,
+            );
+        }
+
+        let response = client.delete(&endpoint, Some(&payload)).await?;
+        if let Some(cache) = client.cache() {
+            cache.invalidate_classes();
+        }
+
+        let status = response.status();
+        if status != 204 {
+            bail!(HttpStatusError::from_response(response).await);
+        }
+
+        Ok(())
+    }
+
+    async fn create_webhook_subscription(
+        &self,
+        client: &C,
+        subscription: &WebhookSubscription,
+    ) -> Result<WebhookSubscription> {
+        let response = client
+            .post(&format!("v2/teams/{}/webhooks", self.slug), subscription)
+            .await?;
+
+        expect_http_ok!(response, WebhookSubscription)
+    }
+
+    async fn update_webhook_subscription(
+        &self,
+        client: &C,
+        subscription: &WebhookSubscription,
+    ) -> Result<WebhookSubscription> {
+        let endpoint = format!(
+            "v2/teams/{}/webhooks/{}",
+            self.slug,
+            subscription
+                .id
+                .as_ref()
+                .context("Webhook subscription is missing id")?
+        );
+        let response = client.put(&endpoint, Some(subscription)).await?;
+
+        expect_http_ok!(response, WebhookSubscription)
+    }
+
+    async fn delete_webhook_subscription(&self, client: &C, subscription_id: &str) -> Result<()> {
+        let response = client
+            .delete::<()>(
+                &format!("v2/teams/{}/webhooks/{subscription_id}", self.slug),
+                None,
+            )
+            .await?;
+
+        if !response.status().is_success() {
+            bail!(HttpStatusError::from_response(response).await);
+        }
+
+        Ok(())
+    }
+
+    async fn invite_member(&self, client: &C, email: &str, role: Role) -> Result<TeamInvitation> {
+        let payload = MemberRolePayload {
+            email: Some(email),
+            role,
+        };
+        let response = client.post("invitations", &payload).await?;
+        if let Some(cache) = client.cache() {
+            cache.invalidate_memberships();
+        }
+
+        expect_http_ok!(response, TeamInvitation)
+    }
+
+    async fn set_member_role(
+        &self,
+        client: &C,
+        membership_id: u32,
+        role: Role,
+    ) -> Result<TeamMember> {
+        if role == Role::Owner {
+            bail!("Ownership of a team cannot be assigned through the API");
+        }
+        let payload = MemberRolePayload { email: None, role };
+        let response = client
+            .put(&format!("memberships/{membership_id}"), Some(&payload))
+            .await?;
+        if let Some(cache) = client.cache() {
+            cache.invalidate_memberships();
+        }
+
+        expect_http_ok!(response, TeamMember)
+    }
+
+    async fn remove_member(&self, client: &C, membership_id: u32) -> Result<()> {
+        let response = client
+            .delete::<()>(&format!("memberships/{membership_id}"), None)
+            .await?;
+        if let Some(cache) = client.cache() {
+            cache.invalidate_memberships();
+        }
+
+        if !response.status().is_success() {
+            bail!(HttpStatusError::from_response(response).await);
+        }
+
+        Ok(())
+    }
+
+    async fn import_classes(
+        &self,
+        client: &C,
+        classes: &[AnnotationClass],
+        policy: ConflictPolicy,
+    ) -> Result<ClassImportReport> {
+        let existing: HashMap<String, AnnotationClass> = self
+            .list_annotation_classes(client)
+            .await?
+            .annotation_classes
+            .into_iter()
+            .flatten()
+            .filter_map(|class| Some((class.name.clone()?, class)))
+            .collect();
+
+        if policy == ConflictPolicy::Fail {
+            let conflicts: Vec<&str> = classes
+                .iter()
+                .filter_map(|class| class.name.as_deref())
+                .filter(|name| existing.contains_key(*name))
+                .collect();
+            if !conflicts.is_empty() {
+                bail!("Classes already exist: {}", conflicts.join(", "));
+            }
+        }
+
+        let mut report = ClassImportReport::default();
+        for class in classes {
+            let name = class
+                .name
+                .clone()
+                .context("Annotation class is missing a name")?;
+            match (existing.get(&name), policy) {
+                (None, _) => {
+                    self.create_annotation_class(client, &portable_class(class))
+                        .await?;
+                    report.created.push(name);
+                }
+                (Some(current), ConflictPolicy::Update) => {
+                    let updated = AnnotationClass {
+                        annotation_types: class.annotation_types.clone(),
+                        description: class.description.clone(),
+                        metadata: class.metadata.clone(),
+                        ..current.clone()
+                    };
+                    updated.update(client).await?;
+                    report.updated.push(name);
+                }
+                (Some(_), _) => report.skipped.push(name),
+            }
+        }
+        Ok(report)
+    }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, Dummy, PartialEq, Eq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[cfg_attr(feature = "fake", derive(Dummy))]
 pub struct MetadataSkeleton {
     #[serde(rename = "_type")]
     pub skeleton_type: String,
 }
 
+#[cfg(feature = "client")]
 pub mod helpers {
     use anyhow::Result;
 
@@ -233,9 +766,446 @@ pub mod helpers {
     }
 }
 
-#[cfg(test)]
+#[cfg(all(test, feature = "client"))]
 mod tests {
     use super::*;
+    use crate::client::V7Client;
+    use crate::datasets::ClassCount;
+    use crate::ids::ClassId;
+    use crate::known::MaybeKnown;
+    use crate::webhooks::WebhookEventType;
+    use serde_json::json;
+    use std::time::Duration;
+    use wiremock::matchers::{body_json, body_partial_json, method, path, query_param};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    #[test]
+    fn test_class_usage() {
+        let class = |id: u32, name: &str| {
+            Some(AnnotationClass {
+                id: Some(ClassId(id)),
+                name: Some(name.to_string()),
+                ..Default::default()
+            })
+        };
+        let classes = TeamAnnotationClasses {
+            annotation_classes: vec![class(1, "Tumour"), class(2, "Stroma"), class(3, "Legacy")],
+            ..Default::default()
+        };
+        let distribution = |counts: &[(u32, u64)]| ClassDistribution {
+            classes: counts
+                .iter()
+                .map(|(class_id, count)| ClassCount {
+                    class_id: Some(*class_id),
+                    count: *count,
+                    ..Default::default()
+                })
+                .collect(),
+            ..Default::default()
+        };
+
+        let usage = classes.usage(&[distribution(&[(1, 5), (2, 1)]), distribution(&[(2, 3)])]);
+        let counts: Vec<(Option<&str>, u64)> = usage
+            .iter()
+            .map(|usage| (usage.class_name.as_deref(), usage.annotation_count))
+            .collect();
+        assert_eq!(
+            counts,
+            vec![
+                (Some("Legacy"), 0),
+                (Some("Stroma"), 4),
+                (Some("Tumour"), 5)
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_webhook_subscriptions() {
+        let mock_server = MockServer::start().await;
+
+        Mock::given(method("POST"))
+            .and(path("/v2/teams/some-team/webhooks"))
+            .and(body_json(json!({
+                "url": "https://hooks.franklin.ai/v7",
+                "events": ["item_completed", "export_finished"]
+            })))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "id": "hook-1",
+                "url": "https://hooks.franklin.ai/v7",
+                "events": ["item_completed", "export_finished"],
+                "enabled": true,
+                "inserted_at": "2024-03-01T10:00:00Z"
+            })))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/v2/teams/some-team/webhooks"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!([{
+                "id": "hook-1",
+                "url": "https://hooks.franklin.ai/v7",
+                "events": ["item_completed", "dataset_archived"]
+            }])))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("PUT"))
+            .and(path("/v2/teams/some-team/webhooks/hook-1"))
+            .and(body_json(json!({
+                "id": "hook-1",
+                "url": "https://hooks.franklin.ai/v7",
+                "events": ["item_completed", "dataset_archived"],
+                "enabled": false
+            })))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "id": "hook-1",
+                "url": "https://hooks.franklin.ai/v7",
+                "events": ["item_completed", "dataset_archived"],
+                "enabled": false
+            })))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("DELETE"))
+            .and(path("/v2/teams/some-team/webhooks/hook-1"))
+            .respond_with(ResponseTemplate::new(204))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let client: V7Client = V7Client::new(
+            format!("{}/", mock_server.uri()),
+            "api-key".to_string(),
+            "some-team".to_string(),
+        )
+        .expect("Failed to get V7Client");
+        let team = Team::new("some-team".to_string(), None, None, None);
+
+        let subscription = WebhookSubscription {
+            url: "https://hooks.franklin.ai/v7".to_string(),
+            events: vec![
+                WebhookEventType::ItemCompleted.into(),
+                WebhookEventType::ExportFinished.into(),
+            ],
+            ..Default::default()
+        };
+        let created = team
+            .create_webhook_subscription(&client, &subscription)
+            .await
+            .expect("Failed to create webhook subscription");
+        assert_eq!(created.id.as_deref(), Some("hook-1"));
+
+        let subscriptions = team
+            .list_webhook_subscriptions(&client)
+            .await
+            .expect("Failed to list webhook subscriptions");
+        assert_eq!(
+            subscriptions[0].events,
+            vec![
+                MaybeKnown::Known(WebhookEventType::ItemCompleted),
+                MaybeKnown::Unknown("dataset_archived".to_string())
+            ]
+        );
+
+        let mut subscription = subscriptions[0].clone();
+        subscription.enabled = Some(false);
+        let updated = team
+            .update_webhook_subscription(&client, &subscription)
+            .await
+            .expect("Failed to update webhook subscription");
+        assert_eq!(updated.enabled, Some(false));
+        team.update_webhook_subscription(&client, &WebhookSubscription::default())
+            .await
+            .expect_err("Webhook subscription is missing id");
+
+        team.delete_webhook_subscription(&client, "hook-1")
+            .await
+            .expect("Failed to delete webhook subscription");
+    }
+
+    #[test]
+    fn test_roles() {
+        let member: TeamMember = serde_json::from_value(json!({
+            "id": 3,
+            "email": "fake.pathologist@franklin.ai",
+            "role": "admin"
+        }))
+        .unwrap();
+        assert_eq!(member.role, Some(MaybeKnown::Known(Role::Admin)));
+        assert!(member.has_role_at_least(Role::Member));
+        assert!(!member.has_role_at_least(Role::Owner));
+
+        let member: TeamMember =
+            serde_json::from_value(json!({"role": "workforce_manager"})).unwrap();
+        assert_eq!(
+            member.role,
+            Some(MaybeKnown::Unknown("workforce_manager".to_string()))
+        );
+        assert!(!member.has_role_at_least(Role::External));
+        assert_eq!(
+            serde_json::to_value(&member).unwrap()["role"],
+            "workforce_manager"
+        );
+        assert!(Role::Annotator.is_at_least(Role::External));
+        assert_eq!(Role::Admin.to_string(), "admin");
+    }
+
+    #[tokio::test]
+    async fn test_manage_members() {
+        let mock_server = MockServer::start().await;
+
+        Mock::given(method("POST"))
+            .and(path("/invitations"))
+            .and(body_json(
+                json!({"email": "new@franklin.ai", "role": "annotator"}),
+            ))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "id": 9, "email": "new@franklin.ai", "role": "annotator", "team_id": 1
+            })))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("PUT"))
+            .and(path("/memberships/3"))
+            .and(body_json(json!({"role": "admin"})))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "id": 3, "role": "admin", "user_id": 42
+            })))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("DELETE"))
+            .and(path("/memberships/3"))
+            .respond_with(ResponseTemplate::new(204))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let client: V7Client = V7Client::new(
+            format!("{}/", mock_server.uri()),
+            "api-key".to_string(),
+            "some-team".to_string(),
+        )
+        .expect("Failed to get V7Client");
+        let team = Team::new("some-team".to_string(), None, None, None);
+
+        let invitation = team
+            .invite_member(&client, "new@franklin.ai", Role::Annotator)
+            .await
+            .expect("Failed to invite member");
+        assert_eq!(invitation.role, Some(MaybeKnown::Known(Role::Annotator)));
+
+        let member = team
+            .set_member_role(&client, 3, Role::Admin)
+            .await
+            .expect("Failed to set member role");
+        assert!(member.has_role_at_least(Role::Admin));
+        assert!(team.set_member_role(&client, 3, Role::Owner).await.is_err());
+
+        team.remove_member(&client, 3)
+            .await
+            .expect("Failed to remove member");
+    }
+
+    #[tokio::test]
+    async fn test_cached_memberships_and_classes() {
+        let mock_server = MockServer::start().await;
+
+        Mock::given(method("GET"))
+            .and(path("/memberships"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_json(json!([{"id": 3, "role": "admin", "user_id": 42}])),
+            )
+            .expect(2)
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/teams/some-team/annotation_classes"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "annotation_classes": [{"id": 1, "name": "Tumour bed"}],
+                "type_counts": []
+            })))
+            .expect(3)
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("PUT"))
+            .and(path("/memberships/3"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "id": 3, "role": "annotator", "user_id": 42
+            })))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let client = V7Client::builder(
+            format!("{}/", mock_server.uri()),
+            "api-key".to_string(),
+            "some-team".to_string(),
+        )
+        .cache_ttl(Duration::from_secs(60))
+        .build()
+        .expect("Failed to get V7Client");
+        let team = Team::new("some-team".to_string(), None, None, None);
+
+        // Clones share the cache
+        for client in [&client, &client.clone()] {
+            let memberships = Team::list_memberships(client).await.unwrap();
+            assert_eq!(memberships[0].user_id, Some(42));
+            let classes = team.list_annotation_classes(client).await.unwrap();
+            assert_eq!(classes.annotation_classes.len(), 1);
+        }
+
+        // Changing a membership drops the cached memberships but not the classes
+        team.set_member_role(&client, 3, Role::Annotator)
+            .await
+            .unwrap();
+        Team::list_memberships(&client).await.unwrap();
+        team.list_annotation_classes(&client).await.unwrap();
+
+        client.invalidate_cache();
+        team.list_annotation_classes(&client).await.unwrap();
+
+        // Entries older than the TTL are refetched
+        let client = V7Client::builder(
+            format!("{}/", mock_server.uri()),
+            "api-key".to_string(),
+            "some-team".to_string(),
+        )
+        .cache_ttl(Duration::ZERO)
+        .build()
+        .expect("Failed to get V7Client");
+        assert!(client.cache().is_some());
+        team.list_annotation_classes(&client).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_query_annotation_classes() {
+        let mock_server = MockServer::start().await;
+
+        Mock::given(method("GET"))
+            .and(path("/teams/some-team/annotation_classes"))
+            .and(query_param("include_tags", "false"))
+            .and(query_param("annotation_types[]", "polygon"))
+            .and(query_param("name_contains", "Tumour bed"))
+            .and(query_param("page[size]", "100"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "annotation_classes": [{"id": 1, "name": "Tumour bed"}],
+                "type_counts": [],
+                "page": {"count": 1, "next": "cursor-1", "previous": null}
+            })))
+            .mount(&mock_server)
+            .await;
+
+        let client: V7Client = V7Client::new(
+            format!("{}/", mock_server.uri()),
+            "api-key".to_string(),
+            "some-team".to_string(),
+        )
+        .expect("Failed to get V7Client");
+        let team = Team::new("some-team".to_string(), None, None, None);
+
+        let query = AnnotationClassQuery {
+            include_tags: Some(false),
+            annotation_types: vec!["polygon".to_string()],
+            name_contains: Some("Tumour bed".to_string()),
+            page_size: Some(100),
+            ..Default::default()
+        };
+        let classes = team
+            .query_annotation_classes(&client, &query)
+            .await
+            .expect("Failed to query annotation classes");
+
+        assert_eq!(classes.annotation_classes.len(), 1);
+        assert_eq!(
+            classes.page.and_then(|page| page.next),
+            Some("cursor-1".to_string())
+        );
+    }
+
+    #[tokio::test]
+    async fn test_delete_annotation_classes() {
+        let mock_server = MockServer::start().await;
+
+        Mock::given(method("DELETE"))
+            .and(path("/teams/123/delete_classes"))
+            .and(body_json(json!({
+                "annotation_class_ids": [1, 2],
+                "annotations_to_delete_count": 7
+            })))
+            .respond_with(ResponseTemplate::new(204))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("DELETE"))
+            .and(path("/teams/123/delete_classes"))
+            .respond_with(ResponseTemplate::new(422))
+            .mount(&mock_server)
+            .await;
+
+        let client: V7Client = V7Client::new(
+            format!("{}/", mock_server.uri()),
+            "api-key".to_string(),
+            "some-team".to_string(),
+        )
+        .expect("Failed to get V7Client");
+        let team = Team::new("some-team".to_string(), None, None, Some(123));
+        let class = |id: u32| AnnotationClass {
+            id: Some(ClassId(id)),
+            ..Default::default()
+        };
+
+        team.delete_annotation_classes(&client, &[class(1), class(2)], 7)
+            .await
+            .expect("Failed to delete annotation classes");
+
+        // V7 refuses a count that does not match the annotations of the classes
+        team.delete_annotation_classes(&client, &[class(1), class(2)], 6)
+            .await
+            .expect_err("Invalid status code 422");
+
+        team.delete_annotation_classes(&client, &[AnnotationClass::default()], 0)
+            .await
+            .expect_err("Class missing id");
+        Team::new("some-team".to_string(), None, None, None)
+            .delete_annotation_classes(&client, &[class(1)], 0)
+            .await
+            .expect_err("Missing team id");
+    }
+
+    #[tokio::test]
+    async fn test_usage() {
+        let mock_server = MockServer::start().await;
+
+        Mock::given(method("GET"))
+            .and(path("/teams/some-team/usage"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "item_count": 9500,
+                "item_limit": 10000,
+                "storage_bytes": 1024,
+                "storage_limit_bytes": null,
+                "seats_used": 4,
+                "seat_limit": 10
+            })))
+            .mount(&mock_server)
+            .await;
+
+        let client: V7Client = V7Client::new(
+            format!("{}/", mock_server.uri()),
+            "api-key".to_string(),
+            "some-team".to_string(),
+        )
+        .expect("Failed to get V7Client");
+        let team = Team::new("some-team".to_string(), None, None, None);
+
+        let usage = team.usage(&client).await.expect("Failed to get usage");
+        assert_eq!(usage.items_remaining(), Some(500));
+        assert_eq!(usage.storage_remaining_bytes(), None);
+        assert_eq!(usage.seats_remaining(), Some(6));
+        assert!(usage.is_near_limit(0.9));
+        assert!(!usage.is_near_limit(0.96));
+    }
 
     #[test]
     fn test_from_str_all_fields() {
@@ -269,4 +1239,98 @@ mod tests {
         assert_eq!(team.api_key.as_ref(), None);
         assert_eq!(team.datasets_dir.as_ref(), None);
     }
+
+    #[tokio::test]
+    async fn test_export_and_import_classes() {
+        let mock_server = MockServer::start().await;
+        let team = Team::new("some-team".to_string(), None, None, Some(1));
+        Mock::given(method("GET"))
+            .and(path("/teams/some-team/annotation_classes"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "annotation_classes": [
+                    {"id": 2, "name": "Tumour", "datasets": [{"id": 5}], "images": [], "description": null,
+                     "annotation_types": ["polygon"], "team_id": 1,
+                     "metadata": {"_color": "rgba(255,0,0,1.0)", "polygon": {}}},
+                    {"id": 1, "name": "Keypoints", "datasets": [], "images": [], "description": "Cell",
+                     "annotation_types": ["skeleton"], "team_id": 1,
+                     "metadata": {"_color": "rgba(0,0,255,1.0)", "polygon": null,
+                                  "skeleton": {"edges": [{"from": "a", "to": "b"}]}}}
+                ],
+                "type_counts": []
+            })))
+            .mount(&mock_server)
+            .await;
+
+        let client: V7Client = V7Client::new(
+            format!("{}/", mock_server.uri()),
+            "api-key".to_string(),
+            "some-team".to_string(),
+        )
+        .expect("Failed to get V7Client");
+
+        let mut classes = team
+            .export_classes(&client)
+            .await
+            .expect("Failed to export classes");
+        assert_eq!(classes[0].name.as_deref(), Some("Keypoints"));
+        assert_eq!(classes[0].id, None);
+        assert!(classes[1].datasets.is_empty());
+        let exported = serde_json::to_value(&classes[0]).unwrap();
+        assert_eq!(exported["metadata"]["skeleton"]["edges"][0]["to"], "b");
+
+        classes[1].description = Some("Tumour cells".to_string());
+        classes.push(AnnotationClass {
+            name: Some("Stroma".to_string()),
+            annotation_types: vec![Some("polygon".to_string())],
+            ..Default::default()
+        });
+
+        assert!(team
+            .import_classes(&client, &classes, ConflictPolicy::Fail)
+            .await
+            .is_err());
+
+        Mock::given(method("PUT"))
+            .and(path("/annotation_classes/2"))
+            .and(body_partial_json(
+                json!({"id": 2, "name": "Tumour", "description": "Tumour cells", "datasets": [{"id": 5}]}),
+            ))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "id": 2, "name": "Tumour", "datasets": [], "images": [], "description": "Tumour cells"
+            })))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("PUT"))
+            .and(path("/annotation_classes/1"))
+            .and(body_partial_json(
+                json!({"metadata": {"skeleton": {"edges": [{"from": "a", "to": "b"}]}}}),
+            ))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "id": 1, "name": "Keypoints", "datasets": [], "images": [], "description": "Cell"
+            })))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/teams/some-team/annotation_classes"))
+            .and(body_partial_json(json!({"name": "Stroma"})))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "id": 3, "name": "Stroma", "datasets": [], "images": [], "description": null
+            })))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let report = team
+            .import_classes(&client, &classes, ConflictPolicy::Update)
+            .await
+            .expect("Failed to import classes");
+        assert_eq!(report.created, vec!["Stroma".to_string()]);
+        assert_eq!(
+            report.updated,
+            vec!["Keypoints".to_string(), "Tumour".to_string()]
+        );
+        assert!(report.skipped.is_empty());
+    }
 }
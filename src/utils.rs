@@ -1,12 +1,12 @@
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Deserializer};
+
+#[cfg(feature = "client")]
 #[macro_export]
 macro_rules! expect_http_ok {
     ($x: ident, $y: ty) => {
         if $x.status() != 200 {
-            bail!(format!(
-                "Invalid status code {} {}",
-                $x.status(),
-                $x.text().await?
-            ))
+            bail!($crate::client::HttpStatusError::from_response($x).await)
         } else {
             let text = $x.text().await?;
             Ok(serde_path_to_error::deserialize(
@@ -15,3 +15,326 @@ macro_rules! expect_http_ok {
         }
     };
 }
+
+/// Builds a URL encoded query string from key value pairs, keys may be repeated
+#[cfg(feature = "client")]
+pub fn encode_query<K, V>(pairs: &[(K, V)]) -> String
+where
+    K: AsRef<str>,
+    V: AsRef<str>,
+{
+    let mut url = reqwest::Url::parse("http://localhost/").expect("Valid base url");
+    url.query_pairs_mut()
+        .extend_pairs(pairs.iter().map(|(k, v)| (k.as_ref(), v.as_ref())));
+    url.query().unwrap_or_default().to_string()
+}
+
+/// Percent encodes `segment` for use as a single segment of an endpoint path, e.g. a
+/// slot name containing spaces or `/`
+#[cfg(feature = "client")]
+pub fn encode_path_segment(segment: &str) -> String {
+    let mut url = reqwest::Url::parse("http://localhost/").expect("Valid base url");
+    url.path_segments_mut()
+        .expect("Base url has a path")
+        .pop()
+        .push(segment);
+    url.path()[1..].to_string()
+}
+
+/// Deserializes a `null` as the default of the type, for use with `deserialize_with`
+/// on fields that V7 sends as `null` rather than omitting
+pub fn null_as_default<'de, D, T>(deserializer: D) -> std::result::Result<T, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Default + Deserialize<'de>,
+{
+    Ok(Option::<T>::deserialize(deserializer)?.unwrap_or_default())
+}
+
+/// Converts a V7 timestamp into seconds since the unix epoch (UTC).
+///
+/// V7 is not consistent about the format of its timestamps, depending upon the
+/// endpoint they may be RFC3339 (`2023-07-18T01:56:05.801600Z`), naive ISO8601
+/// (`2022-12-14T00:28:28.759303`) or space separated as in the item reports
+/// (`2023-05-10 14:15:27`). Naive timestamps are assumed to be UTC and any
+/// fractional seconds are truncated.
+pub fn parse_timestamp(value: &str) -> Result<i64> {
+    let value = value.trim();
+    let invalid = || anyhow::anyhow!("Invalid timestamp {value}");
+    // Sliced with `get` so that short or non-ASCII input is an error rather than a panic
+    let date = value.get(..10).ok_or_else(invalid)?;
+    let time = value.get(11..19).ok_or_else(invalid)?;
+    let zone = value.get(19..).ok_or_else(invalid)?;
+
+    let date: Vec<&str> = date.split('-').collect();
+    let time: Vec<&str> = time.split(':').collect();
+    if date.len() != 3 || time.len() != 3 {
+        bail!("Invalid timestamp {value}");
+    }
+    let parse = |val: &str| -> Result<i64> {
+        val.parse::<i64>()
+            .with_context(|| format!("Invalid timestamp {value}"))
+    };
+
+    let (year, month, day) = (parse(date[0])?, parse(date[1])?, parse(date[2])?);
+    let (hour, minute, second) = (parse(time[0])?, parse(time[1])?, parse(time[2])?);
+
+    // Strip any fractional seconds before looking for the UTC offset
+    let zone = zone.trim_start_matches(|c: char| c == '.' || c.is_ascii_digit());
+    let offset = match zone {
+        "" | "Z" | "z" => 0,
+        _ => {
+            let (sign, offset) = if let Some(offset) = zone.strip_prefix('+') {
+                (1, offset)
+            } else if let Some(offset) = zone.strip_prefix('-') {
+                (-1, offset)
+            } else {
+                bail!(invalid());
+            };
+            let (offset_hour, offset_minute) = offset.split_once(':').ok_or_else(invalid)?;
+            sign * (parse(offset_hour)? * 3600 + parse(offset_minute)? * 60)
+        }
+    };
+
+    Ok(days_from_civil(year, month, day) * 86400 + hour * 3600 + minute * 60 + second - offset)
+}
+
+/// A timestamp of V7 parsed with `parse_timestamp`, keeping values in any other format
+/// as they were sent rather than failing, see the `timestamps` feature.
+#[cfg(feature = "timestamps")]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Timestamp {
+    /// Seconds since the unix epoch (UTC), fractional seconds are truncated
+    Utc(i64),
+    /// A value that is not in any of the formats of `parse_timestamp`
+    Unparsed(String),
+}
+
+#[cfg(feature = "timestamps")]
+impl Timestamp {
+    pub fn parse(value: &str) -> Self {
+        match parse_timestamp(value) {
+            Ok(seconds) => Timestamp::Utc(seconds),
+            Err(_) => Timestamp::Unparsed(value.to_string()),
+        }
+    }
+
+    /// Parses an optional timestamp field, `None` when V7 did not send one
+    pub fn parse_optional(value: &Option<String>) -> Option<Self> {
+        value.as_deref().map(Self::parse)
+    }
+
+    /// Seconds since the unix epoch (UTC), `None` for an unparsed value
+    pub fn unix_seconds(&self) -> Option<i64> {
+        match self {
+            Timestamp::Utc(seconds) => Some(*seconds),
+            Timestamp::Unparsed(_) => None,
+        }
+    }
+
+    /// Calendar date (UTC) e.g. `2023-05-10`, `None` for an unparsed value
+    pub fn date(&self) -> Option<String> {
+        self.unix_seconds().map(utc_date)
+    }
+}
+
+/// RFC3339 in UTC e.g. `2023-05-10T14:15:27Z`, or the value as it was sent when unparsed
+#[cfg(feature = "timestamps")]
+impl std::fmt::Display for Timestamp {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Timestamp::Utc(seconds) => {
+                let time = seconds.rem_euclid(86400);
+                write!(
+                    f,
+                    "{}T{:02}:{:02}:{:02}Z",
+                    utc_date(*seconds),
+                    time / 3600,
+                    time % 3600 / 60,
+                    time % 60
+                )
+            }
+            Timestamp::Unparsed(value) => write!(f, "{value}"),
+        }
+    }
+}
+
+#[cfg(feature = "timestamps")]
+impl serde::Serialize for Timestamp {
+    fn serialize<S: serde::Serializer>(
+        &self,
+        serializer: S,
+    ) -> std::result::Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+#[cfg(feature = "timestamps")]
+impl<'de> Deserialize<'de> for Timestamp {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        Ok(Self::parse(&String::deserialize(deserializer)?))
+    }
+}
+
+/// ISO 8601 week-numbering year and week of a time in seconds since the unix epoch (UTC),
+/// e.g. `(2023, 19)` for `2023-05-10T14:15:27Z`
+pub fn iso_week(timestamp: i64) -> (i64, u32) {
+    let days = timestamp.div_euclid(86400);
+    // 1970-01-01 was a Thursday, weeks start on Monday
+    let weekday = (days + 3).rem_euclid(7);
+    // The ISO year of a week is the year of its Thursday
+    let thursday = days - weekday + 3;
+    let (year, _, _) = civil_from_days(thursday);
+    let week = (thursday - days_from_civil(year, 1, 1)) / 7 + 1;
+    (year, week as u32)
+}
+
+/// Calendar date of a time in seconds since the unix epoch (UTC), e.g. `2023-05-10`
+pub fn utc_date(timestamp: i64) -> String {
+    let (year, month, day) = civil_from_days(timestamp.div_euclid(86400));
+    format!("{year:04}-{month:02}-{day:02}")
+}
+
+/// Time of day of a time in seconds since the unix epoch (UTC), e.g. `143005` for 14:30:05
+pub fn utc_time(timestamp: i64) -> String {
+    let seconds = timestamp.rem_euclid(86400);
+    format!(
+        "{:02}{:02}{:02}",
+        seconds / 3600,
+        seconds % 3600 / 60,
+        seconds % 60
+    )
+}
+
+/// A folder path of V7 with a single leading and no trailing slash, e.g. `/scans/2024` for
+/// `scans/2024/`, or `/` for the root folder
+pub(crate) fn folder_path(path: &str) -> String {
+    format!("/{}", path.trim().trim_matches('/'))
+}
+
+/// Whether `path` is `folder` or one of its sub-folders, both as returned by `folder_path`
+pub(crate) fn in_folder(path: &str, folder: &str) -> bool {
+    folder == "/"
+        || path == folder
+        || path
+            .strip_prefix(folder)
+            .is_some_and(|rest| rest.starts_with('/'))
+}
+
+// Year, month and day of a number of days since 1970-01-01
+// see http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let days = days + 719468;
+    let era = if days >= 0 { days } else { days - 146096 } / 146097;
+    let day_of_era = days - era * 146097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400;
+    (if month <= 2 { year + 1 } else { year }, month, day)
+}
+
+// Days since 1970-01-01 for the proleptic Gregorian calendar
+// see http://howardhinnant.github.io/date_algorithms.html#days_from_civil
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = if year >= 0 { year } else { year - 399 } / 400;
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * (month + if month > 2 { -3 } else { 9 }) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146097 + day_of_era - 719468
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(feature = "client")]
+    #[test]
+    fn test_encode_query() {
+        assert_eq!(
+            encode_query(&[
+                ("name_contains", "Tumour & Stroma"),
+                ("include_tags", "true")
+            ]),
+            "name_contains=Tumour+%26+Stroma&include_tags=true"
+        );
+        assert_eq!(encode_query::<&str, &str>(&[]), "");
+    }
+
+    #[test]
+    fn test_parse_timestamp() {
+        assert_eq!(parse_timestamp("1970-01-01T00:00:00Z").unwrap(), 0);
+        assert_eq!(
+            parse_timestamp("2023-07-18T01:56:05.801600Z").unwrap(),
+            1689645365
+        );
+        assert_eq!(
+            parse_timestamp("2023-05-10 14:15:27").unwrap(),
+            parse_timestamp("2023-05-10T14:15:27").unwrap()
+        );
+        assert_eq!(
+            parse_timestamp("2023-05-10T16:15:27+02:00").unwrap(),
+            parse_timestamp("2023-05-10T14:15:27Z").unwrap()
+        );
+    }
+
+    #[test]
+    fn test_iso_week() {
+        let week = |value: &str| iso_week(parse_timestamp(value).unwrap());
+        assert_eq!(week("2023-05-10T14:15:27Z"), (2023, 19));
+        assert_eq!(week("2021-01-03T00:00:00Z"), (2020, 53));
+        assert_eq!(week("2024-12-30T00:00:00Z"), (2025, 1));
+        assert_eq!(week("1970-01-01T00:00:00Z"), (1970, 1));
+        assert_eq!(civil_from_days(days_from_civil(2024, 2, 29)), (2024, 2, 29));
+        assert_eq!(
+            utc_date(parse_timestamp("2024-02-29T23:59:59Z").unwrap()),
+            "2024-02-29"
+        );
+        assert_eq!(
+            utc_time(parse_timestamp("2024-02-29T23:59:58Z").unwrap()),
+            "235958"
+        );
+    }
+
+    #[cfg(feature = "client")]
+    #[test]
+    fn test_encode_path_segment() {
+        assert_eq!(encode_path_segment("0"), "0");
+        assert_eq!(encode_path_segment("slot a/b?c"), "slot%20a%2Fb%3Fc");
+    }
+
+    #[cfg(feature = "timestamps")]
+    #[test]
+    fn test_timestamp() {
+        let timestamp: Timestamp = serde_json::from_str(r#""2023-05-10 14:15:27""#).unwrap();
+        assert_eq!(timestamp, Timestamp::parse("2023-05-10T16:15:27.5+02:00"));
+        assert_eq!(timestamp.date().as_deref(), Some("2023-05-10"));
+        assert_eq!(
+            serde_json::to_string(&timestamp).unwrap(),
+            r#""2023-05-10T14:15:27Z""#
+        );
+        assert_eq!(Timestamp::Utc(-1).to_string(), "1969-12-31T23:59:59Z");
+        assert_eq!(Timestamp::parse_optional(&None), None);
+
+        let unparsed: Timestamp = serde_json::from_str(r#""yesterday""#).unwrap();
+        assert_eq!(unparsed, Timestamp::Unparsed("yesterday".to_string()));
+        assert_eq!(unparsed.unix_seconds(), None);
+        assert_eq!(serde_json::to_string(&unparsed).unwrap(), r#""yesterday""#);
+    }
+
+    #[test]
+    fn test_parse_timestamp_error() {
+        parse_timestamp("yesterday").expect_err("Invalid timestamp yesterday");
+        parse_timestamp("2023-05-10T14:15:27 PST").expect_err("Invalid timestamp");
+        // Short and multi-byte input is an error rather than a panic
+        parse_timestamp("2023-05-10").expect_err("Invalid timestamp");
+        parse_timestamp("2023-05-1é14:15:27Z").expect_err("Invalid timestamp");
+        parse_timestamp("2023-05-10T14:15:2é").expect_err("Invalid timestamp");
+        parse_timestamp("2023-05-10T14:15:27+").expect_err("Invalid timestamp");
+        parse_timestamp("2023-05-10T14:15:27é").expect_err("Invalid timestamp");
+    }
+}
@@ -0,0 +1,243 @@
+//! Parsing of the payloads sent by V7 to the url of a workflow webhook stage, and the
+//! team level webhook subscriptions managed through `TeamDataMethods`.
+//!
+//! When the stage is configured with an `authorization_header` V7 sends it verbatim in
+//! the `Authorization` header of each call, check it with `verify_authorization` before
+//! trusting the payload.
+//!
+//! Exports can also be waited on without polling `list_exports`, register the url with
+//! `DatasetExportMethods::register_export_callback` and parse each call to it with
+//! `parse_export_finished`.
+
+use crate::datasets::Export;
+use crate::ids::DatasetId;
+use crate::item::DatasetItemV2;
+use crate::known::MaybeKnown;
+use crate::workflow::{StageConfig, StageType};
+use anyhow::{bail, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// The workflow stage that triggered the webhook
+#[derive(Debug, Default, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct WebhookStage {
+    pub id: Option<String>,
+    pub name: Option<String>,
+    #[serde(rename = "type")]
+    pub stage_type: Option<StageType>,
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct WebhookWorkflow {
+    pub id: Option<String>,
+    pub name: Option<String>,
+}
+
+/// An item reaching a webhook stage
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct WebhookEvent {
+    pub item: DatasetItemV2,
+    pub stage: Option<WebhookStage>,
+    pub workflow: Option<WebhookWorkflow>,
+    pub team_slug: Option<String>,
+    /// Fields of the payload not otherwise parsed, e.g. the item annotations when
+    /// the stage is configured to include them
+    #[serde(flatten)]
+    pub other: HashMap<String, serde_json::Value>,
+}
+
+impl WebhookEvent {
+    pub fn stage_id(&self) -> Option<&str> {
+        self.stage.as_ref()?.id.as_deref()
+    }
+
+    pub fn item_id(&self) -> Option<&str> {
+        self.item.id.as_deref()
+    }
+}
+
+/// Team level events a webhook subscription can be notified of, the event types not yet
+/// modelled by this crate are kept as `MaybeKnown::Unknown`
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum WebhookEventType {
+    ItemCompleted,
+    CommentCreated,
+    ExportFinished,
+}
+
+/// A team level webhook subscription, leave `id` empty when creating one
+#[derive(Debug, Default, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct WebhookSubscription {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    pub url: String,
+    pub events: Vec<MaybeKnown<WebhookEventType>>,
+    /// Datasets the subscription is limited to, all datasets of the team when empty
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub dataset_ids: Vec<DatasetId>,
+    /// Sent verbatim in the `Authorization` header of each call
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub authorization_header: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub enabled: Option<bool>,
+    #[serde(skip_serializing)]
+    pub inserted_at: Option<String>,
+}
+
+/// Parses the body of a webhook call, reporting the path of any field that fails to parse
+pub fn parse_event(body: &[u8]) -> Result<WebhookEvent> {
+    Ok(serde_path_to_error::deserialize(
+        &mut serde_json::Deserializer::from_slice(body),
+    )?)
+}
+
+/// The dataset an export finished event is about
+#[derive(Debug, Default, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct WebhookDataset {
+    pub id: Option<u32>,
+    pub name: Option<String>,
+    pub slug: Option<String>,
+}
+
+/// Sent to the subscriptions of `WebhookEventType::ExportFinished` once an export has
+/// been generated, successfully or not
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct ExportFinishedEvent {
+    pub event: Option<MaybeKnown<WebhookEventType>>,
+    pub team_slug: Option<String>,
+    pub dataset: Option<WebhookDataset>,
+    pub export: Export,
+    /// Fields of the payload not otherwise parsed
+    #[serde(flatten)]
+    pub other: HashMap<String, serde_json::Value>,
+}
+
+impl ExportFinishedEvent {
+    pub fn export_name(&self) -> Option<&str> {
+        self.export.name.as_deref()
+    }
+
+    pub fn dataset_id(&self) -> Option<u32> {
+        self.dataset.as_ref()?.id
+    }
+
+    /// Whether the export can be downloaded, see `Export::download`
+    pub fn is_complete(&self) -> bool {
+        self.export.status.as_deref() == Some("complete") && self.export.download_url.is_some()
+    }
+}
+
+/// Parses the body of an export finished call, failing for any other team level event
+/// so that a url subscribed to several events can tell them apart
+pub fn parse_export_finished(body: &[u8]) -> Result<ExportFinishedEvent> {
+    let event: ExportFinishedEvent =
+        serde_path_to_error::deserialize(&mut serde_json::Deserializer::from_slice(body))?;
+    match event.event.as_ref() {
+        None | Some(MaybeKnown::Known(WebhookEventType::ExportFinished)) => Ok(event),
+        Some(other) => bail!("Webhook event {other:?} is not an export finished event"),
+    }
+}
+
+// Compares in constant time so the expected value cannot be guessed from response times
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+/// Checks the `Authorization` header of a webhook call against the value configured on the stage
+pub fn verify_authorization(expected: &str, received: Option<&str>) -> Result<()> {
+    match received {
+        Some(received) if constant_time_eq(expected.as_bytes(), received.as_bytes()) => Ok(()),
+        Some(_) => bail!("Webhook authorization header does not match"),
+        None => bail!("Webhook is missing authorization header"),
+    }
+}
+
+/// Verifies the `Authorization` header against the stage config, when the stage has an
+/// authorization header configured, and parses the body
+pub fn parse_verified_event(
+    config: &StageConfig,
+    authorization: Option<&str>,
+    body: &[u8],
+) -> Result<WebhookEvent> {
+    if let Some(expected) = config.authorization_header.as_ref() {
+        verify_authorization(expected, authorization)?;
+    }
+    parse_event(body)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PAYLOAD: &str = r#"{
+        "item": {
+            "id": "0189b92f-e00c-fea9-476c-0cb6e961362b",
+            "name": "slide.svs",
+            "path": "/batch-3",
+            "dataset_id": 669290
+        },
+        "stage": {"id": "5c3f6b62-1b3a-4b7e-8f2a-0e0f3f1c2d4e", "name": "Pathology QC", "type": "webhook"},
+        "workflow": {"id": "workflow-1", "name": "Slides"},
+        "team_slug": "some-team",
+        "annotations": []
+    }"#;
+
+    #[test]
+    fn test_parse_event() {
+        let event = parse_event(PAYLOAD.as_bytes()).unwrap();
+
+        assert_eq!(
+            event.item_id(),
+            Some("0189b92f-e00c-fea9-476c-0cb6e961362b")
+        );
+        assert_eq!(event.item.dataset_id, Some(669290));
+        assert_eq!(
+            event.stage_id(),
+            Some("5c3f6b62-1b3a-4b7e-8f2a-0e0f3f1c2d4e")
+        );
+        assert!(event.other.contains_key("annotations"));
+
+        let error = parse_event(br#"{"item": {"dataset_id": "abc"}}"#).unwrap_err();
+        assert!(error.to_string().starts_with("item.dataset_id"));
+    }
+
+    #[test]
+    fn test_parse_export_finished() {
+        let body = br#"{
+            "event": "export_finished",
+            "team_slug": "some-team",
+            "dataset": {"id": 7, "slug": "biopsies"},
+            "export": {
+                "name": "nightly",
+                "format": "darwin_json_2",
+                "status": "complete",
+                "download_url": "https://exports.v7labs.com/nightly.zip",
+                "latest": true,
+                "version": 2
+            }
+        }"#;
+        let event = parse_export_finished(body).unwrap();
+        assert_eq!(event.export_name(), Some("nightly"));
+        assert_eq!(event.dataset_id(), Some(7));
+        assert!(event.is_complete());
+
+        parse_export_finished(br#"{"event": "item_completed", "export": {}}"#)
+            .expect_err("Not an export finished event");
+    }
+
+    #[test]
+    fn test_parse_verified_event() {
+        let config = StageConfig {
+            authorization_header: Some("Bearer secret".to_string()),
+            ..Default::default()
+        };
+
+        parse_verified_event(&config, Some("Bearer secret"), PAYLOAD.as_bytes()).unwrap();
+        parse_verified_event(&config, Some("Bearer guess"), PAYLOAD.as_bytes())
+            .expect_err("Webhook authorization header does not match");
+        parse_verified_event(&config, None, PAYLOAD.as_bytes())
+            .expect_err("Webhook is missing authorization header");
+        parse_verified_event(&StageConfig::default(), None, PAYLOAD.as_bytes()).unwrap();
+    }
+}
@@ -1,15 +1,75 @@
+#[cfg(feature = "client")]
+use crate::classes::BoundingBox;
+#[cfg(feature = "client")]
 use crate::client::V7Methods;
+#[cfg(feature = "client")]
+use crate::comment::{CommentBody, CommentMethods, CommentThread};
+use crate::datasets::AnnotationHotKeys;
+#[cfg(feature = "client")]
+use crate::datasets::{
+    Dataset, DatasetDescribeMethods, SetStageFilter, SetStagePayloadV2, SetStageResponse,
+};
+#[cfg(feature = "client")]
 use crate::expect_http_ok;
-use anyhow::{bail, Context, Result};
+use crate::ids::{
+    DatasetId, ItemId, StageId, UserId, WorkflowId, WorkflowStageTemplateId, WorkflowTemplateId,
+};
+use crate::item::DatasetItemV2;
+use crate::known::MaybeKnown;
+use crate::maybe::Maybe;
+#[cfg(feature = "client")]
+use crate::team::{Team, TeamDescribeMethods};
+use crate::utils::{folder_path, in_folder, parse_timestamp};
+#[cfg(feature = "client")]
+use anyhow::bail;
+use anyhow::{Context, Result};
+#[cfg(feature = "client")]
 use async_trait::async_trait;
+#[cfg(feature = "fake")]
 #[allow(unused_imports)]
 use fake::{Dummy, Fake};
 use serde::{Deserialize, Serialize};
 use std::cmp::PartialEq;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::fmt::{self, Display};
 
-#[derive(Default, Debug, Clone, Serialize, Deserialize, Dummy, PartialEq, Eq)]
+/// Who the items of a stage can be assigned to
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "fake", derive(Dummy))]
+#[serde(rename_all = "snake_case")]
+pub enum AssignableTo {
+    /// Any team member with access to the dataset
+    Anyone,
+    /// Only the users assigned to the stage
+    Manual,
+}
+
+/// Kind of model run by a model stage
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "fake", derive(Dummy))]
+#[serde(rename_all = "snake_case")]
+pub enum ModelType {
+    /// A model trained in V7
+    Gust,
+    /// A model registered from an external endpoint
+    External,
+}
+
+/// Name of a stage edge, i.e. the outcome of a stage the edge is followed for
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "fake", derive(Dummy))]
+#[serde(rename_all = "snake_case")]
+pub enum EdgeName {
+    /// The only way out of stages without a decision, e.g. annotate stages
+    Default,
+    /// Followed when an item is approved in a review stage
+    Approve,
+    /// Followed when an item is rejected in a review stage
+    Reject,
+}
+
+#[derive(Default, Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[cfg_attr(feature = "fake", derive(Dummy))]
 #[serde(rename_all = "lowercase")]
 pub enum StageType {
     #[default]
@@ -21,6 +81,7 @@ pub enum StageType {
     Review,
     Dataset,
     Discard,
+    Webhook,
 }
 
 impl Display for StageType {
@@ -34,56 +95,227 @@ impl Display for StageType {
             StageType::Review => "Review",
             StageType::Dataset => "Dataset",
             StageType::Discard => "Discard",
+            StageType::Webhook => "Webhook",
         };
         write!(f, "{val}")
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, Dummy, PartialEq, Eq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[cfg_attr(feature = "fake", derive(Dummy))]
 pub struct MetaData {
     pub ready_for_completion: Option<bool>,
     pub previous_stage_number: Option<u32>,
-    pub review_status: Option<String>,
+    pub review_status: Option<MaybeKnown<ReviewStatus>>,
     pub review_status_modified_at: Option<String>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, Dummy)]
+/// Outcome of the review of an item in a v1 workflow
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "fake", derive(Dummy))]
+#[serde(rename_all = "lowercase")]
+pub enum ReviewStatus {
+    Approved,
+    Rejected,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "fake", derive(Dummy))]
 pub struct TemplateAssignee {
     pub assignee_id: Option<u32>,
+    /// Share of the items of a review stage sent to the assignee, between 0 and 1
     pub sampling_rate: Option<f64>,
 }
-#[derive(Debug, Default, Clone, Serialize, Deserialize, Dummy, PartialEq, Eq)]
+
+/// Share of the items of a review stage routed to an assignee, between 0 and 1.
+/// Out of range rates are rejected when constructed or deserialized.
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd, Serialize, Deserialize)]
+#[serde(try_from = "f64", into = "f64")]
+pub struct SamplingRate(f64);
+
+// NaN is not a valid rate
+impl Eq for SamplingRate {}
+
+impl SamplingRate {
+    /// Every item is reviewed
+    pub const ALL: Self = Self(1.0);
+
+    pub fn new(rate: f64) -> Result<Self> {
+        if !(0.0..=1.0).contains(&rate) {
+            anyhow::bail!("Sampling rate {rate} is not between 0 and 1");
+        }
+        Ok(Self(rate))
+    }
+
+    pub fn from_percentage(percentage: u8) -> Result<Self> {
+        Self::new(f64::from(percentage) / 100.0)
+    }
+
+    pub fn value(&self) -> f64 {
+        self.0
+    }
+}
+
+impl TryFrom<f64> for SamplingRate {
+    type Error = anyhow::Error;
+
+    fn try_from(rate: f64) -> Result<Self> {
+        Self::new(rate)
+    }
+}
+
+impl From<SamplingRate> for f64 {
+    fn from(rate: SamplingRate) -> Self {
+        rate.0
+    }
+}
+
+#[cfg(feature = "fake")]
+impl Dummy<fake::Faker> for SamplingRate {
+    fn dummy_with_rng<R: rand::Rng + ?Sized>(_: &fake::Faker, rng: &mut R) -> Self {
+        Self(f64::from((0..=100u8).fake_with_rng::<u8, R>(rng)) / 100.0)
+    }
+}
+
+/// Stage of a v1 workflow template, the id is the `workflow_stage_template_id`
+/// used to move the items of v1 datasets between stages
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "fake", derive(Dummy))]
+pub struct WorkflowStageTemplate {
+    pub id: Option<WorkflowStageTemplateId>,
+    pub name: Option<String>,
+    pub stage_number: Option<u32>,
+    #[serde(rename = "type")]
+    pub stage_type: Option<StageType>,
+    #[serde(default)]
+    pub assignable_users: Vec<TemplateAssignee>,
+}
+
+/// V1 workflow template, still used by datasets that have not been migrated to v2 workflows
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "fake", derive(Dummy))]
+pub struct WorkflowTemplate {
+    pub id: Option<WorkflowTemplateId>,
+    pub name: Option<String>,
+    pub dataset_id: Option<u32>,
+    #[serde(default)]
+    pub workflow_stage_templates: Vec<WorkflowStageTemplate>,
+}
+
+impl WorkflowTemplate {
+    pub fn stage_by_name(&self, name: &str) -> Option<&WorkflowStageTemplate> {
+        self.workflow_stage_templates
+            .iter()
+            .find(|stage| stage.name.as_deref() == Some(name))
+    }
+
+    pub fn stage_by_type(&self, stage_type: &StageType) -> Option<&WorkflowStageTemplate> {
+        self.workflow_stage_templates
+            .iter()
+            .find(|stage| stage.stage_type.as_ref() == Some(stage_type))
+    }
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[cfg_attr(feature = "fake", derive(Dummy))]
 pub struct FilterAssignItemPayload {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub statuses: Option<Vec<StageType>>,
-    pub dataset_ids: Vec<u32>,
+    pub dataset_ids: Vec<DatasetId>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub item_ids: Option<Vec<String>>,
     pub select_all: bool,
 }
 
-#[derive(Debug, Default, Clone, Serialize, Deserialize, Dummy, PartialEq, Eq)]
+#[derive(Debug, Default, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[cfg_attr(feature = "fake", derive(Dummy))]
+#[non_exhaustive]
 pub struct AssignItemPayload {
     pub filters: FilterAssignItemPayload,
     pub assignee_email: String,
-    pub workflow_id: String,
+    pub workflow_id: WorkflowId,
+    /// Stage to assign the items in, see `WorkflowMethods::assign_for_review`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stage_id: Option<StageId>,
 }
 
-#[derive(Debug, Default, Clone, Serialize, Deserialize, Dummy, PartialEq, Eq)]
+impl AssignItemPayload {
+    pub fn new(
+        filters: FilterAssignItemPayload,
+        assignee_email: &str,
+        workflow_id: WorkflowId,
+    ) -> Self {
+        Self {
+            filters,
+            assignee_email: assignee_email.to_string(),
+            workflow_id,
+            stage_id: None,
+        }
+    }
+
+    /// Assigns the items in `stage_id` rather than in their current stage
+    pub fn with_stage(mut self, stage_id: StageId) -> Self {
+        self.stage_id = Some(stage_id);
+        self
+    }
+}
+
+/// Removes the assignee of the items in a stage of a workflow
+#[derive(Debug, Default, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[cfg_attr(feature = "fake", derive(Dummy))]
+pub struct UnassignItemPayload {
+    pub filters: FilterAssignItemPayload,
+    pub stage_id: StageId,
+    pub workflow_id: WorkflowId,
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[cfg_attr(feature = "fake", derive(Dummy))]
 pub struct AssignItemResponse {
     pub created_commands: Option<u32>,
 }
 
-#[derive(Debug, Default, Clone, Serialize, Deserialize, Dummy, PartialEq, Eq)]
+/// Request for a batch of items to be assigned to a user in a stage of a workflow
+#[derive(Debug, Default, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[cfg_attr(feature = "fake", derive(Dummy))]
+pub struct WorkBatchRequest {
+    pub user_id: UserId,
+    /// Stage to take the items from, the first stage the user can work in when not set
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stage_id: Option<StageId>,
+    /// Number of items in the batch, the default batch size of the team when not set
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub batch_size: Option<u32>,
+}
+
+/// A batch of work requested for a user
+#[derive(Debug, Default, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[cfg_attr(feature = "fake", derive(Dummy))]
+pub struct WorkBatch {
+    pub id: Option<String>,
+    pub workflow_id: Option<String>,
+    pub stage_id: Option<String>,
+    pub user_id: Option<UserId>,
+    /// Items assigned to the user by the batch, empty until the batch is fulfilled
+    #[serde(default)]
+    pub item_ids: Vec<String>,
+    /// Either `pending` or `fulfilled`
+    pub status: Option<String>,
+    pub inserted_at: Option<String>,
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[cfg_attr(feature = "fake", derive(Dummy))]
 pub struct WorkflowDataset {
-    pub annotation_hotkeys: Option<HashMap<String, String>>,
+    pub annotation_hotkeys: Option<AnnotationHotKeys>,
     pub annotators_can_instantiate_workflows: Option<bool>,
     pub id: Option<u32>,
     pub instructions: Option<String>,
     pub name: Option<String>,
 }
 
-#[derive(Debug, Default, Clone, Serialize, Deserialize, Dummy, PartialEq, Eq)]
+#[derive(Debug, Default, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[cfg_attr(feature = "fake", derive(Dummy))]
 pub struct WorkflowProgress {
     pub complete: Option<u32>,
     pub idle: Option<u32>,
@@ -91,21 +323,23 @@ pub struct WorkflowProgress {
     pub total: Option<u32>,
 }
 
-#[derive(Debug, Default, Clone, Serialize, Deserialize, Dummy, PartialEq, Eq)]
+#[derive(Debug, Default, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[cfg_attr(feature = "fake", derive(Dummy))]
 pub struct StageConfig {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub allowed_class_ids: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub annotation_group_id: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub assignable_to: Option<String>,
+    pub assignable_to: Option<MaybeKnown<AssignableTo>>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub authorization_header: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub auto_instantiate: Option<bool>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub champion_stage_id: Option<String>,
-    pub class_mapping: Option<Vec<String>>,
+    #[serde(default, skip_serializing_if = "Maybe::is_missing")]
+    pub class_mapping: Maybe<Vec<String>>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub dataset_id: Option<u32>,
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -118,14 +352,15 @@ pub struct StageConfig {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub model_id: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub model_type: Option<String>,
+    pub model_type: Option<MaybeKnown<ModelType>>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub parallel_stage_ids: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub readonly: Option<bool>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub retry_if_fails: Option<bool>,
-    pub rules: Option<Vec<String>>,
+    #[serde(default, skip_serializing_if = "Maybe::is_missing")]
+    pub rules: Maybe<Vec<String>>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub skippable: Option<bool>,
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -138,22 +373,28 @@ pub struct StageConfig {
     pub y: Option<u32>,
 }
 
-#[derive(Debug, Default, Clone, Serialize, Deserialize, Dummy, PartialEq, Eq)]
+#[derive(Debug, Default, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[cfg_attr(feature = "fake", derive(Dummy))]
 pub struct StageEdge {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub id: Option<String>,
-    pub name: Option<String>, //FIXME: What are the different types?
+    pub name: Option<MaybeKnown<EdgeName>>,
     pub source_stage_id: Option<String>,
     pub target_stage_id: Option<String>,
 }
 
-#[derive(Debug, Default, Clone, Serialize, Deserialize, Dummy, PartialEq, Eq)]
+#[derive(Debug, Default, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[cfg_attr(feature = "fake", derive(Dummy))]
 pub struct WorkflowStageAssignees {
     pub stage_id: Option<String>,
     pub user_id: Option<u32>,
+    /// Only used by review stages, all of the items are routed to the assignee when `None`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sampling_rate: Option<SamplingRate>,
 }
 
-#[derive(Debug, Default, Clone, Serialize, Deserialize, Dummy, PartialEq, Eq)]
+#[derive(Debug, Default, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[cfg_attr(feature = "fake", derive(Dummy))]
 pub struct WorkflowStageV2 {
     pub assignable_users: Vec<Option<WorkflowStageAssignees>>,
     pub config: Option<StageConfig>,
@@ -164,7 +405,8 @@ pub struct WorkflowStageV2 {
     pub stage_type: Option<StageType>,
 }
 
-#[derive(Debug, Default, Clone, Serialize, Deserialize, Dummy, PartialEq, Eq)]
+#[derive(Debug, Default, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[cfg_attr(feature = "fake", derive(Dummy))]
 pub struct WorkflowV2 {
     pub dataset: Option<WorkflowDataset>,
     pub id: Option<String>,
@@ -175,6 +417,8 @@ pub struct WorkflowV2 {
     pub team_id: Option<u32>,
     pub thumbnails: Vec<Option<String>>,
     pub updated_at: Option<String>,
+    /// Whether annotators of the workflow have requested a batch of work,
+    /// see `WorkflowMethods::request_work_batch`
     #[serde(skip_serializing_if = "Option::is_none")]
     pub work_batch_requested: Option<bool>,
     #[serde(rename = "additionalProp")]
@@ -182,12 +426,853 @@ pub struct WorkflowV2 {
     pub additional_prop: Option<u32>,
 }
 
-#[derive(Debug, Default, Clone, Serialize, Deserialize, Dummy, PartialEq, Eq)]
+/// Error returned when the configuration of a stage forbids moving an item out of it,
+/// raised before any request is made rather than relying upon a 422 from V7.
+///
+/// Recover it from an `anyhow::Error` with `error.downcast_ref::<StageTransitionError>()`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StageTransitionError {
+    /// The stage is readonly, holds the stage id
+    Readonly(String),
+    /// The stage is not configured as skippable, holds the stage id
+    NotSkippable(String),
+    /// The stage has no outgoing edge to move the item along, holds the stage id
+    NoNextStage(String),
+}
+
+impl Display for StageTransitionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Readonly(stage_id) => write!(f, "Stage {stage_id} is readonly"),
+            Self::NotSkippable(stage_id) => write!(f, "Stage {stage_id} is not skippable"),
+            Self::NoNextStage(stage_id) => write!(f, "Stage {stage_id} has no next stage"),
+        }
+    }
+}
+
+impl std::error::Error for StageTransitionError {}
+
+/// The transitions allowed out of a stage by its configuration
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct StageTransitions {
+    pub can_skip: bool,
+    pub can_complete: bool,
+    /// Stage the item moves to when skipped or completed
+    pub next_stage_id: Option<String>,
+}
+
+impl WorkflowStageV2 {
+    fn is_readonly(&self) -> bool {
+        self.config
+            .as_ref()
+            .and_then(|config| config.readonly)
+            .unwrap_or_default()
+    }
+
+    fn is_skippable(&self) -> bool {
+        self.config
+            .as_ref()
+            .and_then(|config| config.skippable)
+            .unwrap_or_default()
+    }
+
+    /// Target of the edge followed when the stage is completed, approval for review stages
+    fn next_stage_id(&self) -> Option<&String> {
+        let edges: Vec<&StageEdge> = self.edges.iter().flatten().collect();
+        edges
+            .iter()
+            .find(|edge| {
+                matches!(
+                    edge.name,
+                    Some(MaybeKnown::Known(EdgeName::Default | EdgeName::Approve))
+                )
+            })
+            .or(edges.first())
+            .and_then(|edge| edge.target_stage_id.as_ref())
+    }
+
+    pub fn transitions(&self) -> StageTransitions {
+        let next_stage_id = self.next_stage_id().cloned();
+        let can_move = !self.is_readonly() && next_stage_id.is_some();
+        StageTransitions {
+            can_skip: can_move && self.is_skippable(),
+            can_complete: can_move,
+            next_stage_id,
+        }
+    }
+}
+
+impl WorkflowV2 {
+    pub fn stage(&self, stage_id: &StageId) -> Option<&WorkflowStageV2> {
+        self.stages
+            .iter()
+            .flatten()
+            .find(|stage| stage.id.as_deref() == Some(stage_id.as_str()))
+    }
+
+    /// The stage items enter the workflow at, the stage configured as initial or else the
+    /// dataset stage
+    pub fn initial_stage(&self) -> Option<&WorkflowStageV2> {
+        let mut stages = self.stages.iter().flatten();
+        stages
+            .clone()
+            .find(|stage| {
+                stage
+                    .config
+                    .as_ref()
+                    .and_then(|config| config.initial)
+                    .unwrap_or_default()
+            })
+            .or_else(|| stages.find(|stage| stage.stage_type == Some(StageType::Dataset)))
+    }
+
+    /// The stage the edge named `edge_name` leads to from the stage, e.g. the stage after
+    /// a review stage on `EdgeName::Approve`
+    pub fn next_stage(
+        &self,
+        stage_id: &StageId,
+        edge_name: &MaybeKnown<EdgeName>,
+    ) -> Option<&WorkflowStageV2> {
+        let target = self
+            .stage(stage_id)?
+            .edges
+            .iter()
+            .flatten()
+            .find(|edge| edge.name.as_ref() == Some(edge_name))?
+            .target_stage_id
+            .as_deref()?;
+        self.stage(&StageId::from(target))
+    }
+
+    pub fn stages_of_type(&self, stage_type: &StageType) -> Vec<&WorkflowStageV2> {
+        self.stages
+            .iter()
+            .flatten()
+            .filter(|stage| stage.stage_type.as_ref() == Some(stage_type))
+            .collect()
+    }
+
+    /// The ids of the stages along a cycle of edges, if the workflow has one. Note that
+    /// the reject edge of a review stage usually leads back to an earlier stage and so
+    /// forms a cycle, exclude those edges with `ignore` when only other loops are of interest.
+    pub fn find_cycle(&self, ignore: &[MaybeKnown<EdgeName>]) -> Option<Vec<StageId>> {
+        // Depth first search, a cycle is found when an edge leads to a stage on the path
+        fn visit<'a>(
+            workflow: &'a WorkflowV2,
+            stage: &'a WorkflowStageV2,
+            ignore: &[MaybeKnown<EdgeName>],
+            path: &mut Vec<&'a str>,
+            done: &mut HashSet<&'a str>,
+        ) -> Option<Vec<StageId>> {
+            let id = stage.id.as_deref()?;
+            if let Some(start) = path.iter().position(|visited| *visited == id) {
+                return Some(path[start..].iter().copied().map(StageId::from).collect());
+            }
+            if !done.insert(id) {
+                return None;
+            }
+            path.push(id);
+            for edge in stage.edges.iter().flatten() {
+                if edge.name.as_ref().is_some_and(|name| ignore.contains(name)) {
+                    continue;
+                }
+                let next = edge
+                    .target_stage_id
+                    .as_deref()
+                    .and_then(|target| workflow.stage(&StageId::from(target)));
+                if let Some(cycle) = next.and_then(|next| visit(workflow, next, ignore, path, done))
+                {
+                    return Some(cycle);
+                }
+            }
+            path.pop();
+            None
+        }
+
+        let mut done = HashSet::new();
+        self.stages
+            .iter()
+            .flatten()
+            .find_map(|stage| visit(self, stage, ignore, &mut vec![], &mut done))
+    }
+
+    /// The stage the item is currently in, the item must be listed with workflow data
+    pub fn current_stage(&self, item: &DatasetItemV2) -> Result<&WorkflowStageV2> {
+        let stage_id = item
+            .workflow_data
+            .as_ref()
+            .and_then(|data| data.current_stage_instances.iter().flatten().next())
+            .and_then(|instance| instance.stage_id.as_deref())
+            .map(StageId::from)
+            .context("Item is missing workflow data")?;
+        self.stage(&stage_id)
+            .with_context(|| format!("Stage {stage_id} is not in workflow"))
+    }
+
+    /// The transitions allowed for the item out of its current stage
+    pub fn item_transitions(&self, item: &DatasetItemV2) -> Result<StageTransitions> {
+        Ok(self.current_stage(item)?.transitions())
+    }
+}
+
+/// The workflow stage an item is currently in, embedded in item listings when
+/// `include_workflow_data` is requested
+#[derive(Debug, Default, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[cfg_attr(feature = "fake", derive(Dummy))]
+pub struct ItemCurrentStage {
+    pub id: Option<String>,
+    pub name: Option<String>,
+    #[serde(rename = "type")]
+    pub stage_type: Option<StageType>,
+}
+
+/// A user assigned to an item, embedded in item listings when `include_workflow_data`
+/// is requested
+#[derive(Debug, Default, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[cfg_attr(feature = "fake", derive(Dummy))]
+pub struct ItemAssignee {
+    pub user_id: Option<UserId>,
+    pub email: Option<String>,
+    /// The stage the user is assigned to the item in
+    pub stage_id: Option<String>,
+}
+
+/// An item's presence in a workflow stage
+#[derive(Debug, Default, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[cfg_attr(feature = "fake", derive(Dummy))]
+pub struct StageInstance {
+    pub id: Option<String>,
+    pub stage_id: Option<String>,
+    /// The user currently assigned to the item in this stage
+    pub user_id: Option<u32>,
+    /// Timestamp of when the item entered the stage
+    pub inserted_at: Option<String>,
+    pub updated_at: Option<String>,
+}
+
+/// Workflow data embedded in item listings when `include_workflow_data` is requested
+#[derive(Debug, Default, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[cfg_attr(feature = "fake", derive(Dummy))]
+pub struct ItemWorkflowData {
+    pub workflow_id: Option<String>,
+    #[serde(default)]
+    pub current_stage_instances: Vec<Option<StageInstance>>,
+}
+
+/// How long an item has been sitting in its current workflow stage
+#[derive(Debug, Default, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ItemStageTime {
+    pub item_id: Option<String>,
+    pub item_name: Option<String>,
+    pub stage_id: Option<String>,
+    pub stage_name: Option<String>,
+    pub stage_type: Option<StageType>,
+    pub assignee_id: Option<u32>,
+    /// Timestamp of when the item entered the stage
+    pub entered_at: Option<String>,
+}
+
+impl ItemStageTime {
+    /// Number of seconds the item has spent in the stage as of `now` (seconds since the
+    /// unix epoch). `None` if V7 did not provide a valid entry timestamp.
+    pub fn seconds_in_stage(&self, now: i64) -> Option<i64> {
+        let entered_at = parse_timestamp(self.entered_at.as_ref()?).ok()?;
+        Some(now - entered_at)
+    }
+}
+
+/// The kind of change recorded in an item's history
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ItemEventType {
+    Created,
+    StageChanged,
+    Assigned,
+    Unassigned,
+    StatusChanged,
+}
+
+/// A single entry of an item's workflow history
+#[derive(Debug, Default, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ItemHistoryEvent {
+    pub id: Option<String>,
+    #[serde(rename = "type", default)]
+    pub event_type: Option<MaybeKnown<ItemEventType>>,
+    /// The user who caused the event, `None` for automated transitions
+    pub actor_id: Option<u32>,
+    pub from_stage_id: Option<String>,
+    pub to_stage_id: Option<String>,
+    pub assignee_id: Option<u32>,
+    /// Timestamp of when the event happened
+    pub created_at: Option<String>,
+    /// Any additional fields V7 includes with the event
+    #[serde(flatten)]
+    pub other: HashMap<String, serde_json::Value>,
+}
+
+impl ItemHistoryEvent {
+    /// Seconds since the unix epoch of when the event happened
+    pub fn timestamp(&self) -> Result<i64> {
+        parse_timestamp(
+            self.created_at
+                .as_ref()
+                .context("Event is missing timestamp")?,
+        )
+    }
+}
+
+/// A stage transition of an item, as recorded for audit purposes
+#[derive(Debug, Default, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct StageTransitionRecord {
+    pub item_id: ItemId,
+    pub from_stage: Option<String>,
+    pub to_stage: Option<String>,
+    pub actor_id: Option<u32>,
+    /// Seconds since the unix epoch of the transition
+    pub timestamp: i64,
+}
+
+/// Extracts the stage transitions from an item's history in chronological order,
+/// resolving stage ids to names using `workflow` where possible
+pub fn stage_transitions(
+    item_id: &ItemId,
+    history: &[ItemHistoryEvent],
+    workflow: Option<&WorkflowV2>,
+) -> Result<Vec<StageTransitionRecord>> {
+    let stage_name = |stage_id: &Option<String>| -> Option<String> {
+        let stage_id = StageId::from(stage_id.as_deref()?);
+        Some(
+            workflow
+                .and_then(|workflow| workflow.stage(&stage_id))
+                .and_then(|stage| stage.name.clone())
+                .unwrap_or_else(|| stage_id.into()),
+        )
+    };
+
+    let mut transitions = history
+        .iter()
+        .filter(|event| event.event_type == Some(MaybeKnown::Known(ItemEventType::StageChanged)))
+        .map(|event| {
+            Ok(StageTransitionRecord {
+                item_id: item_id.clone(),
+                from_stage: stage_name(&event.from_stage_id),
+                to_stage: stage_name(&event.to_stage_id),
+                actor_id: event.actor_id,
+                timestamp: event.timestamp()?,
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+    transitions.sort_by_key(|transition| transition.timestamp);
+    Ok(transitions)
+}
+
+/// Aggregated time in stage for all items currently in a single stage
+#[derive(Debug, Default, Clone, Serialize, Deserialize, PartialEq)]
+pub struct StageTimeSummary {
+    pub stage_id: Option<String>,
+    pub stage_name: Option<String>,
+    pub stage_type: Option<StageType>,
+    pub item_count: u32,
+    pub mean_seconds_in_stage: Option<f64>,
+    pub max_seconds_in_stage: Option<i64>,
+}
+
+/// Aggregates per-item stage times into a summary per stage, ordered by stage id.
+pub fn summarize_stage_times(times: &[ItemStageTime], now: i64) -> Vec<StageTimeSummary> {
+    let mut summaries: Vec<StageTimeSummary> = Vec::new();
+    let mut durations: HashMap<Option<String>, Vec<i64>> = HashMap::new();
+
+    for time in times.iter() {
+        match summaries.iter_mut().find(|s| s.stage_id == time.stage_id) {
+            Some(summary) => summary.item_count += 1,
+            None => summaries.push(StageTimeSummary {
+                stage_id: time.stage_id.clone(),
+                stage_name: time.stage_name.clone(),
+                stage_type: time.stage_type.clone(),
+                item_count: 1,
+                ..Default::default()
+            }),
+        }
+        if let Some(seconds) = time.seconds_in_stage(now) {
+            durations
+                .entry(time.stage_id.clone())
+                .or_default()
+                .push(seconds);
+        }
+    }
+
+    for summary in summaries.iter_mut() {
+        if let Some(seconds) = durations.get(&summary.stage_id) {
+            summary.max_seconds_in_stage = seconds.iter().max().copied();
+            summary.mean_seconds_in_stage =
+                Some(seconds.iter().sum::<i64>() as f64 / seconds.len() as f64);
+        }
+    }
+    summaries.sort_by(|a, b| a.stage_id.cmp(&b.stage_id));
+    summaries
+}
+
+/// Restricts `count_items_by_stage` to some of the items, all items by default
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct StageCountFilter {
+    /// Only items in this folder or its sub-folders, e.g. `/scans/2024`
+    pub folder: Option<String>,
+    /// Only items whose name starts with this prefix
+    pub name_prefix: Option<String>,
+}
+
+impl StageCountFilter {
+    pub fn matches(&self, item: &DatasetItemV2) -> bool {
+        let in_folder = self.folder.as_deref().is_none_or(|folder| {
+            in_folder(
+                &folder_path(item.path.as_deref().unwrap_or("/")),
+                &folder_path(folder),
+            )
+        });
+        let has_prefix = self.name_prefix.as_deref().is_none_or(|prefix| {
+            item.name
+                .as_deref()
+                .is_some_and(|name| name.starts_with(prefix))
+        });
+        in_folder && has_prefix
+    }
+}
+
+/// Number of items currently in a single stage
+#[derive(Debug, Default, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct StageCount {
+    pub stage_id: Option<String>,
+    pub stage_name: Option<String>,
+    pub stage_type: Option<StageType>,
+    pub item_count: u32,
+}
+
+/// The items counted by `count_items_by_stage`
+#[derive(Debug, Default, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct StageCounts {
+    /// Every stage of the workflow in workflow order, including empty stages, followed by
+    /// any stages items are in that are no longer part of the workflow
+    pub stages: Vec<StageCount>,
+    /// Items matching the filter that are not in any stage, e.g. not yet in the workflow
+    pub items_without_stage: u32,
+}
+
+impl StageCounts {
+    pub fn total(&self) -> u32 {
+        self.stages
+            .iter()
+            .map(|stage| stage.item_count)
+            .sum::<u32>()
+            + self.items_without_stage
+    }
+
+    pub fn stage(&self, stage_id: &StageId) -> Option<&StageCount> {
+        self.stages
+            .iter()
+            .find(|stage| stage.stage_id.as_deref() == Some(stage_id.as_str()))
+    }
+}
+
+/// Counts the items matching `filter` in each stage of the workflow, the items must be
+/// listed with workflow data. Unlike the `progress` of the workflow the counts are per
+/// stage rather than per status. An item in several stages at once is counted in each.
+pub fn count_items_by_stage(
+    workflow: &WorkflowV2,
+    items: &[DatasetItemV2],
+    filter: &StageCountFilter,
+) -> StageCounts {
+    let mut counts = StageCounts {
+        stages: workflow
+            .stages
+            .iter()
+            .flatten()
+            .map(|stage| StageCount {
+                stage_id: stage.id.clone(),
+                stage_name: stage.name.clone(),
+                stage_type: stage.stage_type.clone(),
+                item_count: 0,
+            })
+            .collect(),
+        items_without_stage: 0,
+    };
+
+    for item in items.iter().filter(|item| filter.matches(item)) {
+        let stage_ids: Vec<&str> = item
+            .workflow_data
+            .iter()
+            .flat_map(|data| data.current_stage_instances.iter().flatten())
+            .filter_map(|instance| instance.stage_id.as_deref())
+            .collect();
+        if stage_ids.is_empty() {
+            counts.items_without_stage += 1;
+        }
+        for stage_id in stage_ids {
+            match counts
+                .stages
+                .iter_mut()
+                .find(|stage| stage.stage_id.as_deref() == Some(stage_id))
+            {
+                Some(stage) => stage.item_count += 1,
+                None => counts.stages.push(StageCount {
+                    stage_id: Some(stage_id.to_string()),
+                    item_count: 1,
+                    ..Default::default()
+                }),
+            }
+        }
+    }
+    counts
+}
+
+/// Finds the items that have been in a stage of `stage_type` for longer than `max_seconds`
+pub fn items_stuck_in_stage<'a>(
+    times: &'a [ItemStageTime],
+    stage_type: &StageType,
+    max_seconds: i64,
+    now: i64,
+) -> Vec<&'a ItemStageTime> {
+    times
+        .iter()
+        .filter(|time| time.stage_type.as_ref() == Some(stage_type))
+        .filter(|time| time.seconds_in_stage(now).unwrap_or_default() > max_seconds)
+        .collect()
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[cfg_attr(feature = "fake", derive(Dummy))]
 pub struct WorkflowBuilder {
     pub stages: Vec<WorkflowStageV2>,
     pub name: Option<String>,
 }
 
+/// The datasets, members and models of a team that a workflow may reference,
+/// see `WorkflowBuilder::validate`
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct TeamCapabilities {
+    pub dataset_ids: HashSet<DatasetId>,
+    /// User ids of the members of the team
+    pub user_ids: HashSet<UserId>,
+    /// Models that model stages may run, not checked when `None`
+    pub model_ids: Option<HashSet<String>>,
+}
+
+impl TeamCapabilities {
+    /// Checks the `model_id` of model stages against `model_ids`
+    pub fn with_model_ids(mut self, model_ids: impl IntoIterator<Item = String>) -> Self {
+        self.model_ids = Some(model_ids.into_iter().collect());
+        self
+    }
+}
+
+#[cfg(feature = "client")]
+impl TeamCapabilities {
+    /// Lists the datasets and members of the team of `client`. The models are not listed
+    /// by this crate and so are left unchecked, add them with `with_model_ids`.
+    pub async fn fetch<C>(client: &C) -> Result<Self>
+    where
+        C: V7Methods + std::marker::Sync,
+    {
+        let dataset_ids = Dataset::list_datasets(client)
+            .await?
+            .into_iter()
+            .flatten()
+            .filter_map(|dataset| dataset.id.map(DatasetId))
+            .collect();
+        let user_ids = Team::list_memberships(client)
+            .await?
+            .into_iter()
+            .filter_map(|member| member.user_id.map(UserId))
+            .collect();
+        Ok(Self {
+            dataset_ids,
+            user_ids,
+            model_ids: None,
+        })
+    }
+}
+
+/// A problem found by `WorkflowBuilder::validate`, each holds the name (or id) of the stage
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WorkflowProblem {
+    UnknownDataset {
+        stage: String,
+        dataset_id: DatasetId,
+    },
+    UnknownAssignee {
+        stage: String,
+        user_id: UserId,
+    },
+    MissingModel {
+        stage: String,
+    },
+    UnknownModel {
+        stage: String,
+        model_id: String,
+    },
+    /// A consensus threshold that is not a number between 0 and 1
+    InvalidThreshold {
+        stage: String,
+        threshold: String,
+    },
+    /// A sampling rate on an assignee of a stage other than a review stage
+    UnexpectedSamplingRate {
+        stage: String,
+        user_id: Option<UserId>,
+    },
+}
+
+impl Display for WorkflowProblem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnknownDataset { stage, dataset_id } => {
+                write!(f, "Stage {stage} references unknown dataset {dataset_id}")
+            }
+            Self::UnknownAssignee { stage, user_id } => {
+                write!(
+                    f,
+                    "Stage {stage} assignee {user_id} is not a member of the team"
+                )
+            }
+            Self::MissingModel { stage } => write!(f, "Model stage {stage} has no model"),
+            Self::UnknownModel { stage, model_id } => {
+                write!(f, "Stage {stage} references unknown model {model_id}")
+            }
+            Self::InvalidThreshold { stage, threshold } => {
+                write!(
+                    f,
+                    "Stage {stage} threshold {threshold} is not between 0 and 1"
+                )
+            }
+            Self::UnexpectedSamplingRate { stage, user_id } => {
+                write!(f, "Stage {stage} is not a review stage but assignee ")?;
+                match user_id {
+                    Some(user_id) => write!(f, "{user_id}")?,
+                    None => write!(f, "without a user")?,
+                }
+                write!(f, " has a sampling rate")
+            }
+        }
+    }
+}
+
+/// Every problem found by `WorkflowBuilder::validate`, raised before any request is made
+/// rather than relying upon a 422 from V7.
+///
+/// Recover it from an `anyhow::Error` with `error.downcast_ref::<WorkflowValidationError>()`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WorkflowValidationError {
+    pub problems: Vec<WorkflowProblem>,
+}
+
+impl Display for WorkflowValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Invalid workflow: ")?;
+        for (index, problem) in self.problems.iter().enumerate() {
+            if index > 0 {
+                write!(f, "; ")?;
+            }
+            write!(f, "{problem}")?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for WorkflowValidationError {}
+
+// Whether a consensus threshold, a number or a map of annotation type to number, is
+// within 0 and 1
+fn is_valid_threshold(threshold: &str) -> bool {
+    let in_bounds = |value: &serde_json::Value| {
+        value
+            .as_f64()
+            .is_some_and(|value| (0.0..=1.0).contains(&value))
+    };
+    match serde_json::from_str::<serde_json::Value>(threshold) {
+        Ok(serde_json::Value::Object(thresholds)) => thresholds.values().all(in_bounds),
+        Ok(value) => in_bounds(&value),
+        Err(_) => false,
+    }
+}
+
+// Whether every field set in `expected` has the same value in `actual`. Null and
+// missing fields and empty lists of `expected` are unset, lists are compared by position.
+fn sets_same_fields(expected: &serde_json::Value, actual: &serde_json::Value) -> bool {
+    use serde_json::Value;
+    match (expected, actual) {
+        (Value::Null, _) => true,
+        (Value::Array(expected), _) if expected.is_empty() => true,
+        (Value::Object(expected), Value::Object(actual)) => expected
+            .iter()
+            .all(|(key, value)| sets_same_fields(value, actual.get(key).unwrap_or(&Value::Null))),
+        (Value::Array(expected), Value::Array(actual)) => {
+            expected.len() == actual.len()
+                && expected
+                    .iter()
+                    .zip(actual.iter())
+                    .all(|(expected, actual)| sets_same_fields(expected, actual))
+        }
+        (expected, actual) => expected == actual,
+    }
+}
+
+impl WorkflowBuilder {
+    /// Whether `workflow` already has the name and stages of the builder, comparing only
+    /// the fields the builder sets so that the ids and defaults V7 fills in are ignored.
+    /// Stages are compared in order, and empty lists, e.g. of assignable users, are unset.
+    pub fn matches(&self, workflow: &WorkflowV2) -> bool {
+        let stages: Vec<&WorkflowStageV2> = workflow.stages.iter().flatten().collect();
+        let (Ok(expected), Ok(actual)) = (
+            serde_json::to_value(&self.stages),
+            serde_json::to_value(&stages),
+        ) else {
+            return false;
+        };
+        let same_name = self.name.is_none() || self.name == workflow.name;
+        same_name && stages.len() == self.stages.len() && sets_same_fields(&expected, &actual)
+    }
+
+    /// Checks the stages against the datasets, members and models of the team, returning
+    /// all of the problems found at once. Call before `set_workflow_v2` or `update_workflow`.
+    pub fn validate(
+        &self,
+        capabilities: &TeamCapabilities,
+    ) -> std::result::Result<(), WorkflowValidationError> {
+        let mut problems = vec![];
+        for (index, stage) in self.stages.iter().enumerate() {
+            let name = stage
+                .name
+                .clone()
+                .or_else(|| stage.id.clone())
+                .unwrap_or_else(|| index.to_string());
+
+            for assignee in stage.assignable_users.iter().flatten() {
+                if let Some(user_id) = assignee.user_id.map(UserId) {
+                    if !capabilities.user_ids.contains(&user_id) {
+                        problems.push(WorkflowProblem::UnknownAssignee {
+                            stage: name.clone(),
+                            user_id,
+                        });
+                    }
+                }
+                if assignee.sampling_rate.is_some() && stage.stage_type != Some(StageType::Review) {
+                    problems.push(WorkflowProblem::UnexpectedSamplingRate {
+                        stage: name.clone(),
+                        user_id: assignee.user_id.map(UserId),
+                    });
+                }
+            }
+
+            let config = stage.config.clone().unwrap_or_default();
+            if let Some(dataset_id) = config.dataset_id.map(DatasetId) {
+                if !capabilities.dataset_ids.contains(&dataset_id) {
+                    problems.push(WorkflowProblem::UnknownDataset {
+                        stage: name.clone(),
+                        dataset_id,
+                    });
+                }
+            }
+
+            match stage.stage_type {
+                Some(StageType::Model) => match (config.model_id, &capabilities.model_ids) {
+                    (None, _) => problems.push(WorkflowProblem::MissingModel {
+                        stage: name.clone(),
+                    }),
+                    (Some(model_id), Some(model_ids)) if !model_ids.contains(&model_id) => problems
+                        .push(WorkflowProblem::UnknownModel {
+                            stage: name.clone(),
+                            model_id,
+                        }),
+                    _ => {}
+                },
+                Some(StageType::Consensus) => {
+                    for threshold in [config.threshold, config.iou_thresholds]
+                        .into_iter()
+                        .flatten()
+                    {
+                        if !is_valid_threshold(&threshold) {
+                            problems.push(WorkflowProblem::InvalidThreshold {
+                                stage: name.clone(),
+                                threshold,
+                            });
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(WorkflowValidationError { problems })
+        }
+    }
+
+    // The review stage named `stage`, or with the id `stage`
+    fn review_stage_mut(&mut self, stage: &str) -> Result<&mut WorkflowStageV2> {
+        let found = self
+            .stages
+            .iter_mut()
+            .find(|s| s.name.as_deref() == Some(stage) || s.id.as_deref() == Some(stage))
+            .with_context(|| format!("Workflow has no stage {stage}"))?;
+        if found.stage_type != Some(StageType::Review) {
+            anyhow::bail!("Stage {stage} is not a review stage");
+        }
+        Ok(found)
+    }
+
+    /// Routes `rate` of the items of the review stage named (or with the id) `stage` to
+    /// `user_id`, adding the user to the assignees of the stage if needed. A rate of `None`
+    /// routes all of the items to the user.
+    pub fn set_review_sampling(
+        &mut self,
+        stage: &str,
+        user_id: u32,
+        rate: Option<SamplingRate>,
+    ) -> Result<()> {
+        let review = self.review_stage_mut(stage)?;
+        let stage_id = review.id.clone();
+        match review
+            .assignable_users
+            .iter_mut()
+            .flatten()
+            .find(|assignee| assignee.user_id == Some(user_id))
+        {
+            Some(assignee) => assignee.sampling_rate = rate,
+            None => review.assignable_users.push(Some(WorkflowStageAssignees {
+                stage_id,
+                user_id: Some(user_id),
+                sampling_rate: rate,
+            })),
+        }
+        Ok(())
+    }
+
+    /// Replaces the assignees of the review stage named (or with the id) `stage` with those
+    /// of a v1 workflow template stage, keeping their sampling rates. Fails without changing
+    /// the stage if any rate is not between 0 and 1.
+    pub fn set_review_assignees(
+        &mut self,
+        stage: &str,
+        assignees: &[TemplateAssignee],
+    ) -> Result<()> {
+        let review = self.review_stage_mut(stage)?;
+        let assignable_users = assignees
+            .iter()
+            .map(|assignee| {
+                Ok(Some(WorkflowStageAssignees {
+                    stage_id: review.id.clone(),
+                    user_id: assignee.assignee_id,
+                    sampling_rate: assignee.sampling_rate.map(SamplingRate::new).transpose()?,
+                }))
+            })
+            .collect::<Result<_>>()?;
+        review.assignable_users = assignable_users;
+        Ok(())
+    }
+}
+
+#[cfg(feature = "client")]
 #[async_trait]
 pub trait WorkflowMethods<C>
 where
@@ -195,14 +1280,156 @@ where
 {
     async fn list_workflows(client: &C, contains_str: Option<String>) -> Result<Vec<WorkflowV2>>;
     async fn assign_items(client: &C, data: &AssignItemPayload) -> Result<AssignItemResponse>;
+    /// Assigns the items matching `filters` to the user in every annotate stage of the
+    /// workflow, failing before any request if the workflow has no annotate stage
+    async fn assign_for_annotation(
+        &self,
+        client: &C,
+        assignee_email: &str,
+        filters: &FilterAssignItemPayload,
+    ) -> Result<AssignItemResponse>;
+    /// Assigns the items matching `filters` to the user in the review stage `stage_id`,
+    /// leaving the annotators of the items assigned. Fails before any request if
+    /// `stage_id` is not a review stage of the workflow
+    async fn assign_for_review(
+        &self,
+        client: &C,
+        stage_id: &StageId,
+        assignee_email: &str,
+        filters: &FilterAssignItemPayload,
+    ) -> Result<AssignItemResponse>;
+    /// Removes the assignee of the items matching `filters` in every stage of `stage_type`
+    async fn unassign(
+        &self,
+        client: &C,
+        stage_type: &StageType,
+        filters: &FilterAssignItemPayload,
+    ) -> Result<AssignItemResponse>;
     async fn get_workflows(client: &C) -> Result<Vec<WorkflowV2>>;
+    async fn get_workflow(client: &C, workflow_id: &WorkflowId) -> Result<WorkflowV2>;
     async fn update_workflow(
         &self,
         client: &C,
         update_payload: &WorkflowBuilder,
     ) -> Result<WorkflowV2>;
+    /// Skips the item past its current stage, failing with a `StageTransitionError`
+    /// if the stage is readonly or not skippable
+    async fn skip_item(&self, client: &C, item: &DatasetItemV2) -> Result<SetStageResponse>;
+    /// Completes the current stage of the item, failing with a `StageTransitionError`
+    /// if the stage is readonly or has no next stage
+    async fn complete_item(&self, client: &C, item: &DatasetItemV2) -> Result<SetStageResponse>;
+    /// Sends the item to the discard stage of the workflow from whichever stage it is in.
+    /// V7 records no reason for a stage change, so a `reason` is left as a comment thread
+    /// on the item once it has been moved, where it is picked up by `export_comment_threads`.
+    async fn discard_item(
+        &self,
+        client: &C,
+        item: &DatasetItemV2,
+        reason: Option<&str>,
+    ) -> Result<SetStageResponse>;
+    /// Requests a batch of items to be assigned to the user of `request`
+    async fn request_work_batch(&self, client: &C, request: &WorkBatchRequest)
+        -> Result<WorkBatch>;
+    /// Lists the work batches requested in the workflow, optionally only those of `user_id`
+    async fn list_work_batches(
+        &self,
+        client: &C,
+        user_id: Option<UserId>,
+    ) -> Result<Vec<WorkBatch>>;
+}
+
+// A comment thread with the reason an item was discarded, covering its first slot
+#[cfg(feature = "client")]
+fn discard_comment(item: &DatasetItemV2, reason: &str) -> CommentThread {
+    let slot = item.slots.iter().flatten().next();
+    let dimensions = slot
+        .and_then(|slot| slot.metadata.as_ref())
+        .and_then(|metadata| Some((metadata.width?, metadata.height?)));
+    let (w, h) = dimensions.map_or((1.0, 1.0), |(w, h)| (w as f32, h as f32));
+    CommentThread {
+        bounding_box: BoundingBox {
+            x: 0.0,
+            y: 0.0,
+            w,
+            h,
+        },
+        comments: vec![CommentBody {
+            body: format!("Discarded: {reason}"),
+        }],
+        slot_name: slot
+            .and_then(|slot| slot.slot_name.clone())
+            .unwrap_or_else(|| "0".to_string()),
+    }
 }
 
+#[cfg(feature = "client")]
+impl WorkflowV2 {
+    // The ids of the stages of `stage_type`, there may be several e.g. a review stage per
+    // branch of the workflow
+    fn assignment_stage_ids(&self, stage_type: &StageType) -> Result<Vec<StageId>> {
+        let stage_ids: Vec<StageId> = self
+            .stages_of_type(stage_type)
+            .into_iter()
+            .filter_map(|stage| stage.id.clone().map(StageId))
+            .collect();
+        if stage_ids.is_empty() {
+            bail!("Workflow has no {stage_type} stage");
+        }
+        Ok(stage_ids)
+    }
+
+    async fn assign_in_stages<C>(
+        &self,
+        client: &C,
+        stage_type: &StageType,
+        assignee_email: &str,
+        filters: &FilterAssignItemPayload,
+    ) -> Result<AssignItemResponse>
+    where
+        C: V7Methods + std::marker::Sync,
+    {
+        let workflow_id = WorkflowId(self.id.clone().context("Workflow is missing Id")?);
+        let mut created_commands = 0;
+        for stage_id in self.assignment_stage_ids(stage_type)? {
+            let payload =
+                AssignItemPayload::new(filters.clone(), assignee_email, workflow_id.clone())
+                    .with_stage(stage_id);
+            let response = WorkflowV2::assign_items(client, &payload).await?;
+            created_commands += response.created_commands.unwrap_or_default();
+        }
+        Ok(AssignItemResponse {
+            created_commands: Some(created_commands),
+        })
+    }
+
+    async fn move_item<C>(
+        &self,
+        client: &C,
+        item: &DatasetItemV2,
+        stage_id: StageId,
+    ) -> Result<SetStageResponse>
+    where
+        C: V7Methods + std::marker::Sync,
+    {
+        let payload = SetStagePayloadV2 {
+            filters: SetStageFilter {
+                dataset_ids: vec![DatasetId(
+                    item.dataset_id.context("Item is missing dataset id")?,
+                )],
+                item_ids: Some(vec![item.id.clone().context("Item is missing Id")?]),
+                ..Default::default()
+            },
+            stage_id,
+            workflow_id: WorkflowId(self.id.clone().context("Workflow is missing Id")?),
+        };
+        let response = client
+            .post(&format!("v2/teams/{}/items/stage", client.team()), &payload)
+            .await?;
+        expect_http_ok!(response, SetStageResponse)
+    }
+}
+
+#[cfg(feature = "client")]
 #[async_trait]
 impl<C> WorkflowMethods<C> for WorkflowV2
 where
@@ -235,6 +1462,64 @@ where
         expect_http_ok!(response, AssignItemResponse)
     }
 
+    async fn assign_for_annotation(
+        &self,
+        client: &C,
+        assignee_email: &str,
+        filters: &FilterAssignItemPayload,
+    ) -> Result<AssignItemResponse> {
+        self.assign_in_stages(client, &StageType::Annotate, assignee_email, filters)
+            .await
+    }
+
+    async fn assign_for_review(
+        &self,
+        client: &C,
+        stage_id: &StageId,
+        assignee_email: &str,
+        filters: &FilterAssignItemPayload,
+    ) -> Result<AssignItemResponse> {
+        if !self
+            .assignment_stage_ids(&StageType::Review)?
+            .contains(stage_id)
+        {
+            bail!("Stage {stage_id} is not a review stage of the workflow");
+        }
+        let workflow_id = WorkflowId(self.id.clone().context("Workflow is missing Id")?);
+        let payload = AssignItemPayload::new(filters.clone(), assignee_email, workflow_id)
+            .with_stage(stage_id.clone());
+        WorkflowV2::assign_items(client, &payload).await
+    }
+
+    async fn unassign(
+        &self,
+        client: &C,
+        stage_type: &StageType,
+        filters: &FilterAssignItemPayload,
+    ) -> Result<AssignItemResponse> {
+        let workflow_id = WorkflowId(self.id.clone().context("Workflow is missing Id")?);
+        let mut created_commands = 0;
+        for stage_id in self.assignment_stage_ids(stage_type)? {
+            let payload = UnassignItemPayload {
+                filters: filters.clone(),
+                stage_id,
+                workflow_id: workflow_id.clone(),
+            };
+            let response = client
+                .post(
+                    &format!("v2/teams/{}/items/unassign", client.team()),
+                    &payload,
+                )
+                .await?;
+            let response: Result<AssignItemResponse> =
+                expect_http_ok!(response, AssignItemResponse);
+            created_commands += response?.created_commands.unwrap_or_default();
+        }
+        Ok(AssignItemResponse {
+            created_commands: Some(created_commands),
+        })
+    }
+
     async fn get_workflows(client: &C) -> Result<Vec<WorkflowV2>> {
         let response = client
             .get(&format!("v2/teams/{}/workflows", client.team()))
@@ -242,6 +1527,17 @@ where
         expect_http_ok!(response, Vec<WorkflowV2>)
     }
 
+    async fn get_workflow(client: &C, workflow_id: &WorkflowId) -> Result<WorkflowV2> {
+        let response = client
+            .get(&format!(
+                "v2/teams/{}/workflows/{}",
+                client.team(),
+                workflow_id
+            ))
+            .await?;
+        expect_http_ok!(response, WorkflowV2)
+    }
+
     async fn update_workflow(
         &self,
         client: &C,
@@ -259,12 +1555,121 @@ where
             .await?;
         expect_http_ok!(response, WorkflowV2)
     }
+
+    async fn skip_item(&self, client: &C, item: &DatasetItemV2) -> Result<SetStageResponse> {
+        let stage = self.current_stage(item)?;
+        let stage_id = stage.id.clone().unwrap_or_default();
+        let transitions = stage.transitions();
+        if stage.is_readonly() {
+            bail!(StageTransitionError::Readonly(stage_id));
+        }
+        if !stage.is_skippable() {
+            bail!(StageTransitionError::NotSkippable(stage_id));
+        }
+        match transitions.next_stage_id {
+            Some(next_stage_id) => self.move_item(client, item, StageId(next_stage_id)).await,
+            None => bail!(StageTransitionError::NoNextStage(stage_id)),
+        }
+    }
+
+    async fn complete_item(&self, client: &C, item: &DatasetItemV2) -> Result<SetStageResponse> {
+        let stage = self.current_stage(item)?;
+        let stage_id = stage.id.clone().unwrap_or_default();
+        if stage.is_readonly() {
+            bail!(StageTransitionError::Readonly(stage_id));
+        }
+        match stage.transitions().next_stage_id {
+            Some(next_stage_id) => self.move_item(client, item, StageId(next_stage_id)).await,
+            None => bail!(StageTransitionError::NoNextStage(stage_id)),
+        }
+    }
+
+    async fn discard_item(
+        &self,
+        client: &C,
+        item: &DatasetItemV2,
+        reason: Option<&str>,
+    ) -> Result<SetStageResponse> {
+        let stage_id = self
+            .stages_of_type(&StageType::Discard)
+            .first()
+            .and_then(|stage| stage.id.clone().map(StageId))
+            .context("Workflow has no Discard stage")?;
+        let response = self.move_item(client, item, stage_id).await?;
+        if let Some(reason) = reason {
+            item.add_comment_thread(
+                client,
+                client.team().to_string(),
+                discard_comment(item, reason),
+            )
+            .await
+            .context("Item was discarded but its reason could not be added")?;
+        }
+        Ok(response)
+    }
+
+    async fn request_work_batch(
+        &self,
+        client: &C,
+        request: &WorkBatchRequest,
+    ) -> Result<WorkBatch> {
+        let endpoint = format!(
+            "v2/teams/{}/workflows/{}/work_batches",
+            client.team(),
+            self.id.as_ref().context("Workflow is missing Id")?
+        );
+        let response = client.post(&endpoint, request).await?;
+        expect_http_ok!(response, WorkBatch)
+    }
+
+    async fn list_work_batches(
+        &self,
+        client: &C,
+        user_id: Option<UserId>,
+    ) -> Result<Vec<WorkBatch>> {
+        let mut endpoint = format!(
+            "v2/teams/{}/workflows/{}/work_batches",
+            client.team(),
+            self.id.as_ref().context("Workflow is missing Id")?
+        );
+        if let Some(user_id) = user_id {
+            endpoint.push_str(&format!("?user_id={user_id}"));
+        }
+        let response = client.get(&endpoint).await?;
+        expect_http_ok!(response, Vec<WorkBatch>)
+    }
 }
 
 #[cfg(test)]
 mod test_serde {
     use super::*;
 
+    #[test]
+    fn test_workflow_template_deser() {
+        let template: WorkflowTemplate = serde_json::from_str(
+            r#"{
+                "id": 12,
+                "name": "Default",
+                "dataset_id": 3,
+                "workflow_stage_templates": [
+                    {"id": 100, "name": "Annotate", "stage_number": 1, "type": "annotate", "assignable_users": []},
+                    {"id": 101, "name": "Review", "stage_number": 2, "type": "review"}
+                ]
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            template.stage_by_name("Review").unwrap().id,
+            Some(WorkflowStageTemplateId(101))
+        );
+        assert_eq!(
+            template.stage_by_type(&StageType::Annotate).unwrap().id,
+            Some(WorkflowStageTemplateId(100))
+        );
+        assert!(template.stage_by_name("Complete").is_none());
+    }
+
     #[test]
     fn test_empty_ser_metadata() {
         let contents = "{}";
@@ -302,7 +1707,10 @@ mod test_serde {
 
         assert_eq!(meta.ready_for_completion, Some(true));
         assert_eq!(meta.previous_stage_number, Some(2));
-        assert_eq!(meta.review_status, Some("approved".to_string()));
+        assert_eq!(
+            meta.review_status,
+            Some(MaybeKnown::Known(ReviewStatus::Approved))
+        );
         assert_eq!(
             meta.review_status_modified_at,
             Some("2022-12-14T00:28:28.759303".to_string())
@@ -361,6 +1769,479 @@ mod test_serde {
         );
     }
 
+    #[test]
+    fn test_workflow_navigation() {
+        let edge = |name: EdgeName, target: &str| {
+            Some(StageEdge {
+                id: None,
+                name: Some(name.into()),
+                source_stage_id: None,
+                target_stage_id: Some(target.to_string()),
+            })
+        };
+        let stage = |id: &str, stage_type: StageType, edges: Vec<Option<StageEdge>>| {
+            Some(WorkflowStageV2 {
+                id: Some(id.to_string()),
+                name: Some(id.to_string()),
+                stage_type: Some(stage_type),
+                edges,
+                ..Default::default()
+            })
+        };
+        let mut workflow = WorkflowV2 {
+            stages: vec![
+                stage("complete", StageType::Complete, vec![]),
+                stage(
+                    "review",
+                    StageType::Review,
+                    vec![
+                        edge(EdgeName::Approve, "complete"),
+                        edge(EdgeName::Reject, "annotate"),
+                    ],
+                ),
+                stage(
+                    "annotate",
+                    StageType::Annotate,
+                    vec![edge(EdgeName::Default, "review")],
+                ),
+                stage(
+                    "dataset",
+                    StageType::Dataset,
+                    vec![edge(EdgeName::Default, "annotate")],
+                ),
+            ],
+            ..Default::default()
+        };
+
+        let id = |stage: Option<&WorkflowStageV2>| stage.and_then(|stage| stage.id.clone());
+        assert_eq!(id(workflow.initial_stage()), Some("dataset".to_string()));
+        let review = StageId::from("review");
+        assert_eq!(
+            id(workflow.next_stage(&review, &EdgeName::Approve.into())),
+            Some("complete".to_string())
+        );
+        assert_eq!(
+            id(workflow.next_stage(&review, &EdgeName::Reject.into())),
+            Some("annotate".to_string())
+        );
+        assert!(workflow
+            .next_stage(&review, &EdgeName::Default.into())
+            .is_none());
+        assert_eq!(workflow.stages_of_type(&StageType::Annotate).len(), 1);
+
+        assert_eq!(
+            workflow.find_cycle(&[]),
+            Some(vec![StageId::from("review"), StageId::from("annotate")])
+        );
+        assert_eq!(workflow.find_cycle(&[EdgeName::Reject.into()]), None);
+
+        workflow.stages[2].as_mut().unwrap().config = Some(StageConfig {
+            initial: Some(true),
+            ..Default::default()
+        });
+        assert_eq!(id(workflow.initial_stage()), Some("annotate".to_string()));
+    }
+
+    #[test]
+    fn test_validate_workflow() {
+        let stage = |name: &str, stage_type: StageType, config: StageConfig| WorkflowStageV2 {
+            name: Some(name.to_string()),
+            stage_type: Some(stage_type),
+            config: Some(config),
+            ..Default::default()
+        };
+        let mut workflow = WorkflowBuilder {
+            name: Some("Workflow".to_string()),
+            stages: vec![
+                stage(
+                    "Dataset",
+                    StageType::Dataset,
+                    StageConfig {
+                        dataset_id: Some(1),
+                        ..Default::default()
+                    },
+                ),
+                WorkflowStageV2 {
+                    assignable_users: vec![Some(WorkflowStageAssignees {
+                        stage_id: None,
+                        user_id: Some(10),
+                        sampling_rate: None,
+                    })],
+                    ..stage("Annotate", StageType::Annotate, Default::default())
+                },
+                stage(
+                    "Model",
+                    StageType::Model,
+                    StageConfig {
+                        model_id: Some("model-1".to_string()),
+                        ..Default::default()
+                    },
+                ),
+                stage(
+                    "Consensus",
+                    StageType::Consensus,
+                    StageConfig {
+                        threshold: Some("0.8".to_string()),
+                        iou_thresholds: Some(r#"{"general": 0.5, "polygon": 0.6}"#.to_string()),
+                        ..Default::default()
+                    },
+                ),
+            ],
+        };
+        let capabilities = TeamCapabilities {
+            dataset_ids: HashSet::from([DatasetId(1)]),
+            user_ids: HashSet::from([UserId(10)]),
+            model_ids: None,
+        }
+        .with_model_ids(["model-1".to_string()]);
+        assert_eq!(workflow.validate(&capabilities), Ok(()));
+
+        workflow.stages[0].config.as_mut().unwrap().dataset_id = Some(2);
+        workflow.stages[1].assignable_users[0]
+            .as_mut()
+            .unwrap()
+            .user_id = Some(11);
+        workflow.stages[2].config.as_mut().unwrap().model_id = Some("model-2".to_string());
+        workflow.stages[3].config.as_mut().unwrap().iou_thresholds =
+            Some(r#"{"general": 1.5}"#.to_string());
+        let error = workflow.validate(&capabilities).unwrap_err();
+        assert_eq!(
+            error.problems,
+            vec![
+                WorkflowProblem::UnknownDataset {
+                    stage: "Dataset".to_string(),
+                    dataset_id: DatasetId(2)
+                },
+                WorkflowProblem::UnknownAssignee {
+                    stage: "Annotate".to_string(),
+                    user_id: UserId(11)
+                },
+                WorkflowProblem::UnknownModel {
+                    stage: "Model".to_string(),
+                    model_id: "model-2".to_string()
+                },
+                WorkflowProblem::InvalidThreshold {
+                    stage: "Consensus".to_string(),
+                    threshold: r#"{"general": 1.5}"#.to_string()
+                },
+            ]
+        );
+        assert!(error.to_string().contains("assignee 11 is not a member"));
+
+        // Models are not checked without a list of models
+        let capabilities = TeamCapabilities {
+            model_ids: None,
+            ..capabilities
+        };
+        workflow.stages[2].config.as_mut().unwrap().model_id = None;
+        let error = workflow.validate(&capabilities).unwrap_err();
+        assert!(error.problems.contains(&WorkflowProblem::MissingModel {
+            stage: "Model".to_string()
+        }));
+    }
+
+    #[test]
+    fn test_workflow_builder_matches() {
+        let builder = WorkflowBuilder {
+            name: Some("Workflow".to_string()),
+            stages: vec![WorkflowStageV2 {
+                name: Some("Annotate".to_string()),
+                stage_type: Some(StageType::Annotate),
+                ..Default::default()
+            }],
+        };
+        let mut existing = WorkflowV2 {
+            id: Some("workflow-1".to_string()),
+            name: Some("Workflow".to_string()),
+            stages: vec![Some(WorkflowStageV2 {
+                id: Some("stage-1".to_string()),
+                name: Some("Annotate".to_string()),
+                stage_type: Some(StageType::Annotate),
+                config: Some(StageConfig {
+                    parallel_stage_ids: Some("stage-2".to_string()),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            })],
+            ..Default::default()
+        };
+        // Ids and defaults filled in by V7 are ignored
+        assert!(builder.matches(&existing));
+
+        existing.stages[0].as_mut().unwrap().stage_type = Some(StageType::Review);
+        assert!(!builder.matches(&existing));
+        existing.stages[0].as_mut().unwrap().stage_type = Some(StageType::Annotate);
+        existing.name = Some("Other".to_string());
+        assert!(!builder.matches(&existing));
+        existing.name = Some("Workflow".to_string());
+        existing.stages.push(Some(WorkflowStageV2::default()));
+        assert!(!builder.matches(&existing));
+    }
+
+    #[test]
+    fn test_review_sampling() {
+        let mut workflow = WorkflowBuilder {
+            name: Some("Workflow".to_string()),
+            stages: vec![
+                WorkflowStageV2 {
+                    id: Some("annotate".to_string()),
+                    name: Some("Annotate".to_string()),
+                    stage_type: Some(StageType::Annotate),
+                    ..Default::default()
+                },
+                WorkflowStageV2 {
+                    id: Some("review".to_string()),
+                    name: Some("Review".to_string()),
+                    stage_type: Some(StageType::Review),
+                    ..Default::default()
+                },
+            ],
+        };
+        let tenth = SamplingRate::from_percentage(10).unwrap();
+        workflow
+            .set_review_sampling("Review", 1, Some(tenth))
+            .unwrap();
+        workflow
+            .set_review_sampling("review", 1, Some(SamplingRate::ALL))
+            .unwrap();
+        workflow.set_review_sampling("Review", 2, None).unwrap();
+        assert_eq!(
+            serde_json::to_value(&workflow.stages[1].assignable_users).unwrap(),
+            serde_json::json!([
+                {"stage_id": "review", "user_id": 1, "sampling_rate": 1.0},
+                {"stage_id": "review", "user_id": 2}
+            ])
+        );
+        assert!(workflow.set_review_sampling("Annotate", 1, None).is_err());
+        assert!(workflow.set_review_sampling("Complete", 1, None).is_err());
+
+        let assignees = |rate: f64| {
+            vec![TemplateAssignee {
+                assignee_id: Some(3),
+                sampling_rate: Some(rate),
+            }]
+        };
+        assert!(workflow
+            .set_review_assignees("Review", &assignees(1.5))
+            .is_err());
+        assert_eq!(workflow.stages[1].assignable_users.len(), 2);
+        workflow
+            .set_review_assignees("Review", &assignees(0.25))
+            .unwrap();
+        let assignee = workflow.stages[1].assignable_users[0].clone().unwrap();
+        assert_eq!(assignee.user_id, Some(3));
+        assert_eq!(assignee.sampling_rate.map(|rate| rate.value()), Some(0.25));
+
+        let capabilities = TeamCapabilities {
+            user_ids: HashSet::from([UserId(3)]),
+            ..Default::default()
+        };
+        assert_eq!(workflow.validate(&capabilities), Ok(()));
+        workflow.stages[0].assignable_users = vec![Some(WorkflowStageAssignees {
+            user_id: Some(3),
+            sampling_rate: Some(tenth),
+            ..Default::default()
+        })];
+        assert_eq!(
+            workflow.validate(&capabilities).unwrap_err().problems,
+            vec![WorkflowProblem::UnexpectedSamplingRate {
+                stage: "Annotate".to_string(),
+                user_id: Some(UserId(3))
+            }]
+        );
+        assert!(serde_json::from_str::<SamplingRate>("-0.1").is_err());
+    }
+
+    #[test]
+    fn test_summarize_stage_times() {
+        let now = parse_timestamp("2023-08-10T00:00:00Z").unwrap();
+        let review = |item: &str, entered_at: &str| ItemStageTime {
+            item_id: Some(item.to_string()),
+            stage_id: Some("review-stage".to_string()),
+            stage_type: Some(StageType::Review),
+            entered_at: Some(entered_at.to_string()),
+            ..Default::default()
+        };
+        let times = vec![
+            review("a", "2023-08-01T00:00:00Z"),
+            review("b", "2023-08-09T00:00:00Z"),
+            ItemStageTime {
+                item_id: Some("c".to_string()),
+                stage_id: Some("annotate-stage".to_string()),
+                stage_type: Some(StageType::Annotate),
+                entered_at: None,
+                ..Default::default()
+            },
+        ];
+
+        let summaries = summarize_stage_times(&times, now);
+        assert_eq!(summaries.len(), 2);
+        assert_eq!(summaries[0].stage_id, Some("annotate-stage".to_string()));
+        assert_eq!(summaries[0].item_count, 1);
+        assert_eq!(summaries[0].max_seconds_in_stage, None);
+        assert_eq!(summaries[1].item_count, 2);
+        assert_eq!(summaries[1].max_seconds_in_stage, Some(9 * 86400));
+        assert_eq!(summaries[1].mean_seconds_in_stage, Some(5.0 * 86400.0));
+
+        let stuck = items_stuck_in_stage(&times, &StageType::Review, 7 * 86400, now);
+        assert_eq!(stuck.len(), 1);
+        assert_eq!(stuck[0].item_id, Some("a".to_string()));
+    }
+
+    #[test]
+    fn test_count_items_by_stage() {
+        let workflow: WorkflowV2 = serde_json::from_value(serde_json::json!({
+            "stages": [
+                {"id": "annotate-stage", "name": "Annotate", "type": "annotate", "assignable_users": [], "edges": []},
+                {"id": "review-stage", "name": "Review", "type": "review", "assignable_users": [], "edges": []},
+                {"id": "complete-stage", "name": "Complete", "type": "complete", "assignable_users": [], "edges": []}
+            ],
+            "thumbnails": []
+        }))
+        .unwrap();
+        let item = |name: &str, path: &str, stage_id: Option<&str>| -> DatasetItemV2 {
+            serde_json::from_value(serde_json::json!({
+                "name": name,
+                "path": path,
+                "workflow_data": stage_id.map(|stage_id| serde_json::json!({
+                    "current_stage_instances": [{"stage_id": stage_id}]
+                }))
+            }))
+            .unwrap()
+        };
+        let items = vec![
+            item("a-1.png", "/scans/2024", Some("review-stage")),
+            item("a-2.png", "/scans/2024/batch", Some("review-stage")),
+            item("b-1.png", "/scans/2023", Some("annotate-stage")),
+            item("a-3.png", "/scans-old", Some("removed-stage")),
+            item("a-4.png", "/", None),
+        ];
+
+        let counts = count_items_by_stage(&workflow, &items, &StageCountFilter::default());
+        assert_eq!(counts.total(), 5);
+        assert_eq!(counts.items_without_stage, 1);
+        assert_eq!(counts.stages.len(), 4);
+        assert_eq!(
+            counts
+                .stage(&StageId::from("review-stage"))
+                .unwrap()
+                .item_count,
+            2
+        );
+        assert_eq!(
+            counts
+                .stage(&StageId::from("complete-stage"))
+                .unwrap()
+                .item_count,
+            0
+        );
+        assert_eq!(counts.stages[2].stage_name.as_deref(), Some("Complete"));
+        assert_eq!(counts.stages[3].stage_id.as_deref(), Some("removed-stage"));
+
+        let filter = StageCountFilter {
+            folder: Some("/scans/".to_string()),
+            name_prefix: Some("a-".to_string()),
+        };
+        let counts = count_items_by_stage(&workflow, &items, &filter);
+        assert_eq!(counts.total(), 2);
+        assert_eq!(
+            counts
+                .stage(&StageId::from("review-stage"))
+                .unwrap()
+                .item_count,
+            2
+        );
+        assert_eq!(
+            counts
+                .stage(&StageId::from("annotate-stage"))
+                .unwrap()
+                .item_count,
+            0
+        );
+        assert_eq!(counts.stage(&StageId::from("removed-stage")), None);
+    }
+
+    #[test]
+    fn test_stage_transitions() {
+        let history: Vec<ItemHistoryEvent> = serde_json::from_str(
+            r#"[
+                {"id": "e3", "type": "stage_changed", "actor_id": 7,
+                 "from_stage_id": "review", "to_stage_id": "complete",
+                 "created_at": "2023-08-02T10:00:00Z"},
+                {"id": "e2", "type": "assigned", "actor_id": 1, "assignee_id": 7,
+                 "created_at": "2023-08-01T12:00:00Z"},
+                {"id": "e1", "type": "stage_changed", "actor_id": 5,
+                 "from_stage_id": "annotate", "to_stage_id": "review",
+                 "created_at": "2023-08-01T09:00:00Z", "comment": "looks good"},
+                {"id": "e0", "type": "frobnicated", "created_at": "2023-07-31T00:00:00Z"}
+            ]"#,
+        )
+        .unwrap();
+        assert_eq!(
+            history[1].event_type,
+            Some(MaybeKnown::Known(ItemEventType::Assigned))
+        );
+        assert_eq!(history[2].other["comment"], "looks good");
+        assert_eq!(
+            history[3].event_type,
+            Some(MaybeKnown::Unknown("frobnicated".to_string()))
+        );
+
+        let workflow = WorkflowV2 {
+            stages: vec![Some(WorkflowStageV2 {
+                id: Some("review".to_string()),
+                name: Some("Pathologist Review".to_string()),
+                ..Default::default()
+            })],
+            ..Default::default()
+        };
+        let transitions =
+            stage_transitions(&ItemId::from("item-1"), &history, Some(&workflow)).unwrap();
+        assert_eq!(
+            transitions,
+            vec![
+                StageTransitionRecord {
+                    item_id: ItemId::from("item-1"),
+                    from_stage: Some("annotate".to_string()),
+                    to_stage: Some("Pathologist Review".to_string()),
+                    actor_id: Some(5),
+                    timestamp: parse_timestamp("2023-08-01T09:00:00Z").unwrap(),
+                },
+                StageTransitionRecord {
+                    item_id: ItemId::from("item-1"),
+                    from_stage: Some("Pathologist Review".to_string()),
+                    to_stage: Some("complete".to_string()),
+                    actor_id: Some(7),
+                    timestamp: parse_timestamp("2023-08-02T10:00:00Z").unwrap(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_unknown_stage_values() {
+        let edge: StageEdge = serde_json::from_str(
+            r#"{"name": "consensus_reached", "source_stage_id": null, "target_stage_id": null}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            edge.name,
+            Some(MaybeKnown::Unknown("consensus_reached".to_string()))
+        );
+        assert_eq!(
+            serde_json::to_value(&edge).unwrap()["name"],
+            "consensus_reached"
+        );
+        assert_eq!(
+            serde_json::to_string(&AssignableTo::Manual).unwrap(),
+            r#""manual""#
+        );
+        assert_eq!(
+            MaybeKnown::<ModelType>::parse("external"),
+            MaybeKnown::Known(ModelType::External)
+        );
+    }
+
     #[test]
     fn test_ser_stage() {
         let contents = r#"
@@ -415,6 +2296,13 @@ mod test_serde {
         let stage: WorkflowStageV2 = serde_json::from_str(contents).unwrap();
 
         assert_eq!(stage.stage_type, Some(StageType::Review));
+        let config = stage.config.clone().expect("Missing config");
+        assert_eq!(config.assignable_to, Some(AssignableTo::Anyone.into()));
+        assert_eq!(config.model_type, Some(ModelType::Gust.into()));
+        assert_eq!(
+            stage.edges[0].clone().expect("Missing edge").name,
+            Some(EdgeName::Reject.into())
+        );
         assert_eq!(
             stage.id,
             Some("9bba4506-694d-4dd3-afd8-ab354c5a21ba".to_string())
@@ -431,3 +2319,364 @@ mod test_serde {
         )
     }
 }
+
+#[cfg(all(test, feature = "client"))]
+mod test_client_calls {
+    use super::*;
+    use crate::client::V7Client;
+    use crate::item::DatasetItemMethods;
+    use serde_json::json;
+    use wiremock::matchers::{body_json, body_partial_json, method, path, query_param};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    fn stage(id: &str, skippable: bool, readonly: bool, next: Option<&str>) -> WorkflowStageV2 {
+        WorkflowStageV2 {
+            id: Some(id.to_string()),
+            config: Some(StageConfig {
+                skippable: Some(skippable),
+                readonly: Some(readonly),
+                ..Default::default()
+            }),
+            edges: next
+                .map(|next| {
+                    vec![Some(StageEdge {
+                        name: Some(EdgeName::Default.into()),
+                        source_stage_id: Some(id.to_string()),
+                        target_stage_id: Some(next.to_string()),
+                        ..Default::default()
+                    })]
+                })
+                .unwrap_or_default(),
+            ..Default::default()
+        }
+    }
+
+    fn item_in_stage(stage_id: &str) -> DatasetItemV2 {
+        DatasetItemV2 {
+            id: Some("item-1".to_string()),
+            dataset_id: Some(3),
+            workflow_data: Some(ItemWorkflowData {
+                workflow_id: Some("workflow-1".to_string()),
+                current_stage_instances: vec![Some(StageInstance {
+                    stage_id: Some(stage_id.to_string()),
+                    ..Default::default()
+                })],
+            }),
+            ..Default::default()
+        }
+    }
+
+    fn workflow() -> WorkflowV2 {
+        WorkflowV2 {
+            id: Some("workflow-1".to_string()),
+            stages: vec![
+                Some(stage("annotate", false, false, Some("review"))),
+                Some(stage("review", true, false, Some("complete"))),
+                Some(stage("locked", true, true, Some("complete"))),
+                Some(stage("complete", false, false, None)),
+            ],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_item_transitions() {
+        let workflow = workflow();
+
+        let transitions = workflow
+            .item_transitions(&item_in_stage("annotate"))
+            .unwrap();
+        assert!(transitions.can_complete);
+        assert!(!transitions.can_skip);
+        assert_eq!(transitions.next_stage_id, Some("review".to_string()));
+
+        let transitions = workflow.item_transitions(&item_in_stage("locked")).unwrap();
+        assert!(!transitions.can_complete);
+        assert!(!transitions.can_skip);
+
+        let transitions = workflow
+            .item_transitions(&item_in_stage("complete"))
+            .unwrap();
+        assert!(!transitions.can_complete);
+        assert_eq!(transitions.next_stage_id, None);
+
+        workflow
+            .item_transitions(&DatasetItemV2::default())
+            .expect_err("Item is missing workflow data");
+    }
+
+    #[tokio::test]
+    async fn test_skip_and_complete_item() {
+        let mock_server = MockServer::start().await;
+        let workflow = workflow();
+
+        Mock::given(method("POST"))
+            .and(path("/v2/teams/some-team/items/stage"))
+            .and(body_json(json!({
+                "filters": {"dataset_ids": [3], "select_all": false, "item_ids": ["item-1"]},
+                "stage_id": "complete",
+                "workflow_id": "workflow-1"
+            })))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({"created_commands": 1})))
+            .expect(2)
+            .mount(&mock_server)
+            .await;
+
+        let client: V7Client = V7Client::new(
+            format!("{}/", mock_server.uri()),
+            "api-key".to_string(),
+            "some-team".to_string(),
+        )
+        .expect("Failed to get V7Client");
+
+        workflow
+            .skip_item(&client, &item_in_stage("review"))
+            .await
+            .expect("Failed to skip item");
+        workflow
+            .complete_item(&client, &item_in_stage("review"))
+            .await
+            .expect("Failed to complete item");
+
+        let error = workflow
+            .skip_item(&client, &item_in_stage("annotate"))
+            .await
+            .unwrap_err();
+        assert_eq!(
+            error.downcast_ref::<StageTransitionError>(),
+            Some(&StageTransitionError::NotSkippable("annotate".to_string()))
+        );
+        let error = workflow
+            .complete_item(&client, &item_in_stage("locked"))
+            .await
+            .unwrap_err();
+        assert_eq!(
+            error.downcast_ref::<StageTransitionError>(),
+            Some(&StageTransitionError::Readonly("locked".to_string()))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_discard_item() {
+        let mock_server = MockServer::start().await;
+        let mut with_discard = workflow();
+        with_discard.stages.push(Some(WorkflowStageV2 {
+            id: Some("discard".to_string()),
+            stage_type: Some(StageType::Discard),
+            ..Default::default()
+        }));
+
+        Mock::given(method("GET"))
+            .and(path("/v2/teams/some-team/workflows/workflow-1"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(&with_discard))
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/v2/teams/some-team/items/item-1/comment_threads"))
+            .and(body_json(json!({
+                "bounding_box": {"x": 0.0, "y": 0.0, "w": 1.0, "h": 1.0},
+                "comments": [{"body": "Discarded: Out of focus"}],
+                "slot_name": "0"
+            })))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({"id": "thread-1"})))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/v2/teams/some-team/items/stage"))
+            .and(body_json(json!({
+                "filters": {"dataset_ids": [3], "select_all": false, "item_ids": ["item-1"]},
+                "stage_id": "discard",
+                "workflow_id": "workflow-1"
+            })))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({"created_commands": 1})))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let client: V7Client = V7Client::new(
+            format!("{}/", mock_server.uri()),
+            "api-key".to_string(),
+            "some-team".to_string(),
+        )
+        .expect("Failed to get V7Client");
+
+        let response = item_in_stage("review")
+            .discard(&client, Some("Out of focus"))
+            .await
+            .expect("Failed to discard item");
+        assert_eq!(response.created_commands, Some(1));
+
+        // No reason is left on an item which could not be moved
+        let mut item = item_in_stage("review");
+        item.id = Some("item-2".to_string());
+        Mock::given(method("POST"))
+            .and(path("/v2/teams/some-team/items/stage"))
+            .and(body_partial_json(
+                json!({"filters": {"item_ids": ["item-2"]}}),
+            ))
+            .respond_with(ResponseTemplate::new(500))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/v2/teams/some-team/items/item-2/comment_threads"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({"id": "thread-2"})))
+            .expect(0)
+            .mount(&mock_server)
+            .await;
+        with_discard
+            .discard_item(&client, &item, Some("Out of focus"))
+            .await
+            .unwrap_err();
+
+        let error = workflow()
+            .discard_item(&client, &item_in_stage("review"), None)
+            .await
+            .unwrap_err();
+        assert_eq!(error.to_string(), "Workflow has no Discard stage");
+    }
+
+    #[tokio::test]
+    async fn test_work_batches() {
+        let mock_server = MockServer::start().await;
+        let workflow = workflow();
+        let batch = json!({
+            "id": "batch-1",
+            "workflow_id": "workflow-1",
+            "stage_id": "annotate",
+            "user_id": 7,
+            "item_ids": [],
+            "status": "pending",
+            "inserted_at": "2024-03-01T10:00:00Z"
+        });
+
+        Mock::given(method("POST"))
+            .and(path(
+                "/v2/teams/some-team/workflows/workflow-1/work_batches",
+            ))
+            .and(body_json(json!({"user_id": 7, "batch_size": 20})))
+            .respond_with(ResponseTemplate::new(200).set_body_json(&batch))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path(
+                "/v2/teams/some-team/workflows/workflow-1/work_batches",
+            ))
+            .and(query_param("user_id", "7"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!([batch])))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let client: V7Client = V7Client::new(
+            format!("{}/", mock_server.uri()),
+            "api-key".to_string(),
+            "some-team".to_string(),
+        )
+        .expect("Failed to get V7Client");
+
+        let request = WorkBatchRequest {
+            user_id: UserId(7),
+            batch_size: Some(20),
+            ..Default::default()
+        };
+        let requested = workflow
+            .request_work_batch(&client, &request)
+            .await
+            .expect("Failed to request work batch");
+        assert_eq!(requested.status.as_deref(), Some("pending"));
+
+        let batches = workflow
+            .list_work_batches(&client, Some(UserId(7)))
+            .await
+            .expect("Failed to list work batches");
+        assert_eq!(batches, vec![requested]);
+    }
+
+    #[tokio::test]
+    async fn test_stage_assignment() {
+        let mock_server = MockServer::start().await;
+        let mut workflow = workflow();
+        for (stage, stage_type) in workflow.stages.iter_mut().flatten().zip([
+            StageType::Annotate,
+            StageType::Review,
+            StageType::Review,
+            StageType::Complete,
+        ]) {
+            stage.stage_type = Some(stage_type);
+        }
+        let filters = FilterAssignItemPayload {
+            dataset_ids: vec![DatasetId(3)],
+            item_ids: Some(vec!["item-1".to_string()]),
+            ..Default::default()
+        };
+
+        Mock::given(method("POST"))
+            .and(path("/v2/teams/some-team/items/assign"))
+            .and(body_json(json!({
+                "filters": {"dataset_ids": [3], "item_ids": ["item-1"], "select_all": false},
+                "assignee_email": "reviewer@example.com",
+                "workflow_id": "workflow-1",
+                "stage_id": "locked"
+            })))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({"created_commands": 1})))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/v2/teams/some-team/items/unassign"))
+            .and(body_json(json!({
+                "filters": {"dataset_ids": [3], "item_ids": ["item-1"], "select_all": false},
+                "stage_id": "annotate",
+                "workflow_id": "workflow-1"
+            })))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({"created_commands": 1})))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let client: V7Client = V7Client::new(
+            format!("{}/", mock_server.uri()),
+            "api-key".to_string(),
+            "some-team".to_string(),
+        )
+        .expect("Failed to get V7Client");
+
+        let response = workflow
+            .assign_for_review(
+                &client,
+                &StageId("locked".to_string()),
+                "reviewer@example.com",
+                &filters,
+            )
+            .await
+            .expect("Failed to assign for review");
+        assert_eq!(response.created_commands, Some(1));
+        let error = workflow
+            .assign_for_review(
+                &client,
+                &StageId("annotate".to_string()),
+                "reviewer@example.com",
+                &filters,
+            )
+            .await
+            .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Stage annotate is not a review stage of the workflow"
+        );
+        let response = workflow
+            .unassign(&client, &StageType::Annotate, &filters)
+            .await
+            .expect("Failed to unassign");
+        assert_eq!(response.created_commands, Some(1));
+
+        let error = workflow
+            .unassign(&client, &StageType::Consensus, &filters)
+            .await
+            .unwrap_err();
+        assert_eq!(error.to_string(), "Workflow has no Consensus stage");
+    }
+}